@@ -0,0 +1,75 @@
+//! Benchmarks of serializing and deserializing JIT artifacts.
+//!
+//! The baseline is format version 1 of the artifacts, which were decoded
+//! whole with `bincode`. This benchmark only uses the public API, so it
+//! runs unchanged on a tree from before format version 2 to compare
+//! with it.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use wasmer::*;
+use wasmer_engine_jit::JIT;
+
+/// The number of functions in the benchmarked module.
+const NUM_FUNCTIONS: usize = 2000;
+
+/// A module with many functions, each calling the previous one.
+fn many_functions_wat() -> String {
+    let mut wat = String::from("(module\n    (func $f0 (param i32) (result i32) (local.get 0))\n");
+    for i in 1..NUM_FUNCTIONS {
+        wat.push_str(&format!(
+            "    (func $f{} (export \"f{}\") (param i32) (result i32)
+       (i32.add (call $f{} (local.get 0)) (i32.const {})))\n",
+            i,
+            i,
+            i - 1,
+            i
+        ));
+    }
+    wat.push(')');
+    wat
+}
+
+pub fn run_deserialize(store: &Store, compiler_name: &str, c: &mut Criterion) {
+    let module = Module::new(&store, many_functions_wat()).unwrap();
+    let serialized = module.serialize().unwrap();
+    let temp_dir = tempfile::tempdir().unwrap();
+    let path = temp_dir.path().join("module.wjit");
+    module.serialize_to_file(&path).unwrap();
+
+    c.bench_function(&format!("serialize {}", compiler_name), |b| {
+        b.iter(|| black_box(module.serialize().unwrap()))
+    });
+
+    c.bench_function(&format!("deserialize {}", compiler_name), |b| {
+        b.iter(|| black_box(unsafe { Module::deserialize(&store, &serialized).unwrap() }))
+    });
+
+    c.bench_function(&format!("deserialize from file {}", compiler_name), |b| {
+        b.iter(|| black_box(unsafe { Module::deserialize_from_file(&store, &path).unwrap() }))
+    });
+}
+
+fn run_deserialize_benchmarks(c: &mut Criterion) {
+    #[cfg(feature = "llvm")]
+    {
+        let store = Store::new(&JIT::new(wasmer_compiler_llvm::LLVM::new()).engine());
+        run_deserialize(&store, "llvm", c);
+    }
+
+    #[cfg(feature = "cranelift")]
+    {
+        let store = Store::new(&JIT::new(wasmer_compiler_cranelift::Cranelift::new()).engine());
+        run_deserialize(&store, "cranelift", c);
+    }
+
+    #[cfg(feature = "singlepass")]
+    {
+        let store = Store::new(&JIT::new(wasmer_compiler_singlepass::Singlepass::new()).engine());
+        run_deserialize(&store, "singlepass", c);
+    }
+}
+
+criterion_group!(benches, run_deserialize_benchmarks);
+
+criterion_main!(benches);
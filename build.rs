@@ -45,11 +45,23 @@ fn main() -> anyhow::Result<()> {
                     wast_processor,
                 )?;
                 test_directory_module(spectests, "tests/wast/spec/proposals/simd", wast_processor)?;
+                test_directory_module(
+                    spectests,
+                    "tests/wast/spec/proposals/threads",
+                    wast_processor,
+                )?;
+                test_directory_module(
+                    spectests,
+                    "tests/wast/spec/proposals/tail-call",
+                    wast_processor,
+                )?;
                 // test_directory_module(spectests, "tests/wast/spec/proposals/bulk-memory-operations", wast_processor)?;
                 Ok(())
             })?;
             with_test_module(&mut spectests, "wasmer", |spectests| {
                 let _spec_tests = test_directory(spectests, "tests/wast/wasmer", wast_processor)?;
+                test_directory_module(spectests, "tests/wast/wasmer/memory64", wast_processor)?;
+                test_directory_module(spectests, "tests/wast/wasmer/multi-memory", wast_processor)?;
                 Ok(())
             })?;
             Ok(())
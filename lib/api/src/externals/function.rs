@@ -12,6 +12,8 @@ pub use inner::{UnsafeMutableEnv, WithUnsafeMutableEnv};
 
 use std::cmp::max;
 use std::fmt;
+#[cfg(feature = "async")]
+use std::future::Future;
 use std::sync::Arc;
 use wasmer_engine::{Export, ExportFunction, ExportFunctionMetadata};
 use wasmer_vm::{
@@ -296,6 +298,76 @@ impl Function {
         }
     }
 
+    /// Creates a new asynchronous host `Function` (dynamic) with the
+    /// provided signature.
+    ///
+    /// The future returned by `func` is polled by the executor driving
+    /// [`Function::call_async`]. While it is pending, the guest stays
+    /// suspended on its own stack and the thread is free to run other
+    /// tasks. Calling this function through [`Function::call`] or a
+    /// [`NativeFunc`] results in a [`RuntimeError`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmer::{Function, FunctionType, Type, Store, Value};
+    /// # let store = Store::default();
+    /// #
+    /// let signature = FunctionType::new(vec![Type::I32, Type::I32], vec![Type::I32]);
+    ///
+    /// let f = Function::new_async(&store, &signature, |args| {
+    ///     let sum = args[0].unwrap_i32() + args[1].unwrap_i32();
+    ///     async move { Ok(vec![Value::I32(sum)]) }
+    /// });
+    /// ```
+    #[cfg(feature = "async")]
+    pub fn new_async<FT, F, Fut>(store: &Store, ty: FT, func: F) -> Self
+    where
+        FT: Into<FunctionType>,
+        F: Fn(&[Val]) -> Fut + 'static + Send + Sync,
+        Fut: Future<Output = Result<Vec<Val>, RuntimeError>>,
+    {
+        Self::new(store, ty, move |args| crate::fiber::block_on(func(args))?)
+    }
+
+    /// Creates a new asynchronous host `Function` (dynamic) with the
+    /// provided signature and environment.
+    ///
+    /// See [`Function::new_async`] to learn how the returned future is
+    /// driven.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmer::{Function, FunctionType, Type, Store, Value, WasmerEnv};
+    /// # let store = Store::default();
+    /// #
+    /// #[derive(WasmerEnv, Clone)]
+    /// struct Env {
+    ///   multiplier: i32,
+    /// };
+    /// let env = Env { multiplier: 2 };
+    ///
+    /// let signature = FunctionType::new(vec![Type::I32, Type::I32], vec![Type::I32]);
+    ///
+    /// let f = Function::new_async_with_env(&store, &signature, env, |env, args| {
+    ///     let result = env.multiplier * (args[0].unwrap_i32() + args[1].unwrap_i32());
+    ///     async move { Ok(vec![Value::I32(result)]) }
+    /// });
+    /// ```
+    #[cfg(feature = "async")]
+    pub fn new_async_with_env<FT, F, Fut, Env>(store: &Store, ty: FT, env: Env, func: F) -> Self
+    where
+        FT: Into<FunctionType>,
+        F: Fn(&Env, &[Val]) -> Fut + 'static + Send + Sync,
+        Fut: Future<Output = Result<Vec<Val>, RuntimeError>>,
+        Env: Sized + WasmerEnv + 'static,
+    {
+        Self::new_with_env(store, ty, env, move |env, args| {
+            crate::fiber::block_on(func(env, args))?
+        })
+    }
+
     /// Creates a new host `Function` from a native function.
     ///
     /// The function signature is automatically retrieved using the
@@ -530,6 +602,7 @@ impl Function {
         }
 
         // Call the trampoline.
+        let _stack_limit = self.store.interrupts().enter_wasm();
         if let Err(error) = unsafe {
             wasmer_call_trampoline(
                 self.exported.vm_function.vmctx,
@@ -634,6 +707,52 @@ impl Function {
         Ok(results.into_boxed_slice())
     }
 
+    /// Call the `Function` function asynchronously.
+    ///
+    /// The call runs on a separate stack, which is suspended every time
+    /// an asynchronous host function (see [`Function::new_async`]) is
+    /// waiting on its future. Dropping the returned future before it
+    /// completes unwinds the guest as if the pending host function had
+    /// trapped.
+    ///
+    /// The returned future is not `Send`; with a multi-threaded executor,
+    /// run it on a task that stays on one thread (for example with
+    /// `tokio::task::LocalSet`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmer::{imports, wat2wasm, Function, Instance, Module, Store, Type, Value};
+    /// # let store = Store::default();
+    /// # let wasm_bytes = wat2wasm(r#"
+    /// # (module
+    /// #   (func (export "sum") (param $x i32) (param $y i32) (result i32)
+    /// #     local.get $x
+    /// #     local.get $y
+    /// #     i32.add
+    /// #   ))
+    /// # "#.as_bytes()).unwrap();
+    /// # let module = Module::new(&store, wasm_bytes).unwrap();
+    /// # let import_object = imports! {};
+    /// # let instance = Instance::new(&module, &import_object).unwrap();
+    /// #
+    /// let sum = instance.exports.get_function("sum").unwrap();
+    /// let result = futures::executor::block_on(sum.call_async(&[Value::I32(1), Value::I32(2)]));
+    ///
+    /// assert_eq!(result.unwrap().to_vec(), vec![Value::I32(3)]);
+    /// ```
+    #[cfg(feature = "async")]
+    pub fn call_async(
+        &self,
+        params: &[Val],
+    ) -> impl Future<Output = Result<Box<[Val]>, RuntimeError>> + 'static {
+        let function = self.clone();
+        let params = params.to_vec();
+        let interrupts = self.store.interrupts().clone();
+
+        async move { crate::fiber::Fiber::new(interrupts, move || function.call(&params))?.await }
+    }
+
     pub(crate) fn from_vm_export(store: &Store, wasmer_export: ExportFunction) -> Self {
         if let Some(trampoline) = wasmer_export.vm_function.call_trampoline {
             Self {
@@ -0,0 +1,191 @@
+//! Support for running WebAssembly on a separate stack, so that
+//! asynchronous host functions can suspend the guest while their
+//! future is pending.
+//!
+//! [`Function::call_async`] runs the call on a [`Fiber`], which is a
+//! `Future` driving a coroutine. Asynchronous host functions use
+//! [`block_on`] to poll their own future with the `Context` of the
+//! `Fiber` and, when the future is not ready, they switch back to the
+//! caller's stack instead of blocking the thread.
+//!
+//! [`Function::call_async`]: crate::Function::call_async
+
+use crate::RuntimeError;
+use corosensei::stack::DefaultStack;
+use corosensei::{Coroutine, CoroutineResult, Yielder};
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::Pin;
+use std::ptr;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use wasmer_vm::{TlsRestore, VMInterrupts};
+
+/// The size of the stack the guest runs on during an asynchronous call.
+const FIBER_STACK_SIZE: usize = 2 * 1024 * 1024;
+
+/// The value a [`Fiber`] is resumed with.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Resume {
+    /// The `Fiber` is being polled, the pending future must be polled
+    /// again.
+    Poll,
+    /// The `Fiber` is being dropped, the pending future must be dropped
+    /// and the guest unwound.
+    Cancel,
+}
+
+/// State shared between a [`Fiber`] and the asynchronous host
+/// functions running on it.
+struct AsyncCx {
+    /// The yielder of the coroutine, set once it has started.
+    yielder: Cell<*const Yielder<Resume, ()>>,
+    /// The context the `Fiber` is currently polled with.
+    poll_cx: Cell<*mut Context<'static>>,
+}
+
+thread_local! {
+    /// The `AsyncCx` of the `Fiber` currently running on this thread,
+    /// if any.
+    static CURRENT: Cell<*const AsyncCx> = Cell::new(ptr::null());
+}
+
+/// A `Future` running a closure on its own stack.
+///
+/// A `Fiber` is not `Send`: it must be polled to completion on the
+/// thread that created it.
+pub(crate) struct Fiber<R: 'static> {
+    coroutine: Coroutine<Resume, (), R, DefaultStack>,
+    async_cx: Box<AsyncCx>,
+    /// The interrupts of the store the guest belongs to.
+    interrupts: Arc<VMInterrupts>,
+    /// The stack limit of the guest while it is suspended.
+    stack_limit: usize,
+}
+
+impl<R: 'static> Fiber<R> {
+    /// Creates a new `Fiber` that runs `f` on a freshly allocated stack
+    /// when it is first polled.
+    ///
+    /// The stack limit in `interrupts` is switched along with the stack.
+    pub(crate) fn new<F>(interrupts: Arc<VMInterrupts>, f: F) -> Result<Self, RuntimeError>
+    where
+        F: FnOnce() -> R + 'static,
+    {
+        let stack = DefaultStack::new(FIBER_STACK_SIZE).map_err(|e| {
+            RuntimeError::new(format!("failed to allocate the stack of a fiber: {}", e))
+        })?;
+        let async_cx = Box::new(AsyncCx {
+            yielder: Cell::new(ptr::null()),
+            poll_cx: Cell::new(ptr::null_mut()),
+        });
+        let async_cx_ptr: *const AsyncCx = &*async_cx;
+        let async_cx_addr = async_cx_ptr as usize;
+        let coroutine = Coroutine::with_stack(stack, move |yielder, resume: Resume| {
+            let async_cx = unsafe { &*(async_cx_addr as *const AsyncCx) };
+            async_cx.yielder.set(yielder as *const _);
+            debug_assert!(resume == Resume::Poll);
+            f()
+        });
+
+        Ok(Self {
+            coroutine,
+            async_cx,
+            interrupts,
+            stack_limit: 0,
+        })
+    }
+
+    /// Switches to the stack of the coroutine until it either suspends
+    /// or completes.
+    fn resume(&mut self, resume: Resume) -> CoroutineResult<(), R> {
+        struct Reset(*const AsyncCx);
+
+        impl Drop for Reset {
+            fn drop(&mut self) {
+                CURRENT.with(|current| current.set(self.0));
+            }
+        }
+
+        let _reset = Reset(CURRENT.with(|current| current.replace(&*self.async_cx)));
+
+        // Any wasm call active on this stack must not be visible from
+        // the coroutine, and the other way around.
+        let outer = unsafe { TlsRestore::take() };
+        let outer_limit = self
+            .interrupts
+            .stack_limit
+            .swap(self.stack_limit, Ordering::SeqCst);
+        let result = self.coroutine.resume(resume);
+        self.stack_limit = self
+            .interrupts
+            .stack_limit
+            .swap(outer_limit, Ordering::SeqCst);
+        unsafe { outer.replace() }.expect("failed to restore the trap handling state");
+
+        result
+    }
+}
+
+impl<R: 'static> Future for Fiber<R> {
+    type Output = R;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // The context is only valid for the duration of this call; it
+        // is reset before returning.
+        self.async_cx.poll_cx.set((cx as *mut Context<'_>).cast());
+        let result = self.resume(Resume::Poll);
+        self.async_cx.poll_cx.set(ptr::null_mut());
+
+        match result {
+            CoroutineResult::Yield(()) => Poll::Pending,
+            CoroutineResult::Return(result) => Poll::Ready(result),
+        }
+    }
+}
+
+impl<R: 'static> Drop for Fiber<R> {
+    fn drop(&mut self) {
+        // Let the pending host function raise a trap, so that the guest
+        // is unwound the same way it is when it traps.
+        if self.coroutine.started() && !self.coroutine.done() {
+            let result = self.resume(Resume::Cancel);
+            debug_assert!(matches!(result, CoroutineResult::Return(_)));
+        }
+    }
+}
+
+/// Polls `future` to completion from an asynchronous host function,
+/// suspending the guest every time the future is not ready.
+///
+/// Returns an error if the host function is not running under
+/// [`Function::call_async`](crate::Function::call_async), or if the
+/// call was dropped while the future was pending.
+pub(crate) fn block_on<F: Future>(future: F) -> Result<F::Output, RuntimeError> {
+    let async_cx = CURRENT.with(|current| current.get());
+    if async_cx.is_null() {
+        return Err(RuntimeError::new(
+            "asynchronous host functions can only be called from `Function::call_async`",
+        ));
+    }
+    let async_cx = unsafe { &*async_cx };
+    let mut future = Box::pin(future);
+
+    loop {
+        let poll_cx = unsafe { &mut *async_cx.poll_cx.get() };
+        if let Poll::Ready(output) = future.as_mut().poll(poll_cx) {
+            return Ok(output);
+        }
+
+        let inner = unsafe { TlsRestore::take() };
+        let resume = unsafe { (*async_cx.yielder.get()).suspend(()) };
+        unsafe { inner.replace() }.map_err(RuntimeError::from_trap)?;
+
+        if resume == Resume::Cancel {
+            return Err(RuntimeError::new(
+                "the asynchronous call was dropped before it completed",
+            ));
+        }
+    }
+}
@@ -7,7 +7,7 @@ use std::fmt;
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
 use wasmer_engine::Resolver;
-use wasmer_vm::{InstanceHandle, VMContext};
+use wasmer_vm::{InstanceHandle, InstanceSnapshot, InterruptHandle, SnapshotError, VMContext};
 
 /// A WebAssembly Instance is a stateful, executable
 /// instance of a WebAssembly [`Module`].
@@ -159,6 +159,58 @@ impl Instance {
         self.module.store()
     }
 
+    /// Returns an [`InterruptHandle`] that can be used from any thread
+    /// to interrupt the WebAssembly code running in this instance.
+    ///
+    /// The handle is shared by all the instances of the same [`Store`],
+    /// see [`Store::interrupt_handle`].
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.store().interrupt_handle()
+    }
+
+    /// Takes a snapshot of the state of this instance: the contents of
+    /// its memories, the values of its mutable globals and the
+    /// elements of its tables. Imported ones aren't part of it.
+    ///
+    /// The snapshot can be serialized, and restored with
+    /// [`Instance::restore`] onto a fresh instance of the same module,
+    /// in this process or in another one.
+    ///
+    /// # Usage
+    ///
+    /// ```
+    /// # use wasmer::{imports, wat2wasm, Instance, InstanceSnapshot, Module, Store};
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let module = Module::new(&store, wat2wasm(br#"
+    ///     (module
+    ///       (global $counter (export "counter") (mut i32) (i32.const 0))
+    ///       (func (export "increment")
+    ///         (global.set $counter (i32.add (global.get $counter) (i32.const 1)))))
+    /// "#)?)?;
+    /// let instance = Instance::new(&module, &imports! {})?;
+    /// instance.exports.get_native_function::<(), ()>("increment")?.call()?;
+    /// let bytes = instance.snapshot()?.serialize()?;
+    ///
+    /// let restored = Instance::new(&module, &imports! {})?;
+    /// restored.restore(&InstanceSnapshot::deserialize(&bytes)?)?;
+    /// assert_eq!(restored.exports.get_global("counter")?.get().i32(), Some(1));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn snapshot(&self) -> Result<InstanceSnapshot, SnapshotError> {
+        self.handle.lock().unwrap().snapshot()
+    }
+
+    /// Restores a snapshot taken with [`Instance::snapshot`] from an
+    /// instance of the same module.
+    ///
+    /// The memories and tables of this instance grow to the size they
+    /// have in the snapshot, and mustn't be larger already.
+    pub fn restore(&self, snapshot: &InstanceSnapshot) -> Result<(), SnapshotError> {
+        self.handle.lock().unwrap().restore(snapshot)
+    }
+
     #[doc(hidden)]
     pub fn vmctx_ptr(&self) -> *mut VMContext {
         self.handle.lock().unwrap().vmctx_ptr()
@@ -221,6 +221,8 @@
 //! - `llvm` - enable Wasmer's LLVM compiler. (See [wasmer-llvm][])
 //! - `singlepass` - enable Wasmer's Singlepass compiler. (See [wasmer-singlepass][])
 //! - `wat` - enable `wasmer` to parse the WebAssembly text format.
+//! - `async` - enable asynchronous host functions and
+//!   `Function::call_async`.
 //!
 //! The features that set defaults come in sets that are mutually exclusive.
 //!
@@ -256,6 +258,8 @@
 mod env;
 mod exports;
 mod externals;
+#[cfg(feature = "async")]
+mod fiber;
 mod import_object;
 mod instance;
 mod module;
@@ -292,12 +296,12 @@ pub use crate::import_object::{ImportObject, ImportObjectIterator, LikeNamespace
 pub use crate::instance::{Instance, InstantiationError};
 pub use crate::module::Module;
 pub use crate::native::NativeFunc;
-pub use crate::ptr::{Array, Item, WasmPtr};
+pub use crate::ptr::{Array, Item, Memory32, Memory64, MemorySize, WasmPtr};
 pub use crate::store::{Store, StoreObject};
-pub use crate::tunables::BaseTunables;
+pub use crate::tunables::{BaseTunables, PoolingTunables};
 pub use crate::types::{
     ExportType, ExternRef, ExternType, FunctionType, GlobalType, HostInfo, HostRef, ImportType,
-    MemoryType, Mutability, TableType, Val, ValType,
+    IndexType, MemoryType, Mutability, TableType, Val, ValType,
 };
 pub use crate::types::{Val as Value, ValType as Type};
 pub use crate::utils::is_wasm;
@@ -316,11 +320,14 @@ pub use wasmer_engine::{
 };
 pub use wasmer_types::{
     Atomically, Bytes, ExportIndex, GlobalInit, LocalFunctionIndex, MemoryView, Pages, ValueType,
-    WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
+    WASM64_MAX_PAGES, WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
 };
 
 // TODO: should those be moved into wasmer::vm as well?
-pub use wasmer_vm::{raise_user_trap, MemoryError, VMExport};
+pub use wasmer_vm::{
+    raise_user_trap, GlobalSnapshot, InstancePool, InstanceSnapshot, InterruptHandle, MemoryError,
+    MemorySegment, MemorySnapshot, PoolingLimits, SnapshotError, TableSnapshot, TrapCode, VMExport,
+};
 pub mod vm {
     //! The vm module re-exports wasmer-vm types.
 
@@ -263,9 +263,12 @@ impl Module {
         resolver: &dyn Resolver,
     ) -> Result<InstanceHandle, InstantiationError> {
         unsafe {
-            let instance_handle =
-                self.artifact
-                    .instantiate(self.store.tunables(), resolver, Box::new(()))?;
+            let instance_handle = self.artifact.instantiate(
+                self.store.tunables(),
+                resolver,
+                self.store.interrupts().clone(),
+                Box::new(()),
+            )?;
 
             // After the instance handle is created, we need to initialize
             // the data, call the start function and so. However, if any
@@ -135,6 +135,7 @@ macro_rules! impl_native_traits {
                             }
                             rets_list.as_mut()
                         };
+                        let _stack_limit = self.store.interrupts().enter_wasm();
                         unsafe {
                             wasmer_vm::wasmer_call_trampoline(
                                 self.vmctx(),
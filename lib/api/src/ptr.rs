@@ -7,6 +7,7 @@
 //! related bugs when implementing an ABI.
 
 use crate::{externals::Memory, FromToNativeWasmType};
+use std::convert::TryFrom;
 use std::{cell::Cell, fmt, marker::PhantomData, mem};
 use wasmer_types::ValueType;
 
@@ -17,6 +18,38 @@ pub struct Array;
 /// specified.
 pub struct Item;
 
+/// The size of the offsets into a linear memory, which depends on the
+/// index type of the memory.
+pub trait MemorySize: Copy {
+    /// The type of an offset into the memory.
+    type Offset: Copy + Eq + fmt::LowerHex + FromToNativeWasmType + Into<u64> + TryFrom<usize>;
+}
+
+/// The `Memory32` marker type, for the offsets into 32-bit memories. This is
+/// the default and does not usually need to be specified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Memory32;
+
+impl MemorySize for Memory32 {
+    type Offset = u32;
+}
+
+/// The `Memory64` marker type, for the offsets into 64-bit memories, as
+/// defined by the memory64 proposal. This type can be used like
+/// `WasmPtr<T, Item, Memory64>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Memory64;
+
+impl MemorySize for Memory64 {
+    type Offset = u64;
+}
+
+/// Converts an offset or a length into a `usize`, if it fits.
+#[inline(always)]
+fn to_usize(value: impl Into<u64>) -> Option<usize> {
+    usize::try_from(value.into()).ok()
+}
+
 /// A zero-cost type that represents a pointer to something in Wasm linear
 /// memory.
 ///
@@ -32,17 +65,20 @@ pub struct Item;
 ///     derefed_ptr.set(inner_val + 1);
 /// }
 /// ```
+///
+/// Pointers into 64-bit memories use `Memory64` as their last type
+/// parameter, and have `u64` offsets.
 #[repr(transparent)]
-pub struct WasmPtr<T: Copy, Ty = Item> {
-    offset: u32,
+pub struct WasmPtr<T: Copy, Ty = Item, M: MemorySize = Memory32> {
+    offset: M::Offset,
     _phantom: PhantomData<(T, Ty)>,
 }
 
 /// Methods relevant to all types of `WasmPtr`.
-impl<T: Copy, Ty> WasmPtr<T, Ty> {
+impl<T: Copy, Ty, M: MemorySize> WasmPtr<T, Ty, M> {
     /// Create a new `WasmPtr` at the given offset.
     #[inline]
-    pub fn new(offset: u32) -> Self {
+    pub fn new(offset: M::Offset) -> Self {
         Self {
             offset,
             _phantom: PhantomData,
@@ -51,7 +87,7 @@ impl<T: Copy, Ty> WasmPtr<T, Ty> {
 
     /// Get the offset into Wasm linear memory for this `WasmPtr`.
     #[inline]
-    pub fn offset(self) -> u32 {
+    pub fn offset(self) -> M::Offset {
         self.offset
     }
 }
@@ -66,7 +102,7 @@ fn align_pointer(ptr: usize, align: usize) -> usize {
 /// Methods for `WasmPtr`s to data that can be dereferenced, namely to types
 /// that implement [`ValueType`], meaning that they're valid for all possible
 /// bit patterns.
-impl<T: Copy + ValueType> WasmPtr<T, Item> {
+impl<T: Copy + ValueType, M: MemorySize> WasmPtr<T, Item, M> {
     /// Dereference the `WasmPtr` getting access to a `&Cell<T>` allowing for
     /// reading and mutating of the inner value.
     ///
@@ -75,14 +111,15 @@ impl<T: Copy + ValueType> WasmPtr<T, Item> {
     /// This invariant will be enforced in the future.
     #[inline]
     pub fn deref<'a>(self, memory: &'a Memory) -> Option<&'a Cell<T>> {
-        if (self.offset as usize) + mem::size_of::<T>() > memory.size().bytes().0
+        let offset = to_usize(self.offset)?;
+        if offset.checked_add(mem::size_of::<T>())? > memory.size().bytes().0
             || mem::size_of::<T>() == 0
         {
             return None;
         }
         unsafe {
             let cell_ptr = align_pointer(
-                memory.view::<u8>().as_ptr().add(self.offset as usize) as usize,
+                memory.view::<u8>().as_ptr().add(offset) as usize,
                 mem::align_of::<T>(),
             ) as *const Cell<T>;
             Some(&*cell_ptr)
@@ -98,13 +135,14 @@ impl<T: Copy + ValueType> WasmPtr<T, Item> {
     ///   exclusive access to Wasm linear memory before calling this method.
     #[inline]
     pub unsafe fn deref_mut<'a>(self, memory: &'a Memory) -> Option<&'a mut Cell<T>> {
-        if (self.offset as usize) + mem::size_of::<T>() > memory.size().bytes().0
+        let offset = to_usize(self.offset)?;
+        if offset.checked_add(mem::size_of::<T>())? > memory.size().bytes().0
             || mem::size_of::<T>() == 0
         {
             return None;
         }
         let cell_ptr = align_pointer(
-            memory.view::<u8>().as_ptr().add(self.offset as usize) as usize,
+            memory.view::<u8>().as_ptr().add(offset) as usize,
             mem::align_of::<T>(),
         ) as *mut Cell<T>;
         Some(&mut *cell_ptr)
@@ -114,7 +152,7 @@ impl<T: Copy + ValueType> WasmPtr<T, Item> {
 /// Methods for `WasmPtr`s to arrays of data that can be dereferenced, namely to
 /// types that implement [`ValueType`], meaning that they're valid for all
 /// possible bit patterns.
-impl<T: Copy + ValueType> WasmPtr<T, Array> {
+impl<T: Copy + ValueType, M: MemorySize> WasmPtr<T, Array, M> {
     /// Dereference the `WasmPtr` getting access to a `&[Cell<T>]` allowing for
     /// reading and mutating of the inner values.
     ///
@@ -122,15 +160,17 @@ impl<T: Copy + ValueType> WasmPtr<T, Array> {
     /// If you're unsure what that means, it likely does not apply to you.
     /// This invariant will be enforced in the future.
     #[inline]
-    pub fn deref(self, memory: &Memory, index: u32, length: u32) -> Option<&[Cell<T>]> {
+    pub fn deref(self, memory: &Memory, index: M::Offset, length: M::Offset) -> Option<&[Cell<T>]> {
         // gets the size of the item in the array with padding added such that
         // for any index, we will always result an aligned memory access
         let item_size = mem::size_of::<T>() + (mem::size_of::<T>() % mem::align_of::<T>());
-        let slice_full_len = index as usize + length as usize;
+        let index = to_usize(index)?;
+        let slice_full_len = index.checked_add(to_usize(length)?)?;
         let memory_size = memory.size().bytes().0;
+        let offset = to_usize(self.offset)?;
 
-        if (self.offset as usize) + (item_size * slice_full_len) > memory_size
-            || self.offset as usize >= memory_size
+        if offset.checked_add(item_size.checked_mul(slice_full_len)?)? > memory_size
+            || offset >= memory_size
             || mem::size_of::<T>() == 0
         {
             return None;
@@ -138,11 +178,11 @@ impl<T: Copy + ValueType> WasmPtr<T, Array> {
 
         unsafe {
             let cell_ptr = align_pointer(
-                memory.view::<u8>().as_ptr().add(self.offset as usize) as usize,
+                memory.view::<u8>().as_ptr().add(offset) as usize,
                 mem::align_of::<T>(),
             ) as *const Cell<T>;
-            let cell_ptrs = &std::slice::from_raw_parts(cell_ptr, slice_full_len)
-                [index as usize..slice_full_len];
+            let cell_ptrs =
+                &std::slice::from_raw_parts(cell_ptr, slice_full_len)[index..slice_full_len];
             Some(cell_ptrs)
         }
     }
@@ -158,28 +198,30 @@ impl<T: Copy + ValueType> WasmPtr<T, Array> {
     pub unsafe fn deref_mut(
         self,
         memory: &Memory,
-        index: u32,
-        length: u32,
+        index: M::Offset,
+        length: M::Offset,
     ) -> Option<&mut [Cell<T>]> {
         // gets the size of the item in the array with padding added such that
         // for any index, we will always result an aligned memory access
         let item_size = mem::size_of::<T>() + (mem::size_of::<T>() % mem::align_of::<T>());
-        let slice_full_len = index as usize + length as usize;
+        let index = to_usize(index)?;
+        let slice_full_len = index.checked_add(to_usize(length)?)?;
         let memory_size = memory.size().bytes().0;
+        let offset = to_usize(self.offset)?;
 
-        if (self.offset as usize) + (item_size * slice_full_len) > memory.size().bytes().0
-            || self.offset as usize >= memory_size
+        if offset.checked_add(item_size.checked_mul(slice_full_len)?)? > memory_size
+            || offset >= memory_size
             || mem::size_of::<T>() == 0
         {
             return None;
         }
 
         let cell_ptr = align_pointer(
-            memory.view::<u8>().as_ptr().add(self.offset as usize) as usize,
+            memory.view::<u8>().as_ptr().add(offset) as usize,
             mem::align_of::<T>(),
         ) as *mut Cell<T>;
-        let cell_ptrs = &mut std::slice::from_raw_parts_mut(cell_ptr, slice_full_len)
-            [index as usize..slice_full_len];
+        let cell_ptrs =
+            &mut std::slice::from_raw_parts_mut(cell_ptr, slice_full_len)[index..slice_full_len];
         Some(cell_ptrs)
     }
 
@@ -200,27 +242,31 @@ impl<T: Copy + ValueType> WasmPtr<T, Array> {
     ///
     /// Additionally, if `memory` is dynamic, the caller must also ensure that `memory`
     /// is not grown while the reference is held.
-    pub unsafe fn get_utf8_str<'a>(self, memory: &'a Memory, str_len: u32) -> Option<&'a str> {
+    pub unsafe fn get_utf8_str<'a>(
+        self,
+        memory: &'a Memory,
+        str_len: M::Offset,
+    ) -> Option<&'a str> {
         let memory_size = memory.size().bytes().0;
+        let offset = to_usize(self.offset)?;
+        let str_len = to_usize(str_len)?;
 
-        if self.offset as usize + str_len as usize > memory.size().bytes().0
-            || self.offset as usize >= memory_size
-        {
+        if offset.checked_add(str_len)? > memory_size || offset >= memory_size {
             return None;
         }
-        let ptr = memory.view::<u8>().as_ptr().add(self.offset as usize) as *const u8;
-        let slice: &[u8] = std::slice::from_raw_parts(ptr, str_len as usize);
+        let ptr = memory.view::<u8>().as_ptr().add(offset) as *const u8;
+        let slice: &[u8] = std::slice::from_raw_parts(ptr, str_len);
         std::str::from_utf8(slice).ok()
     }
 
     /// Get a UTF-8 `String` from the `WasmPtr` with the given length.
     ///
     /// an aliasing `WasmPtr` is used to mutate memory.
-    pub fn get_utf8_string(self, memory: &Memory, str_len: u32) -> Option<String> {
+    pub fn get_utf8_string(self, memory: &Memory, str_len: M::Offset) -> Option<String> {
         let memory_size = memory.size().bytes().0;
-        if self.offset as usize + str_len as usize > memory.size().bytes().0
-            || self.offset as usize >= memory_size
-        {
+        let offset = to_usize(self.offset)?;
+        let str_len = to_usize(str_len)?;
+        if offset.checked_add(str_len)? > memory_size || offset >= memory_size {
             return None;
         }
 
@@ -228,9 +274,9 @@ impl<T: Copy + ValueType> WasmPtr<T, Array> {
         // micro-optimization here and this may be a fairly common function in user code.
         let view = memory.view::<u8>();
 
-        let mut vec: Vec<u8> = Vec::with_capacity(str_len as usize);
-        let base = self.offset as usize;
-        for i in 0..(str_len as usize) {
+        let mut vec: Vec<u8> = Vec::with_capacity(str_len);
+        let base = offset;
+        for i in 0..str_len {
             let byte = view[base + i].get();
             vec.push(byte);
         }
@@ -247,11 +293,12 @@ impl<T: Copy + ValueType> WasmPtr<T, Array> {
     /// This method behaves similarly to [`WasmPtr::get_utf8_str`], all safety invariants on
     /// that method must also be upheld here.
     pub unsafe fn get_utf8_str_with_nul<'a>(self, memory: &'a Memory) -> Option<&'a str> {
-        memory.view::<u8>()[(self.offset as usize)..]
+        memory.view::<u8>()[to_usize(self.offset)?..]
             .iter()
             .map(|cell| cell.get())
             .position(|byte| byte == 0)
-            .and_then(|length| self.get_utf8_str(memory, length as u32))
+            .and_then(|length| M::Offset::try_from(length).ok())
+            .and_then(|length| self.get_utf8_str(memory, length))
     }
 
     /// Get a UTF-8 `String` from the `WasmPtr`, where the string is nul-terminated.
@@ -263,23 +310,23 @@ impl<T: Copy + ValueType> WasmPtr<T, Array> {
     }
 }
 
-unsafe impl<T: Copy, Ty> FromToNativeWasmType for WasmPtr<T, Ty> {
-    type Native = i32;
+unsafe impl<T: Copy, Ty, M: MemorySize> FromToNativeWasmType for WasmPtr<T, Ty, M> {
+    type Native = <M::Offset as FromToNativeWasmType>::Native;
 
     fn to_native(self) -> Self::Native {
-        self.offset as i32
+        self.offset.to_native()
     }
     fn from_native(n: Self::Native) -> Self {
         Self {
-            offset: n as u32,
+            offset: M::Offset::from_native(n),
             _phantom: PhantomData,
         }
     }
 }
 
-unsafe impl<T: Copy, Ty> ValueType for WasmPtr<T, Ty> {}
+unsafe impl<T: Copy, Ty, M: MemorySize> ValueType for WasmPtr<T, Ty, M> {}
 
-impl<T: Copy, Ty> Clone for WasmPtr<T, Ty> {
+impl<T: Copy, Ty, M: MemorySize> Clone for WasmPtr<T, Ty, M> {
     fn clone(&self) -> Self {
         Self {
             offset: self.offset,
@@ -288,17 +335,17 @@ impl<T: Copy, Ty> Clone for WasmPtr<T, Ty> {
     }
 }
 
-impl<T: Copy, Ty> Copy for WasmPtr<T, Ty> {}
+impl<T: Copy, Ty, M: MemorySize> Copy for WasmPtr<T, Ty, M> {}
 
-impl<T: Copy, Ty> PartialEq for WasmPtr<T, Ty> {
+impl<T: Copy, Ty, M: MemorySize> PartialEq for WasmPtr<T, Ty, M> {
     fn eq(&self, other: &Self) -> bool {
         self.offset == other.offset
     }
 }
 
-impl<T: Copy, Ty> Eq for WasmPtr<T, Ty> {}
+impl<T: Copy, Ty, M: MemorySize> Eq for WasmPtr<T, Ty, M> {}
 
-impl<T: Copy, Ty> fmt::Debug for WasmPtr<T, Ty> {
+impl<T: Copy, Ty, M: MemorySize> fmt::Debug for WasmPtr<T, Ty, M> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "WasmPtr({:#x})", self.offset)
     }
@@ -394,4 +441,25 @@ mod test {
             assert!(unsafe { oob_end_array_ptr.deref_mut(&memory, 1, 0).is_none() });
         }
     }
+
+    /// Ensure that pointers into 64-bit memories are bounds checked on their
+    /// whole offset.
+    #[test]
+    fn wasm_ptr_memory64_bounds_checks_hold() {
+        let store = Store::default();
+        let memory_descriptor = MemoryType::new64(1, Some(1));
+        let memory = Memory::new(&store, memory_descriptor).unwrap();
+
+        let last_valid_address_for_u32 = memory.size().bytes().0 as u64 - 4;
+        let end_wasm_ptr: WasmPtr<u32, Item, Memory64> = WasmPtr::new(last_valid_address_for_u32);
+        assert!(end_wasm_ptr.deref(&memory).is_some());
+        assert_eq!(end_wasm_ptr.to_native(), last_valid_address_for_u32 as i64);
+
+        // Offsets past 4GiB must not wrap around.
+        let wrapping_wasm_ptr: WasmPtr<u32, Item, Memory64> = WasmPtr::new(1 << 32);
+        assert!(wrapping_wasm_ptr.deref(&memory).is_none());
+        let huge_wasm_ptr: WasmPtr<u8, Array, Memory64> = WasmPtr::new(u64::MAX);
+        assert!(huge_wasm_ptr.deref(&memory, 0, 1).is_none());
+        assert!(huge_wasm_ptr.get_utf8_string(&memory, 1).is_none());
+    }
 }
@@ -4,6 +4,7 @@ use std::sync::Arc;
 #[cfg(all(feature = "compiler", feature = "engine"))]
 use wasmer_compiler::CompilerConfig;
 use wasmer_engine::{Engine, Tunables};
+use wasmer_vm::{InterruptHandle, VMInterrupts};
 
 /// The store represents all global state that can be manipulated by
 /// WebAssembly programs. It consists of the runtime representation
@@ -19,6 +20,7 @@ use wasmer_engine::{Engine, Tunables};
 pub struct Store {
     engine: Arc<dyn Engine + Send + Sync>,
     tunables: Arc<dyn Tunables + Send + Sync>,
+    interrupts: Arc<VMInterrupts>,
 }
 
 impl Store {
@@ -30,6 +32,7 @@ impl Store {
         Self {
             engine: engine.cloned(),
             tunables: Arc::new(BaseTunables::for_target(engine.target())),
+            interrupts: Arc::new(VMInterrupts::default()),
         }
     }
 
@@ -41,6 +44,7 @@ impl Store {
         Self {
             engine: engine.cloned(),
             tunables: Arc::new(tunables),
+            interrupts: Arc::new(VMInterrupts::default()),
         }
     }
 
@@ -54,6 +58,42 @@ impl Store {
         &self.engine
     }
 
+    /// Returns an [`InterruptHandle`] that can be used from any thread
+    /// to interrupt the WebAssembly code running in the instances of
+    /// this store.
+    ///
+    /// The interrupted code traps with [`TrapCode::Interrupt`] at its
+    /// next function entry or loop header.
+    ///
+    /// [`TrapCode::Interrupt`]: crate::TrapCode::Interrupt
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        InterruptHandle::new(self.interrupts.clone())
+    }
+
+    /// Sets the maximum number of bytes of stack that the WebAssembly
+    /// code of this store may use below the point where the host calls
+    /// into it. Zero, the default, means no limit.
+    ///
+    /// Only code compiled with explicit stack checks, such as Singlepass
+    /// code with `Singlepass::enable_stack_check` set, enforces the limit, by
+    /// trapping with [`TrapCode::StackOverflow`] at function entries.
+    /// This lets guest code run on host threads with small or custom
+    /// stacks, which a guard page may not protect.
+    ///
+    /// The limit is computed every time the host calls into WebAssembly,
+    /// and the previous one is restored when the call returns. All the
+    /// threads running code of this store share it, so it should not be
+    /// relied upon when the store runs code on several threads at once.
+    ///
+    /// [`TrapCode::StackOverflow`]: crate::TrapCode::StackOverflow
+    pub fn set_max_wasm_stack(&self, max_wasm_stack: usize) {
+        self.interrupts.set_max_wasm_stack(max_wasm_stack);
+    }
+
+    pub(crate) fn interrupts(&self) -> &Arc<VMInterrupts> {
+        &self.interrupts
+    }
+
     /// Checks whether two stores are identical. A store is considered
     /// equal to another store if both have the same engine. The
     /// tunables are excluded from the logic.
@@ -111,6 +151,7 @@ impl Default for Store {
         Store {
             engine: Arc::new(engine),
             tunables: Arc::new(tunables),
+            interrupts: Arc::new(VMInterrupts::default()),
         }
     }
 }
@@ -4,11 +4,12 @@ use std::ptr::NonNull;
 use std::sync::Arc;
 use target_lexicon::{OperatingSystem, PointerWidth};
 use wasmer_compiler::Target;
+use wasmer_engine::LinkError;
 use wasmer_engine::Tunables;
 use wasmer_vm::MemoryError;
 use wasmer_vm::{
-    LinearMemory, LinearTable, Memory, MemoryStyle, Table, TableStyle, VMMemoryDefinition,
-    VMTableDefinition,
+    InstanceAllocator, InstancePool, LinearMemory, LinearTable, Memory, MemoryStyle, ModuleInfo,
+    PoolingLimits, Table, TableStyle, VMMemoryDefinition, VMTableDefinition,
 };
 
 /// Tunable parameters for WebAssembly compilation.
@@ -75,8 +76,11 @@ impl Tunables for BaseTunables {
         // tunables make it static.
         //
         // If the module doesn't declare an explicit maximum treat it as 4GiB.
+        //
+        // 64-bit memories are always dynamic: no guard region can cover
+        // their whole index range.
         let maximum = memory.maximum.unwrap_or_else(Pages::max_value);
-        if maximum <= self.static_memory_bound {
+        if !memory.is_64() && maximum <= self.static_memory_bound {
             MemoryStyle::Static {
                 // Bound can be larger than the maximum for performance reasons
                 bound: self.static_memory_bound,
@@ -149,6 +153,132 @@ impl Tunables for BaseTunables {
     }
 }
 
+/// Tunables that instantiate modules in the preallocated slots of an
+/// [`InstancePool`], instead of allocating and mapping memory for each
+/// instance.
+///
+/// Instantiation then takes microseconds, and the pool limits the
+/// number of instances alive at the same time: when it is exhausted,
+/// instantiation fails with a [`LinkError::Resource`]. Memories and
+/// tables created by the host are not pooled.
+///
+/// The local memories can't grow beyond [`PoolingLimits::memory_pages`].
+/// They are bounds checked unless the pool has room for 4 GiB memories,
+/// see [`InstancePool::memory_style`].
+///
+/// ```
+/// # use wasmer::{PoolingLimits, PoolingTunables, Store};
+/// # fn example(engine: &dyn wasmer::Engine) -> Result<(), String> {
+/// let tunables = PoolingTunables::new(PoolingLimits {
+///     instances: 100,
+///     ..PoolingLimits::default()
+/// })?;
+/// let store = Store::new_with_tunables(engine, tunables);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct PoolingTunables {
+    pool: Arc<InstancePool>,
+}
+
+impl PoolingTunables {
+    /// Create the `PoolingTunables`, preallocating a pool for `limits`.
+    pub fn new(limits: PoolingLimits) -> Result<Self, String> {
+        Ok(Self {
+            pool: InstancePool::new(limits)?,
+        })
+    }
+
+    /// Returns the [`InstancePool`] the instances are allocated in.
+    pub fn pool(&self) -> &Arc<InstancePool> {
+        &self.pool
+    }
+}
+
+impl Tunables for PoolingTunables {
+    /// Get the `MemoryStyle` of the memories of the pool.
+    fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        self.pool.memory_style(memory)
+    }
+
+    /// Get a [`TableStyle`] for the provided [`TableType`].
+    fn table_style(&self, _table: &TableType) -> TableStyle {
+        TableStyle::CallerChecksSignature
+    }
+
+    /// Create a memory owned by the host given a [`MemoryType`] and a [`MemoryStyle`].
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        Ok(Arc::new(LinearMemory::new(ty, style)?))
+    }
+
+    /// Create a memory owned by the VM in a memory slot of the pool.
+    ///
+    /// # Safety
+    /// - `vm_definition_location` must point to a valid, owned `VMMemoryDefinition`,
+    ///   for example in `VMContext`.
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        Ok(Arc::new(self.pool.allocate_memory(
+            ty,
+            style,
+            vm_definition_location,
+        )?))
+    }
+
+    /// Create a table owned by the host given a [`TableType`] and a [`TableStyle`].
+    fn create_host_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+    ) -> Result<Arc<dyn Table>, String> {
+        Ok(Arc::new(LinearTable::new(ty, style)?))
+    }
+
+    /// Create a table owned by the VM in a table slot of the pool.
+    ///
+    /// # Safety
+    /// - `vm_definition_location` must point to a valid, owned `VMTableDefinition`,
+    ///   for example in `VMContext`.
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<Arc<dyn Table>, String> {
+        Ok(Arc::new(self.pool.allocate_table(
+            ty,
+            style,
+            vm_definition_location,
+        )?))
+    }
+
+    /// Allocate the instance data in an instance slot of the pool.
+    fn create_instance_allocator(
+        &self,
+        module: &ModuleInfo,
+    ) -> Result<
+        (
+            InstanceAllocator,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        LinkError,
+    > {
+        self.pool
+            .allocate_instance(module)
+            .map_err(LinkError::Resource)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -190,5 +320,13 @@ mod tests {
             }
             s => panic!("Unexpected memory style: {:?}", s),
         }
+
+        // 64-bit memory with a small maximum
+        let requested = MemoryType::new64(3, Some(16));
+        let style = tunables.memory_style(&requested);
+        match style {
+            MemoryStyle::Dynamic { offset_guard_size } => assert_eq!(offset_guard_size, 256),
+            s => panic!("Unexpected memory style: {:?}", s),
+        }
     }
 }
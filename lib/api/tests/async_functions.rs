@@ -0,0 +1,202 @@
+#![cfg(feature = "async")]
+
+use anyhow::Result;
+use futures::task::noop_waker;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use wasmer::*;
+
+/// A future that is pending the first time it is polled.
+struct YieldOnce(bool);
+
+impl Future for YieldOnce {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+fn instance_with_async_import(store: &Store) -> Result<Instance> {
+    let module = Module::new(
+        store,
+        r#"
+    (module
+      (import "host" "double" (func $double (param i32) (result i32)))
+      (func (export "run") (param i32) (result i32)
+        local.get 0
+        call $double
+        i32.const 1
+        i32.add)
+      (func (export "trap") (param i32) (result i32)
+        local.get 0
+        call $double
+        unreachable))
+"#,
+    )?;
+    let double = Function::new_async(
+        store,
+        FunctionType::new(vec![Type::I32], vec![Type::I32]),
+        |args| {
+            let value = args[0].unwrap_i32();
+            async move {
+                YieldOnce(false).await;
+                Ok(vec![Value::I32(value * 2)])
+            }
+        },
+    );
+    let import_object = imports! {
+        "host" => {
+            "double" => double,
+        },
+    };
+
+    Ok(Instance::new(&module, &import_object)?)
+}
+
+#[test]
+fn call_async_suspends_on_pending_host_futures() -> Result<()> {
+    let store = Store::default();
+    let instance = instance_with_async_import(&store)?;
+    let run = instance.exports.get_function("run")?;
+
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut call = Box::pin(run.call_async(&[Value::I32(20)]));
+
+    assert!(call.as_mut().poll(&mut cx).is_pending());
+    match call.as_mut().poll(&mut cx) {
+        Poll::Ready(result) => assert_eq!(result?.into_vec(), vec![Value::I32(41)]),
+        Poll::Pending => panic!("the call should have completed"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn call_async_interleaves_calls_on_one_thread() -> Result<()> {
+    let store = Store::default();
+    let instance = instance_with_async_import(&store)?;
+    let run = instance.exports.get_function("run")?;
+    let trap = instance.exports.get_function("trap")?;
+
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut first = Box::pin(trap.call_async(&[Value::I32(1)]));
+    let mut second = Box::pin(run.call_async(&[Value::I32(2)]));
+
+    assert!(first.as_mut().poll(&mut cx).is_pending());
+    assert!(second.as_mut().poll(&mut cx).is_pending());
+
+    // The trap raised by the first call must unwind its own stack.
+    match first.as_mut().poll(&mut cx) {
+        Poll::Ready(result) => assert!(result.unwrap_err().to_trap().is_some()),
+        Poll::Pending => panic!("the first call should have completed"),
+    }
+    match second.as_mut().poll(&mut cx) {
+        Poll::Ready(result) => assert_eq!(result?.into_vec(), vec![Value::I32(5)]),
+        Poll::Pending => panic!("the second call should have completed"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn call_async_can_be_dropped_while_pending() -> Result<()> {
+    let store = Store::default();
+    let instance = instance_with_async_import(&store)?;
+    let run = instance.exports.get_function("run")?;
+
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut call = Box::pin(run.call_async(&[Value::I32(20)]));
+    assert!(call.as_mut().poll(&mut cx).is_pending());
+    drop(call);
+
+    // The instance is still usable afterwards.
+    let result = futures::executor::block_on(run.call_async(&[Value::I32(3)]))?;
+    assert_eq!(result.into_vec(), vec![Value::I32(7)]);
+
+    Ok(())
+}
+
+#[test]
+fn async_host_functions_fail_outside_of_call_async() -> Result<()> {
+    let store = Store::default();
+    let instance = instance_with_async_import(&store)?;
+    let run = instance.exports.get_function("run")?;
+
+    let error = run.call(&[Value::I32(1)]).unwrap_err();
+    assert_eq!(
+        error.message(),
+        "asynchronous host functions can only be called from `Function::call_async`"
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "singlepass")]
+fn call_async_keeps_its_own_stack_limit() -> Result<()> {
+    let mut compiler = Singlepass::new();
+    compiler.enable_stack_check(true);
+    let store = Store::new(&JIT::new(compiler).engine());
+    store.set_max_wasm_stack(16 * 1024);
+    let module = Module::new(
+        &store,
+        r#"
+    (module
+      (import "host" "yield" (func $yield))
+      (func $depth (export "depth") (param i32) (result i32)
+        (if (result i32) (local.get 0)
+          (then (i32.add (i32.const 1)
+                         (call $depth (i32.sub (local.get 0) (i32.const 1)))))
+          (else (i32.const 0))))
+      (func (export "suspend") (param i32) (result i32)
+        (call $yield)
+        (call $depth (local.get 0))))
+"#,
+    )?;
+    let yield_once = Function::new_async(&store, FunctionType::new(vec![], vec![]), |_| async {
+        YieldOnce(false).await;
+        Ok(vec![])
+    });
+    let import_object = imports! {
+        "host" => {
+            "yield" => yield_once,
+        },
+    };
+    let instance = Instance::new(&module, &import_object)?;
+    let depth = instance.exports.get_function("depth")?;
+    let suspend = instance.exports.get_function("suspend")?;
+
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut call = Box::pin(suspend.call_async(&[Value::I32(100)]));
+    assert!(call.as_mut().poll(&mut cx).is_pending());
+
+    // While the guest is suspended on its fiber, calls on the thread's
+    // stack are checked against a limit for that stack. The deep call
+    // fits in the stack, only the limit makes it trap.
+    let result = depth.call(&[Value::I32(100)])?;
+    assert_eq!(result.into_vec(), vec![Value::I32(100)]);
+    let error = depth.call(&[Value::I32(5000)]).unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::StackOverflow));
+
+    // And the suspended guest gets its own limit back.
+    match call.as_mut().poll(&mut cx) {
+        Poll::Ready(result) => assert_eq!(result?.into_vec(), vec![Value::I32(100)]),
+        Poll::Pending => panic!("the call should have completed"),
+    }
+    let error = futures::executor::block_on(suspend.call_async(&[Value::I32(5000)])).unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::StackOverflow));
+
+    Ok(())
+}
@@ -137,11 +137,7 @@ fn table_copy() -> Result<()> {
 #[test]
 fn memory_new() -> Result<()> {
     let store = Store::default();
-    let memory_type = MemoryType {
-        shared: false,
-        minimum: Pages(0),
-        maximum: Some(Pages(10)),
-    };
+    let memory_type = MemoryType::new(Pages(0), Some(Pages(10)), false);
     let memory = Memory::new(&store, memory_type)?;
     assert_eq!(memory.size(), Pages(0));
     assert_eq!(*memory.ty(), memory_type);
@@ -37,3 +37,50 @@ fn exports_work_after_multiple_instances_have_been_freed() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn pooling_tunables_limit_and_reuse_the_instances() -> Result<()> {
+    let tunables = PoolingTunables::new(PoolingLimits {
+        instances: 2,
+        memory_pages: Pages(2),
+        ..PoolingLimits::default()
+    })
+    .map_err(anyhow::Error::msg)?;
+    let pool = tunables.pool().clone();
+    let store = Store::new_with_tunables(Store::default().engine().as_ref(), tunables);
+    let module = Module::new(
+        &store,
+        r#"
+    (module
+      (memory (export "memory") 1)
+      (table 1 funcref)
+      (func (export "grow") (result i32)
+        i32.const 1
+        memory.grow))
+"#,
+    )?;
+
+    let import_object = ImportObject::new();
+    let first = Instance::new(&module, &import_object)?;
+    let _second = Instance::new(&module, &import_object)?;
+    assert_eq!(pool.available_instances(), 0);
+    assert!(matches!(
+        Instance::new(&module, &import_object),
+        Err(InstantiationError::Link(LinkError::Resource(_)))
+    ));
+
+    first.exports.get_memory("memory")?.view::<u8>()[0].set(42);
+    drop(first);
+    assert_eq!(pool.available_instances(), 1);
+
+    // The freed slots are reused, and the memory is zeroed.
+    let third = Instance::new(&module, &import_object)?;
+    assert_eq!(third.exports.get_memory("memory")?.view::<u8>()[0].get(), 0);
+
+    // A pooled memory can't grow beyond the limit of the pool.
+    let grow = third.exports.get_native_function::<(), i32>("grow")?;
+    assert_eq!(grow.call()?, 1);
+    assert_eq!(grow.call()?, -1);
+
+    Ok(())
+}
@@ -1,15 +1,28 @@
 use crate::cache::Cache;
 use crate::hash::Hash;
-use std::fs::{create_dir_all, File};
+use filetime::FileTime;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, create_dir_all};
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
 use wasmer::{DeserializeError, Module, SerializeError, Store};
 
+/// The extension of the files holding the checksums of the modules.
+const CHECKSUM_EXTENSION: &str = "checksum";
+
 /// Representation of a directory that contains compiled wasm artifacts.
 ///
 /// The `FileSystemCache` type implements the [`Cache`] trait, which allows it to be used
 /// generically when some sort of cache is required.
 ///
+/// Each module is written next to the checksum of its contents, which
+/// `load` verifies on the very bytes it deserializes, and both files are
+/// written to a temporary file first
+/// and then renamed, so that other processes sharing the directory never
+/// load a partially written module. The cache can be bounded with
+/// [`FileSystemCache::set_max_size`].
+///
 /// # Usage
 ///
 /// ```
@@ -21,11 +34,12 @@ use wasmer::{DeserializeError, Module, SerializeError, Store};
 ///     // Create a new file system cache.
 ///     let mut fs_cache = FileSystemCache::new("some/directory/goes/here")?;
 ///
-///     // Compute a key for a given WebAssembly binary
-///     let key = Hash::generate(bytes);
-///
-///     // Store a module into the cache given a key
-///     fs_cache.store(key, module)?;
+///     // Compute a key for a given WebAssembly binary and the engine
+///     // compiling it, unless the engine can't be cached
+///     if let Some(key) = Hash::generate_for_engine(&**module.store().engine(), bytes) {
+///         // Store a module into the cache given a key
+///         fs_cache.store(key, module)?;
+///     }
 ///
 ///     Ok(())
 /// }
@@ -33,6 +47,7 @@ use wasmer::{DeserializeError, Module, SerializeError, Store};
 pub struct FileSystemCache {
     path: PathBuf,
     ext: Option<String>,
+    max_size: Option<u64>,
 }
 
 impl FileSystemCache {
@@ -43,7 +58,11 @@ impl FileSystemCache {
             let metadata = path.metadata()?;
             if metadata.is_dir() {
                 if !metadata.permissions().readonly() {
-                    Ok(Self { path, ext: None })
+                    Ok(Self {
+                        path,
+                        ext: None,
+                        max_size: None,
+                    })
                 } else {
                     // This directory is readonly.
                     Err(io::Error::new(
@@ -64,7 +83,11 @@ impl FileSystemCache {
         } else {
             // Create the directory and any parent directories if they don't yet exist.
             create_dir_all(&path)?;
-            Ok(Self { path, ext: None })
+            Ok(Self {
+                path,
+                ext: None,
+                max_size: None,
+            })
         }
     }
 
@@ -75,34 +98,202 @@ impl FileSystemCache {
     pub fn set_cache_extension(&mut self, ext: Option<impl ToString>) {
         self.ext = ext.map(|ext| ext.to_string());
     }
-}
 
-impl Cache for FileSystemCache {
-    type DeserializeError = DeserializeError;
-    type SerializeError = SerializeError;
+    /// Set the maximum size, in bytes, of the files of the cache.
+    ///
+    /// When storing a module makes the cache larger, the least recently
+    /// used modules are removed until it fits again. The module just
+    /// stored is always kept.
+    pub fn set_max_size(&mut self, max_size: Option<u64>) {
+        self.max_size = max_size;
+    }
 
-    unsafe fn load(&self, store: &Store, key: Hash) -> Result<Module, Self::DeserializeError> {
+    fn module_path(&self, key: Hash) -> PathBuf {
         let filename = if let Some(ref ext) = self.ext {
             format!("{}.{}", key.to_string(), ext)
         } else {
             key.to_string()
         };
-        let path = self.path.join(filename);
-        Module::deserialize_from_file(&store, path)
+        self.path.join(filename)
     }
 
-    fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
-        let filename = if let Some(ref ext) = self.ext {
-            format!("{}.{}", key.to_string(), ext)
-        } else {
-            key.to_string()
-        };
-        let path = self.path.join(filename);
-        let mut file = File::create(path)?;
+    /// Remove the least recently used modules, other than `keep`, until
+    /// the files of the cache take at most `max_size` bytes.
+    fn evict(&self, max_size: u64, keep: &Path) -> io::Result<()> {
+        let mut modules = Vec::new();
+        let mut size = 0;
+        for entry in fs::read_dir(&self.path)? {
+            let entry = entry?;
+            let path = entry.path();
+            // Checksums are counted with their module, and temporary files
+            // belong to the modules being written.
+            if is_checksum(&path) || entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            // The module may have been removed by another process.
+            let metadata = match entry.metadata() {
+                Ok(metadata) if metadata.is_file() => metadata,
+                _ => continue,
+            };
+            let module_size = metadata.len()
+                + fs::metadata(checksum_path(&path))
+                    .map(|metadata| metadata.len())
+                    .unwrap_or(0);
+            size += module_size;
+            modules.push((
+                FileTime::from_last_modification_time(&metadata),
+                module_size,
+                path,
+            ));
+        }
 
+        modules.sort_by_key(|(last_use, _, _)| *last_use);
+        for (_, module_size, path) in modules {
+            if size <= max_size {
+                break;
+            }
+            if path != keep {
+                remove_module(&path)?;
+                size -= module_size;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Cache for FileSystemCache {
+    type DeserializeError = DeserializeError;
+    type SerializeError = SerializeError;
+
+    unsafe fn load(&self, store: &Store, key: Hash) -> Result<Module, Self::DeserializeError> {
+        let path = self.module_path(key);
+        // The module is read once, so that another process replacing it
+        // can't make us deserialize other bytes than the ones verified.
+        let buffer = fs::read(&path)?;
+        verify_checksum(&path, &buffer)?;
+        let module = Module::deserialize(store, &buffer)?;
+        // The modification time records the last use of the module.
+        let _ = filetime::set_file_mtime(&path, FileTime::now());
+        Ok(module)
+    }
+
+    fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
+        let path = self.module_path(key);
         let buffer = module.serialize()?;
-        file.write_all(&buffer)?;
+        write_atomically(&path, &buffer)?;
+        write_atomically(
+            &checksum_path(&path),
+            Hash::generate(&buffer).to_string().as_bytes(),
+        )?;
 
+        if let Some(max_size) = self.max_size {
+            self.evict(max_size, &path)?;
+        }
         Ok(())
     }
 }
+
+/// Returns the path of the checksum of the module at `path`.
+fn checksum_path(path: &Path) -> PathBuf {
+    let mut checksum_path = OsString::from(path);
+    checksum_path.push(".");
+    checksum_path.push(CHECKSUM_EXTENSION);
+    checksum_path.into()
+}
+
+fn is_checksum(path: &Path) -> bool {
+    path.extension() == Some(OsStr::new(CHECKSUM_EXTENSION))
+}
+
+/// Check that `contents`, read from the module at `path`, match its
+/// checksum.
+fn verify_checksum(path: &Path, contents: &[u8]) -> Result<(), DeserializeError> {
+    let expected = fs::read_to_string(checksum_path(path))?.parse::<Hash>()?;
+    if Hash::generate(contents) != expected {
+        return Err(DeserializeError::CorruptedBinary(format!(
+            "`{}` doesn't match its checksum",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Write `contents` to a temporary file, and then rename it to `path`.
+pub(crate) fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = NamedTempFile::new_in(path.parent().unwrap())?;
+    file.write_all(contents)?;
+    file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Remove the module at `path` and its checksum, unless another process
+/// already did.
+fn remove_module(path: &Path) -> io::Result<()> {
+    for path in &[path.to_path_buf(), checksum_path(path)] {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_module(cache: &FileSystemCache, key: u8, contents: &[u8], last_use: i64) -> PathBuf {
+        let path = cache.module_path(Hash::new([key; 32]));
+        write_atomically(&path, contents).unwrap();
+        write_atomically(
+            &checksum_path(&path),
+            Hash::generate(contents).to_string().as_bytes(),
+        )
+        .unwrap();
+        filetime::set_file_mtime(&path, FileTime::from_unix_time(last_use, 0)).unwrap();
+        path
+    }
+
+    #[test]
+    fn corrupted_modules_are_detected() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = FileSystemCache::new(dir.path()).unwrap();
+        let path = write_module(&cache, 0, b"module", 0);
+        assert!(verify_checksum(&path, b"module").is_ok());
+
+        assert!(matches!(
+            verify_checksum(&path, b"modulE"),
+            Err(DeserializeError::CorruptedBinary(_))
+        ));
+        fs::remove_file(checksum_path(&path)).unwrap();
+        assert!(matches!(
+            verify_checksum(&path, b"module"),
+            Err(DeserializeError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn least_recently_used_modules_are_evicted() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = FileSystemCache::new(dir.path()).unwrap();
+        cache.set_cache_extension(Some("bin"));
+        let contents = [0; 100];
+        let old = write_module(&cache, 0, &contents, 1_000);
+        let recent = write_module(&cache, 1, &contents, 3_000);
+        let kept = write_module(&cache, 2, &contents, 2_000);
+        fs::write(dir.path().join(".tmp1234"), &contents[..]).unwrap();
+        let module_size = 100 + 64;
+
+        cache.evict(3 * module_size, &kept).unwrap();
+        assert!(old.exists() && recent.exists() && kept.exists());
+
+        cache.evict(2 * module_size, &kept).unwrap();
+        assert!(!old.exists() && !checksum_path(&old).exists());
+        assert!(recent.exists() && kept.exists());
+
+        cache.evict(0, &kept).unwrap();
+        assert!(!recent.exists());
+        assert!(kept.exists() && checksum_path(&kept).exists());
+        assert!(dir.path().join(".tmp1234").exists());
+    }
+}
@@ -1,6 +1,7 @@
 use crate::DeserializeError;
 use std::str::FromStr;
 use std::string::ToString;
+use wasmer::Engine;
 
 /// A hash used as a key when loading and storing modules in a
 /// [`Cache`].
@@ -17,11 +18,39 @@ impl Hash {
     }
 
     /// Creates a new hash from a slice of bytes.
+    ///
+    /// The hash only depends on `bytes`: the modules compiled from them
+    /// by engines with different settings get the same key. Use
+    /// `Hash::generate_for_engine` to tell them apart.
     pub fn generate(bytes: &[u8]) -> Self {
         let hash = blake3::hash(bytes);
         Self::new(hash.into())
     }
 
+    /// Creates a new hash from a slice of bytes and from the
+    /// [`Engine::fingerprint`] of the engine compiling them, so that
+    /// artifacts produced with a different target, features, compiler
+    /// configuration or version of Wasmer are never loaded.
+    ///
+    /// Returns `None` if the engine has no fingerprint, in which case the
+    /// modules it compiles mustn't be cached.
+    pub fn generate_for_engine(engine: &dyn Engine, bytes: &[u8]) -> Option<Self> {
+        Some(Self::generate_with_fingerprint(
+            &engine.fingerprint()?,
+            bytes,
+        ))
+    }
+
+    fn generate_with_fingerprint(fingerprint: &str, bytes: &[u8]) -> Self {
+        let mut hasher = blake3::Hasher::new();
+        // The length prefix keeps the fingerprint from running into the
+        // bytes.
+        hasher.update(&(fingerprint.len() as u64).to_le_bytes());
+        hasher.update(fingerprint.as_bytes());
+        hasher.update(bytes);
+        Self::new(hasher.finalize().into())
+    }
+
     pub(crate) fn to_array(&self) -> [u8; 32] {
         self.0
     }
@@ -71,4 +100,17 @@ mod tests {
         let hash = Hash::new(original);
         assert_eq!(hash.to_array(), original);
     }
+
+    #[test]
+    fn hash_depends_on_the_fingerprint() {
+        let bytes = b"\0asm\x01\0\0\0";
+        let hash = Hash::generate_with_fingerprint("engine a", bytes);
+        assert_eq!(hash, Hash::generate_with_fingerprint("engine a", bytes));
+        assert_ne!(hash, Hash::generate_with_fingerprint("engine b", bytes));
+        assert_ne!(hash, Hash::generate(bytes));
+        assert_ne!(
+            Hash::generate_with_fingerprint("ab", b"c"),
+            Hash::generate_with_fingerprint("a", b"bc")
+        );
+    }
 }
@@ -0,0 +1,137 @@
+use crate::cache::Cache;
+use crate::filesystem::write_atomically;
+use crate::hash::Hash;
+use std::error::Error;
+use std::fs::{self, create_dir_all};
+use std::io;
+use std::path::PathBuf;
+use wasmer::{DeserializeError, Module, SerializeError, Store};
+
+/// A key-value store, such as an embedded database or a cache shared by
+/// several hosts, in which a [`KeyValueCache`] keeps serialized modules.
+pub trait KeyValueStore {
+    /// The error of the operations of the store.
+    type Error: Error + Send + Sync + 'static;
+
+    /// Returns the value of `key`, or `None` if there is no such key.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Self::Error>;
+
+    /// Sets the value of `key`.
+    fn put(&self, key: &str, value: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// A cache storing serialized modules in a [`KeyValueStore`].
+///
+/// The `KeyValueCache` type implements the [`Cache`] trait. The key of a
+/// module in the store is the hexadecimal representation of its
+/// [`Hash`], and its value is the checksum of its artifact followed by
+/// the artifact. A value not matching its checksum is a miss.
+///
+/// # Usage
+///
+/// ```
+/// use wasmer::{Module, SerializeError};
+/// use wasmer_cache::{Cache, DirectoryStore, Hash, KeyValueCache};
+///
+/// fn store_module(module: &Module, bytes: &[u8]) -> Result<(), SerializeError> {
+///     // Create a new cache, backed by a directory shared with other hosts.
+///     let store = DirectoryStore::new("some/shared/directory/goes/here")?;
+///     let mut cache = KeyValueCache::new(store);
+///
+///     // Store a module into the cache given a key, unless its engine
+///     // can't be cached
+///     if let Some(key) = Hash::generate_for_engine(&**module.store().engine(), bytes) {
+///         cache.store(key, module)?;
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub struct KeyValueCache<S> {
+    store: S,
+}
+
+impl<S> KeyValueCache<S> {
+    /// Construct a new `KeyValueCache` around the specified store.
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    /// Returns the store of the cache.
+    pub fn key_value_store(&self) -> &S {
+        &self.store
+    }
+}
+
+/// The size of the checksum preceding the artifacts.
+const CHECKSUM_SIZE: usize = 32;
+
+impl<S: KeyValueStore> Cache for KeyValueCache<S> {
+    type DeserializeError = DeserializeError;
+    type SerializeError = SerializeError;
+
+    unsafe fn load(&self, store: &Store, key: Hash) -> Result<Module, Self::DeserializeError> {
+        let value = self
+            .store
+            .get(&key.to_string())
+            .map_err(|e| DeserializeError::Generic(e.to_string()))?
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "the module isn't in the cache")
+            })?;
+        if value.len() < CHECKSUM_SIZE
+            || value[..CHECKSUM_SIZE] != Hash::generate(&value[CHECKSUM_SIZE..]).to_array()
+        {
+            return Err(DeserializeError::CorruptedBinary(format!(
+                "the value of `{}` doesn't match its checksum",
+                key.to_string()
+            )));
+        }
+        Module::deserialize(store, &value[CHECKSUM_SIZE..])
+    }
+
+    fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
+        let artifact = module.serialize()?;
+        let mut value = Vec::with_capacity(CHECKSUM_SIZE + artifact.len());
+        value.extend_from_slice(&Hash::generate(&artifact).to_array());
+        value.extend_from_slice(&artifact);
+        self.store
+            .put(&key.to_string(), &value)
+            .map_err(|e| SerializeError::Generic(e.to_string()))?;
+        Ok(())
+    }
+}
+
+/// A [`KeyValueStore`] keeping each value in a file of a directory, which
+/// can be shared by several processes or hosts.
+///
+/// Values are written to a temporary file first, and then renamed, so
+/// that readers never get a partially written value.
+pub struct DirectoryStore {
+    path: PathBuf,
+}
+
+impl DirectoryStore {
+    /// Construct a new `DirectoryStore` around the specified directory,
+    /// creating it if it doesn't exist yet.
+    pub fn new<P: Into<PathBuf>>(path: P) -> io::Result<Self> {
+        let path = path.into();
+        create_dir_all(&path)?;
+        Ok(Self { path })
+    }
+}
+
+impl KeyValueStore for DirectoryStore {
+    type Error = io::Error;
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Self::Error> {
+        match fs::read(self.path.join(key)) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn put(&self, key: &str, value: &[u8]) -> Result<(), Self::Error> {
+        write_atomically(&self.path.join(key), value)
+    }
+}
@@ -22,10 +22,16 @@
 mod cache;
 mod filesystem;
 mod hash;
+mod key_value;
+mod memory;
+mod tiered;
 
 pub use crate::cache::Cache;
 pub use crate::filesystem::FileSystemCache;
 pub use crate::hash::Hash;
+pub use crate::key_value::{DirectoryStore, KeyValueCache, KeyValueStore};
+pub use crate::memory::MemoryCache;
+pub use crate::tiered::TieredCache;
 
 // We re-export those for convinience of users
 pub use wasmer::{DeserializeError, SerializeError};
@@ -0,0 +1,169 @@
+use crate::cache::Cache;
+use crate::hash::Hash;
+use std::collections::HashMap;
+use std::io;
+use std::sync::Mutex;
+use wasmer::{DeserializeError, Module, SerializeError, Store};
+
+/// A cache keeping compiled modules in memory, ready to be instantiated.
+///
+/// The `MemoryCache` type implements the [`Cache`] trait. It only hands
+/// out a module to the engine it was compiled with: loading it into a
+/// [`Store`] with another engine is a miss. It can be bounded with
+/// [`MemoryCache::set_max_entries`] and [`MemoryCache::set_max_size`],
+/// and then removes the least recently used modules first.
+///
+/// # Usage
+///
+/// ```
+/// use wasmer::{Module, SerializeError};
+/// use wasmer_cache::{Cache, Hash, MemoryCache};
+///
+/// fn store_module(module: &Module, bytes: &[u8]) -> Result<MemoryCache, SerializeError> {
+///     // Create a new memory cache, holding up to 16 modules.
+///     let mut memory_cache = MemoryCache::new();
+///     memory_cache.set_max_entries(Some(16));
+///
+///     // Store a module into the cache given a key, unless its engine
+///     // can't be cached
+///     if let Some(key) = Hash::generate_for_engine(&**module.store().engine(), bytes) {
+///         memory_cache.store(key, module)?;
+///     }
+///
+///     Ok(memory_cache)
+/// }
+/// ```
+pub struct MemoryCache {
+    entries: Mutex<Entries>,
+    max_entries: Option<usize>,
+    max_size: Option<u64>,
+}
+
+#[derive(Default)]
+struct Entries {
+    modules: HashMap<Hash, Entry>,
+    /// The sum of the sizes of the modules.
+    size: u64,
+    /// Counts the uses of the cache, to order the modules by last use.
+    clock: u64,
+}
+
+struct Entry {
+    module: Module,
+    size: u64,
+    last_use: u64,
+}
+
+impl MemoryCache {
+    /// Construct a new, unbounded, `MemoryCache`.
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(Entries::default()),
+            max_entries: None,
+            max_size: None,
+        }
+    }
+
+    /// Set the maximum number of modules in the cache.
+    pub fn set_max_entries(&mut self, max_entries: Option<usize>) {
+        self.max_entries = max_entries;
+        self.evict(&mut self.entries.lock().unwrap());
+    }
+
+    /// Set the maximum size, in bytes, of the modules in the cache.
+    ///
+    /// The size of a module is the size of its serialized artifact, which
+    /// is only computed when the cache has a maximum size. Modules larger
+    /// than the maximum size aren't cached.
+    pub fn set_max_size(&mut self, max_size: Option<u64>) {
+        self.max_size = max_size;
+        self.evict(&mut self.entries.lock().unwrap());
+    }
+
+    /// Returns the number of modules in the cache.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().modules.len()
+    }
+
+    /// Returns whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Store a [`Module`] into the cache with the given [`Hash`], from a
+    /// shared reference.
+    pub fn insert(&self, key: Hash, module: &Module) -> Result<(), SerializeError> {
+        let size = match self.max_size {
+            Some(max_size) => {
+                let size = module.serialize()?.len() as u64;
+                if size > max_size {
+                    return Ok(());
+                }
+                size
+            }
+            None => 0,
+        };
+
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let entry = Entry {
+            module: module.clone(),
+            size,
+            last_use: entries.clock,
+        };
+        if let Some(previous) = entries.modules.insert(key, entry) {
+            entries.size -= previous.size;
+        }
+        entries.size += size;
+        self.evict(&mut entries);
+        Ok(())
+    }
+
+    /// Remove the least recently used modules until the cache fits in
+    /// its bounds.
+    fn evict(&self, entries: &mut Entries) {
+        let max_entries = self.max_entries.unwrap_or(usize::MAX);
+        let max_size = self.max_size.unwrap_or(u64::MAX);
+        while entries.modules.len() > max_entries || entries.size > max_size {
+            let key = *entries
+                .modules
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_use)
+                .unwrap()
+                .0;
+            let entry = entries.modules.remove(&key).unwrap();
+            entries.size -= entry.size;
+        }
+    }
+}
+
+impl Default for MemoryCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Cache for MemoryCache {
+    type DeserializeError = DeserializeError;
+    type SerializeError = SerializeError;
+
+    unsafe fn load(&self, store: &Store, key: Hash) -> Result<Module, Self::DeserializeError> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let clock = entries.clock;
+        match entries.modules.get_mut(&key) {
+            Some(entry) if Store::same(entry.module.store(), store) => {
+                entry.last_use = clock;
+                Ok(entry.module.clone())
+            }
+            _ => Err(DeserializeError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                "the module isn't in the cache",
+            ))),
+        }
+    }
+
+    fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
+        self.insert(key, module)
+    }
+}
@@ -0,0 +1,76 @@
+use crate::cache::Cache;
+use crate::hash::Hash;
+use std::sync::RwLock;
+use wasmer::{Module, Store};
+
+/// A cache made of two caches: a fast `upper` cache, such as a
+/// [`MemoryCache`], in front of a slower `lower` cache, such as a
+/// [`FileSystemCache`].
+///
+/// The `TieredCache` type implements the [`Cache`] trait. Modules are
+/// loaded from `upper` first, and then from `lower`, in which case they
+/// are also stored in `upper`. Modules are stored in both caches. Tiered
+/// caches can be nested to get more tiers.
+///
+/// Only the errors of `lower` are reported: `upper` is just a shortcut.
+///
+/// # Usage
+///
+/// ```
+/// use wasmer_cache::{FileSystemCache, MemoryCache, TieredCache};
+///
+/// # fn make_cache() -> std::io::Result<()> {
+/// let mut memory = MemoryCache::new();
+/// memory.set_max_entries(Some(64));
+/// let cache = TieredCache::new(memory, FileSystemCache::new("some/directory/goes/here")?);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`MemoryCache`]: crate::MemoryCache
+/// [`FileSystemCache`]: crate::FileSystemCache
+pub struct TieredCache<U, L> {
+    upper: RwLock<U>,
+    lower: L,
+}
+
+impl<U, L> TieredCache<U, L> {
+    /// Construct a new `TieredCache` loading modules from `upper` first,
+    /// and then from `lower`.
+    pub fn new(upper: U, lower: L) -> Self {
+        Self {
+            upper: RwLock::new(upper),
+            lower,
+        }
+    }
+
+    /// Returns the lower cache.
+    pub fn lower(&self) -> &L {
+        &self.lower
+    }
+
+    /// Returns the upper and lower caches.
+    pub fn into_inner(self) -> (U, L) {
+        (self.upper.into_inner().unwrap(), self.lower)
+    }
+}
+
+impl<U: Cache, L: Cache> Cache for TieredCache<U, L> {
+    type DeserializeError = L::DeserializeError;
+    type SerializeError = L::SerializeError;
+
+    unsafe fn load(&self, store: &Store, key: Hash) -> Result<Module, Self::DeserializeError> {
+        if let Ok(module) = self.upper.read().unwrap().load(store, key) {
+            return Ok(module);
+        }
+        let module = self.lower.load(store, key)?;
+        let _ = self.upper.write().unwrap().store(key, &module);
+        Ok(module)
+    }
+
+    fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
+        self.lower.store(key, module)?;
+        let _ = self.upper.get_mut().unwrap().store(key, module);
+        Ok(())
+    }
+}
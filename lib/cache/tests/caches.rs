@@ -0,0 +1,112 @@
+use wasmer::{imports, wat2wasm, Cranelift, DeserializeError, Instance, Module, Store, JIT};
+use wasmer_cache::{
+    Cache, DirectoryStore, FileSystemCache, Hash, KeyValueCache, MemoryCache, TieredCache,
+};
+
+fn get_store() -> Store {
+    Store::new(&JIT::new(Cranelift::default()).engine())
+}
+
+/// Compiles a module whose `value` function returns `value`, and returns
+/// it with its key.
+fn compile(store: &Store, value: i32) -> (Hash, Module) {
+    let wat = format!(
+        r#"(module (func (export "value") (result i32) (i32.const {})))"#,
+        value
+    );
+    let bytes = wat2wasm(wat.as_bytes()).unwrap();
+    let key = Hash::generate_for_engine(&**store.engine(), &bytes).unwrap();
+    (key, Module::new(store, bytes).unwrap())
+}
+
+fn value(module: &Module) -> i32 {
+    let instance = Instance::new(module, &imports! {}).unwrap();
+    let value = instance.exports.get_native_function::<(), i32>("value");
+    value.unwrap().call().unwrap()
+}
+
+#[test]
+fn memory_cache_evicts_least_recently_used_modules() {
+    let store = get_store();
+    let mut cache = MemoryCache::new();
+    cache.set_max_entries(Some(2));
+    let modules = (0..3).map(|i| compile(&store, i)).collect::<Vec<_>>();
+
+    cache.store(modules[0].0, &modules[0].1).unwrap();
+    cache.store(modules[1].0, &modules[1].1).unwrap();
+    assert_eq!(
+        value(&unsafe { cache.load(&store, modules[0].0) }.unwrap()),
+        0
+    );
+    cache.store(modules[2].0, &modules[2].1).unwrap();
+    assert_eq!(cache.len(), 2);
+    assert!(unsafe { cache.load(&store, modules[0].0) }.is_ok());
+    assert!(unsafe { cache.load(&store, modules[1].0) }.is_err());
+    assert!(unsafe { cache.load(&store, modules[2].0) }.is_ok());
+
+    // A module is only handed out to its engine.
+    assert!(unsafe { cache.load(&get_store(), modules[0].0) }.is_err());
+}
+
+#[test]
+fn memory_cache_bounds_the_size_of_modules() {
+    let store = get_store();
+    let (first_key, first) = compile(&store, 1);
+    let (second_key, second) = compile(&store, 2);
+    let size = first.serialize().unwrap().len() as u64;
+    let mut cache = MemoryCache::new();
+    cache.set_max_size(Some(size + size / 2));
+
+    cache.store(first_key, &first).unwrap();
+    cache.store(second_key, &second).unwrap();
+    assert_eq!(cache.len(), 1);
+    assert!(unsafe { cache.load(&store, second_key) }.is_ok());
+
+    cache.set_max_size(Some(size / 2));
+    assert!(cache.is_empty());
+    cache.store(first_key, &first).unwrap();
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn tiered_cache_populates_the_upper_cache() {
+    let store = get_store();
+    let (key, module) = compile(&store, 42);
+    let dir = tempfile::tempdir().unwrap();
+    FileSystemCache::new(dir.path())
+        .unwrap()
+        .store(key, &module)
+        .unwrap();
+
+    let cache = TieredCache::new(
+        MemoryCache::new(),
+        FileSystemCache::new(dir.path()).unwrap(),
+    );
+    assert_eq!(value(&unsafe { cache.load(&store, key) }.unwrap()), 42);
+    let (memory, _) = cache.into_inner();
+    assert_eq!(value(&unsafe { memory.load(&store, key) }.unwrap()), 42);
+}
+
+#[test]
+fn key_value_cache_checks_values() {
+    let store = get_store();
+    let (key, module) = compile(&store, 7);
+    let dir = tempfile::tempdir().unwrap();
+    let mut cache = KeyValueCache::new(DirectoryStore::new(dir.path()).unwrap());
+    assert!(matches!(
+        unsafe { cache.load(&store, key) },
+        Err(DeserializeError::Io(_))
+    ));
+
+    cache.store(key, &module).unwrap();
+    assert_eq!(value(&unsafe { cache.load(&store, key) }.unwrap()), 7);
+
+    let path = dir.path().join(key.to_string());
+    let mut value = std::fs::read(&path).unwrap();
+    *value.last_mut().unwrap() ^= 1;
+    std::fs::write(&path, value).unwrap();
+    assert!(matches!(
+        unsafe { cache.load(&store, key) },
+        Err(DeserializeError::CorruptedBinary(_))
+    ));
+}
@@ -0,0 +1,945 @@
+//! The canonical ABI: how the values of the interface types are passed to
+//! the guest and back, as core WebAssembly values and in its memory.
+//!
+//! The generated bindings implement [`ComponentType`], [`Lower`] and
+//! [`Lift`] for the records, variants and enums of their interface, on
+//! top of the implementations for the Rust types the other interface
+//! types map to:
+//!
+//! | Interface type                    | Rust type                        |
+//! |-----------------------------------|----------------------------------|
+//! | `bool`                            | `bool`                           |
+//! | `u8`, ..., `s64`                  | `u8`, ..., `i64`                 |
+//! | `float32`, `float64`              | `f32`, `f64`                     |
+//! | `char`                            | `char`                           |
+//! | `string`                          | `String`, or `&str` as parameter |
+//! | `list<T>`                         | `Vec<T>`, or `&[T]` as parameter |
+//! | `option<T>`                       | `Option<T>`                      |
+//! | `result<T, E>`, `result<_, E>`... | `Result<T, E>`, `Result<(), E>`  |
+//! | `tuple<A, B>`                     | `(A, B)`                         |
+
+use crate::Error;
+use std::slice;
+use wasmer::{ExportError, Instance, Memory, NativeFunc};
+
+/// The maximum number of core WebAssembly parameters of a function,
+/// beyond which its parameters are passed in memory.
+pub const MAX_FLAT_PARAMS: usize = 16;
+
+/// The maximum number of core WebAssembly results of a function, beyond
+/// which its result is returned in memory.
+pub const MAX_FLAT_RESULTS: usize = 1;
+
+/// A core WebAssembly type, which the interface types flatten to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlatType {
+    /// `i32`.
+    I32,
+    /// `i64`.
+    I64,
+    /// `f32`.
+    F32,
+    /// `f64`.
+    F64,
+}
+
+/// A core WebAssembly value, which the interface values flatten to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FlatValue {
+    /// An `i32`.
+    I32(i32),
+    /// An `i64`.
+    I64(i64),
+    /// An `f32`.
+    F32(f32),
+    /// An `f64`.
+    F64(f64),
+}
+
+/// The flat values left to lift.
+pub type Flat<'a> = slice::Iter<'a, FlatValue>;
+
+macro_rules! flat_value_conversions {
+    ($($variant:ident($ty:ty), $as:ident;)*) => {
+        $(
+            impl From<$ty> for FlatValue {
+                fn from(value: $ty) -> Self {
+                    Self::$variant(value)
+                }
+            }
+        )*
+
+        impl FlatValue {
+            $(
+                #[doc = concat!("The `", stringify!($ty), "` value.")]
+                ///
+                /// # Panics
+                ///
+                /// Panics if the value has another type, which the bindings
+                /// never give their guest.
+                pub fn $as(self) -> $ty {
+                    match self {
+                        Self::$variant(value) => value,
+                        _ => panic!("expected {}, found {:?}", stringify!($ty), self),
+                    }
+                }
+            )*
+        }
+    };
+}
+
+flat_value_conversions! {
+    I32(i32), as_i32;
+    I64(i64), as_i64;
+    F32(f32), as_f32;
+    F64(f64), as_f64;
+}
+
+impl FlatValue {
+    fn zero(ty: FlatType) -> Self {
+        match ty {
+            FlatType::I32 => Self::I32(0),
+            FlatType::I64 => Self::I64(0),
+            FlatType::F32 => Self::F32(0.0),
+            FlatType::F64 => Self::F64(0.0),
+        }
+    }
+
+    /// Convert the value of a case to the joined type `ty` of the variant.
+    fn coerce(self, ty: FlatType) -> Self {
+        match (self, ty) {
+            (Self::F32(value), FlatType::I32) => Self::I32(value.to_bits() as i32),
+            (Self::I32(value), FlatType::I64) => Self::I64(value as u32 as i64),
+            (Self::F32(value), FlatType::I64) => Self::I64(value.to_bits() as i64),
+            (Self::F64(value), FlatType::I64) => Self::I64(value.to_bits() as i64),
+            (value, _) => value,
+        }
+    }
+
+    /// Convert a value of the joined type of a variant back to the type
+    /// `ty` of the case, undoing [`FlatValue::coerce`].
+    fn uncoerce(self, ty: FlatType) -> Self {
+        match (self, ty) {
+            (Self::I32(value), FlatType::F32) => Self::F32(f32::from_bits(value as u32)),
+            (Self::I64(value), FlatType::I32) => Self::I32(value as i32),
+            (Self::I64(value), FlatType::F32) => Self::F32(f32::from_bits(value as u32)),
+            (Self::I64(value), FlatType::F64) => Self::F64(f64::from_bits(value as u64)),
+            (value, _) => value,
+        }
+    }
+}
+
+/// The type, in a variant, of the flat values at a position where its
+/// cases have values of types `a` and `b`.
+fn join(a: FlatType, b: FlatType) -> FlatType {
+    match (a, b) {
+        (a, b) if a == b => a,
+        (FlatType::I32, FlatType::F32) | (FlatType::F32, FlatType::I32) => FlatType::I32,
+        _ => FlatType::I64,
+    }
+}
+
+/// The types of the flat values of the payloads of a variant whose cases
+/// flatten to `cases`.
+pub fn join_flat_types(cases: &[Vec<FlatType>]) -> Vec<FlatType> {
+    let mut joined: Vec<FlatType> = Vec::new();
+    for case in cases {
+        for (i, &ty) in case.iter().enumerate() {
+            match joined.get_mut(i) {
+                Some(joined) => *joined = join(*joined, ty),
+                None => joined.push(ty),
+            }
+        }
+    }
+    joined
+}
+
+/// `offset` rounded up to a multiple of `align`, a power of 2.
+pub const fn align_to(offset: usize, align: usize) -> usize {
+    (offset + align - 1) & !(align - 1)
+}
+
+const fn max(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+/// The size, in bytes, of the discriminant of a variant with `cases`
+/// cases.
+pub const fn discriminant_size(cases: usize) -> usize {
+    if cases <= 1 << 8 {
+        1
+    } else if cases <= 1 << 16 {
+        2
+    } else {
+        4
+    }
+}
+
+/// The memory of a guest, and the exports it manages its memory with.
+///
+/// The guest allocates the memory of the values passed to it with
+/// `canonical_abi_realloc(old_ptr, old_size, align, new_size) -> ptr`,
+/// and takes their ownership. The strings and lists it returns are
+/// released once lifted with `canonical_abi_free(ptr, size, align)`;
+/// both exports are optional, as long as they aren't needed.
+#[derive(Clone)]
+pub struct Abi {
+    memory: Memory,
+    realloc: Option<NativeFunc<(i32, i32, i32, i32), i32>>,
+    free: Option<NativeFunc<(i32, i32, i32), ()>>,
+}
+
+/// Look up the export `name`, which may be missing.
+fn optional<T>(export: Result<T, ExportError>) -> Result<Option<T>, Error> {
+    match export {
+        Ok(export) => Ok(Some(export)),
+        Err(ExportError::Missing(_)) => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
+
+impl Abi {
+    /// Look up the exports of `instance` the canonical ABI needs.
+    pub fn new(instance: &Instance) -> Result<Self, Error> {
+        Ok(Self {
+            memory: instance.exports.get_memory("memory")?.clone(),
+            realloc: optional(
+                instance
+                    .exports
+                    .get_native_function("canonical_abi_realloc"),
+            )?,
+            free: optional(instance.exports.get_native_function("canonical_abi_free"))?,
+        })
+    }
+
+    /// The memory of the guest.
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    /// Check the `len` bytes at `pointer` are in the memory, and that
+    /// `pointer` is aligned to `align`.
+    pub fn check_pointer(&self, pointer: u32, len: usize, align: usize) -> Result<(), Error> {
+        if pointer as usize & (align - 1) != 0 {
+            return Err(Error::UnalignedPointer { pointer, align });
+        }
+        if pointer as u64 + len as u64 > self.memory.data_size() {
+            return Err(Error::OutOfBounds { pointer, len });
+        }
+        Ok(())
+    }
+
+    /// Allocate `size` bytes aligned to `align` in the guest.
+    pub fn alloc(&self, size: usize, align: usize) -> Result<u32, Error> {
+        // Nothing to allocate, any aligned pointer will do.
+        if size == 0 {
+            return Ok(align as u32);
+        }
+        let realloc = self.realloc.as_ref().ok_or(Error::MissingRealloc)?;
+        let pointer = realloc.call(0, 0, align as i32, size as i32)? as u32;
+        self.check_pointer(pointer, size, align)?;
+        Ok(pointer)
+    }
+
+    /// Release the `size` bytes aligned to `align` at `pointer`, which
+    /// the guest gave away.
+    pub fn free(&self, pointer: u32, size: usize, align: usize) -> Result<(), Error> {
+        match &self.free {
+            Some(free) if size != 0 => Ok(free.call(pointer as i32, size as i32, align as i32)?),
+            _ => Ok(()),
+        }
+    }
+
+    /// Copy the bytes at `pointer` to `bytes`.
+    pub fn read(&self, pointer: u32, bytes: &mut [u8]) -> Result<(), Error> {
+        self.check_pointer(pointer, bytes.len(), 1)?;
+        let start = pointer as usize;
+        // Safety: the bytes are in bounds, and the guest isn't running.
+        let data = unsafe { self.memory.data_unchecked() };
+        bytes.copy_from_slice(&data[start..start + bytes.len()]);
+        Ok(())
+    }
+
+    /// Copy `bytes` to `pointer`.
+    pub fn write(&self, pointer: u32, bytes: &[u8]) -> Result<(), Error> {
+        self.check_pointer(pointer, bytes.len(), 1)?;
+        let start = pointer as usize;
+        // Safety: the bytes are in bounds, and the guest isn't running.
+        let data = unsafe { self.memory.data_unchecked_mut() };
+        data[start..start + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// A type of the interface.
+pub trait ComponentType {
+    /// The size, in bytes, of the values in memory.
+    const SIZE: usize;
+
+    /// The alignment, in bytes, of the values in memory.
+    const ALIGN: usize;
+
+    /// Push the core WebAssembly types the values flatten to onto `out`.
+    fn push_flat_types(out: &mut Vec<FlatType>);
+}
+
+/// The core WebAssembly types the values of `T` flatten to.
+pub fn flat_types<T: ComponentType + ?Sized>() -> Vec<FlatType> {
+    let mut out = Vec::new();
+    T::push_flat_types(&mut out);
+    out
+}
+
+/// A value that can be passed to the guest.
+pub trait Lower: ComponentType {
+    /// Push the flat values of `self` onto `out`.
+    fn lower(&self, abi: &Abi, out: &mut Vec<FlatValue>) -> Result<(), Error>;
+
+    /// Store `self` in the memory of the guest, at `offset`.
+    fn store(&self, abi: &Abi, offset: u32) -> Result<(), Error>;
+}
+
+/// A value that can be returned by the guest.
+pub trait Lift: ComponentType + Sized {
+    /// Read a value from its flat values.
+    fn lift(abi: &Abi, flat: &mut Flat) -> Result<Self, Error>;
+
+    /// Load a value from the memory of the guest, at `offset`.
+    fn load(abi: &Abi, offset: u32) -> Result<Self, Error>;
+}
+
+fn next(flat: &mut Flat) -> FlatValue {
+    *flat
+        .next()
+        .expect("the bindings always give enough flat values")
+}
+
+/// Push the flat values of the case `discriminant` of the variant `V`
+/// onto `out`, given the flat values of its payload.
+pub fn lower_case<V: ComponentType + ?Sized>(
+    out: &mut Vec<FlatValue>,
+    discriminant: u32,
+    payload: Vec<FlatValue>,
+) {
+    out.push(FlatValue::I32(discriminant as i32));
+    let mut payload = payload.into_iter();
+    // The first flat type is the discriminant's.
+    for &ty in &flat_types::<V>()[1..] {
+        out.push(match payload.next() {
+            Some(value) => value.coerce(ty),
+            None => FlatValue::zero(ty),
+        });
+    }
+}
+
+/// Read the discriminant of a variant from its flat values.
+pub fn lift_discriminant(flat: &mut Flat) -> u32 {
+    next(flat).as_i32() as u32
+}
+
+/// Read the payload `T` of a case of the variant `V`, from the flat
+/// values following the discriminant.
+pub fn lift_case<V: ComponentType, T: Lift>(abi: &Abi, flat: &mut Flat) -> Result<T, Error> {
+    let joined = flat_types::<V>().len() - 1;
+    let values = flat
+        .by_ref()
+        .take(joined)
+        .copied()
+        .zip(flat_types::<T>())
+        .map(|(value, ty)| value.uncoerce(ty))
+        .collect::<Vec<_>>();
+    T::lift(abi, &mut values.iter())
+}
+
+/// Skip the flat values following the discriminant of the variant `V`,
+/// for a case without payload.
+pub fn skip_case<V: ComponentType>(flat: &mut Flat) {
+    let joined = flat_types::<V>().len() - 1;
+    flat.by_ref().take(joined).for_each(drop);
+}
+
+/// Store the discriminant of a variant, which takes `size` bytes, at
+/// `offset`.
+pub fn store_discriminant(
+    abi: &Abi,
+    offset: u32,
+    size: usize,
+    discriminant: u32,
+) -> Result<(), Error> {
+    abi.write(offset, &discriminant.to_le_bytes()[..size])
+}
+
+/// Load the discriminant of a variant, which takes `size` bytes, from
+/// `offset`.
+pub fn load_discriminant(abi: &Abi, offset: u32, size: usize) -> Result<u32, Error> {
+    let mut bytes = [0; 4];
+    abi.read(offset, &mut bytes[..size])?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// Lift the result of a function returned as a single flat value.
+pub fn lift_result<T: Lift>(abi: &Abi, value: impl Into<FlatValue>) -> Result<T, Error> {
+    T::lift(abi, &mut [value.into()].iter())
+}
+
+/// Load the result of a function returned in memory, at `pointer`.
+pub fn load_result<T: Lift>(abi: &Abi, pointer: i32) -> Result<T, Error> {
+    let pointer = pointer as u32;
+    abi.check_pointer(pointer, T::SIZE, T::ALIGN)?;
+    T::load(abi, pointer)
+}
+
+macro_rules! primitives {
+    ($($ty:ty: $flat:ident as $wasm:ty;)*) => {
+        $(
+            impl ComponentType for $ty {
+                const SIZE: usize = std::mem::size_of::<$ty>();
+                const ALIGN: usize = std::mem::size_of::<$ty>();
+
+                fn push_flat_types(out: &mut Vec<FlatType>) {
+                    out.push(FlatType::$flat);
+                }
+            }
+
+            impl Lower for $ty {
+                fn lower(&self, _abi: &Abi, out: &mut Vec<FlatValue>) -> Result<(), Error> {
+                    out.push(FlatValue::$flat(*self as $wasm));
+                    Ok(())
+                }
+
+                fn store(&self, abi: &Abi, offset: u32) -> Result<(), Error> {
+                    abi.write(offset, &self.to_le_bytes())
+                }
+            }
+
+            impl Lift for $ty {
+                fn lift(_abi: &Abi, flat: &mut Flat) -> Result<Self, Error> {
+                    match next(flat) {
+                        FlatValue::$flat(value) => Ok(value as $ty),
+                        value => panic!("expected {:?}, found {:?}", FlatType::$flat, value),
+                    }
+                }
+
+                fn load(abi: &Abi, offset: u32) -> Result<Self, Error> {
+                    let mut bytes = [0; std::mem::size_of::<$ty>()];
+                    abi.read(offset, &mut bytes)?;
+                    Ok(<$ty>::from_le_bytes(bytes))
+                }
+            }
+        )*
+    };
+}
+
+primitives! {
+    u8: I32 as i32;
+    u16: I32 as i32;
+    u32: I32 as i32;
+    u64: I64 as i64;
+    i8: I32 as i32;
+    i16: I32 as i32;
+    i32: I32 as i32;
+    i64: I64 as i64;
+    f32: F32 as f32;
+    f64: F64 as f64;
+}
+
+impl ComponentType for bool {
+    const SIZE: usize = 1;
+    const ALIGN: usize = 1;
+
+    fn push_flat_types(out: &mut Vec<FlatType>) {
+        out.push(FlatType::I32);
+    }
+}
+
+impl Lower for bool {
+    fn lower(&self, abi: &Abi, out: &mut Vec<FlatValue>) -> Result<(), Error> {
+        (*self as u8).lower(abi, out)
+    }
+
+    fn store(&self, abi: &Abi, offset: u32) -> Result<(), Error> {
+        (*self as u8).store(abi, offset)
+    }
+}
+
+impl Lift for bool {
+    fn lift(abi: &Abi, flat: &mut Flat) -> Result<Self, Error> {
+        Ok(i32::lift(abi, flat)? != 0)
+    }
+
+    fn load(abi: &Abi, offset: u32) -> Result<Self, Error> {
+        Ok(u8::load(abi, offset)? != 0)
+    }
+}
+
+impl ComponentType for char {
+    const SIZE: usize = 4;
+    const ALIGN: usize = 4;
+
+    fn push_flat_types(out: &mut Vec<FlatType>) {
+        out.push(FlatType::I32);
+    }
+}
+
+impl Lower for char {
+    fn lower(&self, abi: &Abi, out: &mut Vec<FlatValue>) -> Result<(), Error> {
+        (*self as u32).lower(abi, out)
+    }
+
+    fn store(&self, abi: &Abi, offset: u32) -> Result<(), Error> {
+        (*self as u32).store(abi, offset)
+    }
+}
+
+fn to_char(value: u32) -> Result<char, Error> {
+    std::char::from_u32(value).ok_or(Error::InvalidChar(value))
+}
+
+impl Lift for char {
+    fn lift(abi: &Abi, flat: &mut Flat) -> Result<Self, Error> {
+        to_char(u32::lift(abi, flat)?)
+    }
+
+    fn load(abi: &Abi, offset: u32) -> Result<Self, Error> {
+        to_char(u32::load(abi, offset)?)
+    }
+}
+
+impl ComponentType for () {
+    const SIZE: usize = 0;
+    const ALIGN: usize = 1;
+
+    fn push_flat_types(_out: &mut Vec<FlatType>) {}
+}
+
+impl Lower for () {
+    fn lower(&self, _abi: &Abi, _out: &mut Vec<FlatValue>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn store(&self, _abi: &Abi, _offset: u32) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl Lift for () {
+    fn lift(_abi: &Abi, _flat: &mut Flat) -> Result<Self, Error> {
+        Ok(())
+    }
+
+    fn load(_abi: &Abi, _offset: u32) -> Result<Self, Error> {
+        Ok(())
+    }
+}
+
+/// Copy `bytes` to memory allocated in the guest, returning the pointer.
+fn lower_bytes(abi: &Abi, bytes: &[u8], align: usize) -> Result<u32, Error> {
+    let pointer = abi.alloc(bytes.len(), align)?;
+    abi.write(pointer, bytes)?;
+    Ok(pointer)
+}
+
+/// Copy the `len` bytes at `pointer`, and release them.
+fn lift_bytes(abi: &Abi, pointer: u32, len: usize, align: usize) -> Result<Vec<u8>, Error> {
+    abi.check_pointer(pointer, len, align)?;
+    let mut bytes = vec![0; len];
+    abi.read(pointer, &mut bytes)?;
+    abi.free(pointer, len, align)?;
+    Ok(bytes)
+}
+
+/// Lower the pointer and the length of a string or a list.
+fn lower_pointer_len(out: &mut Vec<FlatValue>, pointer: u32, len: usize) {
+    out.push(FlatValue::I32(pointer as i32));
+    out.push(FlatValue::I32(len as i32));
+}
+
+/// Store the pointer and the length of a string or a list at `offset`.
+fn store_pointer_len(abi: &Abi, offset: u32, pointer: u32, len: usize) -> Result<(), Error> {
+    pointer.store(abi, offset)?;
+    (len as u32).store(abi, offset + 4)
+}
+
+/// Load the pointer and the length of a string or a list from `offset`.
+fn load_pointer_len(abi: &Abi, offset: u32) -> Result<(u32, usize), Error> {
+    Ok((
+        u32::load(abi, offset)?,
+        u32::load(abi, offset + 4)? as usize,
+    ))
+}
+
+macro_rules! pointer_len_component_type {
+    ($($ty:ty $(, $param:ident)?;)*) => {
+        $(
+            impl$(<$param>)? ComponentType for $ty {
+                const SIZE: usize = 8;
+                const ALIGN: usize = 4;
+
+                fn push_flat_types(out: &mut Vec<FlatType>) {
+                    out.push(FlatType::I32);
+                    out.push(FlatType::I32);
+                }
+            }
+        )*
+    };
+}
+
+pointer_len_component_type! {
+    str;
+    String;
+    [T], T;
+    Vec<T>, T;
+}
+
+impl Lower for str {
+    fn lower(&self, abi: &Abi, out: &mut Vec<FlatValue>) -> Result<(), Error> {
+        let pointer = lower_bytes(abi, self.as_bytes(), 1)?;
+        lower_pointer_len(out, pointer, self.len());
+        Ok(())
+    }
+
+    fn store(&self, abi: &Abi, offset: u32) -> Result<(), Error> {
+        let pointer = lower_bytes(abi, self.as_bytes(), 1)?;
+        store_pointer_len(abi, offset, pointer, self.len())
+    }
+}
+
+impl Lower for String {
+    fn lower(&self, abi: &Abi, out: &mut Vec<FlatValue>) -> Result<(), Error> {
+        self.as_str().lower(abi, out)
+    }
+
+    fn store(&self, abi: &Abi, offset: u32) -> Result<(), Error> {
+        self.as_str().store(abi, offset)
+    }
+}
+
+impl Lift for String {
+    fn lift(abi: &Abi, flat: &mut Flat) -> Result<Self, Error> {
+        let pointer = u32::lift(abi, flat)?;
+        let len = u32::lift(abi, flat)? as usize;
+        Ok(String::from_utf8(lift_bytes(abi, pointer, len, 1)?)?)
+    }
+
+    fn load(abi: &Abi, offset: u32) -> Result<Self, Error> {
+        let (pointer, len) = load_pointer_len(abi, offset)?;
+        Ok(String::from_utf8(lift_bytes(abi, pointer, len, 1)?)?)
+    }
+}
+
+/// Store the elements of a list in memory allocated in the guest,
+/// returning the pointer.
+fn lower_list<T: Lower>(abi: &Abi, list: &[T]) -> Result<u32, Error> {
+    let pointer = abi.alloc(list.len() * T::SIZE, T::ALIGN)?;
+    for (i, element) in list.iter().enumerate() {
+        element.store(abi, pointer + (i * T::SIZE) as u32)?;
+    }
+    Ok(pointer)
+}
+
+/// Load the `len` elements of a list at `pointer`, and release them.
+fn lift_list<T: Lift>(abi: &Abi, pointer: u32, len: usize) -> Result<Vec<T>, Error> {
+    let size = len
+        .checked_mul(T::SIZE)
+        .ok_or(Error::OutOfBounds { pointer, len })?;
+    abi.check_pointer(pointer, size, T::ALIGN)?;
+    let list = (0..len)
+        .map(|i| T::load(abi, pointer + (i * T::SIZE) as u32))
+        .collect::<Result<_, _>>()?;
+    abi.free(pointer, size, T::ALIGN)?;
+    Ok(list)
+}
+
+impl<T: Lower> Lower for [T] {
+    fn lower(&self, abi: &Abi, out: &mut Vec<FlatValue>) -> Result<(), Error> {
+        let pointer = lower_list(abi, self)?;
+        lower_pointer_len(out, pointer, self.len());
+        Ok(())
+    }
+
+    fn store(&self, abi: &Abi, offset: u32) -> Result<(), Error> {
+        let pointer = lower_list(abi, self)?;
+        store_pointer_len(abi, offset, pointer, self.len())
+    }
+}
+
+impl<T: Lower> Lower for Vec<T> {
+    fn lower(&self, abi: &Abi, out: &mut Vec<FlatValue>) -> Result<(), Error> {
+        self.as_slice().lower(abi, out)
+    }
+
+    fn store(&self, abi: &Abi, offset: u32) -> Result<(), Error> {
+        self.as_slice().store(abi, offset)
+    }
+}
+
+impl<T: Lift> Lift for Vec<T> {
+    fn lift(abi: &Abi, flat: &mut Flat) -> Result<Self, Error> {
+        let pointer = u32::lift(abi, flat)?;
+        let len = u32::lift(abi, flat)? as usize;
+        lift_list(abi, pointer, len)
+    }
+
+    fn load(abi: &Abi, offset: u32) -> Result<Self, Error> {
+        let (pointer, len) = load_pointer_len(abi, offset)?;
+        lift_list(abi, pointer, len)
+    }
+}
+
+/// The payload offset of a variant with two cases of payloads `A` and
+/// `B`.
+const fn payload_offset<A: ComponentType, B: ComponentType>() -> usize {
+    align_to(1, max(A::ALIGN, B::ALIGN))
+}
+
+impl<T: ComponentType> ComponentType for Option<T> {
+    const ALIGN: usize = max(1, T::ALIGN);
+    const SIZE: usize = align_to(payload_offset::<(), T>() + T::SIZE, Self::ALIGN);
+
+    fn push_flat_types(out: &mut Vec<FlatType>) {
+        out.push(FlatType::I32);
+        T::push_flat_types(out);
+    }
+}
+
+impl<T: Lower> Lower for Option<T> {
+    fn lower(&self, abi: &Abi, out: &mut Vec<FlatValue>) -> Result<(), Error> {
+        let mut payload = Vec::new();
+        let discriminant = match self {
+            None => 0,
+            Some(value) => {
+                value.lower(abi, &mut payload)?;
+                1
+            }
+        };
+        lower_case::<Self>(out, discriminant, payload);
+        Ok(())
+    }
+
+    fn store(&self, abi: &Abi, offset: u32) -> Result<(), Error> {
+        match self {
+            None => store_discriminant(abi, offset, 1, 0),
+            Some(value) => {
+                store_discriminant(abi, offset, 1, 1)?;
+                value.store(abi, offset + payload_offset::<(), T>() as u32)
+            }
+        }
+    }
+}
+
+impl<T: Lift> Lift for Option<T> {
+    fn lift(abi: &Abi, flat: &mut Flat) -> Result<Self, Error> {
+        match lift_discriminant(flat) {
+            0 => {
+                skip_case::<Self>(flat);
+                Ok(None)
+            }
+            1 => Ok(Some(lift_case::<Self, T>(abi, flat)?)),
+            discriminant => Err(Error::InvalidDiscriminant(discriminant)),
+        }
+    }
+
+    fn load(abi: &Abi, offset: u32) -> Result<Self, Error> {
+        match load_discriminant(abi, offset, 1)? {
+            0 => Ok(None),
+            1 => Ok(Some(T::load(
+                abi,
+                offset + payload_offset::<(), T>() as u32,
+            )?)),
+            discriminant => Err(Error::InvalidDiscriminant(discriminant)),
+        }
+    }
+}
+
+impl<T: ComponentType, E: ComponentType> ComponentType for Result<T, E> {
+    const ALIGN: usize = max(1, max(T::ALIGN, E::ALIGN));
+    const SIZE: usize = align_to(
+        payload_offset::<T, E>() + max(T::SIZE, E::SIZE),
+        Self::ALIGN,
+    );
+
+    fn push_flat_types(out: &mut Vec<FlatType>) {
+        out.push(FlatType::I32);
+        out.extend(join_flat_types(&[flat_types::<T>(), flat_types::<E>()]));
+    }
+}
+
+impl<T: Lower, E: Lower> Lower for Result<T, E> {
+    fn lower(&self, abi: &Abi, out: &mut Vec<FlatValue>) -> Result<(), Error> {
+        let mut payload = Vec::new();
+        let discriminant = match self {
+            Ok(value) => {
+                value.lower(abi, &mut payload)?;
+                0
+            }
+            Err(error) => {
+                error.lower(abi, &mut payload)?;
+                1
+            }
+        };
+        lower_case::<Self>(out, discriminant, payload);
+        Ok(())
+    }
+
+    fn store(&self, abi: &Abi, offset: u32) -> Result<(), Error> {
+        let payload = offset + payload_offset::<T, E>() as u32;
+        match self {
+            Ok(value) => {
+                store_discriminant(abi, offset, 1, 0)?;
+                value.store(abi, payload)
+            }
+            Err(error) => {
+                store_discriminant(abi, offset, 1, 1)?;
+                error.store(abi, payload)
+            }
+        }
+    }
+}
+
+impl<T: Lift, E: Lift> Lift for Result<T, E> {
+    fn lift(abi: &Abi, flat: &mut Flat) -> Result<Self, Error> {
+        match lift_discriminant(flat) {
+            0 => Ok(Ok(lift_case::<Self, T>(abi, flat)?)),
+            1 => Ok(Err(lift_case::<Self, E>(abi, flat)?)),
+            discriminant => Err(Error::InvalidDiscriminant(discriminant)),
+        }
+    }
+
+    fn load(abi: &Abi, offset: u32) -> Result<Self, Error> {
+        let payload = offset + payload_offset::<T, E>() as u32;
+        match load_discriminant(abi, offset, 1)? {
+            0 => Ok(Ok(T::load(abi, payload)?)),
+            1 => Ok(Err(E::load(abi, payload)?)),
+            discriminant => Err(Error::InvalidDiscriminant(discriminant)),
+        }
+    }
+}
+
+macro_rules! tuples {
+    ($(($($name:ident: $ty:ident),+);)*) => {
+        $(
+            impl<$($ty: ComponentType),+> ComponentType for ($($ty,)+) {
+                const ALIGN: usize = {
+                    let align = 1;
+                    $(let align = max(align, $ty::ALIGN);)+
+                    align
+                };
+                const SIZE: usize = {
+                    let size = 0;
+                    $(let size = align_to(size, $ty::ALIGN) + $ty::SIZE;)+
+                    align_to(size, Self::ALIGN)
+                };
+
+                fn push_flat_types(out: &mut Vec<FlatType>) {
+                    $($ty::push_flat_types(out);)+
+                }
+            }
+
+            impl<$($ty: Lower),+> Lower for ($($ty,)+) {
+                fn lower(&self, abi: &Abi, out: &mut Vec<FlatValue>) -> Result<(), Error> {
+                    let ($($name,)+) = self;
+                    $($name.lower(abi, out)?;)+
+                    Ok(())
+                }
+
+                fn store(&self, abi: &Abi, offset: u32) -> Result<(), Error> {
+                    let ($($name,)+) = self;
+                    let size = 0;
+                    $(
+                        let field = align_to(size, $ty::ALIGN);
+                        $name.store(abi, offset + field as u32)?;
+                        #[allow(unused_variables)]
+                        let size = field + $ty::SIZE;
+                    )+
+                    Ok(())
+                }
+            }
+
+            impl<$($ty: Lift),+> Lift for ($($ty,)+) {
+                fn lift(abi: &Abi, flat: &mut Flat) -> Result<Self, Error> {
+                    Ok(($($ty::lift(abi, flat)?,)+))
+                }
+
+                fn load(abi: &Abi, offset: u32) -> Result<Self, Error> {
+                    let size = 0;
+                    $(
+                        let field = align_to(size, $ty::ALIGN);
+                        let $name = $ty::load(abi, offset + field as u32)?;
+                        #[allow(unused_variables)]
+                        let size = field + $ty::SIZE;
+                    )+
+                    Ok(($($name,)+))
+                }
+            }
+        )*
+    };
+}
+
+tuples! {
+    (a: A);
+    (a: A, b: B);
+    (a: A, b: B, c: C);
+    (a: A, b: B, c: C, d: D);
+    (a: A, b: B, c: C, d: D, e: E);
+    (a: A, b: B, c: C, d: D, e: E, f: F);
+    (a: A, b: B, c: C, d: D, e: E, f: F, g: G);
+    (a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ty(value: FlatValue) -> FlatType {
+        match value {
+            FlatValue::I32(_) => FlatType::I32,
+            FlatValue::I64(_) => FlatType::I64,
+            FlatValue::F32(_) => FlatType::F32,
+            FlatValue::F64(_) => FlatType::F64,
+        }
+    }
+
+    #[test]
+    fn join_flat_types_of_cases() {
+        use FlatType::*;
+        assert_eq!(join_flat_types(&[]), vec![]);
+        assert_eq!(join_flat_types(&[vec![F32], vec![I32]]), vec![I32]);
+        assert_eq!(
+            join_flat_types(&[vec![F64], vec![I32, F32], vec![]]),
+            vec![I64, F32]
+        );
+        assert_eq!(join_flat_types(&[vec![F32], vec![F64]]), vec![I64]);
+    }
+
+    #[test]
+    fn coerce_to_joined_types() {
+        for (value, joined) in &[
+            (FlatValue::F32(-1.5), FlatType::I32),
+            (FlatValue::F32(2.5), FlatType::I64),
+            (FlatValue::F64(-3.25), FlatType::I64),
+            (FlatValue::I32(-1), FlatType::I64),
+        ] {
+            let coerced = value.coerce(*joined);
+            assert_eq!(ty(coerced), *joined);
+            assert_eq!(coerced.uncoerce(ty(*value)), *value);
+        }
+        assert_eq!(
+            FlatValue::I32(-1).coerce(FlatType::I64),
+            FlatValue::I64(0xffff_ffff)
+        );
+    }
+
+    #[test]
+    fn layouts() {
+        assert_eq!((<Option<u8>>::SIZE, <Option<u8>>::ALIGN), (2, 1));
+        assert_eq!((<Option<String>>::SIZE, <Option<String>>::ALIGN), (12, 4));
+        assert_eq!((<Result<(), u64>>::SIZE, <Result<(), u64>>::ALIGN), (16, 8));
+        assert_eq!(<(u8, u32, u8)>::SIZE, 12);
+        assert_eq!(<(u8, u16)>::SIZE, 4);
+        assert_eq!(<Option<()>>::SIZE, 1);
+    }
+}
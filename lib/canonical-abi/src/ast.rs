@@ -0,0 +1,267 @@
+//! The definitions of an interface.
+
+use crate::abi::{align_to, discriminant_size, join_flat_types, FlatType};
+
+/// A type of the interface.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Type {
+    /// `bool`.
+    Bool,
+    /// `u8`.
+    U8,
+    /// `u16`.
+    U16,
+    /// `u32`.
+    U32,
+    /// `u64`.
+    U64,
+    /// `s8`.
+    S8,
+    /// `s16`.
+    S16,
+    /// `s32`.
+    S32,
+    /// `s64`.
+    S64,
+    /// `float32`.
+    Float32,
+    /// `float64`.
+    Float64,
+    /// `char`, a Unicode scalar value.
+    Char,
+    /// `string`, in UTF-8.
+    String,
+    /// `list<T>`.
+    List(Box<Type>),
+    /// `option<T>`.
+    Option(Box<Type>),
+    /// `result<T, E>`, where `_` stands for no payload.
+    Result(Option<Box<Type>>, Option<Box<Type>>),
+    /// `tuple<T, ...>`.
+    Tuple(Vec<Type>),
+    /// A record, variant or enum defined by the interface.
+    Named(String),
+}
+
+/// A field of a record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Field {
+    /// The name of the field.
+    pub name: String,
+    /// The type of the field.
+    pub ty: Type,
+}
+
+/// A case of a variant.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Case {
+    /// The name of the case.
+    pub name: String,
+    /// The payload of the case, if any.
+    pub ty: Option<Type>,
+}
+
+/// The definition of a named type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TypeDefKind {
+    /// `record name { field: type, ... }`.
+    Record(Vec<Field>),
+    /// `variant name { case(type), case, ... }`.
+    Variant(Vec<Case>),
+    /// `enum name { case, ... }`, a variant whose cases have no payload.
+    Enum(Vec<String>),
+}
+
+/// A named type defined by the interface.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeDef {
+    /// The name of the type.
+    pub name: String,
+    /// The definition of the type.
+    pub kind: TypeDefKind,
+}
+
+/// A parameter of a function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Param {
+    /// The name of the parameter.
+    pub name: String,
+    /// The type of the parameter.
+    pub ty: Type,
+}
+
+/// A function exported by the guest.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Function {
+    /// The name of the function, which is also the name of its export.
+    pub name: String,
+    /// The parameters of the function.
+    pub params: Vec<Param>,
+    /// The result of the function, if any.
+    pub result: Option<Type>,
+}
+
+/// An interface: the types and the functions exported by a guest.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Interface {
+    /// The named types, in the order they are defined.
+    pub types: Vec<TypeDef>,
+    /// The functions, in the order they are defined.
+    pub functions: Vec<Function>,
+}
+
+impl Interface {
+    /// Look up the named type `name`.
+    pub fn type_def(&self, name: &str) -> Option<&TypeDef> {
+        self.types.iter().find(|def| def.name == name)
+    }
+
+    fn resolve(&self, name: &str) -> &TypeDef {
+        self.type_def(name)
+            .unwrap_or_else(|| panic!("unknown type `{}`", name))
+    }
+
+    /// The size and alignment, in bytes, of the values of `ty` in memory.
+    pub fn size_align(&self, ty: &Type) -> (usize, usize) {
+        match ty {
+            Type::Bool | Type::U8 | Type::S8 => (1, 1),
+            Type::U16 | Type::S16 => (2, 2),
+            Type::U32 | Type::S32 | Type::Float32 | Type::Char => (4, 4),
+            Type::U64 | Type::S64 | Type::Float64 => (8, 8),
+            Type::String | Type::List(_) => (8, 4),
+            Type::Option(ty) => self.variant_size_align(&[None, Some(&**ty)]),
+            Type::Result(ok, error) => self.variant_size_align(&[ok.as_deref(), error.as_deref()]),
+            Type::Tuple(types) => self.record_size_align(types.iter()),
+            Type::Named(name) => match &self.resolve(name).kind {
+                TypeDefKind::Record(fields) => {
+                    self.record_size_align(fields.iter().map(|field| &field.ty))
+                }
+                TypeDefKind::Variant(cases) => self.variant_size_align(
+                    &cases
+                        .iter()
+                        .map(|case| case.ty.as_ref())
+                        .collect::<Vec<_>>(),
+                ),
+                TypeDefKind::Enum(cases) => self.variant_size_align(&vec![None; cases.len()]),
+            },
+        }
+    }
+
+    /// The offsets, in bytes, of the fields of a record laid out in
+    /// memory.
+    pub fn field_offsets<'a>(&self, types: impl IntoIterator<Item = &'a Type>) -> Vec<usize> {
+        let mut size = 0;
+        types
+            .into_iter()
+            .map(|ty| {
+                let (field_size, field_align) = self.size_align(ty);
+                let offset = align_to(size, field_align);
+                size = offset + field_size;
+                offset
+            })
+            .collect()
+    }
+
+    fn record_size_align<'a>(&self, types: impl Iterator<Item = &'a Type>) -> (usize, usize) {
+        let (size, align) = types.fold((0, 1), |(size, align), ty| {
+            let (field_size, field_align) = self.size_align(ty);
+            (
+                align_to(size, field_align) + field_size,
+                align.max(field_align),
+            )
+        });
+        (align_to(size, align), align)
+    }
+
+    /// The offset, in bytes, of the payload of a variant laid out in
+    /// memory.
+    pub fn payload_offset(&self, cases: &[Option<&Type>]) -> usize {
+        align_to(discriminant_size(cases.len()), self.max_case_align(cases))
+    }
+
+    fn max_case_align(&self, cases: &[Option<&Type>]) -> usize {
+        cases
+            .iter()
+            .flatten()
+            .map(|ty| self.size_align(ty).1)
+            .max()
+            .unwrap_or(1)
+    }
+
+    fn variant_size_align(&self, cases: &[Option<&Type>]) -> (usize, usize) {
+        let payload_size = cases
+            .iter()
+            .flatten()
+            .map(|ty| self.size_align(ty).0)
+            .max()
+            .unwrap_or(0);
+        let align = discriminant_size(cases.len()).max(self.max_case_align(cases));
+        (
+            align_to(self.payload_offset(cases) + payload_size, align),
+            align,
+        )
+    }
+
+    /// Push the core WebAssembly types `ty` flattens to onto `out`.
+    pub fn push_flat_types(&self, ty: &Type, out: &mut Vec<FlatType>) {
+        match ty {
+            Type::Bool
+            | Type::U8
+            | Type::U16
+            | Type::U32
+            | Type::S8
+            | Type::S16
+            | Type::S32
+            | Type::Char => out.push(FlatType::I32),
+            Type::U64 | Type::S64 => out.push(FlatType::I64),
+            Type::Float32 => out.push(FlatType::F32),
+            Type::Float64 => out.push(FlatType::F64),
+            Type::String | Type::List(_) => out.extend(&[FlatType::I32, FlatType::I32]),
+            Type::Option(ty) => self.push_variant_flat_types(&[None, Some(&**ty)], out),
+            Type::Result(ok, error) => {
+                self.push_variant_flat_types(&[ok.as_deref(), error.as_deref()], out)
+            }
+            Type::Tuple(types) => {
+                for ty in types {
+                    self.push_flat_types(ty, out);
+                }
+            }
+            Type::Named(name) => match &self.resolve(name).kind {
+                TypeDefKind::Record(fields) => {
+                    for field in fields {
+                        self.push_flat_types(&field.ty, out);
+                    }
+                }
+                TypeDefKind::Variant(cases) => self.push_variant_flat_types(
+                    &cases
+                        .iter()
+                        .map(|case| case.ty.as_ref())
+                        .collect::<Vec<_>>(),
+                    out,
+                ),
+                TypeDefKind::Enum(_) => out.push(FlatType::I32),
+            },
+        }
+    }
+
+    fn push_variant_flat_types(&self, cases: &[Option<&Type>], out: &mut Vec<FlatType>) {
+        let cases = cases
+            .iter()
+            .flatten()
+            .map(|ty| {
+                let mut flat = Vec::new();
+                self.push_flat_types(ty, &mut flat);
+                flat
+            })
+            .collect::<Vec<_>>();
+        out.push(FlatType::I32);
+        out.extend(join_flat_types(&cases));
+    }
+
+    /// The core WebAssembly types `ty` flattens to.
+    pub fn flat_types(&self, ty: &Type) -> Vec<FlatType> {
+        let mut out = Vec::new();
+        self.push_flat_types(ty, &mut out);
+        out
+    }
+}
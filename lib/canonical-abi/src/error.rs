@@ -0,0 +1,51 @@
+use std::string::FromUtf8Error;
+use thiserror::Error;
+use wasmer::{ExportError, RuntimeError};
+
+/// An error while calling the guest through the bindings.
+#[derive(Error, Debug)]
+pub enum Error {
+    /// The guest doesn't export what the bindings need, or with the wrong
+    /// type.
+    #[error(transparent)]
+    Export(#[from] ExportError),
+
+    /// The guest trapped.
+    #[error(transparent)]
+    Runtime(#[from] RuntimeError),
+
+    /// A value needs memory in the guest, which doesn't export
+    /// `canonical_abi_realloc`.
+    #[error("the guest doesn't export `canonical_abi_realloc`")]
+    MissingRealloc,
+
+    /// The guest gave a pointer to memory it doesn't have.
+    #[error("out of bounds memory access: {len} bytes at {pointer}")]
+    OutOfBounds {
+        /// The pointer.
+        pointer: u32,
+        /// The number of bytes at `pointer`.
+        len: usize,
+    },
+
+    /// The guest gave a pointer that isn't aligned for what it points to.
+    #[error("pointer {pointer} isn't aligned to {align} bytes")]
+    UnalignedPointer {
+        /// The pointer.
+        pointer: u32,
+        /// The alignment of what it points to.
+        align: usize,
+    },
+
+    /// The guest gave a string that isn't valid UTF-8.
+    #[error("invalid string: {0}")]
+    InvalidUtf8(#[from] FromUtf8Error),
+
+    /// The guest gave a `char` that isn't a Unicode scalar value.
+    #[error("invalid char: {0:#x}")]
+    InvalidChar(u32),
+
+    /// The guest gave a discriminant with no case.
+    #[error("invalid discriminant: {0}")]
+    InvalidDiscriminant(u32),
+}
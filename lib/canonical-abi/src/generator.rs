@@ -0,0 +1,663 @@
+//! The generator of the host bindings of an interface.
+
+use crate::abi::{discriminant_size, FlatType, MAX_FLAT_PARAMS, MAX_FLAT_RESULTS};
+use crate::ast::{Function, Interface, Type, TypeDef, TypeDefKind};
+
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "static", "struct", "super", "trait", "true", "try", "type", "typeof", "unsafe",
+    "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// `kebab-case` to `snake_case`, escaping the Rust keywords.
+fn snake_case(name: &str) -> String {
+    let name = name.replace('-', "_");
+    // These can't be raw identifiers.
+    if ["crate", "self", "super"].contains(&&*name) {
+        format!("{}_", name)
+    } else if RUST_KEYWORDS.contains(&&*name) {
+        format!("r#{}", name)
+    } else {
+        name
+    }
+}
+
+/// The name of a parameter in the bindings, which mustn't shadow their
+/// locals.
+fn param_name(name: &str) -> String {
+    let name = snake_case(name);
+    if name == "params" || name == "ret" {
+        format!("{}_", name)
+    } else {
+        name
+    }
+}
+
+/// `kebab-case` to `CamelCase`.
+fn camel_case(name: &str) -> String {
+    name.split('-')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+fn flat_type(ty: FlatType) -> &'static str {
+    match ty {
+        FlatType::I32 => "i32",
+        FlatType::I64 => "i64",
+        FlatType::F32 => "f32",
+        FlatType::F64 => "f64",
+    }
+}
+
+/// The type of a `NativeFunc` parameter or result with the flat types
+/// `types`.
+fn native_type(types: &[FlatType]) -> String {
+    match types {
+        [] => "()".to_string(),
+        [ty] => flat_type(*ty).to_string(),
+        types => format!(
+            "({})",
+            types
+                .iter()
+                .map(|ty| flat_type(*ty))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// `offset + delta`, as Rust source.
+fn plus(offset: &str, delta: usize) -> String {
+    if delta == 0 {
+        offset.to_string()
+    } else {
+        format!("{} + {}", offset, delta)
+    }
+}
+
+/// The Rust source of a module, indented by its braces.
+#[derive(Default)]
+struct Source {
+    out: String,
+    indent: usize,
+}
+
+impl Source {
+    fn line(&mut self, line: impl AsRef<str>) {
+        let line = line.as_ref();
+        if line.starts_with('}') || line.starts_with(')') || line.starts_with(']') {
+            self.indent -= 1;
+        }
+        if line.is_empty() {
+            self.out.push('\n');
+        } else {
+            self.out.push_str(&"    ".repeat(self.indent));
+            self.out.push_str(line);
+            self.out.push('\n');
+        }
+        if line.ends_with('{') || line.ends_with('(') || line.ends_with('[') {
+            self.indent += 1;
+        }
+    }
+}
+
+struct Generator<'a> {
+    interface: &'a Interface,
+    source: Source,
+}
+
+impl<'a> Generator<'a> {
+    /// The Rust type of the values of `ty`.
+    fn rust_type(&self, ty: &Type) -> String {
+        match ty {
+            Type::Bool => "bool".to_string(),
+            Type::U8 => "u8".to_string(),
+            Type::U16 => "u16".to_string(),
+            Type::U32 => "u32".to_string(),
+            Type::U64 => "u64".to_string(),
+            Type::S8 => "i8".to_string(),
+            Type::S16 => "i16".to_string(),
+            Type::S32 => "i32".to_string(),
+            Type::S64 => "i64".to_string(),
+            Type::Float32 => "f32".to_string(),
+            Type::Float64 => "f64".to_string(),
+            Type::Char => "char".to_string(),
+            Type::String => "String".to_string(),
+            Type::List(ty) => format!("Vec<{}>", self.rust_type(ty)),
+            Type::Option(ty) => format!("Option<{}>", self.rust_type(ty)),
+            Type::Result(ok, error) => format!(
+                "Result<{}, {}>",
+                ok.as_ref()
+                    .map_or("()".to_string(), |ty| self.rust_type(ty)),
+                error
+                    .as_ref()
+                    .map_or("()".to_string(), |ty| self.rust_type(ty)),
+            ),
+            Type::Tuple(types) if types.len() == 1 => format!("({},)", self.rust_type(&types[0])),
+            Type::Tuple(types) => format!(
+                "({})",
+                types
+                    .iter()
+                    .map(|ty| self.rust_type(ty))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Type::Named(name) => camel_case(name),
+        }
+    }
+
+    /// The Rust type of the parameters of type `ty`, which borrow what
+    /// isn't `Copy`.
+    fn param_type(&self, ty: &Type) -> String {
+        match ty {
+            Type::String => "&str".to_string(),
+            Type::List(ty) => format!("&[{}]", self.rust_type(ty)),
+            Type::Option(_) | Type::Result(..) | Type::Tuple(_) => {
+                format!("&{}", self.rust_type(ty))
+            }
+            Type::Named(name) => match self.interface.type_def(name).map(|def| &def.kind) {
+                Some(TypeDefKind::Enum(_)) => self.rust_type(ty),
+                _ => format!("&{}", self.rust_type(ty)),
+            },
+            _ => self.rust_type(ty),
+        }
+    }
+
+    fn type_def(&mut self, def: &TypeDef) {
+        let name = camel_case(&def.name);
+        let (size, align) = self.interface.size_align(&Type::Named(def.name.clone()));
+        let s = &mut self.source;
+        match &def.kind {
+            TypeDefKind::Record(fields) => {
+                s.line("#[derive(Clone, Debug, PartialEq)]");
+                s.line(format!("pub struct {} {{", name));
+                for field in fields {
+                    let ty = self.rust_type(&field.ty);
+                    let s = &mut self.source;
+                    s.line(format!("pub {}: {},", snake_case(&field.name), ty));
+                }
+                self.source.line("}");
+            }
+            TypeDefKind::Variant(cases) => {
+                s.line("#[derive(Clone, Debug, PartialEq)]");
+                s.line(format!("pub enum {} {{", name));
+                for case in cases {
+                    let ty = case.ty.as_ref().map(|ty| self.rust_type(ty));
+                    let s = &mut self.source;
+                    match ty {
+                        Some(ty) => s.line(format!("{}({}),", camel_case(&case.name), ty)),
+                        None => s.line(format!("{},", camel_case(&case.name))),
+                    }
+                }
+                self.source.line("}");
+            }
+            TypeDefKind::Enum(cases) => {
+                s.line("#[derive(Clone, Copy, Debug, PartialEq, Eq)]");
+                s.line(format!("pub enum {} {{", name));
+                for case in cases {
+                    s.line(format!("{},", camel_case(case)));
+                }
+                s.line("}");
+            }
+        }
+
+        let s = &mut self.source;
+        s.line("");
+        s.line(format!("impl ComponentType for {} {{", name));
+        s.line(format!("const SIZE: usize = {};", size));
+        s.line(format!("const ALIGN: usize = {};", align));
+        s.line("");
+        s.line("fn push_flat_types(out: &mut Vec<FlatType>) {");
+        match &def.kind {
+            TypeDefKind::Record(fields) => {
+                for field in fields {
+                    let ty = self.rust_type(&field.ty);
+                    self.source.line(format!("<{}>::push_flat_types(out);", ty));
+                }
+            }
+            TypeDefKind::Variant(cases) if cases.iter().any(|case| case.ty.is_some()) => {
+                let types = cases
+                    .iter()
+                    .flat_map(|case| &case.ty)
+                    .map(|ty| self.rust_type(ty))
+                    .collect::<Vec<_>>();
+                let s = &mut self.source;
+                s.line("out.push(FlatType::I32);");
+                s.line("out.extend(abi::join_flat_types(&[");
+                for ty in types {
+                    s.line(format!("abi::flat_types::<{}>(),", ty));
+                }
+                s.line("]));");
+            }
+            TypeDefKind::Variant(_) | TypeDefKind::Enum(_) => {
+                self.source.line("out.push(FlatType::I32);")
+            }
+        }
+        let s = &mut self.source;
+        s.line("}");
+        s.line("}");
+
+        match &def.kind {
+            TypeDefKind::Record(fields) => {
+                let names = fields
+                    .iter()
+                    .map(|field| snake_case(&field.name))
+                    .collect::<Vec<_>>();
+                let offsets = self
+                    .interface
+                    .field_offsets(fields.iter().map(|field| &field.ty));
+                self.record(&name, &names, &offsets);
+            }
+            TypeDefKind::Variant(cases) => {
+                let names = cases
+                    .iter()
+                    .map(|case| (camel_case(&case.name), case.ty.is_some()))
+                    .collect::<Vec<_>>();
+                let payload_offset = self.interface.payload_offset(
+                    &cases
+                        .iter()
+                        .map(|case| case.ty.as_ref())
+                        .collect::<Vec<_>>(),
+                );
+                self.variant(&name, &names, payload_offset);
+            }
+            TypeDefKind::Enum(cases) => {
+                let names = cases
+                    .iter()
+                    .map(|case| (camel_case(case), false))
+                    .collect::<Vec<_>>();
+                self.variant(&name, &names, 0);
+            }
+        }
+    }
+
+    fn record(&mut self, name: &str, fields: &[String], offsets: &[usize]) {
+        let s = &mut self.source;
+        s.line("");
+        s.line(format!("impl Lower for {} {{", name));
+        s.line("fn lower(&self, abi: &Abi, out: &mut Vec<FlatValue>) -> Result<(), Error> {");
+        for field in fields {
+            s.line(format!("self.{}.lower(abi, out)?;", field));
+        }
+        s.line("Ok(())");
+        s.line("}");
+        s.line("");
+        s.line("fn store(&self, abi: &Abi, offset: u32) -> Result<(), Error> {");
+        for (field, offset) in fields.iter().zip(offsets) {
+            s.line(format!(
+                "self.{}.store(abi, {})?;",
+                field,
+                plus("offset", *offset)
+            ));
+        }
+        s.line("Ok(())");
+        s.line("}");
+        s.line("}");
+        s.line("");
+        s.line(format!("impl Lift for {} {{", name));
+        s.line("fn lift(abi: &Abi, flat: &mut Flat) -> Result<Self, Error> {");
+        s.line("Ok(Self {");
+        for field in fields {
+            s.line(format!("{}: Lift::lift(abi, flat)?,", field));
+        }
+        s.line("})");
+        s.line("}");
+        s.line("");
+        s.line("fn load(abi: &Abi, offset: u32) -> Result<Self, Error> {");
+        s.line("Ok(Self {");
+        for (field, offset) in fields.iter().zip(offsets) {
+            s.line(format!(
+                "{}: Lift::load(abi, {})?,",
+                field,
+                plus("offset", *offset)
+            ));
+        }
+        s.line("})");
+        s.line("}");
+        s.line("}");
+    }
+
+    /// The `Lower` and `Lift` implementations of a variant, given the
+    /// names of its cases and whether they have a payload.
+    fn variant(&mut self, name: &str, cases: &[(String, bool)], payload_offset: usize) {
+        let discriminant_size = discriminant_size(cases.len());
+        let has_payloads = cases.iter().any(|(_, payload)| *payload);
+        let abi = if has_payloads { "abi" } else { "_abi" };
+        let payload = plus("offset", payload_offset);
+        let s = &mut self.source;
+        s.line("");
+        s.line(format!("impl Lower for {} {{", name));
+        s.line(format!(
+            "fn lower(&self, {}: &Abi, out: &mut Vec<FlatValue>) -> Result<(), Error> {{",
+            abi
+        ));
+        if has_payloads {
+            s.line("let mut payload = Vec::new();");
+        }
+        s.line("let discriminant = match self {");
+        for (i, (case, has_payload)) in cases.iter().enumerate() {
+            if *has_payload {
+                s.line(format!("Self::{}(value) => {{", case));
+                s.line("value.lower(abi, &mut payload)?;");
+                s.line(i.to_string());
+                s.line("}");
+            } else {
+                s.line(format!("Self::{} => {},", case, i));
+            }
+        }
+        s.line("};");
+        s.line(format!(
+            "abi::lower_case::<Self>(out, discriminant, {});",
+            if has_payloads {
+                "payload"
+            } else {
+                "Vec::new()"
+            }
+        ));
+        s.line("Ok(())");
+        s.line("}");
+        s.line("");
+        s.line("fn store(&self, abi: &Abi, offset: u32) -> Result<(), Error> {");
+        s.line("match self {");
+        for (i, (case, has_payload)) in cases.iter().enumerate() {
+            if *has_payload {
+                s.line(format!("Self::{}(value) => {{", case));
+                s.line(format!(
+                    "abi::store_discriminant(abi, offset, {}, {})?;",
+                    discriminant_size, i
+                ));
+                s.line(format!("value.store(abi, {})", payload));
+                s.line("}");
+            } else {
+                s.line(format!(
+                    "Self::{} => abi::store_discriminant(abi, offset, {}, {}),",
+                    case, discriminant_size, i
+                ));
+            }
+        }
+        s.line("}");
+        s.line("}");
+        s.line("}");
+        s.line("");
+        s.line(format!("impl Lift for {} {{", name));
+        s.line(format!(
+            "fn lift({}: &Abi, flat: &mut Flat) -> Result<Self, Error> {{",
+            abi
+        ));
+        s.line("match abi::lift_discriminant(flat) {");
+        for (i, (case, has_payload)) in cases.iter().enumerate() {
+            if *has_payload {
+                s.line(format!(
+                    "{} => Ok(Self::{}(abi::lift_case::<Self, _>(abi, flat)?)),",
+                    i, case
+                ));
+            } else {
+                s.line(format!("{} => {{", i));
+                s.line("abi::skip_case::<Self>(flat);");
+                s.line(format!("Ok(Self::{})", case));
+                s.line("}");
+            }
+        }
+        s.line("discriminant => Err(Error::InvalidDiscriminant(discriminant)),");
+        s.line("}");
+        s.line("}");
+        s.line("");
+        s.line("fn load(abi: &Abi, offset: u32) -> Result<Self, Error> {");
+        s.line(format!(
+            "match abi::load_discriminant(abi, offset, {})? {{",
+            discriminant_size
+        ));
+        for (i, (case, has_payload)) in cases.iter().enumerate() {
+            if *has_payload {
+                s.line(format!(
+                    "{} => Ok(Self::{}(Lift::load(abi, {})?)),",
+                    i, case, payload
+                ));
+            } else {
+                s.line(format!("{} => Ok(Self::{}),", i, case));
+            }
+        }
+        s.line("discriminant => Err(Error::InvalidDiscriminant(discriminant)),");
+        s.line("}");
+        s.line("}");
+        s.line("}");
+    }
+
+    fn flat_params(&self, function: &Function) -> Vec<FlatType> {
+        let mut types = Vec::new();
+        for param in &function.params {
+            self.interface.push_flat_types(&param.ty, &mut types);
+        }
+        types
+    }
+
+    fn flat_results(&self, function: &Function) -> Vec<FlatType> {
+        function
+            .result
+            .as_ref()
+            .map(|ty| self.interface.flat_types(ty))
+            .unwrap_or_default()
+    }
+
+    /// The type of the `NativeFunc` of `function`.
+    fn native_func(&self, function: &Function) -> String {
+        let params = self.flat_params(function);
+        let results = self.flat_results(function);
+        format!(
+            "NativeFunc<{}, {}>",
+            if params.len() > MAX_FLAT_PARAMS {
+                "i32".to_string()
+            } else {
+                native_type(&params)
+            },
+            if results.len() > MAX_FLAT_RESULTS {
+                "i32".to_string()
+            } else {
+                native_type(&results)
+            }
+        )
+    }
+
+    fn function(&mut self, function: &Function) {
+        let flat_params = self.flat_params(function);
+        let flat_results = self.flat_results(function);
+        let names = function
+            .params
+            .iter()
+            .map(|param| param_name(&param.name))
+            .collect::<Vec<_>>();
+        let params = function
+            .params
+            .iter()
+            .zip(&names)
+            .map(|(param, name)| format!("{}: {}", name, self.param_type(&param.ty)))
+            .collect::<Vec<_>>();
+        let result = function
+            .result
+            .as_ref()
+            .map_or("()".to_string(), |ty| self.rust_type(ty));
+        let name = snake_case(&function.name);
+
+        let s = &mut self.source;
+        s.line("");
+        // `self` counts.
+        if params.len() + 1 > 7 {
+            s.line("#[allow(clippy::too_many_arguments)]");
+        }
+        let signature = format!(
+            "pub fn {}(&self, {}) -> Result<{}, Error> {{",
+            name,
+            params.join(", "),
+            result
+        );
+        // rustfmt's maximum width, indented twice.
+        if signature.len() + 8 <= 100 {
+            s.line(signature.replace("(&self, )", "(&self)"));
+        } else {
+            s.line(format!("pub fn {}(", name));
+            s.line("&self,");
+            for param in &params {
+                s.line(format!("{},", param));
+            }
+            s.line(format!(") -> Result<{}, Error> {{", result));
+        }
+
+        let args = if flat_params.len() > MAX_FLAT_PARAMS {
+            let types = function
+                .params
+                .iter()
+                .map(|param| param.ty.clone())
+                .collect::<Vec<_>>();
+            let (size, align) = self.interface.size_align(&Type::Tuple(types.clone()));
+            let offsets = self.interface.field_offsets(&types);
+            let s = &mut self.source;
+            s.line(format!(
+                "let params = self.abi.alloc({}, {})?;",
+                size, align
+            ));
+            for (name, offset) in names.iter().zip(offsets) {
+                s.line(format!(
+                    "{}.store(&self.abi, {})?;",
+                    name,
+                    plus("params", offset)
+                ));
+            }
+            vec!["params as i32".to_string()]
+        } else {
+            if !flat_params.is_empty() {
+                self.source.line("let mut params = Vec::new();");
+            }
+            for name in &names {
+                self.source
+                    .line(format!("{}.lower(&self.abi, &mut params)?;", name));
+            }
+            flat_params
+                .iter()
+                .enumerate()
+                .map(|(i, ty)| format!("params[{}].as_{}()", i, flat_type(*ty)))
+                .collect()
+        };
+
+        let s = &mut self.source;
+        let call = if flat_results.is_empty() {
+            format!("self.{}.call(", name)
+        } else {
+            format!("let ret = self.{}.call(", name)
+        };
+        // The maximum width, indented twice.
+        if call.len() + args.join(", ").len() + 3 + 8 <= 100 {
+            s.line(format!("{}{})?;", call, args.join(", ")));
+        } else {
+            s.line(call);
+            for arg in &args {
+                s.line(format!("{},", arg));
+            }
+            s.line(")?;");
+        }
+        if flat_results.is_empty() {
+            s.line("Ok(())");
+        } else if flat_results.len() > MAX_FLAT_RESULTS {
+            s.line("abi::load_result(&self.abi, ret)");
+        } else {
+            s.line("abi::lift_result(&self.abi, ret)");
+        }
+        s.line("}");
+    }
+
+    fn generate(mut self, name: &str) -> String {
+        let module = snake_case(name);
+        let bindings = camel_case(name);
+        let interface = self.interface;
+
+        let s = &mut self.source;
+        s.line(format!(
+            "// Generated by `wasmer-canonical-abi` from the `{}` interface, do not edit.",
+            name
+        ));
+        s.line("");
+        s.line(format!("pub mod {} {{", module));
+        s.line("#[allow(unused_imports)]");
+        s.line("use wasmer::{Instance, NativeFunc};");
+        s.line("#[allow(unused_imports)]");
+        s.line("use wasmer_canonical_abi::abi::{");
+        s.line("self, Abi, ComponentType, Flat, FlatType, FlatValue, Lift, Lower,");
+        s.line("};");
+        s.line("use wasmer_canonical_abi::Error;");
+
+        for def in &interface.types {
+            self.source.line("");
+            self.type_def(def);
+        }
+
+        let fields = interface
+            .functions
+            .iter()
+            .map(|function| {
+                format!(
+                    "{}: {},",
+                    snake_case(&function.name),
+                    self.native_func(function)
+                )
+            })
+            .collect::<Vec<_>>();
+        let s = &mut self.source;
+        s.line("");
+        s.line(format!(
+            "/// Calls the functions of the `{}` interface exported by a guest.",
+            name
+        ));
+        s.line(format!("pub struct {} {{", bindings));
+        s.line("abi: Abi,");
+        for field in fields {
+            s.line(field);
+        }
+        s.line("}");
+        s.line("");
+        s.line(format!("impl {} {{", bindings));
+        s.line("/// Look up the exports of `instance`.");
+        s.line("pub fn new(instance: &Instance) -> Result<Self, Error> {");
+        s.line("Ok(Self {");
+        s.line("abi: Abi::new(instance)?,");
+        for function in &interface.functions {
+            s.line(format!(
+                "{}: instance.exports.get_native_function(\"{}\")?,",
+                snake_case(&function.name),
+                function.name
+            ));
+        }
+        s.line("})");
+        s.line("}");
+        for function in &interface.functions {
+            self.function(function);
+        }
+        let s = &mut self.source;
+        s.line("}");
+        s.line("}");
+        self.source.out
+    }
+}
+
+impl Interface {
+    /// Generate the host bindings of the interface, as the source of a
+    /// Rust module named `name`.
+    ///
+    /// The module has the Rust types of the records, variants and enums
+    /// of the interface, and a struct named after the module, in
+    /// `CamelCase`, whose methods call the functions of the interface
+    /// exported by a guest.
+    pub fn generate_host_bindings(&self, name: &str) -> String {
+        Generator {
+            interface: self,
+            source: Source::default(),
+        }
+        .generate(name)
+    }
+}
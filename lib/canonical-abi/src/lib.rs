@@ -0,0 +1,30 @@
+//! Host bindings for the functions a WebAssembly guest exports,
+//! generated from WIT-like interface definitions.
+//!
+//! The bindings pass strings, lists, records, variants, options and
+//! results to the guest and back following the canonical ABI, on top of
+//! `NativeFunc` and `Memory`. See [`generate_host_bindings`] and the
+//! [`abi`] module the generated code builds on.
+
+#![deny(missing_docs, unused_extern_crates)]
+#![warn(unused_import_braces)]
+
+pub mod abi;
+pub mod ast;
+mod error;
+mod generator;
+mod parser;
+
+pub use crate::abi::Abi;
+pub use crate::ast::Interface;
+pub use crate::error::Error;
+pub use crate::parser::ParseError;
+
+/// Generate the host bindings of the interface defined by `source`, as
+/// the source of a Rust module named `name`.
+///
+/// This is a shortcut for [`Interface::parse`] followed by
+/// [`Interface::generate_host_bindings`].
+pub fn generate_host_bindings(source: &str, name: &str) -> Result<String, ParseError> {
+    Ok(Interface::parse(source)?.generate_host_bindings(name))
+}
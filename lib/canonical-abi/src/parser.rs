@@ -0,0 +1,593 @@
+//! The parser of the WIT-like interface definitions:
+//!
+//! ```wit
+//! // A comment.
+//! record point { x: s32, y: s32 }
+//! variant shape { circle(float64), square(float32), none }
+//! enum color { red, green, blue }
+//!
+//! area: func(shape: shape) -> float64
+//! names: func(colors: list<color>) -> list<string>
+//! find: func(haystack: string, needle: string) -> result<u32, _>
+//! ```
+
+use crate::ast::{Case, Field, Function, Interface, Param, Type, TypeDef, TypeDefKind};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+use thiserror::Error;
+
+/// An error in the definitions of an interface.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("line {line}: {message}")]
+pub struct ParseError {
+    /// The line of the error, starting at 1.
+    pub line: usize,
+    /// What's wrong.
+    pub message: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Punct(char),
+    Arrow,
+    Eof,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ident(ident) => write!(f, "`{}`", ident),
+            Self::Punct(punct) => write!(f, "`{}`", punct),
+            Self::Arrow => write!(f, "`->`"),
+            Self::Eof => write!(f, "end of input"),
+        }
+    }
+}
+
+/// Split `source` into tokens, with their lines.
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut chars: Peekable<CharIndices> = source.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            '/' if chars.peek().map(|&(_, c)| c) == Some('/') => {
+                while matches!(chars.peek(), Some(&(_, c)) if c != '\n') {
+                    chars.next();
+                }
+            }
+            '-' if chars.peek().map(|&(_, c)| c) == Some('>') => {
+                chars.next();
+                tokens.push((Token::Arrow, line));
+            }
+            '{' | '}' | '(' | ')' | '<' | '>' | ',' | ':' => tokens.push((Token::Punct(c), line)),
+            c if c.is_ascii_alphanumeric() || c == '_' => {
+                let mut end = start + c.len_utf8();
+                while let Some(&(i, c)) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                tokens.push((Token::Ident(source[start..end].to_string()), line));
+            }
+            c => {
+                return Err(ParseError {
+                    line,
+                    message: format!("unexpected character `{}`", c),
+                })
+            }
+        }
+    }
+    tokens.push((Token::Eof, line));
+    Ok(tokens)
+}
+
+/// Whether `ident` is a valid name: lowercase words separated by dashes.
+fn is_valid_name(ident: &str) -> bool {
+    ident.split('-').all(|word| {
+        word.starts_with(|c: char| c.is_ascii_lowercase())
+            && word
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+    })
+}
+
+const KEYWORDS: &[&str] = &[
+    "bool", "u8", "u16", "u32", "u64", "s8", "s16", "s32", "s64", "float32", "float64", "char",
+    "string", "list", "option", "result", "tuple", "record", "variant", "enum", "func",
+];
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].0
+    }
+
+    fn line(&self) -> usize {
+        self.tokens[self.position].1
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.position].0.clone();
+        if token != Token::Eof {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, ParseError> {
+        Err(ParseError {
+            line: self.line(),
+            message: message.into(),
+        })
+    }
+
+    fn eat(&mut self, punct: char) -> bool {
+        if *self.peek() == Token::Punct(punct) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punct: char) -> Result<(), ParseError> {
+        if self.eat(punct) {
+            Ok(())
+        } else {
+            self.error(format!("expected `{}`, found {}", punct, self.peek()))
+        }
+    }
+
+    fn name(&mut self) -> Result<String, ParseError> {
+        match self.peek().clone() {
+            Token::Ident(ident) if KEYWORDS.contains(&&*ident) => {
+                self.error(format!("expected a name, found the keyword `{}`", ident))
+            }
+            Token::Ident(ident) if !is_valid_name(&ident) => self.error(format!(
+                "invalid name `{}`, names are lowercase words separated by dashes",
+                ident
+            )),
+            Token::Ident(ident) => {
+                self.position += 1;
+                Ok(ident)
+            }
+            token => self.error(format!("expected a name, found {}", token)),
+        }
+    }
+
+    /// Parse `item, ...` up to `close`, allowing a trailing comma.
+    fn list<T>(
+        &mut self,
+        close: char,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        let mut items = Vec::new();
+        while !self.eat(close) {
+            items.push(item(self)?);
+            if !self.eat(',') {
+                self.expect(close)?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    fn ty(&mut self) -> Result<Type, ParseError> {
+        let ident = match self.peek().clone() {
+            Token::Ident(ident) => ident,
+            token => return self.error(format!("expected a type, found {}", token)),
+        };
+        let ty = match &*ident {
+            "bool" => Type::Bool,
+            "u8" => Type::U8,
+            "u16" => Type::U16,
+            "u32" => Type::U32,
+            "u64" => Type::U64,
+            "s8" => Type::S8,
+            "s16" => Type::S16,
+            "s32" => Type::S32,
+            "s64" => Type::S64,
+            "float32" => Type::Float32,
+            "float64" => Type::Float64,
+            "char" => Type::Char,
+            "string" => Type::String,
+            "list" | "option" => {
+                self.position += 1;
+                self.expect('<')?;
+                let ty = Box::new(self.ty()?);
+                self.expect('>')?;
+                return Ok(if ident == "list" {
+                    Type::List(ty)
+                } else {
+                    Type::Option(ty)
+                });
+            }
+            "result" => {
+                self.position += 1;
+                if !self.eat('<') {
+                    return Ok(Type::Result(None, None));
+                }
+                let ok = self.payload()?;
+                let error = if self.eat(',') { self.payload()? } else { None };
+                self.expect('>')?;
+                return Ok(Type::Result(ok, error));
+            }
+            "tuple" => {
+                self.position += 1;
+                self.expect('<')?;
+                return Ok(Type::Tuple(self.list('>', Self::ty)?));
+            }
+            _ => return Ok(Type::Named(self.name()?)),
+        };
+        self.position += 1;
+        Ok(ty)
+    }
+
+    /// Parse the payload of a `result`, where `_` stands for none.
+    fn payload(&mut self) -> Result<Option<Box<Type>>, ParseError> {
+        if *self.peek() == Token::Ident("_".to_string()) {
+            self.position += 1;
+            Ok(None)
+        } else {
+            Ok(Some(Box::new(self.ty()?)))
+        }
+    }
+
+    fn type_def(&mut self, keyword: &str) -> Result<TypeDef, ParseError> {
+        let name = self.name()?;
+        self.expect('{')?;
+        let kind = match keyword {
+            "record" => TypeDefKind::Record(self.list('}', |parser| {
+                let name = parser.name()?;
+                parser.expect(':')?;
+                Ok(Field {
+                    name,
+                    ty: parser.ty()?,
+                })
+            })?),
+            "variant" => TypeDefKind::Variant(self.list('}', |parser| {
+                let name = parser.name()?;
+                let ty = if parser.eat('(') {
+                    let ty = parser.ty()?;
+                    parser.expect(')')?;
+                    Some(ty)
+                } else {
+                    None
+                };
+                Ok(Case { name, ty })
+            })?),
+            _ => TypeDefKind::Enum(self.list('}', Self::name)?),
+        };
+        Ok(TypeDef { name, kind })
+    }
+
+    fn function(&mut self) -> Result<Function, ParseError> {
+        let name = self.name()?;
+        self.expect(':')?;
+        match self.next() {
+            Token::Ident(ident) if ident == "func" => {}
+            token => {
+                self.position -= 1;
+                return self.error(format!("expected `func`, found {}", token));
+            }
+        }
+        self.expect('(')?;
+        let params = self.list(')', |parser| {
+            let name = parser.name()?;
+            parser.expect(':')?;
+            Ok(Param {
+                name,
+                ty: parser.ty()?,
+            })
+        })?;
+        let result = if *self.peek() == Token::Arrow {
+            self.position += 1;
+            Some(self.ty()?)
+        } else {
+            None
+        };
+        Ok(Function {
+            name,
+            params,
+            result,
+        })
+    }
+
+    /// Parse the interface, along with the lines its types and its
+    /// functions are defined at.
+    fn interface(&mut self) -> Result<(Interface, Lines), ParseError> {
+        let mut interface = Interface::default();
+        let mut lines = Lines::default();
+        loop {
+            let line = self.line();
+            match self.peek().clone() {
+                Token::Eof => break,
+                Token::Ident(keyword)
+                    if keyword == "record" || keyword == "variant" || keyword == "enum" =>
+                {
+                    self.position += 1;
+                    lines.types.push(line);
+                    interface.types.push(self.type_def(&keyword)?);
+                }
+                _ => {
+                    lines.functions.push(line);
+                    interface.functions.push(self.function()?);
+                }
+            }
+        }
+        Ok((interface, lines))
+    }
+}
+
+#[derive(Default)]
+struct Lines {
+    types: Vec<usize>,
+    functions: Vec<usize>,
+}
+
+/// Check the names are unique, the named types exist and don't contain
+/// themselves.
+fn validate(interface: &Interface, lines: &Lines) -> Result<(), ParseError> {
+    /// The index of the first name already seen.
+    fn duplicate<'a>(names: impl Iterator<Item = &'a String>) -> Option<(usize, &'a String)> {
+        let mut seen = HashSet::new();
+        names.enumerate().find(|(_, name)| !seen.insert(*name))
+    }
+
+    fn named_types<'a>(ty: &'a Type, out: &mut Vec<&'a str>) {
+        match ty {
+            Type::List(ty) | Type::Option(ty) => named_types(ty, out),
+            Type::Result(ok, error) => {
+                for ty in ok.iter().chain(error) {
+                    named_types(ty, out);
+                }
+            }
+            Type::Tuple(types) => {
+                for ty in types {
+                    named_types(ty, out);
+                }
+            }
+            Type::Named(name) => out.push(name),
+            _ => {}
+        }
+    }
+
+    let error = |line: usize, message: String| Err(ParseError { line, message });
+    if let Some((i, name)) = duplicate(interface.types.iter().map(|def| &def.name)) {
+        return error(lines.types[i], format!("duplicate type `{}`", name));
+    }
+    if let Some((i, name)) = duplicate(interface.functions.iter().map(|f| &f.name)) {
+        return error(lines.functions[i], format!("duplicate function `{}`", name));
+    }
+
+    // The named types each type refers to.
+    let mut references = HashMap::new();
+    for (def, &line) in interface.types.iter().zip(&lines.types) {
+        let (what, names, mut types) = match &def.kind {
+            TypeDefKind::Record(fields) => (
+                ("record", "field"),
+                fields.iter().map(|field| &field.name).collect::<Vec<_>>(),
+                fields.iter().map(|field| &field.ty).collect::<Vec<_>>(),
+            ),
+            TypeDefKind::Variant(cases) => (
+                ("variant", "case"),
+                cases.iter().map(|case| &case.name).collect(),
+                cases.iter().flat_map(|case| &case.ty).collect(),
+            ),
+            TypeDefKind::Enum(cases) => (("enum", "case"), cases.iter().collect(), vec![]),
+        };
+        if names.is_empty() {
+            return error(
+                line,
+                format!("{} `{}` has no {}s", what.0, def.name, what.1),
+            );
+        }
+        if let Some((_, name)) = duplicate(names.into_iter()) {
+            return error(line, format!("duplicate {} `{}`", what.1, name));
+        }
+        let mut named = Vec::new();
+        for ty in types.drain(..) {
+            named_types(ty, &mut named);
+        }
+        if let Some(unknown) = named.iter().find(|name| interface.type_def(name).is_none()) {
+            return error(line, format!("unknown type `{}`", unknown));
+        }
+        references.insert(&*def.name, (named, line));
+    }
+
+    for (function, &line) in interface.functions.iter().zip(&lines.functions) {
+        if let Some((_, name)) = duplicate(function.params.iter().map(|param| &param.name)) {
+            return error(line, format!("duplicate parameter `{}`", name));
+        }
+        let mut named = Vec::new();
+        for ty in function
+            .params
+            .iter()
+            .map(|param| &param.ty)
+            .chain(&function.result)
+        {
+            named_types(ty, &mut named);
+        }
+        if let Some(unknown) = named.iter().find(|name| interface.type_def(name).is_none()) {
+            return error(line, format!("unknown type `{}`", unknown));
+        }
+    }
+
+    // The canonical ABI has no recursive types.
+    for def in &interface.types {
+        let (references_of_def, line) = &references[&*def.name];
+        let mut stack = references_of_def.clone();
+        let mut visited = HashSet::new();
+        while let Some(name) = stack.pop() {
+            if name == def.name {
+                return error(*line, format!("type `{}` contains itself", def.name));
+            }
+            if visited.insert(name) {
+                stack.extend(&references[name].0);
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Interface {
+    /// Parse the definitions of an interface.
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+        };
+        let (interface, lines) = parser.interface()?;
+        validate(&interface, &lines)?;
+        Ok(interface)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(source: &str) -> String {
+        Interface::parse(source).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn parse_interface() {
+        let interface = Interface::parse(
+            "
+            // Comments are ignored.
+            record point { x: s32, y: s32, }
+            variant shape { circle(float64), empty }
+            enum color { red, light-green }
+
+            move-to: func(p: point, shapes: list<shape>) -> result<_, string>
+            get: func(id: u64) -> option<tuple<string, color>>
+            reset: func()
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            interface.types,
+            vec![
+                TypeDef {
+                    name: "point".to_string(),
+                    kind: TypeDefKind::Record(vec![
+                        Field {
+                            name: "x".to_string(),
+                            ty: Type::S32,
+                        },
+                        Field {
+                            name: "y".to_string(),
+                            ty: Type::S32,
+                        },
+                    ]),
+                },
+                TypeDef {
+                    name: "shape".to_string(),
+                    kind: TypeDefKind::Variant(vec![
+                        Case {
+                            name: "circle".to_string(),
+                            ty: Some(Type::Float64),
+                        },
+                        Case {
+                            name: "empty".to_string(),
+                            ty: None,
+                        },
+                    ]),
+                },
+                TypeDef {
+                    name: "color".to_string(),
+                    kind: TypeDefKind::Enum(vec!["red".to_string(), "light-green".to_string()]),
+                },
+            ]
+        );
+        assert_eq!(
+            interface.functions,
+            vec![
+                Function {
+                    name: "move-to".to_string(),
+                    params: vec![
+                        Param {
+                            name: "p".to_string(),
+                            ty: Type::Named("point".to_string()),
+                        },
+                        Param {
+                            name: "shapes".to_string(),
+                            ty: Type::List(Box::new(Type::Named("shape".to_string()))),
+                        },
+                    ],
+                    result: Some(Type::Result(None, Some(Box::new(Type::String)))),
+                },
+                Function {
+                    name: "get".to_string(),
+                    params: vec![Param {
+                        name: "id".to_string(),
+                        ty: Type::U64,
+                    }],
+                    result: Some(Type::Option(Box::new(Type::Tuple(vec![
+                        Type::String,
+                        Type::Named("color".to_string()),
+                    ])))),
+                },
+                Function {
+                    name: "reset".to_string(),
+                    params: vec![],
+                    result: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_invalid_interfaces() {
+        assert_eq!(
+            parse_error("f: func(a: s32 b: s32)"),
+            "line 1: expected `)`, found `b`"
+        );
+        assert_eq!(
+            parse_error("record r {\n  a: strng,\n}"),
+            "line 1: unknown type `strng`"
+        );
+        assert_eq!(
+            parse_error("enum e { a }\nenum e { b }"),
+            "line 2: duplicate type `e`"
+        );
+        assert_eq!(
+            parse_error("record r { a: s32, a: s64 }"),
+            "line 1: duplicate field `a`"
+        );
+        assert_eq!(
+            parse_error("\nFoo: func()"),
+            "line 2: invalid name `Foo`, names are lowercase words separated by dashes"
+        );
+        assert_eq!(
+            parse_error("\n\nf: func(string: string)"),
+            "line 3: expected a name, found the keyword `string`"
+        );
+        assert_eq!(
+            parse_error("record a { b: list<b> }\nvariant b { a(a) }"),
+            "line 1: type `a` contains itself"
+        );
+        assert_eq!(
+            parse_error("f: func()\ng: func()\nf: func(a: s32)"),
+            "line 3: duplicate function `f`"
+        );
+        assert_eq!(
+            parse_error("f: func(p: point)"),
+            "line 1: unknown type `point`"
+        );
+    }
+}
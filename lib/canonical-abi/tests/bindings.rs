@@ -0,0 +1,143 @@
+//! Calls `guest/guest.wat` through the bindings generated from
+//! `guest/guest.wit`, in `guest/bindings.rs`.
+
+use anyhow::Result;
+use std::path::Path;
+use wasmer::{imports, Instance, Module, Store};
+use wasmer_canonical_abi::{generate_host_bindings, Error};
+
+include!("guest/bindings.rs");
+
+use guest::{Color, Guest, Point, Shape};
+
+fn instantiate() -> Result<(Instance, Guest)> {
+    let store = Store::default();
+    let module = Module::new(&store, include_str!("guest/guest.wat"))?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let guest = Guest::new(&instance)?;
+    Ok((instance, guest))
+}
+
+/// The number of bytes the host released.
+fn freed(instance: &Instance) -> Result<i32> {
+    let freed = instance.exports.get_native_function::<(), i32>("freed")?;
+    Ok(freed.call()?)
+}
+
+#[test]
+fn generated_bindings_are_up_to_date() -> Result<()> {
+    let bindings = generate_host_bindings(include_str!("guest/guest.wit"), "guest")?;
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/guest/bindings.rs");
+    if std::env::var("UPDATE_BINDINGS").is_ok() {
+        std::fs::write(&path, &bindings)?;
+    }
+    assert_eq!(
+        bindings,
+        std::fs::read_to_string(&path)?,
+        "the bindings are outdated, regenerate them with `UPDATE_BINDINGS=1 cargo test`"
+    );
+    Ok(())
+}
+
+#[test]
+fn strings() -> Result<()> {
+    let (instance, guest) = instantiate()?;
+    assert_eq!(guest.greet("world")?, "Hello, world!");
+    // The guest gave away the greeting.
+    assert_eq!(freed(&instance)?, 13);
+    assert_eq!(guest.greet("")?, "Hello, !");
+    Ok(())
+}
+
+#[test]
+fn records() -> Result<()> {
+    let (_, guest) = instantiate()?;
+    assert_eq!(
+        guest.translate(&Point { x: 1, y: -2 }, 10, 20)?,
+        Point { x: 11, y: 18 }
+    );
+
+    // The points don't fit in the parameters, and are passed in memory.
+    let points = (1..=9)
+        .map(|i| Point { x: i, y: 10 * i })
+        .collect::<Vec<_>>();
+    let sum = guest.sum_points(
+        &points[0], &points[1], &points[2], &points[3], &points[4], &points[5], &points[6],
+        &points[7], &points[8],
+    )?;
+    assert_eq!(sum, 11 * 45);
+    Ok(())
+}
+
+#[test]
+fn variants() -> Result<()> {
+    let (_, guest) = instantiate()?;
+    assert_eq!(guest.area(&Shape::Circle(2.0))?, 12.0);
+    assert_eq!(guest.area(&Shape::Square(1.5))?, 2.25);
+    assert_eq!(guest.area(&Shape::Rectangle(Point { x: 3, y: -4 }))?, -12.0);
+    assert_eq!(guest.area(&Shape::Empty)?, 0.0);
+    Ok(())
+}
+
+#[test]
+fn lists() -> Result<()> {
+    let (instance, guest) = instantiate()?;
+    assert_eq!(guest.sum(&[])?, 0);
+    assert_eq!(
+        guest.sum(&[u32::MAX, u32::MAX, 2])?,
+        2 * u32::MAX as u64 + 2
+    );
+
+    assert_eq!(guest.colors()?, vec![Color::Red, Color::Blue, Color::Green]);
+    assert_eq!(freed(&instance)?, 3);
+    Ok(())
+}
+
+#[test]
+fn options_and_results() -> Result<()> {
+    let (instance, guest) = instantiate()?;
+    let haystack = ["a".to_string(), "needle".to_string(), "b".to_string()];
+    assert_eq!(guest.find(&haystack, "needle")?, Some(1));
+    assert_eq!(guest.find(&haystack, "pin")?, None);
+    assert_eq!(guest.find(&[], "needle")?, None);
+
+    assert_eq!(guest.parse_color("green")?, Ok(Color::Green));
+    assert_eq!(guest.parse_color("blue")?, Ok(Color::Blue));
+    assert_eq!(freed(&instance)?, 0);
+    assert_eq!(guest.parse_color("purple")?, Err("purple".to_string()));
+    assert_eq!(freed(&instance)?, 6);
+    Ok(())
+}
+
+#[test]
+fn invalid_values() -> Result<()> {
+    let (_, guest) = instantiate()?;
+    assert_eq!(guest.next_char('a')?, 'b');
+    // The guest returns a surrogate.
+    match guest.next_char('\u{d7ff}') {
+        Err(Error::InvalidChar(0xd800)) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+    Ok(())
+}
+
+#[test]
+fn missing_realloc() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"(module
+             (memory (export "memory") 1)
+             (func (export "greet") (param i32 i32) (result i32)
+               (i32.const 0)))"#,
+    )?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let guest = Guest::new(&instance);
+    // The other functions are missing.
+    assert!(matches!(guest, Err(Error::Export(_))));
+
+    let abi = wasmer_canonical_abi::Abi::new(&instance)?;
+    assert!(matches!(abi.alloc(8, 4), Err(Error::MissingRealloc)));
+    assert_eq!(abi.alloc(0, 4)?, 4);
+    Ok(())
+}
@@ -0,0 +1,336 @@
+// Generated by `wasmer-canonical-abi` from the `guest` interface, do not edit.
+
+pub mod guest {
+    #[allow(unused_imports)]
+    use wasmer::{Instance, NativeFunc};
+    #[allow(unused_imports)]
+    use wasmer_canonical_abi::abi::{
+        self, Abi, ComponentType, Flat, FlatType, FlatValue, Lift, Lower,
+    };
+    use wasmer_canonical_abi::Error;
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Point {
+        pub x: i32,
+        pub y: i32,
+    }
+
+    impl ComponentType for Point {
+        const SIZE: usize = 8;
+        const ALIGN: usize = 4;
+
+        fn push_flat_types(out: &mut Vec<FlatType>) {
+            <i32>::push_flat_types(out);
+            <i32>::push_flat_types(out);
+        }
+    }
+
+    impl Lower for Point {
+        fn lower(&self, abi: &Abi, out: &mut Vec<FlatValue>) -> Result<(), Error> {
+            self.x.lower(abi, out)?;
+            self.y.lower(abi, out)?;
+            Ok(())
+        }
+
+        fn store(&self, abi: &Abi, offset: u32) -> Result<(), Error> {
+            self.x.store(abi, offset)?;
+            self.y.store(abi, offset + 4)?;
+            Ok(())
+        }
+    }
+
+    impl Lift for Point {
+        fn lift(abi: &Abi, flat: &mut Flat) -> Result<Self, Error> {
+            Ok(Self {
+                x: Lift::lift(abi, flat)?,
+                y: Lift::lift(abi, flat)?,
+            })
+        }
+
+        fn load(abi: &Abi, offset: u32) -> Result<Self, Error> {
+            Ok(Self {
+                x: Lift::load(abi, offset)?,
+                y: Lift::load(abi, offset + 4)?,
+            })
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum Shape {
+        Circle(f64),
+        Square(f32),
+        Rectangle(Point),
+        Empty,
+    }
+
+    impl ComponentType for Shape {
+        const SIZE: usize = 16;
+        const ALIGN: usize = 8;
+
+        fn push_flat_types(out: &mut Vec<FlatType>) {
+            out.push(FlatType::I32);
+            out.extend(abi::join_flat_types(&[
+                abi::flat_types::<f64>(),
+                abi::flat_types::<f32>(),
+                abi::flat_types::<Point>(),
+            ]));
+        }
+    }
+
+    impl Lower for Shape {
+        fn lower(&self, abi: &Abi, out: &mut Vec<FlatValue>) -> Result<(), Error> {
+            let mut payload = Vec::new();
+            let discriminant = match self {
+                Self::Circle(value) => {
+                    value.lower(abi, &mut payload)?;
+                    0
+                }
+                Self::Square(value) => {
+                    value.lower(abi, &mut payload)?;
+                    1
+                }
+                Self::Rectangle(value) => {
+                    value.lower(abi, &mut payload)?;
+                    2
+                }
+                Self::Empty => 3,
+            };
+            abi::lower_case::<Self>(out, discriminant, payload);
+            Ok(())
+        }
+
+        fn store(&self, abi: &Abi, offset: u32) -> Result<(), Error> {
+            match self {
+                Self::Circle(value) => {
+                    abi::store_discriminant(abi, offset, 1, 0)?;
+                    value.store(abi, offset + 8)
+                }
+                Self::Square(value) => {
+                    abi::store_discriminant(abi, offset, 1, 1)?;
+                    value.store(abi, offset + 8)
+                }
+                Self::Rectangle(value) => {
+                    abi::store_discriminant(abi, offset, 1, 2)?;
+                    value.store(abi, offset + 8)
+                }
+                Self::Empty => abi::store_discriminant(abi, offset, 1, 3),
+            }
+        }
+    }
+
+    impl Lift for Shape {
+        fn lift(abi: &Abi, flat: &mut Flat) -> Result<Self, Error> {
+            match abi::lift_discriminant(flat) {
+                0 => Ok(Self::Circle(abi::lift_case::<Self, _>(abi, flat)?)),
+                1 => Ok(Self::Square(abi::lift_case::<Self, _>(abi, flat)?)),
+                2 => Ok(Self::Rectangle(abi::lift_case::<Self, _>(abi, flat)?)),
+                3 => {
+                    abi::skip_case::<Self>(flat);
+                    Ok(Self::Empty)
+                }
+                discriminant => Err(Error::InvalidDiscriminant(discriminant)),
+            }
+        }
+
+        fn load(abi: &Abi, offset: u32) -> Result<Self, Error> {
+            match abi::load_discriminant(abi, offset, 1)? {
+                0 => Ok(Self::Circle(Lift::load(abi, offset + 8)?)),
+                1 => Ok(Self::Square(Lift::load(abi, offset + 8)?)),
+                2 => Ok(Self::Rectangle(Lift::load(abi, offset + 8)?)),
+                3 => Ok(Self::Empty),
+                discriminant => Err(Error::InvalidDiscriminant(discriminant)),
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Color {
+        Red,
+        Green,
+        Blue,
+    }
+
+    impl ComponentType for Color {
+        const SIZE: usize = 1;
+        const ALIGN: usize = 1;
+
+        fn push_flat_types(out: &mut Vec<FlatType>) {
+            out.push(FlatType::I32);
+        }
+    }
+
+    impl Lower for Color {
+        fn lower(&self, _abi: &Abi, out: &mut Vec<FlatValue>) -> Result<(), Error> {
+            let discriminant = match self {
+                Self::Red => 0,
+                Self::Green => 1,
+                Self::Blue => 2,
+            };
+            abi::lower_case::<Self>(out, discriminant, Vec::new());
+            Ok(())
+        }
+
+        fn store(&self, abi: &Abi, offset: u32) -> Result<(), Error> {
+            match self {
+                Self::Red => abi::store_discriminant(abi, offset, 1, 0),
+                Self::Green => abi::store_discriminant(abi, offset, 1, 1),
+                Self::Blue => abi::store_discriminant(abi, offset, 1, 2),
+            }
+        }
+    }
+
+    impl Lift for Color {
+        fn lift(_abi: &Abi, flat: &mut Flat) -> Result<Self, Error> {
+            match abi::lift_discriminant(flat) {
+                0 => {
+                    abi::skip_case::<Self>(flat);
+                    Ok(Self::Red)
+                }
+                1 => {
+                    abi::skip_case::<Self>(flat);
+                    Ok(Self::Green)
+                }
+                2 => {
+                    abi::skip_case::<Self>(flat);
+                    Ok(Self::Blue)
+                }
+                discriminant => Err(Error::InvalidDiscriminant(discriminant)),
+            }
+        }
+
+        fn load(abi: &Abi, offset: u32) -> Result<Self, Error> {
+            match abi::load_discriminant(abi, offset, 1)? {
+                0 => Ok(Self::Red),
+                1 => Ok(Self::Green),
+                2 => Ok(Self::Blue),
+                discriminant => Err(Error::InvalidDiscriminant(discriminant)),
+            }
+        }
+    }
+
+    /// Calls the functions of the `guest` interface exported by a guest.
+    pub struct Guest {
+        abi: Abi,
+        greet: NativeFunc<(i32, i32), i32>,
+        translate: NativeFunc<(i32, i32, i32, i32), i32>,
+        area: NativeFunc<(i32, i64, i32), f64>,
+        sum: NativeFunc<(i32, i32), i64>,
+        find: NativeFunc<(i32, i32, i32, i32), i32>,
+        parse_color: NativeFunc<(i32, i32), i32>,
+        colors: NativeFunc<(), i32>,
+        next_char: NativeFunc<i32, i32>,
+        sum_points: NativeFunc<i32, i32>,
+    }
+
+    impl Guest {
+        /// Look up the exports of `instance`.
+        pub fn new(instance: &Instance) -> Result<Self, Error> {
+            Ok(Self {
+                abi: Abi::new(instance)?,
+                greet: instance.exports.get_native_function("greet")?,
+                translate: instance.exports.get_native_function("translate")?,
+                area: instance.exports.get_native_function("area")?,
+                sum: instance.exports.get_native_function("sum")?,
+                find: instance.exports.get_native_function("find")?,
+                parse_color: instance.exports.get_native_function("parse-color")?,
+                colors: instance.exports.get_native_function("colors")?,
+                next_char: instance.exports.get_native_function("next-char")?,
+                sum_points: instance.exports.get_native_function("sum-points")?,
+            })
+        }
+
+        pub fn greet(&self, name: &str) -> Result<String, Error> {
+            let mut params = Vec::new();
+            name.lower(&self.abi, &mut params)?;
+            let ret = self.greet.call(params[0].as_i32(), params[1].as_i32())?;
+            abi::load_result(&self.abi, ret)
+        }
+
+        pub fn translate(&self, p: &Point, dx: i32, dy: i32) -> Result<Point, Error> {
+            let mut params = Vec::new();
+            p.lower(&self.abi, &mut params)?;
+            dx.lower(&self.abi, &mut params)?;
+            dy.lower(&self.abi, &mut params)?;
+            let ret = self.translate.call(
+                params[0].as_i32(),
+                params[1].as_i32(),
+                params[2].as_i32(),
+                params[3].as_i32(),
+            )?;
+            abi::load_result(&self.abi, ret)
+        }
+
+        pub fn area(&self, shape: &Shape) -> Result<f64, Error> {
+            let mut params = Vec::new();
+            shape.lower(&self.abi, &mut params)?;
+            let ret = self.area.call(params[0].as_i32(), params[1].as_i64(), params[2].as_i32())?;
+            abi::lift_result(&self.abi, ret)
+        }
+
+        pub fn sum(&self, values: &[u32]) -> Result<u64, Error> {
+            let mut params = Vec::new();
+            values.lower(&self.abi, &mut params)?;
+            let ret = self.sum.call(params[0].as_i32(), params[1].as_i32())?;
+            abi::lift_result(&self.abi, ret)
+        }
+
+        pub fn find(&self, haystack: &[String], needle: &str) -> Result<Option<u32>, Error> {
+            let mut params = Vec::new();
+            haystack.lower(&self.abi, &mut params)?;
+            needle.lower(&self.abi, &mut params)?;
+            let ret = self.find.call(
+                params[0].as_i32(),
+                params[1].as_i32(),
+                params[2].as_i32(),
+                params[3].as_i32(),
+            )?;
+            abi::load_result(&self.abi, ret)
+        }
+
+        pub fn parse_color(&self, name: &str) -> Result<Result<Color, String>, Error> {
+            let mut params = Vec::new();
+            name.lower(&self.abi, &mut params)?;
+            let ret = self.parse_color.call(params[0].as_i32(), params[1].as_i32())?;
+            abi::load_result(&self.abi, ret)
+        }
+
+        pub fn colors(&self) -> Result<Vec<Color>, Error> {
+            let ret = self.colors.call()?;
+            abi::load_result(&self.abi, ret)
+        }
+
+        pub fn next_char(&self, c: char) -> Result<char, Error> {
+            let mut params = Vec::new();
+            c.lower(&self.abi, &mut params)?;
+            let ret = self.next_char.call(params[0].as_i32())?;
+            abi::lift_result(&self.abi, ret)
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        pub fn sum_points(
+            &self,
+            a: &Point,
+            b: &Point,
+            c: &Point,
+            d: &Point,
+            e: &Point,
+            f: &Point,
+            g: &Point,
+            h: &Point,
+            i: &Point,
+        ) -> Result<i32, Error> {
+            let params = self.abi.alloc(72, 4)?;
+            a.store(&self.abi, params)?;
+            b.store(&self.abi, params + 8)?;
+            c.store(&self.abi, params + 16)?;
+            d.store(&self.abi, params + 24)?;
+            e.store(&self.abi, params + 32)?;
+            f.store(&self.abi, params + 40)?;
+            g.store(&self.abi, params + 48)?;
+            h.store(&self.abi, params + 56)?;
+            i.store(&self.abi, params + 64)?;
+            let ret = self.sum_points.call(params as i32)?;
+            abi::lift_result(&self.abi, ret)
+        }
+    }
+}
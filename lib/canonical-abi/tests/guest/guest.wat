@@ -0,0 +1,166 @@
+;; A guest implementing `guest.wit`, which allocates with a bump allocator
+;; and counts the bytes the host frees.
+(module
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 1024))
+  (global $freed (mut i32) (i32.const 0))
+
+  ;; The results returned in memory are at 16.
+  (data (i32.const 64) "\00\02\01")
+  (data (i32.const 80) "Hello, ")
+  (data (i32.const 96) "redgreenblue")
+
+  (func $alloc (param $align i32) (param $size i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr
+      (i32.and
+        (i32.add (global.get $heap) (i32.sub (local.get $align) (i32.const 1)))
+        (i32.sub (i32.const 0) (local.get $align))))
+    (global.set $heap (i32.add (local.get $ptr) (local.get $size)))
+    (local.get $ptr))
+
+  (func $copy (param $dst i32) (param $src i32) (param $len i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $len)))
+        (i32.store8 (local.get $dst) (i32.load8_u (local.get $src)))
+        (local.set $dst (i32.add (local.get $dst) (i32.const 1)))
+        (local.set $src (i32.add (local.get $src) (i32.const 1)))
+        (local.set $len (i32.sub (local.get $len) (i32.const 1)))
+        (br $next))))
+
+  (func $eq (param $a i32) (param $a_len i32) (param $b i32) (param $b_len i32) (result i32)
+    (if (i32.ne (local.get $a_len) (local.get $b_len))
+      (then (return (i32.const 0))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $a_len)))
+        (if (i32.ne (i32.load8_u (local.get $a)) (i32.load8_u (local.get $b)))
+          (then (return (i32.const 0))))
+        (local.set $a (i32.add (local.get $a) (i32.const 1)))
+        (local.set $b (i32.add (local.get $b) (i32.const 1)))
+        (local.set $a_len (i32.sub (local.get $a_len) (i32.const 1)))
+        (br $next)))
+    (i32.const 1))
+
+  (func (export "canonical_abi_realloc")
+    (param $old i32) (param $old_size i32) (param $align i32) (param $size i32) (result i32)
+    (call $alloc (local.get $align) (local.get $size)))
+
+  (func (export "canonical_abi_free") (param $ptr i32) (param $size i32) (param $align i32)
+    (global.set $freed (i32.add (global.get $freed) (local.get $size))))
+
+  (func (export "freed") (result i32)
+    (global.get $freed))
+
+  (func (export "greet") (param $ptr i32) (param $len i32) (result i32)
+    (local $out i32)
+    (local.set $out (call $alloc (i32.const 1) (i32.add (local.get $len) (i32.const 8))))
+    (call $copy (local.get $out) (i32.const 80) (i32.const 7))
+    (call $copy (i32.add (local.get $out) (i32.const 7)) (local.get $ptr) (local.get $len))
+    (i32.store8
+      (i32.add (i32.add (local.get $out) (i32.const 7)) (local.get $len))
+      (i32.const 33))
+    (i32.store (i32.const 16) (local.get $out))
+    (i32.store (i32.const 20) (i32.add (local.get $len) (i32.const 8)))
+    (i32.const 16))
+
+  (func (export "translate") (param $x i32) (param $y i32) (param $dx i32) (param $dy i32) (result i32)
+    (i32.store (i32.const 16) (i32.add (local.get $x) (local.get $dx)))
+    (i32.store (i32.const 20) (i32.add (local.get $y) (local.get $dy)))
+    (i32.const 16))
+
+  ;; The cases of `shape` flatten to `f64`, `f32` and `i32 i32`, which are
+  ;; joined to `i64 i32`.
+  (func (export "area") (param $case i32) (param $a i64) (param $b i32) (result f64)
+    (local $side f64)
+    (block $empty
+      (block $rectangle
+        (block $square
+          (block $circle
+            (br_table $circle $square $rectangle $empty (local.get $case)))
+          ;; Close enough.
+          (local.set $side (f64.reinterpret_i64 (local.get $a)))
+          (return (f64.mul (f64.const 3) (f64.mul (local.get $side) (local.get $side)))))
+        (local.set $side (f64.promote_f32 (f32.reinterpret_i32 (i32.wrap_i64 (local.get $a)))))
+        (return (f64.mul (local.get $side) (local.get $side))))
+      (return (f64.convert_i32_s (i32.mul (i32.wrap_i64 (local.get $a)) (local.get $b)))))
+    (f64.const 0))
+
+  (func (export "sum") (param $ptr i32) (param $len i32) (result i64)
+    (local $sum i64)
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $len)))
+        (local.set $sum (i64.add (local.get $sum) (i64.load32_u (local.get $ptr))))
+        (local.set $ptr (i32.add (local.get $ptr) (i32.const 4)))
+        (local.set $len (i32.sub (local.get $len) (i32.const 1)))
+        (br $next)))
+    (local.get $sum))
+
+  (func (export "find")
+    (param $list i32) (param $len i32) (param $needle i32) (param $needle_len i32) (result i32)
+    (local $i i32)
+    (local $string i32)
+    (i32.store8 (i32.const 16) (i32.const 0))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eq (local.get $i) (local.get $len)))
+        (local.set $string (i32.add (local.get $list) (i32.mul (local.get $i) (i32.const 8))))
+        (if (call $eq
+              (i32.load (local.get $string))
+              (i32.load offset=4 (local.get $string))
+              (local.get $needle)
+              (local.get $needle_len))
+          (then
+            (i32.store8 (i32.const 16) (i32.const 1))
+            (i32.store (i32.const 20) (local.get $i))
+            (br $done)))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (i32.const 16))
+
+  (func $color (param $ptr i32) (param $len i32) (param $color i32) (param $name i32) (param $name_len i32) (result i32)
+    (if (result i32) (call $eq (local.get $ptr) (local.get $len) (local.get $name) (local.get $name_len))
+      (then
+        (i32.store8 (i32.const 16) (i32.const 0))
+        (i32.store8 (i32.const 20) (local.get $color))
+        (i32.const 1))
+      (else (i32.const 0))))
+
+  ;; Fails with a copy of the name.
+  (func (export "parse-color") (param $ptr i32) (param $len i32) (result i32)
+    (local $out i32)
+    (if (i32.or
+          (call $color (local.get $ptr) (local.get $len) (i32.const 0) (i32.const 96) (i32.const 3))
+          (i32.or
+            (call $color (local.get $ptr) (local.get $len) (i32.const 1) (i32.const 99) (i32.const 5))
+            (call $color (local.get $ptr) (local.get $len) (i32.const 2) (i32.const 104) (i32.const 4))))
+      (then (return (i32.const 16))))
+    (local.set $out (call $alloc (i32.const 1) (local.get $len)))
+    (call $copy (local.get $out) (local.get $ptr) (local.get $len))
+    (i32.store8 (i32.const 16) (i32.const 1))
+    (i32.store (i32.const 20) (local.get $out))
+    (i32.store (i32.const 24) (local.get $len))
+    (i32.const 16))
+
+  (func (export "colors") (result i32)
+    (i32.store (i32.const 16) (i32.const 64))
+    (i32.store (i32.const 20) (i32.const 3))
+    (i32.const 16))
+
+  (func (export "next-char") (param $c i32) (result i32)
+    (i32.add (local.get $c) (i32.const 1)))
+
+  ;; The 9 points flatten to 18 values, which are passed in memory.
+  (func (export "sum-points") (param $ptr i32) (result i32)
+    (local $sum i32)
+    (local $end i32)
+    (local.set $end (i32.add (local.get $ptr) (i32.const 72)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eq (local.get $ptr) (local.get $end)))
+        (local.set $sum (i32.add (local.get $sum) (i32.load (local.get $ptr))))
+        (local.set $ptr (i32.add (local.get $ptr) (i32.const 4)))
+        (br $next)))
+    (local.get $sum)))
@@ -4,6 +4,8 @@
 use crate::commands::Compile;
 #[cfg(all(feature = "object-file", feature = "compiler"))]
 use crate::commands::CreateExe;
+#[cfg(feature = "compiler")]
+use crate::commands::Preinit;
 #[cfg(feature = "wast")]
 use crate::commands::Wast;
 use crate::commands::{Cache, Config, Inspect, Run, SelfUpdate, Validate};
@@ -49,6 +51,13 @@ enum WasmerCLIOptions {
     #[structopt(name = "create-exe")]
     CreateExe(CreateExe),
 
+    /// Pre-initialize a WebAssembly binary: run its init function and
+    /// write its resulting state in the data segments and globals of a
+    /// new binary
+    #[cfg(feature = "compiler")]
+    #[structopt(name = "preinit")]
+    Preinit(Preinit),
+
     /// Get various configuration information needed
     /// to compile programs which use Wasmer
     #[structopt(name = "config")]
@@ -79,6 +88,8 @@ impl WasmerCLIOptions {
             Self::Compile(compile) => compile.execute(),
             #[cfg(all(feature = "object-file", feature = "compiler"))]
             Self::CreateExe(create_exe) => create_exe.execute(),
+            #[cfg(feature = "compiler")]
+            Self::Preinit(preinit) => preinit.execute(),
             Self::Config(config) => config.execute(),
             Self::Inspect(inspect) => inspect.execute(),
             #[cfg(feature = "wast")]
@@ -6,6 +6,8 @@ mod config;
 #[cfg(all(feature = "object-file", feature = "compiler"))]
 mod create_exe;
 mod inspect;
+#[cfg(feature = "compiler")]
+mod preinit;
 mod run;
 mod self_update;
 mod validate;
@@ -16,6 +18,8 @@ mod wast;
 pub use compile::*;
 #[cfg(all(feature = "object-file", feature = "compiler"))]
 pub use create_exe::*;
+#[cfg(feature = "compiler")]
+pub use preinit::*;
 #[cfg(feature = "wast")]
 pub use wast::*;
 pub use {cache::*, config::*, inspect::*, run::*, self_update::*, validate::*};
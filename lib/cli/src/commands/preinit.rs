@@ -0,0 +1,114 @@
+#[cfg(feature = "wasi")]
+use super::run::Wasi;
+use crate::store::StoreOptions;
+use anyhow::{bail, Context, Result};
+use std::path::PathBuf;
+use structopt::StructOpt;
+use wasmer::*;
+
+mod rewrite;
+
+#[derive(Debug, StructOpt)]
+/// The options for the `wasmer preinit` subcommand
+pub struct Preinit {
+    /// Input file
+    #[structopt(name = "FILE", parse(from_os_str))]
+    path: PathBuf,
+
+    /// Output file
+    #[structopt(name = "OUTPUT PATH", short = "o", parse(from_os_str))]
+    output: PathBuf,
+
+    /// The exported function initializing the module
+    #[structopt(long = "init-func", name = "NAME")]
+    init_func: String,
+
+    /// Keep the export of the init function in the output file
+    #[structopt(long = "keep-init-func")]
+    keep_init_func: bool,
+
+    #[structopt(flatten)]
+    store: StoreOptions,
+
+    #[cfg(feature = "wasi")]
+    #[structopt(flatten)]
+    wasi: Wasi,
+
+    /// Application arguments, for WASI modules
+    #[cfg(feature = "wasi")]
+    #[structopt(name = "--", multiple = true)]
+    args: Vec<String>,
+}
+
+impl Preinit {
+    /// Runs logic for the `preinit` subcommand
+    pub fn execute(&self) -> Result<()> {
+        self.inner_execute().context(format!(
+            "failed to pre-initialize `{}`",
+            self.path.display()
+        ))
+    }
+
+    fn inner_execute(&self) -> Result<()> {
+        let contents = std::fs::read(&self.path)?;
+        #[cfg(feature = "wat")]
+        let contents = wat2wasm(&contents)?.into_owned();
+        let (store, _engine_type, _compiler_type) = self.store.get_store()?;
+        let module = Module::new(&store, &contents)?;
+        if module.info().num_imported_memories > 0 {
+            bail!("the module imports a memory, which can't be pre-initialized");
+        }
+
+        let instance = self.instantiate(&module)?;
+        let initial = instance.snapshot()?;
+        let init_func = instance
+            .exports
+            .get_native_function::<(), ()>(&self.init_func)
+            .with_context(|| format!("can't find the init function `{}`", self.init_func))?;
+        init_func
+            .call()
+            .with_context(|| format!("the init function `{}` failed", self.init_func))?;
+        let snapshot = instance.snapshot()?;
+        // Element segments aren't rewritten.
+        if snapshot.tables != initial.tables
+            || snapshot.dropped_elements != initial.dropped_elements
+        {
+            bail!("the init function modified a table, which can't be pre-initialized");
+        }
+
+        let output = rewrite::rewrite(
+            &contents,
+            &snapshot,
+            module.info().num_imported_globals,
+            if self.keep_init_func {
+                None
+            } else {
+                Some(&self.init_func)
+            },
+        )?;
+        Module::validate(&store, &output).context("the pre-initialized module is invalid")?;
+        std::fs::write(&self.output, output)?;
+        eprintln!(
+            "✔ File pre-initialized successfully to `{}`.",
+            self.output.display(),
+        );
+        Ok(())
+    }
+
+    fn instantiate(&self, module: &Module) -> Result<Instance> {
+        #[cfg(feature = "wasi")]
+        {
+            if Wasi::has_wasi_imports(module) {
+                let program_name = self
+                    .path
+                    .file_name()
+                    .map(|f| f.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                return self
+                    .wasi
+                    .instantiate(module, program_name, self.args.clone());
+            }
+        }
+        Ok(Instance::new(module, &imports! {})?)
+    }
+}
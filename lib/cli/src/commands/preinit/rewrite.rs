@@ -0,0 +1,344 @@
+//! Rewrites a WebAssembly module so that its data segments and the
+//! initializers of its globals hold the state of an instance.
+
+use anyhow::{bail, Context, Result};
+use wasmer::InstanceSnapshot;
+use wasmer_compiler::wasmparser::{
+    DataKind, DataSectionReader, ExportSectionReader, GlobalSectionReader, MemorySectionReader,
+    MemoryType,
+};
+use wasmer_types::entity::EntityRef;
+use wasmer_types::{DataIndex, GlobalIndex, MemoryIndex, Type};
+
+const SECTION_MEMORY: u8 = 5;
+const SECTION_GLOBAL: u8 = 6;
+const SECTION_EXPORT: u8 = 7;
+const SECTION_START: u8 = 8;
+const SECTION_DATA: u8 = 11;
+const SECTION_DATA_COUNT: u8 = 12;
+
+const OP_END: u8 = 0x0b;
+const OP_I32_CONST: u8 = 0x41;
+const OP_I64_CONST: u8 = 0x42;
+const OP_F32_CONST: u8 = 0x43;
+const OP_F64_CONST: u8 = 0x44;
+const OP_SIMD_PREFIX: u8 = 0xfd;
+const OP_V128_CONST: u8 = 0x0c;
+
+/// A section of a module.
+struct Section<'a> {
+    id: u8,
+    /// The offset of the contents in the module.
+    offset: usize,
+    contents: &'a [u8],
+}
+
+/// Rewrite the module `wasm`, instantiated and initialized into the
+/// state of `snapshot`:
+///
+/// - the memories start with the size they have in the snapshot,
+/// - the active data segments are replaced with the contents of the
+///   memories, and the dropped passive ones are emptied,
+/// - the mutable globals are initialized with their value,
+/// - the start function, which already ran, is removed, as well as
+///   the export named `removed_export`, if any.
+///
+/// The module mustn't import any memory, which wouldn't be part of the
+/// snapshot.
+pub(crate) fn rewrite(
+    wasm: &[u8],
+    snapshot: &InstanceSnapshot,
+    imported_globals: usize,
+    removed_export: Option<&str>,
+) -> Result<Vec<u8>> {
+    let sections = parse_sections(wasm)?;
+    let (data_count, data) = rewrite_data(
+        sections.iter().find(|section| section.id == SECTION_DATA),
+        snapshot,
+    )?;
+    let mut data = Some(data);
+
+    let mut output = wasm[..8].to_vec();
+    for section in &sections {
+        let contents = match section.id {
+            SECTION_MEMORY => rewrite_memories(section, snapshot)?,
+            SECTION_GLOBAL => rewrite_globals(section, snapshot, imported_globals)?,
+            SECTION_EXPORT => match removed_export {
+                Some(name) => remove_export(section, name)?,
+                None => section.contents.to_vec(),
+            },
+            SECTION_START => continue,
+            SECTION_DATA_COUNT => {
+                let mut contents = Vec::new();
+                write_u32(&mut contents, data_count);
+                contents
+            }
+            SECTION_DATA => data.take().unwrap(),
+            _ => section.contents.to_vec(),
+        };
+        write_section(&mut output, section.id, &contents);
+    }
+    // The data section is the last one, besides custom sections.
+    if let Some(data) = data {
+        if data_count > 0 {
+            write_section(&mut output, SECTION_DATA, &data);
+        }
+    }
+    Ok(output)
+}
+
+fn parse_sections(wasm: &[u8]) -> Result<Vec<Section<'_>>> {
+    if wasm.len() < 8 || &wasm[..4] != b"\0asm" {
+        bail!("the module isn't a WebAssembly binary");
+    }
+    let mut sections = Vec::new();
+    let mut offset = 8;
+    while offset < wasm.len() {
+        let id = wasm[offset];
+        let (size, size_len) = read_u32(&wasm[offset + 1..]).context("malformed section")?;
+        let start = offset + 1 + size_len;
+        let end = start
+            .checked_add(size as usize)
+            .filter(|end| *end <= wasm.len())
+            .context("malformed section")?;
+        sections.push(Section {
+            id,
+            offset: start,
+            contents: &wasm[start..end],
+        });
+        offset = end;
+    }
+    Ok(sections)
+}
+
+fn rewrite_memories(section: &Section, snapshot: &InstanceSnapshot) -> Result<Vec<u8>> {
+    let mut reader = MemorySectionReader::new(section.contents, section.offset)?;
+    let mut contents = Vec::new();
+    write_u32(&mut contents, reader.get_count());
+    for index in 0..reader.get_count() {
+        let (limits, shared) = match reader.read()? {
+            MemoryType::M32 { limits, shared } => (limits, shared),
+            MemoryType::M64 { .. } => bail!("64-bit memories aren't supported"),
+        };
+        let pages = snapshot
+            .memories
+            .iter()
+            .find(|memory| memory.index == MemoryIndex::from_u32(index))
+            .map_or(limits.initial, |memory| memory.pages.0);
+        contents.push(limits.maximum.is_some() as u8 | (shared as u8) << 1);
+        write_u32(&mut contents, pages);
+        if let Some(maximum) = limits.maximum {
+            write_u32(&mut contents, maximum);
+        }
+    }
+    Ok(contents)
+}
+
+fn rewrite_globals(
+    section: &Section,
+    snapshot: &InstanceSnapshot,
+    imported_globals: usize,
+) -> Result<Vec<u8>> {
+    let mut reader = GlobalSectionReader::new(section.contents, section.offset)?;
+    let mut contents = Vec::new();
+    write_u32(&mut contents, reader.get_count());
+    for index in 0..reader.get_count() as usize {
+        let start = reader.original_position() - section.offset;
+        reader.read()?;
+        let end = reader.original_position() - section.offset;
+        let global = &section.contents[start..end];
+
+        let index = GlobalIndex::new(imported_globals + index);
+        match snapshot.globals.iter().find(|global| global.index == index) {
+            Some(snapshot) => {
+                // The value type and the mutability.
+                contents.extend_from_slice(&global[..2]);
+                write_const(&mut contents, snapshot.ty, snapshot.value)?;
+            }
+            None => contents.extend_from_slice(global),
+        }
+    }
+    Ok(contents)
+}
+
+fn remove_export(section: &Section, name: &str) -> Result<Vec<u8>> {
+    let mut reader = ExportSectionReader::new(section.contents, section.offset)?;
+    let mut exports = Vec::new();
+    let mut count = 0;
+    for _ in 0..reader.get_count() {
+        let start = reader.original_position() - section.offset;
+        let export = reader.read()?;
+        let end = reader.original_position() - section.offset;
+        if export.field != name {
+            exports.extend_from_slice(&section.contents[start..end]);
+            count += 1;
+        }
+    }
+    let mut contents = Vec::new();
+    write_u32(&mut contents, count);
+    contents.extend(exports);
+    Ok(contents)
+}
+
+/// Returns the number of data segments, and the contents of the data
+/// section.
+fn rewrite_data(section: Option<&Section>, snapshot: &InstanceSnapshot) -> Result<(u32, Vec<u8>)> {
+    let mut segments = Vec::new();
+    let mut count = 0;
+    if let Some(section) = section {
+        let mut reader = DataSectionReader::new(section.contents, section.offset)?;
+        for index in 0..reader.get_count() {
+            let data = reader.read()?;
+            match data.kind {
+                // The segments keep their index, for `memory.init`
+                // and `data.drop`.
+                DataKind::Passive => {
+                    segments.push(0x01);
+                    if snapshot.dropped_data.contains(&DataIndex::from_u32(index)) {
+                        write_u32(&mut segments, 0);
+                    } else {
+                        write_bytes(&mut segments, data.data);
+                    }
+                }
+                // The contents of the memories replace the active
+                // segments, which are dropped after the instantiation.
+                DataKind::Active { memory_index, .. } => {
+                    write_active_segment(&mut segments, memory_index, 0, &[]);
+                }
+            }
+            count += 1;
+        }
+    }
+    for memory in &snapshot.memories {
+        for segment in &memory.segments {
+            write_active_segment(
+                &mut segments,
+                memory.index.as_u32(),
+                segment.offset as u32,
+                &segment.data,
+            );
+            count += 1;
+        }
+    }
+
+    let mut contents = Vec::new();
+    write_u32(&mut contents, count);
+    contents.extend(segments);
+    Ok((count, contents))
+}
+
+fn write_active_segment(out: &mut Vec<u8>, memory_index: u32, offset: u32, data: &[u8]) {
+    if memory_index == 0 {
+        out.push(0x00);
+    } else {
+        out.push(0x02);
+        write_u32(out, memory_index);
+    }
+    out.push(OP_I32_CONST);
+    write_i64(out, offset as i32 as i64);
+    out.push(OP_END);
+    write_bytes(out, data);
+}
+
+/// Write the constant expression of the value of a global, as stored
+/// in an [`InstanceSnapshot`].
+fn write_const(out: &mut Vec<u8>, ty: Type, value: u128) -> Result<()> {
+    match ty {
+        Type::I32 => {
+            out.push(OP_I32_CONST);
+            write_i64(out, value as u32 as i32 as i64);
+        }
+        Type::I64 => {
+            out.push(OP_I64_CONST);
+            write_i64(out, value as u64 as i64);
+        }
+        Type::F32 => {
+            out.push(OP_F32_CONST);
+            out.extend_from_slice(&(value as u32).to_le_bytes());
+        }
+        Type::F64 => {
+            out.push(OP_F64_CONST);
+            out.extend_from_slice(&(value as u64).to_le_bytes());
+        }
+        Type::V128 => {
+            out.extend_from_slice(&[OP_SIMD_PREFIX, OP_V128_CONST]);
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        ty => bail!("globals of type {} can't be pre-initialized", ty),
+    }
+    out.push(OP_END);
+    Ok(())
+}
+
+fn write_section(out: &mut Vec<u8>, id: u8, contents: &[u8]) {
+    out.push(id);
+    write_bytes(out, contents);
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_u32(out, bytes.len() as u32);
+    out.extend_from_slice(bytes);
+}
+
+fn write_u32(out: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_i64(out: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Returns the value and the number of bytes read.
+fn read_u32(bytes: &[u8]) -> Option<(u32, usize)> {
+    let mut value = 0u32;
+    for (index, byte) in bytes.iter().take(5).enumerate() {
+        value |= ((byte & 0x7f) as u32) << (7 * index);
+        if byte & 0x80 == 0 {
+            return Some((value, index + 1));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leb128() {
+        for value in &[0, 1, 63, 64, 127, 128, 624_485, u32::MAX] {
+            let mut bytes = Vec::new();
+            write_u32(&mut bytes, *value);
+            assert_eq!(read_u32(&bytes), Some((*value, bytes.len())));
+        }
+
+        let encode = |value| {
+            let mut bytes = Vec::new();
+            write_i64(&mut bytes, value);
+            bytes
+        };
+        assert_eq!(encode(0), [0x00]);
+        assert_eq!(encode(63), [0x3f]);
+        assert_eq!(encode(64), [0xc0, 0x00]);
+        assert_eq!(encode(-1), [0x7f]);
+        assert_eq!(encode(-64), [0x40]);
+        assert_eq!(encode(-65), [0xbf, 0x7f]);
+        assert_eq!(encode(-123_456), [0xc0, 0xbb, 0x78]);
+    }
+}
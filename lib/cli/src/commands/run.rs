@@ -1,4 +1,4 @@
-use crate::common::get_cache_dir;
+use crate::common::{get_cache_dir, get_cache_max_size};
 #[cfg(feature = "debug")]
 use crate::logging;
 use crate::store::{CompilerType, EngineType, StoreOptions};
@@ -17,7 +17,7 @@ use structopt::StructOpt;
 mod wasi;
 
 #[cfg(feature = "wasi")]
-use wasi::Wasi;
+pub(crate) use wasi::Wasi;
 
 #[derive(Debug, StructOpt, Clone)]
 /// The options for the `wasmer run` subcommand
@@ -236,18 +236,25 @@ impl Run {
         // as it takes space and the speedup is minimal.
         let mut cache = self.get_cache(engine_type, compiler_type)?;
         // Try to get the hash from the provided `--cache-key`, otherwise
-        // generate one from the provided file `.wasm` contents.
-        let hash = self
+        // generate one from the provided file `.wasm` contents and from
+        // the settings of the engine. The modules of an engine without
+        // a fingerprint aren't cached.
+        let hash = match self
             .cache_key
             .as_ref()
             .and_then(|key| Hash::from_str(&key).ok())
-            .unwrap_or_else(|| Hash::generate(&contents));
+            .or_else(|| Hash::generate_for_engine(&**store.engine(), &contents))
+        {
+            Some(hash) => hash,
+            None => return Ok(Module::new(&store, &contents)?),
+        };
         match unsafe { cache.load(&store, hash) } {
             Ok(module) => Ok(module),
             Err(e) => {
                 match e {
-                    DeserializeError::Io(_) => {
-                        // Do not notify on IO errors
+                    DeserializeError::Io(_) | DeserializeError::Incompatible { .. } => {
+                        // Do not notify on IO errors, nor on modules cached
+                        // by other versions of Wasmer
                     }
                     err => {
                         warning!("cached module is corrupted: {}", err);
@@ -289,6 +296,7 @@ impl Run {
             _ => compiler_type.to_string(),
         };
         cache.set_cache_extension(Some(extension));
+        cache.set_max_size(get_cache_max_size()?);
         Ok(cache)
     }
 
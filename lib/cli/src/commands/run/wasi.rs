@@ -43,8 +43,13 @@ impl Wasi {
         get_wasi_version(&module, false).is_some()
     }
 
-    /// Helper function for executing Wasi from the `Run` command.
-    pub fn execute(&self, module: Module, program_name: String, args: Vec<String>) -> Result<()> {
+    /// Helper function for instantiating a module with the WASI imports.
+    pub fn instantiate(
+        &self,
+        module: &Module,
+        program_name: String,
+        args: Vec<String>,
+    ) -> Result<Instance> {
         let args = args.iter().cloned().map(|arg| arg.into_bytes());
 
         let mut wasi_state_builder = WasiState::new(program_name);
@@ -63,8 +68,13 @@ impl Wasi {
         }
 
         let mut wasi_env = wasi_state_builder.finalize()?;
-        let import_object = wasi_env.import_object(&module)?;
-        let instance = Instance::new(&module, &import_object)?;
+        let import_object = wasi_env.import_object(module)?;
+        Ok(Instance::new(module, &import_object)?)
+    }
+
+    /// Helper function for executing Wasi from the `Run` command.
+    pub fn execute(&self, module: Module, program_name: String, args: Vec<String>) -> Result<()> {
+        let instance = self.instantiate(&module, program_name, args)?;
 
         let start = instance.exports.get_function("_start")?;
         let result = start.call(&[]);
@@ -1,6 +1,7 @@
 //! Common module with common used structures across different
 //! commands.
 use crate::VERSION;
+use anyhow::{Context, Result};
 use std::env;
 use std::path::PathBuf;
 use structopt::StructOpt;
@@ -51,3 +52,14 @@ pub fn get_cache_dir() -> PathBuf {
         }
     }
 }
+
+/// Get the maximum size of the cache of each compiler, in bytes, if
+/// `WASMER_CACHE_MAX_SIZE` sets one
+pub fn get_cache_max_size() -> Result<Option<u64>> {
+    match env::var("WASMER_CACHE_MAX_SIZE") {
+        Ok(size) => Ok(Some(size.parse().with_context(|| {
+            format!("invalid WASMER_CACHE_MAX_SIZE `{}`", size)
+        })?)),
+        Err(_) => Ok(None),
+    }
+}
@@ -3,7 +3,8 @@ use cranelift_codegen::isa::{lookup, TargetIsa};
 use cranelift_codegen::settings::{self, Configurable};
 use std::sync::Arc;
 use wasmer_compiler::{
-    Architecture, Compiler, CompilerConfig, CpuFeature, ModuleMiddleware, Target,
+    Architecture, Compiler, CompilerConfig, CpuFeature, ModuleMiddleware, ModuleMiddlewareChain,
+    Target,
 };
 
 // Runtime Environment
@@ -202,6 +203,18 @@ impl CompilerConfig for Cranelift {
     fn push_middleware(&mut self, middleware: Arc<dyn ModuleMiddleware>) {
         self.middlewares.push(middleware);
     }
+
+    fn fingerprint(&self) -> Option<String> {
+        Some(format!(
+            "cranelift(nan_canonicalization={}, verifier={}, simd={}, pic={}, opt_level={:?}, middlewares={})",
+            self.enable_nan_canonicalization,
+            self.enable_verifier,
+            self.enable_simd,
+            self.enable_pic,
+            self.opt_level,
+            self.middlewares.fingerprint()?,
+        ))
+    }
 }
 
 impl Default for Cranelift {
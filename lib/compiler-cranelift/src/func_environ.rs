@@ -2,7 +2,8 @@
 // Attributions: https://github.com/wasmerio/wasmer/blob/master/ATTRIBUTIONS.md
 
 use crate::translator::{
-    type_to_irtype, FuncEnvironment as BaseFuncEnvironment, GlobalVariable, TargetEnvironment,
+    type_to_irtype, FuncEnvironment as BaseFuncEnvironment, FuncTranslationState, GlobalVariable,
+    TargetEnvironment,
 };
 use cranelift_codegen::cursor::FuncCursor;
 use cranelift_codegen::ir;
@@ -13,8 +14,9 @@ use cranelift_codegen::ir::{AbiParam, ArgumentPurpose, Function, InstBuilder, Si
 use cranelift_codegen::isa::TargetFrontendConfig;
 use cranelift_frontend::FunctionBuilder;
 use std::convert::TryFrom;
+use std::{cmp, mem};
 use wasmer_compiler::wasmparser::Type;
-use wasmer_compiler::{WasmError, WasmResult};
+use wasmer_compiler::{wasm_unsupported, WasmError, WasmResult};
 use wasmer_types::entity::EntityRef;
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex};
@@ -73,6 +75,10 @@ pub struct FuncEnvironment<'module_environment> {
     /// (it's the same for both local and imported memories).
     memory_copy_sig: Option<ir::SigRef>,
 
+    /// The external function signature for implementing wasm's `memory.copy`
+    /// between two different memories.
+    memory_copy_between_sig: Option<ir::SigRef>,
+
     /// The external function signature for implementing wasm's `memory.fill`
     /// (it's the same for both local and imported memories).
     memory_fill_sig: Option<ir::SigRef>,
@@ -83,6 +89,30 @@ pub struct FuncEnvironment<'module_environment> {
     /// The external function signature for implementing wasm's `data.drop`.
     data_drop_sig: Option<ir::SigRef>,
 
+    /// The external function signature for implementing wasm's
+    /// `memory.atomic.wait32`.
+    memory_atomic_wait32_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's
+    /// `memory.atomic.wait64`.
+    memory_atomic_wait64_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's
+    /// `memory.atomic.notify`.
+    memory_atomic_notify_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's
+    /// `return_call` and `return_call_indirect`.
+    return_call_sig: Option<ir::SigRef>,
+
+    /// The external function signature for making the tail calls left by
+    /// the callee of a call.
+    finish_tail_calls_sig: Option<ir::SigRef>,
+
+    /// Whether the module may make tail calls, in which case calls are
+    /// followed by the completion of the tail calls left by their callee.
+    tail_calls: bool,
+
     /// Offsets to struct fields accessed by JIT code.
     offsets: VMOffsets,
 
@@ -100,6 +130,7 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         signatures: &'module_environment PrimaryMap<SignatureIndex, ir::Signature>,
         memory_styles: &'module_environment PrimaryMap<MemoryIndex, MemoryStyle>,
         table_styles: &'module_environment PrimaryMap<TableIndex, TableStyle>,
+        tail_calls: bool,
     ) -> Self {
         Self {
             target_config,
@@ -112,9 +143,16 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
             table_init_sig: None,
             elem_drop_sig: None,
             memory_copy_sig: None,
+            memory_copy_between_sig: None,
             memory_fill_sig: None,
             memory_init_sig: None,
             data_drop_sig: None,
+            memory_atomic_wait32_sig: None,
+            memory_atomic_wait64_sig: None,
+            memory_atomic_notify_sig: None,
+            return_call_sig: None,
+            finish_tail_calls_sig: None,
+            tail_calls,
             offsets: VMOffsets::new(target_config.pointer_bytes(), module),
             memory_styles,
             table_styles,
@@ -349,6 +387,30 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         }
     }
 
+    fn get_memory_copy_between_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.memory_copy_between_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Destination memory index.
+                    AbiParam::new(I32),
+                    // Source memory index.
+                    AbiParam::new(I32),
+                    // Destination address.
+                    AbiParam::new(I32),
+                    // Source address.
+                    AbiParam::new(I32),
+                    // Length.
+                    AbiParam::new(I32),
+                ],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.memory_copy_between_sig = Some(sig);
+        sig
+    }
+
     fn get_memory_fill_sig(&mut self, func: &mut Function) -> ir::SigRef {
         let sig = self.memory_fill_sig.unwrap_or_else(|| {
             func.import_signature(Signature {
@@ -447,6 +509,249 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
 
     /// Translates load of builtin function and returns a pair of values `vmctx`
     /// and address of the loaded function.
+    fn get_memory_atomic_wait_sig(&mut self, func: &mut Function, ty: ir::Type) -> ir::SigRef {
+        let cached = match ty {
+            I32 => self.memory_atomic_wait32_sig,
+            _ => self.memory_atomic_wait64_sig,
+        };
+        let sig = cached.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Memory index.
+                    AbiParam::new(I32),
+                    // Offset.
+                    AbiParam::new(I32),
+                    // Address.
+                    AbiParam::new(I64),
+                    // Expected value.
+                    AbiParam::new(ty),
+                    // Timeout.
+                    AbiParam::new(I64),
+                ],
+                returns: vec![AbiParam::new(I32)],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        match ty {
+            I32 => self.memory_atomic_wait32_sig = Some(sig),
+            _ => self.memory_atomic_wait64_sig = Some(sig),
+        }
+        sig
+    }
+
+    fn get_memory_atomic_notify_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.memory_atomic_notify_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Memory index.
+                    AbiParam::new(I32),
+                    // Offset.
+                    AbiParam::new(I32),
+                    // Address.
+                    AbiParam::new(I64),
+                    // Count.
+                    AbiParam::new(I32),
+                ],
+                returns: vec![AbiParam::new(I32)],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.memory_atomic_notify_sig = Some(sig);
+        sig
+    }
+
+    fn get_return_call_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.return_call_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Signature index.
+                    AbiParam::new(I32),
+                    // Callee vmctx.
+                    AbiParam::new(self.pointer_type()),
+                    // Callee address.
+                    AbiParam::new(self.pointer_type()),
+                    // Arguments.
+                    AbiParam::new(self.pointer_type()),
+                ],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.return_call_sig = Some(sig);
+        sig
+    }
+
+    fn get_finish_tail_calls_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.finish_tail_calls_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                // Results.
+                params: vec![AbiParam::new(self.pointer_type())],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.finish_tail_calls_sig = Some(sig);
+        sig
+    }
+
+    /// Translates the checks and loads of a `call_indirect` of the `callee`
+    /// element of `table`, and returns the address of the function and its
+    /// vmctx.
+    fn translate_load_indirect_callee(
+        &mut self,
+        pos: &mut FuncCursor<'_>,
+        table_index: TableIndex,
+        table: ir::Table,
+        sig_index: SignatureIndex,
+        callee: ir::Value,
+    ) -> (ir::Value, ir::Value) {
+        let pointer_type = self.pointer_type();
+
+        let table_entry_addr = pos.ins().table_addr(pointer_type, table, callee, 0);
+
+        // Dereference table_entry_addr to get the function address.
+        let mem_flags = ir::MemFlags::trusted();
+        let func_addr = pos.ins().load(
+            pointer_type,
+            mem_flags,
+            table_entry_addr,
+            i32::from(self.offsets.vmcaller_checked_anyfunc_func_ptr()),
+        );
+
+        // Check whether `func_addr` is null.
+        pos.ins().trapz(func_addr, ir::TrapCode::IndirectCallToNull);
+
+        // If necessary, check the signature.
+        match self.table_styles[table_index] {
+            TableStyle::CallerChecksSignature => {
+                let sig_id_size = self.offsets.size_of_vmshared_signature_index();
+                let sig_id_type = ir::Type::int(u16::from(sig_id_size) * 8).unwrap();
+                let vmctx = self.vmctx(pos.func);
+                let base = pos.ins().global_value(pointer_type, vmctx);
+                let offset =
+                    i32::try_from(self.offsets.vmctx_vmshared_signature_id(sig_index)).unwrap();
+
+                // Load the caller ID.
+                let mut mem_flags = ir::MemFlags::trusted();
+                mem_flags.set_readonly();
+                let caller_sig_id = pos.ins().load(sig_id_type, mem_flags, base, offset);
+
+                // Load the callee ID.
+                let mem_flags = ir::MemFlags::trusted();
+                let callee_sig_id = pos.ins().load(
+                    sig_id_type,
+                    mem_flags,
+                    table_entry_addr,
+                    i32::from(self.offsets.vmcaller_checked_anyfunc_type_index()),
+                );
+
+                // Check that they match.
+                let cmp = pos.ins().icmp(IntCC::Equal, callee_sig_id, caller_sig_id);
+                pos.ins().trapz(cmp, ir::TrapCode::BadSignature);
+            }
+        }
+
+        // Load the callee vmctx address.
+        let vmctx = pos.ins().load(
+            pointer_type,
+            mem_flags,
+            table_entry_addr,
+            i32::from(self.offsets.vmcaller_checked_anyfunc_vmctx()),
+        );
+
+        (func_addr, vmctx)
+    }
+
+    /// Translates the loads of the address and vmctx of the imported
+    /// function `callee_index`.
+    fn translate_load_imported_callee(
+        &mut self,
+        pos: &mut FuncCursor<'_>,
+        callee_index: FunctionIndex,
+    ) -> (ir::Value, ir::Value) {
+        let pointer_type = self.pointer_type();
+        let vmctx = self.vmctx(pos.func);
+        let base = pos.ins().global_value(pointer_type, vmctx);
+
+        let mem_flags = ir::MemFlags::trusted();
+
+        // Load the callee address.
+        let body_offset =
+            i32::try_from(self.offsets.vmctx_vmfunction_import_body(callee_index)).unwrap();
+        let func_addr = pos.ins().load(pointer_type, mem_flags, base, body_offset);
+
+        // Load the callee vmctx address.
+        let vmctx_offset =
+            i32::try_from(self.offsets.vmctx_vmfunction_import_vmctx(callee_index)).unwrap();
+        let vmctx = pos.ins().load(pointer_type, mem_flags, base, vmctx_offset);
+
+        (func_addr, vmctx)
+    }
+
+    /// Creates a stack slot for `count` values passed to a builtin, 16 bytes
+    /// apart, and returns it along with its address.
+    fn translate_values_slot(
+        &mut self,
+        pos: &mut FuncCursor<'_>,
+        count: usize,
+    ) -> (ir::StackSlot, ir::Value) {
+        let size = cmp::max(count, 1) * mem::size_of::<u128>();
+        let slot = pos.func.create_stack_slot(ir::StackSlotData::new(
+            ir::StackSlotKind::ExplicitSlot,
+            size as u32,
+        ));
+        let addr = pos.ins().stack_addr(self.pointer_type(), slot, 0);
+        (slot, addr)
+    }
+
+    /// Translates a call to the builtin recording a tail call to `func_addr`,
+    /// a function with the `sig_index` signature, which the caller of the
+    /// current function makes once it has returned.
+    fn translate_record_tail_call(
+        &mut self,
+        pos: &mut FuncCursor<'_>,
+        sig_index: SignatureIndex,
+        callee_vmctx: ir::Value,
+        func_addr: ir::Value,
+        call_args: &[ir::Value],
+    ) {
+        let value_size = mem::size_of::<u128>();
+        let (slot, values) = self.translate_values_slot(pos, call_args.len());
+        for (i, arg) in call_args.iter().enumerate() {
+            pos.ins().stack_store(*arg, slot, (i * value_size) as i32);
+        }
+
+        let func_sig = self.get_return_call_sig(pos.func);
+        let sig_index_arg = pos.ins().iconst(I32, sig_index.index() as i64);
+        let (vmctx, func_addr_builtin) = self.translate_load_builtin_function_address(
+            pos,
+            VMBuiltinFunctionIndex::get_return_call_index(),
+        );
+        pos.ins().call_indirect(
+            func_sig,
+            func_addr_builtin,
+            &[vmctx, sig_index_arg, callee_vmctx, func_addr, values],
+        );
+    }
+
+    /// Extend the address of an atomic access to the 64 bits taken by the
+    /// `memory.atomic.*` builtins.
+    fn atomic_address_arg(
+        &self,
+        pos: &mut FuncCursor,
+        index: MemoryIndex,
+        addr: ir::Value,
+    ) -> ir::Value {
+        if self.module.memories[index].is_64() {
+            addr
+        } else {
+            pos.ins().uextend(I64, addr)
+        }
+    }
+
     fn translate_load_builtin_function_address(
         &mut self,
         pos: &mut FuncCursor<'_>,
@@ -467,6 +772,47 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
 
         (base, func_addr)
     }
+
+    /// Emits a check of the interrupt flag of the `VMInterrupts`, which
+    /// clears the flag and traps with `TrapCode::Interrupt` when it is set.
+    fn translate_interrupt_check(&mut self, builder: &mut FunctionBuilder) {
+        let pointer_type = self.pointer_type();
+        let vmctx = self.vmctx(builder.func);
+        let base = builder.ins().global_value(pointer_type, vmctx);
+
+        let mut mem_flags = ir::MemFlags::trusted();
+        mem_flags.set_readonly();
+
+        let interrupts_offset = i32::try_from(self.offsets.vmctx_interrupts()).unwrap();
+        let interrupts = builder
+            .ins()
+            .load(pointer_type, mem_flags, base, interrupts_offset);
+        // The flag is written by other threads, so it must not be
+        // considered read-only.
+        let interrupt_offset = i32::from(self.offsets.vminterrupts_interrupt());
+        let interrupt = builder.ins().load(
+            pointer_type,
+            ir::MemFlags::trusted(),
+            interrupts,
+            interrupt_offset,
+        );
+
+        let interrupted_block = builder.create_block();
+        let continuation_block = builder.create_block();
+        builder.ins().brnz(interrupt, interrupted_block, &[]);
+        builder.ins().jump(continuation_block, &[]);
+
+        builder.switch_to_block(interrupted_block);
+        builder.seal_block(interrupted_block);
+        let zero = builder.ins().iconst(pointer_type, 0);
+        builder
+            .ins()
+            .store(ir::MemFlags::trusted(), zero, interrupts, interrupt_offset);
+        builder.ins().trap(ir::TrapCode::Interrupt);
+
+        builder.switch_to_block(continuation_block);
+        builder.seal_block(continuation_block);
+    }
 }
 
 impl<'module_environment> TargetEnvironment for FuncEnvironment<'module_environment> {
@@ -680,6 +1026,16 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
             }
         };
 
+        // 64-bit memories, and dynamic heaps whose bound is the pointer-sized
+        // `current_length` field, are indexed with `i64` values.
+        let index_type = if self.module.memories[index].is_64()
+            || matches!(self.memory_styles[index], MemoryStyle::Dynamic { .. })
+        {
+            I64
+        } else {
+            I32
+        };
+
         // If we have a declared maximum, we can make this a "static" heap, which is
         // allocated up front and never moved.
         let (offset_guard_size, heap_style, readonly_base) = match self.memory_styles[index] {
@@ -721,7 +1077,7 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
             min_size: 0.into(),
             offset_guard_size,
             style: heap_style,
-            index_type: I32,
+            index_type,
         }))
     }
 
@@ -791,61 +1147,12 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         callee: ir::Value,
         call_args: &[ir::Value],
     ) -> WasmResult<ir::Inst> {
-        let pointer_type = self.pointer_type();
-
-        let table_entry_addr = pos.ins().table_addr(pointer_type, table, callee, 0);
-
-        // Dereference table_entry_addr to get the function address.
-        let mem_flags = ir::MemFlags::trusted();
-        let func_addr = pos.ins().load(
-            pointer_type,
-            mem_flags,
-            table_entry_addr,
-            i32::from(self.offsets.vmcaller_checked_anyfunc_func_ptr()),
-        );
-
-        // Check whether `func_addr` is null.
-        pos.ins().trapz(func_addr, ir::TrapCode::IndirectCallToNull);
-
-        // If necessary, check the signature.
-        match self.table_styles[table_index] {
-            TableStyle::CallerChecksSignature => {
-                let sig_id_size = self.offsets.size_of_vmshared_signature_index();
-                let sig_id_type = ir::Type::int(u16::from(sig_id_size) * 8).unwrap();
-                let vmctx = self.vmctx(pos.func);
-                let base = pos.ins().global_value(pointer_type, vmctx);
-                let offset =
-                    i32::try_from(self.offsets.vmctx_vmshared_signature_id(sig_index)).unwrap();
-
-                // Load the caller ID.
-                let mut mem_flags = ir::MemFlags::trusted();
-                mem_flags.set_readonly();
-                let caller_sig_id = pos.ins().load(sig_id_type, mem_flags, base, offset);
-
-                // Load the callee ID.
-                let mem_flags = ir::MemFlags::trusted();
-                let callee_sig_id = pos.ins().load(
-                    sig_id_type,
-                    mem_flags,
-                    table_entry_addr,
-                    i32::from(self.offsets.vmcaller_checked_anyfunc_type_index()),
-                );
-
-                // Check that they match.
-                let cmp = pos.ins().icmp(IntCC::Equal, callee_sig_id, caller_sig_id);
-                pos.ins().trapz(cmp, ir::TrapCode::BadSignature);
-            }
-        }
+        let (func_addr, vmctx) =
+            self.translate_load_indirect_callee(&mut pos, table_index, table, sig_index, callee);
 
         let mut real_call_args = Vec::with_capacity(call_args.len() + 2);
 
         // First append the callee vmctx address.
-        let vmctx = pos.ins().load(
-            pointer_type,
-            mem_flags,
-            table_entry_addr,
-            i32::from(self.offsets.vmcaller_checked_anyfunc_vmctx()),
-        );
         real_call_args.push(vmctx);
 
         // Then append the regular call arguments.
@@ -854,6 +1161,22 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         Ok(pos.ins().call_indirect(sig_ref, func_addr, &real_call_args))
     }
 
+    fn translate_return_call_indirect(
+        &mut self,
+        mut pos: FuncCursor<'_>,
+        table_index: TableIndex,
+        table: ir::Table,
+        sig_index: SignatureIndex,
+        _sig_ref: ir::SigRef,
+        callee: ir::Value,
+        call_args: &[ir::Value],
+    ) -> WasmResult<()> {
+        let (func_addr, vmctx) =
+            self.translate_load_indirect_callee(&mut pos, table_index, table, sig_index, callee);
+        self.translate_record_tail_call(&mut pos, sig_index, vmctx, func_addr, call_args);
+        Ok(())
+    }
+
     fn translate_call(
         &mut self,
         mut pos: FuncCursor<'_>,
@@ -879,22 +1202,10 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
 
         // Handle direct calls to imported functions. We use an indirect call
         // so that we don't have to patch the code at runtime.
-        let pointer_type = self.pointer_type();
         let sig_ref = pos.func.dfg.ext_funcs[callee].signature;
-        let vmctx = self.vmctx(&mut pos.func);
-        let base = pos.ins().global_value(pointer_type, vmctx);
-
-        let mem_flags = ir::MemFlags::trusted();
-
-        // Load the callee address.
-        let body_offset =
-            i32::try_from(self.offsets.vmctx_vmfunction_import_body(callee_index)).unwrap();
-        let func_addr = pos.ins().load(pointer_type, mem_flags, base, body_offset);
+        let (func_addr, vmctx) = self.translate_load_imported_callee(&mut pos, callee_index);
 
         // First append the callee vmctx address.
-        let vmctx_offset =
-            i32::try_from(self.offsets.vmctx_vmfunction_import_vmctx(callee_index)).unwrap();
-        let vmctx = pos.ins().load(pointer_type, mem_flags, base, vmctx_offset);
         real_call_args.push(vmctx);
 
         // Then append the regular call arguments.
@@ -903,6 +1214,61 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         Ok(pos.ins().call_indirect(sig_ref, func_addr, &real_call_args))
     }
 
+    fn translate_return_call(
+        &mut self,
+        mut pos: FuncCursor<'_>,
+        callee_index: FunctionIndex,
+        callee: ir::FuncRef,
+        call_args: &[ir::Value],
+    ) -> WasmResult<()> {
+        let (func_addr, vmctx) = if self.module.is_imported_function(callee_index) {
+            self.translate_load_imported_callee(&mut pos, callee_index)
+        } else {
+            // The callee vmctx is the same as the caller vmctx.
+            let caller_vmctx = pos.func.special_param(ArgumentPurpose::VMContext).unwrap();
+            let func_addr = pos.ins().func_addr(self.pointer_type(), callee);
+            (func_addr, caller_vmctx)
+        };
+        let sig_index = self.module.functions[callee_index];
+        self.translate_record_tail_call(&mut pos, sig_index, vmctx, func_addr, call_args);
+        Ok(())
+    }
+
+    fn translate_finish_tail_calls(
+        &mut self,
+        mut pos: FuncCursor<'_>,
+        results: &[ir::Value],
+    ) -> WasmResult<Vec<ir::Value>> {
+        if !self.tail_calls {
+            return Ok(results.to_vec());
+        }
+
+        // The results go through a stack slot, in which the builtin replaces
+        // them by those of the last tail call, if any.
+        let value_size = mem::size_of::<u128>();
+        let (slot, values) = self.translate_values_slot(&mut pos, results.len());
+        for (i, result) in results.iter().enumerate() {
+            pos.ins()
+                .stack_store(*result, slot, (i * value_size) as i32);
+        }
+
+        let func_sig = self.get_finish_tail_calls_sig(pos.func);
+        let (_vmctx, func_addr) = self.translate_load_builtin_function_address(
+            &mut pos,
+            VMBuiltinFunctionIndex::get_finish_tail_calls_index(),
+        );
+        pos.ins().call_indirect(func_sig, func_addr, &[values]);
+
+        Ok(results
+            .iter()
+            .enumerate()
+            .map(|(i, result)| {
+                let ty = pos.func.dfg.value_type(*result);
+                pos.ins().stack_load(ty, slot, (i * value_size) as i32)
+            })
+            .collect())
+    }
+
     fn translate_memory_grow(
         &mut self,
         mut pos: FuncCursor<'_>,
@@ -913,10 +1279,28 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         let (func_sig, index_arg, func_idx) = self.get_memory_grow_func(&mut pos.func, index);
         let memory_index = pos.ins().iconst(I32, index_arg as i64);
         let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        if !self.module.memories[index].is_64() {
+            let call_inst =
+                pos.ins()
+                    .call_indirect(func_sig, func_addr, &[vmctx, val, memory_index]);
+            return Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap());
+        }
+
+        // A 64-bit memory can't grow by more than `u32::MAX` pages, and
+        // growing by `u32::MAX` pages always fails, so clamp the delta.
+        let max_delta = pos.ins().iconst(I64, i64::from(u32::MAX));
+        let too_large = pos.ins().icmp(IntCC::UnsignedGreaterThan, val, max_delta);
+        let val = pos.ins().select(too_large, max_delta, val);
+        let val = pos.ins().ireduce(I32, val);
         let call_inst = pos
             .ins()
             .call_indirect(func_sig, func_addr, &[vmctx, val, memory_index]);
-        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+        let result = *pos.func.dfg.inst_results(call_inst).first().unwrap();
+        // The builtin returns `-1` as a `u32` on failure.
+        let failed = pos.ins().icmp_imm(IntCC::Equal, result, -1);
+        let result = pos.ins().uextend(I64, result);
+        let minus_one = pos.ins().iconst(I64, -1);
+        Ok(pos.ins().select(failed, minus_one, result))
     }
 
     fn translate_memory_size(
@@ -931,7 +1315,12 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         let call_inst = pos
             .ins()
             .call_indirect(func_sig, func_addr, &[vmctx, memory_index]);
-        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+        let result = *pos.func.dfg.inst_results(call_inst).first().unwrap();
+        if self.module.memories[index].is_64() {
+            Ok(pos.ins().uextend(I64, result))
+        } else {
+            Ok(result)
+        }
     }
 
     fn translate_memory_copy(
@@ -939,12 +1328,30 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         mut pos: FuncCursor,
         src_index: MemoryIndex,
         _src_heap: ir::Heap,
-        _dst_index: MemoryIndex,
+        dst_index: MemoryIndex,
         _dst_heap: ir::Heap,
         dst: ir::Value,
         src: ir::Value,
         len: ir::Value,
     ) -> WasmResult<()> {
+        if self.module.memories[src_index].is_64() || self.module.memories[dst_index].is_64() {
+            return Err(wasm_unsupported!("memory.copy on a 64-bit memory"));
+        }
+        if src_index != dst_index {
+            let func_sig = self.get_memory_copy_between_sig(&mut pos.func);
+            let dst_index_arg = pos.ins().iconst(I32, dst_index.index() as i64);
+            let src_index_arg = pos.ins().iconst(I32, src_index.index() as i64);
+            let (vmctx, func_addr) = self.translate_load_builtin_function_address(
+                &mut pos,
+                VMBuiltinFunctionIndex::get_memory_copy_between_index(),
+            );
+            pos.ins().call_indirect(
+                func_sig,
+                func_addr,
+                &[vmctx, dst_index_arg, src_index_arg, dst, src, len],
+            );
+            return Ok(());
+        }
         let (func_sig, src_index, func_idx) = self.get_memory_copy_func(&mut pos.func, src_index);
 
         let src_index_arg = pos.ins().iconst(I32, src_index as i64);
@@ -966,6 +1373,9 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         val: ir::Value,
         len: ir::Value,
     ) -> WasmResult<()> {
+        if self.module.memories[memory_index].is_64() {
+            return Err(wasm_unsupported!("memory.fill on a 64-bit memory"));
+        }
         let (func_sig, memory_index, func_idx) =
             self.get_memory_fill_func(&mut pos.func, memory_index);
 
@@ -992,6 +1402,9 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         src: ir::Value,
         len: ir::Value,
     ) -> WasmResult<()> {
+        if self.module.memories[memory_index].is_64() {
+            return Err(wasm_unsupported!("memory.init on a 64-bit memory"));
+        }
         let (func_sig, func_idx) = self.get_memory_init_func(&mut pos.func);
 
         let memory_index_arg = pos.ins().iconst(I32, memory_index.index() as i64);
@@ -1103,30 +1516,74 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         Ok(())
     }
 
+    fn translate_loop_header(&mut self, builder: &mut FunctionBuilder) -> WasmResult<()> {
+        self.translate_interrupt_check(builder);
+        Ok(())
+    }
+
+    fn before_translate_function(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        _state: &FuncTranslationState,
+    ) -> WasmResult<()> {
+        self.translate_interrupt_check(builder);
+        Ok(())
+    }
+
     fn translate_atomic_wait(
         &mut self,
-        _pos: FuncCursor,
-        _index: MemoryIndex,
+        mut pos: FuncCursor,
+        index: MemoryIndex,
         _heap: ir::Heap,
-        _addr: ir::Value,
-        _expected: ir::Value,
-        _timeout: ir::Value,
+        addr: ir::Value,
+        offset: u32,
+        expected: ir::Value,
+        timeout: ir::Value,
     ) -> WasmResult<ir::Value> {
-        Err(WasmError::Unsupported(
-            "wasm atomics (fn translate_atomic_wait)".to_string(),
-        ))
+        let ty = pos.func.dfg.value_type(expected);
+        let func_sig = self.get_memory_atomic_wait_sig(&mut pos.func, ty);
+        let func_idx = match ty {
+            I32 => VMBuiltinFunctionIndex::get_memory_atomic_wait32_index(),
+            _ => VMBuiltinFunctionIndex::get_memory_atomic_wait64_index(),
+        };
+        let memory_index_arg = pos.ins().iconst(I32, index.index() as i64);
+        let addr = self.atomic_address_arg(&mut pos, index, addr);
+        let offset_arg = pos.ins().iconst(I32, i64::from(offset));
+
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+
+        let call_inst = pos.ins().call_indirect(
+            func_sig,
+            func_addr,
+            &[vmctx, memory_index_arg, offset_arg, addr, expected, timeout],
+        );
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
     }
 
     fn translate_atomic_notify(
         &mut self,
-        _pos: FuncCursor,
-        _index: MemoryIndex,
+        mut pos: FuncCursor,
+        index: MemoryIndex,
         _heap: ir::Heap,
-        _addr: ir::Value,
-        _count: ir::Value,
+        addr: ir::Value,
+        offset: u32,
+        count: ir::Value,
     ) -> WasmResult<ir::Value> {
-        Err(WasmError::Unsupported(
-            "wasm atomics (fn translate_atomic_notify)".to_string(),
-        ))
+        let func_sig = self.get_memory_atomic_notify_sig(&mut pos.func);
+        let memory_index_arg = pos.ins().iconst(I32, index.index() as i64);
+        let addr = self.atomic_address_arg(&mut pos, index, addr);
+        let offset_arg = pos.ins().iconst(I32, i64::from(offset));
+
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(
+            &mut pos,
+            VMBuiltinFunctionIndex::get_memory_atomic_notify_index(),
+        );
+
+        let call_inst = pos.ins().call_indirect(
+            func_sig,
+            func_addr,
+            &[vmctx, memory_index_arg, offset_arg, addr, count],
+        );
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
     }
 }
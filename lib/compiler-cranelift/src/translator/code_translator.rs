@@ -203,7 +203,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 .extend_from_slice(builder.block_params(loop_body));
 
             builder.switch_to_block(loop_body);
-            environ.translate_loop_header(builder.cursor())?;
+            environ.translate_loop_header(builder)?;
         }
         Operator::If { ty } => {
             let val = state.pop1();
@@ -510,7 +510,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 fref,
                 args,
             )?;
-            let inst_results = builder.inst_results(call);
+            let inst_results = builder.inst_results(call).to_vec();
             debug_assert_eq!(
                 inst_results.len(),
                 builder.func.dfg.signatures[builder.func.dfg.ext_funcs[fref].signature]
@@ -518,8 +518,9 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                     .len(),
                 "translate_call results should match the call signature"
             );
+            let results = environ.translate_finish_tail_calls(builder.cursor(), &inst_results)?;
             state.popn(num_args);
-            state.pushn(inst_results);
+            state.pushn(&results);
         }
         Operator::CallIndirect { index, table_index } => {
             // `index` is the index of the function's signature and `table_index` is the index of
@@ -545,14 +546,61 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 callee,
                 state.peekn(num_args),
             )?;
-            let inst_results = builder.inst_results(call);
+            let inst_results = builder.inst_results(call).to_vec();
             debug_assert_eq!(
                 inst_results.len(),
                 builder.func.dfg.signatures[sigref].returns.len(),
                 "translate_call_indirect results should match the call signature"
             );
+            let results = environ.translate_finish_tail_calls(builder.cursor(), &inst_results)?;
             state.popn(num_args);
-            state.pushn(inst_results);
+            state.pushn(&results);
+        }
+        Operator::ReturnCall { function_index } => {
+            let (fref, num_args) = state.get_direct_func(builder.func, *function_index, environ)?;
+
+            // Bitcast any vector arguments to their default type, I8X16, before calling.
+            let callee_signature =
+                &builder.func.dfg.signatures[builder.func.dfg.ext_funcs[fref].signature];
+            let args = state.peekn_mut(num_args);
+            let types = wasm_param_types(&callee_signature.params, |i| {
+                environ.is_wasm_parameter(&callee_signature, i)
+            });
+            bitcast_arguments(args, &types, builder);
+
+            environ.translate_return_call(
+                builder.cursor(),
+                FunctionIndex::from_u32(*function_index),
+                fref,
+                args,
+            )?;
+            state.popn(num_args);
+            return_after_tail_call(module_translation_state, builder, state, environ)?;
+        }
+        Operator::ReturnCallIndirect { index, table_index } => {
+            let (sigref, num_args) = state.get_indirect_sig(builder.func, *index, environ)?;
+            let table = state.get_or_create_table(builder.func, *table_index, environ)?;
+            let callee = state.pop1();
+
+            // Bitcast any vector arguments to their default type, I8X16, before calling.
+            let callee_signature = &builder.func.dfg.signatures[sigref];
+            let args = state.peekn_mut(num_args);
+            let types = wasm_param_types(&callee_signature.params, |i| {
+                environ.is_wasm_parameter(&callee_signature, i)
+            });
+            bitcast_arguments(args, &types, builder);
+
+            environ.translate_return_call_indirect(
+                builder.cursor(),
+                TableIndex::from_u32(*table_index),
+                table,
+                SignatureIndex::from_u32(*index),
+                sigref,
+                callee,
+                state.peekn(num_args),
+            )?;
+            state.popn(num_args);
+            return_after_tail_call(module_translation_state, builder, state, environ)?;
         }
         /******************************* Memory management ***********************************
          * Memory management is handled by environment. It is usually translated into calls to
@@ -989,7 +1037,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             let heap = state.get_heap(builder.func, memarg.memory, environ)?;
             let timeout = state.pop1(); // 64 (fixed)
             let expected = state.pop1(); // 32 or 64 (per the `Ixx` in `IxxAtomicWait`)
-            let addr = state.pop1(); // 32 (fixed)
+            let addr = state.pop1(); // 32 or 64 (per the index type of the memory)
             assert!(builder.func.dfg.value_type(expected) == implied_ty);
             // `fn translate_atomic_wait` can inspect the type of `expected` to figure out what
             // code it needs to generate, if it wants.
@@ -998,6 +1046,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 heap_index,
                 heap,
                 addr,
+                memarg.offset,
                 expected,
                 timeout,
             )?;
@@ -1008,8 +1057,14 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             let heap = state.get_heap(builder.func, memarg.memory, environ)?;
             let count = state.pop1(); // 32 (fixed)
             let addr = state.pop1(); // 32 (fixed)
-            let res =
-                environ.translate_atomic_notify(builder.cursor(), heap_index, heap, addr, count)?;
+            let res = environ.translate_atomic_notify(
+                builder.cursor(),
+                heap_index,
+                heap,
+                addr,
+                memarg.offset,
+                count,
+            )?;
             state.push1(res);
         }
         Operator::I32AtomicLoad { memarg } => {
@@ -1753,14 +1808,47 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             let arg = pop1_with_bitcast(state, type_of(op), builder);
             state.push1(builder.ins().nearest(arg));
         }
-
-        Operator::ReturnCall { .. } | Operator::ReturnCallIndirect { .. } => {
-            return Err(wasm_unsupported!("proposed tail-call operator {:?}", op));
-        }
     };
     Ok(())
 }
 
+/// Return from the function after a `return_call` or `return_call_indirect`.
+///
+/// The callee of the tail call produces the actual results of the function, so placeholder
+/// values are returned.
+fn return_after_tail_call<FE: FuncEnvironment + ?Sized>(
+    module_translation_state: &ModuleTranslationState,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    let return_types = wasm_param_types(&builder.func.signature.returns, |i| {
+        environ.is_wasm_return(&builder.func.signature, i)
+    });
+    for ty in return_types {
+        let placeholder = if ty.is_ref() {
+            builder.ins().null(ty)
+        } else if ty.is_vector() {
+            let constant_handle = builder.func.dfg.constants.insert([0; 16].to_vec().into());
+            builder.ins().vconst(ty, constant_handle)
+        } else if ty == F32 {
+            builder.ins().f32const(ir::immediates::Ieee32::with_bits(0))
+        } else if ty == F64 {
+            builder.ins().f64const(ir::immediates::Ieee64::with_bits(0))
+        } else {
+            builder.ins().iconst(ty, 0)
+        };
+        state.push1(placeholder);
+    }
+    translate_operator(
+        module_translation_state,
+        &Operator::Return,
+        builder,
+        state,
+        environ,
+    )
+}
+
 // Clippy warns us of some fields we are deliberately ignoring
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::unneeded_field_pattern))]
 /// Deals with a Wasm instruction located in an unreachable portion of the code. Most of them
@@ -1905,6 +1993,15 @@ fn get_heap_addr(
 ) -> (ir::Value, i32) {
     let offset_guard_size: u64 = builder.func.heaps[heap].offset_guard_size.into();
 
+    // Heaps indexed with `i64` values may still be accessed with `i32`
+    // addresses, when they're bounded by a pointer-sized length.
+    let index_type = builder.func.heaps[heap].index_type;
+    let addr32 = if builder.func.dfg.value_type(addr32) == index_type {
+        addr32
+    } else {
+        builder.ins().uextend(index_type, addr32)
+    };
+
     // How exactly the bounds check is performed here and what it's performed
     // on is a bit tricky. Generally we want to rely on access violations (e.g.
     // segfaults) to generate traps since that means we don't have to bounds
@@ -13,7 +13,7 @@ use cranelift_codegen::ir::{self, InstBuilder};
 use cranelift_codegen::isa::TargetFrontendConfig;
 use cranelift_frontend::FunctionBuilder;
 use wasmer_compiler::wasmparser::{Operator, Type};
-use wasmer_compiler::WasmResult;
+use wasmer_compiler::{wasm_unsupported, WasmResult};
 use wasmer_types::{FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex};
 
 /// The value of a WebAssembly global variable.
@@ -193,6 +193,52 @@ pub trait FuncEnvironment: TargetEnvironment {
         Ok(pos.ins().call(callee, call_args))
     }
 
+    /// Translate a `return_call_indirect` WebAssembly instruction at `pos`.
+    ///
+    /// Like `translate_call_indirect`, but the call must not grow the stack. The translator
+    /// returns from the function right after the instructions inserted at `pos`.
+    #[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
+    fn translate_return_call_indirect(
+        &mut self,
+        _pos: FuncCursor,
+        _table_index: TableIndex,
+        _table: ir::Table,
+        _sig_index: SignatureIndex,
+        _sig_ref: ir::SigRef,
+        _callee: ir::Value,
+        _call_args: &[ir::Value],
+    ) -> WasmResult<()> {
+        Err(wasm_unsupported!(
+            "proposed tail-call operator return_call_indirect"
+        ))
+    }
+
+    /// Translate a `return_call` WebAssembly instruction at `pos`.
+    ///
+    /// Like `translate_call`, but the call must not grow the stack. The translator returns
+    /// from the function right after the instructions inserted at `pos`.
+    fn translate_return_call(
+        &mut self,
+        _pos: FuncCursor,
+        _callee_index: FunctionIndex,
+        _callee: ir::FuncRef,
+        _call_args: &[ir::Value],
+    ) -> WasmResult<()> {
+        Err(wasm_unsupported!("proposed tail-call operator return_call"))
+    }
+
+    /// Insert instructions at `pos`, right after a call returning `results`, that complete
+    /// the tail calls the callee may have left to its caller.
+    ///
+    /// Return the values to use as the results of the call.
+    fn translate_finish_tail_calls(
+        &mut self,
+        _pos: FuncCursor,
+        results: &[ir::Value],
+    ) -> WasmResult<Vec<ir::Value>> {
+        Ok(results.to_vec())
+    }
+
     /// Translate a `memory.grow` WebAssembly instruction.
     ///
     /// The `index` provided identifies the linear memory to grow, and `heap` is the heap reference
@@ -408,16 +454,19 @@ pub trait FuncEnvironment: TargetEnvironment {
     /// Translate an `i32.atomic.wait` or `i64.atomic.wait` WebAssembly instruction.
     /// The `index` provided identifies the linear memory containing the value
     /// to wait on, and `heap` is the heap reference returned by `make_heap`
-    /// for the same index.  Whether the waited-on value is 32- or 64-bit can be
+    /// for the same index.  The value waited on is at `addr` plus the static
+    /// `offset`.  Whether the waited-on value is 32- or 64-bit can be
     /// determined by examining the type of `expected`, which must be only I32 or I64.
     ///
-    /// Returns an i32, which is negative if the helper call failed.
+    /// Returns an i32: 0 when woken up, 1 if the value isn't `expected` and 2
+    /// on timeout.
     fn translate_atomic_wait(
         &mut self,
         pos: FuncCursor,
         index: MemoryIndex,
         heap: ir::Heap,
         addr: ir::Value,
+        offset: u32,
         expected: ir::Value,
         timeout: ir::Value,
     ) -> WasmResult<ir::Value>;
@@ -425,15 +474,17 @@ pub trait FuncEnvironment: TargetEnvironment {
     /// Translate an `atomic.notify` WebAssembly instruction.
     /// The `index` provided identifies the linear memory containing the value
     /// to wait on, and `heap` is the heap reference returned by `make_heap`
-    /// for the same index.
+    /// for the same index.  The value waited on is at `addr` plus the static
+    /// `offset`.
     ///
-    /// Returns an i64, which is negative if the helper call failed.
+    /// Returns an i32, the number of waiters that were woken up.
     fn translate_atomic_notify(
         &mut self,
         pos: FuncCursor,
         index: MemoryIndex,
         heap: ir::Heap,
         addr: ir::Value,
+        offset: u32,
         count: ir::Value,
     ) -> WasmResult<ir::Value>;
 
@@ -441,11 +492,21 @@ pub trait FuncEnvironment: TargetEnvironment {
     ///
     /// This can be used to insert explicit interrupt or safepoint checking at
     /// the beginnings of loops.
-    fn translate_loop_header(&mut self, _pos: FuncCursor) -> WasmResult<()> {
+    fn translate_loop_header(&mut self, _builder: &mut FunctionBuilder) -> WasmResult<()> {
         // By default, don't emit anything.
         Ok(())
     }
 
+    /// Optional callback for the `FunctionEnvironment` performing this translation
+    /// to emit code at the entry of the function, before its body is translated.
+    fn before_translate_function(
+        &mut self,
+        _builder: &mut FunctionBuilder,
+        _state: &FuncTranslationState,
+    ) -> WasmResult<()> {
+        Ok(())
+    }
+
     /// Optional callback for the `FunctionEnvironment` performing this translation to maintain
     /// internal state or prepare custom state for the operator to translate
     fn before_translate_operator(
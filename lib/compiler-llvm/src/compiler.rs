@@ -11,9 +11,10 @@ use rayon::iter::ParallelBridge;
 use rayon::prelude::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use std::sync::Arc;
 use wasmer_compiler::{
-    Compilation, CompileError, CompileModuleInfo, Compiler, CustomSection, CustomSectionProtection,
-    Dwarf, FunctionBodyData, ModuleMiddlewareChain, ModuleTranslationState, RelocationTarget,
-    SectionBody, SectionIndex, Symbol, SymbolRegistry, Target,
+    Compilation, CompileError, CompileModuleInfo, Compiler, CompilerConfig, CustomSection,
+    CustomSectionProtection, Dwarf, FunctionBodyData, ModuleMiddlewareChain,
+    ModuleTranslationState, RelocationTarget, SectionBody, SectionIndex, Symbol, SymbolRegistry,
+    Target,
 };
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{FunctionIndex, LocalFunctionIndex, SignatureIndex};
@@ -102,6 +103,7 @@ impl LLVMCompiler {
                     self.config(),
                     &compile_info.memory_styles,
                     &compile_info.table_styles,
+                    compile_info.features.tail_call,
                     symbol_registry,
                 )?;
                 Ok(module.write_bitcode_to_memory().as_slice().to_vec())
@@ -189,6 +191,10 @@ impl LLVMCompiler {
 }
 
 impl Compiler for LLVMCompiler {
+    fn fingerprint(&self) -> Option<String> {
+        self.config().fingerprint()
+    }
+
     fn experimental_native_compile_module<'data, 'module>(
         &self,
         target: &Target,
@@ -226,6 +232,7 @@ impl Compiler for LLVMCompiler {
         //let data = Arc::new(Mutex::new(0));
         let memory_styles = &compile_info.memory_styles;
         let table_styles = &compile_info.table_styles;
+        let tail_calls = compile_info.features.tail_call;
 
         let mut module = (*compile_info.module).clone();
         self.config.middlewares.apply_on_module_info(&mut module);
@@ -257,6 +264,7 @@ impl Compiler for LLVMCompiler {
                         self.config(),
                         memory_styles,
                         &table_styles,
+                        tail_calls,
                         &ShortNames {},
                     )
                 },
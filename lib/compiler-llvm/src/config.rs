@@ -8,7 +8,9 @@ use itertools::Itertools;
 use std::fmt::Debug;
 use std::sync::Arc;
 use target_lexicon::Architecture;
-use wasmer_compiler::{Compiler, CompilerConfig, ModuleMiddleware, Target, Triple};
+use wasmer_compiler::{
+    Compiler, CompilerConfig, ModuleMiddleware, ModuleMiddlewareChain, Target, Triple,
+};
 use wasmer_types::{FunctionType, LocalFunctionIndex};
 
 /// The InkWell ModuleInfo type
@@ -215,6 +217,19 @@ impl CompilerConfig for LLVM {
     fn push_middleware(&mut self, middleware: Arc<dyn ModuleMiddleware>) {
         self.middlewares.push(middleware);
     }
+
+    /// The callbacks only observe the compilation, so they aren't part
+    /// of the fingerprint.
+    fn fingerprint(&self) -> Option<String> {
+        Some(format!(
+            "llvm(nan_canonicalization={}, verifier={}, opt_level={:?}, pic={}, middlewares={})",
+            self.enable_nan_canonicalization,
+            self.enable_verifier,
+            self.opt_level,
+            self.is_pic,
+            self.middlewares.fingerprint()?,
+        ))
+    }
 }
 
 impl Default for LLVM {
@@ -20,6 +20,7 @@ use inkwell::{
     AddressSpace, AtomicOrdering, AtomicRMWBinOp, DLLStorageClass, FloatPredicate, IntPredicate,
 };
 use smallvec::SmallVec;
+use std::cmp;
 
 use crate::abi::{get_abi, Abi};
 use crate::config::{CompiledKind, LLVM};
@@ -34,7 +35,7 @@ use wasmer_types::{
     FunctionIndex, FunctionType, GlobalIndex, LocalFunctionIndex, MemoryIndex, SignatureIndex,
     TableIndex, Type,
 };
-use wasmer_vm::{MemoryStyle, ModuleInfo, TableStyle};
+use wasmer_vm::{MemoryStyle, ModuleInfo, TableStyle, VMBuiltinFunctionIndex};
 
 const FUNCTION_SECTION: &str = "__TEXT,wasmer_function";
 
@@ -79,6 +80,7 @@ impl FuncTranslator {
         config: &LLVM,
         memory_styles: &PrimaryMap<MemoryIndex, MemoryStyle>,
         _table_styles: &PrimaryMap<TableIndex, TableStyle>,
+        tail_calls: bool,
         symbol_registry: &dyn SymbolRegistry,
     ) -> Result<Module, CompileError> {
         // The function type, used for the callbacks.
@@ -209,6 +211,7 @@ impl FuncTranslator {
             unreachable_depth: 0,
             memory_styles,
             _table_styles,
+            tail_calls,
             module: &module,
             module_translation,
             wasm_module,
@@ -222,6 +225,8 @@ impl FuncTranslator {
             &func_attrs,
         );
 
+        fcg.build_interrupt_check();
+
         while fcg.state.has_control_frames() {
             let pos = reader.current_position() as u32;
             let op = reader.read_operator()?;
@@ -288,6 +293,7 @@ impl FuncTranslator {
         config: &LLVM,
         memory_styles: &PrimaryMap<MemoryIndex, MemoryStyle>,
         table_styles: &PrimaryMap<TableIndex, TableStyle>,
+        tail_calls: bool,
         symbol_registry: &dyn SymbolRegistry,
     ) -> Result<CompiledFunction, CompileError> {
         let module = self.translate_to_module(
@@ -298,6 +304,7 @@ impl FuncTranslator {
             config,
             memory_styles,
             table_styles,
+            tail_calls,
             symbol_registry,
         )?;
         let function = CompiledKind::Local(*local_func_index);
@@ -762,6 +769,62 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
         self.builder.position_at_end(shouldnt_trap_block);
     }
 
+    /// Traps with `TrapCode::Interrupt` if the interrupt flag of the
+    /// `VMInterrupts` is set, clearing it.
+    fn build_interrupt_check(&mut self) {
+        let interrupt_ptr = self.ctx.interrupt(self.intrinsics);
+        let interrupt = self.builder.build_load(interrupt_ptr, "interrupt");
+        // The flag is written by other threads, the load must not be
+        // hoisted out of loops.
+        interrupt
+            .as_instruction_value()
+            .unwrap()
+            .set_volatile(true)
+            .unwrap();
+        let should_trap = self.builder.build_int_compare(
+            IntPredicate::NE,
+            interrupt.into_int_value(),
+            self.intrinsics.i64_zero,
+            "is_interrupted",
+        );
+
+        let should_trap = self
+            .builder
+            .build_call(
+                self.intrinsics.expect_i1,
+                &[
+                    should_trap.as_basic_value_enum(),
+                    self.intrinsics.i1_ty.const_zero().as_basic_value_enum(),
+                ],
+                "should_trap_expect",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+
+        let shouldnt_trap_block = self
+            .context
+            .append_basic_block(self.function, "shouldnt_trap_block");
+        let should_trap_block = self
+            .context
+            .append_basic_block(self.function, "should_trap_block");
+        self.builder
+            .build_conditional_branch(should_trap, should_trap_block, shouldnt_trap_block);
+        self.builder.position_at_end(should_trap_block);
+        self.builder
+            .build_store(interrupt_ptr, self.intrinsics.i64_zero)
+            .set_volatile(true)
+            .unwrap();
+        self.builder.build_call(
+            self.intrinsics.throw_trap,
+            &[self.intrinsics.trap_interrupt],
+            "throw",
+        );
+        self.builder.build_unreachable();
+        self.builder.position_at_end(shouldnt_trap_block);
+    }
+
     fn v128_into_int_vec(
         &self,
         value: BasicValueEnum<'ctx>,
@@ -956,6 +1019,328 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
         Ok(())
     }
 
+    /// Extend the address of an atomic access to the 64 bits taken by the
+    /// `memory.atomic.*` builtins.
+    fn atomic_address_arg(
+        &self,
+        memory_index: MemoryIndex,
+        addr: BasicValueEnum<'ctx>,
+    ) -> BasicValueEnum<'ctx> {
+        if self.wasm_module.memories[memory_index].is_64() {
+            addr
+        } else {
+            self.builder
+                .build_int_z_extend(addr.into_int_value(), self.intrinsics.i64_ty, "")
+                .as_basic_value_enum()
+        }
+    }
+
+    /// Apply the pending canonicalizations of the arguments of a call to a
+    /// function of type `func_type`.
+    fn canonicalize_call_params(
+        &self,
+        params: &[(BasicValueEnum<'ctx>, ExtraInfo)],
+        func_type: &FunctionType,
+    ) -> Vec<BasicValueEnum<'ctx>> {
+        params
+            .iter()
+            .zip(func_type.params().iter())
+            .map(|((v, info), wasm_ty)| match wasm_ty {
+                Type::F32 => self.builder.build_bitcast(
+                    self.apply_pending_canonicalization(*v, *info),
+                    self.intrinsics.f32_ty,
+                    "",
+                ),
+                Type::F64 => self.builder.build_bitcast(
+                    self.apply_pending_canonicalization(*v, *info),
+                    self.intrinsics.f64_ty,
+                    "",
+                ),
+                Type::V128 => self.apply_pending_canonicalization(*v, *info),
+                _ => *v,
+            })
+            .collect()
+    }
+
+    /// Pop the element index of a `call_indirect` from the stack, check the
+    /// element of the `table_index` table it refers to, and return the
+    /// function pointer and vmctx of the element.
+    fn load_indirect_callee(
+        &mut self,
+        sigindex: SignatureIndex,
+        table_index: u32,
+    ) -> Result<(PointerValue<'ctx>, BasicValueEnum<'ctx>), CompileError> {
+        let expected_dynamic_sigindex =
+            self.ctx
+                .dynamic_sigindex(sigindex, self.intrinsics, self.module);
+        let (table_base, table_bound) = self.ctx.table(
+            TableIndex::from_u32(table_index),
+            self.intrinsics,
+            self.module,
+        );
+        let func_index = self.state.pop1()?.into_int_value();
+
+        // We assume the table has the `anyfunc` element type.
+        let casted_table_base = self.builder.build_pointer_cast(
+            table_base,
+            self.intrinsics.anyfunc_ty.ptr_type(AddressSpace::Generic),
+            "casted_table_base",
+        );
+
+        let anyfunc_struct_ptr = unsafe {
+            self.builder
+                .build_in_bounds_gep(casted_table_base, &[func_index], "anyfunc_struct_ptr")
+        };
+
+        // Load things from the anyfunc data structure.
+        let (func_ptr, found_dynamic_sigindex, ctx_ptr) = (
+            self.builder
+                .build_load(
+                    self.builder
+                        .build_struct_gep(anyfunc_struct_ptr, 0, "func_ptr_ptr")
+                        .unwrap(),
+                    "func_ptr",
+                )
+                .into_pointer_value(),
+            self.builder
+                .build_load(
+                    self.builder
+                        .build_struct_gep(anyfunc_struct_ptr, 1, "sigindex_ptr")
+                        .unwrap(),
+                    "sigindex",
+                )
+                .into_int_value(),
+            self.builder.build_load(
+                self.builder
+                    .build_struct_gep(anyfunc_struct_ptr, 2, "ctx_ptr_ptr")
+                    .unwrap(),
+                "ctx_ptr",
+            ),
+        );
+
+        let truncated_table_bounds = self.builder.build_int_truncate(
+            table_bound,
+            self.intrinsics.i32_ty,
+            "truncated_table_bounds",
+        );
+
+        // First, check if the index is outside of the table bounds.
+        let index_in_bounds = self.builder.build_int_compare(
+            IntPredicate::ULT,
+            func_index,
+            truncated_table_bounds,
+            "index_in_bounds",
+        );
+
+        let index_in_bounds = self
+            .builder
+            .build_call(
+                self.intrinsics.expect_i1,
+                &[
+                    index_in_bounds.as_basic_value_enum(),
+                    self.intrinsics
+                        .i1_ty
+                        .const_int(1, false)
+                        .as_basic_value_enum(),
+                ],
+                "index_in_bounds_expect",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+
+        let in_bounds_continue_block = self
+            .context
+            .append_basic_block(self.function, "in_bounds_continue_block");
+        let not_in_bounds_block = self
+            .context
+            .append_basic_block(self.function, "not_in_bounds_block");
+        self.builder.build_conditional_branch(
+            index_in_bounds,
+            in_bounds_continue_block,
+            not_in_bounds_block,
+        );
+        self.builder.position_at_end(not_in_bounds_block);
+        self.builder.build_call(
+            self.intrinsics.throw_trap,
+            &[self.intrinsics.trap_table_access_oob],
+            "throw",
+        );
+        self.builder.build_unreachable();
+        self.builder.position_at_end(in_bounds_continue_block);
+
+        // Next, check if the table element is initialized.
+
+        let elem_initialized = self.builder.build_is_not_null(func_ptr, "");
+
+        // Next, check if the signature id is correct.
+
+        let sigindices_equal = self.builder.build_int_compare(
+            IntPredicate::EQ,
+            expected_dynamic_sigindex,
+            found_dynamic_sigindex,
+            "sigindices_equal",
+        );
+
+        let initialized_and_sigindices_match =
+            self.builder
+                .build_and(elem_initialized, sigindices_equal, "");
+
+        // Tell llvm that `expected_dynamic_sigindex` should equal `found_dynamic_sigindex`.
+        let initialized_and_sigindices_match = self
+            .builder
+            .build_call(
+                self.intrinsics.expect_i1,
+                &[
+                    initialized_and_sigindices_match.as_basic_value_enum(),
+                    self.intrinsics
+                        .i1_ty
+                        .const_int(1, false)
+                        .as_basic_value_enum(),
+                ],
+                "initialized_and_sigindices_match_expect",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+
+        let continue_block = self
+            .context
+            .append_basic_block(self.function, "continue_block");
+        let sigindices_notequal_block = self
+            .context
+            .append_basic_block(self.function, "sigindices_notequal_block");
+        self.builder.build_conditional_branch(
+            initialized_and_sigindices_match,
+            continue_block,
+            sigindices_notequal_block,
+        );
+
+        self.builder.position_at_end(sigindices_notequal_block);
+        let trap_code = self.builder.build_select(
+            elem_initialized,
+            self.intrinsics.trap_call_indirect_sig,
+            self.intrinsics.trap_call_indirect_null,
+            "",
+        );
+        self.builder
+            .build_call(self.intrinsics.throw_trap, &[trap_code], "throw");
+        self.builder.build_unreachable();
+        self.builder.position_at_end(continue_block);
+
+        Ok((func_ptr, ctx_ptr))
+    }
+
+    /// Store `values` in a new stack slot, 16 bytes apart as expected by the
+    /// tail call builtins, and return a pointer to the slot.
+    fn build_values_slot(&mut self, values: &[BasicValueEnum<'ctx>]) -> PointerValue<'ctx> {
+        let slot_ty = self
+            .intrinsics
+            .i128_ty
+            .array_type(cmp::max(values.len(), 1) as u32);
+        let slot = self.alloca_builder.build_alloca(slot_ty, "values");
+        let slot = self
+            .builder
+            .build_pointer_cast(slot, self.intrinsics.i128_ptr_ty, "");
+        for (i, value) in values.iter().enumerate() {
+            let ptr = self.value_slot_ptr(slot, i, value.get_type());
+            self.builder.build_store(ptr, *value);
+        }
+        slot
+    }
+
+    /// Return a pointer to the `i`th value of type `ty` of a slot created by
+    /// `build_values_slot`.
+    fn value_slot_ptr(
+        &self,
+        slot: PointerValue<'ctx>,
+        i: usize,
+        ty: BasicTypeEnum<'ctx>,
+    ) -> PointerValue<'ctx> {
+        let index = self.intrinsics.i32_ty.const_int(i as u64, false);
+        let ptr = unsafe { self.builder.build_in_bounds_gep(slot, &[index], "") };
+        self.builder
+            .build_pointer_cast(ptr, ty.ptr_type(AddressSpace::Generic), "")
+    }
+
+    /// Record a tail call to `func`, a function with the `sigindex`
+    /// signature, which the caller makes once this function has returned,
+    /// and return from this function.
+    fn build_return_call(
+        &mut self,
+        sigindex: SignatureIndex,
+        func: PointerValue<'ctx>,
+        callee_vmctx: PointerValue<'ctx>,
+        params: &[BasicValueEnum<'ctx>],
+    ) -> Result<(), CompileError> {
+        let vmctx = self.ctx.basic();
+        let values = self.build_values_slot(params);
+        let return_call = self.ctx.builtin_function(
+            VMBuiltinFunctionIndex::get_return_call_index(),
+            self.intrinsics.return_call_ptr_ty,
+            self.intrinsics,
+        );
+        self.builder.build_call(
+            return_call,
+            &[
+                vmctx,
+                self.intrinsics
+                    .i32_ty
+                    .const_int(sigindex.as_u32().into(), false)
+                    .as_basic_value_enum(),
+                self.builder
+                    .build_pointer_cast(callee_vmctx, self.intrinsics.i8_ptr_ty, "")
+                    .as_basic_value_enum(),
+                self.builder
+                    .build_pointer_cast(func, self.intrinsics.i8_ptr_ty, "")
+                    .as_basic_value_enum(),
+                values.as_basic_value_enum(),
+            ],
+            "",
+        );
+
+        // The callee of the tail call produces the actual results of the
+        // function, so return placeholders.
+        let current_block = self
+            .builder
+            .get_insert_block()
+            .ok_or_else(|| CompileError::Codegen("not currently in a block".to_string()))?;
+        let frame = self.state.outermost_frame()?;
+        for phi in frame.phis() {
+            let placeholder = const_zero(phi.as_basic_value().get_type());
+            phi.add_incoming(&[(&placeholder, current_block)]);
+        }
+        self.builder.build_unconditional_branch(*frame.br_dest());
+
+        self.state.reachable = false;
+        Ok(())
+    }
+
+    /// Make the tail calls left by the callee of a call returning `rets`, if
+    /// any, and return the results of the last one.
+    fn finish_tail_calls(&mut self, rets: Vec<BasicValueEnum<'ctx>>) -> Vec<BasicValueEnum<'ctx>> {
+        if !self.tail_calls {
+            return rets;
+        }
+        let values = self.build_values_slot(&rets);
+        let finish_tail_calls = self.ctx.builtin_function(
+            VMBuiltinFunctionIndex::get_finish_tail_calls_index(),
+            self.intrinsics.finish_tail_calls_ptr_ty,
+            self.intrinsics,
+        );
+        self.builder
+            .build_call(finish_tail_calls, &[values.as_basic_value_enum()], "");
+        rets.iter()
+            .enumerate()
+            .map(|(i, ret)| {
+                let ptr = self.value_slot_ptr(values, i, ret.get_type());
+                self.builder.build_load(ptr, "")
+            })
+            .collect()
+    }
+
     fn annotate_user_memaccess(
         &mut self,
         memory_index: MemoryIndex,
@@ -992,9 +1377,11 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
         let context = &self.context;
         let function = &self.function;
 
-        // Compute the offset into the storage.
+        // Compute the offset into the storage. The addresses of 64-bit
+        // memories are already `i64` values.
+        let is_64 = self.wasm_module.memories[memory_index].is_64();
         let imm_offset = intrinsics.i64_ty.const_int(memarg.offset as u64, false);
-        let var_offset = builder.build_int_z_extend(var_offset, intrinsics.i64_ty, "");
+        let var_offset = builder.build_int_z_extend_or_bit_cast(var_offset, intrinsics.i64_ty, "");
         let offset = builder.build_int_add(var_offset, imm_offset, "");
 
         // Look up the memory base (as pointer) and bounds (as unsigned integer).
@@ -1010,9 +1397,11 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     // Bounds check it.
                     let minimum = self.wasm_module.memories[memory_index].minimum;
                     let value_size_v = intrinsics.i64_ty.const_int(value_size as u64, false);
-                    let ptr_in_bounds = if offset.is_const() {
+                    let ptr_in_bounds = if offset.is_const() && !is_64 {
                         // When the offset is constant, if it's below the minimum
                         // memory size, we've statically shown that it's safe.
+                        // This doesn't hold for 64-bit memories, whose offsets
+                        // may have wrapped around.
                         let load_offset_end = offset.const_add(value_size_v);
                         let ptr_in_bounds = load_offset_end.const_int_compare(
                             IntPredicate::ULE,
@@ -1038,15 +1427,26 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                             format!("memory {} length", memory_index.as_u32()),
                             current_length.as_instruction_value().unwrap(),
                         );
-                        let current_length =
-                            builder.build_int_z_extend(current_length, intrinsics.i64_ty, "");
 
-                        builder.build_int_compare(
+                        let end_in_bounds = builder.build_int_compare(
                             IntPredicate::ULE,
                             load_offset_end,
                             current_length,
                             "",
-                        )
+                        );
+                        if is_64 {
+                            // A 64-bit address plus the offset may wrap around,
+                            // but not when the address itself is in bounds.
+                            let start_in_bounds = builder.build_int_compare(
+                                IntPredicate::ULE,
+                                var_offset,
+                                current_length,
+                                "",
+                            );
+                            builder.build_and(start_in_bounds, end_in_bounds, "")
+                        } else {
+                            end_in_bounds
+                        }
                     });
                     if !ptr_in_bounds.is_constant_int()
                         || ptr_in_bounds.get_zero_extended_constant().unwrap() != 1
@@ -1097,6 +1497,11 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     );
                     ptr_to_base
                 }
+                MemoryCache::Static { .. } if is_64 => {
+                    return Err(CompileError::Codegen(
+                        "64-bit memories need a dynamic memory style".to_string(),
+                    ));
+                }
                 MemoryCache::Static { base_ptr } => base_ptr,
             };
         let value_ptr = unsafe { builder.build_gep(base_ptr, &[offset], "") };
@@ -1300,6 +1705,8 @@ pub struct LLVMFunctionCodeGenerator<'ctx, 'a> {
     unreachable_depth: usize,
     memory_styles: &'a PrimaryMap<MemoryIndex, MemoryStyle>,
     _table_styles: &'a PrimaryMap<TableIndex, TableStyle>,
+    // Whether calls are followed by the completion of the tail calls left by their callee.
+    tail_calls: bool,
 
     // This is support for stackmaps:
     /*
@@ -1453,6 +1860,8 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 }
                 */
 
+                self.build_interrupt_check();
+
                 self.state.push_loop(loop_body, loop_next, loop_phis, phis);
             }
             Operator::Br { relative_depth } => {
@@ -2115,31 +2524,14 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let params = self.state.popn_save_extra(func_type.params().len())?;
 
                 // Apply pending canonicalizations.
-                let params =
-                    params
-                        .iter()
-                        .zip(func_type.params().iter())
-                        .map(|((v, info), wasm_ty)| match wasm_ty {
-                            Type::F32 => self.builder.build_bitcast(
-                                self.apply_pending_canonicalization(*v, *info),
-                                self.intrinsics.f32_ty,
-                                "",
-                            ),
-                            Type::F64 => self.builder.build_bitcast(
-                                self.apply_pending_canonicalization(*v, *info),
-                                self.intrinsics.f64_ty,
-                                "",
-                            ),
-                            Type::V128 => self.apply_pending_canonicalization(*v, *info),
-                            _ => *v,
-                        });
+                let params = self.canonicalize_call_params(&params, func_type);
 
                 let params = self.abi.args_to_call(
                     &self.alloca_builder,
                     func_type,
                     callee_vmctx.into_pointer_value(),
                     &func.get_type().get_element_type().into_function_type(),
-                    params.collect::<Vec<_>>().as_slice(),
+                    &params,
                 );
 
                 /*
@@ -2182,176 +2574,50 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 }
                 */
 
-                self.abi
-                    .rets_from_call(&self.builder, &self.intrinsics, call_site, func_type)
+                let rets =
+                    self.abi
+                        .rets_from_call(&self.builder, &self.intrinsics, call_site, func_type);
+                self.finish_tail_calls(rets)
                     .iter()
                     .for_each(|ret| self.state.push1(*ret));
             }
-            Operator::CallIndirect { index, table_index } => {
-                let sigindex = SignatureIndex::from_u32(index);
+            Operator::ReturnCall { function_index } => {
+                let func_index = FunctionIndex::from_u32(function_index);
+                let sigindex = self.wasm_module.functions[func_index];
                 let func_type = &self.wasm_module.signatures[sigindex];
-                let expected_dynamic_sigindex =
-                    self.ctx
-                        .dynamic_sigindex(sigindex, self.intrinsics, self.module);
-                let (table_base, table_bound) = self.ctx.table(
-                    TableIndex::from_u32(table_index),
-                    self.intrinsics,
-                    self.module,
-                );
-                let func_index = self.state.pop1()?.into_int_value();
 
-                // We assume the table has the `anyfunc` element type.
-                let casted_table_base = self.builder.build_pointer_cast(
-                    table_base,
-                    self.intrinsics.anyfunc_ty.ptr_type(AddressSpace::Generic),
-                    "casted_table_base",
-                );
-
-                let anyfunc_struct_ptr = unsafe {
-                    self.builder.build_in_bounds_gep(
-                        casted_table_base,
-                        &[func_index],
-                        "anyfunc_struct_ptr",
-                    )
+                let FunctionCache {
+                    func,
+                    vmctx: callee_vmctx,
+                    ..
+                } = if let Some(local_func_index) = self.wasm_module.local_func_index(func_index) {
+                    let function_name = self
+                        .symbol_registry
+                        .symbol_to_name(Symbol::LocalFunction(local_func_index));
+                    self.ctx.local_func(
+                        local_func_index,
+                        func_index,
+                        self.intrinsics,
+                        self.module,
+                        self.context,
+                        func_type,
+                        &function_name,
+                    )?
+                } else {
+                    self.ctx
+                        .func(func_index, self.intrinsics, self.context, func_type)?
                 };
+                let func = *func;
+                let callee_vmctx = callee_vmctx.into_pointer_value();
 
-                // Load things from the anyfunc data structure.
-                let (func_ptr, found_dynamic_sigindex, ctx_ptr) = (
-                    self.builder
-                        .build_load(
-                            self.builder
-                                .build_struct_gep(anyfunc_struct_ptr, 0, "func_ptr_ptr")
-                                .unwrap(),
-                            "func_ptr",
-                        )
-                        .into_pointer_value(),
-                    self.builder
-                        .build_load(
-                            self.builder
-                                .build_struct_gep(anyfunc_struct_ptr, 1, "sigindex_ptr")
-                                .unwrap(),
-                            "sigindex",
-                        )
-                        .into_int_value(),
-                    self.builder.build_load(
-                        self.builder
-                            .build_struct_gep(anyfunc_struct_ptr, 2, "ctx_ptr_ptr")
-                            .unwrap(),
-                        "ctx_ptr",
-                    ),
-                );
-
-                let truncated_table_bounds = self.builder.build_int_truncate(
-                    table_bound,
-                    self.intrinsics.i32_ty,
-                    "truncated_table_bounds",
-                );
-
-                // First, check if the index is outside of the table bounds.
-                let index_in_bounds = self.builder.build_int_compare(
-                    IntPredicate::ULT,
-                    func_index,
-                    truncated_table_bounds,
-                    "index_in_bounds",
-                );
-
-                let index_in_bounds = self
-                    .builder
-                    .build_call(
-                        self.intrinsics.expect_i1,
-                        &[
-                            index_in_bounds.as_basic_value_enum(),
-                            self.intrinsics
-                                .i1_ty
-                                .const_int(1, false)
-                                .as_basic_value_enum(),
-                        ],
-                        "index_in_bounds_expect",
-                    )
-                    .try_as_basic_value()
-                    .left()
-                    .unwrap()
-                    .into_int_value();
-
-                let in_bounds_continue_block = self
-                    .context
-                    .append_basic_block(self.function, "in_bounds_continue_block");
-                let not_in_bounds_block = self
-                    .context
-                    .append_basic_block(self.function, "not_in_bounds_block");
-                self.builder.build_conditional_branch(
-                    index_in_bounds,
-                    in_bounds_continue_block,
-                    not_in_bounds_block,
-                );
-                self.builder.position_at_end(not_in_bounds_block);
-                self.builder.build_call(
-                    self.intrinsics.throw_trap,
-                    &[self.intrinsics.trap_table_access_oob],
-                    "throw",
-                );
-                self.builder.build_unreachable();
-                self.builder.position_at_end(in_bounds_continue_block);
-
-                // Next, check if the table element is initialized.
-
-                let elem_initialized = self.builder.build_is_not_null(func_ptr, "");
-
-                // Next, check if the signature id is correct.
-
-                let sigindices_equal = self.builder.build_int_compare(
-                    IntPredicate::EQ,
-                    expected_dynamic_sigindex,
-                    found_dynamic_sigindex,
-                    "sigindices_equal",
-                );
-
-                let initialized_and_sigindices_match =
-                    self.builder
-                        .build_and(elem_initialized, sigindices_equal, "");
-
-                // Tell llvm that `expected_dynamic_sigindex` should equal `found_dynamic_sigindex`.
-                let initialized_and_sigindices_match = self
-                    .builder
-                    .build_call(
-                        self.intrinsics.expect_i1,
-                        &[
-                            initialized_and_sigindices_match.as_basic_value_enum(),
-                            self.intrinsics
-                                .i1_ty
-                                .const_int(1, false)
-                                .as_basic_value_enum(),
-                        ],
-                        "initialized_and_sigindices_match_expect",
-                    )
-                    .try_as_basic_value()
-                    .left()
-                    .unwrap()
-                    .into_int_value();
-
-                let continue_block = self
-                    .context
-                    .append_basic_block(self.function, "continue_block");
-                let sigindices_notequal_block = self
-                    .context
-                    .append_basic_block(self.function, "sigindices_notequal_block");
-                self.builder.build_conditional_branch(
-                    initialized_and_sigindices_match,
-                    continue_block,
-                    sigindices_notequal_block,
-                );
-
-                self.builder.position_at_end(sigindices_notequal_block);
-                let trap_code = self.builder.build_select(
-                    elem_initialized,
-                    self.intrinsics.trap_call_indirect_sig,
-                    self.intrinsics.trap_call_indirect_null,
-                    "",
-                );
-                self.builder
-                    .build_call(self.intrinsics.throw_trap, &[trap_code], "throw");
-                self.builder.build_unreachable();
-                self.builder.position_at_end(continue_block);
+                let params = self.state.popn_save_extra(func_type.params().len())?;
+                let params = self.canonicalize_call_params(&params, func_type);
+                self.build_return_call(sigindex, func, callee_vmctx, &params)?;
+            }
+            Operator::CallIndirect { index, table_index } => {
+                let sigindex = SignatureIndex::from_u32(index);
+                let (func_ptr, ctx_ptr) = self.load_indirect_callee(sigindex, table_index)?;
+                let func_type = &self.wasm_module.signatures[sigindex];
 
                 let (llvm_func_type, llvm_func_attrs) =
                     self.abi
@@ -2360,31 +2626,14 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let params = self.state.popn_save_extra(func_type.params().len())?;
 
                 // Apply pending canonicalizations.
-                let params =
-                    params
-                        .iter()
-                        .zip(func_type.params().iter())
-                        .map(|((v, info), wasm_ty)| match wasm_ty {
-                            Type::F32 => self.builder.build_bitcast(
-                                self.apply_pending_canonicalization(*v, *info),
-                                self.intrinsics.f32_ty,
-                                "",
-                            ),
-                            Type::F64 => self.builder.build_bitcast(
-                                self.apply_pending_canonicalization(*v, *info),
-                                self.intrinsics.f64_ty,
-                                "",
-                            ),
-                            Type::V128 => self.apply_pending_canonicalization(*v, *info),
-                            _ => *v,
-                        });
+                let params = self.canonicalize_call_params(&params, func_type);
 
                 let params = self.abi.args_to_call(
                     &self.alloca_builder,
                     func_type,
                     ctx_ptr.into_pointer_value(),
                     &llvm_func_type,
-                    params.collect::<Vec<_>>().as_slice(),
+                    &params,
                 );
 
                 let typed_func_ptr = self.builder.build_pointer_cast(
@@ -2434,11 +2683,22 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 }
                 */
 
-                self.abi
-                    .rets_from_call(&self.builder, &self.intrinsics, call_site, func_type)
+                let rets =
+                    self.abi
+                        .rets_from_call(&self.builder, &self.intrinsics, call_site, func_type);
+                self.finish_tail_calls(rets)
                     .iter()
                     .for_each(|ret| self.state.push1(*ret));
             }
+            Operator::ReturnCallIndirect { index, table_index } => {
+                let sigindex = SignatureIndex::from_u32(index);
+                let (func_ptr, ctx_ptr) = self.load_indirect_callee(sigindex, table_index)?;
+                let func_type = &self.wasm_module.signatures[sigindex];
+
+                let params = self.state.popn_save_extra(func_type.params().len())?;
+                let params = self.canonicalize_call_params(&params, func_type);
+                self.build_return_call(sigindex, func_ptr, ctx_ptr.into_pointer_value(), &params)?;
+            }
 
             /***************************
              * Integer Arithmetic instructions.
@@ -6180,7 +6440,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
              ***************************/
             Operator::I32Load { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6199,7 +6459,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6218,7 +6478,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::F32Load { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6237,7 +6497,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::F64Load { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6256,7 +6516,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6277,7 +6537,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32Store { ref memarg } => {
                 let value = self.state.pop1()?;
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6298,7 +6558,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64Store { ref memarg } => {
                 let value = self.state.pop1()?;
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6320,7 +6580,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let (v, i) = self.state.pop1_extra()?;
                 let v = self.apply_pending_canonicalization(v, i);
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6342,7 +6602,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let (v, i) = self.state.pop1_extra()?;
                 let v = self.apply_pending_canonicalization(v, i);
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6364,7 +6624,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let (v, i) = self.state.pop1_extra()?;
                 let v = self.apply_pending_canonicalization(v, i);
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6384,7 +6644,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I32Load8S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6408,7 +6668,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I32Load16S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6432,7 +6692,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load8S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6457,7 +6717,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load16S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6482,7 +6742,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load32S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6507,7 +6767,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
 
             Operator::I32Load8U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6531,7 +6791,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I32Load16U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6555,7 +6815,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load8U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6579,7 +6839,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load16U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6603,7 +6863,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load32U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6629,7 +6889,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32Store8 { ref memarg } | Operator::I64Store8 { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6653,7 +6913,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32Store16 { ref memarg } | Operator::I64Store16 { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6677,7 +6937,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64Store32 { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7057,7 +7317,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load8x8S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7078,7 +7338,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load8x8U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7099,7 +7359,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load16x4S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7120,7 +7380,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load16x4U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7141,7 +7401,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load32x2S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7162,7 +7422,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load32x2U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7183,7 +7443,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load32Zero { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7207,7 +7467,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load64Zero { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7231,7 +7491,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load8Splat { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7252,7 +7512,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load16Splat { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7273,7 +7533,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load32Splat { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7294,7 +7554,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load64Splat { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7324,7 +7584,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I32AtomicLoad { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7342,7 +7602,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64AtomicLoad { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7360,7 +7620,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I32AtomicLoad8U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7384,7 +7644,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I32AtomicLoad16U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7408,7 +7668,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64AtomicLoad8U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7432,7 +7692,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64AtomicLoad16U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7456,7 +7716,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64AtomicLoad32U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7481,7 +7741,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicStore { ref memarg } => {
                 let value = self.state.pop1()?;
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7499,7 +7759,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicStore { ref memarg } => {
                 let value = self.state.pop1()?;
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7517,7 +7777,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicStore8 { ref memarg } | Operator::I64AtomicStore8 { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7539,7 +7799,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             | Operator::I64AtomicStore16 { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7560,7 +7820,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicStore32 { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7581,7 +7841,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw8AddU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7616,7 +7876,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw16AddU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7651,7 +7911,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmwAdd { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7680,7 +7940,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw8AddU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7715,7 +7975,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw16AddU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7750,7 +8010,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw32AddU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7785,7 +8045,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmwAdd { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7814,7 +8074,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw8SubU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7849,7 +8109,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw16SubU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7884,7 +8144,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmwSub { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7913,7 +8173,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw8SubU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7948,7 +8208,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw16SubU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7983,7 +8243,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw32SubU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8018,7 +8278,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmwSub { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8047,7 +8307,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw8AndU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8082,7 +8342,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw16AndU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8117,7 +8377,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmwAnd { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8146,7 +8406,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw8AndU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8181,7 +8441,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw16AndU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8216,7 +8476,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw32AndU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8251,7 +8511,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmwAnd { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8280,7 +8540,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw8OrU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8315,7 +8575,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw16OrU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8350,7 +8610,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmwOr { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8382,7 +8642,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw8OrU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8417,7 +8677,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw16OrU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8452,7 +8712,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw32OrU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8487,7 +8747,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmwOr { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8516,7 +8776,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw8XorU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8551,7 +8811,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw16XorU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8586,7 +8846,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmwXor { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8615,7 +8875,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw8XorU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8650,7 +8910,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw16XorU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8685,7 +8945,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw32XorU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8720,7 +8980,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmwXor { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8749,7 +9009,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw8XchgU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8784,7 +9044,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw16XchgU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8819,7 +9079,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmwXchg { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8848,7 +9108,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw8XchgU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8883,7 +9143,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw16XchgU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8918,7 +9178,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw32XchgU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8953,7 +9213,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmwXchg { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8985,7 +9245,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9032,7 +9292,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9079,7 +9339,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9113,7 +9373,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9160,7 +9420,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9207,7 +9467,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9254,7 +9514,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9285,8 +9545,33 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
 
             Operator::MemoryGrow { mem, mem_byte: _ } => {
                 let memory_index = MemoryIndex::from_u32(mem);
+                let is_64 = self.wasm_module.memories[memory_index].is_64();
                 let delta = self.state.pop1()?;
+                let delta = if is_64 {
+                    // A 64-bit memory can't grow by more than `u32::MAX` pages,
+                    // and growing by `u32::MAX` pages always fails, so clamp
+                    // the delta.
+                    let delta = delta.into_int_value();
+                    let max_delta = self.intrinsics.i64_ty.const_int(u32::MAX.into(), false);
+                    let too_large =
+                        self.builder
+                            .build_int_compare(IntPredicate::UGT, delta, max_delta, "");
+                    let delta = self
+                        .builder
+                        .build_select(too_large, max_delta, delta, "")
+                        .into_int_value();
+                    self.builder
+                        .build_int_truncate(delta, self.intrinsics.i32_ty, "")
+                        .as_basic_value_enum()
+                } else {
+                    delta
+                };
                 let grow_fn_ptr = self.ctx.memory_grow(memory_index, self.intrinsics);
+                // The builtin for local memories takes a local memory index.
+                let index_arg = self
+                    .wasm_module
+                    .local_memory_index(memory_index)
+                    .map_or(mem, |local_index| local_index.as_u32());
                 let grow = self.builder.build_call(
                     grow_fn_ptr,
                     &[
@@ -9294,29 +9579,131 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                         delta,
                         self.intrinsics
                             .i32_ty
-                            .const_int(mem.into(), false)
+                            .const_int(index_arg.into(), false)
                             .as_basic_value_enum(),
                     ],
                     "",
                 );
-                self.state.push1(grow.try_as_basic_value().left().unwrap());
+                let grow = grow.try_as_basic_value().left().unwrap();
+                if is_64 {
+                    // The builtin returns `-1` as a `u32` on failure.
+                    let grow = grow.into_int_value();
+                    let failed = self.builder.build_int_compare(
+                        IntPredicate::EQ,
+                        grow,
+                        self.intrinsics.i32_ty.const_int(u32::MAX.into(), false),
+                        "",
+                    );
+                    let grow = self
+                        .builder
+                        .build_int_z_extend(grow, self.intrinsics.i64_ty, "");
+                    let minus_one = self.intrinsics.i64_ty.const_int(u64::MAX, false);
+                    self.state
+                        .push1(self.builder.build_select(failed, minus_one, grow, ""));
+                } else {
+                    self.state.push1(grow);
+                }
             }
             Operator::MemorySize { mem, mem_byte: _ } => {
                 let memory_index = MemoryIndex::from_u32(mem);
                 let size_fn_ptr = self.ctx.memory_size(memory_index, self.intrinsics);
+                let index_arg = self
+                    .wasm_module
+                    .local_memory_index(memory_index)
+                    .map_or(mem, |local_index| local_index.as_u32());
                 let size = self.builder.build_call(
                     size_fn_ptr,
                     &[
                         vmctx.as_basic_value_enum(),
                         self.intrinsics
                             .i32_ty
-                            .const_int(mem.into(), false)
+                            .const_int(index_arg.into(), false)
                             .as_basic_value_enum(),
                     ],
                     "",
                 );
                 size.add_attribute(AttributeLoc::Function, self.intrinsics.readonly);
-                self.state.push1(size.try_as_basic_value().left().unwrap());
+                let size = size.try_as_basic_value().left().unwrap();
+                if self.wasm_module.memories[memory_index].is_64() {
+                    let size = self.builder.build_int_z_extend(
+                        size.into_int_value(),
+                        self.intrinsics.i64_ty,
+                        "",
+                    );
+                    self.state.push1(size);
+                } else {
+                    self.state.push1(size);
+                }
+            }
+            Operator::MemoryAtomicWait32 { ref memarg }
+            | Operator::MemoryAtomicWait64 { ref memarg } => {
+                let (builtin, fn_ptr_ty) = match op {
+                    Operator::MemoryAtomicWait32 { .. } => (
+                        VMBuiltinFunctionIndex::get_memory_atomic_wait32_index(),
+                        self.intrinsics.memory_atomic_wait32_ptr_ty,
+                    ),
+                    _ => (
+                        VMBuiltinFunctionIndex::get_memory_atomic_wait64_index(),
+                        self.intrinsics.memory_atomic_wait64_ptr_ty,
+                    ),
+                };
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
+                let ((addr, _), (expected, expected_info), (timeout, _)) =
+                    self.state.pop3_extra()?;
+                let expected = self.apply_pending_canonicalization(expected, expected_info);
+                let addr = self.atomic_address_arg(memory_index, addr);
+                let wait_fn_ptr = self
+                    .ctx
+                    .builtin_function(builtin, fn_ptr_ty, self.intrinsics);
+                let result = self.builder.build_call(
+                    wait_fn_ptr,
+                    &[
+                        vmctx.as_basic_value_enum(),
+                        self.intrinsics
+                            .i32_ty
+                            .const_int(memarg.memory.into(), false)
+                            .as_basic_value_enum(),
+                        self.intrinsics
+                            .i32_ty
+                            .const_int(memarg.offset.into(), false)
+                            .as_basic_value_enum(),
+                        addr,
+                        expected,
+                        timeout,
+                    ],
+                    "",
+                );
+                self.state
+                    .push1(result.try_as_basic_value().left().unwrap());
+            }
+            Operator::MemoryAtomicNotify { ref memarg } => {
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
+                let (addr, count) = self.state.pop2()?;
+                let addr = self.atomic_address_arg(memory_index, addr);
+                let notify_fn_ptr = self.ctx.builtin_function(
+                    VMBuiltinFunctionIndex::get_memory_atomic_notify_index(),
+                    self.intrinsics.memory_atomic_notify_ptr_ty,
+                    self.intrinsics,
+                );
+                let result = self.builder.build_call(
+                    notify_fn_ptr,
+                    &[
+                        vmctx.as_basic_value_enum(),
+                        self.intrinsics
+                            .i32_ty
+                            .const_int(memarg.memory.into(), false)
+                            .as_basic_value_enum(),
+                        self.intrinsics
+                            .i32_ty
+                            .const_int(memarg.offset.into(), false)
+                            .as_basic_value_enum(),
+                        addr,
+                        count,
+                    ],
+                    "",
+                );
+                self.state
+                    .push1(result.try_as_basic_value().left().unwrap());
             }
             _ => {
                 return Err(CompileError::Codegen(format!(
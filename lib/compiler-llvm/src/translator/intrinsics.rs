@@ -166,6 +166,7 @@ pub struct Intrinsics<'ctx> {
     pub trap_bad_conversion_to_integer: BasicValueEnum<'ctx>,
     pub trap_unaligned_atomic: BasicValueEnum<'ctx>,
     pub trap_table_access_oob: BasicValueEnum<'ctx>,
+    pub trap_interrupt: BasicValueEnum<'ctx>,
 
     // VM intrinsics.
     pub throw_trap: FunctionValue<'ctx>,
@@ -184,6 +185,11 @@ pub struct Intrinsics<'ctx> {
     pub imported_memory32_grow_ptr_ty: PointerType<'ctx>,
     pub memory32_size_ptr_ty: PointerType<'ctx>,
     pub imported_memory32_size_ptr_ty: PointerType<'ctx>,
+    pub memory_atomic_wait32_ptr_ty: PointerType<'ctx>,
+    pub memory_atomic_wait64_ptr_ty: PointerType<'ctx>,
+    pub memory_atomic_notify_ptr_ty: PointerType<'ctx>,
+    pub return_call_ptr_ty: PointerType<'ctx>,
+    pub finish_tail_calls_ptr_ty: PointerType<'ctx>,
 
     pub ctx_ptr_ty: PointerType<'ctx>,
 }
@@ -436,6 +442,9 @@ impl<'ctx> Intrinsics<'ctx> {
             trap_table_access_oob: i32_ty
                 .const_int(TrapCode::TableAccessOutOfBounds as _, false)
                 .as_basic_value_enum(),
+            trap_interrupt: i32_ty
+                .const_int(TrapCode::Interrupt as _, false)
+                .as_basic_value_enum(),
 
             // VM intrinsics.
             throw_trap: module.add_function(
@@ -463,7 +472,7 @@ impl<'ctx> Intrinsics<'ctx> {
 
             // TODO: this i64 is actually a rust usize
             vmmemory_definition_ptr_ty: context
-                .struct_type(&[i8_ptr_ty_basic, i32_ty_basic], false)
+                .struct_type(&[i8_ptr_ty_basic, i64_ty_basic], false)
                 .ptr_type(AddressSpace::Generic),
             vmmemory_definition_base_element: 0,
             vmmemory_definition_current_length_element: 1,
@@ -486,6 +495,59 @@ impl<'ctx> Intrinsics<'ctx> {
             imported_memory32_size_ptr_ty: i32_ty
                 .fn_type(&[ctx_ptr_ty.as_basic_type_enum(), i32_ty_basic], false)
                 .ptr_type(AddressSpace::Generic),
+            memory_atomic_wait32_ptr_ty: i32_ty
+                .fn_type(
+                    &[
+                        ctx_ptr_ty.as_basic_type_enum(),
+                        i32_ty_basic,
+                        i32_ty_basic,
+                        i64_ty_basic,
+                        i32_ty_basic,
+                        i64_ty_basic,
+                    ],
+                    false,
+                )
+                .ptr_type(AddressSpace::Generic),
+            memory_atomic_wait64_ptr_ty: i32_ty
+                .fn_type(
+                    &[
+                        ctx_ptr_ty.as_basic_type_enum(),
+                        i32_ty_basic,
+                        i32_ty_basic,
+                        i64_ty_basic,
+                        i64_ty_basic,
+                        i64_ty_basic,
+                    ],
+                    false,
+                )
+                .ptr_type(AddressSpace::Generic),
+            memory_atomic_notify_ptr_ty: i32_ty
+                .fn_type(
+                    &[
+                        ctx_ptr_ty.as_basic_type_enum(),
+                        i32_ty_basic,
+                        i32_ty_basic,
+                        i64_ty_basic,
+                        i32_ty_basic,
+                    ],
+                    false,
+                )
+                .ptr_type(AddressSpace::Generic),
+            return_call_ptr_ty: void_ty
+                .fn_type(
+                    &[
+                        ctx_ptr_ty.as_basic_type_enum(),
+                        i32_ty_basic,
+                        i8_ptr_ty_basic,
+                        i8_ptr_ty_basic,
+                        i128_ptr_ty.as_basic_type_enum(),
+                    ],
+                    false,
+                )
+                .ptr_type(AddressSpace::Generic),
+            finish_tail_calls_ptr_ty: void_ty
+                .fn_type(&[i128_ptr_ty.as_basic_type_enum()], false)
+                .ptr_type(AddressSpace::Generic),
 
             ctx_ptr_ty,
         };
@@ -545,6 +607,8 @@ pub struct CtxType<'ctx, 'a> {
     cached_functions: HashMap<FunctionIndex, FunctionCache<'ctx>>,
     cached_memory_grow: HashMap<MemoryIndex, PointerValue<'ctx>>,
     cached_memory_size: HashMap<MemoryIndex, PointerValue<'ctx>>,
+    cached_builtin_functions: HashMap<u32, PointerValue<'ctx>>,
+    cached_interrupt: Option<PointerValue<'ctx>>,
 
     offsets: VMOffsets,
 }
@@ -570,6 +634,8 @@ impl<'ctx, 'a> CtxType<'ctx, 'a> {
             cached_functions: HashMap::new(),
             cached_memory_grow: HashMap::new(),
             cached_memory_size: HashMap::new(),
+            cached_builtin_functions: HashMap::new(),
+            cached_interrupt: None,
 
             // TODO: pointer width
             offsets: VMOffsets::new(8, &wasm_module),
@@ -1097,6 +1163,71 @@ impl<'ctx, 'a> CtxType<'ctx, 'a> {
                 .into_pointer_value()
         })
     }
+
+    /// Returns a pointer to the builtin function `index`, whose type is
+    /// `fn_ptr_ty`.
+    pub fn builtin_function(
+        &mut self,
+        index: VMBuiltinFunctionIndex,
+        fn_ptr_ty: PointerType<'ctx>,
+        intrinsics: &Intrinsics<'ctx>,
+    ) -> PointerValue<'ctx> {
+        let (cached_builtin_functions, offsets, cache_builder, ctx_ptr_value) = (
+            &mut self.cached_builtin_functions,
+            &self.offsets,
+            &self.cache_builder,
+            &self.ctx_ptr_value,
+        );
+        *cached_builtin_functions
+            .entry(index.index())
+            .or_insert_with(|| {
+                let offset = offsets.vmctx_builtin_function(index);
+                let offset = intrinsics.i32_ty.const_int(offset.into(), false);
+                let fn_ptr_ptr = unsafe { cache_builder.build_gep(*ctx_ptr_value, &[offset], "") };
+
+                let fn_ptr_ptr = cache_builder
+                    .build_bitcast(fn_ptr_ptr, fn_ptr_ty.ptr_type(AddressSpace::Generic), "")
+                    .into_pointer_value();
+                cache_builder
+                    .build_load(fn_ptr_ptr, "")
+                    .into_pointer_value()
+            })
+    }
+
+    /// Returns a pointer to the `interrupt` flag of the `VMInterrupts`.
+    pub fn interrupt(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        let (cached_interrupt, offsets, cache_builder, ctx_ptr_value) = (
+            &mut self.cached_interrupt,
+            &self.offsets,
+            &self.cache_builder,
+            &self.ctx_ptr_value,
+        );
+        *cached_interrupt.get_or_insert_with(|| {
+            let offset = intrinsics
+                .i32_ty
+                .const_int(offsets.vmctx_interrupts().into(), false);
+            let interrupts_ptr_ptr =
+                unsafe { cache_builder.build_gep(*ctx_ptr_value, &[offset], "") };
+            let interrupts_ptr_ptr = cache_builder
+                .build_bitcast(
+                    interrupts_ptr_ptr,
+                    intrinsics.i8_ptr_ty.ptr_type(AddressSpace::Generic),
+                    "",
+                )
+                .into_pointer_value();
+            let interrupts_ptr = cache_builder
+                .build_load(interrupts_ptr_ptr, "interrupts_ptr")
+                .into_pointer_value();
+
+            let offset = intrinsics
+                .i32_ty
+                .const_int(offsets.vminterrupts_interrupt().into(), false);
+            let interrupt_ptr = unsafe { cache_builder.build_gep(interrupts_ptr, &[offset], "") };
+            cache_builder
+                .build_bitcast(interrupt_ptr, intrinsics.i64_ptr_ty, "interrupt_ptr")
+                .into_pointer_value()
+        })
+    }
 }
 
 // Given an instruction that operates on memory, mark the access as not aliasing
@@ -0,0 +1,2610 @@
+use crate::address_map::get_function_address_map;
+use crate::{
+    arm64_decl::{new_machine_state, ARM64Register, ArgumentRegisterAllocator},
+    common_decl::*,
+    config::Singlepass,
+    emitter_arm64::*,
+    machine::Machine as GenericMachine,
+    machine_arm64::ARM64,
+};
+use dynasmrt::{aarch64::Assembler, DynamicLabel};
+use smallvec::{smallvec, SmallVec};
+use std::iter;
+use wasmer_compiler::wasmparser::{
+    MemoryImmediate, Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType,
+};
+use wasmer_compiler::{
+    CompileError, CompiledFunction, CompiledFunctionFrameInfo, CustomSection,
+    CustomSectionProtection, FunctionBody, FunctionBodyData, InstructionAddressMap, Relocation,
+    RelocationKind, RelocationTarget, SectionBody, SectionIndex, SourceLoc, TrapInformation,
+};
+use wasmer_types::{
+    entity::{EntityRef, PrimaryMap, SecondaryMap},
+    FunctionType,
+};
+use wasmer_types::{
+    FunctionIndex, GlobalIndex, LocalFunctionIndex, LocalMemoryIndex, MemoryIndex, SignatureIndex,
+    TableIndex, Type,
+};
+use wasmer_vm::{MemoryStyle, ModuleInfo, TableStyle, TrapCode, VMBuiltinFunctionIndex, VMOffsets};
+
+type Machine = GenericMachine<ARM64>;
+
+/// The singlepass per-function code generator for ARM64.
+///
+/// It follows the code generator for x86-64, but only supports the MVP value types:
+/// SIMD, threads and multiple values are rejected with a `CodegenError`. NaNs are not
+/// canonicalized.
+pub struct FuncGen<'a> {
+    // Immutable properties assigned at creation time.
+    /// Static module information.
+    module: &'a ModuleInfo,
+
+    /// ModuleInfo compilation config.
+    config: &'a Singlepass,
+
+    /// Offsets of vmctx fields.
+    vmoffsets: &'a VMOffsets,
+
+    // // Memory plans.
+    memory_styles: &'a PrimaryMap<MemoryIndex, MemoryStyle>,
+
+    /// Function signature.
+    signature: FunctionType,
+
+    // Working storage.
+    /// The assembler.
+    assembler: Assembler,
+
+    /// Memory locations of local variables.
+    locals: Vec<Location>,
+
+    /// Types of local variables, including arguments.
+    local_types: Vec<WpType>,
+
+    /// Value stack.
+    value_stack: Vec<Location>,
+
+    /// A list of frames describing the current control stack.
+    control_stack: Vec<ControlFrame>,
+
+    /// Low-level machine state.
+    machine: Machine,
+
+    /// Nesting level of unreachable code.
+    unreachable_depth: usize,
+
+    /// Function state map. Not yet used in the reborn version but let's keep it.
+    fsm: FunctionStateMap,
+
+    /// Trap table.
+    trap_table: TrapTable,
+
+    /// Relocation information.
+    relocations: Vec<Relocation>,
+
+    /// A set of special labels for trapping.
+    special_labels: SpecialLabelSet,
+
+    /// The source location for the current operator.
+    src_loc: u32,
+
+    /// Map from byte offset into wasm function to range of native instructions.
+    ///
+    // Ordered by increasing InstructionAddressMap::srcloc.
+    instructions_address_map: Vec<InstructionAddressMap>,
+}
+
+struct SpecialLabelSet {
+    integer_division_by_zero: DynamicLabel,
+    integer_overflow: DynamicLabel,
+    bad_conversion_to_integer: DynamicLabel,
+    heap_access_oob: DynamicLabel,
+    table_access_oob: DynamicLabel,
+    indirect_call_null: DynamicLabel,
+    bad_signature: DynamicLabel,
+    interrupt: DynamicLabel,
+    stack_overflow: DynamicLabel,
+}
+
+#[derive(Debug)]
+pub struct ControlFrame {
+    pub label: DynamicLabel,
+    pub loop_like: bool,
+    pub if_else: IfElseState,
+    pub returns: SmallVec<[WpType; 1]>,
+    pub value_stack_depth: usize,
+    pub state: MachineState,
+    pub state_diff_id: usize,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum IfElseState {
+    None,
+    If(DynamicLabel),
+    Else,
+}
+
+/// Abstraction for a 2-input, 1-output operator. Can be an integer/floating-point
+/// binop/cmpop.
+struct I2O1 {
+    loc_a: Location,
+    loc_b: Location,
+    ret: Location,
+}
+
+impl<'a> FuncGen<'a> {
+    /// Set the source location of the Wasm to the given offset.
+    pub fn set_srcloc(&mut self, offset: u32) {
+        self.src_loc = offset;
+    }
+
+    fn get_location_released(&mut self, loc: Location) -> Location {
+        self.machine.release_locations(&mut self.assembler, &[loc]);
+        loc
+    }
+
+    fn pop_value_released(&mut self) -> Location {
+        let loc = self
+            .value_stack
+            .pop()
+            .expect("pop_value_released: value stack is empty");
+        self.get_location_released(loc)
+    }
+
+    /// Acquires a location for a value of type `ty` and pushes it on the value stack.
+    fn push_value(&mut self, ty: WpType) -> Location {
+        let loc = self.machine.acquire_locations(
+            &mut self.assembler,
+            &[(ty, MachineValue::WasmStack(self.value_stack.len()))],
+            false,
+        )[0];
+        self.value_stack.push(loc);
+        loc
+    }
+
+    /// Prepare data for binary operator with 2 inputs and 1 output.
+    fn i2o1_prepare(&mut self, ty: WpType) -> I2O1 {
+        let loc_b = self.pop_value_released();
+        let loc_a = self.pop_value_released();
+        let ret = self.push_value(ty);
+        I2O1 { loc_a, loc_b, ret }
+    }
+
+    fn mark_trappable(&mut self) {
+        let state_diff_id = self.get_state_diff();
+        let offset = self.assembler.get_offset().0;
+        self.fsm.trappable_offsets.insert(
+            offset,
+            OffsetInfo {
+                end_offset: offset + 1,
+                activate_offset: offset,
+                diff_id: state_diff_id,
+            },
+        );
+        self.fsm.wasm_offset_to_target_offset.insert(
+            self.machine.state.wasm_inst_offset,
+            SuspendOffset::Trappable(offset),
+        );
+    }
+
+    /// Marks each address in the code range emitted by `f` with the trap code `code`.
+    fn mark_range_with_trap_code<F: FnOnce(&mut Self) -> R, R>(
+        &mut self,
+        code: TrapCode,
+        f: F,
+    ) -> R {
+        let begin = self.assembler.get_offset().0;
+        let ret = f(self);
+        let end = self.assembler.get_offset().0;
+        for i in (begin..end).step_by(4) {
+            self.trap_table.offset_to_code.insert(i, code);
+        }
+        self.mark_instruction_address_end(begin);
+        ret
+    }
+
+    /// Marks one address as trappable with trap code `code`.
+    fn mark_address_with_trap_code(&mut self, code: TrapCode) {
+        let offset = self.assembler.get_offset().0;
+        self.trap_table.offset_to_code.insert(offset, code);
+        self.mark_instruction_address_end(offset);
+    }
+
+    /// Jumps to the `interrupt` special label if the interrupt flag of the
+    /// `VMInterrupts` is set.
+    fn emit_interrupt_check(&mut self) {
+        let interrupts = self.machine.acquire_temp_gpr().unwrap();
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(
+                Machine::get_vmctx_reg(),
+                self.vmoffsets.vmctx_interrupts() as i32,
+            ),
+            Location::GPR(interrupts),
+        );
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(interrupts, self.vmoffsets.vminterrupts_interrupt() as i32),
+            Location::GPR(interrupts),
+        );
+        self.assembler.emit_cmp_imm(Size::S64, interrupts, 0);
+        self.assembler
+            .emit_jmp(Condition::Ne, self.special_labels.interrupt);
+        self.machine.release_temp_gpr(interrupts);
+    }
+
+    /// Jumps to the `stack_overflow` special label if the stack pointer is below the
+    /// stack limit of the `VMInterrupts`.
+    fn emit_stack_check(&mut self) {
+        let limit = self.machine.acquire_temp_gpr().unwrap();
+        let sp = self.machine.acquire_temp_gpr().unwrap();
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(
+                Machine::get_vmctx_reg(),
+                self.vmoffsets.vmctx_interrupts() as i32,
+            ),
+            Location::GPR(limit),
+        );
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(limit, self.vmoffsets.vminterrupts_stack_limit() as i32),
+            Location::GPR(limit),
+        );
+        // A zero limit is never above the stack pointer.
+        self.assembler.emit_mov_from_sp(sp);
+        self.assembler.emit_cmp(Size::S64, sp, limit);
+        self.assembler
+            .emit_jmp(Condition::Lo, self.special_labels.stack_overflow);
+        self.machine.release_temp_gpr(sp);
+        self.machine.release_temp_gpr(limit);
+    }
+
+    /// Returns a register holding the `sz` value at `loc`, loading it into a temporary
+    /// register added to `temps` if needed.
+    fn location_to_gpr(&mut self, sz: Size, loc: Location, temps: &mut Vec<GPR>) -> GPR {
+        match loc {
+            Location::GPR(x) => x,
+            _ => {
+                let tmp = self.machine.acquire_temp_gpr().unwrap();
+                temps.push(tmp);
+                self.assembler.emit_mov(sz, loc, Location::GPR(tmp));
+                tmp
+            }
+        }
+    }
+
+    /// Returns a register holding the `sz` floating point value at `loc`, loading it
+    /// into a temporary register added to `temps` if needed.
+    fn location_to_neon(&mut self, sz: Size, loc: Location, temps: &mut Vec<NEON>) -> NEON {
+        match loc {
+            Location::SIMD(x) => x,
+            _ => {
+                let tmp = self.machine.acquire_temp_simd().unwrap();
+                temps.push(tmp);
+                self.assembler.emit_mov(sz, loc, Location::SIMD(tmp));
+                tmp
+            }
+        }
+    }
+
+    /// Returns the register to compute a value into before it is stored to `ret`.
+    fn gpr_for_result(&mut self, ret: Location, temps: &mut Vec<GPR>) -> GPR {
+        match ret {
+            Location::GPR(x) => x,
+            _ => {
+                let tmp = self.machine.acquire_temp_gpr().unwrap();
+                temps.push(tmp);
+                tmp
+            }
+        }
+    }
+
+    /// Returns the register to compute a floating point value into before it is stored
+    /// to `ret`.
+    fn neon_for_result(&mut self, ret: Location, temps: &mut Vec<NEON>) -> NEON {
+        match ret {
+            Location::SIMD(x) => x,
+            _ => {
+                let tmp = self.machine.acquire_temp_simd().unwrap();
+                temps.push(tmp);
+                tmp
+            }
+        }
+    }
+
+    /// Stores the value computed in `src` to `ret`, and releases the temporary registers.
+    ///
+    /// 32-bit operations clear the upper bits of their destination register, so the whole
+    /// register is always stored.
+    fn store_result(&mut self, src: Location, ret: Location, gprs: Vec<GPR>, neons: Vec<NEON>) {
+        if src != ret {
+            self.assembler.emit_mov(Size::S64, src, ret);
+        }
+        for r in gprs.into_iter().rev() {
+            self.machine.release_temp_gpr(r);
+        }
+        for r in neons.into_iter().rev() {
+            self.machine.release_temp_simd(r);
+        }
+    }
+
+    fn emit_binop_i(&mut self, sz: Size, f: fn(&mut Assembler, Size, GPR, GPR, GPR)) {
+        let ty = if sz == Size::S32 {
+            WpType::I32
+        } else {
+            WpType::I64
+        };
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(ty);
+        let mut temps = vec![];
+        let a = self.location_to_gpr(sz, loc_a, &mut temps);
+        let b = self.location_to_gpr(sz, loc_b, &mut temps);
+        let dst = self.gpr_for_result(ret, &mut temps);
+        f(&mut self.assembler, sz, a, b, dst);
+        self.store_result(Location::GPR(dst), ret, temps, vec![]);
+    }
+
+    fn emit_rotl(&mut self, sz: Size) {
+        let ty = if sz == Size::S32 {
+            WpType::I32
+        } else {
+            WpType::I64
+        };
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(ty);
+        let mut temps = vec![];
+        let a = self.location_to_gpr(sz, loc_a, &mut temps);
+        let b = self.location_to_gpr(sz, loc_b, &mut temps);
+        // Rotating left by `n` is rotating right by `-n`, modulo the size.
+        let count = self.machine.acquire_temp_gpr().unwrap();
+        temps.push(count);
+        self.assembler.emit_neg(sz, b, count);
+        let dst = self.gpr_for_result(ret, &mut temps);
+        self.assembler.emit_ror(sz, a, count, dst);
+        self.store_result(Location::GPR(dst), ret, temps, vec![]);
+    }
+
+    /// Emits a division or a remainder, checking the divisor and the overflow of signed
+    /// divisions.
+    fn emit_div_i(&mut self, sz: Size, signed: bool, rem: bool) {
+        let ty = if sz == Size::S32 {
+            WpType::I32
+        } else {
+            WpType::I64
+        };
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(ty);
+        let mut temps = vec![];
+        let a = self.location_to_gpr(sz, loc_a, &mut temps);
+        let b = self.location_to_gpr(sz, loc_b, &mut temps);
+
+        self.assembler.emit_cmp_imm(sz, b, 0);
+        self.assembler
+            .emit_jmp(Condition::Eq, self.special_labels.integer_division_by_zero);
+
+        // `sdiv` does not trap on overflow, and the remainder of `MIN / -1` is 0 as required.
+        if signed && !rem {
+            let (all_ones, min) = match sz {
+                Size::S32 => (u32::MAX as u64, i32::MIN as u32 as u64),
+                _ => (u64::MAX, i64::MIN as u64),
+            };
+            let no_overflow = self.assembler.get_label();
+            self.assembler.emit_cmp_imm(sz, b, all_ones);
+            self.assembler.emit_jmp(Condition::Ne, no_overflow);
+            self.assembler.emit_cmp_imm(sz, a, min);
+            self.assembler
+                .emit_jmp(Condition::Eq, self.special_labels.integer_overflow);
+            self.assembler.emit_label(no_overflow);
+        }
+
+        let div = if signed {
+            Assembler::emit_sdiv
+        } else {
+            Assembler::emit_udiv
+        };
+        if rem {
+            let quotient = self.machine.acquire_temp_gpr().unwrap();
+            temps.push(quotient);
+            div(&mut self.assembler, sz, a, b, quotient);
+            let dst = self.gpr_for_result(ret, &mut temps);
+            self.assembler.emit_msub(sz, quotient, b, a, dst);
+            self.store_result(Location::GPR(dst), ret, temps, vec![]);
+        } else {
+            let dst = self.gpr_for_result(ret, &mut temps);
+            div(&mut self.assembler, sz, a, b, dst);
+            self.store_result(Location::GPR(dst), ret, temps, vec![]);
+        }
+    }
+
+    fn emit_cmpop_i(&mut self, sz: Size, c: Condition) {
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
+        let mut temps = vec![];
+        let a = self.location_to_gpr(sz, loc_a, &mut temps);
+        let b = self.location_to_gpr(sz, loc_b, &mut temps);
+        self.assembler.emit_cmp(sz, a, b);
+        let dst = self.gpr_for_result(ret, &mut temps);
+        self.assembler.emit_cset(dst, c);
+        self.store_result(Location::GPR(dst), ret, temps, vec![]);
+    }
+
+    fn emit_eqz(&mut self, sz: Size) {
+        let loc = self.pop_value_released();
+        let ret = self.push_value(WpType::I32);
+        let mut temps = vec![];
+        let src = self.location_to_gpr(sz, loc, &mut temps);
+        self.assembler.emit_cmp_imm(sz, src, 0);
+        let dst = self.gpr_for_result(ret, &mut temps);
+        self.assembler.emit_cset(dst, Condition::Eq);
+        self.store_result(Location::GPR(dst), ret, temps, vec![]);
+    }
+
+    /// Emits a unary integer operator reading a `sz_src` value and producing a `ty` value.
+    fn emit_unop_i<F: FnOnce(&mut Self, GPR, GPR)>(&mut self, sz_src: Size, ty: WpType, f: F) {
+        let loc = self.pop_value_released();
+        let ret = self.push_value(ty);
+        let mut temps = vec![];
+        let src = self.location_to_gpr(sz_src, loc, &mut temps);
+        let dst = self.gpr_for_result(ret, &mut temps);
+        f(self, src, dst);
+        self.store_result(Location::GPR(dst), ret, temps, vec![]);
+    }
+
+    fn emit_fp_binop(&mut self, sz: Size, f: fn(&mut Assembler, Size, NEON, NEON, NEON)) {
+        let ty = if sz == Size::S32 {
+            WpType::F32
+        } else {
+            WpType::F64
+        };
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(ty);
+        let mut temps = vec![];
+        let a = self.location_to_neon(sz, loc_a, &mut temps);
+        let b = self.location_to_neon(sz, loc_b, &mut temps);
+        let dst = self.neon_for_result(ret, &mut temps);
+        f(&mut self.assembler, sz, a, b, dst);
+        self.store_result(Location::SIMD(dst), ret, vec![], temps);
+    }
+
+    fn emit_fp_unop(&mut self, sz: Size, f: fn(&mut Assembler, Size, NEON, NEON)) {
+        let ty = if sz == Size::S32 {
+            WpType::F32
+        } else {
+            WpType::F64
+        };
+        let loc = self.pop_value_released();
+        let ret = self.push_value(ty);
+        let mut temps = vec![];
+        let src = self.location_to_neon(sz, loc, &mut temps);
+        let dst = self.neon_for_result(ret, &mut temps);
+        f(&mut self.assembler, sz, src, dst);
+        self.store_result(Location::SIMD(dst), ret, vec![], temps);
+    }
+
+    /// Emits a float comparison. The conditions are chosen so that they do not hold when
+    /// the operands are unordered, except for `Ne`.
+    fn emit_fp_cmpop(&mut self, sz: Size, c: Condition) {
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
+        let mut temps = vec![];
+        let a = self.location_to_neon(sz, loc_a, &mut temps);
+        let b = self.location_to_neon(sz, loc_b, &mut temps);
+        self.assembler.emit_fcmp(sz, a, b);
+        for r in temps.into_iter().rev() {
+            self.machine.release_temp_simd(r);
+        }
+        let mut gprs = vec![];
+        let dst = self.gpr_for_result(ret, &mut gprs);
+        self.assembler.emit_cset(dst, c);
+        self.store_result(Location::GPR(dst), ret, gprs, vec![]);
+    }
+
+    /// Copies the sign of the second operand to the first one, on their bit patterns.
+    fn emit_fp_copysign(&mut self, sz: Size) {
+        let ty = if sz == Size::S32 {
+            WpType::F32
+        } else {
+            WpType::F64
+        };
+        let sign_mask = match sz {
+            Size::S32 => 0x8000_0000,
+            _ => 0x8000_0000_0000_0000,
+        };
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(ty);
+        let a = self.machine.acquire_temp_gpr().unwrap();
+        let b = self.machine.acquire_temp_gpr().unwrap();
+        let mask = self.machine.acquire_temp_gpr().unwrap();
+        self.assembler.emit_mov(sz, loc_a, Location::GPR(a));
+        self.assembler.emit_mov(sz, loc_b, Location::GPR(b));
+        self.assembler.emit_mov_imm(mask, sign_mask);
+        self.assembler.emit_and(sz, b, mask, b);
+        self.assembler.emit_mov_imm(mask, sign_mask - 1);
+        self.assembler.emit_and(sz, a, mask, a);
+        self.assembler.emit_orr(sz, a, b, a);
+        self.assembler.emit_mov(sz, Location::GPR(a), ret);
+        self.machine.release_temp_gpr(mask);
+        self.machine.release_temp_gpr(b);
+        self.machine.release_temp_gpr(a);
+    }
+
+    /// Converts between `f32` and `f64`.
+    fn emit_fp_convert(&mut self, sz_src: Size) {
+        let ty = if sz_src == Size::S32 {
+            WpType::F64
+        } else {
+            WpType::F32
+        };
+        let loc = self.pop_value_released();
+        let ret = self.push_value(ty);
+        let mut temps = vec![];
+        let src = self.location_to_neon(sz_src, loc, &mut temps);
+        let dst = self.neon_for_result(ret, &mut temps);
+        self.assembler.emit_fcvt(sz_src, src, dst);
+        self.store_result(Location::SIMD(dst), ret, vec![], temps);
+    }
+
+    /// Moves the bit pattern of a value to a location of another type.
+    fn emit_reinterpret(&mut self, sz: Size, ty: WpType) {
+        let loc = self.pop_value_released();
+        let ret = self.push_value(ty);
+        self.assembler.emit_mov(sz, loc, ret);
+    }
+
+    /// Converts a float to an integer, rounding towards zero.
+    ///
+    /// `fcvtzs`/`fcvtzu` saturate, and convert NaN to 0, which is what the saturating
+    /// operators need. The trapping operators check their input first.
+    fn emit_fp_to_int(&mut self, sz_src: Size, sz_dst: Size, signed: bool, sat: bool) {
+        let ty = if sz_dst == Size::S32 {
+            WpType::I32
+        } else {
+            WpType::I64
+        };
+        let loc = self.pop_value_released();
+        let ret = self.push_value(ty);
+        let mut neons = vec![];
+        let src = self.location_to_neon(sz_src, loc, &mut neons);
+
+        if !sat {
+            self.assembler.emit_fcmp(sz_src, src, src);
+            self.assembler
+                .emit_jmp(Condition::Vs, self.special_labels.bad_conversion_to_integer);
+
+            let (lower, upper) = trunc_bounds(sz_src, sz_dst, signed);
+            let bound = self.machine.acquire_temp_simd().unwrap();
+            self.assembler
+                .emit_mov(sz_src, Location::Imm64(lower), Location::SIMD(bound));
+            self.assembler.emit_fcmp(sz_src, src, bound);
+            self.assembler
+                .emit_jmp(Condition::Ls, self.special_labels.integer_overflow);
+            self.assembler
+                .emit_mov(sz_src, Location::Imm64(upper), Location::SIMD(bound));
+            self.assembler.emit_fcmp(sz_src, src, bound);
+            self.assembler
+                .emit_jmp(Condition::Ge, self.special_labels.integer_overflow);
+            self.machine.release_temp_simd(bound);
+        }
+
+        let mut gprs = vec![];
+        let dst = self.gpr_for_result(ret, &mut gprs);
+        if signed {
+            self.assembler.emit_fcvtzs(sz_src, src, sz_dst, dst);
+        } else {
+            self.assembler.emit_fcvtzu(sz_src, src, sz_dst, dst);
+        }
+        self.store_result(Location::GPR(dst), ret, gprs, neons);
+    }
+
+    /// Converts an integer to a float.
+    fn emit_int_to_fp(&mut self, sz_src: Size, sz_dst: Size, signed: bool) {
+        let ty = if sz_dst == Size::S32 {
+            WpType::F32
+        } else {
+            WpType::F64
+        };
+        let loc = self.pop_value_released();
+        let ret = self.push_value(ty);
+        let mut gprs = vec![];
+        let src = self.location_to_gpr(sz_src, loc, &mut gprs);
+        let mut neons = vec![];
+        let dst = self.neon_for_result(ret, &mut neons);
+        if signed {
+            self.assembler.emit_scvtf(sz_src, src, sz_dst, dst);
+        } else {
+            self.assembler.emit_ucvtf(sz_src, src, sz_dst, dst);
+        }
+        self.store_result(Location::SIMD(dst), ret, gprs, neons);
+    }
+
+    /// Returns the result type of a block. Blocks with parameters or several results are
+    /// not supported.
+    fn block_signature(&self, ty: WpTypeOrFuncType) -> Result<SmallVec<[WpType; 1]>, CodegenError> {
+        match ty {
+            WpTypeOrFuncType::Type(WpType::EmptyBlockType) => Ok(smallvec![]),
+            WpTypeOrFuncType::Type(WpType::V128) => Err(CodegenError {
+                message: "v128 values are not supported on ARM64".to_string(),
+            }),
+            WpTypeOrFuncType::Type(inner_ty) => Ok(smallvec![inner_ty]),
+            WpTypeOrFuncType::FuncType(sig_index) => {
+                let sig = &self.module.signatures[SignatureIndex::new(sig_index as usize)];
+                if !sig.params().is_empty() || sig.results().len() > 1 {
+                    return Err(CodegenError {
+                        message: "multi-value blocks are not supported on ARM64".to_string(),
+                    });
+                }
+                Ok(sig.results().iter().cloned().map(type_to_wp_type).collect())
+            }
+        }
+    }
+
+    /// Moves the result of a block from the top of the value stack to X0, where the end of
+    /// the block expects it.
+    fn emit_block_results(&mut self, returns: &[WpType]) {
+        if !returns.is_empty() {
+            let loc = *self.value_stack.last().unwrap();
+            self.assembler
+                .emit_mov(Size::S64, loc, Location::GPR(GPR::X0));
+        }
+    }
+
+    /// Pushes the result of a block onto the value stack, from where
+    /// `emit_block_results` left it.
+    fn emit_load_block_results(&mut self, returns: &[WpType]) {
+        if let Some(&ty) = returns.first() {
+            let loc = self.push_value(ty);
+            self.assembler
+                .emit_mov(Size::S64, Location::GPR(GPR::X0), loc);
+        }
+    }
+
+    /// Emits a branch to the frame at `frame_index` in the control stack, with the value
+    /// on top of the value stack as its result.
+    fn emit_branch(&mut self, frame_index: usize) {
+        let frame = &self.control_stack[frame_index];
+        if !frame.loop_like {
+            let returns = frame.returns.clone();
+            self.emit_block_results(&returns);
+        }
+        let frame = &self.control_stack[frame_index];
+        let label = frame.label;
+        let released = &self.value_stack[frame.value_stack_depth..];
+        self.machine
+            .release_locations_keep_state(&mut self.assembler, released);
+        self.assembler.emit_jmp(Condition::Al, label);
+    }
+
+    /// Emits a call following the singlepass calling convention, which is the AAPCS64
+    /// with every parameter passed like an integer.
+    ///
+    /// The used registers are saved below the stack pointer, under the parameters passed
+    /// on the stack. `cb` emits the call itself, once the vmctx is in X0 and the
+    /// parameters are in place.
+    ///
+    /// The caller MUST NOT hold any temporary registers allocated by `acquire_temp_gpr` when calling
+    /// this function.
+    fn emit_call<I: Iterator<Item = Location>, F: FnOnce(&mut Self)>(
+        &mut self,
+        cb: F,
+        params: I,
+    ) -> Result<(), CodegenError> {
+        // Values pushed in this function are above the shadow region.
+        self.machine
+            .state
+            .stack_values
+            .push(MachineValue::ExplicitShadow);
+
+        let params: Vec<_> = params.collect();
+        let used_gprs = self.machine.get_used_gprs();
+        let used_neons = self.machine.get_used_simd();
+
+        let n_stack_params = params
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| matches!(Machine::get_param_location(1 + i), Location::Memory(_, _)))
+            .count();
+        let save_area = n_stack_params * 8;
+        let frame_size = (save_area + (used_gprs.len() + used_neons.len()) * 8 + 15) & !15;
+        if frame_size > 0 {
+            self.assembler.emit_sub_sp(frame_size as u32);
+        }
+
+        // Save used registers.
+        for (i, r) in used_gprs.iter().enumerate() {
+            self.assembler.emit_mov(
+                Size::S64,
+                Location::GPR(*r),
+                Location::Memory(GPR::XzrSp, (save_area + i * 8) as i32),
+            );
+            let content =
+                self.machine.state.register_values[ARM64Register::GPR(*r).to_index().0].clone();
+            if content == MachineValue::Undefined {
+                return Err(CodegenError {
+                    message: "emit_call: Undefined used_gprs content".to_string(),
+                });
+            }
+            self.machine.state.stack_values.push(content);
+        }
+        let neon_area = save_area + used_gprs.len() * 8;
+        for (i, r) in used_neons.iter().enumerate() {
+            self.assembler.emit_mov(
+                Size::S64,
+                Location::SIMD(*r),
+                Location::Memory(GPR::XzrSp, (neon_area + i * 8) as i32),
+            );
+            let content =
+                self.machine.state.register_values[ARM64Register::NEON(*r).to_index().0].clone();
+            if content == MachineValue::Undefined {
+                return Err(CodegenError {
+                    message: "emit_call: Undefined used_neons content".to_string(),
+                });
+            }
+            self.machine.state.stack_values.push(content);
+        }
+
+        // Prepare register & stack parameters. The parameter registers are temporary
+        // registers, which hold no value here, so the moves can't overwrite each other.
+        let mut n_stack_args = 0;
+        for (i, param) in params.iter().enumerate() {
+            match Machine::get_param_location(1 + i) {
+                Location::GPR(x) => {
+                    self.assembler.emit_mov(Size::S64, *param, Location::GPR(x));
+                }
+                Location::Memory(_, _) => {
+                    self.assembler.emit_mov(
+                        Size::S64,
+                        *param,
+                        Location::Memory(GPR::XzrSp, n_stack_args * 8),
+                    );
+                    n_stack_args += 1;
+                }
+                _ => {
+                    return Err(CodegenError {
+                        message: "emit_call loc: unreachable code".to_string(),
+                    })
+                }
+            }
+        }
+
+        // Put vmctx as the first parameter.
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::GPR(Machine::get_vmctx_reg()),
+            Machine::get_param_location(0),
+        ); // vmctx
+
+        cb(self);
+
+        // Offset needs to be after the call instruction.
+        {
+            let state_diff_id = self.get_state_diff();
+            let offset = self.assembler.get_offset().0;
+            self.fsm.call_offsets.insert(
+                offset,
+                OffsetInfo {
+                    end_offset: offset + 1,
+                    activate_offset: offset,
+                    diff_id: state_diff_id,
+                },
+            );
+            self.fsm.wasm_offset_to_target_offset.insert(
+                self.machine.state.wasm_inst_offset,
+                SuspendOffset::Call(offset),
+            );
+        }
+
+        // Restore used registers.
+        for (i, r) in used_neons.iter().enumerate() {
+            self.assembler.emit_mov(
+                Size::S64,
+                Location::Memory(GPR::XzrSp, (neon_area + i * 8) as i32),
+                Location::SIMD(*r),
+            );
+            self.machine.state.stack_values.pop().unwrap();
+        }
+        for (i, r) in used_gprs.iter().enumerate() {
+            self.assembler.emit_mov(
+                Size::S64,
+                Location::Memory(GPR::XzrSp, (save_area + i * 8) as i32),
+                Location::GPR(*r),
+            );
+            self.machine.state.stack_values.pop().unwrap();
+        }
+        if frame_size > 0 {
+            self.assembler.emit_add_sp(frame_size as u32);
+        }
+
+        if self.machine.state.stack_values.pop().unwrap() != MachineValue::ExplicitShadow {
+            return Err(CodegenError {
+                message: "emit_call: Popped value is not ExplicitShadow".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Emits a call to the native function at `[vmctx + offset]`.
+    fn emit_call_vmctx_function(&mut self, offset: u32) {
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(Machine::get_vmctx_reg(), offset as i32),
+            Location::GPR(GPR::X16),
+        );
+        self.assembler.emit_blr(GPR::X16);
+    }
+
+    /// Emits a call to a runtime builtin function.
+    ///
+    /// The builtin is called with the vmctx, the `immediates` and the `n_operands` values
+    /// popped from the value stack, in that order.
+    fn emit_builtin_call(
+        &mut self,
+        builtin: VMBuiltinFunctionIndex,
+        immediates: &[u32],
+        n_operands: usize,
+    ) -> Result<(), CodegenError> {
+        let operands: SmallVec<[Location; 4]> = self
+            .value_stack
+            .drain(self.value_stack.len() - n_operands..)
+            .collect();
+        self.machine.release_locations_only_regs(&operands);
+        self.machine
+            .release_locations_only_osr_state(operands.len());
+
+        let offset = self.vmoffsets.vmctx_builtin_function(builtin);
+        self.emit_call(
+            |this| this.emit_call_vmctx_function(offset),
+            immediates
+                .iter()
+                .map(|&x| Location::Imm32(x))
+                .chain(operands.iter().cloned()),
+        )?;
+
+        self.machine
+            .release_locations_only_stack(&mut self.assembler, &operands);
+        Ok(())
+    }
+
+    /// Pushes the result of a call, returned in X0, or V0 for floats.
+    fn emit_load_call_result(&mut self, return_types: &[WpType]) {
+        if let Some(&ty) = return_types.first() {
+            let ret = self.push_value(ty);
+            if ty.is_float() {
+                self.assembler
+                    .emit_mov(Size::S64, Location::SIMD(NEON::V0), ret);
+            } else {
+                self.assembler
+                    .emit_mov(Size::S64, Location::GPR(GPR::X0), ret);
+            }
+        }
+    }
+
+    /// Emits a memory operation.
+    fn emit_memory_op<F: FnOnce(&mut Self, GPR) -> Result<(), CodegenError>>(
+        &mut self,
+        addr: Location,
+        memarg: &MemoryImmediate,
+        value_size: usize,
+        cb: F,
+    ) -> Result<(), CodegenError> {
+        let need_check = match self.memory_styles[MemoryIndex::new(0)] {
+            MemoryStyle::Static { .. } => false,
+            MemoryStyle::Dynamic { .. } => true,
+        };
+        let tmp_addr = self.machine.acquire_temp_gpr().unwrap();
+
+        // Reusing `tmp_addr` for temporary indirection here, since it's not used before the last reference to `{base,bound}_loc`.
+        let (base_loc, bound_loc) = if self.module.num_imported_memories != 0 {
+            // Imported memories require one level of indirection.
+            let offset = self
+                .vmoffsets
+                .vmctx_vmmemory_import_definition(MemoryIndex::new(0));
+            self.assembler.emit_mov(
+                Size::S64,
+                Location::Memory(Machine::get_vmctx_reg(), offset as i32),
+                Location::GPR(tmp_addr),
+            );
+            (Location::Memory(tmp_addr, 0), Location::Memory(tmp_addr, 8))
+        } else {
+            let offset = self
+                .vmoffsets
+                .vmctx_vmmemory_definition(LocalMemoryIndex::new(0));
+            (
+                Location::Memory(Machine::get_vmctx_reg(), offset as i32),
+                Location::Memory(Machine::get_vmctx_reg(), (offset + 8) as i32),
+            )
+        };
+
+        let tmp_base = self.machine.acquire_temp_gpr().unwrap();
+        let tmp_bound = self.machine.acquire_temp_gpr().unwrap();
+
+        // Load base into temporary register.
+        self.assembler
+            .emit_mov(Size::S64, base_loc, Location::GPR(tmp_base));
+
+        // Load bound into temporary register, if needed.
+        if need_check {
+            self.assembler
+                .emit_mov(Size::S64, bound_loc, Location::GPR(tmp_bound));
+
+            // The maximum allowed beginning of word is (inclusively)
+            // `tmp_bound + tmp_base - value_size`.
+            self.assembler
+                .emit_add(Size::S64, tmp_bound, tmp_base, tmp_bound);
+            self.assembler
+                .emit_add_offset(tmp_bound, -(value_size as i64), tmp_bound);
+        }
+
+        // Load effective address, zero-extended.
+        // `base_loc` and `bound_loc` becomes INVALID after this line, because `tmp_addr`
+        // might be reused.
+        self.assembler
+            .emit_mov(Size::S32, addr, Location::GPR(tmp_addr));
+
+        // Add offset to memory address.
+        if memarg.offset != 0 {
+            self.assembler
+                .emit_add_offset(tmp_addr, memarg.offset as i64, tmp_addr);
+
+            // Trap if offset calculation overflowed 32 bits.
+            self.assembler
+                .emit_cmp_imm(Size::S64, tmp_addr, u32::MAX as u64);
+            self.assembler
+                .emit_jmp(Condition::Hi, self.special_labels.heap_access_oob);
+        }
+
+        // Wasm linear memory -> real memory
+        self.assembler
+            .emit_add(Size::S64, tmp_base, tmp_addr, tmp_addr);
+
+        if need_check {
+            // `tmp_bound` is inclusive. So trap only if `tmp_addr > tmp_bound`.
+            self.assembler.emit_cmp(Size::S64, tmp_addr, tmp_bound);
+            self.assembler
+                .emit_jmp(Condition::Hi, self.special_labels.heap_access_oob);
+        }
+
+        self.machine.release_temp_gpr(tmp_bound);
+        self.machine.release_temp_gpr(tmp_base);
+
+        self.mark_range_with_trap_code(TrapCode::HeapAccessOutOfBounds, |this| cb(this, tmp_addr))?;
+
+        self.machine.release_temp_gpr(tmp_addr);
+        Ok(())
+    }
+
+    /// Emits a load of a `sz` value, sign-extended to `sext` if set, and zero-extended
+    /// otherwise.
+    fn emit_load(
+        &mut self,
+        memarg: &MemoryImmediate,
+        ty: WpType,
+        sz: Size,
+        sext: Option<Size>,
+    ) -> Result<(), CodegenError> {
+        let target = self.pop_value_released();
+        let ret = self.push_value(ty);
+        let value_size = match sz {
+            Size::S8 => 1,
+            Size::S16 => 2,
+            Size::S32 => 4,
+            Size::S64 => 8,
+        };
+        self.emit_memory_op(target, memarg, value_size, |this, addr| {
+            match (sext, ret) {
+                // Full-width values are moved as is, to registers or to stack slots.
+                (None, _) if sz >= Size::S32 => {
+                    this.assembler.emit_mov(sz, Location::Memory(addr, 0), ret);
+                }
+                (None, Location::GPR(dst)) => {
+                    this.assembler
+                        .emit_mov(sz, Location::Memory(addr, 0), Location::GPR(dst));
+                }
+                (Some(sz_dst), Location::GPR(dst)) => {
+                    this.assembler.emit_ldr_sext(sz, sz_dst, addr, 0, dst);
+                }
+                // Narrow values are extended in the address register first.
+                (None, _) => {
+                    this.assembler
+                        .emit_mov(sz, Location::Memory(addr, 0), Location::GPR(addr));
+                    this.assembler.emit_mov(Size::S64, Location::GPR(addr), ret);
+                }
+                (Some(sz_dst), _) => {
+                    this.assembler.emit_ldr_sext(sz, sz_dst, addr, 0, addr);
+                    this.assembler.emit_mov(Size::S64, Location::GPR(addr), ret);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Emits a store of the `sz` low bits of a value.
+    fn emit_store(&mut self, memarg: &MemoryImmediate, sz: Size) -> Result<(), CodegenError> {
+        let target_value = self.pop_value_released();
+        let target_addr = self.pop_value_released();
+        let value_size = match sz {
+            Size::S8 => 1,
+            Size::S16 => 2,
+            Size::S32 => 4,
+            Size::S64 => 8,
+        };
+        self.emit_memory_op(target_addr, memarg, value_size, |this, addr| {
+            this.assembler
+                .emit_mov(sz, target_value, Location::Memory(addr, 0));
+            Ok(())
+        })
+    }
+
+    pub fn get_state_diff(&mut self) -> usize {
+        if !self.machine.track_state {
+            return usize::MAX;
+        }
+        let last_frame = self.control_stack.last_mut().unwrap();
+        let mut diff = self.machine.state.diff(&last_frame.state);
+        diff.last = Some(last_frame.state_diff_id);
+        let id = self.fsm.diffs.len();
+        last_frame.state = self.machine.state.clone();
+        last_frame.state_diff_id = id;
+        self.fsm.diffs.push(diff);
+        id
+    }
+
+    fn emit_head(&mut self) -> Result<(), CodegenError> {
+        // Save the frame pointer and the link register.
+        self.assembler.emit_prologue();
+
+        // Initialize locals.
+        self.locals = self.machine.init_locals(
+            &mut self.assembler,
+            &self.local_types,
+            self.signature.params().len(),
+            false,
+            0,
+        );
+
+        // Mark vmctx register. The actual loading of the vmctx value is handled by init_local.
+        self.machine.state.register_values
+            [ARM64Register::GPR(Machine::get_vmctx_reg()).to_index().0] = MachineValue::Vmctx;
+
+        let diff = self.machine.state.diff(&new_machine_state());
+        let state_diff_id = self.fsm.diffs.len();
+        self.fsm.diffs.push(diff);
+
+        // The frame of the function, with its locals, is allocated at this point.
+        if self.config.enable_stack_check {
+            self.emit_stack_check();
+        }
+
+        self.control_stack.push(ControlFrame {
+            label: self.assembler.get_label(),
+            loop_like: false,
+            if_else: IfElseState::None,
+            returns: self
+                .signature
+                .results()
+                .iter()
+                .map(|&x| type_to_wp_type(x))
+                .collect(),
+            value_stack_depth: 0,
+            state: self.machine.state.clone(),
+            state_diff_id,
+        });
+
+        self.emit_interrupt_check();
+
+        // We insert set StackOverflow as the default trap that can happen
+        // anywhere in the function prologue.
+        let offset = 0;
+        self.trap_table
+            .offset_to_code
+            .insert(offset, TrapCode::StackOverflow);
+        self.mark_instruction_address_end(offset);
+
+        if self.machine.state.wasm_inst_offset != usize::MAX {
+            return Err(CodegenError {
+                message: "emit_head: wasm_inst_offset not usize::MAX".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Pushes the instruction to the address map, calculating the offset from a
+    /// provided beginning address.
+    fn mark_instruction_address_end(&mut self, begin: usize) {
+        self.instructions_address_map.push(InstructionAddressMap {
+            srcloc: SourceLoc::new(self.src_loc),
+            code_offset: begin,
+            code_len: self.assembler.get_offset().0 - begin,
+        });
+    }
+
+    pub fn new(
+        module: &'a ModuleInfo,
+        config: &'a Singlepass,
+        vmoffsets: &'a VMOffsets,
+        memory_styles: &'a PrimaryMap<MemoryIndex, MemoryStyle>,
+        _table_styles: &'a PrimaryMap<TableIndex, TableStyle>,
+        local_func_index: LocalFunctionIndex,
+        local_types_excluding_arguments: &[WpType],
+    ) -> Result<FuncGen<'a>, CodegenError> {
+        let func_index = module.func_index(local_func_index);
+        let sig_index = module.functions[func_index];
+        let signature = module.signatures[sig_index].clone();
+
+        let mut local_types: Vec<_> = signature
+            .params()
+            .iter()
+            .map(|&x| type_to_wp_type(x))
+            .collect();
+        local_types.extend_from_slice(local_types_excluding_arguments);
+        if local_types.contains(&WpType::V128) {
+            return Err(CodegenError {
+                message: "v128 values are not supported on ARM64".to_string(),
+            });
+        }
+
+        let fsm = FunctionStateMap::new(
+            new_machine_state(),
+            local_func_index.index(),
+            0,
+            (0..local_types.len())
+                .map(|_| WasmAbstractValue::Runtime)
+                .collect(),
+        );
+
+        let mut assembler = Assembler::new().unwrap();
+        let special_labels = SpecialLabelSet {
+            integer_division_by_zero: assembler.get_label(),
+            integer_overflow: assembler.get_label(),
+            bad_conversion_to_integer: assembler.get_label(),
+            heap_access_oob: assembler.get_label(),
+            table_access_oob: assembler.get_label(),
+            indirect_call_null: assembler.get_label(),
+            bad_signature: assembler.get_label(),
+            interrupt: assembler.get_label(),
+            stack_overflow: assembler.get_label(),
+        };
+
+        let mut fg = FuncGen {
+            module,
+            config,
+            vmoffsets,
+            memory_styles,
+            signature,
+            assembler,
+            locals: vec![], // initialization deferred to emit_head
+            local_types,
+            value_stack: vec![],
+            control_stack: vec![],
+            machine: Machine::new(),
+            unreachable_depth: 0,
+            fsm,
+            trap_table: TrapTable::default(),
+            relocations: vec![],
+            special_labels,
+            src_loc: 0,
+            instructions_address_map: vec![],
+        };
+        fg.emit_head()?;
+        Ok(fg)
+    }
+
+    pub fn has_control_frames(&self) -> bool {
+        !self.control_stack.is_empty()
+    }
+
+    pub fn feed_operator(&mut self, op: Operator) -> Result<(), CodegenError> {
+        self.machine.state.wasm_inst_offset = self.machine.state.wasm_inst_offset.wrapping_add(1);
+
+        let was_unreachable;
+
+        if self.unreachable_depth > 0 {
+            was_unreachable = true;
+
+            match op {
+                Operator::Block { .. } | Operator::Loop { .. } | Operator::If { .. } => {
+                    self.unreachable_depth += 1;
+                }
+                Operator::End => {
+                    self.unreachable_depth -= 1;
+                }
+                Operator::Else => {
+                    // We are in a reachable true branch
+                    if self.unreachable_depth == 1 {
+                        if let Some(IfElseState::If(_)) =
+                            self.control_stack.last().map(|x| x.if_else)
+                        {
+                            self.unreachable_depth -= 1;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            if self.unreachable_depth > 0 {
+                return Ok(());
+            }
+        } else {
+            was_unreachable = false;
+        }
+
+        match op {
+            Operator::GlobalGet { global_index } => {
+                let global_index = GlobalIndex::from_u32(global_index);
+
+                let ty = type_to_wp_type(self.module.globals[global_index].ty);
+                let loc = self.push_value(ty);
+
+                let tmp = self.machine.acquire_temp_gpr().unwrap();
+                let offset = if let Some(local_global_index) =
+                    self.module.local_global_index(global_index)
+                {
+                    self.vmoffsets.vmctx_vmglobal_definition(local_global_index)
+                } else {
+                    // Imported globals require one level of indirection.
+                    self.vmoffsets
+                        .vmctx_vmglobal_import_definition(global_index)
+                };
+                self.assembler.emit_mov(
+                    Size::S64,
+                    Location::Memory(Machine::get_vmctx_reg(), offset as i32),
+                    Location::GPR(tmp),
+                );
+                self.assembler
+                    .emit_mov(Size::S64, Location::Memory(tmp, 0), loc);
+
+                self.machine.release_temp_gpr(tmp);
+            }
+            Operator::GlobalSet { global_index } => {
+                let global_index = GlobalIndex::from_u32(global_index);
+                let tmp = self.machine.acquire_temp_gpr().unwrap();
+                let offset = if let Some(local_global_index) =
+                    self.module.local_global_index(global_index)
+                {
+                    self.vmoffsets.vmctx_vmglobal_definition(local_global_index)
+                } else {
+                    // Imported globals require one level of indirection.
+                    self.vmoffsets
+                        .vmctx_vmglobal_import_definition(global_index)
+                };
+                self.assembler.emit_mov(
+                    Size::S64,
+                    Location::Memory(Machine::get_vmctx_reg(), offset as i32),
+                    Location::GPR(tmp),
+                );
+                let loc = self.pop_value_released();
+                self.assembler
+                    .emit_mov(Size::S64, loc, Location::Memory(tmp, 0));
+                self.machine.release_temp_gpr(tmp);
+            }
+            Operator::LocalGet { local_index } => {
+                let local_index = local_index as usize;
+                let ret = self.push_value(WpType::I64);
+                self.assembler
+                    .emit_mov(Size::S64, self.locals[local_index], ret);
+            }
+            Operator::LocalSet { local_index } => {
+                let local_index = local_index as usize;
+                let loc = self.pop_value_released();
+                self.assembler
+                    .emit_mov(Size::S64, loc, self.locals[local_index]);
+            }
+            Operator::LocalTee { local_index } => {
+                let local_index = local_index as usize;
+                let loc = *self.value_stack.last().unwrap();
+                self.assembler
+                    .emit_mov(Size::S64, loc, self.locals[local_index]);
+            }
+            Operator::I32Const { value } => {
+                self.value_stack.push(Location::Imm32(value as u32));
+                self.machine
+                    .state
+                    .wasm_stack
+                    .push(WasmAbstractValue::Const(value as u32 as u64));
+            }
+            Operator::I32Add => self.emit_binop_i(Size::S32, Assembler::emit_add),
+            Operator::I32Sub => self.emit_binop_i(Size::S32, Assembler::emit_sub),
+            Operator::I32Mul => self.emit_binop_i(Size::S32, Assembler::emit_mul),
+            Operator::I32DivU => self.emit_div_i(Size::S32, false, false),
+            Operator::I32DivS => self.emit_div_i(Size::S32, true, false),
+            Operator::I32RemU => self.emit_div_i(Size::S32, false, true),
+            Operator::I32RemS => self.emit_div_i(Size::S32, true, true),
+            Operator::I32And => self.emit_binop_i(Size::S32, Assembler::emit_and),
+            Operator::I32Or => self.emit_binop_i(Size::S32, Assembler::emit_orr),
+            Operator::I32Xor => self.emit_binop_i(Size::S32, Assembler::emit_eor),
+            Operator::I32Eq => self.emit_cmpop_i(Size::S32, Condition::Eq),
+            Operator::I32Ne => self.emit_cmpop_i(Size::S32, Condition::Ne),
+            Operator::I32Eqz => self.emit_eqz(Size::S32),
+            Operator::I32Clz => self.emit_unop_i(Size::S32, WpType::I32, |this, src, dst| {
+                this.assembler.emit_clz(Size::S32, src, dst)
+            }),
+            Operator::I32Ctz => self.emit_unop_i(Size::S32, WpType::I32, |this, src, dst| {
+                this.assembler.emit_rbit(Size::S32, src, dst);
+                this.assembler.emit_clz(Size::S32, dst, dst);
+            }),
+            Operator::I32Popcnt => self.emit_unop_i(Size::S32, WpType::I32, |this, src, dst| {
+                let tmp = this.machine.acquire_temp_simd().unwrap();
+                this.assembler.emit_popcnt(Size::S32, src, dst, tmp);
+                this.machine.release_temp_simd(tmp);
+            }),
+            // Shift amounts are taken modulo the operand size, as in Wasm.
+            Operator::I32Shl => self.emit_binop_i(Size::S32, Assembler::emit_lsl),
+            Operator::I32ShrU => self.emit_binop_i(Size::S32, Assembler::emit_lsr),
+            Operator::I32ShrS => self.emit_binop_i(Size::S32, Assembler::emit_asr),
+            Operator::I32Rotl => self.emit_rotl(Size::S32),
+            Operator::I32Rotr => self.emit_binop_i(Size::S32, Assembler::emit_ror),
+            Operator::I32LtU => self.emit_cmpop_i(Size::S32, Condition::Lo),
+            Operator::I32LeU => self.emit_cmpop_i(Size::S32, Condition::Ls),
+            Operator::I32GtU => self.emit_cmpop_i(Size::S32, Condition::Hi),
+            Operator::I32GeU => self.emit_cmpop_i(Size::S32, Condition::Hs),
+            Operator::I32LtS => self.emit_cmpop_i(Size::S32, Condition::Lt),
+            Operator::I32LeS => self.emit_cmpop_i(Size::S32, Condition::Le),
+            Operator::I32GtS => self.emit_cmpop_i(Size::S32, Condition::Gt),
+            Operator::I32GeS => self.emit_cmpop_i(Size::S32, Condition::Ge),
+            Operator::I64Const { value } => {
+                let value = value as u64;
+                self.value_stack.push(Location::Imm64(value));
+                self.machine
+                    .state
+                    .wasm_stack
+                    .push(WasmAbstractValue::Const(value));
+            }
+            Operator::I64Add => self.emit_binop_i(Size::S64, Assembler::emit_add),
+            Operator::I64Sub => self.emit_binop_i(Size::S64, Assembler::emit_sub),
+            Operator::I64Mul => self.emit_binop_i(Size::S64, Assembler::emit_mul),
+            Operator::I64DivU => self.emit_div_i(Size::S64, false, false),
+            Operator::I64DivS => self.emit_div_i(Size::S64, true, false),
+            Operator::I64RemU => self.emit_div_i(Size::S64, false, true),
+            Operator::I64RemS => self.emit_div_i(Size::S64, true, true),
+            Operator::I64And => self.emit_binop_i(Size::S64, Assembler::emit_and),
+            Operator::I64Or => self.emit_binop_i(Size::S64, Assembler::emit_orr),
+            Operator::I64Xor => self.emit_binop_i(Size::S64, Assembler::emit_eor),
+            Operator::I64Eq => self.emit_cmpop_i(Size::S64, Condition::Eq),
+            Operator::I64Ne => self.emit_cmpop_i(Size::S64, Condition::Ne),
+            Operator::I64Eqz => self.emit_eqz(Size::S64),
+            Operator::I64Clz => self.emit_unop_i(Size::S64, WpType::I64, |this, src, dst| {
+                this.assembler.emit_clz(Size::S64, src, dst)
+            }),
+            Operator::I64Ctz => self.emit_unop_i(Size::S64, WpType::I64, |this, src, dst| {
+                this.assembler.emit_rbit(Size::S64, src, dst);
+                this.assembler.emit_clz(Size::S64, dst, dst);
+            }),
+            Operator::I64Popcnt => self.emit_unop_i(Size::S64, WpType::I64, |this, src, dst| {
+                let tmp = this.machine.acquire_temp_simd().unwrap();
+                this.assembler.emit_popcnt(Size::S64, src, dst, tmp);
+                this.machine.release_temp_simd(tmp);
+            }),
+            Operator::I64Shl => self.emit_binop_i(Size::S64, Assembler::emit_lsl),
+            Operator::I64ShrU => self.emit_binop_i(Size::S64, Assembler::emit_lsr),
+            Operator::I64ShrS => self.emit_binop_i(Size::S64, Assembler::emit_asr),
+            Operator::I64Rotl => self.emit_rotl(Size::S64),
+            Operator::I64Rotr => self.emit_binop_i(Size::S64, Assembler::emit_ror),
+            Operator::I64LtU => self.emit_cmpop_i(Size::S64, Condition::Lo),
+            Operator::I64LeU => self.emit_cmpop_i(Size::S64, Condition::Ls),
+            Operator::I64GtU => self.emit_cmpop_i(Size::S64, Condition::Hi),
+            Operator::I64GeU => self.emit_cmpop_i(Size::S64, Condition::Hs),
+            Operator::I64LtS => self.emit_cmpop_i(Size::S64, Condition::Lt),
+            Operator::I64LeS => self.emit_cmpop_i(Size::S64, Condition::Le),
+            Operator::I64GtS => self.emit_cmpop_i(Size::S64, Condition::Gt),
+            Operator::I64GeS => self.emit_cmpop_i(Size::S64, Condition::Ge),
+            Operator::I64ExtendI32U => {
+                // A 32-bit move clears the upper bits.
+                self.emit_unop_i(Size::S32, WpType::I64, |this, src, dst| {
+                    this.assembler
+                        .emit_mov(Size::S32, Location::GPR(src), Location::GPR(dst))
+                })
+            }
+            Operator::I64ExtendI32S => {
+                self.emit_unop_i(Size::S32, WpType::I64, |this, src, dst| {
+                    this.assembler.emit_sxt(Size::S32, Size::S64, src, dst)
+                })
+            }
+            Operator::I32Extend8S => self.emit_unop_i(Size::S32, WpType::I32, |this, src, dst| {
+                this.assembler.emit_sxt(Size::S8, Size::S32, src, dst)
+            }),
+            Operator::I32Extend16S => self.emit_unop_i(Size::S32, WpType::I32, |this, src, dst| {
+                this.assembler.emit_sxt(Size::S16, Size::S32, src, dst)
+            }),
+            Operator::I64Extend8S => self.emit_unop_i(Size::S64, WpType::I64, |this, src, dst| {
+                this.assembler.emit_sxt(Size::S8, Size::S64, src, dst)
+            }),
+            Operator::I64Extend16S => self.emit_unop_i(Size::S64, WpType::I64, |this, src, dst| {
+                this.assembler.emit_sxt(Size::S16, Size::S64, src, dst)
+            }),
+            Operator::I64Extend32S => self.emit_unop_i(Size::S64, WpType::I64, |this, src, dst| {
+                this.assembler.emit_sxt(Size::S32, Size::S64, src, dst)
+            }),
+            Operator::I32WrapI64 => self.emit_unop_i(Size::S64, WpType::I32, |this, src, dst| {
+                this.assembler
+                    .emit_mov(Size::S32, Location::GPR(src), Location::GPR(dst))
+            }),
+
+            Operator::F32Const { value } => {
+                self.value_stack.push(Location::Imm32(value.bits()));
+                self.machine
+                    .state
+                    .wasm_stack
+                    .push(WasmAbstractValue::Const(value.bits() as u64));
+            }
+            Operator::F32Add => self.emit_fp_binop(Size::S32, Assembler::emit_fadd),
+            Operator::F32Sub => self.emit_fp_binop(Size::S32, Assembler::emit_fsub),
+            Operator::F32Mul => self.emit_fp_binop(Size::S32, Assembler::emit_fmul),
+            Operator::F32Div => self.emit_fp_binop(Size::S32, Assembler::emit_fdiv),
+            Operator::F32Max => self.emit_fp_binop(Size::S32, Assembler::emit_fmax),
+            Operator::F32Min => self.emit_fp_binop(Size::S32, Assembler::emit_fmin),
+            Operator::F32Eq => self.emit_fp_cmpop(Size::S32, Condition::Eq),
+            Operator::F32Ne => self.emit_fp_cmpop(Size::S32, Condition::Ne),
+            Operator::F32Lt => self.emit_fp_cmpop(Size::S32, Condition::Mi),
+            Operator::F32Le => self.emit_fp_cmpop(Size::S32, Condition::Ls),
+            Operator::F32Gt => self.emit_fp_cmpop(Size::S32, Condition::Gt),
+            Operator::F32Ge => self.emit_fp_cmpop(Size::S32, Condition::Ge),
+            Operator::F32Nearest => self.emit_fp_unop(Size::S32, Assembler::emit_frintn),
+            Operator::F32Floor => self.emit_fp_unop(Size::S32, Assembler::emit_frintm),
+            Operator::F32Ceil => self.emit_fp_unop(Size::S32, Assembler::emit_frintp),
+            Operator::F32Trunc => self.emit_fp_unop(Size::S32, Assembler::emit_frintz),
+            Operator::F32Sqrt => self.emit_fp_unop(Size::S32, Assembler::emit_fsqrt),
+            Operator::F32Copysign => self.emit_fp_copysign(Size::S32),
+            Operator::F32Abs => self.emit_fp_unop(Size::S32, Assembler::emit_fabs),
+            Operator::F32Neg => self.emit_fp_unop(Size::S32, Assembler::emit_fneg),
+
+            Operator::F64Const { value } => {
+                self.value_stack.push(Location::Imm64(value.bits()));
+                self.machine
+                    .state
+                    .wasm_stack
+                    .push(WasmAbstractValue::Const(value.bits()));
+            }
+            Operator::F64Add => self.emit_fp_binop(Size::S64, Assembler::emit_fadd),
+            Operator::F64Sub => self.emit_fp_binop(Size::S64, Assembler::emit_fsub),
+            Operator::F64Mul => self.emit_fp_binop(Size::S64, Assembler::emit_fmul),
+            Operator::F64Div => self.emit_fp_binop(Size::S64, Assembler::emit_fdiv),
+            Operator::F64Max => self.emit_fp_binop(Size::S64, Assembler::emit_fmax),
+            Operator::F64Min => self.emit_fp_binop(Size::S64, Assembler::emit_fmin),
+            Operator::F64Eq => self.emit_fp_cmpop(Size::S64, Condition::Eq),
+            Operator::F64Ne => self.emit_fp_cmpop(Size::S64, Condition::Ne),
+            Operator::F64Lt => self.emit_fp_cmpop(Size::S64, Condition::Mi),
+            Operator::F64Le => self.emit_fp_cmpop(Size::S64, Condition::Ls),
+            Operator::F64Gt => self.emit_fp_cmpop(Size::S64, Condition::Gt),
+            Operator::F64Ge => self.emit_fp_cmpop(Size::S64, Condition::Ge),
+            Operator::F64Nearest => self.emit_fp_unop(Size::S64, Assembler::emit_frintn),
+            Operator::F64Floor => self.emit_fp_unop(Size::S64, Assembler::emit_frintm),
+            Operator::F64Ceil => self.emit_fp_unop(Size::S64, Assembler::emit_frintp),
+            Operator::F64Trunc => self.emit_fp_unop(Size::S64, Assembler::emit_frintz),
+            Operator::F64Sqrt => self.emit_fp_unop(Size::S64, Assembler::emit_fsqrt),
+            Operator::F64Copysign => self.emit_fp_copysign(Size::S64),
+            Operator::F64Abs => self.emit_fp_unop(Size::S64, Assembler::emit_fabs),
+            Operator::F64Neg => self.emit_fp_unop(Size::S64, Assembler::emit_fneg),
+
+            Operator::F64PromoteF32 => self.emit_fp_convert(Size::S32),
+            Operator::F32DemoteF64 => self.emit_fp_convert(Size::S64),
+
+            Operator::I32ReinterpretF32 => self.emit_reinterpret(Size::S32, WpType::I32),
+            Operator::F32ReinterpretI32 => self.emit_reinterpret(Size::S32, WpType::F32),
+            Operator::I64ReinterpretF64 => self.emit_reinterpret(Size::S64, WpType::I64),
+            Operator::F64ReinterpretI64 => self.emit_reinterpret(Size::S64, WpType::F64),
+
+            Operator::I32TruncF32U => self.emit_fp_to_int(Size::S32, Size::S32, false, false),
+            Operator::I32TruncSatF32U => self.emit_fp_to_int(Size::S32, Size::S32, false, true),
+            Operator::I32TruncF32S => self.emit_fp_to_int(Size::S32, Size::S32, true, false),
+            Operator::I32TruncSatF32S => self.emit_fp_to_int(Size::S32, Size::S32, true, true),
+            Operator::I64TruncF32S => self.emit_fp_to_int(Size::S32, Size::S64, true, false),
+            Operator::I64TruncSatF32S => self.emit_fp_to_int(Size::S32, Size::S64, true, true),
+            Operator::I64TruncF32U => self.emit_fp_to_int(Size::S32, Size::S64, false, false),
+            Operator::I64TruncSatF32U => self.emit_fp_to_int(Size::S32, Size::S64, false, true),
+            Operator::I32TruncF64U => self.emit_fp_to_int(Size::S64, Size::S32, false, false),
+            Operator::I32TruncSatF64U => self.emit_fp_to_int(Size::S64, Size::S32, false, true),
+            Operator::I32TruncF64S => self.emit_fp_to_int(Size::S64, Size::S32, true, false),
+            Operator::I32TruncSatF64S => self.emit_fp_to_int(Size::S64, Size::S32, true, true),
+            Operator::I64TruncF64S => self.emit_fp_to_int(Size::S64, Size::S64, true, false),
+            Operator::I64TruncSatF64S => self.emit_fp_to_int(Size::S64, Size::S64, true, true),
+            Operator::I64TruncF64U => self.emit_fp_to_int(Size::S64, Size::S64, false, false),
+            Operator::I64TruncSatF64U => self.emit_fp_to_int(Size::S64, Size::S64, false, true),
+
+            Operator::F32ConvertI32S => self.emit_int_to_fp(Size::S32, Size::S32, true),
+            Operator::F32ConvertI32U => self.emit_int_to_fp(Size::S32, Size::S32, false),
+            Operator::F32ConvertI64S => self.emit_int_to_fp(Size::S64, Size::S32, true),
+            Operator::F32ConvertI64U => self.emit_int_to_fp(Size::S64, Size::S32, false),
+            Operator::F64ConvertI32S => self.emit_int_to_fp(Size::S32, Size::S64, true),
+            Operator::F64ConvertI32U => self.emit_int_to_fp(Size::S32, Size::S64, false),
+            Operator::F64ConvertI64S => self.emit_int_to_fp(Size::S64, Size::S64, true),
+            Operator::F64ConvertI64U => self.emit_int_to_fp(Size::S64, Size::S64, false),
+
+            Operator::Call { function_index } => {
+                let function_index = function_index as usize;
+
+                let sig_index = *self
+                    .module
+                    .functions
+                    .get(FunctionIndex::new(function_index))
+                    .unwrap();
+                let sig = self.module.signatures.get(sig_index).unwrap();
+                if sig.results().len() > 1 {
+                    return Err(CodegenError {
+                        message: "calls returning multiple values are not supported on ARM64"
+                            .to_string(),
+                    });
+                }
+                let param_types: SmallVec<[WpType; 8]> =
+                    sig.params().iter().cloned().map(type_to_wp_type).collect();
+                let return_types: SmallVec<[WpType; 1]> =
+                    sig.results().iter().cloned().map(type_to_wp_type).collect();
+
+                let params: SmallVec<[_; 8]> = self
+                    .value_stack
+                    .drain(self.value_stack.len() - param_types.len()..)
+                    .collect();
+                self.machine.release_locations_only_regs(&params);
+                self.machine.release_locations_only_osr_state(params.len());
+
+                // Imported functions are called through trampolines placed as custom sections.
+                let reloc_target = if function_index < self.module.num_imported_functions {
+                    RelocationTarget::CustomSection(SectionIndex::new(function_index))
+                } else {
+                    RelocationTarget::LocalFunc(LocalFunctionIndex::new(
+                        function_index - self.module.num_imported_functions,
+                    ))
+                };
+
+                self.emit_call(
+                    |this| {
+                        // The literal holding the address of the callee is relocated by the
+                        // JIT linker.
+                        let reloc_at = this.assembler.emit_call_literal();
+                        this.relocations.push(Relocation {
+                            kind: RelocationKind::Abs8,
+                            reloc_target,
+                            offset: reloc_at as u32,
+                            addend: 0,
+                        });
+                        let offset = this.assembler.get_offset().0 - 4;
+                        this.trap_table
+                            .offset_to_code
+                            .insert(offset, TrapCode::StackOverflow);
+                        this.mark_instruction_address_end(offset);
+                    },
+                    params.iter().cloned(),
+                )?;
+
+                self.machine
+                    .release_locations_only_stack(&mut self.assembler, &params);
+
+                self.emit_load_call_result(&return_types);
+            }
+            Operator::CallIndirect { index, table_index } => {
+                if table_index != 0 {
+                    return Err(CodegenError {
+                        message: "CallIndirect: table_index is not 0".to_string(),
+                    });
+                }
+                let table_index = TableIndex::new(table_index as _);
+                let index = SignatureIndex::new(index as usize);
+                let sig = self.module.signatures.get(index).unwrap();
+                if sig.results().len() > 1 {
+                    return Err(CodegenError {
+                        message: "calls returning multiple values are not supported on ARM64"
+                            .to_string(),
+                    });
+                }
+                let param_types: SmallVec<[WpType; 8]> =
+                    sig.params().iter().cloned().map(type_to_wp_type).collect();
+                let return_types: SmallVec<[WpType; 1]> =
+                    sig.results().iter().cloned().map(type_to_wp_type).collect();
+
+                let func_index = self.pop_value_released();
+
+                let params: SmallVec<[_; 8]> = self
+                    .value_stack
+                    .drain(self.value_stack.len() - param_types.len()..)
+                    .collect();
+                self.machine.release_locations_only_regs(&params);
+
+                let table_base = self.machine.acquire_temp_gpr().unwrap();
+                let table_count = self.machine.acquire_temp_gpr().unwrap();
+                let tmp = self.machine.acquire_temp_gpr().unwrap();
+
+                if let Some(local_table_index) = self.module.local_table_index(table_index) {
+                    let (vmctx_offset_base, vmctx_offset_len) = (
+                        self.vmoffsets.vmctx_vmtable_definition(local_table_index),
+                        self.vmoffsets
+                            .vmctx_vmtable_definition_current_elements(local_table_index),
+                    );
+                    self.assembler.emit_mov(
+                        Size::S64,
+                        Location::Memory(Machine::get_vmctx_reg(), vmctx_offset_base as i32),
+                        Location::GPR(table_base),
+                    );
+                    self.assembler.emit_mov(
+                        Size::S32,
+                        Location::Memory(Machine::get_vmctx_reg(), vmctx_offset_len as i32),
+                        Location::GPR(table_count),
+                    );
+                } else {
+                    // Do an indirection.
+                    let import_offset = self.vmoffsets.vmctx_vmtable_import(table_index);
+                    self.assembler.emit_mov(
+                        Size::S64,
+                        Location::Memory(Machine::get_vmctx_reg(), import_offset as i32),
+                        Location::GPR(table_base),
+                    );
+
+                    // Load len.
+                    self.assembler.emit_mov(
+                        Size::S32,
+                        Location::Memory(
+                            table_base,
+                            self.vmoffsets.vmtable_definition_current_elements() as _,
+                        ),
+                        Location::GPR(table_count),
+                    );
+
+                    // Load base.
+                    self.assembler.emit_mov(
+                        Size::S64,
+                        Location::Memory(table_base, self.vmoffsets.vmtable_definition_base() as _),
+                        Location::GPR(table_base),
+                    );
+                }
+
+                self.assembler
+                    .emit_mov(Size::S32, func_index, Location::GPR(tmp));
+                self.assembler.emit_cmp(Size::S32, tmp, table_count);
+                self.assembler
+                    .emit_jmp(Condition::Hs, self.special_labels.table_access_oob);
+
+                // X8 is never allocated, so it holds the address of the table entry until
+                // the call.
+                self.assembler.emit_mov_imm(
+                    table_count,
+                    self.vmoffsets.size_of_vmcaller_checked_anyfunc() as u64,
+                );
+                self.assembler.emit_mul(Size::S64, tmp, table_count, tmp);
+                self.assembler.emit_add(Size::S64, table_base, tmp, GPR::X8);
+
+                // Trap if the current table entry is null.
+                self.assembler.emit_mov(
+                    Size::S64,
+                    Location::Memory(
+                        GPR::X8,
+                        self.vmoffsets.vmcaller_checked_anyfunc_func_ptr() as i32,
+                    ),
+                    Location::GPR(tmp),
+                );
+                self.assembler.emit_cmp_imm(Size::S64, tmp, 0);
+                self.assembler
+                    .emit_jmp(Condition::Eq, self.special_labels.indirect_call_null);
+
+                // Trap if signature mismatches.
+                self.assembler.emit_mov(
+                    Size::S32,
+                    Location::Memory(
+                        Machine::get_vmctx_reg(),
+                        self.vmoffsets.vmctx_vmshared_signature_id(index) as i32,
+                    ),
+                    Location::GPR(table_base),
+                );
+                self.assembler.emit_mov(
+                    Size::S32,
+                    Location::Memory(
+                        GPR::X8,
+                        self.vmoffsets.vmcaller_checked_anyfunc_type_index() as i32,
+                    ),
+                    Location::GPR(tmp),
+                );
+                self.assembler.emit_cmp(Size::S32, table_base, tmp);
+                self.assembler
+                    .emit_jmp(Condition::Ne, self.special_labels.bad_signature);
+
+                self.machine.release_temp_gpr(tmp);
+                self.machine.release_temp_gpr(table_count);
+                self.machine.release_temp_gpr(table_base);
+
+                self.machine.release_locations_only_osr_state(params.len());
+
+                let vmcaller_checked_anyfunc_func_ptr =
+                    self.vmoffsets.vmcaller_checked_anyfunc_func_ptr() as i32;
+                let vmcaller_checked_anyfunc_vmctx =
+                    self.vmoffsets.vmcaller_checked_anyfunc_vmctx() as i32;
+
+                self.emit_call(
+                    |this| {
+                        // The callee may belong to another instance, or be a host function.
+                        this.assembler.emit_mov(
+                            Size::S64,
+                            Location::Memory(GPR::X8, vmcaller_checked_anyfunc_vmctx),
+                            Machine::get_param_location(0),
+                        );
+                        this.assembler.emit_mov(
+                            Size::S64,
+                            Location::Memory(GPR::X8, vmcaller_checked_anyfunc_func_ptr),
+                            Location::GPR(GPR::X16),
+                        );
+                        let offset = this.assembler.get_offset().0;
+                        this.trap_table
+                            .offset_to_code
+                            .insert(offset, TrapCode::StackOverflow);
+                        this.assembler.emit_blr(GPR::X16);
+                        this.mark_instruction_address_end(offset);
+                    },
+                    params.iter().cloned(),
+                )?;
+
+                self.machine
+                    .release_locations_only_stack(&mut self.assembler, &params);
+
+                self.emit_load_call_result(&return_types);
+            }
+            Operator::If { ty } => {
+                let label_end = self.assembler.get_label();
+                let label_else = self.assembler.get_label();
+
+                let cond = self.pop_value_released();
+                let returns = self.block_signature(ty)?;
+
+                let frame = ControlFrame {
+                    label: label_end,
+                    loop_like: false,
+                    if_else: IfElseState::If(label_else),
+                    returns,
+                    value_stack_depth: self.value_stack.len(),
+                    state: self.machine.state.clone(),
+                    state_diff_id: self.get_state_diff(),
+                };
+                self.control_stack.push(frame);
+                let mut temps = vec![];
+                let cond = self.location_to_gpr(Size::S32, cond, &mut temps);
+                self.assembler.emit_cmp_imm(Size::S32, cond, 0);
+                self.assembler.emit_jmp(Condition::Eq, label_else);
+                self.store_result(Location::GPR(cond), Location::GPR(cond), temps, vec![]);
+            }
+            Operator::Else => {
+                if !was_unreachable {
+                    let returns = self.control_stack.last().unwrap().returns.clone();
+                    self.emit_block_results(&returns);
+                }
+
+                let frame = self.control_stack.last_mut().unwrap();
+
+                let released: &[Location] = &self.value_stack[frame.value_stack_depth..];
+                self.machine
+                    .release_locations(&mut self.assembler, released);
+                self.value_stack.truncate(frame.value_stack_depth);
+
+                match frame.if_else {
+                    IfElseState::If(label) => {
+                        self.assembler.emit_jmp(Condition::Al, frame.label);
+                        self.assembler.emit_label(label);
+                        frame.if_else = IfElseState::Else;
+                    }
+                    _ => {
+                        return Err(CodegenError {
+                            message: "Else: frame.if_else unreachable code".to_string(),
+                        })
+                    }
+                }
+            }
+            Operator::Select | Operator::TypedSelect { .. } => {
+                let cond = self.pop_value_released();
+                let v_b = self.pop_value_released();
+                let v_a = self.pop_value_released();
+                let ret = self.push_value(WpType::I64);
+
+                let mut temps = vec![];
+                let cond = self.location_to_gpr(Size::S32, cond, &mut temps);
+                let a = self.location_to_gpr(Size::S64, v_a, &mut temps);
+                let b = self.location_to_gpr(Size::S64, v_b, &mut temps);
+                self.assembler.emit_cmp_imm(Size::S32, cond, 0);
+                let dst = self.gpr_for_result(ret, &mut temps);
+                self.assembler
+                    .emit_csel(Size::S64, a, b, dst, Condition::Ne);
+                self.store_result(Location::GPR(dst), ret, temps, vec![]);
+            }
+            Operator::Block { ty } => {
+                let returns = self.block_signature(ty)?;
+                let frame = ControlFrame {
+                    label: self.assembler.get_label(),
+                    loop_like: false,
+                    if_else: IfElseState::None,
+                    returns,
+                    value_stack_depth: self.value_stack.len(),
+                    state: self.machine.state.clone(),
+                    state_diff_id: self.get_state_diff(),
+                };
+                self.control_stack.push(frame);
+            }
+            Operator::Loop { ty } => {
+                let returns = self.block_signature(ty)?;
+                let label = self.assembler.get_label();
+                let state_diff_id = self.get_state_diff();
+
+                self.control_stack.push(ControlFrame {
+                    label,
+                    loop_like: true,
+                    if_else: IfElseState::None,
+                    returns,
+                    value_stack_depth: self.value_stack.len(),
+                    state: self.machine.state.clone(),
+                    state_diff_id,
+                });
+                self.assembler.emit_label(label);
+
+                self.emit_interrupt_check();
+            }
+            Operator::Nop => {}
+            Operator::MemorySize { mem, mem_byte: _ } => {
+                let memory_index = MemoryIndex::new(mem as usize);
+                let builtin = if self.module.local_memory_index(memory_index).is_some() {
+                    VMBuiltinFunctionIndex::get_memory32_size_index()
+                } else {
+                    VMBuiltinFunctionIndex::get_imported_memory32_size_index()
+                };
+                // [vmctx, memory_index]
+                self.emit_builtin_call(builtin, &[memory_index.index() as u32], 0)?;
+                self.emit_load_call_result(&[WpType::I64]);
+            }
+            Operator::MemoryGrow { mem, mem_byte: _ } => {
+                let memory_index = MemoryIndex::new(mem as usize);
+                let param_pages = self.value_stack.pop().unwrap();
+
+                self.machine.release_locations_only_regs(&[param_pages]);
+                self.machine.release_locations_only_osr_state(1);
+
+                let offset = self.vmoffsets.vmctx_builtin_function(
+                    if self.module.local_memory_index(memory_index).is_some() {
+                        VMBuiltinFunctionIndex::get_memory32_grow_index()
+                    } else {
+                        VMBuiltinFunctionIndex::get_imported_memory32_grow_index()
+                    },
+                );
+                self.emit_call(
+                    |this| this.emit_call_vmctx_function(offset),
+                    // [vmctx, val, memory_index]
+                    iter::once(param_pages)
+                        .chain(iter::once(Location::Imm32(memory_index.index() as u32))),
+                )?;
+
+                self.machine
+                    .release_locations_only_stack(&mut self.assembler, &[param_pages]);
+
+                self.emit_load_call_result(&[WpType::I64]);
+            }
+            Operator::MemoryCopy { src, dst: _ } => {
+                let memory_index = MemoryIndex::new(src as usize);
+                let (builtin, index) = match self.module.local_memory_index(memory_index) {
+                    Some(local_memory_index) => (
+                        VMBuiltinFunctionIndex::get_local_memory_copy_index(),
+                        local_memory_index.index(),
+                    ),
+                    None => (
+                        VMBuiltinFunctionIndex::get_imported_memory_copy_index(),
+                        memory_index.index(),
+                    ),
+                };
+                // [vmctx, memory_index, dst, src, len]
+                self.emit_builtin_call(builtin, &[index as u32], 3)?;
+            }
+            Operator::MemoryFill { mem } => {
+                let memory_index = MemoryIndex::new(mem as usize);
+                let (builtin, index) = match self.module.local_memory_index(memory_index) {
+                    Some(local_memory_index) => (
+                        VMBuiltinFunctionIndex::get_memory_fill_index(),
+                        local_memory_index.index(),
+                    ),
+                    None => (
+                        VMBuiltinFunctionIndex::get_imported_memory_fill_index(),
+                        memory_index.index(),
+                    ),
+                };
+                // [vmctx, memory_index, dst, val, len]
+                self.emit_builtin_call(builtin, &[index as u32], 3)?;
+            }
+            Operator::MemoryInit { segment, mem } => {
+                // [vmctx, memory_index, segment_index, dst, src, len]
+                self.emit_builtin_call(
+                    VMBuiltinFunctionIndex::get_memory_init_index(),
+                    &[mem, segment],
+                    3,
+                )?;
+            }
+            Operator::DataDrop { segment } => {
+                // [vmctx, segment_index]
+                self.emit_builtin_call(
+                    VMBuiltinFunctionIndex::get_data_drop_index(),
+                    &[segment],
+                    0,
+                )?;
+            }
+            Operator::TableCopy {
+                dst_table,
+                src_table,
+            } => {
+                // [vmctx, dst_table_index, src_table_index, dst, src, len]
+                self.emit_builtin_call(
+                    VMBuiltinFunctionIndex::get_table_copy_index(),
+                    &[dst_table, src_table],
+                    3,
+                )?;
+            }
+            Operator::TableInit { segment, table } => {
+                // [vmctx, table_index, segment_index, dst, src, len]
+                self.emit_builtin_call(
+                    VMBuiltinFunctionIndex::get_table_init_index(),
+                    &[table, segment],
+                    3,
+                )?;
+            }
+            Operator::ElemDrop { segment } => {
+                // [vmctx, segment_index]
+                self.emit_builtin_call(
+                    VMBuiltinFunctionIndex::get_elem_drop_index(),
+                    &[segment],
+                    0,
+                )?;
+            }
+            Operator::TableSize { table } => {
+                let table_index = TableIndex::new(table as usize);
+                let ret = self.push_value(WpType::I32);
+
+                let tmp = self.machine.acquire_temp_gpr().unwrap();
+                if let Some(local_table_index) = self.module.local_table_index(table_index) {
+                    let offset = self
+                        .vmoffsets
+                        .vmctx_vmtable_definition_current_elements(local_table_index);
+                    self.assembler.emit_mov(
+                        Size::S32,
+                        Location::Memory(Machine::get_vmctx_reg(), offset as i32),
+                        Location::GPR(tmp),
+                    );
+                } else {
+                    // Imported tables require one level of indirection.
+                    let offset = self.vmoffsets.vmctx_vmtable_import(table_index);
+                    self.assembler.emit_mov(
+                        Size::S64,
+                        Location::Memory(Machine::get_vmctx_reg(), offset as i32),
+                        Location::GPR(tmp),
+                    );
+                    self.assembler.emit_mov(
+                        Size::S32,
+                        Location::Memory(
+                            tmp,
+                            self.vmoffsets.vmtable_definition_current_elements() as i32,
+                        ),
+                        Location::GPR(tmp),
+                    );
+                }
+                self.assembler.emit_mov(Size::S64, Location::GPR(tmp), ret);
+                self.machine.release_temp_gpr(tmp);
+            }
+            Operator::RefNull { .. } => {
+                self.value_stack.push(Location::Imm64(0));
+                self.machine
+                    .state
+                    .wasm_stack
+                    .push(WasmAbstractValue::Const(0));
+            }
+            Operator::RefIsNull => self.emit_eqz(Size::S64),
+            Operator::TableGet { .. }
+            | Operator::TableSet { .. }
+            | Operator::TableGrow { .. }
+            | Operator::TableFill { .. }
+            | Operator::RefFunc { .. } => {
+                return Err(CodegenError {
+                    message: format!("{:?}: reference types instruction not supported yet", op),
+                })
+            }
+            Operator::I32Load { ref memarg } => {
+                self.emit_load(memarg, WpType::I32, Size::S32, None)?
+            }
+            Operator::F32Load { ref memarg } => {
+                self.emit_load(memarg, WpType::F32, Size::S32, None)?
+            }
+            Operator::I32Load8U { ref memarg } => {
+                self.emit_load(memarg, WpType::I32, Size::S8, None)?
+            }
+            Operator::I32Load8S { ref memarg } => {
+                self.emit_load(memarg, WpType::I32, Size::S8, Some(Size::S32))?
+            }
+            Operator::I32Load16U { ref memarg } => {
+                self.emit_load(memarg, WpType::I32, Size::S16, None)?
+            }
+            Operator::I32Load16S { ref memarg } => {
+                self.emit_load(memarg, WpType::I32, Size::S16, Some(Size::S32))?
+            }
+            Operator::I32Store { ref memarg } => self.emit_store(memarg, Size::S32)?,
+            Operator::F32Store { ref memarg } => self.emit_store(memarg, Size::S32)?,
+            Operator::I32Store8 { ref memarg } => self.emit_store(memarg, Size::S8)?,
+            Operator::I32Store16 { ref memarg } => self.emit_store(memarg, Size::S16)?,
+            Operator::I64Load { ref memarg } => {
+                self.emit_load(memarg, WpType::I64, Size::S64, None)?
+            }
+            Operator::F64Load { ref memarg } => {
+                self.emit_load(memarg, WpType::F64, Size::S64, None)?
+            }
+            Operator::I64Load8U { ref memarg } => {
+                self.emit_load(memarg, WpType::I64, Size::S8, None)?
+            }
+            Operator::I64Load8S { ref memarg } => {
+                self.emit_load(memarg, WpType::I64, Size::S8, Some(Size::S64))?
+            }
+            Operator::I64Load16U { ref memarg } => {
+                self.emit_load(memarg, WpType::I64, Size::S16, None)?
+            }
+            Operator::I64Load16S { ref memarg } => {
+                self.emit_load(memarg, WpType::I64, Size::S16, Some(Size::S64))?
+            }
+            Operator::I64Load32U { ref memarg } => {
+                // A 32-bit load clears the upper bits of the register, but a move between
+                // stack slots would not.
+                self.emit_load(memarg, WpType::I64, Size::S32, None)?;
+                let ret = *self.value_stack.last().unwrap();
+                if let Location::Memory(base, offset) = ret {
+                    self.assembler.emit_mov(
+                        Size::S32,
+                        Location::Imm32(0),
+                        Location::Memory(base, offset + 4),
+                    );
+                }
+            }
+            Operator::I64Load32S { ref memarg } => {
+                self.emit_load(memarg, WpType::I64, Size::S32, Some(Size::S64))?
+            }
+            Operator::I64Store { ref memarg } => self.emit_store(memarg, Size::S64)?,
+            Operator::F64Store { ref memarg } => self.emit_store(memarg, Size::S64)?,
+            Operator::I64Store8 { ref memarg } => self.emit_store(memarg, Size::S8)?,
+            Operator::I64Store16 { ref memarg } => self.emit_store(memarg, Size::S16)?,
+            Operator::I64Store32 { ref memarg } => self.emit_store(memarg, Size::S32)?,
+            Operator::Unreachable => {
+                self.mark_trappable();
+                let offset = self.assembler.get_offset().0;
+                self.trap_table
+                    .offset_to_code
+                    .insert(offset, TrapCode::UnreachableCodeReached);
+                self.assembler.emit_udf();
+                self.mark_instruction_address_end(offset);
+                self.unreachable_depth = 1;
+            }
+            Operator::Return => {
+                self.emit_branch(0);
+                self.unreachable_depth = 1;
+            }
+            Operator::Br { relative_depth } => {
+                self.emit_branch(self.control_stack.len() - 1 - (relative_depth as usize));
+                self.unreachable_depth = 1;
+            }
+            Operator::BrIf { relative_depth } => {
+                let after = self.assembler.get_label();
+                let cond = self.pop_value_released();
+                let mut temps = vec![];
+                let cond = self.location_to_gpr(Size::S32, cond, &mut temps);
+                self.assembler.emit_cmp_imm(Size::S32, cond, 0);
+                self.store_result(Location::GPR(cond), Location::GPR(cond), temps, vec![]);
+                self.assembler.emit_jmp(Condition::Eq, after);
+
+                self.emit_branch(self.control_stack.len() - 1 - (relative_depth as usize));
+
+                self.assembler.emit_label(after);
+            }
+            Operator::BrTable { ref table } => {
+                let mut targets = table
+                    .targets()
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| CodegenError {
+                        message: format!("BrTable read_table: {:?}", e),
+                    })?;
+                let default_target = targets.pop().unwrap().0;
+                let cond = self.pop_value_released();
+                let table_label = self.assembler.get_label();
+                let mut table: Vec<DynamicLabel> = vec![];
+                let default_br = self.assembler.get_label();
+
+                let index = self.machine.acquire_temp_gpr().unwrap();
+                self.assembler
+                    .emit_mov(Size::S32, cond, Location::GPR(index));
+                self.assembler
+                    .emit_cmp_imm(Size::S32, index, targets.len() as u64);
+                self.assembler.emit_jmp(Condition::Hs, default_br);
+
+                // Each entry of the jump table is a single `b` instruction.
+                self.assembler.emit_adr(table_label, GPR::X16);
+                self.assembler.emit_add_lsl(GPR::X16, index, 2, GPR::X16);
+                self.assembler.emit_br(GPR::X16);
+                self.machine.release_temp_gpr(index);
+
+                for (target, _) in targets.iter() {
+                    let label = self.assembler.get_label();
+                    self.assembler.emit_label(label);
+                    table.push(label);
+                    self.emit_branch(self.control_stack.len() - 1 - (*target as usize));
+                }
+                self.assembler.emit_label(default_br);
+                self.emit_branch(self.control_stack.len() - 1 - (default_target as usize));
+
+                self.assembler.emit_label(table_label);
+                for x in table {
+                    self.assembler.emit_jmp(Condition::Al, x);
+                }
+                self.unreachable_depth = 1;
+            }
+            Operator::Drop => {
+                self.pop_value_released();
+            }
+            Operator::End => {
+                let frame = self.control_stack.pop().unwrap();
+
+                if !was_unreachable {
+                    self.emit_block_results(&frame.returns);
+                }
+
+                if self.control_stack.is_empty() {
+                    self.assembler.emit_label(frame.label);
+                    self.machine
+                        .finalize_locals(&mut self.assembler, &self.locals);
+
+                    // Make a copy of the return value in D0, as required by the AAPCS64.
+                    match self.signature.results() {
+                        [x] if *x == Type::F32 || *x == Type::F64 => {
+                            self.assembler.emit_mov(
+                                Size::S64,
+                                Location::GPR(GPR::X0),
+                                Location::SIMD(NEON::V0),
+                            );
+                        }
+                        _ => {}
+                    }
+                    self.assembler.emit_epilogue();
+                } else {
+                    let released = &self.value_stack[frame.value_stack_depth..];
+                    self.machine
+                        .release_locations(&mut self.assembler, released);
+                    self.value_stack.truncate(frame.value_stack_depth);
+
+                    match frame.if_else {
+                        IfElseState::If(label) => {
+                            self.assembler.emit_label(frame.label);
+                            self.assembler.emit_label(label);
+                        }
+                        IfElseState::Else => {
+                            self.assembler.emit_label(frame.label);
+                        }
+                        IfElseState::None => {
+                            if !frame.loop_like {
+                                self.assembler.emit_label(frame.label);
+                            }
+                        }
+                    }
+
+                    self.emit_load_block_results(&frame.returns);
+                }
+            }
+            Operator::AtomicFence { flags: _ } => {
+                // Fence is a nop.
+                //
+                // Fence was added to preserve information about fences from
+                // source languages. If in the future Wasm extends the memory
+                // model, and if we hadn't recorded what fences used to be there,
+                // it would lead to data races that weren't present in the
+                // original source language.
+            }
+            _ => {
+                return Err(CodegenError {
+                    message: format!("not yet implemented on ARM64: {:?}", op),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn finalize(mut self, data: &FunctionBodyData) -> CompiledFunction {
+        // Generate actual code for special labels.
+        self.assembler
+            .emit_label(self.special_labels.integer_division_by_zero);
+        self.mark_address_with_trap_code(TrapCode::IntegerDivisionByZero);
+        self.assembler.emit_udf();
+
+        self.assembler
+            .emit_label(self.special_labels.integer_overflow);
+        self.mark_address_with_trap_code(TrapCode::IntegerOverflow);
+        self.assembler.emit_udf();
+
+        self.assembler
+            .emit_label(self.special_labels.bad_conversion_to_integer);
+        self.mark_address_with_trap_code(TrapCode::BadConversionToInteger);
+        self.assembler.emit_udf();
+
+        self.assembler
+            .emit_label(self.special_labels.heap_access_oob);
+        self.mark_address_with_trap_code(TrapCode::HeapAccessOutOfBounds);
+        self.assembler.emit_udf();
+
+        self.assembler
+            .emit_label(self.special_labels.table_access_oob);
+        self.mark_address_with_trap_code(TrapCode::TableAccessOutOfBounds);
+        self.assembler.emit_udf();
+
+        self.assembler
+            .emit_label(self.special_labels.indirect_call_null);
+        self.mark_address_with_trap_code(TrapCode::IndirectCallToNull);
+        self.assembler.emit_udf();
+
+        self.assembler.emit_label(self.special_labels.bad_signature);
+        self.mark_address_with_trap_code(TrapCode::BadSignature);
+        self.assembler.emit_udf();
+
+        // The interrupt is consumed by the code that observes it.
+        self.assembler.emit_label(self.special_labels.interrupt);
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(
+                Machine::get_vmctx_reg(),
+                self.vmoffsets.vmctx_interrupts() as i32,
+            ),
+            Location::GPR(GPR::X0),
+        );
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Imm32(0),
+            Location::Memory(GPR::X0, self.vmoffsets.vminterrupts_interrupt() as i32),
+        );
+        self.mark_address_with_trap_code(TrapCode::Interrupt);
+        self.assembler.emit_udf();
+
+        self.assembler
+            .emit_label(self.special_labels.stack_overflow);
+        self.mark_address_with_trap_code(TrapCode::StackOverflow);
+        self.assembler.emit_udf();
+
+        let body_len = self.assembler.get_offset().0;
+        let instructions_address_map = self.instructions_address_map;
+        let address_map = get_function_address_map(instructions_address_map, data, body_len);
+
+        CompiledFunction {
+            body: FunctionBody {
+                body: self.assembler.finalize().unwrap().to_vec(),
+                unwind_info: None,
+            },
+            relocations: self.relocations,
+            jt_offsets: SecondaryMap::new(),
+            frame_info: CompiledFunctionFrameInfo {
+                traps: self
+                    .trap_table
+                    .offset_to_code
+                    .into_iter()
+                    .map(|(offset, code)| TrapInformation {
+                        code_offset: offset as u32,
+                        trap_code: code,
+                    })
+                    .collect(),
+                address_map,
+            },
+        }
+    }
+}
+
+trait WpTypeExt {
+    fn is_float(&self) -> bool;
+}
+
+impl WpTypeExt for WpType {
+    fn is_float(&self) -> bool {
+        matches!(self, WpType::F32 | WpType::F64)
+    }
+}
+
+/// Returns the bit patterns of the exclusive bounds of the floats that a trapping
+/// truncation accepts.
+fn trunc_bounds(sz_src: Size, sz_dst: Size, signed: bool) -> (u64, u64) {
+    match (sz_src, sz_dst, signed) {
+        (Size::S32, Size::S32, true) => (
+            GEF32_LT_I32_MIN.to_bits() as u64,
+            LEF32_GT_I32_MAX.to_bits() as u64,
+        ),
+        (Size::S32, Size::S32, false) => (
+            GEF32_LT_U32_MIN.to_bits() as u64,
+            LEF32_GT_U32_MAX.to_bits() as u64,
+        ),
+        (Size::S32, Size::S64, true) => (
+            GEF32_LT_I64_MIN.to_bits() as u64,
+            LEF32_GT_I64_MAX.to_bits() as u64,
+        ),
+        (Size::S32, Size::S64, false) => (
+            GEF32_LT_U64_MIN.to_bits() as u64,
+            LEF32_GT_U64_MAX.to_bits() as u64,
+        ),
+        (Size::S64, Size::S32, true) => (GEF64_LT_I32_MIN.to_bits(), LEF64_GT_I32_MAX.to_bits()),
+        (Size::S64, Size::S32, false) => (GEF64_LT_U32_MIN.to_bits(), LEF64_GT_U32_MAX.to_bits()),
+        (Size::S64, Size::S64, true) => (GEF64_LT_I64_MIN.to_bits(), LEF64_GT_I64_MAX.to_bits()),
+        (Size::S64, Size::S64, false) => (GEF64_LT_U64_MIN.to_bits(), LEF64_GT_U64_MAX.to_bits()),
+        _ => unreachable!(),
+    }
+}
+
+/// Stack pointer, as a base register.
+const SP: GPR = GPR::XzrSp;
+
+// Standard entry trampoline.
+pub fn gen_std_trampoline(sig: &FunctionType) -> FunctionBody {
+    let mut a = Assembler::new().unwrap();
+
+    // Calculate stack offset.
+    let mut stack_offset: u32 = 0;
+    for i in 0..sig.params().len() {
+        if let Location::Memory(_, _) = Machine::get_param_location(1 + i) {
+            stack_offset += 8;
+        }
+    }
+    // Keep the stack pointer 16-byte aligned, with room for two callee-saved registers.
+    let stack_offset = ((stack_offset + 15) & !15) + 16;
+
+    a.emit_prologue();
+    a.emit_sub_sp(stack_offset);
+
+    // Used callee-saved registers
+    a.emit_mov(
+        Size::S64,
+        Location::GPR(GPR::X19),
+        Location::Memory(SP, stack_offset as i32 - 16),
+    );
+    a.emit_mov(
+        Size::S64,
+        Location::GPR(GPR::X20),
+        Location::Memory(SP, stack_offset as i32 - 8),
+    );
+
+    // Arguments
+    a.emit_mov(
+        Size::S64,
+        Machine::get_param_location(1),
+        Location::GPR(GPR::X19),
+    ); // func_ptr
+    a.emit_mov(
+        Size::S64,
+        Machine::get_param_location(2),
+        Location::GPR(GPR::X20),
+    ); // args_rets
+
+    // Move arguments to their locations.
+    // `callee_vmctx` is already in the first argument register, so no need to move.
+    {
+        let mut n_stack_args: usize = 0;
+        for i in 0..sig.params().len() {
+            let src_loc = Location::Memory(GPR::X20, (i * 16) as _); // args_rets[i]
+            let dst_loc = Machine::get_param_location(1 + i);
+
+            match dst_loc {
+                Location::GPR(_) => {
+                    a.emit_mov(Size::S64, src_loc, dst_loc);
+                }
+                Location::Memory(_, _) => {
+                    // This location is for reading arguments but we are writing arguments here.
+                    // So recalculate it.
+                    a.emit_mov(
+                        Size::S64,
+                        src_loc,
+                        Location::Memory(SP, (n_stack_args * 8) as _),
+                    );
+                    n_stack_args += 1;
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    // Call.
+    a.emit_blr(GPR::X19);
+
+    // Write return value.
+    if !sig.results().is_empty() {
+        a.emit_mov(
+            Size::S64,
+            Location::GPR(GPR::X0),
+            Location::Memory(GPR::X20, 0),
+        );
+    }
+
+    // Restore callee-saved registers.
+    a.emit_mov(
+        Size::S64,
+        Location::Memory(SP, stack_offset as i32 - 16),
+        Location::GPR(GPR::X19),
+    );
+    a.emit_mov(
+        Size::S64,
+        Location::Memory(SP, stack_offset as i32 - 8),
+        Location::GPR(GPR::X20),
+    );
+
+    a.emit_epilogue();
+
+    FunctionBody {
+        body: a.finalize().unwrap().to_vec(),
+        unwind_info: None,
+    }
+}
+
+/// Generates dynamic import function call trampoline for a function type.
+pub fn gen_std_dynamic_import_trampoline(
+    vmoffsets: &VMOffsets,
+    sig: &FunctionType,
+) -> Result<FunctionBody, CompileError> {
+    let mut a = Assembler::new().unwrap();
+
+    // Allocate argument array, 16 bytes each.
+    let values_size: usize = 16 * std::cmp::max(sig.params().len(), sig.results().len());
+    let stack_offset = (values_size + 15) & !15;
+
+    a.emit_prologue();
+    if stack_offset > 0 {
+        a.emit_sub_sp(stack_offset as u32);
+    }
+
+    // Copy arguments.
+    if !sig.params().is_empty() {
+        let mut argalloc = ArgumentRegisterAllocator::default();
+        argalloc.next(Type::I64)?; // skip VMContext
+
+        let mut stack_param_count: usize = 0;
+
+        for (i, ty) in sig.params().iter().enumerate() {
+            let sz = match *ty {
+                Type::F32 | Type::I32 => Size::S32,
+                _ => Size::S64,
+            };
+            let source_loc = match argalloc.next(*ty)? {
+                Some(ARM64Register::GPR(gpr)) => Location::GPR(gpr),
+                Some(ARM64Register::NEON(neon)) => Location::SIMD(neon),
+                None => {
+                    // Above the saved frame pointer and link register.
+                    let loc = Location::Memory(GPR::X29, (16 + stack_param_count * 8) as _);
+                    stack_param_count += 1;
+                    loc
+                }
+            };
+            // Zero upper bits, as the upper bits of the argument registers are unspecified.
+            a.emit_mov(
+                Size::S64,
+                Location::Imm32(0),
+                Location::Memory(SP, (i * 16) as _),
+            );
+            a.emit_mov(
+                Size::S64,
+                Location::Imm32(0),
+                Location::Memory(SP, (i * 16 + 8) as _),
+            );
+            a.emit_mov(sz, source_loc, Location::Memory(SP, (i * 16) as _));
+        }
+    }
+
+    // Load target address.
+    a.emit_mov(
+        Size::S64,
+        Location::Memory(
+            GPR::X0,
+            vmoffsets.vmdynamicfunction_import_context_address() as i32,
+        ),
+        Location::GPR(GPR::X16),
+    );
+
+    // Load values array.
+    a.emit_mov_from_sp(GPR::X1);
+
+    // Call target.
+    a.emit_blr(GPR::X16);
+
+    // Fetch return value, in both X0 and D0 so that it is found whatever its type.
+    if !sig.results().is_empty() {
+        assert_eq!(sig.results().len(), 1);
+        a.emit_mov(Size::S64, Location::Memory(SP, 0), Location::GPR(GPR::X0));
+        a.emit_mov(Size::S64, Location::GPR(GPR::X0), Location::SIMD(NEON::V0));
+    }
+
+    // Release values array and return.
+    a.emit_epilogue();
+
+    Ok(FunctionBody {
+        body: a.finalize().unwrap().to_vec(),
+        unwind_info: None,
+    })
+}
+
+// Singlepass calls import functions through a trampoline.
+pub fn gen_import_call_trampoline(
+    vmoffsets: &VMOffsets,
+    index: FunctionIndex,
+    sig: &FunctionType,
+) -> Result<CustomSection, CompileError> {
+    let mut a = Assembler::new().unwrap();
+
+    // Singlepass internally treats all arguments as integers, but the AAPCS64 requires
+    // floating point arguments to be passed in NEON registers.
+    //
+    // FIXME: This is only a workaround. We should fix singlepass to use the standard CC.
+    //
+    // Stack arguments take 8 bytes each, as on Linux. Apple platforms pack them instead,
+    // and are not supported.
+
+    // Translation is expensive, so only do it if needed.
+    if sig
+        .params()
+        .iter()
+        .any(|&x| x == Type::F32 || x == Type::F64)
+    {
+        const N_REG_PARAMS: usize = 7;
+        let mut param_locations: Vec<Location> = vec![];
+
+        // Allocate stack space for arguments.
+        let stack_offset: i32 = 64;
+        a.emit_sub_sp(stack_offset as u32);
+
+        // Store all arguments to the stack to prevent overwrite.
+        for i in 0..sig.params().len() {
+            let loc = match i {
+                0..=6 => {
+                    static PARAM_REGS: &[GPR] = &[
+                        GPR::X1,
+                        GPR::X2,
+                        GPR::X3,
+                        GPR::X4,
+                        GPR::X5,
+                        GPR::X6,
+                        GPR::X7,
+                    ];
+                    let loc = Location::Memory(SP, (i * 8) as i32);
+                    a.emit_mov(Size::S64, Location::GPR(PARAM_REGS[i]), loc);
+                    loc
+                }
+                _ => Location::Memory(SP, stack_offset + ((i - N_REG_PARAMS) * 8) as i32),
+            };
+            param_locations.push(loc);
+        }
+
+        // Copy arguments.
+        let mut argalloc = ArgumentRegisterAllocator::default();
+        argalloc.next(Type::I64)?; // skip VMContext
+        let mut caller_stack_offset: i32 = 0;
+        for (i, ty) in sig.params().iter().enumerate() {
+            let prev_loc = param_locations[i];
+            let target = match argalloc.next(*ty)? {
+                Some(ARM64Register::GPR(gpr)) => Location::GPR(gpr),
+                Some(ARM64Register::NEON(neon)) => Location::SIMD(neon),
+                None => {
+                    // No register can be allocated. Put this argument on the stack.
+                    //
+                    // Since here we never use fewer registers than by the original call, on the caller's frame
+                    // we always have enough space to store the rearranged arguments, and the copy "backward" between different
+                    // slots in the caller argument region will always work.
+                    a.emit_mov(
+                        Size::S64,
+                        prev_loc,
+                        Location::Memory(SP, stack_offset + caller_stack_offset),
+                    );
+                    caller_stack_offset += 8;
+                    continue;
+                }
+            };
+            a.emit_mov(Size::S64, prev_loc, target);
+        }
+
+        // Restore stack pointer.
+        a.emit_add_sp(stack_offset as u32);
+    }
+
+    // Emits a tail call trampoline that loads the address of the target import function
+    // from Ctx and jumps to it.
+
+    let offset = vmoffsets.vmctx_vmfunction_import(index);
+
+    // X16 may be used to address the vmctx fields, so the target vmctx goes through X17.
+    a.emit_mov(
+        Size::S64,
+        Location::Memory(GPR::X0, offset as i32 + 8), // target vmctx
+        Location::GPR(GPR::X17),
+    );
+    a.emit_mov(
+        Size::S64,
+        Location::Memory(GPR::X0, offset as i32), // function pointer
+        Location::GPR(GPR::X16),
+    );
+    a.emit_mov(Size::S64, Location::GPR(GPR::X17), Location::GPR(GPR::X0));
+    a.emit_br(GPR::X16);
+
+    let section_body = SectionBody::new_with_vec(a.finalize().unwrap().to_vec());
+
+    Ok(CustomSection {
+        protection: CustomSectionProtection::ReadExecute,
+        bytes: section_body,
+        relocations: vec![],
+    })
+}
@@ -1,8 +1,10 @@
 use crate::address_map::get_function_address_map;
-use crate::{common_decl::*, config::Singlepass, emitter_x64::*, machine::Machine, x64_decl::*};
+use crate::{
+    common_decl::*, config::Singlepass, emitter_x64::*, machine::Machine as GenericMachine,
+    machine_x64::X64, x64_decl::*,
+};
 use dynasmrt::{x64::Assembler, DynamicLabel};
 use smallvec::{smallvec, SmallVec};
-use std::collections::BTreeMap;
 use std::iter;
 use wasmer_compiler::wasmparser::{
     MemoryImmediate, Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType,
@@ -22,6 +24,8 @@ use wasmer_types::{
 };
 use wasmer_vm::{MemoryStyle, ModuleInfo, TableStyle, TrapCode, VMBuiltinFunctionIndex, VMOffsets};
 
+type Machine = GenericMachine<X64>;
+
 /// The singlepass per-function code generator.
 pub struct FuncGen<'a> {
     // Immutable properties assigned at creation time.
@@ -58,6 +62,10 @@ pub struct FuncGen<'a> {
     /// Value stack.
     value_stack: Vec<Location>,
 
+    /// Number of 16-byte slots of the value area, large enough for the results of any
+    /// block or call with multiple results.
+    value_area_slots: usize,
+
     /// Metadata about floating point values on the stack.
     fp_stack: Vec<FloatValue>,
 
@@ -94,16 +102,12 @@ pub struct FuncGen<'a> {
 struct SpecialLabelSet {
     integer_division_by_zero: DynamicLabel,
     heap_access_oob: DynamicLabel,
+    unaligned_atomic: DynamicLabel,
     table_access_oob: DynamicLabel,
     indirect_call_null: DynamicLabel,
     bad_signature: DynamicLabel,
-}
-
-/// A trap table for a `RunnableModuleInfo`.
-#[derive(Clone, Debug, Default)]
-pub struct TrapTable {
-    /// Mappings from offsets in generated machine code to the corresponding trap code.
-    pub offset_to_code: BTreeMap<usize, TrapCode>,
+    interrupt: DynamicLabel,
+    stack_overflow: DynamicLabel,
 }
 
 /// Metadata about a floating-point value.
@@ -232,7 +236,12 @@ pub struct ControlFrame {
     pub label: DynamicLabel,
     pub loop_like: bool,
     pub if_else: IfElseState,
+    pub params: SmallVec<[WpType; 8]>,
     pub returns: SmallVec<[WpType; 1]>,
+    /// Locations of the parameters when entering a loop, which branches to it must fill.
+    pub loop_param_locations: SmallVec<[Location; 8]>,
+    /// Machine stack offset when entering a loop, which branches to it must restore.
+    pub loop_stack_offset: usize,
     pub value_stack_depth: usize,
     pub fp_stack_depth: usize,
     pub state: MachineState,
@@ -246,11 +255,6 @@ pub enum IfElseState {
     Else,
 }
 
-#[derive(Debug)]
-pub struct CodegenError {
-    pub message: String,
-}
-
 /// Abstraction for a 2-input, 1-output operator. Can be an integer/floating-point
 /// binop/cmpop.
 struct I2O1 {
@@ -331,14 +335,59 @@ impl<'a> FuncGen<'a> {
         self.mark_instruction_address_end(offset);
     }
 
+    /// Jumps to the `interrupt` special label if the interrupt flag of the
+    /// `VMInterrupts` is set.
+    fn emit_interrupt_check(&mut self) {
+        let interrupts = self.machine.acquire_temp_gpr().unwrap();
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(
+                Machine::get_vmctx_reg(),
+                self.vmoffsets.vmctx_interrupts() as i32,
+            ),
+            Location::GPR(interrupts),
+        );
+        self.assembler.emit_cmp(
+            Size::S64,
+            Location::Imm32(0),
+            Location::Memory(interrupts, self.vmoffsets.vminterrupts_interrupt() as i32),
+        );
+        self.assembler
+            .emit_jmp(Condition::NotEqual, self.special_labels.interrupt);
+        self.machine.release_temp_gpr(interrupts);
+    }
+
+    /// Jumps to the `stack_overflow` special label if the stack pointer is below the
+    /// stack limit of the `VMInterrupts`.
+    fn emit_stack_check(&mut self) {
+        let interrupts = self.machine.acquire_temp_gpr().unwrap();
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(
+                Machine::get_vmctx_reg(),
+                self.vmoffsets.vmctx_interrupts() as i32,
+            ),
+            Location::GPR(interrupts),
+        );
+        // A zero limit is never above the stack pointer.
+        self.assembler.emit_cmp(
+            Size::S64,
+            Location::Memory(interrupts, self.vmoffsets.vminterrupts_stack_limit() as i32),
+            Location::GPR(GPR::RSP),
+        );
+        self.assembler
+            .emit_jmp(Condition::Below, self.special_labels.stack_overflow);
+        self.machine.release_temp_gpr(interrupts);
+    }
+
     /// Canonicalizes the floating point value at `input` into `output`.
     fn canonicalize_nan(&mut self, sz: Size, input: Location, output: Location) {
-        let tmp1 = self.machine.acquire_temp_xmm().unwrap();
-        let tmp2 = self.machine.acquire_temp_xmm().unwrap();
-        let tmp3 = self.machine.acquire_temp_xmm().unwrap();
+        let tmp1 = self.machine.acquire_temp_simd().unwrap();
+        let tmp2 = self.machine.acquire_temp_simd().unwrap();
+        let tmp3 = self.machine.acquire_temp_simd().unwrap();
         let tmpg1 = self.machine.acquire_temp_gpr().unwrap();
 
-        self.emit_relaxed_binop(Assembler::emit_mov, sz, input, Location::XMM(tmp1));
+        self.emit_relaxed_binop(Assembler::emit_mov, sz, input, Location::SIMD(tmp1));
 
         match sz {
             Size::S32 => {
@@ -350,7 +399,7 @@ impl<'a> FuncGen<'a> {
                     Location::GPR(tmpg1),
                 );
                 self.assembler
-                    .emit_mov(Size::S64, Location::GPR(tmpg1), Location::XMM(tmp3));
+                    .emit_mov(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp3));
                 self.assembler
                     .emit_vblendvps(tmp2, XMMOrMemory::XMM(tmp3), tmp1, tmp1);
             }
@@ -363,19 +412,19 @@ impl<'a> FuncGen<'a> {
                     Location::GPR(tmpg1),
                 );
                 self.assembler
-                    .emit_mov(Size::S64, Location::GPR(tmpg1), Location::XMM(tmp3));
+                    .emit_mov(Size::S64, Location::GPR(tmpg1), Location::SIMD(tmp3));
                 self.assembler
                     .emit_vblendvpd(tmp2, XMMOrMemory::XMM(tmp3), tmp1, tmp1);
             }
             _ => unreachable!(),
         }
 
-        self.emit_relaxed_binop(Assembler::emit_mov, sz, Location::XMM(tmp1), output);
+        self.emit_relaxed_binop(Assembler::emit_mov, sz, Location::SIMD(tmp1), output);
 
         self.machine.release_temp_gpr(tmpg1);
-        self.machine.release_temp_xmm(tmp3);
-        self.machine.release_temp_xmm(tmp2);
-        self.machine.release_temp_xmm(tmp1);
+        self.machine.release_temp_simd(tmp3);
+        self.machine.release_temp_simd(tmp2);
+        self.machine.release_temp_simd(tmp1);
     }
 
     /// Moves `loc` to a valid location for `div`/`idiv`.
@@ -494,6 +543,11 @@ impl<'a> FuncGen<'a> {
             _ if (op as *const u8 == Assembler::emit_imul as *const u8) => RelaxMode::BothToGPR,
 
             (Location::Memory(_, _), Location::Memory(_, _)) => RelaxMode::SrcToGPR,
+            (Location::Imm32(_), _) | (Location::Imm64(_), _)
+                if (op as *const u8 == Assembler::emit_xchg as *const u8) =>
+            {
+                RelaxMode::SrcToGPR
+            }
             (Location::Imm64(_), Location::Imm64(_)) | (Location::Imm64(_), Location::Imm32(_)) => {
                 RelaxMode::BothToGPR
             }
@@ -504,7 +558,7 @@ impl<'a> FuncGen<'a> {
             {
                 RelaxMode::SrcToGPR
             }
-            (_, Location::XMM(_)) => RelaxMode::SrcToGPR,
+            (_, Location::SIMD(_)) => RelaxMode::SrcToGPR,
             _ => RelaxMode::Direct,
         };
 
@@ -572,30 +626,30 @@ impl<'a> FuncGen<'a> {
         src2: Location,
         dst: Location,
     ) -> Result<(), CodegenError> {
-        let tmp1 = self.machine.acquire_temp_xmm().unwrap();
-        let tmp2 = self.machine.acquire_temp_xmm().unwrap();
-        let tmp3 = self.machine.acquire_temp_xmm().unwrap();
+        let tmp1 = self.machine.acquire_temp_simd().unwrap();
+        let tmp2 = self.machine.acquire_temp_simd().unwrap();
+        let tmp3 = self.machine.acquire_temp_simd().unwrap();
         let tmpg = self.machine.acquire_temp_gpr().unwrap();
 
         let src1 = match src1 {
-            Location::XMM(x) => x,
+            Location::SIMD(x) => x,
             Location::GPR(_) | Location::Memory(_, _) => {
                 self.assembler
-                    .emit_mov(Size::S64, src1, Location::XMM(tmp1));
+                    .emit_mov(Size::S64, src1, Location::SIMD(tmp1));
                 tmp1
             }
             Location::Imm32(_) => {
                 self.assembler
                     .emit_mov(Size::S32, src1, Location::GPR(tmpg));
                 self.assembler
-                    .emit_mov(Size::S32, Location::GPR(tmpg), Location::XMM(tmp1));
+                    .emit_mov(Size::S32, Location::GPR(tmpg), Location::SIMD(tmp1));
                 tmp1
             }
             Location::Imm64(_) => {
                 self.assembler
                     .emit_mov(Size::S64, src1, Location::GPR(tmpg));
                 self.assembler
-                    .emit_mov(Size::S64, Location::GPR(tmpg), Location::XMM(tmp1));
+                    .emit_mov(Size::S64, Location::GPR(tmpg), Location::SIMD(tmp1));
                 tmp1
             }
             _ => {
@@ -606,25 +660,25 @@ impl<'a> FuncGen<'a> {
         };
 
         let src2 = match src2 {
-            Location::XMM(x) => XMMOrMemory::XMM(x),
+            Location::SIMD(x) => XMMOrMemory::XMM(x),
             Location::Memory(base, disp) => XMMOrMemory::Memory(base, disp),
             Location::GPR(_) => {
                 self.assembler
-                    .emit_mov(Size::S64, src2, Location::XMM(tmp2));
+                    .emit_mov(Size::S64, src2, Location::SIMD(tmp2));
                 XMMOrMemory::XMM(tmp2)
             }
             Location::Imm32(_) => {
                 self.assembler
                     .emit_mov(Size::S32, src2, Location::GPR(tmpg));
                 self.assembler
-                    .emit_mov(Size::S32, Location::GPR(tmpg), Location::XMM(tmp2));
+                    .emit_mov(Size::S32, Location::GPR(tmpg), Location::SIMD(tmp2));
                 XMMOrMemory::XMM(tmp2)
             }
             Location::Imm64(_) => {
                 self.assembler
                     .emit_mov(Size::S64, src2, Location::GPR(tmpg));
                 self.assembler
-                    .emit_mov(Size::S64, Location::GPR(tmpg), Location::XMM(tmp2));
+                    .emit_mov(Size::S64, Location::GPR(tmpg), Location::SIMD(tmp2));
                 XMMOrMemory::XMM(tmp2)
             }
             _ => {
@@ -635,12 +689,13 @@ impl<'a> FuncGen<'a> {
         };
 
         match dst {
-            Location::XMM(x) => {
+            Location::SIMD(x) => {
                 op(self, src1, src2, x);
             }
             Location::Memory(_, _) | Location::GPR(_) => {
                 op(self, src1, src2, tmp3);
-                self.assembler.emit_mov(Size::S64, Location::XMM(tmp3), dst);
+                self.assembler
+                    .emit_mov(Size::S64, Location::SIMD(tmp3), dst);
             }
             _ => {
                 return Err(CodegenError {
@@ -650,9 +705,9 @@ impl<'a> FuncGen<'a> {
         }
 
         self.machine.release_temp_gpr(tmpg);
-        self.machine.release_temp_xmm(tmp3);
-        self.machine.release_temp_xmm(tmp2);
-        self.machine.release_temp_xmm(tmp1);
+        self.machine.release_temp_simd(tmp3);
+        self.machine.release_temp_simd(tmp2);
+        self.machine.release_temp_simd(tmp1);
         Ok(())
     }
 
@@ -921,39 +976,968 @@ impl<'a> FuncGen<'a> {
     ) -> Result<(), CodegenError> {
         let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64);
 
-        self.emit_relaxed_avx(f, loc_a, loc_b, ret)?;
-        Ok(())
+        self.emit_relaxed_avx(f, loc_a, loc_b, ret)?;
+        Ok(())
+    }
+
+    /// Floating point (AVX) comparison with both operands popped from the virtual stack.
+    fn emit_fp_cmpop_avx(
+        &mut self,
+        f: fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+    ) -> Result<(), CodegenError> {
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
+
+        self.emit_relaxed_avx(f, loc_a, loc_b, ret)?;
+
+        // Workaround for behavior inconsistency among different backing implementations.
+        // (all bits or only the least significant bit are set to one?)
+        self.assembler.emit_and(Size::S32, Location::Imm32(1), ret);
+        Ok(())
+    }
+
+    /// Floating point (AVX) unop with both operands popped from the virtual stack.
+    fn emit_fp_unop_avx(
+        &mut self,
+        f: fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+    ) -> Result<(), CodegenError> {
+        let loc = self.pop_value_released();
+        let ret = self.machine.acquire_locations(
+            &mut self.assembler,
+            &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
+            false,
+        )[0];
+        self.value_stack.push(ret);
+        self.emit_relaxed_avx(f, loc, loc, ret)?;
+        Ok(())
+    }
+
+    /// Acquires a stack slot for a `v128` result and pushes it onto the virtual stack.
+    fn acquire_v128_location(&mut self) -> Location {
+        let loc = self.machine.acquire_locations(
+            &mut self.assembler,
+            &[(
+                WpType::V128,
+                MachineValue::WasmStack(self.value_stack.len()),
+            )],
+            false,
+        )[0];
+        self.value_stack.push(loc);
+        loc
+    }
+
+    /// Pops a `v128` value from the virtual stack and loads it into `dst`.
+    fn pop_v128(&mut self, dst: XMM) {
+        let loc = self.pop_value_released();
+        self.assembler
+            .emit_vmovdqu(v128_operand(loc), XMMOrMemory::XMM(dst));
+    }
+
+    /// Pushes the `v128` value in `src` onto the virtual stack.
+    fn push_v128(&mut self, src: XMM) {
+        let loc = self.acquire_v128_location();
+        self.assembler
+            .emit_vmovdqu(XMMOrMemory::XMM(src), v128_operand(loc));
+    }
+
+    /// Copies the `v128` value at `src` to `dst`.
+    fn emit_v128_mov(&mut self, src: Location, dst: Location) {
+        if src == dst {
+            return;
+        }
+        match (src, dst) {
+            (Location::Memory(_, _), Location::Memory(_, _)) => {
+                let tmp = self.machine.acquire_temp_simd().unwrap();
+                self.assembler
+                    .emit_vmovdqu(v128_operand(src), XMMOrMemory::XMM(tmp));
+                self.assembler
+                    .emit_vmovdqu(XMMOrMemory::XMM(tmp), v128_operand(dst));
+                self.machine.release_temp_simd(tmp);
+            }
+            _ => self
+                .assembler
+                .emit_vmovdqu(v128_operand(src), v128_operand(dst)),
+        }
+    }
+
+    /// Loads the constant `value` into `dst`.
+    fn emit_v128_const(&mut self, value: u128, dst: XMM) {
+        if value == 0 {
+            self.assembler.emit_vpxor(dst, XMMOrMemory::XMM(dst), dst);
+            return;
+        }
+        if value == std::u128::MAX {
+            self.assembler
+                .emit_vpcmpeqd(dst, XMMOrMemory::XMM(dst), dst);
+            return;
+        }
+        let tmp = self.machine.acquire_temp_gpr().unwrap();
+        self.assembler
+            .emit_mov(Size::S64, Location::Imm64(value as u64), Location::GPR(tmp));
+        self.assembler
+            .emit_mov(Size::S64, Location::GPR(tmp), Location::SIMD(dst));
+        let high = (value >> 64) as u64;
+        if high != 0 {
+            self.assembler
+                .emit_mov(Size::S64, Location::Imm64(high), Location::GPR(tmp));
+            self.assembler.emit_vpinsrq(dst, tmp, 1, dst);
+        }
+        self.machine.release_temp_gpr(tmp);
+    }
+
+    /// Replaces the NaN lanes of the `f32x4` or `f64x2` value in `x` with canonical NaNs.
+    fn canonicalize_nan_v128(&mut self, sz: Size, x: XMM) {
+        let mask = self.machine.acquire_temp_simd().unwrap();
+        let nan = self.machine.acquire_temp_simd().unwrap();
+
+        match sz {
+            Size::S32 => {
+                self.assembler
+                    .emit_vcmpunordps(x, XMMOrMemory::XMM(x), mask);
+                self.emit_v128_const(0x7FC0_0000_7FC0_0000_7FC0_0000_7FC0_0000, nan);
+                self.assembler
+                    .emit_vblendvps(mask, XMMOrMemory::XMM(nan), x, x);
+            }
+            Size::S64 => {
+                self.assembler
+                    .emit_vcmpunordpd(x, XMMOrMemory::XMM(x), mask);
+                self.emit_v128_const(0x7FF8_0000_0000_0000_7FF8_0000_0000_0000, nan);
+                self.assembler
+                    .emit_vblendvpd(mask, XMMOrMemory::XMM(nan), x, x);
+            }
+            _ => unreachable!(),
+        }
+
+        self.machine.release_temp_simd(nan);
+        self.machine.release_temp_simd(mask);
+    }
+
+    /// SIMD operation with one `v128` operand popped from the virtual stack.
+    fn emit_simd_unop<F: FnOnce(&mut Self, XMM, XMM)>(&mut self, f: F) {
+        let src = self.machine.acquire_temp_simd().unwrap();
+        let dst = self.machine.acquire_temp_simd().unwrap();
+        self.pop_v128(src);
+        f(self, src, dst);
+        self.push_v128(dst);
+        self.machine.release_temp_simd(dst);
+        self.machine.release_temp_simd(src);
+    }
+
+    /// SIMD (AVX) operation with one `v128` operand popped from the virtual stack.
+    fn emit_simd_unop_avx(&mut self, f: fn(&mut Assembler, XMMOrMemory, XMM)) {
+        self.emit_simd_unop(|this, src, dst| f(&mut this.assembler, XMMOrMemory::XMM(src), dst));
+    }
+
+    /// SIMD operation with two `v128` operands popped from the virtual stack.
+    fn emit_simd_binop<F: FnOnce(&mut Self, XMM, XMM, XMM)>(&mut self, f: F) {
+        let a = self.machine.acquire_temp_simd().unwrap();
+        let b = self.machine.acquire_temp_simd().unwrap();
+        let dst = self.machine.acquire_temp_simd().unwrap();
+        self.pop_v128(b);
+        self.pop_v128(a);
+        f(self, a, b, dst);
+        self.push_v128(dst);
+        self.machine.release_temp_simd(dst);
+        self.machine.release_temp_simd(b);
+        self.machine.release_temp_simd(a);
+    }
+
+    /// SIMD (AVX) operation with two `v128` operands popped from the virtual stack.
+    fn emit_simd_binop_avx(&mut self, f: fn(&mut Assembler, XMM, XMMOrMemory, XMM)) {
+        self.emit_simd_binop(|this, a, b, dst| f(&mut this.assembler, a, XMMOrMemory::XMM(b), dst));
+    }
+
+    /// Like `emit_simd_binop_avx`, but with the operands of the instruction swapped.
+    fn emit_simd_binop_avx_rev(&mut self, f: fn(&mut Assembler, XMM, XMMOrMemory, XMM)) {
+        self.emit_simd_binop(|this, a, b, dst| f(&mut this.assembler, b, XMMOrMemory::XMM(a), dst));
+    }
+
+    /// SIMD (AVX) floating point operation with two `v128` operands popped from the virtual stack.
+    ///
+    /// The `f64x2` results always get canonical NaNs: x86 returns the first NaN operand, and
+    /// the `f64x2` spec tests expect a NaN operand with the sign bit set to give a positive NaN.
+    fn emit_simd_fp_binop_avx(&mut self, sz: Size, f: fn(&mut Assembler, XMM, XMMOrMemory, XMM)) {
+        self.emit_simd_binop(|this, a, b, dst| {
+            f(&mut this.assembler, a, XMMOrMemory::XMM(b), dst);
+            if this.assembler.arch_supports_canonicalize_nan()
+                && (sz == Size::S64 || this.config.enable_nan_canonicalization)
+            {
+                this.canonicalize_nan_v128(sz, dst);
+            }
+        });
+    }
+
+    /// SIMD (AVX) floating point operation with one `v128` operand popped from the virtual stack.
+    fn emit_simd_fp_unop_avx(&mut self, sz: Size, f: fn(&mut Assembler, XMMOrMemory, XMM)) {
+        self.emit_simd_unop(|this, src, dst| {
+            f(&mut this.assembler, XMMOrMemory::XMM(src), dst);
+            if this.assembler.arch_supports_canonicalize_nan()
+                && this.config.enable_nan_canonicalization
+            {
+                this.canonicalize_nan_v128(sz, dst);
+            }
+        });
+    }
+
+    /// SIMD integer comparison whose result is the inverse of the comparison `f`.
+    fn emit_simd_cmpop_not<F: FnOnce(&mut Self, XMM, XMM, XMM)>(&mut self, f: F) {
+        self.emit_simd_binop(|this, a, b, dst| {
+            f(this, a, b, dst);
+            // `b` is no longer needed, use it for the all-ones mask.
+            this.assembler.emit_vpcmpeqd(b, XMMOrMemory::XMM(b), b);
+            this.assembler.emit_vpxor(dst, XMMOrMemory::XMM(b), dst);
+        });
+    }
+
+    /// SIMD comparison `a >= b` (or `a <= b`) built from the lane-wise maximum (or minimum) `f`.
+    fn emit_simd_cmpop_minmax(
+        &mut self,
+        f: fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+        eq: fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+    ) {
+        self.emit_simd_binop(|this, a, b, dst| {
+            f(&mut this.assembler, a, XMMOrMemory::XMM(b), dst);
+            eq(&mut this.assembler, dst, XMMOrMemory::XMM(a), dst);
+        });
+    }
+
+    /// Like `emit_simd_cmpop_minmax`, but the result is inverted.
+    fn emit_simd_cmpop_minmax_not(
+        &mut self,
+        f: fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+        eq: fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+    ) {
+        self.emit_simd_cmpop_not(|this, a, b, dst| {
+            f(&mut this.assembler, a, XMMOrMemory::XMM(b), dst);
+            eq(&mut this.assembler, dst, XMMOrMemory::XMM(a), dst);
+        });
+    }
+
+    /// SIMD shift of a `v128` value by an `i32` count, both popped from the virtual stack.
+    ///
+    /// The count is taken modulo the lane width (`mask + 1`) and passed to `f` in the low
+    /// 64 bits of an XMM register.
+    fn emit_simd_shift<F: FnOnce(&mut Self, XMM, XMM, XMM)>(&mut self, mask: u32, f: F) {
+        let count = self.pop_value_released();
+        let src = self.machine.acquire_temp_simd().unwrap();
+        let tmp_count = self.machine.acquire_temp_simd().unwrap();
+        let dst = self.machine.acquire_temp_simd().unwrap();
+        self.pop_v128(src);
+
+        let tmp = self.machine.acquire_temp_gpr().unwrap();
+        self.emit_relaxed_binop(Assembler::emit_mov, Size::S32, count, Location::GPR(tmp));
+        self.assembler
+            .emit_and(Size::S32, Location::Imm32(mask), Location::GPR(tmp));
+        self.assembler
+            .emit_mov(Size::S32, Location::GPR(tmp), Location::SIMD(tmp_count));
+        self.machine.release_temp_gpr(tmp);
+
+        f(self, src, tmp_count, dst);
+        self.push_v128(dst);
+
+        self.machine.release_temp_simd(dst);
+        self.machine.release_temp_simd(tmp_count);
+        self.machine.release_temp_simd(src);
+    }
+
+    /// `i8x16` shift right, done on 16-bit lanes and narrowed back with `pack`.
+    fn emit_simd_shr_i8x16(
+        &mut self,
+        shift: fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+        pack: fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+    ) {
+        self.emit_simd_shift(7, |this, src, count, dst| {
+            let high = this.machine.acquire_temp_simd().unwrap();
+            // Each byte is duplicated into both halves of a 16-bit lane, so shifting by
+            // `count + 8` leaves the (sign or zero extended) shifted byte in the lane.
+            this.assembler
+                .emit_vpunpcklbw(src, XMMOrMemory::XMM(src), dst);
+            this.assembler
+                .emit_vpunpckhbw(src, XMMOrMemory::XMM(src), high);
+            this.emit_simd_shift_count_plus_8(count);
+            shift(&mut this.assembler, dst, XMMOrMemory::XMM(count), dst);
+            shift(&mut this.assembler, high, XMMOrMemory::XMM(count), high);
+            pack(&mut this.assembler, dst, XMMOrMemory::XMM(high), dst);
+            this.machine.release_temp_simd(high);
+        });
+    }
+
+    /// Adds 8 to the shift count in the low 64 bits of `count`.
+    fn emit_simd_shift_count_plus_8(&mut self, count: XMM) {
+        let tmp = self.machine.acquire_temp_gpr().unwrap();
+        self.assembler
+            .emit_mov(Size::S32, Location::SIMD(count), Location::GPR(tmp));
+        self.assembler
+            .emit_add(Size::S32, Location::Imm32(8), Location::GPR(tmp));
+        self.assembler
+            .emit_mov(Size::S32, Location::GPR(tmp), Location::SIMD(count));
+        self.machine.release_temp_gpr(tmp);
+    }
+
+    /// Extracts lane `lane` of `size` from a `v128` value popped from the virtual stack.
+    ///
+    /// 8-bit and 16-bit lanes are sign extended if `signed` is set and zero extended otherwise.
+    fn emit_simd_extract_lane(
+        &mut self,
+        lane: u8,
+        size: Size,
+        signed: bool,
+        ty: WpType,
+    ) -> Result<(), CodegenError> {
+        let loc = self.pop_value_released();
+        let (base, disp) = match loc {
+            Location::Memory(base, disp) => (base, disp),
+            _ => {
+                return Err(CodegenError {
+                    message: "emit_simd_extract_lane: v128 value not in memory".to_string(),
+                })
+            }
+        };
+        let lane_size = match size {
+            Size::S8 => 1,
+            Size::S16 => 2,
+            Size::S32 => 4,
+            Size::S64 => 8,
+        };
+        let src = Location::Memory(base, disp + lane as i32 * lane_size);
+
+        // Load the lane before the result is allocated, which may reuse the slot of the value.
+        let tmp = self.machine.acquire_temp_gpr().unwrap();
+        match size {
+            Size::S8 | Size::S16 if signed => {
+                self.assembler
+                    .emit_movsx(size, src, Size::S32, Location::GPR(tmp));
+            }
+            Size::S8 | Size::S16 => {
+                self.assembler
+                    .emit_movzx(size, src, Size::S32, Location::GPR(tmp));
+            }
+            _ => {
+                self.assembler.emit_mov(size, src, Location::GPR(tmp));
+            }
+        }
+
+        if ty.is_float() {
+            self.fp_stack.push(FloatValue::new(self.value_stack.len()));
+        }
+        let ret = self.machine.acquire_locations(
+            &mut self.assembler,
+            &[(ty, MachineValue::WasmStack(self.value_stack.len()))],
+            false,
+        )[0];
+        self.value_stack.push(ret);
+        self.emit_relaxed_binop(
+            Assembler::emit_mov,
+            if ty == WpType::I64 || ty == WpType::F64 {
+                Size::S64
+            } else {
+                Size::S32
+            },
+            Location::GPR(tmp),
+            ret,
+        );
+        self.machine.release_temp_gpr(tmp);
+        Ok(())
+    }
+
+    /// Pops a scalar operand of a SIMD operation off the virtual stack and loads it into a
+    /// temporary GPR, canonicalizing it first if it is a float.
+    fn pop_simd_scalar(&mut self, ty: WpType) -> Result<GPR, CodegenError> {
+        let loc = self.pop_value_released();
+        let sz = match ty {
+            WpType::I64 | WpType::F64 => Size::S64,
+            _ => Size::S32,
+        };
+        if ty.is_float() {
+            let fp = self.fp_stack.pop1()?;
+            if self.assembler.arch_supports_canonicalize_nan()
+                && self.config.enable_nan_canonicalization
+                && fp.canonicalization.is_some()
+            {
+                self.canonicalize_nan(sz, loc, loc);
+            }
+        }
+        let tmp = self.machine.acquire_temp_gpr().unwrap();
+        self.emit_relaxed_binop(Assembler::emit_mov, sz, loc, Location::GPR(tmp));
+        Ok(tmp)
+    }
+
+    /// Replaces lane `lane` of `size` in a `v128` value with a scalar, both popped from the
+    /// virtual stack.
+    fn emit_simd_replace_lane(
+        &mut self,
+        lane: u8,
+        size: Size,
+        ty: WpType,
+    ) -> Result<(), CodegenError> {
+        let value = self.pop_simd_scalar(ty)?;
+        let loc = self.pop_value_released();
+        let ret = self.acquire_v128_location();
+        self.emit_v128_mov(loc, ret);
+        let (base, disp) = match ret {
+            Location::Memory(base, disp) => (base, disp),
+            _ => {
+                return Err(CodegenError {
+                    message: "emit_simd_replace_lane: v128 value not in memory".to_string(),
+                })
+            }
+        };
+        let lane_size = match size {
+            Size::S8 => 1,
+            Size::S16 => 2,
+            Size::S32 => 4,
+            Size::S64 => 8,
+        };
+        self.assembler.emit_mov(
+            size,
+            Location::GPR(value),
+            Location::Memory(base, disp + lane as i32 * lane_size),
+        );
+        self.machine.release_temp_gpr(value);
+        Ok(())
+    }
+
+    /// Broadcasts the lowest lane of `size` in `x` to all the lanes of `x`.
+    fn emit_simd_broadcast(&mut self, size: Size, x: XMM) {
+        match size {
+            Size::S8 => {
+                let zero = self.machine.acquire_temp_simd().unwrap();
+                self.assembler
+                    .emit_vpxor(zero, XMMOrMemory::XMM(zero), zero);
+                self.assembler.emit_vpshufb(x, XMMOrMemory::XMM(zero), x);
+                self.machine.release_temp_simd(zero);
+            }
+            Size::S16 => {
+                self.assembler.emit_vpshuflw(XMMOrMemory::XMM(x), 0, x);
+                self.assembler.emit_vpshufd(XMMOrMemory::XMM(x), 0, x);
+            }
+            Size::S32 => {
+                self.assembler.emit_vpshufd(XMMOrMemory::XMM(x), 0, x);
+            }
+            Size::S64 => {
+                self.assembler.emit_vpunpcklqdq(x, XMMOrMemory::XMM(x), x);
+            }
+        }
+    }
+
+    /// Creates a `v128` value with all lanes of `size` set to a scalar popped from the virtual stack.
+    fn emit_simd_splat(&mut self, size: Size, ty: WpType) -> Result<(), CodegenError> {
+        let value = self.pop_simd_scalar(ty)?;
+        let tmp = self.machine.acquire_temp_simd().unwrap();
+        self.assembler.emit_mov(
+            if size == Size::S64 {
+                Size::S64
+            } else {
+                Size::S32
+            },
+            Location::GPR(value),
+            Location::SIMD(tmp),
+        );
+        self.machine.release_temp_gpr(value);
+        self.emit_simd_broadcast(size, tmp);
+        self.push_v128(tmp);
+        self.machine.release_temp_simd(tmp);
+        Ok(())
+    }
+
+    /// Reduces a `v128` value popped from the virtual stack to an `i32`, computed by `f` into
+    /// a temporary GPR.
+    fn emit_simd_reduce<F: FnOnce(&mut Self, XMM, GPR)>(&mut self, f: F) {
+        let src = self.machine.acquire_temp_simd().unwrap();
+        self.pop_v128(src);
+        let tmp = self.machine.acquire_temp_gpr().unwrap();
+        f(self, src, tmp);
+        let ret = self.machine.acquire_locations(
+            &mut self.assembler,
+            &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
+            false,
+        )[0];
+        self.value_stack.push(ret);
+        self.assembler.emit_mov(Size::S32, Location::GPR(tmp), ret);
+        self.machine.release_temp_gpr(tmp);
+        self.machine.release_temp_simd(src);
+    }
+
+    /// `any_true`: 1 if any bit of the `v128` value is set.
+    fn emit_simd_any_true(&mut self) {
+        self.emit_simd_reduce(|this, src, tmp| {
+            this.assembler.emit_vptest(src, XMMOrMemory::XMM(src));
+            this.assembler.emit_set(Condition::NotEqual, tmp);
+            this.assembler
+                .emit_and(Size::S32, Location::Imm32(0xff), Location::GPR(tmp));
+        });
+    }
+
+    /// `all_true`: 1 if no lane of the `v128` value is zero, `eq` compares the lanes.
+    fn emit_simd_all_true(&mut self, eq: fn(&mut Assembler, XMM, XMMOrMemory, XMM)) {
+        self.emit_simd_reduce(|this, src, tmp| {
+            let zero = this.machine.acquire_temp_simd().unwrap();
+            this.assembler
+                .emit_vpxor(zero, XMMOrMemory::XMM(zero), zero);
+            eq(&mut this.assembler, src, XMMOrMemory::XMM(zero), src);
+            this.assembler.emit_vptest(src, XMMOrMemory::XMM(src));
+            this.assembler.emit_set(Condition::Equal, tmp);
+            this.assembler
+                .emit_and(Size::S32, Location::Imm32(0xff), Location::GPR(tmp));
+            this.machine.release_temp_simd(zero);
+        });
+    }
+
+    /// Loads a `v128` value from memory with `f`, which reads `value_size` bytes.
+    fn emit_simd_load<F: FnOnce(&mut Self, GPR, XMM)>(
+        &mut self,
+        memarg: &MemoryImmediate,
+        value_size: usize,
+        f: F,
+    ) -> Result<(), CodegenError> {
+        let target = self.pop_value_released();
+        let tmp = self.machine.acquire_temp_simd().unwrap();
+        self.emit_memory_op(target, memarg, false, value_size, |this, addr| {
+            f(this, addr, tmp);
+            Ok(())
+        })?;
+        self.push_v128(tmp);
+        self.machine.release_temp_simd(tmp);
+        Ok(())
+    }
+
+    /// Loads a value of `size` from memory and broadcasts it to all the lanes of a `v128` value.
+    fn emit_simd_load_splat(
+        &mut self,
+        memarg: &MemoryImmediate,
+        size: Size,
+    ) -> Result<(), CodegenError> {
+        let value_size = match size {
+            Size::S8 => 1,
+            Size::S16 => 2,
+            Size::S32 => 4,
+            Size::S64 => 8,
+        };
+        self.emit_simd_load(memarg, value_size, |this, addr, dst| {
+            match size {
+                Size::S8 | Size::S16 => {
+                    let tmp = this.machine.acquire_temp_gpr().unwrap();
+                    this.assembler.emit_movzx(
+                        size,
+                        Location::Memory(addr, 0),
+                        Size::S32,
+                        Location::GPR(tmp),
+                    );
+                    this.assembler
+                        .emit_mov(Size::S32, Location::GPR(tmp), Location::SIMD(dst));
+                    this.machine.release_temp_gpr(tmp);
+                }
+                _ => {
+                    this.assembler
+                        .emit_mov(size, Location::Memory(addr, 0), Location::SIMD(dst));
+                }
+            }
+            this.emit_simd_broadcast(size, dst);
+        })
+    }
+
+    /// Shared part of `f32x4.min`, `f32x4.max`, `f64x2.min` and `f64x2.max`.
+    ///
+    /// `minps`/`maxps` return their second operand if either operand is NaN or both are
+    /// zeros, so the operation is done in both orders and the results are merged so that
+    /// NaNs propagate and `-0.0 < 0.0`. NaN lanes are canonicalized.
+    fn emit_simd_fp_minmax(&mut self, sz: Size, is_max: bool) {
+        let minmax: fn(&mut Assembler, XMM, XMMOrMemory, XMM) = match (sz, is_max) {
+            (Size::S32, false) => Assembler::emit_vminps,
+            (Size::S32, true) => Assembler::emit_vmaxps,
+            (Size::S64, false) => Assembler::emit_vminpd,
+            (Size::S64, true) => Assembler::emit_vmaxpd,
+            _ => unreachable!(),
+        };
+        let cmpunord: fn(&mut Assembler, XMM, XMMOrMemory, XMM) = if sz == Size::S32 {
+            Assembler::emit_vcmpunordps
+        } else {
+            Assembler::emit_vcmpunordpd
+        };
+        let shift: fn(&mut Assembler, XMM, u8, XMM) = if sz == Size::S32 {
+            Assembler::emit_vpsrld_imm8
+        } else {
+            Assembler::emit_vpsrlq_imm8
+        };
+        // Shift amount that keeps the sign, the exponent and the quiet bit of a NaN.
+        let payload_shift = if sz == Size::S32 { 10 } else { 13 };
+        let sub: fn(&mut Assembler, XMM, XMMOrMemory, XMM) = if sz == Size::S32 {
+            Assembler::emit_vsubps
+        } else {
+            Assembler::emit_vsubpd
+        };
+
+        self.emit_simd_binop(|this, a, b, dst| {
+            let tmp = this.machine.acquire_temp_simd().unwrap();
+            minmax(&mut this.assembler, a, XMMOrMemory::XMM(b), tmp);
+            minmax(&mut this.assembler, b, XMMOrMemory::XMM(a), dst);
+            if is_max {
+                // Find the discrepancies, then propagate NaNs and the sign of zeros.
+                this.assembler.emit_vpxor(dst, XMMOrMemory::XMM(tmp), dst);
+                this.assembler.emit_vpor(tmp, XMMOrMemory::XMM(dst), tmp);
+                sub(&mut this.assembler, tmp, XMMOrMemory::XMM(dst), tmp);
+                cmpunord(&mut this.assembler, dst, XMMOrMemory::XMM(tmp), dst);
+            } else {
+                // Propagate NaNs and the sign of zeros.
+                this.assembler.emit_vpor(tmp, XMMOrMemory::XMM(dst), tmp);
+                cmpunord(&mut this.assembler, dst, XMMOrMemory::XMM(tmp), dst);
+                this.assembler.emit_vpor(tmp, XMMOrMemory::XMM(dst), tmp);
+            }
+            // Clear the payload of NaNs.
+            shift(&mut this.assembler, dst, payload_shift, dst);
+            this.assembler.emit_vpandn(dst, XMMOrMemory::XMM(tmp), dst);
+            this.machine.release_temp_simd(tmp);
+        });
+    }
+
+    /// `f32x4.abs`, `f32x4.neg`, `f64x2.abs` and `f64x2.neg` on the sign bits.
+    fn emit_simd_fp_sign(&mut self, sz: Size, is_neg: bool) {
+        self.emit_simd_unop(|this, src, dst| {
+            let mask = this.machine.acquire_temp_simd().unwrap();
+            this.assembler
+                .emit_vpcmpeqd(mask, XMMOrMemory::XMM(mask), mask);
+            match (sz, is_neg) {
+                (Size::S32, false) => this.assembler.emit_vpsrld_imm8(mask, 1, mask),
+                (Size::S32, true) => this.assembler.emit_vpslld_imm8(mask, 31, mask),
+                (Size::S64, false) => this.assembler.emit_vpsrlq_imm8(mask, 1, mask),
+                (Size::S64, true) => this.assembler.emit_vpsllq_imm8(mask, 63, mask),
+                _ => unreachable!(),
+            }
+            if is_neg {
+                this.assembler.emit_vpxor(src, XMMOrMemory::XMM(mask), dst);
+            } else {
+                this.assembler.emit_vpand(src, XMMOrMemory::XMM(mask), dst);
+            }
+            this.machine.release_temp_simd(mask);
+        });
+    }
+
+    /// Integer `neg`, computed as `0 - x` with `sub`.
+    fn emit_simd_int_neg(&mut self, sub: fn(&mut Assembler, XMM, XMMOrMemory, XMM)) {
+        self.emit_simd_unop(|this, src, dst| {
+            this.assembler.emit_vpxor(dst, XMMOrMemory::XMM(dst), dst);
+            sub(&mut this.assembler, dst, XMMOrMemory::XMM(src), dst);
+        });
+    }
+
+    /// Returns the parameter and result types of a block.
+    fn block_signature(
+        &self,
+        ty: WpTypeOrFuncType,
+    ) -> (SmallVec<[WpType; 8]>, SmallVec<[WpType; 1]>) {
+        match ty {
+            WpTypeOrFuncType::Type(WpType::EmptyBlockType) => (smallvec![], smallvec![]),
+            WpTypeOrFuncType::Type(inner_ty) => (smallvec![], smallvec![inner_ty]),
+            WpTypeOrFuncType::FuncType(sig_index) => {
+                let sig = &self.module.signatures[SignatureIndex::new(sig_index as usize)];
+                (
+                    sig.params().iter().cloned().map(type_to_wp_type).collect(),
+                    sig.results().iter().cloned().map(type_to_wp_type).collect(),
+                )
+            }
+        }
+    }
+
+    /// Returns whether the float value at `depth` in the value stack still needs to be
+    /// canonicalized.
+    fn needs_canonicalization(&self, depth: usize) -> Option<Size> {
+        if !self.assembler.arch_supports_canonicalize_nan()
+            || !self.config.enable_nan_canonicalization
+        {
+            return None;
+        }
+        self.fp_stack
+            .iter()
+            .rev()
+            .find(|fp| fp.depth == depth)
+            .and_then(|fp| fp.canonicalization)
+            .map(|c| c.to_size())
+    }
+
+    /// Returns the number of entries of the FP stack below value stack depth `depth`.
+    fn fp_stack_depth_at(&self, depth: usize) -> usize {
+        self.fp_stack
+            .iter()
+            .take_while(|fp| fp.depth < depth)
+            .count()
+    }
+
+    /// Pushes a copy of the block parameters on top of the value stack. The parameters
+    /// themselves stay below the frame, which works on the copy.
+    fn emit_param_copies(&mut self, params: &[WpType]) {
+        let first = self.value_stack.len() - params.len();
+        for (i, &ty) in params.iter().enumerate() {
+            let src = self.value_stack[first + i];
+            let canonicalization = self
+                .fp_stack
+                .iter()
+                .rev()
+                .find(|fp| fp.depth == first + i)
+                .map(|fp| fp.canonicalization);
+            let dst = self.machine.acquire_locations(
+                &mut self.assembler,
+                &[(ty, MachineValue::WasmStack(self.value_stack.len()))],
+                false,
+            )[0];
+            if ty == WpType::V128 {
+                self.emit_v128_mov(src, dst);
+            } else {
+                self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, src, dst);
+            }
+            self.value_stack.push(dst);
+            if let Some(canonicalization) = canonicalization {
+                self.fp_stack.push(FloatValue {
+                    canonicalization,
+                    depth: self.value_stack.len() - 1,
+                });
+            }
+        }
+    }
+
+    /// Moves the results of a block from the top of the value stack to where the end of
+    /// the block expects them.
+    ///
+    /// A single result goes in RAX, or XMM0 for `v128`. Multiple results go in the value
+    /// area, or in the caller's return area when leaving the function body.
+    fn emit_block_results(
+        &mut self,
+        returns: &[WpType],
+        is_function_body: bool,
+    ) -> Result<(), CodegenError> {
+        match returns.len() {
+            0 => {}
+            1 => {
+                let loc = *self.value_stack.last().unwrap();
+                if returns[0] == WpType::V128 {
+                    self.assembler
+                        .emit_vmovdqu(v128_operand(loc), XMMOrMemory::XMM(XMM::XMM0));
+                } else if returns[0].is_float() {
+                    let fp = self.fp_stack.peek1()?;
+                    if self.assembler.arch_supports_canonicalize_nan()
+                        && self.config.enable_nan_canonicalization
+                        && fp.canonicalization.is_some()
+                    {
+                        self.canonicalize_nan(
+                            match returns[0] {
+                                WpType::F32 => Size::S32,
+                                WpType::F64 => Size::S64,
+                                _ => unreachable!(),
+                            },
+                            loc,
+                            Location::GPR(GPR::RAX),
+                        );
+                    } else {
+                        self.emit_relaxed_binop(
+                            Assembler::emit_mov,
+                            Size::S64,
+                            loc,
+                            Location::GPR(GPR::RAX),
+                        );
+                    }
+                } else {
+                    self.emit_relaxed_binop(
+                        Assembler::emit_mov,
+                        Size::S64,
+                        loc,
+                        Location::GPR(GPR::RAX),
+                    );
+                }
+            }
+            _ => {
+                let tmp = self.machine.acquire_temp_gpr().unwrap();
+                let (base, offset) = if is_function_body {
+                    self.assembler.emit_mov(
+                        Size::S64,
+                        self.machine.get_return_area_ptr(),
+                        Location::GPR(tmp),
+                    );
+                    (tmp, 0)
+                } else {
+                    match self.machine.get_value_area() {
+                        Location::Memory(base, offset) => (base, offset),
+                        _ => unreachable!(),
+                    }
+                };
+                let first = self.value_stack.len() - returns.len();
+                for (i, &ty) in returns.iter().enumerate() {
+                    let loc = self.value_stack[first + i];
+                    let dst = Location::Memory(base, offset + (i * 16) as i32);
+                    if ty == WpType::V128 {
+                        self.emit_v128_mov(loc, dst);
+                    } else if let Some(size) = self.needs_canonicalization(first + i) {
+                        self.canonicalize_nan(size, loc, dst);
+                    } else {
+                        self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, loc, dst);
+                    }
+                }
+                self.machine.release_temp_gpr(tmp);
+            }
+        }
+        Ok(())
+    }
+
+    /// Pushes the results of a block onto the value stack, from where
+    /// `emit_block_results` left them.
+    fn emit_load_block_results(&mut self, returns: &[WpType]) {
+        if returns.len() == 1 {
+            if returns[0] == WpType::V128 {
+                let loc = self.acquire_v128_location();
+                self.assembler
+                    .emit_vmovdqu(XMMOrMemory::XMM(XMM::XMM0), v128_operand(loc));
+            } else {
+                let loc = self.machine.acquire_locations(
+                    &mut self.assembler,
+                    &[(returns[0], MachineValue::WasmStack(self.value_stack.len()))],
+                    false,
+                )[0];
+                self.assembler
+                    .emit_mov(Size::S64, Location::GPR(GPR::RAX), loc);
+                self.value_stack.push(loc);
+            }
+            if returns[0].is_float() {
+                self.fp_stack
+                    .push(FloatValue::new(self.value_stack.len() - 1));
+                // we already canonicalized at the `Br*` instruction or here previously.
+            }
+            return;
+        }
+        self.emit_load_multi_value(returns);
+    }
+
+    /// Pushes the values held in the value area onto the value stack.
+    fn emit_load_multi_value(&mut self, types: &[WpType]) {
+        let (base, offset) = match self.machine.get_value_area() {
+            Location::Memory(base, offset) => (base, offset),
+            _ => unreachable!(),
+        };
+        let depth = self.value_stack.len();
+        let locs = self.machine.acquire_locations(
+            &mut self.assembler,
+            &types
+                .iter()
+                .enumerate()
+                .map(|(i, &ty)| (ty, MachineValue::WasmStack(depth + i)))
+                .collect::<SmallVec<[_; 8]>>(),
+            false,
+        );
+        for (i, (&ty, loc)) in types.iter().zip(locs).enumerate() {
+            let src = Location::Memory(base, offset + (i * 16) as i32);
+            if ty == WpType::V128 {
+                self.emit_v128_mov(src, loc);
+            } else {
+                self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, src, loc);
+            }
+            self.value_stack.push(loc);
+            if ty.is_float() {
+                self.fp_stack
+                    .push(FloatValue::new(self.value_stack.len() - 1));
+            }
+        }
+    }
+
+    /// Moves the arguments of a branch to a loop with parameters into the locations the
+    /// parameters had when entering the loop, and restores the stack pointer of the loop
+    /// header.
+    ///
+    /// The arguments may overlap with the parameter locations in any order, so they are
+    /// shuffled through the native stack.
+    fn emit_loop_args(&mut self, frame_index: usize) {
+        let frame = &self.control_stack[frame_index];
+        let types = frame.params.clone();
+        let dsts = frame.loop_param_locations.clone();
+        let header_offset = frame.loop_stack_offset;
+        let current_offset = self.machine.get_stack_offset();
+        if current_offset < header_offset {
+            self.assembler.emit_sub(
+                Size::S64,
+                Location::Imm32((header_offset - current_offset) as u32),
+                Location::GPR(GPR::RSP),
+            );
+        }
+
+        let tmp = self.machine.acquire_temp_gpr().unwrap();
+        let first = self.value_stack.len() - types.len();
+        for (i, &ty) in types.iter().enumerate() {
+            let src = self.value_stack[first + i];
+            if let Some(size) = self.needs_canonicalization(first + i) {
+                self.canonicalize_nan(size, src, src);
+            }
+            match (ty, src) {
+                (WpType::V128, Location::Memory(base, offset)) => {
+                    self.assembler
+                        .emit_push(Size::S64, Location::Memory(base, offset + 8));
+                    self.assembler
+                        .emit_push(Size::S64, Location::Memory(base, offset));
+                }
+                (_, Location::SIMD(_)) | (_, Location::Imm64(_)) => {
+                    self.assembler.emit_mov(Size::S64, src, Location::GPR(tmp));
+                    self.assembler.emit_push(Size::S64, Location::GPR(tmp));
+                }
+                _ => self.assembler.emit_push(Size::S64, src),
+            }
+        }
+        for (&ty, &dst) in types.iter().zip(dsts.iter()).rev() {
+            match (ty, dst) {
+                (WpType::V128, Location::Memory(base, offset)) => {
+                    self.assembler
+                        .emit_pop(Size::S64, Location::Memory(base, offset));
+                    self.assembler
+                        .emit_pop(Size::S64, Location::Memory(base, offset + 8));
+                }
+                (_, Location::SIMD(_)) => {
+                    self.assembler.emit_pop(Size::S64, Location::GPR(tmp));
+                    self.assembler.emit_mov(Size::S64, Location::GPR(tmp), dst);
+                }
+                _ => self.assembler.emit_pop(Size::S64, dst),
+            }
+        }
+        self.machine.release_temp_gpr(tmp);
+
+        if current_offset > header_offset {
+            self.assembler.emit_add(
+                Size::S64,
+                Location::Imm32((current_offset - header_offset) as u32),
+                Location::GPR(GPR::RSP),
+            );
+        }
     }
 
-    /// Floating point (AVX) comparison with both operands popped from the virtual stack.
-    fn emit_fp_cmpop_avx(
-        &mut self,
-        f: fn(&mut Assembler, XMM, XMMOrMemory, XMM),
-    ) -> Result<(), CodegenError> {
-        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
-
-        self.emit_relaxed_avx(f, loc_a, loc_b, ret)?;
-
-        // Workaround for behavior inconsistency among different backing implementations.
-        // (all bits or only the least significant bit are set to one?)
-        self.assembler.emit_and(Size::S32, Location::Imm32(1), ret);
+    /// Emits a branch to the frame at `frame_index` in the control stack, with the values
+    /// on top of the value stack as its arguments.
+    fn emit_branch(&mut self, frame_index: usize) -> Result<(), CodegenError> {
+        let frame = &self.control_stack[frame_index];
+        let label = frame.label;
+        if frame.loop_like {
+            if !frame.params.is_empty() {
+                self.emit_loop_args(frame_index);
+                self.assembler.emit_jmp(Condition::None, label);
+                return Ok(());
+            }
+        } else {
+            let returns = frame.returns.clone();
+            self.emit_block_results(&returns, frame_index == 0)?;
+        }
+        let frame = &self.control_stack[frame_index];
+        let released = &self.value_stack[frame.value_stack_depth..];
+        self.machine
+            .release_locations_keep_state(&mut self.assembler, released);
+        self.assembler.emit_jmp(Condition::None, label);
         Ok(())
     }
 
-    /// Floating point (AVX) unop with both operands popped from the virtual stack.
-    fn emit_fp_unop_avx(
-        &mut self,
-        f: fn(&mut Assembler, XMM, XMMOrMemory, XMM),
-    ) -> Result<(), CodegenError> {
-        let loc = self.pop_value_released();
-        let ret = self.machine.acquire_locations(
-            &mut self.assembler,
-            &[(WpType::F64, MachineValue::WasmStack(self.value_stack.len()))],
-            false,
-        )[0];
-        self.value_stack.push(ret);
-        self.emit_relaxed_avx(f, loc, loc, ret)?;
-        Ok(())
+    /// Expands every `v128` parameter into its two 8-byte halves, low half first, which
+    /// is how they are passed in calls.
+    fn split_v128_params(&self, params: &[Location]) -> SmallVec<[Location; 8]> {
+        let mut ret = SmallVec::new();
+        for &loc in params {
+            match loc {
+                Location::Memory(base, offset) if self.machine.is_v128_stack_slot(loc) => {
+                    ret.push(Location::Memory(base, offset));
+                    ret.push(Location::Memory(base, offset + 8));
+                }
+                _ => ret.push(loc),
+            }
+        }
+        ret
     }
 
     /// Emits a System V call sequence.
@@ -990,7 +1974,7 @@ impl<'a> FuncGen<'a> {
         }
 
         // Save used XMM registers.
-        let used_xmms = self.machine.get_used_xmms();
+        let used_xmms = self.machine.get_used_simd();
         if used_xmms.len() > 0 {
             self.assembler.emit_sub(
                 Size::S64,
@@ -1001,7 +1985,7 @@ impl<'a> FuncGen<'a> {
             for (i, r) in used_xmms.iter().enumerate() {
                 self.assembler.emit_mov(
                     Size::S64,
-                    Location::XMM(*r),
+                    Location::SIMD(*r),
                     Location::Memory(GPR::RSP, (i * 8) as i32),
                 );
             }
@@ -1064,7 +2048,7 @@ impl<'a> FuncGen<'a> {
                             //assert!(content != MachineValue::Undefined);
                             self.machine.state.stack_values.push(content);
                         }
-                        Location::XMM(x) => {
+                        Location::SIMD(x) => {
                             let content = self.machine.state.register_values
                                 [X64Register::XMM(x).to_index().0]
                                 .clone();
@@ -1109,7 +2093,7 @@ impl<'a> FuncGen<'a> {
                             );
                             self.machine.release_temp_gpr(GPR::RCX);
                         }
-                        Location::XMM(_) => {
+                        Location::SIMD(_) => {
                             // Dummy value slot to be filled with `mov`.
                             self.assembler.emit_push(Size::S64, Location::GPR(GPR::RAX));
 
@@ -1198,7 +2182,7 @@ impl<'a> FuncGen<'a> {
                 self.assembler.emit_mov(
                     Size::S64,
                     Location::Memory(GPR::RSP, (i * 8) as i32),
-                    Location::XMM(*r),
+                    Location::SIMD(*r),
                 );
             }
             self.assembler.emit_add(
@@ -1235,6 +2219,88 @@ impl<'a> FuncGen<'a> {
         Ok(())
     }
 
+    /// Emits a call to a runtime builtin function.
+    ///
+    /// The builtin is called with the vmctx, the `immediates` and the `n_operands` values
+    /// popped from the value stack, in that order.
+    fn emit_builtin_call(
+        &mut self,
+        builtin: VMBuiltinFunctionIndex,
+        immediates: &[u32],
+        n_operands: usize,
+    ) -> Result<(), CodegenError> {
+        let operands: SmallVec<[Location; 4]> = self
+            .value_stack
+            .drain(self.value_stack.len() - n_operands..)
+            .collect();
+        self.machine.release_locations_only_regs(&operands);
+
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(
+                Machine::get_vmctx_reg(),
+                self.vmoffsets.vmctx_builtin_function(builtin) as i32,
+            ),
+            Location::GPR(GPR::RAX),
+        );
+
+        self.machine
+            .release_locations_only_osr_state(operands.len());
+
+        self.emit_call_sysv(
+            |this| {
+                let label = this.assembler.get_label();
+                let after = this.assembler.get_label();
+                this.assembler.emit_jmp(Condition::None, after);
+                this.assembler.emit_label(label);
+                this.assembler.emit_host_redirection(GPR::RAX);
+                this.assembler.emit_label(after);
+                this.assembler.emit_call_label(label);
+            },
+            immediates
+                .iter()
+                .map(|&x| Location::Imm32(x))
+                .chain(operands.iter().cloned()),
+        )?;
+
+        self.machine
+            .release_locations_only_stack(&mut self.assembler, &operands);
+        Ok(())
+    }
+
+    /// Zero-extends the i32 value `depth` slots from the top of the value
+    /// stack to 64 bits, in place.
+    fn zero_extend_operand(&mut self, depth: usize) {
+        let index = self.value_stack.len() - depth;
+        match self.value_stack[index] {
+            // A 32-bit immediate would be sign-extended.
+            Location::Imm32(x) => self.value_stack[index] = Location::Imm64(x.into()),
+            // Writing to a 32-bit register clears its upper half.
+            Location::GPR(x) => {
+                self.assembler
+                    .emit_mov(Size::S32, Location::GPR(x), Location::GPR(x))
+            }
+            loc => {
+                let tmp = self.machine.acquire_temp_gpr().unwrap();
+                self.assembler.emit_mov(Size::S32, loc, Location::GPR(tmp));
+                self.assembler.emit_mov(Size::S64, Location::GPR(tmp), loc);
+                self.machine.release_temp_gpr(tmp);
+            }
+        }
+    }
+
+    /// Pushes the value returned in `RAX` by a runtime builtin function.
+    fn push_builtin_result(&mut self, ty: WpType) {
+        let ret = self.machine.acquire_locations(
+            &mut self.assembler,
+            &[(ty, MachineValue::WasmStack(self.value_stack.len()))],
+            false,
+        )[0];
+        self.value_stack.push(ret);
+        self.assembler
+            .emit_mov(Size::S64, Location::GPR(GPR::RAX), ret);
+    }
+
     /// Emits a memory operation.
     fn emit_memory_op<F: FnOnce(&mut Self, GPR) -> Result<(), CodegenError>>(
         &mut self,
@@ -1250,9 +2316,11 @@ impl<'a> FuncGen<'a> {
         };
         let tmp_addr = self.machine.acquire_temp_gpr().unwrap();
 
-        // Reusing `tmp_addr` for temporary indirection here, since it's not used before the last reference to `{base,bound}_loc`.
-        let (base_loc, bound_loc) = if self.module.num_imported_memories != 0 {
+        // `emit_memory_op` callers may hold a temporary register of their own, so
+        // the memory definition is only loaded into a register when it's imported.
+        let (base_loc, bound_loc, tmp_definition) = if self.module.num_imported_memories != 0 {
             // Imported memories require one level of indirection.
+            let tmp_definition = self.machine.acquire_temp_gpr().unwrap();
             let offset = self
                 .vmoffsets
                 .vmctx_vmmemory_import_definition(MemoryIndex::new(0));
@@ -1260,9 +2328,13 @@ impl<'a> FuncGen<'a> {
                 Assembler::emit_mov,
                 Size::S64,
                 Location::Memory(Machine::get_vmctx_reg(), offset as i32),
-                Location::GPR(tmp_addr),
+                Location::GPR(tmp_definition),
             );
-            (Location::Memory(tmp_addr, 0), Location::Memory(tmp_addr, 8))
+            (
+                Location::Memory(tmp_definition, 0),
+                Location::Memory(tmp_definition, 8),
+                Some(tmp_definition),
+            )
         } else {
             let offset = self
                 .vmoffsets
@@ -1270,39 +2342,11 @@ impl<'a> FuncGen<'a> {
             (
                 Location::Memory(Machine::get_vmctx_reg(), offset as i32),
                 Location::Memory(Machine::get_vmctx_reg(), (offset + 8) as i32),
+                None,
             )
         };
 
-        let tmp_base = self.machine.acquire_temp_gpr().unwrap();
-        let tmp_bound = self.machine.acquire_temp_gpr().unwrap();
-
-        // Load base into temporary register.
-        self.assembler
-            .emit_mov(Size::S64, base_loc, Location::GPR(tmp_base));
-
-        // Load bound into temporary register, if needed.
-        if need_check {
-            self.assembler
-                .emit_mov(Size::S32, bound_loc, Location::GPR(tmp_bound));
-
-            // Wasm -> Effective.
-            // Assuming we never underflow - should always be true on Linux/macOS and Windows >=8,
-            // since the first page from 0x0 to 0x1000 is not accepted by mmap.
-
-            // This `lea` calculates the upper bound allowed for the beginning of the word.
-            // Since the upper bound of the memory is (exclusively) `tmp_bound + tmp_base`,
-            // the maximum allowed beginning of word is (inclusively)
-            // `tmp_bound + tmp_base - value_size`.
-            self.assembler.emit_lea(
-                Size::S64,
-                Location::MemoryAddTriple(tmp_bound, tmp_base, -(value_size as i32)),
-                Location::GPR(tmp_bound),
-            );
-        }
-
         // Load effective address.
-        // `base_loc` and `bound_loc` becomes INVALID after this line, because `tmp_addr`
-        // might be reused.
         self.assembler
             .emit_mov(Size::S32, addr, Location::GPR(tmp_addr));
 
@@ -1319,25 +2363,36 @@ impl<'a> FuncGen<'a> {
                 .emit_jmp(Condition::Carry, self.special_labels.heap_access_oob);
         }
 
-        // Wasm linear memory -> real memory
-        self.assembler
-            .emit_add(Size::S64, Location::GPR(tmp_base), Location::GPR(tmp_addr));
-
         if need_check {
-            // Trap if the end address of the requested area is above that of the linear memory.
+            // Trap if the end address of the requested area is above that of the
+            // linear memory. The effective address fits in 33 bits, so adding
+            // `value_size` can't overflow.
+            self.assembler.emit_lea(
+                Size::S64,
+                Location::Memory(tmp_addr, value_size as i32),
+                Location::GPR(tmp_addr),
+            );
             self.assembler
-                .emit_cmp(Size::S64, Location::GPR(tmp_bound), Location::GPR(tmp_addr));
-
-            // `tmp_bound` is inclusive. So trap only if `tmp_addr > tmp_bound`.
+                .emit_cmp(Size::S64, bound_loc, Location::GPR(tmp_addr));
             self.assembler
                 .emit_jmp(Condition::Above, self.special_labels.heap_access_oob);
+            self.assembler.emit_lea(
+                Size::S64,
+                Location::Memory(tmp_addr, -(value_size as i32)),
+                Location::GPR(tmp_addr),
+            );
         }
 
-        self.machine.release_temp_gpr(tmp_bound);
-        self.machine.release_temp_gpr(tmp_base);
+        // Wasm linear memory -> real memory
+        self.assembler
+            .emit_add(Size::S64, base_loc, Location::GPR(tmp_addr));
+
+        if let Some(tmp_definition) = tmp_definition {
+            self.machine.release_temp_gpr(tmp_definition);
+        }
 
-        let align = memarg.align;
-        if check_alignment && align != 1 {
+        // Atomic accesses must be naturally aligned, whatever `memarg.align` says.
+        if check_alignment && value_size != 1 {
             let tmp_aligncheck = self.machine.acquire_temp_gpr().unwrap();
             self.assembler.emit_mov(
                 Size::S32,
@@ -1346,11 +2401,11 @@ impl<'a> FuncGen<'a> {
             );
             self.assembler.emit_and(
                 Size::S64,
-                Location::Imm32((align - 1).into()),
+                Location::Imm32((value_size - 1) as u32),
                 Location::GPR(tmp_aligncheck),
             );
             self.assembler
-                .emit_jmp(Condition::NotEqual, self.special_labels.heap_access_oob);
+                .emit_jmp(Condition::NotEqual, self.special_labels.unaligned_atomic);
             self.machine.release_temp_gpr(tmp_aligncheck);
         }
 
@@ -1432,17 +2487,17 @@ impl<'a> FuncGen<'a> {
         let upper_bound = f32::to_bits(upper_bound);
 
         let tmp = self.machine.acquire_temp_gpr().unwrap();
-        let tmp_x = self.machine.acquire_temp_xmm().unwrap();
+        let tmp_x = self.machine.acquire_temp_simd().unwrap();
 
         // Underflow.
         self.assembler
             .emit_mov(Size::S32, Location::Imm32(lower_bound), Location::GPR(tmp));
         self.assembler
-            .emit_mov(Size::S32, Location::GPR(tmp), Location::XMM(tmp_x));
+            .emit_mov(Size::S32, Location::GPR(tmp), Location::SIMD(tmp_x));
         self.assembler
             .emit_vcmpless(reg, XMMOrMemory::XMM(tmp_x), tmp_x);
         self.assembler
-            .emit_mov(Size::S32, Location::XMM(tmp_x), Location::GPR(tmp));
+            .emit_mov(Size::S32, Location::SIMD(tmp_x), Location::GPR(tmp));
         self.assembler
             .emit_cmp(Size::S32, Location::Imm32(0), Location::GPR(tmp));
         self.assembler
@@ -1452,11 +2507,11 @@ impl<'a> FuncGen<'a> {
         self.assembler
             .emit_mov(Size::S32, Location::Imm32(upper_bound), Location::GPR(tmp));
         self.assembler
-            .emit_mov(Size::S32, Location::GPR(tmp), Location::XMM(tmp_x));
+            .emit_mov(Size::S32, Location::GPR(tmp), Location::SIMD(tmp_x));
         self.assembler
             .emit_vcmpgess(reg, XMMOrMemory::XMM(tmp_x), tmp_x);
         self.assembler
-            .emit_mov(Size::S32, Location::XMM(tmp_x), Location::GPR(tmp));
+            .emit_mov(Size::S32, Location::SIMD(tmp_x), Location::GPR(tmp));
         self.assembler
             .emit_cmp(Size::S32, Location::Imm32(0), Location::GPR(tmp));
         self.assembler.emit_jmp(Condition::NotEqual, overflow_label);
@@ -1465,14 +2520,14 @@ impl<'a> FuncGen<'a> {
         self.assembler
             .emit_vcmpeqss(reg, XMMOrMemory::XMM(reg), tmp_x);
         self.assembler
-            .emit_mov(Size::S32, Location::XMM(tmp_x), Location::GPR(tmp));
+            .emit_mov(Size::S32, Location::SIMD(tmp_x), Location::GPR(tmp));
         self.assembler
             .emit_cmp(Size::S32, Location::Imm32(0), Location::GPR(tmp));
         self.assembler.emit_jmp(Condition::Equal, nan_label);
 
         self.assembler.emit_jmp(Condition::None, succeed_label);
 
-        self.machine.release_temp_xmm(tmp_x);
+        self.machine.release_temp_simd(tmp_x);
         self.machine.release_temp_gpr(tmp);
     }
 
@@ -1585,17 +2640,17 @@ impl<'a> FuncGen<'a> {
         let upper_bound = f64::to_bits(upper_bound);
 
         let tmp = self.machine.acquire_temp_gpr().unwrap();
-        let tmp_x = self.machine.acquire_temp_xmm().unwrap();
+        let tmp_x = self.machine.acquire_temp_simd().unwrap();
 
         // Underflow.
         self.assembler
             .emit_mov(Size::S64, Location::Imm64(lower_bound), Location::GPR(tmp));
         self.assembler
-            .emit_mov(Size::S64, Location::GPR(tmp), Location::XMM(tmp_x));
+            .emit_mov(Size::S64, Location::GPR(tmp), Location::SIMD(tmp_x));
         self.assembler
             .emit_vcmplesd(reg, XMMOrMemory::XMM(tmp_x), tmp_x);
         self.assembler
-            .emit_mov(Size::S32, Location::XMM(tmp_x), Location::GPR(tmp));
+            .emit_mov(Size::S32, Location::SIMD(tmp_x), Location::GPR(tmp));
         self.assembler
             .emit_cmp(Size::S32, Location::Imm32(0), Location::GPR(tmp));
         self.assembler
@@ -1605,11 +2660,11 @@ impl<'a> FuncGen<'a> {
         self.assembler
             .emit_mov(Size::S64, Location::Imm64(upper_bound), Location::GPR(tmp));
         self.assembler
-            .emit_mov(Size::S64, Location::GPR(tmp), Location::XMM(tmp_x));
+            .emit_mov(Size::S64, Location::GPR(tmp), Location::SIMD(tmp_x));
         self.assembler
             .emit_vcmpgesd(reg, XMMOrMemory::XMM(tmp_x), tmp_x);
         self.assembler
-            .emit_mov(Size::S32, Location::XMM(tmp_x), Location::GPR(tmp));
+            .emit_mov(Size::S32, Location::SIMD(tmp_x), Location::GPR(tmp));
         self.assembler
             .emit_cmp(Size::S32, Location::Imm32(0), Location::GPR(tmp));
         self.assembler.emit_jmp(Condition::NotEqual, overflow_label);
@@ -1618,14 +2673,14 @@ impl<'a> FuncGen<'a> {
         self.assembler
             .emit_vcmpeqsd(reg, XMMOrMemory::XMM(reg), tmp_x);
         self.assembler
-            .emit_mov(Size::S32, Location::XMM(tmp_x), Location::GPR(tmp));
+            .emit_mov(Size::S32, Location::SIMD(tmp_x), Location::GPR(tmp));
         self.assembler
             .emit_cmp(Size::S32, Location::Imm32(0), Location::GPR(tmp));
         self.assembler.emit_jmp(Condition::Equal, nan_label);
 
         self.assembler.emit_jmp(Condition::None, succeed_label);
 
-        self.machine.release_temp_xmm(tmp_x);
+        self.machine.release_temp_simd(tmp_x);
         self.machine.release_temp_gpr(tmp);
     }
 
@@ -1747,15 +2802,16 @@ impl<'a> FuncGen<'a> {
         // Initialize locals.
         self.locals = self.machine.init_locals(
             &mut self.assembler,
-            self.local_types.len(),
+            &self.local_types,
             self.signature.params().len(),
+            self.signature.results().len() > 1,
+            self.value_area_slots,
         );
 
         // Mark vmctx register. The actual loading of the vmctx value is handled by init_local.
         self.machine.state.register_values
             [X64Register::GPR(Machine::get_vmctx_reg()).to_index().0] = MachineValue::Vmctx;
 
-        // TODO: Explicit stack check is not supported for now.
         let diff = self.machine.state.diff(&new_machine_state());
         let state_diff_id = self.fsm.diffs.len();
         self.fsm.diffs.push(diff);
@@ -1763,23 +2819,31 @@ impl<'a> FuncGen<'a> {
         self.assembler
             .emit_sub(Size::S64, Location::Imm32(32), Location::GPR(GPR::RSP)); // simulate "red zone" if not supported by the platform
 
+        // The frame of the function, with its locals, is allocated at this point.
+        if self.config.enable_stack_check {
+            self.emit_stack_check();
+        }
+
         self.control_stack.push(ControlFrame {
             label: self.assembler.get_label(),
             loop_like: false,
             if_else: IfElseState::None,
+            params: smallvec![],
             returns: self
                 .signature
                 .results()
                 .iter()
                 .map(|&x| type_to_wp_type(x))
                 .collect(),
+            loop_param_locations: smallvec![],
+            loop_stack_offset: 0,
             value_stack_depth: 0,
             fp_stack_depth: 0,
             state: self.machine.state.clone(),
             state_diff_id,
         });
 
-        // TODO: Full preemption by explicit signal checking
+        self.emit_interrupt_check();
 
         // We insert set StackOverflow as the default trap that can happen
         // anywhere in the function prologue.
@@ -1827,6 +2891,14 @@ impl<'a> FuncGen<'a> {
             .collect();
         local_types.extend_from_slice(&local_types_excluding_arguments);
 
+        let value_area_slots = module
+            .signatures
+            .values()
+            .map(|sig| sig.results().len())
+            .filter(|&n| n > 1)
+            .max()
+            .unwrap_or(0);
+
         let fsm = FunctionStateMap::new(
             new_machine_state(),
             local_func_index.index() as usize,
@@ -1840,9 +2912,12 @@ impl<'a> FuncGen<'a> {
         let special_labels = SpecialLabelSet {
             integer_division_by_zero: assembler.get_label(),
             heap_access_oob: assembler.get_label(),
+            unaligned_atomic: assembler.get_label(),
             table_access_oob: assembler.get_label(),
             indirect_call_null: assembler.get_label(),
             bad_signature: assembler.get_label(),
+            interrupt: assembler.get_label(),
+            stack_overflow: assembler.get_label(),
         };
 
         let mut fg = FuncGen {
@@ -1856,6 +2931,7 @@ impl<'a> FuncGen<'a> {
             locals: vec![], // initialization deferred to emit_head
             local_types,
             value_stack: vec![],
+            value_area_slots,
             fp_stack: vec![],
             control_stack: vec![],
             machine: Machine::new(),
@@ -1954,7 +3030,11 @@ impl<'a> FuncGen<'a> {
                     Location::Memory(tmp, 0)
                 };
 
-                self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, src, loc);
+                if ty == WpType::V128 {
+                    self.emit_v128_mov(src, loc);
+                } else {
+                    self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, src, loc);
+                }
 
                 self.machine.release_temp_gpr(tmp);
             }
@@ -1987,7 +3067,9 @@ impl<'a> FuncGen<'a> {
                 };
                 let ty = type_to_wp_type(self.module.globals[global_index].ty);
                 let loc = self.pop_value_released();
-                if ty.is_float() {
+                if ty == WpType::V128 {
+                    self.emit_v128_mov(loc, dst);
+                } else if ty.is_float() {
                     let fp = self.fp_stack.pop1()?;
                     if self.assembler.arch_supports_canonicalize_nan()
                         && self.config.enable_nan_canonicalization
@@ -2012,28 +3094,35 @@ impl<'a> FuncGen<'a> {
             }
             Operator::LocalGet { local_index } => {
                 let local_index = local_index as usize;
-                let ret = self.machine.acquire_locations(
-                    &mut self.assembler,
-                    &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
-                    false,
-                )[0];
-                self.emit_relaxed_binop(
-                    Assembler::emit_mov,
-                    Size::S64,
-                    self.locals[local_index],
-                    ret,
-                );
-                self.value_stack.push(ret);
-                if self.local_types[local_index].is_float() {
-                    self.fp_stack
-                        .push(FloatValue::new(self.value_stack.len() - 1));
+                if self.local_types[local_index] == WpType::V128 {
+                    let ret = self.acquire_v128_location();
+                    self.emit_v128_mov(self.locals[local_index], ret);
+                } else {
+                    let ret = self.machine.acquire_locations(
+                        &mut self.assembler,
+                        &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
+                        false,
+                    )[0];
+                    self.emit_relaxed_binop(
+                        Assembler::emit_mov,
+                        Size::S64,
+                        self.locals[local_index],
+                        ret,
+                    );
+                    self.value_stack.push(ret);
+                    if self.local_types[local_index].is_float() {
+                        self.fp_stack
+                            .push(FloatValue::new(self.value_stack.len() - 1));
+                    }
                 }
             }
             Operator::LocalSet { local_index } => {
                 let local_index = local_index as usize;
                 let loc = self.pop_value_released();
 
-                if self.local_types[local_index].is_float() {
+                if self.local_types[local_index] == WpType::V128 {
+                    self.emit_v128_mov(loc, self.locals[local_index]);
+                } else if self.local_types[local_index].is_float() {
                     let fp = self.fp_stack.pop1()?;
                     if self.assembler.arch_supports_canonicalize_nan()
                         && self.config.enable_nan_canonicalization
@@ -2069,7 +3158,9 @@ impl<'a> FuncGen<'a> {
                 let local_index = local_index as usize;
                 let loc = *self.value_stack.last().unwrap();
 
-                if self.local_types[local_index].is_float() {
+                if self.local_types[local_index] == WpType::V128 {
+                    self.emit_v128_mov(loc, self.locals[local_index]);
+                } else if self.local_types[local_index].is_float() {
                     let fp = self.fp_stack.peek1()?;
                     if self.assembler.arch_supports_canonicalize_nan()
                         && self.config.enable_nan_canonicalization
@@ -2717,16 +3808,16 @@ impl<'a> FuncGen<'a> {
                 } else {
                     let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64);
 
-                    let tmp1 = self.machine.acquire_temp_xmm().unwrap();
-                    let tmp2 = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp1 = self.machine.acquire_temp_simd().unwrap();
+                    let tmp2 = self.machine.acquire_temp_simd().unwrap();
                     let tmpg1 = self.machine.acquire_temp_gpr().unwrap();
                     let tmpg2 = self.machine.acquire_temp_gpr().unwrap();
 
                     let src1 = match loc_a {
-                        Location::XMM(x) => x,
+                        Location::SIMD(x) => x,
                         Location::GPR(_) | Location::Memory(_, _) => {
                             self.assembler
-                                .emit_mov(Size::S64, loc_a, Location::XMM(tmp1));
+                                .emit_mov(Size::S64, loc_a, Location::SIMD(tmp1));
                             tmp1
                         }
                         Location::Imm32(_) => {
@@ -2735,7 +3826,7 @@ impl<'a> FuncGen<'a> {
                             self.assembler.emit_mov(
                                 Size::S32,
                                 Location::GPR(tmpg1),
-                                Location::XMM(tmp1),
+                                Location::SIMD(tmp1),
                             );
                             tmp1
                         }
@@ -2745,7 +3836,7 @@ impl<'a> FuncGen<'a> {
                             self.assembler.emit_mov(
                                 Size::S64,
                                 Location::GPR(tmpg1),
-                                Location::XMM(tmp1),
+                                Location::SIMD(tmp1),
                             );
                             tmp1
                         }
@@ -2756,10 +3847,10 @@ impl<'a> FuncGen<'a> {
                         }
                     };
                     let src2 = match loc_b {
-                        Location::XMM(x) => x,
+                        Location::SIMD(x) => x,
                         Location::GPR(_) | Location::Memory(_, _) => {
                             self.assembler
-                                .emit_mov(Size::S64, loc_b, Location::XMM(tmp2));
+                                .emit_mov(Size::S64, loc_b, Location::SIMD(tmp2));
                             tmp2
                         }
                         Location::Imm32(_) => {
@@ -2768,7 +3859,7 @@ impl<'a> FuncGen<'a> {
                             self.assembler.emit_mov(
                                 Size::S32,
                                 Location::GPR(tmpg1),
-                                Location::XMM(tmp2),
+                                Location::SIMD(tmp2),
                             );
                             tmp2
                         }
@@ -2778,7 +3869,7 @@ impl<'a> FuncGen<'a> {
                             self.assembler.emit_mov(
                                 Size::S64,
                                 Location::GPR(tmpg1),
-                                Location::XMM(tmp2),
+                                Location::SIMD(tmp2),
                             );
                             tmp2
                         }
@@ -2794,9 +3885,9 @@ impl<'a> FuncGen<'a> {
                     let tmp_xmm3 = XMM::XMM10;
 
                     self.assembler
-                        .emit_mov(Size::S32, Location::XMM(src1), Location::GPR(tmpg1));
+                        .emit_mov(Size::S32, Location::SIMD(src1), Location::GPR(tmpg1));
                     self.assembler
-                        .emit_mov(Size::S32, Location::XMM(src2), Location::GPR(tmpg2));
+                        .emit_mov(Size::S32, Location::SIMD(src2), Location::GPR(tmpg2));
                     self.assembler
                         .emit_cmp(Size::S32, Location::GPR(tmpg2), Location::GPR(tmpg1));
                     self.assembler
@@ -2828,16 +3919,17 @@ impl<'a> FuncGen<'a> {
                         Location::GPR(tmpg1),
                     );
                     self.assembler
-                        .emit_mov(Size::S64, Location::GPR(tmpg1), Location::XMM(src2));
+                        .emit_mov(Size::S64, Location::GPR(tmpg1), Location::SIMD(src2));
                     self.assembler
                         .emit_vblendvps(src1, XMMOrMemory::XMM(src2), tmp_xmm1, src1);
                     match ret {
-                        Location::XMM(x) => {
+                        Location::SIMD(x) => {
                             self.assembler
                                 .emit_vmovaps(XMMOrMemory::XMM(src1), XMMOrMemory::XMM(x));
                         }
                         Location::Memory(_, _) | Location::GPR(_) => {
-                            self.assembler.emit_mov(Size::S64, Location::XMM(src1), ret);
+                            self.assembler
+                                .emit_mov(Size::S64, Location::SIMD(src1), ret);
                         }
                         _ => {
                             return Err(CodegenError {
@@ -2848,8 +3940,8 @@ impl<'a> FuncGen<'a> {
 
                     self.machine.release_temp_gpr(tmpg2);
                     self.machine.release_temp_gpr(tmpg1);
-                    self.machine.release_temp_xmm(tmp2);
-                    self.machine.release_temp_xmm(tmp1);
+                    self.machine.release_temp_simd(tmp2);
+                    self.machine.release_temp_simd(tmp1);
                 }
             }
             Operator::F32Min => {
@@ -2861,16 +3953,16 @@ impl<'a> FuncGen<'a> {
                 } else {
                     let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64);
 
-                    let tmp1 = self.machine.acquire_temp_xmm().unwrap();
-                    let tmp2 = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp1 = self.machine.acquire_temp_simd().unwrap();
+                    let tmp2 = self.machine.acquire_temp_simd().unwrap();
                     let tmpg1 = self.machine.acquire_temp_gpr().unwrap();
                     let tmpg2 = self.machine.acquire_temp_gpr().unwrap();
 
                     let src1 = match loc_a {
-                        Location::XMM(x) => x,
+                        Location::SIMD(x) => x,
                         Location::GPR(_) | Location::Memory(_, _) => {
                             self.assembler
-                                .emit_mov(Size::S64, loc_a, Location::XMM(tmp1));
+                                .emit_mov(Size::S64, loc_a, Location::SIMD(tmp1));
                             tmp1
                         }
                         Location::Imm32(_) => {
@@ -2879,7 +3971,7 @@ impl<'a> FuncGen<'a> {
                             self.assembler.emit_mov(
                                 Size::S32,
                                 Location::GPR(tmpg1),
-                                Location::XMM(tmp1),
+                                Location::SIMD(tmp1),
                             );
                             tmp1
                         }
@@ -2889,7 +3981,7 @@ impl<'a> FuncGen<'a> {
                             self.assembler.emit_mov(
                                 Size::S64,
                                 Location::GPR(tmpg1),
-                                Location::XMM(tmp1),
+                                Location::SIMD(tmp1),
                             );
                             tmp1
                         }
@@ -2900,10 +3992,10 @@ impl<'a> FuncGen<'a> {
                         }
                     };
                     let src2 = match loc_b {
-                        Location::XMM(x) => x,
+                        Location::SIMD(x) => x,
                         Location::GPR(_) | Location::Memory(_, _) => {
                             self.assembler
-                                .emit_mov(Size::S64, loc_b, Location::XMM(tmp2));
+                                .emit_mov(Size::S64, loc_b, Location::SIMD(tmp2));
                             tmp2
                         }
                         Location::Imm32(_) => {
@@ -2912,7 +4004,7 @@ impl<'a> FuncGen<'a> {
                             self.assembler.emit_mov(
                                 Size::S32,
                                 Location::GPR(tmpg1),
-                                Location::XMM(tmp2),
+                                Location::SIMD(tmp2),
                             );
                             tmp2
                         }
@@ -2922,7 +4014,7 @@ impl<'a> FuncGen<'a> {
                             self.assembler.emit_mov(
                                 Size::S64,
                                 Location::GPR(tmpg1),
-                                Location::XMM(tmp2),
+                                Location::SIMD(tmp2),
                             );
                             tmp2
                         }
@@ -2938,9 +4030,9 @@ impl<'a> FuncGen<'a> {
                     let tmp_xmm3 = XMM::XMM10;
 
                     self.assembler
-                        .emit_mov(Size::S32, Location::XMM(src1), Location::GPR(tmpg1));
+                        .emit_mov(Size::S32, Location::SIMD(src1), Location::GPR(tmpg1));
                     self.assembler
-                        .emit_mov(Size::S32, Location::XMM(src2), Location::GPR(tmpg2));
+                        .emit_mov(Size::S32, Location::SIMD(src2), Location::GPR(tmpg2));
                     self.assembler
                         .emit_cmp(Size::S32, Location::GPR(tmpg2), Location::GPR(tmpg1));
                     self.assembler
@@ -2961,7 +4053,7 @@ impl<'a> FuncGen<'a> {
                     self.assembler.emit_mov(
                         Size::S64,
                         Location::GPR(tmpg1),
-                        Location::XMM(tmp_xmm2),
+                        Location::SIMD(tmp_xmm2),
                     );
                     self.assembler.emit_label(label2);
                     self.assembler
@@ -2981,16 +4073,17 @@ impl<'a> FuncGen<'a> {
                         Location::GPR(tmpg1),
                     );
                     self.assembler
-                        .emit_mov(Size::S64, Location::GPR(tmpg1), Location::XMM(src2));
+                        .emit_mov(Size::S64, Location::GPR(tmpg1), Location::SIMD(src2));
                     self.assembler
                         .emit_vblendvps(src1, XMMOrMemory::XMM(src2), tmp_xmm1, src1);
                     match ret {
-                        Location::XMM(x) => {
+                        Location::SIMD(x) => {
                             self.assembler
                                 .emit_vmovaps(XMMOrMemory::XMM(src1), XMMOrMemory::XMM(x));
                         }
                         Location::Memory(_, _) | Location::GPR(_) => {
-                            self.assembler.emit_mov(Size::S64, Location::XMM(src1), ret);
+                            self.assembler
+                                .emit_mov(Size::S64, Location::SIMD(src1), ret);
                         }
                         _ => {
                             return Err(CodegenError {
@@ -3001,8 +4094,8 @@ impl<'a> FuncGen<'a> {
 
                     self.machine.release_temp_gpr(tmpg2);
                     self.machine.release_temp_gpr(tmpg1);
-                    self.machine.release_temp_xmm(tmp2);
-                    self.machine.release_temp_xmm(tmp1);
+                    self.machine.release_temp_simd(tmp2);
+                    self.machine.release_temp_simd(tmp1);
                 }
             }
             Operator::F32Eq => {
@@ -3140,21 +4233,21 @@ impl<'a> FuncGen<'a> {
                 self.value_stack.push(ret);
 
                 if self.assembler.arch_has_fneg() {
-                    let tmp = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp = self.machine.acquire_temp_simd().unwrap();
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S32,
                         loc,
-                        Location::XMM(tmp),
+                        Location::SIMD(tmp),
                     );
                     self.assembler.arch_emit_f32_neg(tmp, tmp);
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S32,
-                        Location::XMM(tmp),
+                        Location::SIMD(tmp),
                         ret,
                     );
-                    self.machine.release_temp_xmm(tmp);
+                    self.machine.release_temp_simd(tmp);
                 } else {
                     let tmp = self.machine.acquire_temp_gpr().unwrap();
                     self.assembler.emit_mov(Size::S32, loc, Location::GPR(tmp));
@@ -3207,16 +4300,16 @@ impl<'a> FuncGen<'a> {
                 } else {
                     let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64);
 
-                    let tmp1 = self.machine.acquire_temp_xmm().unwrap();
-                    let tmp2 = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp1 = self.machine.acquire_temp_simd().unwrap();
+                    let tmp2 = self.machine.acquire_temp_simd().unwrap();
                     let tmpg1 = self.machine.acquire_temp_gpr().unwrap();
                     let tmpg2 = self.machine.acquire_temp_gpr().unwrap();
 
                     let src1 = match loc_a {
-                        Location::XMM(x) => x,
+                        Location::SIMD(x) => x,
                         Location::GPR(_) | Location::Memory(_, _) => {
                             self.assembler
-                                .emit_mov(Size::S64, loc_a, Location::XMM(tmp1));
+                                .emit_mov(Size::S64, loc_a, Location::SIMD(tmp1));
                             tmp1
                         }
                         Location::Imm32(_) => {
@@ -3225,7 +4318,7 @@ impl<'a> FuncGen<'a> {
                             self.assembler.emit_mov(
                                 Size::S32,
                                 Location::GPR(tmpg1),
-                                Location::XMM(tmp1),
+                                Location::SIMD(tmp1),
                             );
                             tmp1
                         }
@@ -3235,7 +4328,7 @@ impl<'a> FuncGen<'a> {
                             self.assembler.emit_mov(
                                 Size::S64,
                                 Location::GPR(tmpg1),
-                                Location::XMM(tmp1),
+                                Location::SIMD(tmp1),
                             );
                             tmp1
                         }
@@ -3246,10 +4339,10 @@ impl<'a> FuncGen<'a> {
                         }
                     };
                     let src2 = match loc_b {
-                        Location::XMM(x) => x,
+                        Location::SIMD(x) => x,
                         Location::GPR(_) | Location::Memory(_, _) => {
                             self.assembler
-                                .emit_mov(Size::S64, loc_b, Location::XMM(tmp2));
+                                .emit_mov(Size::S64, loc_b, Location::SIMD(tmp2));
                             tmp2
                         }
                         Location::Imm32(_) => {
@@ -3258,7 +4351,7 @@ impl<'a> FuncGen<'a> {
                             self.assembler.emit_mov(
                                 Size::S32,
                                 Location::GPR(tmpg1),
-                                Location::XMM(tmp2),
+                                Location::SIMD(tmp2),
                             );
                             tmp2
                         }
@@ -3268,7 +4361,7 @@ impl<'a> FuncGen<'a> {
                             self.assembler.emit_mov(
                                 Size::S64,
                                 Location::GPR(tmpg1),
-                                Location::XMM(tmp2),
+                                Location::SIMD(tmp2),
                             );
                             tmp2
                         }
@@ -3284,9 +4377,9 @@ impl<'a> FuncGen<'a> {
                     let tmp_xmm3 = XMM::XMM10;
 
                     self.assembler
-                        .emit_mov(Size::S64, Location::XMM(src1), Location::GPR(tmpg1));
+                        .emit_mov(Size::S64, Location::SIMD(src1), Location::GPR(tmpg1));
                     self.assembler
-                        .emit_mov(Size::S64, Location::XMM(src2), Location::GPR(tmpg2));
+                        .emit_mov(Size::S64, Location::SIMD(src2), Location::GPR(tmpg2));
                     self.assembler
                         .emit_cmp(Size::S64, Location::GPR(tmpg2), Location::GPR(tmpg1));
                     self.assembler
@@ -3318,16 +4411,17 @@ impl<'a> FuncGen<'a> {
                         Location::GPR(tmpg1),
                     );
                     self.assembler
-                        .emit_mov(Size::S64, Location::GPR(tmpg1), Location::XMM(src2));
+                        .emit_mov(Size::S64, Location::GPR(tmpg1), Location::SIMD(src2));
                     self.assembler
                         .emit_vblendvpd(src1, XMMOrMemory::XMM(src2), tmp_xmm1, src1);
                     match ret {
-                        Location::XMM(x) => {
+                        Location::SIMD(x) => {
                             self.assembler
                                 .emit_vmovapd(XMMOrMemory::XMM(src1), XMMOrMemory::XMM(x));
                         }
                         Location::Memory(_, _) | Location::GPR(_) => {
-                            self.assembler.emit_mov(Size::S64, Location::XMM(src1), ret);
+                            self.assembler
+                                .emit_mov(Size::S64, Location::SIMD(src1), ret);
                         }
                         _ => {
                             return Err(CodegenError {
@@ -3338,8 +4432,8 @@ impl<'a> FuncGen<'a> {
 
                     self.machine.release_temp_gpr(tmpg2);
                     self.machine.release_temp_gpr(tmpg1);
-                    self.machine.release_temp_xmm(tmp2);
-                    self.machine.release_temp_xmm(tmp1);
+                    self.machine.release_temp_simd(tmp2);
+                    self.machine.release_temp_simd(tmp1);
                 }
             }
             Operator::F64Min => {
@@ -3352,16 +4446,16 @@ impl<'a> FuncGen<'a> {
                 } else {
                     let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::F64);
 
-                    let tmp1 = self.machine.acquire_temp_xmm().unwrap();
-                    let tmp2 = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp1 = self.machine.acquire_temp_simd().unwrap();
+                    let tmp2 = self.machine.acquire_temp_simd().unwrap();
                     let tmpg1 = self.machine.acquire_temp_gpr().unwrap();
                     let tmpg2 = self.machine.acquire_temp_gpr().unwrap();
 
                     let src1 = match loc_a {
-                        Location::XMM(x) => x,
+                        Location::SIMD(x) => x,
                         Location::GPR(_) | Location::Memory(_, _) => {
                             self.assembler
-                                .emit_mov(Size::S64, loc_a, Location::XMM(tmp1));
+                                .emit_mov(Size::S64, loc_a, Location::SIMD(tmp1));
                             tmp1
                         }
                         Location::Imm32(_) => {
@@ -3370,7 +4464,7 @@ impl<'a> FuncGen<'a> {
                             self.assembler.emit_mov(
                                 Size::S32,
                                 Location::GPR(tmpg1),
-                                Location::XMM(tmp1),
+                                Location::SIMD(tmp1),
                             );
                             tmp1
                         }
@@ -3380,7 +4474,7 @@ impl<'a> FuncGen<'a> {
                             self.assembler.emit_mov(
                                 Size::S64,
                                 Location::GPR(tmpg1),
-                                Location::XMM(tmp1),
+                                Location::SIMD(tmp1),
                             );
                             tmp1
                         }
@@ -3391,10 +4485,10 @@ impl<'a> FuncGen<'a> {
                         }
                     };
                     let src2 = match loc_b {
-                        Location::XMM(x) => x,
+                        Location::SIMD(x) => x,
                         Location::GPR(_) | Location::Memory(_, _) => {
                             self.assembler
-                                .emit_mov(Size::S64, loc_b, Location::XMM(tmp2));
+                                .emit_mov(Size::S64, loc_b, Location::SIMD(tmp2));
                             tmp2
                         }
                         Location::Imm32(_) => {
@@ -3403,7 +4497,7 @@ impl<'a> FuncGen<'a> {
                             self.assembler.emit_mov(
                                 Size::S32,
                                 Location::GPR(tmpg1),
-                                Location::XMM(tmp2),
+                                Location::SIMD(tmp2),
                             );
                             tmp2
                         }
@@ -3413,7 +4507,7 @@ impl<'a> FuncGen<'a> {
                             self.assembler.emit_mov(
                                 Size::S64,
                                 Location::GPR(tmpg1),
-                                Location::XMM(tmp2),
+                                Location::SIMD(tmp2),
                             );
                             tmp2
                         }
@@ -3429,9 +4523,9 @@ impl<'a> FuncGen<'a> {
                     let tmp_xmm3 = XMM::XMM10;
 
                     self.assembler
-                        .emit_mov(Size::S64, Location::XMM(src1), Location::GPR(tmpg1));
+                        .emit_mov(Size::S64, Location::SIMD(src1), Location::GPR(tmpg1));
                     self.assembler
-                        .emit_mov(Size::S64, Location::XMM(src2), Location::GPR(tmpg2));
+                        .emit_mov(Size::S64, Location::SIMD(src2), Location::GPR(tmpg2));
                     self.assembler
                         .emit_cmp(Size::S64, Location::GPR(tmpg2), Location::GPR(tmpg1));
                     self.assembler
@@ -3452,7 +4546,7 @@ impl<'a> FuncGen<'a> {
                     self.assembler.emit_mov(
                         Size::S64,
                         Location::GPR(tmpg1),
-                        Location::XMM(tmp_xmm2),
+                        Location::SIMD(tmp_xmm2),
                     );
                     self.assembler.emit_label(label2);
                     self.assembler
@@ -3472,16 +4566,17 @@ impl<'a> FuncGen<'a> {
                         Location::GPR(tmpg1),
                     );
                     self.assembler
-                        .emit_mov(Size::S64, Location::GPR(tmpg1), Location::XMM(src2));
+                        .emit_mov(Size::S64, Location::GPR(tmpg1), Location::SIMD(src2));
                     self.assembler
                         .emit_vblendvpd(src1, XMMOrMemory::XMM(src2), tmp_xmm1, src1);
                     match ret {
-                        Location::XMM(x) => {
+                        Location::SIMD(x) => {
                             self.assembler
                                 .emit_vmovaps(XMMOrMemory::XMM(src1), XMMOrMemory::XMM(x));
                         }
                         Location::Memory(_, _) | Location::GPR(_) => {
-                            self.assembler.emit_mov(Size::S64, Location::XMM(src1), ret);
+                            self.assembler
+                                .emit_mov(Size::S64, Location::SIMD(src1), ret);
                         }
                         _ => {
                             return Err(CodegenError {
@@ -3492,8 +4587,8 @@ impl<'a> FuncGen<'a> {
 
                     self.machine.release_temp_gpr(tmpg2);
                     self.machine.release_temp_gpr(tmpg1);
-                    self.machine.release_temp_xmm(tmp2);
-                    self.machine.release_temp_xmm(tmp1);
+                    self.machine.release_temp_simd(tmp2);
+                    self.machine.release_temp_simd(tmp1);
                 }
             }
             Operator::F64Eq => {
@@ -3648,21 +4743,21 @@ impl<'a> FuncGen<'a> {
                 )[0];
                 self.value_stack.push(ret);
                 if self.assembler.arch_has_fneg() {
-                    let tmp = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp = self.machine.acquire_temp_simd().unwrap();
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S64,
                         loc,
-                        Location::XMM(tmp),
+                        Location::SIMD(tmp),
                     );
                     self.assembler.arch_emit_f64_neg(tmp, tmp);
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S64,
-                        Location::XMM(tmp),
+                        Location::SIMD(tmp),
                         ret,
                     );
-                    self.machine.release_temp_xmm(tmp);
+                    self.machine.release_temp_simd(tmp);
                 } else {
                     let tmp = self.machine.acquire_temp_gpr().unwrap();
                     self.assembler.emit_mov(Size::S64, loc, Location::GPR(tmp));
@@ -3769,12 +4864,12 @@ impl<'a> FuncGen<'a> {
 
                 if self.assembler.arch_has_itruncf() {
                     let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                    let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_in = self.machine.acquire_temp_simd().unwrap();
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S32,
                         loc,
-                        Location::XMM(tmp_in),
+                        Location::SIMD(tmp_in),
                     );
                     self.assembler.arch_emit_i32_trunc_uf32(tmp_in, tmp_out);
                     self.emit_relaxed_binop(
@@ -3783,16 +4878,16 @@ impl<'a> FuncGen<'a> {
                         Location::GPR(tmp_out),
                         ret,
                     );
-                    self.machine.release_temp_xmm(tmp_in);
+                    self.machine.release_temp_simd(tmp_in);
                     self.machine.release_temp_gpr(tmp_out);
                 } else {
                     let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                    let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_in = self.machine.acquire_temp_simd().unwrap();
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S32,
                         loc,
-                        Location::XMM(tmp_in),
+                        Location::SIMD(tmp_in),
                     );
                     self.emit_f32_int_conv_check_trap(tmp_in, GEF32_LT_U32_MIN, LEF32_GT_U32_MAX);
 
@@ -3801,7 +4896,7 @@ impl<'a> FuncGen<'a> {
                     self.assembler
                         .emit_mov(Size::S32, Location::GPR(tmp_out), ret);
 
-                    self.machine.release_temp_xmm(tmp_in);
+                    self.machine.release_temp_simd(tmp_in);
                     self.machine.release_temp_gpr(tmp_out);
                 }
             }
@@ -3817,8 +4912,13 @@ impl<'a> FuncGen<'a> {
                 self.fp_stack.pop1()?;
 
                 let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                let tmp_in = self.machine.acquire_temp_xmm().unwrap();
-                self.emit_relaxed_binop(Assembler::emit_mov, Size::S32, loc, Location::XMM(tmp_in));
+                let tmp_in = self.machine.acquire_temp_simd().unwrap();
+                self.emit_relaxed_binop(
+                    Assembler::emit_mov,
+                    Size::S32,
+                    loc,
+                    Location::SIMD(tmp_in),
+                );
                 self.emit_f32_int_conv_check_sat(
                     tmp_in,
                     GEF32_LT_U32_MIN,
@@ -3850,7 +4950,7 @@ impl<'a> FuncGen<'a> {
 
                 self.assembler
                     .emit_mov(Size::S32, Location::GPR(tmp_out), ret);
-                self.machine.release_temp_xmm(tmp_in);
+                self.machine.release_temp_simd(tmp_in);
                 self.machine.release_temp_gpr(tmp_out);
             }
 
@@ -3866,12 +4966,12 @@ impl<'a> FuncGen<'a> {
 
                 if self.assembler.arch_has_itruncf() {
                     let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                    let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_in = self.machine.acquire_temp_simd().unwrap();
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S32,
                         loc,
-                        Location::XMM(tmp_in),
+                        Location::SIMD(tmp_in),
                     );
                     self.assembler.arch_emit_i32_trunc_sf32(tmp_in, tmp_out);
                     self.emit_relaxed_binop(
@@ -3880,17 +4980,17 @@ impl<'a> FuncGen<'a> {
                         Location::GPR(tmp_out),
                         ret,
                     );
-                    self.machine.release_temp_xmm(tmp_in);
+                    self.machine.release_temp_simd(tmp_in);
                     self.machine.release_temp_gpr(tmp_out);
                 } else {
                     let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                    let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_in = self.machine.acquire_temp_simd().unwrap();
 
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S32,
                         loc,
-                        Location::XMM(tmp_in),
+                        Location::SIMD(tmp_in),
                     );
                     self.emit_f32_int_conv_check_trap(tmp_in, GEF32_LT_I32_MIN, LEF32_GT_I32_MAX);
 
@@ -3899,7 +4999,7 @@ impl<'a> FuncGen<'a> {
                     self.assembler
                         .emit_mov(Size::S32, Location::GPR(tmp_out), ret);
 
-                    self.machine.release_temp_xmm(tmp_in);
+                    self.machine.release_temp_simd(tmp_in);
                     self.machine.release_temp_gpr(tmp_out);
                 }
             }
@@ -3914,9 +5014,14 @@ impl<'a> FuncGen<'a> {
                 self.fp_stack.pop1()?;
 
                 let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                let tmp_in = self.machine.acquire_temp_simd().unwrap();
 
-                self.emit_relaxed_binop(Assembler::emit_mov, Size::S32, loc, Location::XMM(tmp_in));
+                self.emit_relaxed_binop(
+                    Assembler::emit_mov,
+                    Size::S32,
+                    loc,
+                    Location::SIMD(tmp_in),
+                );
                 self.emit_f32_int_conv_check_sat(
                     tmp_in,
                     GEF32_LT_I32_MIN,
@@ -3954,7 +5059,7 @@ impl<'a> FuncGen<'a> {
 
                 self.assembler
                     .emit_mov(Size::S32, Location::GPR(tmp_out), ret);
-                self.machine.release_temp_xmm(tmp_in);
+                self.machine.release_temp_simd(tmp_in);
                 self.machine.release_temp_gpr(tmp_out);
             }
 
@@ -3970,12 +5075,12 @@ impl<'a> FuncGen<'a> {
 
                 if self.assembler.arch_has_itruncf() {
                     let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                    let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_in = self.machine.acquire_temp_simd().unwrap();
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S32,
                         loc,
-                        Location::XMM(tmp_in),
+                        Location::SIMD(tmp_in),
                     );
                     self.assembler.arch_emit_i64_trunc_sf32(tmp_in, tmp_out);
                     self.emit_relaxed_binop(
@@ -3984,17 +5089,17 @@ impl<'a> FuncGen<'a> {
                         Location::GPR(tmp_out),
                         ret,
                     );
-                    self.machine.release_temp_xmm(tmp_in);
+                    self.machine.release_temp_simd(tmp_in);
                     self.machine.release_temp_gpr(tmp_out);
                 } else {
                     let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                    let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_in = self.machine.acquire_temp_simd().unwrap();
 
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S32,
                         loc,
-                        Location::XMM(tmp_in),
+                        Location::SIMD(tmp_in),
                     );
                     self.emit_f32_int_conv_check_trap(tmp_in, GEF32_LT_I64_MIN, LEF32_GT_I64_MAX);
                     self.assembler
@@ -4002,7 +5107,7 @@ impl<'a> FuncGen<'a> {
                     self.assembler
                         .emit_mov(Size::S64, Location::GPR(tmp_out), ret);
 
-                    self.machine.release_temp_xmm(tmp_in);
+                    self.machine.release_temp_simd(tmp_in);
                     self.machine.release_temp_gpr(tmp_out);
                 }
             }
@@ -4018,9 +5123,14 @@ impl<'a> FuncGen<'a> {
                 self.fp_stack.pop1()?;
 
                 let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                let tmp_in = self.machine.acquire_temp_simd().unwrap();
 
-                self.emit_relaxed_binop(Assembler::emit_mov, Size::S32, loc, Location::XMM(tmp_in));
+                self.emit_relaxed_binop(
+                    Assembler::emit_mov,
+                    Size::S32,
+                    loc,
+                    Location::SIMD(tmp_in),
+                );
                 self.emit_f32_int_conv_check_sat(
                     tmp_in,
                     GEF32_LT_I64_MIN,
@@ -4058,7 +5168,7 @@ impl<'a> FuncGen<'a> {
 
                 self.assembler
                     .emit_mov(Size::S64, Location::GPR(tmp_out), ret);
-                self.machine.release_temp_xmm(tmp_in);
+                self.machine.release_temp_simd(tmp_in);
                 self.machine.release_temp_gpr(tmp_out);
             }
 
@@ -4074,12 +5184,12 @@ impl<'a> FuncGen<'a> {
 
                 if self.assembler.arch_has_itruncf() {
                     let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                    let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_in = self.machine.acquire_temp_simd().unwrap();
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S32,
                         loc,
-                        Location::XMM(tmp_in),
+                        Location::SIMD(tmp_in),
                     );
                     self.assembler.arch_emit_i64_trunc_uf32(tmp_in, tmp_out);
                     self.emit_relaxed_binop(
@@ -4088,23 +5198,23 @@ impl<'a> FuncGen<'a> {
                         Location::GPR(tmp_out),
                         ret,
                     );
-                    self.machine.release_temp_xmm(tmp_in);
+                    self.machine.release_temp_simd(tmp_in);
                     self.machine.release_temp_gpr(tmp_out);
                 } else {
                     let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                    let tmp_in = self.machine.acquire_temp_xmm().unwrap(); // xmm2
+                    let tmp_in = self.machine.acquire_temp_simd().unwrap(); // xmm2
 
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S32,
                         loc,
-                        Location::XMM(tmp_in),
+                        Location::SIMD(tmp_in),
                     );
                     self.emit_f32_int_conv_check_trap(tmp_in, GEF32_LT_U64_MIN, LEF32_GT_U64_MAX);
 
                     let tmp = self.machine.acquire_temp_gpr().unwrap(); // r15
-                    let tmp_x1 = self.machine.acquire_temp_xmm().unwrap(); // xmm1
-                    let tmp_x2 = self.machine.acquire_temp_xmm().unwrap(); // xmm3
+                    let tmp_x1 = self.machine.acquire_temp_simd().unwrap(); // xmm1
+                    let tmp_x2 = self.machine.acquire_temp_simd().unwrap(); // xmm3
 
                     self.assembler.emit_mov(
                         Size::S32,
@@ -4112,11 +5222,11 @@ impl<'a> FuncGen<'a> {
                         Location::GPR(tmp),
                     ); //float 9.22337203E+18
                     self.assembler
-                        .emit_mov(Size::S32, Location::GPR(tmp), Location::XMM(tmp_x1));
+                        .emit_mov(Size::S32, Location::GPR(tmp), Location::SIMD(tmp_x1));
                     self.assembler.emit_mov(
                         Size::S32,
-                        Location::XMM(tmp_in),
-                        Location::XMM(tmp_x2),
+                        Location::SIMD(tmp_in),
+                        Location::SIMD(tmp_x2),
                     );
                     self.assembler
                         .emit_vsubss(tmp_in, XMMOrMemory::XMM(tmp_x1), tmp_in);
@@ -4137,10 +5247,10 @@ impl<'a> FuncGen<'a> {
                     self.assembler
                         .emit_mov(Size::S64, Location::GPR(tmp_out), ret);
 
-                    self.machine.release_temp_xmm(tmp_x2);
-                    self.machine.release_temp_xmm(tmp_x1);
+                    self.machine.release_temp_simd(tmp_x2);
+                    self.machine.release_temp_simd(tmp_x1);
                     self.machine.release_temp_gpr(tmp);
-                    self.machine.release_temp_xmm(tmp_in);
+                    self.machine.release_temp_simd(tmp_in);
                     self.machine.release_temp_gpr(tmp_out);
                 }
             }
@@ -4155,9 +5265,14 @@ impl<'a> FuncGen<'a> {
                 self.fp_stack.pop1()?;
 
                 let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                let tmp_in = self.machine.acquire_temp_simd().unwrap();
 
-                self.emit_relaxed_binop(Assembler::emit_mov, Size::S32, loc, Location::XMM(tmp_in));
+                self.emit_relaxed_binop(
+                    Assembler::emit_mov,
+                    Size::S32,
+                    loc,
+                    Location::SIMD(tmp_in),
+                );
                 self.emit_f32_int_conv_check_sat(
                     tmp_in,
                     GEF32_LT_U64_MIN,
@@ -4182,8 +5297,8 @@ impl<'a> FuncGen<'a> {
                             this.assembler.arch_emit_i64_trunc_uf32(tmp_in, tmp_out);
                         } else {
                             let tmp = this.machine.acquire_temp_gpr().unwrap();
-                            let tmp_x1 = this.machine.acquire_temp_xmm().unwrap();
-                            let tmp_x2 = this.machine.acquire_temp_xmm().unwrap();
+                            let tmp_x1 = this.machine.acquire_temp_simd().unwrap();
+                            let tmp_x2 = this.machine.acquire_temp_simd().unwrap();
 
                             this.assembler.emit_mov(
                                 Size::S32,
@@ -4193,12 +5308,12 @@ impl<'a> FuncGen<'a> {
                             this.assembler.emit_mov(
                                 Size::S32,
                                 Location::GPR(tmp),
-                                Location::XMM(tmp_x1),
+                                Location::SIMD(tmp_x1),
                             );
                             this.assembler.emit_mov(
                                 Size::S32,
-                                Location::XMM(tmp_in),
-                                Location::XMM(tmp_x2),
+                                Location::SIMD(tmp_in),
+                                Location::SIMD(tmp_x2),
                             );
                             this.assembler
                                 .emit_vsubss(tmp_in, XMMOrMemory::XMM(tmp_x1), tmp_in);
@@ -4220,8 +5335,8 @@ impl<'a> FuncGen<'a> {
                                 .emit_ucomiss(XMMOrMemory::XMM(tmp_x1), tmp_x2);
                             this.assembler.emit_cmovae_gpr_64(tmp, tmp_out);
 
-                            this.machine.release_temp_xmm(tmp_x2);
-                            this.machine.release_temp_xmm(tmp_x1);
+                            this.machine.release_temp_simd(tmp_x2);
+                            this.machine.release_temp_simd(tmp_x1);
                             this.machine.release_temp_gpr(tmp);
                         }
                     },
@@ -4229,7 +5344,7 @@ impl<'a> FuncGen<'a> {
 
                 self.assembler
                     .emit_mov(Size::S64, Location::GPR(tmp_out), ret);
-                self.machine.release_temp_xmm(tmp_in);
+                self.machine.release_temp_simd(tmp_in);
                 self.machine.release_temp_gpr(tmp_out);
             }
 
@@ -4245,12 +5360,12 @@ impl<'a> FuncGen<'a> {
 
                 if self.assembler.arch_has_itruncf() {
                     let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                    let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_in = self.machine.acquire_temp_simd().unwrap();
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S64,
                         loc,
-                        Location::XMM(tmp_in),
+                        Location::SIMD(tmp_in),
                     );
                     self.assembler.arch_emit_i32_trunc_uf64(tmp_in, tmp_out);
                     self.emit_relaxed_binop(
@@ -4259,17 +5374,17 @@ impl<'a> FuncGen<'a> {
                         Location::GPR(tmp_out),
                         ret,
                     );
-                    self.machine.release_temp_xmm(tmp_in);
+                    self.machine.release_temp_simd(tmp_in);
                     self.machine.release_temp_gpr(tmp_out);
                 } else {
                     let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                    let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_in = self.machine.acquire_temp_simd().unwrap();
 
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S64,
                         loc,
-                        Location::XMM(tmp_in),
+                        Location::SIMD(tmp_in),
                     );
                     self.emit_f64_int_conv_check_trap(tmp_in, GEF64_LT_U32_MIN, LEF64_GT_U32_MAX);
 
@@ -4278,7 +5393,7 @@ impl<'a> FuncGen<'a> {
                     self.assembler
                         .emit_mov(Size::S32, Location::GPR(tmp_out), ret);
 
-                    self.machine.release_temp_xmm(tmp_in);
+                    self.machine.release_temp_simd(tmp_in);
                     self.machine.release_temp_gpr(tmp_out);
                 }
             }
@@ -4294,9 +5409,14 @@ impl<'a> FuncGen<'a> {
                 self.fp_stack.pop1()?;
 
                 let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                let tmp_in = self.machine.acquire_temp_simd().unwrap();
 
-                self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, loc, Location::XMM(tmp_in));
+                self.emit_relaxed_binop(
+                    Assembler::emit_mov,
+                    Size::S64,
+                    loc,
+                    Location::SIMD(tmp_in),
+                );
                 self.emit_f64_int_conv_check_sat(
                     tmp_in,
                     GEF64_LT_U32_MIN,
@@ -4328,7 +5448,7 @@ impl<'a> FuncGen<'a> {
 
                 self.assembler
                     .emit_mov(Size::S32, Location::GPR(tmp_out), ret);
-                self.machine.release_temp_xmm(tmp_in);
+                self.machine.release_temp_simd(tmp_in);
                 self.machine.release_temp_gpr(tmp_out);
             }
 
@@ -4344,12 +5464,12 @@ impl<'a> FuncGen<'a> {
 
                 if self.assembler.arch_has_itruncf() {
                     let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                    let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_in = self.machine.acquire_temp_simd().unwrap();
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S64,
                         loc,
-                        Location::XMM(tmp_in),
+                        Location::SIMD(tmp_in),
                     );
                     self.assembler.arch_emit_i32_trunc_sf64(tmp_in, tmp_out);
                     self.emit_relaxed_binop(
@@ -4358,11 +5478,11 @@ impl<'a> FuncGen<'a> {
                         Location::GPR(tmp_out),
                         ret,
                     );
-                    self.machine.release_temp_xmm(tmp_in);
+                    self.machine.release_temp_simd(tmp_in);
                     self.machine.release_temp_gpr(tmp_out);
                 } else {
                     let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                    let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_in = self.machine.acquire_temp_simd().unwrap();
 
                     let real_in = match loc {
                         Location::Imm32(_) | Location::Imm64(_) => {
@@ -4371,14 +5491,14 @@ impl<'a> FuncGen<'a> {
                             self.assembler.emit_mov(
                                 Size::S64,
                                 Location::GPR(tmp_out),
-                                Location::XMM(tmp_in),
+                                Location::SIMD(tmp_in),
                             );
                             tmp_in
                         }
-                        Location::XMM(x) => x,
+                        Location::SIMD(x) => x,
                         _ => {
                             self.assembler
-                                .emit_mov(Size::S64, loc, Location::XMM(tmp_in));
+                                .emit_mov(Size::S64, loc, Location::SIMD(tmp_in));
                             tmp_in
                         }
                     };
@@ -4390,7 +5510,7 @@ impl<'a> FuncGen<'a> {
                     self.assembler
                         .emit_mov(Size::S32, Location::GPR(tmp_out), ret);
 
-                    self.machine.release_temp_xmm(tmp_in);
+                    self.machine.release_temp_simd(tmp_in);
                     self.machine.release_temp_gpr(tmp_out);
                 }
             }
@@ -4406,7 +5526,7 @@ impl<'a> FuncGen<'a> {
                 self.fp_stack.pop1()?;
 
                 let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                let tmp_in = self.machine.acquire_temp_simd().unwrap();
 
                 let real_in = match loc {
                     Location::Imm32(_) | Location::Imm64(_) => {
@@ -4415,14 +5535,14 @@ impl<'a> FuncGen<'a> {
                         self.assembler.emit_mov(
                             Size::S64,
                             Location::GPR(tmp_out),
-                            Location::XMM(tmp_in),
+                            Location::SIMD(tmp_in),
                         );
                         tmp_in
                     }
-                    Location::XMM(x) => x,
+                    Location::SIMD(x) => x,
                     _ => {
                         self.assembler
-                            .emit_mov(Size::S64, loc, Location::XMM(tmp_in));
+                            .emit_mov(Size::S64, loc, Location::SIMD(tmp_in));
                         tmp_in
                     }
                 };
@@ -4464,7 +5584,7 @@ impl<'a> FuncGen<'a> {
 
                 self.assembler
                     .emit_mov(Size::S32, Location::GPR(tmp_out), ret);
-                self.machine.release_temp_xmm(tmp_in);
+                self.machine.release_temp_simd(tmp_in);
                 self.machine.release_temp_gpr(tmp_out);
             }
 
@@ -4480,12 +5600,12 @@ impl<'a> FuncGen<'a> {
 
                 if self.assembler.arch_has_itruncf() {
                     let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                    let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_in = self.machine.acquire_temp_simd().unwrap();
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S64,
                         loc,
-                        Location::XMM(tmp_in),
+                        Location::SIMD(tmp_in),
                     );
                     self.assembler.arch_emit_i64_trunc_sf64(tmp_in, tmp_out);
                     self.emit_relaxed_binop(
@@ -4494,17 +5614,17 @@ impl<'a> FuncGen<'a> {
                         Location::GPR(tmp_out),
                         ret,
                     );
-                    self.machine.release_temp_xmm(tmp_in);
+                    self.machine.release_temp_simd(tmp_in);
                     self.machine.release_temp_gpr(tmp_out);
                 } else {
                     let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                    let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_in = self.machine.acquire_temp_simd().unwrap();
 
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S64,
                         loc,
-                        Location::XMM(tmp_in),
+                        Location::SIMD(tmp_in),
                     );
                     self.emit_f64_int_conv_check_trap(tmp_in, GEF64_LT_I64_MIN, LEF64_GT_I64_MAX);
 
@@ -4513,7 +5633,7 @@ impl<'a> FuncGen<'a> {
                     self.assembler
                         .emit_mov(Size::S64, Location::GPR(tmp_out), ret);
 
-                    self.machine.release_temp_xmm(tmp_in);
+                    self.machine.release_temp_simd(tmp_in);
                     self.machine.release_temp_gpr(tmp_out);
                 }
             }
@@ -4529,9 +5649,14 @@ impl<'a> FuncGen<'a> {
                 self.fp_stack.pop1()?;
 
                 let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                let tmp_in = self.machine.acquire_temp_simd().unwrap();
 
-                self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, loc, Location::XMM(tmp_in));
+                self.emit_relaxed_binop(
+                    Assembler::emit_mov,
+                    Size::S64,
+                    loc,
+                    Location::SIMD(tmp_in),
+                );
                 self.emit_f64_int_conv_check_sat(
                     tmp_in,
                     GEF64_LT_I64_MIN,
@@ -4569,7 +5694,7 @@ impl<'a> FuncGen<'a> {
 
                 self.assembler
                     .emit_mov(Size::S64, Location::GPR(tmp_out), ret);
-                self.machine.release_temp_xmm(tmp_in);
+                self.machine.release_temp_simd(tmp_in);
                 self.machine.release_temp_gpr(tmp_out);
             }
 
@@ -4585,12 +5710,12 @@ impl<'a> FuncGen<'a> {
 
                 if self.assembler.arch_has_itruncf() {
                     let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                    let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_in = self.machine.acquire_temp_simd().unwrap();
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S64,
                         loc,
-                        Location::XMM(tmp_in),
+                        Location::SIMD(tmp_in),
                     );
                     self.assembler.arch_emit_i64_trunc_uf64(tmp_in, tmp_out);
                     self.emit_relaxed_binop(
@@ -4599,23 +5724,23 @@ impl<'a> FuncGen<'a> {
                         Location::GPR(tmp_out),
                         ret,
                     );
-                    self.machine.release_temp_xmm(tmp_in);
+                    self.machine.release_temp_simd(tmp_in);
                     self.machine.release_temp_gpr(tmp_out);
                 } else {
                     let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                    let tmp_in = self.machine.acquire_temp_xmm().unwrap(); // xmm2
+                    let tmp_in = self.machine.acquire_temp_simd().unwrap(); // xmm2
 
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S64,
                         loc,
-                        Location::XMM(tmp_in),
+                        Location::SIMD(tmp_in),
                     );
                     self.emit_f64_int_conv_check_trap(tmp_in, GEF64_LT_U64_MIN, LEF64_GT_U64_MAX);
 
                     let tmp = self.machine.acquire_temp_gpr().unwrap(); // r15
-                    let tmp_x1 = self.machine.acquire_temp_xmm().unwrap(); // xmm1
-                    let tmp_x2 = self.machine.acquire_temp_xmm().unwrap(); // xmm3
+                    let tmp_x1 = self.machine.acquire_temp_simd().unwrap(); // xmm1
+                    let tmp_x2 = self.machine.acquire_temp_simd().unwrap(); // xmm3
 
                     self.assembler.emit_mov(
                         Size::S64,
@@ -4623,11 +5748,11 @@ impl<'a> FuncGen<'a> {
                         Location::GPR(tmp),
                     ); //double 9.2233720368547758E+18
                     self.assembler
-                        .emit_mov(Size::S64, Location::GPR(tmp), Location::XMM(tmp_x1));
+                        .emit_mov(Size::S64, Location::GPR(tmp), Location::SIMD(tmp_x1));
                     self.assembler.emit_mov(
                         Size::S64,
-                        Location::XMM(tmp_in),
-                        Location::XMM(tmp_x2),
+                        Location::SIMD(tmp_in),
+                        Location::SIMD(tmp_x2),
                     );
                     self.assembler
                         .emit_vsubsd(tmp_in, XMMOrMemory::XMM(tmp_x1), tmp_in);
@@ -4648,10 +5773,10 @@ impl<'a> FuncGen<'a> {
                     self.assembler
                         .emit_mov(Size::S64, Location::GPR(tmp_out), ret);
 
-                    self.machine.release_temp_xmm(tmp_x2);
-                    self.machine.release_temp_xmm(tmp_x1);
+                    self.machine.release_temp_simd(tmp_x2);
+                    self.machine.release_temp_simd(tmp_x1);
                     self.machine.release_temp_gpr(tmp);
-                    self.machine.release_temp_xmm(tmp_in);
+                    self.machine.release_temp_simd(tmp_in);
                     self.machine.release_temp_gpr(tmp_out);
                 }
             }
@@ -4667,9 +5792,14 @@ impl<'a> FuncGen<'a> {
                 self.fp_stack.pop1()?;
 
                 let tmp_out = self.machine.acquire_temp_gpr().unwrap();
-                let tmp_in = self.machine.acquire_temp_xmm().unwrap();
+                let tmp_in = self.machine.acquire_temp_simd().unwrap();
 
-                self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, loc, Location::XMM(tmp_in));
+                self.emit_relaxed_binop(
+                    Assembler::emit_mov,
+                    Size::S64,
+                    loc,
+                    Location::SIMD(tmp_in),
+                );
                 self.emit_f64_int_conv_check_sat(
                     tmp_in,
                     GEF64_LT_U64_MIN,
@@ -4694,8 +5824,8 @@ impl<'a> FuncGen<'a> {
                             this.assembler.arch_emit_i64_trunc_uf64(tmp_in, tmp_out);
                         } else {
                             let tmp = this.machine.acquire_temp_gpr().unwrap();
-                            let tmp_x1 = this.machine.acquire_temp_xmm().unwrap();
-                            let tmp_x2 = this.machine.acquire_temp_xmm().unwrap();
+                            let tmp_x1 = this.machine.acquire_temp_simd().unwrap();
+                            let tmp_x2 = this.machine.acquire_temp_simd().unwrap();
 
                             this.assembler.emit_mov(
                                 Size::S64,
@@ -4705,12 +5835,12 @@ impl<'a> FuncGen<'a> {
                             this.assembler.emit_mov(
                                 Size::S64,
                                 Location::GPR(tmp),
-                                Location::XMM(tmp_x1),
+                                Location::SIMD(tmp_x1),
                             );
                             this.assembler.emit_mov(
                                 Size::S64,
-                                Location::XMM(tmp_in),
-                                Location::XMM(tmp_x2),
+                                Location::SIMD(tmp_in),
+                                Location::SIMD(tmp_x2),
                             );
                             this.assembler
                                 .emit_vsubsd(tmp_in, XMMOrMemory::XMM(tmp_x1), tmp_in);
@@ -4732,8 +5862,8 @@ impl<'a> FuncGen<'a> {
                                 .emit_ucomisd(XMMOrMemory::XMM(tmp_x1), tmp_x2);
                             this.assembler.emit_cmovae_gpr_64(tmp, tmp_out);
 
-                            this.machine.release_temp_xmm(tmp_x2);
-                            this.machine.release_temp_xmm(tmp_x1);
+                            this.machine.release_temp_simd(tmp_x2);
+                            this.machine.release_temp_simd(tmp_x1);
                             this.machine.release_temp_gpr(tmp);
                         }
                     },
@@ -4741,7 +5871,7 @@ impl<'a> FuncGen<'a> {
 
                 self.assembler
                     .emit_mov(Size::S64, Location::GPR(tmp_out), ret);
-                self.machine.release_temp_xmm(tmp_in);
+                self.machine.release_temp_simd(tmp_in);
                 self.machine.release_temp_gpr(tmp_out);
             }
 
@@ -4757,7 +5887,7 @@ impl<'a> FuncGen<'a> {
                     .push(FloatValue::new(self.value_stack.len() - 1)); // Converting i32 to f32 never results in NaN.
 
                 if self.assembler.arch_has_fconverti() {
-                    let tmp_out = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_out = self.machine.acquire_temp_simd().unwrap();
                     let tmp_in = self.machine.acquire_temp_gpr().unwrap();
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
@@ -4769,13 +5899,13 @@ impl<'a> FuncGen<'a> {
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S32,
-                        Location::XMM(tmp_out),
+                        Location::SIMD(tmp_out),
                         ret,
                     );
                     self.machine.release_temp_gpr(tmp_in);
-                    self.machine.release_temp_xmm(tmp_out);
+                    self.machine.release_temp_simd(tmp_out);
                 } else {
-                    let tmp_out = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_out = self.machine.acquire_temp_simd().unwrap();
                     let tmp_in = self.machine.acquire_temp_gpr().unwrap();
 
                     self.assembler
@@ -4783,10 +5913,10 @@ impl<'a> FuncGen<'a> {
                     self.assembler
                         .emit_vcvtsi2ss_32(tmp_out, GPROrMemory::GPR(tmp_in), tmp_out);
                     self.assembler
-                        .emit_mov(Size::S32, Location::XMM(tmp_out), ret);
+                        .emit_mov(Size::S32, Location::SIMD(tmp_out), ret);
 
                     self.machine.release_temp_gpr(tmp_in);
-                    self.machine.release_temp_xmm(tmp_out);
+                    self.machine.release_temp_simd(tmp_out);
                 }
             }
             Operator::F32ConvertI32U => {
@@ -4801,7 +5931,7 @@ impl<'a> FuncGen<'a> {
                     .push(FloatValue::new(self.value_stack.len() - 1)); // Converting i32 to f32 never results in NaN.
 
                 if self.assembler.arch_has_fconverti() {
-                    let tmp_out = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_out = self.machine.acquire_temp_simd().unwrap();
                     let tmp_in = self.machine.acquire_temp_gpr().unwrap();
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
@@ -4813,13 +5943,13 @@ impl<'a> FuncGen<'a> {
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S32,
-                        Location::XMM(tmp_out),
+                        Location::SIMD(tmp_out),
                         ret,
                     );
                     self.machine.release_temp_gpr(tmp_in);
-                    self.machine.release_temp_xmm(tmp_out);
+                    self.machine.release_temp_simd(tmp_out);
                 } else {
-                    let tmp_out = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_out = self.machine.acquire_temp_simd().unwrap();
                     let tmp_in = self.machine.acquire_temp_gpr().unwrap();
 
                     self.assembler
@@ -4827,10 +5957,10 @@ impl<'a> FuncGen<'a> {
                     self.assembler
                         .emit_vcvtsi2ss_64(tmp_out, GPROrMemory::GPR(tmp_in), tmp_out);
                     self.assembler
-                        .emit_mov(Size::S32, Location::XMM(tmp_out), ret);
+                        .emit_mov(Size::S32, Location::SIMD(tmp_out), ret);
 
                     self.machine.release_temp_gpr(tmp_in);
-                    self.machine.release_temp_xmm(tmp_out);
+                    self.machine.release_temp_simd(tmp_out);
                 }
             }
             Operator::F32ConvertI64S => {
@@ -4845,7 +5975,7 @@ impl<'a> FuncGen<'a> {
                     .push(FloatValue::new(self.value_stack.len() - 1)); // Converting i64 to f32 never results in NaN.
 
                 if self.assembler.arch_has_fconverti() {
-                    let tmp_out = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_out = self.machine.acquire_temp_simd().unwrap();
                     let tmp_in = self.machine.acquire_temp_gpr().unwrap();
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
@@ -4857,13 +5987,13 @@ impl<'a> FuncGen<'a> {
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S32,
-                        Location::XMM(tmp_out),
+                        Location::SIMD(tmp_out),
                         ret,
                     );
                     self.machine.release_temp_gpr(tmp_in);
-                    self.machine.release_temp_xmm(tmp_out);
+                    self.machine.release_temp_simd(tmp_out);
                 } else {
-                    let tmp_out = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_out = self.machine.acquire_temp_simd().unwrap();
                     let tmp_in = self.machine.acquire_temp_gpr().unwrap();
 
                     self.assembler
@@ -4871,10 +6001,10 @@ impl<'a> FuncGen<'a> {
                     self.assembler
                         .emit_vcvtsi2ss_64(tmp_out, GPROrMemory::GPR(tmp_in), tmp_out);
                     self.assembler
-                        .emit_mov(Size::S32, Location::XMM(tmp_out), ret);
+                        .emit_mov(Size::S32, Location::SIMD(tmp_out), ret);
 
                     self.machine.release_temp_gpr(tmp_in);
-                    self.machine.release_temp_xmm(tmp_out);
+                    self.machine.release_temp_simd(tmp_out);
                 }
             }
             Operator::F32ConvertI64U => {
@@ -4889,7 +6019,7 @@ impl<'a> FuncGen<'a> {
                     .push(FloatValue::new(self.value_stack.len() - 1)); // Converting i64 to f32 never results in NaN.
 
                 if self.assembler.arch_has_fconverti() {
-                    let tmp_out = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_out = self.machine.acquire_temp_simd().unwrap();
                     let tmp_in = self.machine.acquire_temp_gpr().unwrap();
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
@@ -4901,13 +6031,13 @@ impl<'a> FuncGen<'a> {
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S32,
-                        Location::XMM(tmp_out),
+                        Location::SIMD(tmp_out),
                         ret,
                     );
                     self.machine.release_temp_gpr(tmp_in);
-                    self.machine.release_temp_xmm(tmp_out);
+                    self.machine.release_temp_simd(tmp_out);
                 } else {
-                    let tmp_out = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_out = self.machine.acquire_temp_simd().unwrap();
                     let tmp_in = self.machine.acquire_temp_gpr().unwrap();
                     let tmp = self.machine.acquire_temp_gpr().unwrap();
 
@@ -4936,11 +6066,11 @@ impl<'a> FuncGen<'a> {
                         .emit_vaddss(tmp_out, XMMOrMemory::XMM(tmp_out), tmp_out);
                     self.assembler.emit_label(end_convert);
                     self.assembler
-                        .emit_mov(Size::S32, Location::XMM(tmp_out), ret);
+                        .emit_mov(Size::S32, Location::SIMD(tmp_out), ret);
 
                     self.machine.release_temp_gpr(tmp);
                     self.machine.release_temp_gpr(tmp_in);
-                    self.machine.release_temp_xmm(tmp_out);
+                    self.machine.release_temp_simd(tmp_out);
                 }
             }
 
@@ -4956,7 +6086,7 @@ impl<'a> FuncGen<'a> {
                     .push(FloatValue::new(self.value_stack.len() - 1)); // Converting i32 to f64 never results in NaN.
 
                 if self.assembler.arch_has_fconverti() {
-                    let tmp_out = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_out = self.machine.acquire_temp_simd().unwrap();
                     let tmp_in = self.machine.acquire_temp_gpr().unwrap();
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
@@ -4968,13 +6098,13 @@ impl<'a> FuncGen<'a> {
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S64,
-                        Location::XMM(tmp_out),
+                        Location::SIMD(tmp_out),
                         ret,
                     );
                     self.machine.release_temp_gpr(tmp_in);
-                    self.machine.release_temp_xmm(tmp_out);
+                    self.machine.release_temp_simd(tmp_out);
                 } else {
-                    let tmp_out = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_out = self.machine.acquire_temp_simd().unwrap();
                     let tmp_in = self.machine.acquire_temp_gpr().unwrap();
 
                     self.assembler
@@ -4982,10 +6112,10 @@ impl<'a> FuncGen<'a> {
                     self.assembler
                         .emit_vcvtsi2sd_32(tmp_out, GPROrMemory::GPR(tmp_in), tmp_out);
                     self.assembler
-                        .emit_mov(Size::S64, Location::XMM(tmp_out), ret);
+                        .emit_mov(Size::S64, Location::SIMD(tmp_out), ret);
 
                     self.machine.release_temp_gpr(tmp_in);
-                    self.machine.release_temp_xmm(tmp_out);
+                    self.machine.release_temp_simd(tmp_out);
                 }
             }
             Operator::F64ConvertI32U => {
@@ -5000,7 +6130,7 @@ impl<'a> FuncGen<'a> {
                     .push(FloatValue::new(self.value_stack.len() - 1)); // Converting i32 to f64 never results in NaN.
 
                 if self.assembler.arch_has_fconverti() {
-                    let tmp_out = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_out = self.machine.acquire_temp_simd().unwrap();
                     let tmp_in = self.machine.acquire_temp_gpr().unwrap();
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
@@ -5012,13 +6142,13 @@ impl<'a> FuncGen<'a> {
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S64,
-                        Location::XMM(tmp_out),
+                        Location::SIMD(tmp_out),
                         ret,
                     );
                     self.machine.release_temp_gpr(tmp_in);
-                    self.machine.release_temp_xmm(tmp_out);
+                    self.machine.release_temp_simd(tmp_out);
                 } else {
-                    let tmp_out = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_out = self.machine.acquire_temp_simd().unwrap();
                     let tmp_in = self.machine.acquire_temp_gpr().unwrap();
 
                     self.assembler
@@ -5026,10 +6156,10 @@ impl<'a> FuncGen<'a> {
                     self.assembler
                         .emit_vcvtsi2sd_64(tmp_out, GPROrMemory::GPR(tmp_in), tmp_out);
                     self.assembler
-                        .emit_mov(Size::S64, Location::XMM(tmp_out), ret);
+                        .emit_mov(Size::S64, Location::SIMD(tmp_out), ret);
 
                     self.machine.release_temp_gpr(tmp_in);
-                    self.machine.release_temp_xmm(tmp_out);
+                    self.machine.release_temp_simd(tmp_out);
                 }
             }
             Operator::F64ConvertI64S => {
@@ -5044,7 +6174,7 @@ impl<'a> FuncGen<'a> {
                     .push(FloatValue::new(self.value_stack.len() - 1)); // Converting i64 to f64 never results in NaN.
 
                 if self.assembler.arch_has_fconverti() {
-                    let tmp_out = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_out = self.machine.acquire_temp_simd().unwrap();
                     let tmp_in = self.machine.acquire_temp_gpr().unwrap();
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
@@ -5056,13 +6186,13 @@ impl<'a> FuncGen<'a> {
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S64,
-                        Location::XMM(tmp_out),
+                        Location::SIMD(tmp_out),
                         ret,
                     );
                     self.machine.release_temp_gpr(tmp_in);
-                    self.machine.release_temp_xmm(tmp_out);
+                    self.machine.release_temp_simd(tmp_out);
                 } else {
-                    let tmp_out = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_out = self.machine.acquire_temp_simd().unwrap();
                     let tmp_in = self.machine.acquire_temp_gpr().unwrap();
 
                     self.assembler
@@ -5070,10 +6200,10 @@ impl<'a> FuncGen<'a> {
                     self.assembler
                         .emit_vcvtsi2sd_64(tmp_out, GPROrMemory::GPR(tmp_in), tmp_out);
                     self.assembler
-                        .emit_mov(Size::S64, Location::XMM(tmp_out), ret);
+                        .emit_mov(Size::S64, Location::SIMD(tmp_out), ret);
 
                     self.machine.release_temp_gpr(tmp_in);
-                    self.machine.release_temp_xmm(tmp_out);
+                    self.machine.release_temp_simd(tmp_out);
                 }
             }
             Operator::F64ConvertI64U => {
@@ -5088,7 +6218,7 @@ impl<'a> FuncGen<'a> {
                     .push(FloatValue::new(self.value_stack.len() - 1)); // Converting i64 to f64 never results in NaN.
 
                 if self.assembler.arch_has_fconverti() {
-                    let tmp_out = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_out = self.machine.acquire_temp_simd().unwrap();
                     let tmp_in = self.machine.acquire_temp_gpr().unwrap();
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
@@ -5100,13 +6230,13 @@ impl<'a> FuncGen<'a> {
                     self.emit_relaxed_binop(
                         Assembler::emit_mov,
                         Size::S64,
-                        Location::XMM(tmp_out),
+                        Location::SIMD(tmp_out),
                         ret,
                     );
                     self.machine.release_temp_gpr(tmp_in);
-                    self.machine.release_temp_xmm(tmp_out);
+                    self.machine.release_temp_simd(tmp_out);
                 } else {
-                    let tmp_out = self.machine.acquire_temp_xmm().unwrap();
+                    let tmp_out = self.machine.acquire_temp_simd().unwrap();
                     let tmp_in = self.machine.acquire_temp_gpr().unwrap();
                     let tmp = self.machine.acquire_temp_gpr().unwrap();
 
@@ -5135,11 +6265,11 @@ impl<'a> FuncGen<'a> {
                         .emit_vaddsd(tmp_out, XMMOrMemory::XMM(tmp_out), tmp_out);
                     self.assembler.emit_label(end_convert);
                     self.assembler
-                        .emit_mov(Size::S64, Location::XMM(tmp_out), ret);
+                        .emit_mov(Size::S64, Location::SIMD(tmp_out), ret);
 
                     self.machine.release_temp_gpr(tmp);
                     self.machine.release_temp_gpr(tmp_in);
-                    self.machine.release_temp_xmm(tmp_out);
+                    self.machine.release_temp_simd(tmp_out);
                 }
             }
 
@@ -5161,8 +6291,18 @@ impl<'a> FuncGen<'a> {
                     .value_stack
                     .drain(self.value_stack.len() - param_types.len()..)
                     .collect();
+                let mut call_params = self.split_v128_params(&params);
                 self.machine.release_locations_only_regs(&params);
 
+                // Multiple results are written to the return area passed as the first parameter,
+                // which is the value area of this frame.
+                let return_area = if return_types.len() > 1 {
+                    call_params.insert(0, Location::Imm32(0));
+                    Some(self.machine.get_value_area())
+                } else {
+                    None
+                };
+
                 self.machine.release_locations_only_osr_state(params.len());
 
                 // Pop arguments off the FP stack and canonicalize them if needed.
@@ -5212,6 +6352,13 @@ impl<'a> FuncGen<'a> {
 
                 self.emit_call_sysv(
                     |this| {
+                        if let Some(return_area) = return_area {
+                            this.assembler.emit_lea(
+                                Size::S64,
+                                return_area,
+                                Location::GPR(GPR::RSI),
+                            );
+                        }
                         let offset = this.assembler.get_offset().0;
                         this.trap_table
                             .offset_to_code
@@ -5219,13 +6366,19 @@ impl<'a> FuncGen<'a> {
                         this.assembler.emit_call_location(Location::GPR(GPR::RAX));
                         this.mark_instruction_address_end(offset);
                     },
-                    params.iter().copied(),
+                    call_params.into_iter(),
                 )?;
 
                 self.machine
                     .release_locations_only_stack(&mut self.assembler, &params);
 
-                if !return_types.is_empty() {
+                if return_types.len() > 1 {
+                    self.emit_load_multi_value(&return_types);
+                } else if return_types.first() == Some(&WpType::V128) {
+                    let ret = self.acquire_v128_location();
+                    self.assembler
+                        .emit_vmovdqu(XMMOrMemory::XMM(XMM::XMM0), v128_operand(ret));
+                } else if !return_types.is_empty() {
                     let ret = self.machine.acquire_locations(
                         &mut self.assembler,
                         &[(
@@ -5237,7 +6390,7 @@ impl<'a> FuncGen<'a> {
                     self.value_stack.push(ret);
                     if return_types[0].is_float() {
                         self.assembler
-                            .emit_mov(Size::S64, Location::XMM(XMM::XMM0), ret);
+                            .emit_mov(Size::S64, Location::SIMD(XMM::XMM0), ret);
                         self.fp_stack
                             .push(FloatValue::new(self.value_stack.len() - 1));
                     } else {
@@ -5266,8 +6419,18 @@ impl<'a> FuncGen<'a> {
                     .value_stack
                     .drain(self.value_stack.len() - param_types.len()..)
                     .collect();
+                let mut call_params = self.split_v128_params(&params);
                 self.machine.release_locations_only_regs(&params);
 
+                // Multiple results are written to the return area passed as the first parameter,
+                // which is the value area of this frame.
+                let return_area = if return_types.len() > 1 {
+                    call_params.insert(0, Location::Imm32(0));
+                    Some(self.machine.get_value_area())
+                } else {
+                    None
+                };
+
                 // Pop arguments off the FP stack and canonicalize them if needed.
                 //
                 // Canonicalization state will be lost across function calls, so early canonicalization
@@ -5399,9 +6562,24 @@ impl<'a> FuncGen<'a> {
 
                 let vmcaller_checked_anyfunc_func_ptr =
                     self.vmoffsets.vmcaller_checked_anyfunc_func_ptr() as usize;
+                let vmcaller_checked_anyfunc_vmctx =
+                    self.vmoffsets.vmcaller_checked_anyfunc_vmctx() as usize;
 
                 self.emit_call_sysv(
                     |this| {
+                        if let Some(return_area) = return_area {
+                            this.assembler.emit_lea(
+                                Size::S64,
+                                return_area,
+                                Location::GPR(GPR::RSI),
+                            );
+                        }
+                        // The callee may belong to another instance, or be a host function.
+                        this.assembler.emit_mov(
+                            Size::S64,
+                            Location::Memory(GPR::RAX, vmcaller_checked_anyfunc_vmctx as i32),
+                            Machine::get_param_location(0),
+                        );
                         if this.assembler.arch_requires_indirect_call_trampoline() {
                             this.assembler.arch_emit_indirect_call_with_trampoline(
                                 Location::Memory(
@@ -5421,13 +6599,19 @@ impl<'a> FuncGen<'a> {
                             this.mark_instruction_address_end(offset);
                         }
                     },
-                    params.iter().copied(),
+                    call_params.into_iter(),
                 )?;
 
                 self.machine
                     .release_locations_only_stack(&mut self.assembler, &params);
 
-                if !return_types.is_empty() {
+                if return_types.len() > 1 {
+                    self.emit_load_multi_value(&return_types);
+                } else if return_types.first() == Some(&WpType::V128) {
+                    let ret = self.acquire_v128_location();
+                    self.assembler
+                        .emit_vmovdqu(XMMOrMemory::XMM(XMM::XMM0), v128_operand(ret));
+                } else if !return_types.is_empty() {
                     let ret = self.machine.acquire_locations(
                         &mut self.assembler,
                         &[(
@@ -5439,7 +6623,7 @@ impl<'a> FuncGen<'a> {
                     self.value_stack.push(ret);
                     if return_types[0].is_float() {
                         self.assembler
-                            .emit_mov(Size::S64, Location::XMM(XMM::XMM0), ret);
+                            .emit_mov(Size::S64, Location::SIMD(XMM::XMM0), ret);
                         self.fp_stack
                             .push(FloatValue::new(self.value_stack.len() - 1));
                     } else {
@@ -5449,70 +6633,36 @@ impl<'a> FuncGen<'a> {
                 }
             }
             Operator::If { ty } => {
-                let label_end = self.assembler.get_label();
-                let label_else = self.assembler.get_label();
-
-                let cond = self.pop_value_released();
-
-                let frame = ControlFrame {
-                    label: label_end,
-                    loop_like: false,
-                    if_else: IfElseState::If(label_else),
-                    returns: match ty {
-                        WpTypeOrFuncType::Type(WpType::EmptyBlockType) => smallvec![],
-                        WpTypeOrFuncType::Type(inner_ty) => smallvec![inner_ty],
-                        _ => {
-                            return Err(CodegenError {
-                                message: "If: multi-value returns not yet implemented".to_string(),
-                            })
-                        }
-                    },
-                    value_stack_depth: self.value_stack.len(),
-                    fp_stack_depth: self.fp_stack.len(),
-                    state: self.machine.state.clone(),
-                    state_diff_id: self.get_state_diff(),
-                };
-                self.control_stack.push(frame);
-                self.emit_relaxed_binop(Assembler::emit_cmp, Size::S32, Location::Imm32(0), cond);
-                self.assembler.emit_jmp(Condition::Equal, label_else);
-            }
-            Operator::Else => {
-                let frame = self.control_stack.last_mut().unwrap();
-
-                if !was_unreachable && !frame.returns.is_empty() {
-                    let first_return = frame.returns[0];
-                    let loc = *self.value_stack.last().unwrap();
-                    if first_return.is_float() {
-                        let fp = self.fp_stack.peek1()?;
-                        if self.assembler.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization
-                            && fp.canonicalization.is_some()
-                        {
-                            self.canonicalize_nan(
-                                match first_return {
-                                    WpType::F32 => Size::S32,
-                                    WpType::F64 => Size::S64,
-                                    _ => unreachable!(),
-                                },
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        } else {
-                            self.emit_relaxed_binop(
-                                Assembler::emit_mov,
-                                Size::S64,
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        }
-                    } else {
-                        self.emit_relaxed_binop(
-                            Assembler::emit_mov,
-                            Size::S64,
-                            loc,
-                            Location::GPR(GPR::RAX),
-                        );
-                    }
+                let label_end = self.assembler.get_label();
+                let label_else = self.assembler.get_label();
+
+                let cond = self.pop_value_released();
+                let (params, returns) = self.block_signature(ty);
+
+                // The parameters stay below the frame, and each branch works on its own copy.
+                let frame = ControlFrame {
+                    label: label_end,
+                    loop_like: false,
+                    if_else: IfElseState::If(label_else),
+                    params,
+                    returns,
+                    loop_param_locations: smallvec![],
+                    loop_stack_offset: 0,
+                    value_stack_depth: self.value_stack.len(),
+                    fp_stack_depth: self.fp_stack.len(),
+                    state: self.machine.state.clone(),
+                    state_diff_id: self.get_state_diff(),
+                };
+                self.control_stack.push(frame);
+                self.emit_relaxed_binop(Assembler::emit_cmp, Size::S32, Location::Imm32(0), cond);
+                self.assembler.emit_jmp(Condition::Equal, label_else);
+                let params = self.control_stack.last().unwrap().params.clone();
+                self.emit_param_copies(&params);
+            }
+            Operator::Else => {
+                if !was_unreachable {
+                    let returns = self.control_stack.last().unwrap().returns.clone();
+                    self.emit_block_results(&returns, false)?;
                 }
 
                 let mut frame = self.control_stack.last_mut().unwrap();
@@ -5528,6 +6678,8 @@ impl<'a> FuncGen<'a> {
                         self.assembler.emit_jmp(Condition::None, frame.label);
                         self.assembler.emit_label(label);
                         frame.if_else = IfElseState::Else;
+                        let params = frame.params.clone();
+                        self.emit_param_copies(&params);
                     }
                     _ => {
                         return Err(CodegenError {
@@ -5536,8 +6688,33 @@ impl<'a> FuncGen<'a> {
                     }
                 }
             }
-            Operator::Select => {
+            Operator::Select | Operator::TypedSelect { .. } => {
                 let cond = self.pop_value_released();
+                if self
+                    .machine
+                    .is_v128_stack_slot(*self.value_stack.last().unwrap())
+                {
+                    let v_b = self.pop_value_released();
+                    let v_a = self.pop_value_released();
+                    let ret = self.acquire_v128_location();
+
+                    let end_label = self.assembler.get_label();
+                    let zero_label = self.assembler.get_label();
+
+                    self.emit_relaxed_binop(
+                        Assembler::emit_cmp,
+                        Size::S32,
+                        Location::Imm32(0),
+                        cond,
+                    );
+                    self.assembler.emit_jmp(Condition::Equal, zero_label);
+                    self.emit_v128_mov(v_a, ret);
+                    self.assembler.emit_jmp(Condition::None, end_label);
+                    self.assembler.emit_label(zero_label);
+                    self.emit_v128_mov(v_b, ret);
+                    self.assembler.emit_label(end_label);
+                    return Ok(());
+                }
                 let v_b = self.pop_value_released();
                 let v_a = self.pop_value_released();
                 let cncl: Option<(Option<CanonicalizeType>, Option<CanonicalizeType>)> =
@@ -5595,28 +6772,31 @@ impl<'a> FuncGen<'a> {
                 self.assembler.emit_label(end_label);
             }
             Operator::Block { ty } => {
+                let (params, returns) = self.block_signature(ty);
+                let value_stack_depth = self.value_stack.len() - params.len();
                 let frame = ControlFrame {
                     label: self.assembler.get_label(),
                     loop_like: false,
                     if_else: IfElseState::None,
-                    returns: match ty {
-                        WpTypeOrFuncType::Type(WpType::EmptyBlockType) => smallvec![],
-                        WpTypeOrFuncType::Type(inner_ty) => smallvec![inner_ty],
-                        _ => {
-                            return Err(CodegenError {
-                                message: "Block: multi-value returns not yet implemented"
-                                    .to_string(),
-                            })
-                        }
-                    },
-                    value_stack_depth: self.value_stack.len(),
-                    fp_stack_depth: self.fp_stack.len(),
+                    params,
+                    returns,
+                    loop_param_locations: smallvec![],
+                    loop_stack_offset: 0,
+                    value_stack_depth,
+                    fp_stack_depth: self.fp_stack_depth_at(value_stack_depth),
                     state: self.machine.state.clone(),
                     state_diff_id: self.get_state_diff(),
                 };
                 self.control_stack.push(frame);
             }
             Operator::Loop { ty } => {
+                // Branches to the loop write their arguments to the locations of the parameters,
+                // so the loop works on a copy in case some of them are not writable.
+                let (params, returns) = self.block_signature(ty);
+                let value_stack_depth = self.value_stack.len();
+                let fp_stack_depth = self.fp_stack.len();
+                self.emit_param_copies(&params);
+
                 // Pad with NOPs to the next 16-byte boundary.
                 // Here we don't use the dynasm `.align 16` attribute because it pads the alignment with single-byte nops
                 // which may lead to efficiency problems.
@@ -5636,24 +6816,21 @@ impl<'a> FuncGen<'a> {
                     label,
                     loop_like: true,
                     if_else: IfElseState::None,
-                    returns: match ty {
-                        WpTypeOrFuncType::Type(WpType::EmptyBlockType) => smallvec![],
-                        WpTypeOrFuncType::Type(inner_ty) => smallvec![inner_ty],
-                        _ => {
-                            return Err(CodegenError {
-                                message: "Loop: multi-value returns not yet implemented"
-                                    .to_string(),
-                            })
-                        }
-                    },
-                    value_stack_depth: self.value_stack.len(),
-                    fp_stack_depth: self.fp_stack.len(),
+                    params,
+                    returns,
+                    loop_param_locations: self.value_stack[value_stack_depth..]
+                        .iter()
+                        .cloned()
+                        .collect(),
+                    loop_stack_offset: self.machine.get_stack_offset(),
+                    value_stack_depth,
+                    fp_stack_depth,
                     state: self.machine.state.clone(),
                     state_diff_id,
                 });
                 self.assembler.emit_label(label);
 
-                // TODO: Re-enable interrupt signal check without branching
+                self.emit_interrupt_check();
             }
             Operator::Nop => {}
             Operator::MemorySize { mem, mem_byte: _ } => {
@@ -5744,6 +6921,162 @@ impl<'a> FuncGen<'a> {
                 self.assembler
                     .emit_mov(Size::S64, Location::GPR(GPR::RAX), ret);
             }
+            Operator::MemoryCopy { src, dst: _ } => {
+                let memory_index = MemoryIndex::new(src as usize);
+                let (builtin, index) = match self.module.local_memory_index(memory_index) {
+                    Some(local_memory_index) => (
+                        VMBuiltinFunctionIndex::get_local_memory_copy_index(),
+                        local_memory_index.index(),
+                    ),
+                    None => (
+                        VMBuiltinFunctionIndex::get_imported_memory_copy_index(),
+                        memory_index.index(),
+                    ),
+                };
+                // [vmctx, memory_index, dst, src, len]
+                self.emit_builtin_call(builtin, &[index as u32], 3)?;
+            }
+            Operator::MemoryFill { mem } => {
+                let memory_index = MemoryIndex::new(mem as usize);
+                let (builtin, index) = match self.module.local_memory_index(memory_index) {
+                    Some(local_memory_index) => (
+                        VMBuiltinFunctionIndex::get_memory_fill_index(),
+                        local_memory_index.index(),
+                    ),
+                    None => (
+                        VMBuiltinFunctionIndex::get_imported_memory_fill_index(),
+                        memory_index.index(),
+                    ),
+                };
+                // [vmctx, memory_index, dst, val, len]
+                self.emit_builtin_call(builtin, &[index as u32], 3)?;
+            }
+            Operator::MemoryAtomicWait32 { ref memarg }
+            | Operator::MemoryAtomicWait64 { ref memarg } => {
+                let builtin = match op {
+                    Operator::MemoryAtomicWait32 { .. } => {
+                        VMBuiltinFunctionIndex::get_memory_atomic_wait32_index()
+                    }
+                    _ => VMBuiltinFunctionIndex::get_memory_atomic_wait64_index(),
+                };
+                // The builtin takes a 64-bit address.
+                self.zero_extend_operand(3);
+                // [vmctx, memory_index, offset, addr, expected, timeout]
+                self.emit_builtin_call(builtin, &[memarg.memory, memarg.offset], 3)?;
+                self.push_builtin_result(WpType::I32);
+            }
+            Operator::MemoryAtomicNotify { ref memarg } => {
+                // The builtin takes a 64-bit address.
+                self.zero_extend_operand(2);
+                // [vmctx, memory_index, offset, addr, count]
+                self.emit_builtin_call(
+                    VMBuiltinFunctionIndex::get_memory_atomic_notify_index(),
+                    &[memarg.memory, memarg.offset],
+                    2,
+                )?;
+                self.push_builtin_result(WpType::I32);
+            }
+            Operator::MemoryInit { segment, mem } => {
+                // [vmctx, memory_index, segment_index, dst, src, len]
+                self.emit_builtin_call(
+                    VMBuiltinFunctionIndex::get_memory_init_index(),
+                    &[mem, segment],
+                    3,
+                )?;
+            }
+            Operator::DataDrop { segment } => {
+                // [vmctx, segment_index]
+                self.emit_builtin_call(
+                    VMBuiltinFunctionIndex::get_data_drop_index(),
+                    &[segment],
+                    0,
+                )?;
+            }
+            Operator::TableCopy {
+                dst_table,
+                src_table,
+            } => {
+                // [vmctx, dst_table_index, src_table_index, dst, src, len]
+                self.emit_builtin_call(
+                    VMBuiltinFunctionIndex::get_table_copy_index(),
+                    &[dst_table, src_table],
+                    3,
+                )?;
+            }
+            Operator::TableInit { segment, table } => {
+                // [vmctx, table_index, segment_index, dst, src, len]
+                self.emit_builtin_call(
+                    VMBuiltinFunctionIndex::get_table_init_index(),
+                    &[table, segment],
+                    3,
+                )?;
+            }
+            Operator::ElemDrop { segment } => {
+                // [vmctx, segment_index]
+                self.emit_builtin_call(
+                    VMBuiltinFunctionIndex::get_elem_drop_index(),
+                    &[segment],
+                    0,
+                )?;
+            }
+            Operator::TableSize { table } => {
+                let table_index = TableIndex::new(table as usize);
+                let ret = self.machine.acquire_locations(
+                    &mut self.assembler,
+                    &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
+                    false,
+                )[0];
+                self.value_stack.push(ret);
+
+                let tmp = self.machine.acquire_temp_gpr().unwrap();
+                if let Some(local_table_index) = self.module.local_table_index(table_index) {
+                    let offset = self
+                        .vmoffsets
+                        .vmctx_vmtable_definition_current_elements(local_table_index);
+                    self.assembler.emit_mov(
+                        Size::S32,
+                        Location::Memory(Machine::get_vmctx_reg(), offset as i32),
+                        Location::GPR(tmp),
+                    );
+                } else {
+                    // Imported tables require one level of indirection.
+                    let offset = self.vmoffsets.vmctx_vmtable_import(table_index);
+                    self.assembler.emit_mov(
+                        Size::S64,
+                        Location::Memory(Machine::get_vmctx_reg(), offset as i32),
+                        Location::GPR(tmp),
+                    );
+                    self.assembler.emit_mov(
+                        Size::S32,
+                        Location::Memory(
+                            tmp,
+                            self.vmoffsets.vmtable_definition_current_elements() as i32,
+                        ),
+                        Location::GPR(tmp),
+                    );
+                }
+                self.assembler.emit_mov(Size::S32, Location::GPR(tmp), ret);
+                self.machine.release_temp_gpr(tmp);
+            }
+            Operator::RefNull { .. } => {
+                self.value_stack.push(Location::Imm64(0));
+                self.machine
+                    .state
+                    .wasm_stack
+                    .push(WasmAbstractValue::Const(0));
+            }
+            Operator::RefIsNull => {
+                self.emit_cmpop_i64_dynamic_b(Condition::Equal, Location::Imm64(0))?
+            }
+            Operator::TableGet { .. }
+            | Operator::TableSet { .. }
+            | Operator::TableGrow { .. }
+            | Operator::TableFill { .. }
+            | Operator::RefFunc { .. } => {
+                return Err(CodegenError {
+                    message: format!("{:?}: reference types instruction not supported yet", op),
+                })
+            }
             Operator::I32Load { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.machine.acquire_locations(
@@ -6194,101 +7527,11 @@ impl<'a> FuncGen<'a> {
                 self.unreachable_depth = 1;
             }
             Operator::Return => {
-                let frame = &self.control_stack[0];
-                if !frame.returns.is_empty() {
-                    if frame.returns.len() != 1 {
-                        return Err(CodegenError {
-                            message: "Return: incorrect frame.returns".to_string(),
-                        });
-                    }
-                    let first_return = frame.returns[0];
-                    let loc = *self.value_stack.last().unwrap();
-                    if first_return.is_float() {
-                        let fp = self.fp_stack.peek1()?;
-                        if self.assembler.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization
-                            && fp.canonicalization.is_some()
-                        {
-                            self.canonicalize_nan(
-                                match first_return {
-                                    WpType::F32 => Size::S32,
-                                    WpType::F64 => Size::S64,
-                                    _ => unreachable!(),
-                                },
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        } else {
-                            self.emit_relaxed_binop(
-                                Assembler::emit_mov,
-                                Size::S64,
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        }
-                    } else {
-                        self.emit_relaxed_binop(
-                            Assembler::emit_mov,
-                            Size::S64,
-                            loc,
-                            Location::GPR(GPR::RAX),
-                        );
-                    }
-                }
-                let frame = &self.control_stack[0];
-                let released = &self.value_stack[frame.value_stack_depth..];
-                self.machine
-                    .release_locations_keep_state(&mut self.assembler, released);
-                self.assembler.emit_jmp(Condition::None, frame.label);
+                self.emit_branch(0)?;
                 self.unreachable_depth = 1;
             }
             Operator::Br { relative_depth } => {
-                let frame =
-                    &self.control_stack[self.control_stack.len() - 1 - (relative_depth as usize)];
-                if !frame.loop_like && !frame.returns.is_empty() {
-                    if frame.returns.len() != 1 {
-                        return Err(CodegenError {
-                            message: "Br: incorrect frame.returns".to_string(),
-                        });
-                    }
-                    let first_return = frame.returns[0];
-                    let loc = *self.value_stack.last().unwrap();
-
-                    if first_return.is_float() {
-                        let fp = self.fp_stack.peek1()?;
-                        if self.assembler.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization
-                            && fp.canonicalization.is_some()
-                        {
-                            self.canonicalize_nan(
-                                match first_return {
-                                    WpType::F32 => Size::S32,
-                                    WpType::F64 => Size::S64,
-                                    _ => unreachable!(),
-                                },
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        } else {
-                            self.emit_relaxed_binop(
-                                Assembler::emit_mov,
-                                Size::S64,
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        }
-                    } else {
-                        self.assembler
-                            .emit_mov(Size::S64, loc, Location::GPR(GPR::RAX));
-                    }
-                }
-                let frame =
-                    &self.control_stack[self.control_stack.len() - 1 - (relative_depth as usize)];
-
-                let released = &self.value_stack[frame.value_stack_depth..];
-                self.machine
-                    .release_locations_keep_state(&mut self.assembler, released);
-                self.assembler.emit_jmp(Condition::None, frame.label);
+                self.emit_branch(self.control_stack.len() - 1 - (relative_depth as usize))?;
                 self.unreachable_depth = 1;
             }
             Operator::BrIf { relative_depth } => {
@@ -6297,51 +7540,7 @@ impl<'a> FuncGen<'a> {
                 self.emit_relaxed_binop(Assembler::emit_cmp, Size::S32, Location::Imm32(0), cond);
                 self.assembler.emit_jmp(Condition::Equal, after);
 
-                let frame =
-                    &self.control_stack[self.control_stack.len() - 1 - (relative_depth as usize)];
-                if !frame.loop_like && !frame.returns.is_empty() {
-                    if frame.returns.len() != 1 {
-                        return Err(CodegenError {
-                            message: "BrIf: incorrect frame.returns".to_string(),
-                        });
-                    }
-
-                    let first_return = frame.returns[0];
-                    let loc = *self.value_stack.last().unwrap();
-                    if first_return.is_float() {
-                        let fp = self.fp_stack.peek1()?;
-                        if self.assembler.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization
-                            && fp.canonicalization.is_some()
-                        {
-                            self.canonicalize_nan(
-                                match first_return {
-                                    WpType::F32 => Size::S32,
-                                    WpType::F64 => Size::S64,
-                                    _ => unreachable!(),
-                                },
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        } else {
-                            self.emit_relaxed_binop(
-                                Assembler::emit_mov,
-                                Size::S64,
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        }
-                    } else {
-                        self.assembler
-                            .emit_mov(Size::S64, loc, Location::GPR(GPR::RAX));
-                    }
-                }
-                let frame =
-                    &self.control_stack[self.control_stack.len() - 1 - (relative_depth as usize)];
-                let released = &self.value_stack[frame.value_stack_depth..];
-                self.machine
-                    .release_locations_keep_state(&mut self.assembler, released);
-                self.assembler.emit_jmp(Condition::None, frame.label);
+                self.emit_branch(self.control_stack.len() - 1 - (relative_depth as usize))?;
 
                 self.assembler.emit_label(after);
             }
@@ -6384,104 +7583,10 @@ impl<'a> FuncGen<'a> {
                     let label = self.assembler.get_label();
                     self.assembler.emit_label(label);
                     table.push(label);
-                    let frame =
-                        &self.control_stack[self.control_stack.len() - 1 - (*target as usize)];
-                    if !frame.loop_like && !frame.returns.is_empty() {
-                        if frame.returns.len() != 1 {
-                            return Err(CodegenError {
-                                message: format!(
-                                    "BrTable: incorrect frame.returns for {:?}",
-                                    target
-                                ),
-                            });
-                        }
-
-                        let first_return = frame.returns[0];
-                        let loc = *self.value_stack.last().unwrap();
-                        if first_return.is_float() {
-                            let fp = self.fp_stack.peek1()?;
-                            if self.assembler.arch_supports_canonicalize_nan()
-                                && self.config.enable_nan_canonicalization
-                                && fp.canonicalization.is_some()
-                            {
-                                self.canonicalize_nan(
-                                    match first_return {
-                                        WpType::F32 => Size::S32,
-                                        WpType::F64 => Size::S64,
-                                        _ => unreachable!(),
-                                    },
-                                    loc,
-                                    Location::GPR(GPR::RAX),
-                                );
-                            } else {
-                                self.emit_relaxed_binop(
-                                    Assembler::emit_mov,
-                                    Size::S64,
-                                    loc,
-                                    Location::GPR(GPR::RAX),
-                                );
-                            }
-                        } else {
-                            self.assembler
-                                .emit_mov(Size::S64, loc, Location::GPR(GPR::RAX));
-                        }
-                    }
-                    let frame =
-                        &self.control_stack[self.control_stack.len() - 1 - (*target as usize)];
-                    let released = &self.value_stack[frame.value_stack_depth..];
-                    self.machine
-                        .release_locations_keep_state(&mut self.assembler, released);
-                    self.assembler.emit_jmp(Condition::None, frame.label);
+                    self.emit_branch(self.control_stack.len() - 1 - (*target as usize))?;
                 }
                 self.assembler.emit_label(default_br);
-
-                {
-                    let frame = &self.control_stack
-                        [self.control_stack.len() - 1 - (default_target as usize)];
-                    if !frame.loop_like && !frame.returns.is_empty() {
-                        if frame.returns.len() != 1 {
-                            return Err(CodegenError {
-                                message: "BrTable: incorrect frame.returns".to_string(),
-                            });
-                        }
-
-                        let first_return = frame.returns[0];
-                        let loc = *self.value_stack.last().unwrap();
-                        if first_return.is_float() {
-                            let fp = self.fp_stack.peek1()?;
-                            if self.assembler.arch_supports_canonicalize_nan()
-                                && self.config.enable_nan_canonicalization
-                                && fp.canonicalization.is_some()
-                            {
-                                self.canonicalize_nan(
-                                    match first_return {
-                                        WpType::F32 => Size::S32,
-                                        WpType::F64 => Size::S64,
-                                        _ => unreachable!(),
-                                    },
-                                    loc,
-                                    Location::GPR(GPR::RAX),
-                                );
-                            } else {
-                                self.emit_relaxed_binop(
-                                    Assembler::emit_mov,
-                                    Size::S64,
-                                    loc,
-                                    Location::GPR(GPR::RAX),
-                                );
-                            }
-                        } else {
-                            self.assembler
-                                .emit_mov(Size::S64, loc, Location::GPR(GPR::RAX));
-                        }
-                    }
-                    let frame = &self.control_stack
-                        [self.control_stack.len() - 1 - (default_target as usize)];
-                    let released = &self.value_stack[frame.value_stack_depth..];
-                    self.machine
-                        .release_locations_keep_state(&mut self.assembler, released);
-                    self.assembler.emit_jmp(Condition::None, frame.label);
-                }
+                self.emit_branch(self.control_stack.len() - 1 - (default_target as usize))?;
 
                 self.assembler.emit_label(table_label);
                 for x in table {
@@ -6500,39 +7605,8 @@ impl<'a> FuncGen<'a> {
             Operator::End => {
                 let frame = self.control_stack.pop().unwrap();
 
-                if !was_unreachable && !frame.returns.is_empty() {
-                    let loc = *self.value_stack.last().unwrap();
-                    if frame.returns[0].is_float() {
-                        let fp = self.fp_stack.peek1()?;
-                        if self.assembler.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization
-                            && fp.canonicalization.is_some()
-                        {
-                            self.canonicalize_nan(
-                                match frame.returns[0] {
-                                    WpType::F32 => Size::S32,
-                                    WpType::F64 => Size::S64,
-                                    _ => unreachable!(),
-                                },
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        } else {
-                            self.emit_relaxed_binop(
-                                Assembler::emit_mov,
-                                Size::S64,
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        }
-                    } else {
-                        self.emit_relaxed_binop(
-                            Assembler::emit_mov,
-                            Size::S64,
-                            loc,
-                            Location::GPR(GPR::RAX),
-                        );
-                    }
+                if !was_unreachable {
+                    self.emit_block_results(&frame.returns, self.control_stack.is_empty())?;
                 }
 
                 if self.control_stack.is_empty() {
@@ -6552,7 +7626,7 @@ impl<'a> FuncGen<'a> {
                             self.assembler.emit_mov(
                                 Size::S64,
                                 Location::GPR(GPR::RAX),
-                                Location::XMM(XMM::XMM0),
+                                Location::SIMD(XMM::XMM0),
                             );
                         }
                         _ => {}
@@ -6565,36 +7639,41 @@ impl<'a> FuncGen<'a> {
                     self.value_stack.truncate(frame.value_stack_depth);
                     self.fp_stack.truncate(frame.fp_stack_depth);
 
-                    if !frame.loop_like {
-                        self.assembler.emit_label(frame.label);
+                    match frame.if_else {
+                        IfElseState::If(label) if !frame.params.is_empty() => {
+                            // Without an `else`, the parameters are passed through as the results.
+                            self.assembler.emit_jmp(Condition::None, frame.label);
+                            self.assembler.emit_label(label);
+                            self.emit_block_results(&frame.returns, false)?;
+                            self.assembler.emit_label(frame.label);
+                        }
+                        IfElseState::If(label) => {
+                            self.assembler.emit_label(frame.label);
+                            self.assembler.emit_label(label);
+                        }
+                        IfElseState::Else => {
+                            self.assembler.emit_label(frame.label);
+                        }
+                        IfElseState::None => {
+                            if !frame.loop_like {
+                                self.assembler.emit_label(frame.label);
+                            }
+                        }
                     }
 
-                    if let IfElseState::If(label) = frame.if_else {
-                        self.assembler.emit_label(label);
+                    // The parameters of an `if` or a loop are kept below its frame until the end.
+                    if frame.loop_like || !matches!(frame.if_else, IfElseState::None) {
+                        let depth = self.value_stack.len() - frame.params.len();
+                        let released: &[Location] = &self.value_stack[depth..];
+                        self.machine
+                            .release_locations(&mut self.assembler, released);
+                        self.value_stack.truncate(depth);
+                        let fp_depth = self.fp_stack_depth_at(depth);
+                        self.fp_stack.truncate(fp_depth);
                     }
 
                     if !frame.returns.is_empty() {
-                        if frame.returns.len() != 1 {
-                            return Err(CodegenError {
-                                message: "End: incorrect frame.returns".to_string(),
-                            });
-                        }
-                        let loc = self.machine.acquire_locations(
-                            &mut self.assembler,
-                            &[(
-                                frame.returns[0],
-                                MachineValue::WasmStack(self.value_stack.len()),
-                            )],
-                            false,
-                        )[0];
-                        self.assembler
-                            .emit_mov(Size::S64, Location::GPR(GPR::RAX), loc);
-                        self.value_stack.push(loc);
-                        if frame.returns[0].is_float() {
-                            self.fp_stack
-                                .push(FloatValue::new(self.value_stack.len() - 1));
-                            // we already canonicalized at the `Br*` instruction or here previously.
-                        }
+                        self.emit_load_block_results(&frame.returns);
                     }
                 }
             }
@@ -6918,8 +7997,13 @@ impl<'a> FuncGen<'a> {
                 self.value_stack.push(ret);
 
                 let value = self.machine.acquire_temp_gpr().unwrap();
-                self.assembler
-                    .emit_movzx(Size::S8, loc, Size::S32, Location::GPR(value));
+                self.emit_relaxed_zx_sx(
+                    Assembler::emit_movzx,
+                    Size::S8,
+                    loc,
+                    Size::S32,
+                    Location::GPR(value),
+                )?;
                 self.emit_memory_op(target, memarg, true, 1, |this, addr| {
                     this.assembler.emit_lock_xadd(
                         Size::S8,
@@ -6943,8 +8027,13 @@ impl<'a> FuncGen<'a> {
                 self.value_stack.push(ret);
 
                 let value = self.machine.acquire_temp_gpr().unwrap();
-                self.assembler
-                    .emit_movzx(Size::S16, loc, Size::S32, Location::GPR(value));
+                self.emit_relaxed_zx_sx(
+                    Assembler::emit_movzx,
+                    Size::S16,
+                    loc,
+                    Size::S32,
+                    Location::GPR(value),
+                )?;
                 self.emit_memory_op(target, memarg, true, 2, |this, addr| {
                     this.assembler.emit_lock_xadd(
                         Size::S16,
@@ -6968,8 +8057,13 @@ impl<'a> FuncGen<'a> {
                 self.value_stack.push(ret);
 
                 let value = self.machine.acquire_temp_gpr().unwrap();
-                self.assembler
-                    .emit_movzx(Size::S8, loc, Size::S64, Location::GPR(value));
+                self.emit_relaxed_zx_sx(
+                    Assembler::emit_movzx,
+                    Size::S8,
+                    loc,
+                    Size::S64,
+                    Location::GPR(value),
+                )?;
                 self.emit_memory_op(target, memarg, true, 1, |this, addr| {
                     this.assembler.emit_lock_xadd(
                         Size::S8,
@@ -6993,8 +8087,13 @@ impl<'a> FuncGen<'a> {
                 self.value_stack.push(ret);
 
                 let value = self.machine.acquire_temp_gpr().unwrap();
-                self.assembler
-                    .emit_movzx(Size::S16, loc, Size::S64, Location::GPR(value));
+                self.emit_relaxed_zx_sx(
+                    Assembler::emit_movzx,
+                    Size::S16,
+                    loc,
+                    Size::S64,
+                    Location::GPR(value),
+                )?;
                 self.emit_memory_op(target, memarg, true, 2, |this, addr| {
                     this.assembler.emit_lock_xadd(
                         Size::S16,
@@ -7095,8 +8194,13 @@ impl<'a> FuncGen<'a> {
                 self.value_stack.push(ret);
 
                 let value = self.machine.acquire_temp_gpr().unwrap();
-                self.assembler
-                    .emit_movzx(Size::S8, loc, Size::S32, Location::GPR(value));
+                self.emit_relaxed_zx_sx(
+                    Assembler::emit_movzx,
+                    Size::S8,
+                    loc,
+                    Size::S32,
+                    Location::GPR(value),
+                )?;
                 self.assembler.emit_neg(Size::S8, Location::GPR(value));
                 self.emit_memory_op(target, memarg, true, 1, |this, addr| {
                     this.assembler.emit_lock_xadd(
@@ -7121,8 +8225,13 @@ impl<'a> FuncGen<'a> {
                 self.value_stack.push(ret);
 
                 let value = self.machine.acquire_temp_gpr().unwrap();
-                self.assembler
-                    .emit_movzx(Size::S16, loc, Size::S32, Location::GPR(value));
+                self.emit_relaxed_zx_sx(
+                    Assembler::emit_movzx,
+                    Size::S16,
+                    loc,
+                    Size::S32,
+                    Location::GPR(value),
+                )?;
                 self.assembler.emit_neg(Size::S16, Location::GPR(value));
                 self.emit_memory_op(target, memarg, true, 2, |this, addr| {
                     this.assembler.emit_lock_xadd(
@@ -7147,8 +8256,13 @@ impl<'a> FuncGen<'a> {
                 self.value_stack.push(ret);
 
                 let value = self.machine.acquire_temp_gpr().unwrap();
-                self.assembler
-                    .emit_movzx(Size::S8, loc, Size::S64, Location::GPR(value));
+                self.emit_relaxed_zx_sx(
+                    Assembler::emit_movzx,
+                    Size::S8,
+                    loc,
+                    Size::S64,
+                    Location::GPR(value),
+                )?;
                 self.assembler.emit_neg(Size::S8, Location::GPR(value));
                 self.emit_memory_op(target, memarg, true, 1, |this, addr| {
                     this.assembler.emit_lock_xadd(
@@ -7173,8 +8287,13 @@ impl<'a> FuncGen<'a> {
                 self.value_stack.push(ret);
 
                 let value = self.machine.acquire_temp_gpr().unwrap();
-                self.assembler
-                    .emit_movzx(Size::S16, loc, Size::S64, Location::GPR(value));
+                self.emit_relaxed_zx_sx(
+                    Assembler::emit_movzx,
+                    Size::S16,
+                    loc,
+                    Size::S64,
+                    Location::GPR(value),
+                )?;
                 self.assembler.emit_neg(Size::S16, Location::GPR(value));
                 self.emit_memory_op(target, memarg, true, 2, |this, addr| {
                     this.assembler.emit_lock_xadd(
@@ -7301,7 +8420,7 @@ impl<'a> FuncGen<'a> {
                     target,
                     ret,
                     memarg,
-                    1,
+                    2,
                     Size::S16,
                     Size::S32,
                     |this, src, dst| {
@@ -7349,7 +8468,7 @@ impl<'a> FuncGen<'a> {
                     target,
                     ret,
                     memarg,
-                    1,
+                    2,
                     Size::S16,
                     Size::S64,
                     |this, src, dst| {
@@ -7373,7 +8492,7 @@ impl<'a> FuncGen<'a> {
                     target,
                     ret,
                     memarg,
-                    1,
+                    4,
                     Size::S32,
                     Size::S64,
                     |this, src, dst| {
@@ -7469,7 +8588,7 @@ impl<'a> FuncGen<'a> {
                     target,
                     ret,
                     memarg,
-                    1,
+                    2,
                     Size::S16,
                     Size::S32,
                     |this, src, dst| {
@@ -7517,7 +8636,7 @@ impl<'a> FuncGen<'a> {
                     target,
                     ret,
                     memarg,
-                    1,
+                    2,
                     Size::S16,
                     Size::S64,
                     |this, src, dst| {
@@ -7541,7 +8660,7 @@ impl<'a> FuncGen<'a> {
                     target,
                     ret,
                     memarg,
-                    1,
+                    4,
                     Size::S32,
                     Size::S64,
                     |this, src, dst| {
@@ -7637,7 +8756,7 @@ impl<'a> FuncGen<'a> {
                     target,
                     ret,
                     memarg,
-                    1,
+                    2,
                     Size::S16,
                     Size::S32,
                     |this, src, dst| {
@@ -7685,7 +8804,7 @@ impl<'a> FuncGen<'a> {
                     target,
                     ret,
                     memarg,
-                    1,
+                    2,
                     Size::S16,
                     Size::S64,
                     |this, src, dst| {
@@ -7709,7 +8828,7 @@ impl<'a> FuncGen<'a> {
                     target,
                     ret,
                     memarg,
-                    1,
+                    4,
                     Size::S32,
                     Size::S64,
                     |this, src, dst| {
@@ -7779,8 +8898,13 @@ impl<'a> FuncGen<'a> {
                 self.value_stack.push(ret);
 
                 let value = self.machine.acquire_temp_gpr().unwrap();
-                self.assembler
-                    .emit_movzx(Size::S8, loc, Size::S32, Location::GPR(value));
+                self.emit_relaxed_zx_sx(
+                    Assembler::emit_movzx,
+                    Size::S8,
+                    loc,
+                    Size::S32,
+                    Location::GPR(value),
+                )?;
                 self.emit_memory_op(target, memarg, true, 1, |this, addr| {
                     this.assembler.emit_xchg(
                         Size::S8,
@@ -7804,8 +8928,13 @@ impl<'a> FuncGen<'a> {
                 self.value_stack.push(ret);
 
                 let value = self.machine.acquire_temp_gpr().unwrap();
-                self.assembler
-                    .emit_movzx(Size::S16, loc, Size::S32, Location::GPR(value));
+                self.emit_relaxed_zx_sx(
+                    Assembler::emit_movzx,
+                    Size::S16,
+                    loc,
+                    Size::S32,
+                    Location::GPR(value),
+                )?;
                 self.emit_memory_op(target, memarg, true, 2, |this, addr| {
                     this.assembler.emit_xchg(
                         Size::S16,
@@ -7829,8 +8958,13 @@ impl<'a> FuncGen<'a> {
                 self.value_stack.push(ret);
 
                 let value = self.machine.acquire_temp_gpr().unwrap();
-                self.assembler
-                    .emit_movzx(Size::S8, loc, Size::S64, Location::GPR(value));
+                self.emit_relaxed_zx_sx(
+                    Assembler::emit_movzx,
+                    Size::S8,
+                    loc,
+                    Size::S64,
+                    Location::GPR(value),
+                )?;
                 self.emit_memory_op(target, memarg, true, 1, |this, addr| {
                     this.assembler.emit_xchg(
                         Size::S8,
@@ -7854,8 +8988,13 @@ impl<'a> FuncGen<'a> {
                 self.value_stack.push(ret);
 
                 let value = self.machine.acquire_temp_gpr().unwrap();
-                self.assembler
-                    .emit_movzx(Size::S16, loc, Size::S64, Location::GPR(value));
+                self.emit_relaxed_zx_sx(
+                    Assembler::emit_movzx,
+                    Size::S16,
+                    loc,
+                    Size::S64,
+                    Location::GPR(value),
+                )?;
                 self.emit_memory_op(target, memarg, true, 2, |this, addr| {
                     this.assembler.emit_xchg(
                         Size::S16,
@@ -8040,7 +9179,7 @@ impl<'a> FuncGen<'a> {
                 self.assembler
                     .emit_mov(Size::S32, new, Location::GPR(value));
 
-                self.emit_memory_op(target, memarg, true, 1, |this, addr| {
+                self.emit_memory_op(target, memarg, true, 2, |this, addr| {
                     this.assembler.emit_lock_cmpxchg(
                         Size::S16,
                         Location::GPR(value),
@@ -8120,7 +9259,7 @@ impl<'a> FuncGen<'a> {
                 self.assembler
                     .emit_mov(Size::S64, new, Location::GPR(value));
 
-                self.emit_memory_op(target, memarg, true, 1, |this, addr| {
+                self.emit_memory_op(target, memarg, true, 2, |this, addr| {
                     this.assembler.emit_lock_cmpxchg(
                         Size::S16,
                         Location::GPR(value),
@@ -8160,7 +9299,7 @@ impl<'a> FuncGen<'a> {
                 self.assembler
                     .emit_mov(Size::S64, new, Location::GPR(value));
 
-                self.emit_memory_op(target, memarg, true, 1, |this, addr| {
+                self.emit_memory_op(target, memarg, true, 4, |this, addr| {
                     this.assembler.emit_lock_cmpxchg(
                         Size::S32,
                         Location::GPR(value),
@@ -8173,6 +9312,577 @@ impl<'a> FuncGen<'a> {
                 self.assembler.emit_pop(Size::S64, Location::GPR(value));
                 self.machine.release_temp_gpr(compare);
             }
+            Operator::V128Const { value } => {
+                let tmp = self.machine.acquire_temp_simd().unwrap();
+                self.emit_v128_const(u128::from_le_bytes(*value.bytes()), tmp);
+                self.push_v128(tmp);
+                self.machine.release_temp_simd(tmp);
+            }
+            Operator::V128Load { ref memarg } => {
+                self.emit_simd_load(memarg, 16, |this, addr, dst| {
+                    this.assembler
+                        .emit_vmovdqu(XMMOrMemory::Memory(addr, 0), XMMOrMemory::XMM(dst));
+                })?;
+            }
+            Operator::V128Load8x8S { ref memarg } => {
+                self.emit_simd_load(memarg, 8, |this, addr, dst| {
+                    this.assembler
+                        .emit_vpmovsxbw(XMMOrMemory::Memory(addr, 0), dst);
+                })?;
+            }
+            Operator::V128Load8x8U { ref memarg } => {
+                self.emit_simd_load(memarg, 8, |this, addr, dst| {
+                    this.assembler
+                        .emit_vpmovzxbw(XMMOrMemory::Memory(addr, 0), dst);
+                })?;
+            }
+            Operator::V128Load16x4S { ref memarg } => {
+                self.emit_simd_load(memarg, 8, |this, addr, dst| {
+                    this.assembler
+                        .emit_vpmovsxwd(XMMOrMemory::Memory(addr, 0), dst);
+                })?;
+            }
+            Operator::V128Load16x4U { ref memarg } => {
+                self.emit_simd_load(memarg, 8, |this, addr, dst| {
+                    this.assembler
+                        .emit_vpmovzxwd(XMMOrMemory::Memory(addr, 0), dst);
+                })?;
+            }
+            Operator::V128Load32x2S { ref memarg } => {
+                self.emit_simd_load(memarg, 8, |this, addr, dst| {
+                    this.assembler
+                        .emit_vpmovsxdq(XMMOrMemory::Memory(addr, 0), dst);
+                })?;
+            }
+            Operator::V128Load32x2U { ref memarg } => {
+                self.emit_simd_load(memarg, 8, |this, addr, dst| {
+                    this.assembler
+                        .emit_vpmovzxdq(XMMOrMemory::Memory(addr, 0), dst);
+                })?;
+            }
+            Operator::V128Load8Splat { ref memarg } => {
+                self.emit_simd_load_splat(memarg, Size::S8)?
+            }
+            Operator::V128Load16Splat { ref memarg } => {
+                self.emit_simd_load_splat(memarg, Size::S16)?
+            }
+            Operator::V128Load32Splat { ref memarg } => {
+                self.emit_simd_load_splat(memarg, Size::S32)?
+            }
+            Operator::V128Load64Splat { ref memarg } => {
+                self.emit_simd_load_splat(memarg, Size::S64)?
+            }
+            Operator::V128Load32Zero { ref memarg } => {
+                self.emit_simd_load(memarg, 4, |this, addr, dst| {
+                    this.assembler.emit_mov(
+                        Size::S32,
+                        Location::Memory(addr, 0),
+                        Location::SIMD(dst),
+                    );
+                })?;
+            }
+            Operator::V128Load64Zero { ref memarg } => {
+                self.emit_simd_load(memarg, 8, |this, addr, dst| {
+                    this.assembler.emit_mov(
+                        Size::S64,
+                        Location::Memory(addr, 0),
+                        Location::SIMD(dst),
+                    );
+                })?;
+            }
+            Operator::V128Store { ref memarg } => {
+                let value = self.machine.acquire_temp_simd().unwrap();
+                self.pop_v128(value);
+                let target_addr = self.pop_value_released();
+                self.emit_memory_op(target_addr, memarg, false, 16, |this, addr| {
+                    this.assembler
+                        .emit_vmovdqu(XMMOrMemory::XMM(value), XMMOrMemory::Memory(addr, 0));
+                    Ok(())
+                })?;
+                self.machine.release_temp_simd(value);
+            }
+
+            Operator::I8x16Splat => self.emit_simd_splat(Size::S8, WpType::I32)?,
+            Operator::I16x8Splat => self.emit_simd_splat(Size::S16, WpType::I32)?,
+            Operator::I32x4Splat => self.emit_simd_splat(Size::S32, WpType::I32)?,
+            Operator::I64x2Splat => self.emit_simd_splat(Size::S64, WpType::I64)?,
+            Operator::F32x4Splat => self.emit_simd_splat(Size::S32, WpType::F32)?,
+            Operator::F64x2Splat => self.emit_simd_splat(Size::S64, WpType::F64)?,
+            Operator::I8x16ExtractLaneS { lane } => {
+                self.emit_simd_extract_lane(lane, Size::S8, true, WpType::I32)?
+            }
+            Operator::I8x16ExtractLaneU { lane } => {
+                self.emit_simd_extract_lane(lane, Size::S8, false, WpType::I32)?
+            }
+            Operator::I16x8ExtractLaneS { lane } => {
+                self.emit_simd_extract_lane(lane, Size::S16, true, WpType::I32)?
+            }
+            Operator::I16x8ExtractLaneU { lane } => {
+                self.emit_simd_extract_lane(lane, Size::S16, false, WpType::I32)?
+            }
+            Operator::I32x4ExtractLane { lane } => {
+                self.emit_simd_extract_lane(lane, Size::S32, false, WpType::I32)?
+            }
+            Operator::I64x2ExtractLane { lane } => {
+                self.emit_simd_extract_lane(lane, Size::S64, false, WpType::I64)?
+            }
+            Operator::F32x4ExtractLane { lane } => {
+                self.emit_simd_extract_lane(lane, Size::S32, false, WpType::F32)?
+            }
+            Operator::F64x2ExtractLane { lane } => {
+                self.emit_simd_extract_lane(lane, Size::S64, false, WpType::F64)?
+            }
+            Operator::I8x16ReplaceLane { lane } => {
+                self.emit_simd_replace_lane(lane, Size::S8, WpType::I32)?
+            }
+            Operator::I16x8ReplaceLane { lane } => {
+                self.emit_simd_replace_lane(lane, Size::S16, WpType::I32)?
+            }
+            Operator::I32x4ReplaceLane { lane } => {
+                self.emit_simd_replace_lane(lane, Size::S32, WpType::I32)?
+            }
+            Operator::I64x2ReplaceLane { lane } => {
+                self.emit_simd_replace_lane(lane, Size::S64, WpType::I64)?
+            }
+            Operator::F32x4ReplaceLane { lane } => {
+                self.emit_simd_replace_lane(lane, Size::S32, WpType::F32)?
+            }
+            Operator::F64x2ReplaceLane { lane } => {
+                self.emit_simd_replace_lane(lane, Size::S64, WpType::F64)?
+            }
+            Operator::I8x16Swizzle => {
+                self.emit_simd_binop(|this, a, b, dst| {
+                    // Indices out of range get the top bit set, which makes `pshufb` write 0.
+                    this.emit_v128_const(0x7070_7070_7070_7070_7070_7070_7070_7070, dst);
+                    this.assembler.emit_vpaddusb(b, XMMOrMemory::XMM(dst), dst);
+                    this.assembler.emit_vpshufb(a, XMMOrMemory::XMM(dst), dst);
+                });
+            }
+            Operator::I8x16Shuffle { lanes } => {
+                let mut mask_a = [0x80u8; 16];
+                let mut mask_b = [0x80u8; 16];
+                for (i, &lane) in lanes.iter().enumerate() {
+                    if lane < 16 {
+                        mask_a[i] = lane;
+                    } else {
+                        mask_b[i] = lane - 16;
+                    }
+                }
+                self.emit_simd_binop(|this, a, b, dst| {
+                    let mask = this.machine.acquire_temp_simd().unwrap();
+                    this.emit_v128_const(u128::from_le_bytes(mask_a), mask);
+                    this.assembler.emit_vpshufb(a, XMMOrMemory::XMM(mask), dst);
+                    this.emit_v128_const(u128::from_le_bytes(mask_b), mask);
+                    this.assembler.emit_vpshufb(b, XMMOrMemory::XMM(mask), b);
+                    this.assembler.emit_vpor(dst, XMMOrMemory::XMM(b), dst);
+                    this.machine.release_temp_simd(mask);
+                });
+            }
+
+            Operator::I8x16Eq => self.emit_simd_binop_avx(Assembler::emit_vpcmpeqb),
+            Operator::I8x16Ne => self.emit_simd_cmpop_not(|this, a, b, dst| {
+                this.assembler.emit_vpcmpeqb(a, XMMOrMemory::XMM(b), dst)
+            }),
+            Operator::I8x16LtS => self.emit_simd_binop_avx_rev(Assembler::emit_vpcmpgtb),
+            Operator::I8x16GtS => self.emit_simd_binop_avx(Assembler::emit_vpcmpgtb),
+            Operator::I8x16LeS => {
+                self.emit_simd_cmpop_minmax(Assembler::emit_vpminsb, Assembler::emit_vpcmpeqb)
+            }
+            Operator::I8x16GeS => {
+                self.emit_simd_cmpop_minmax(Assembler::emit_vpmaxsb, Assembler::emit_vpcmpeqb)
+            }
+            Operator::I8x16LeU => {
+                self.emit_simd_cmpop_minmax(Assembler::emit_vpminub, Assembler::emit_vpcmpeqb)
+            }
+            Operator::I8x16GeU => {
+                self.emit_simd_cmpop_minmax(Assembler::emit_vpmaxub, Assembler::emit_vpcmpeqb)
+            }
+            Operator::I8x16LtU => {
+                self.emit_simd_cmpop_minmax_not(Assembler::emit_vpmaxub, Assembler::emit_vpcmpeqb)
+            }
+            Operator::I8x16GtU => {
+                self.emit_simd_cmpop_minmax_not(Assembler::emit_vpminub, Assembler::emit_vpcmpeqb)
+            }
+            Operator::I16x8Eq => self.emit_simd_binop_avx(Assembler::emit_vpcmpeqw),
+            Operator::I16x8Ne => self.emit_simd_cmpop_not(|this, a, b, dst| {
+                this.assembler.emit_vpcmpeqw(a, XMMOrMemory::XMM(b), dst)
+            }),
+            Operator::I16x8LtS => self.emit_simd_binop_avx_rev(Assembler::emit_vpcmpgtw),
+            Operator::I16x8GtS => self.emit_simd_binop_avx(Assembler::emit_vpcmpgtw),
+            Operator::I16x8LeS => {
+                self.emit_simd_cmpop_minmax(Assembler::emit_vpminsw, Assembler::emit_vpcmpeqw)
+            }
+            Operator::I16x8GeS => {
+                self.emit_simd_cmpop_minmax(Assembler::emit_vpmaxsw, Assembler::emit_vpcmpeqw)
+            }
+            Operator::I16x8LeU => {
+                self.emit_simd_cmpop_minmax(Assembler::emit_vpminuw, Assembler::emit_vpcmpeqw)
+            }
+            Operator::I16x8GeU => {
+                self.emit_simd_cmpop_minmax(Assembler::emit_vpmaxuw, Assembler::emit_vpcmpeqw)
+            }
+            Operator::I16x8LtU => {
+                self.emit_simd_cmpop_minmax_not(Assembler::emit_vpmaxuw, Assembler::emit_vpcmpeqw)
+            }
+            Operator::I16x8GtU => {
+                self.emit_simd_cmpop_minmax_not(Assembler::emit_vpminuw, Assembler::emit_vpcmpeqw)
+            }
+            Operator::I32x4Eq => self.emit_simd_binop_avx(Assembler::emit_vpcmpeqd),
+            Operator::I32x4Ne => self.emit_simd_cmpop_not(|this, a, b, dst| {
+                this.assembler.emit_vpcmpeqd(a, XMMOrMemory::XMM(b), dst)
+            }),
+            Operator::I32x4LtS => self.emit_simd_binop_avx_rev(Assembler::emit_vpcmpgtd),
+            Operator::I32x4GtS => self.emit_simd_binop_avx(Assembler::emit_vpcmpgtd),
+            Operator::I32x4LeS => {
+                self.emit_simd_cmpop_minmax(Assembler::emit_vpminsd, Assembler::emit_vpcmpeqd)
+            }
+            Operator::I32x4GeS => {
+                self.emit_simd_cmpop_minmax(Assembler::emit_vpmaxsd, Assembler::emit_vpcmpeqd)
+            }
+            Operator::I32x4LeU => {
+                self.emit_simd_cmpop_minmax(Assembler::emit_vpminud, Assembler::emit_vpcmpeqd)
+            }
+            Operator::I32x4GeU => {
+                self.emit_simd_cmpop_minmax(Assembler::emit_vpmaxud, Assembler::emit_vpcmpeqd)
+            }
+            Operator::I32x4LtU => {
+                self.emit_simd_cmpop_minmax_not(Assembler::emit_vpmaxud, Assembler::emit_vpcmpeqd)
+            }
+            Operator::I32x4GtU => {
+                self.emit_simd_cmpop_minmax_not(Assembler::emit_vpminud, Assembler::emit_vpcmpeqd)
+            }
+            Operator::F32x4Eq => self.emit_simd_binop_avx(Assembler::emit_vcmpeqps),
+            Operator::F32x4Ne => self.emit_simd_binop_avx(Assembler::emit_vcmpneqps),
+            Operator::F32x4Lt => self.emit_simd_binop_avx(Assembler::emit_vcmpltps),
+            Operator::F32x4Gt => self.emit_simd_binop_avx_rev(Assembler::emit_vcmpltps),
+            Operator::F32x4Le => self.emit_simd_binop_avx(Assembler::emit_vcmpleps),
+            Operator::F32x4Ge => self.emit_simd_binop_avx_rev(Assembler::emit_vcmpleps),
+            Operator::F64x2Eq => self.emit_simd_binop_avx(Assembler::emit_vcmpeqpd),
+            Operator::F64x2Ne => self.emit_simd_binop_avx(Assembler::emit_vcmpneqpd),
+            Operator::F64x2Lt => self.emit_simd_binop_avx(Assembler::emit_vcmpltpd),
+            Operator::F64x2Gt => self.emit_simd_binop_avx_rev(Assembler::emit_vcmpltpd),
+            Operator::F64x2Le => self.emit_simd_binop_avx(Assembler::emit_vcmplepd),
+            Operator::F64x2Ge => self.emit_simd_binop_avx_rev(Assembler::emit_vcmplepd),
+
+            Operator::V128Not => self.emit_simd_unop(|this, src, dst| {
+                this.assembler
+                    .emit_vpcmpeqd(dst, XMMOrMemory::XMM(dst), dst);
+                this.assembler.emit_vpxor(src, XMMOrMemory::XMM(dst), dst);
+            }),
+            Operator::V128And => self.emit_simd_binop_avx(Assembler::emit_vpand),
+            Operator::V128AndNot => self.emit_simd_binop_avx_rev(Assembler::emit_vpandn),
+            Operator::V128Or => self.emit_simd_binop_avx(Assembler::emit_vpor),
+            Operator::V128Xor => self.emit_simd_binop_avx(Assembler::emit_vpxor),
+            Operator::V128Bitselect => {
+                let c = self.machine.acquire_temp_simd().unwrap();
+                let v2 = self.machine.acquire_temp_simd().unwrap();
+                let v1 = self.machine.acquire_temp_simd().unwrap();
+                self.pop_v128(c);
+                self.pop_v128(v2);
+                self.pop_v128(v1);
+                // v2 ^ ((v1 ^ v2) & c)
+                self.assembler.emit_vpxor(v1, XMMOrMemory::XMM(v2), v1);
+                self.assembler.emit_vpand(v1, XMMOrMemory::XMM(c), v1);
+                self.assembler.emit_vpxor(v1, XMMOrMemory::XMM(v2), v1);
+                self.push_v128(v1);
+                self.machine.release_temp_simd(v1);
+                self.machine.release_temp_simd(v2);
+                self.machine.release_temp_simd(c);
+            }
+
+            Operator::I8x16AnyTrue | Operator::I16x8AnyTrue | Operator::I32x4AnyTrue => {
+                self.emit_simd_any_true()
+            }
+            Operator::I8x16AllTrue => self.emit_simd_all_true(Assembler::emit_vpcmpeqb),
+            Operator::I16x8AllTrue => self.emit_simd_all_true(Assembler::emit_vpcmpeqw),
+            Operator::I32x4AllTrue => self.emit_simd_all_true(Assembler::emit_vpcmpeqd),
+            Operator::I8x16Bitmask => self.emit_simd_reduce(|this, src, tmp| {
+                this.assembler.emit_vpmovmskb(src, tmp);
+            }),
+            Operator::I16x8Bitmask => self.emit_simd_reduce(|this, src, tmp| {
+                // Narrow to bytes, with saturation the sign of each lane is kept.
+                this.assembler
+                    .emit_vpacksswb(src, XMMOrMemory::XMM(src), src);
+                this.assembler.emit_vpmovmskb(src, tmp);
+                this.assembler
+                    .emit_and(Size::S32, Location::Imm32(0xff), Location::GPR(tmp));
+            }),
+            Operator::I32x4Bitmask => self.emit_simd_reduce(|this, src, tmp| {
+                this.assembler.emit_vmovmskps(src, tmp);
+            }),
+
+            Operator::I8x16Abs => self.emit_simd_unop_avx(Assembler::emit_vpabsb),
+            Operator::I16x8Abs => self.emit_simd_unop_avx(Assembler::emit_vpabsw),
+            Operator::I32x4Abs => self.emit_simd_unop_avx(Assembler::emit_vpabsd),
+            Operator::I8x16Neg => self.emit_simd_int_neg(Assembler::emit_vpsubb),
+            Operator::I16x8Neg => self.emit_simd_int_neg(Assembler::emit_vpsubw),
+            Operator::I32x4Neg => self.emit_simd_int_neg(Assembler::emit_vpsubd),
+            Operator::I64x2Neg => self.emit_simd_int_neg(Assembler::emit_vpsubq),
+
+            Operator::I8x16Shl => self.emit_simd_shift(7, |this, src, count, dst| {
+                // There is no 8-bit shift, clear the bits that would be shifted into the next
+                // byte and shift 16-bit lanes instead.
+                let mask = this.machine.acquire_temp_simd().unwrap();
+                let tmp_count = this.machine.acquire_temp_simd().unwrap();
+                this.assembler
+                    .emit_vmovdqu(XMMOrMemory::XMM(count), XMMOrMemory::XMM(tmp_count));
+                this.emit_simd_shift_count_plus_8(tmp_count);
+                this.assembler
+                    .emit_vpcmpeqd(mask, XMMOrMemory::XMM(mask), mask);
+                this.assembler
+                    .emit_vpsrlw(mask, XMMOrMemory::XMM(tmp_count), mask);
+                this.assembler
+                    .emit_vpackuswb(mask, XMMOrMemory::XMM(mask), mask);
+                this.assembler.emit_vpand(src, XMMOrMemory::XMM(mask), dst);
+                this.assembler
+                    .emit_vpsllw(dst, XMMOrMemory::XMM(count), dst);
+                this.machine.release_temp_simd(tmp_count);
+                this.machine.release_temp_simd(mask);
+            }),
+            Operator::I8x16ShrS => {
+                self.emit_simd_shr_i8x16(Assembler::emit_vpsraw, Assembler::emit_vpacksswb)
+            }
+            Operator::I8x16ShrU => {
+                self.emit_simd_shr_i8x16(Assembler::emit_vpsrlw, Assembler::emit_vpackuswb)
+            }
+            Operator::I16x8Shl => self.emit_simd_shift(15, |this, src, count, dst| {
+                this.assembler
+                    .emit_vpsllw(src, XMMOrMemory::XMM(count), dst)
+            }),
+            Operator::I16x8ShrS => self.emit_simd_shift(15, |this, src, count, dst| {
+                this.assembler
+                    .emit_vpsraw(src, XMMOrMemory::XMM(count), dst)
+            }),
+            Operator::I16x8ShrU => self.emit_simd_shift(15, |this, src, count, dst| {
+                this.assembler
+                    .emit_vpsrlw(src, XMMOrMemory::XMM(count), dst)
+            }),
+            Operator::I32x4Shl => self.emit_simd_shift(31, |this, src, count, dst| {
+                this.assembler
+                    .emit_vpslld(src, XMMOrMemory::XMM(count), dst)
+            }),
+            Operator::I32x4ShrS => self.emit_simd_shift(31, |this, src, count, dst| {
+                this.assembler
+                    .emit_vpsrad(src, XMMOrMemory::XMM(count), dst)
+            }),
+            Operator::I32x4ShrU => self.emit_simd_shift(31, |this, src, count, dst| {
+                this.assembler
+                    .emit_vpsrld(src, XMMOrMemory::XMM(count), dst)
+            }),
+            Operator::I64x2Shl => self.emit_simd_shift(63, |this, src, count, dst| {
+                this.assembler
+                    .emit_vpsllq(src, XMMOrMemory::XMM(count), dst)
+            }),
+            Operator::I64x2ShrS => self.emit_simd_shift(63, |this, src, count, dst| {
+                // There is no 64-bit arithmetic shift, shift logically and sign extend with
+                // `(x ^ m) - m` where `m` is the shifted sign bit.
+                let mask = this.machine.acquire_temp_simd().unwrap();
+                this.emit_v128_const(0x8000_0000_0000_0000_8000_0000_0000_0000, mask);
+                this.assembler
+                    .emit_vpsrlq(mask, XMMOrMemory::XMM(count), mask);
+                this.assembler
+                    .emit_vpsrlq(src, XMMOrMemory::XMM(count), dst);
+                this.assembler.emit_vpxor(dst, XMMOrMemory::XMM(mask), dst);
+                this.assembler.emit_vpsubq(dst, XMMOrMemory::XMM(mask), dst);
+                this.machine.release_temp_simd(mask);
+            }),
+            Operator::I64x2ShrU => self.emit_simd_shift(63, |this, src, count, dst| {
+                this.assembler
+                    .emit_vpsrlq(src, XMMOrMemory::XMM(count), dst)
+            }),
+
+            Operator::I8x16Add => self.emit_simd_binop_avx(Assembler::emit_vpaddb),
+            Operator::I8x16AddSatS => self.emit_simd_binop_avx(Assembler::emit_vpaddsb),
+            Operator::I8x16AddSatU => self.emit_simd_binop_avx(Assembler::emit_vpaddusb),
+            Operator::I8x16Sub => self.emit_simd_binop_avx(Assembler::emit_vpsubb),
+            Operator::I8x16SubSatS => self.emit_simd_binop_avx(Assembler::emit_vpsubsb),
+            Operator::I8x16SubSatU => self.emit_simd_binop_avx(Assembler::emit_vpsubusb),
+            Operator::I8x16MinS => self.emit_simd_binop_avx(Assembler::emit_vpminsb),
+            Operator::I8x16MinU => self.emit_simd_binop_avx(Assembler::emit_vpminub),
+            Operator::I8x16MaxS => self.emit_simd_binop_avx(Assembler::emit_vpmaxsb),
+            Operator::I8x16MaxU => self.emit_simd_binop_avx(Assembler::emit_vpmaxub),
+            Operator::I8x16RoundingAverageU => self.emit_simd_binop_avx(Assembler::emit_vpavgb),
+            Operator::I16x8Add => self.emit_simd_binop_avx(Assembler::emit_vpaddw),
+            Operator::I16x8AddSatS => self.emit_simd_binop_avx(Assembler::emit_vpaddsw),
+            Operator::I16x8AddSatU => self.emit_simd_binop_avx(Assembler::emit_vpaddusw),
+            Operator::I16x8Sub => self.emit_simd_binop_avx(Assembler::emit_vpsubw),
+            Operator::I16x8SubSatS => self.emit_simd_binop_avx(Assembler::emit_vpsubsw),
+            Operator::I16x8SubSatU => self.emit_simd_binop_avx(Assembler::emit_vpsubusw),
+            Operator::I16x8Mul => self.emit_simd_binop_avx(Assembler::emit_vpmullw),
+            Operator::I16x8MinS => self.emit_simd_binop_avx(Assembler::emit_vpminsw),
+            Operator::I16x8MinU => self.emit_simd_binop_avx(Assembler::emit_vpminuw),
+            Operator::I16x8MaxS => self.emit_simd_binop_avx(Assembler::emit_vpmaxsw),
+            Operator::I16x8MaxU => self.emit_simd_binop_avx(Assembler::emit_vpmaxuw),
+            Operator::I16x8RoundingAverageU => self.emit_simd_binop_avx(Assembler::emit_vpavgw),
+            Operator::I32x4Add => self.emit_simd_binop_avx(Assembler::emit_vpaddd),
+            Operator::I32x4Sub => self.emit_simd_binop_avx(Assembler::emit_vpsubd),
+            Operator::I32x4Mul => self.emit_simd_binop_avx(Assembler::emit_vpmulld),
+            Operator::I32x4MinS => self.emit_simd_binop_avx(Assembler::emit_vpminsd),
+            Operator::I32x4MinU => self.emit_simd_binop_avx(Assembler::emit_vpminud),
+            Operator::I32x4MaxS => self.emit_simd_binop_avx(Assembler::emit_vpmaxsd),
+            Operator::I32x4MaxU => self.emit_simd_binop_avx(Assembler::emit_vpmaxud),
+            Operator::I32x4DotI16x8S => self.emit_simd_binop_avx(Assembler::emit_vpmaddwd),
+            Operator::I64x2Add => self.emit_simd_binop_avx(Assembler::emit_vpaddq),
+            Operator::I64x2Sub => self.emit_simd_binop_avx(Assembler::emit_vpsubq),
+            Operator::I64x2Mul => self.emit_simd_binop(|this, a, b, dst| {
+                // lo(a) * lo(b) + ((hi(a) * lo(b) + lo(a) * hi(b)) << 32)
+                let tmp1 = this.machine.acquire_temp_simd().unwrap();
+                let tmp2 = this.machine.acquire_temp_simd().unwrap();
+                this.assembler.emit_vpsrlq_imm8(a, 32, tmp1);
+                this.assembler
+                    .emit_vpmuludq(tmp1, XMMOrMemory::XMM(b), tmp1);
+                this.assembler.emit_vpsrlq_imm8(b, 32, tmp2);
+                this.assembler
+                    .emit_vpmuludq(tmp2, XMMOrMemory::XMM(a), tmp2);
+                this.assembler
+                    .emit_vpaddq(tmp1, XMMOrMemory::XMM(tmp2), tmp1);
+                this.assembler.emit_vpsllq_imm8(tmp1, 32, tmp1);
+                this.assembler.emit_vpmuludq(a, XMMOrMemory::XMM(b), dst);
+                this.assembler.emit_vpaddq(dst, XMMOrMemory::XMM(tmp1), dst);
+                this.machine.release_temp_simd(tmp2);
+                this.machine.release_temp_simd(tmp1);
+            }),
+
+            Operator::F32x4Ceil => {
+                self.emit_simd_fp_unop_avx(Size::S32, Assembler::emit_vroundps_ceil)
+            }
+            Operator::F32x4Floor => {
+                self.emit_simd_fp_unop_avx(Size::S32, Assembler::emit_vroundps_floor)
+            }
+            Operator::F32x4Trunc => {
+                self.emit_simd_fp_unop_avx(Size::S32, Assembler::emit_vroundps_trunc)
+            }
+            Operator::F32x4Nearest => {
+                self.emit_simd_fp_unop_avx(Size::S32, Assembler::emit_vroundps_nearest)
+            }
+            Operator::F64x2Ceil => {
+                self.emit_simd_fp_unop_avx(Size::S64, Assembler::emit_vroundpd_ceil)
+            }
+            Operator::F64x2Floor => {
+                self.emit_simd_fp_unop_avx(Size::S64, Assembler::emit_vroundpd_floor)
+            }
+            Operator::F64x2Trunc => {
+                self.emit_simd_fp_unop_avx(Size::S64, Assembler::emit_vroundpd_trunc)
+            }
+            Operator::F64x2Nearest => {
+                self.emit_simd_fp_unop_avx(Size::S64, Assembler::emit_vroundpd_nearest)
+            }
+            Operator::F32x4Abs => self.emit_simd_fp_sign(Size::S32, false),
+            Operator::F32x4Neg => self.emit_simd_fp_sign(Size::S32, true),
+            Operator::F64x2Abs => self.emit_simd_fp_sign(Size::S64, false),
+            Operator::F64x2Neg => self.emit_simd_fp_sign(Size::S64, true),
+            Operator::F32x4Sqrt => self.emit_simd_fp_unop_avx(Size::S32, Assembler::emit_vsqrtps),
+            Operator::F64x2Sqrt => self.emit_simd_fp_unop_avx(Size::S64, Assembler::emit_vsqrtpd),
+            Operator::F32x4Add => self.emit_simd_fp_binop_avx(Size::S32, Assembler::emit_vaddps),
+            Operator::F32x4Sub => self.emit_simd_fp_binop_avx(Size::S32, Assembler::emit_vsubps),
+            Operator::F32x4Mul => self.emit_simd_fp_binop_avx(Size::S32, Assembler::emit_vmulps),
+            Operator::F32x4Div => self.emit_simd_fp_binop_avx(Size::S32, Assembler::emit_vdivps),
+            Operator::F64x2Add => self.emit_simd_fp_binop_avx(Size::S64, Assembler::emit_vaddpd),
+            Operator::F64x2Sub => self.emit_simd_fp_binop_avx(Size::S64, Assembler::emit_vsubpd),
+            Operator::F64x2Mul => self.emit_simd_fp_binop_avx(Size::S64, Assembler::emit_vmulpd),
+            Operator::F64x2Div => self.emit_simd_fp_binop_avx(Size::S64, Assembler::emit_vdivpd),
+            Operator::F32x4Min => self.emit_simd_fp_minmax(Size::S32, false),
+            Operator::F32x4Max => self.emit_simd_fp_minmax(Size::S32, true),
+            Operator::F64x2Min => self.emit_simd_fp_minmax(Size::S64, false),
+            Operator::F64x2Max => self.emit_simd_fp_minmax(Size::S64, true),
+            // `minps`/`maxps` return the second operand unless the first one is strictly
+            // less/greater, which is exactly `pmin`/`pmax` with the operands swapped.
+            Operator::F32x4PMin => self.emit_simd_binop_avx_rev(Assembler::emit_vminps),
+            Operator::F32x4PMax => self.emit_simd_binop_avx_rev(Assembler::emit_vmaxps),
+            Operator::F64x2PMin => self.emit_simd_binop_avx_rev(Assembler::emit_vminpd),
+            Operator::F64x2PMax => self.emit_simd_binop_avx_rev(Assembler::emit_vmaxpd),
+
+            Operator::I32x4TruncSatF32x4S => self.emit_simd_unop(|this, src, dst| {
+                let tmp = this.machine.acquire_temp_simd().unwrap();
+                // Zero the NaN lanes.
+                this.assembler
+                    .emit_vcmpeqps(src, XMMOrMemory::XMM(src), tmp);
+                this.assembler.emit_vpand(src, XMMOrMemory::XMM(tmp), dst);
+                // The sign bit of `tmp` is now set for the non-negative lanes.
+                this.assembler.emit_vpxor(tmp, XMMOrMemory::XMM(dst), tmp);
+                // Overflowing lanes become 0x80000000.
+                this.assembler.emit_vcvttps2dq(XMMOrMemory::XMM(dst), dst);
+                // Turn that into 0x7FFFFFFF for the non-negative ones.
+                this.assembler.emit_vpand(tmp, XMMOrMemory::XMM(dst), tmp);
+                this.assembler.emit_vpsrad_imm8(tmp, 31, tmp);
+                this.assembler.emit_vpxor(dst, XMMOrMemory::XMM(tmp), dst);
+                this.machine.release_temp_simd(tmp);
+            }),
+            Operator::I32x4TruncSatF32x4U => self.emit_simd_unop(|this, src, dst| {
+                let tmp1 = this.machine.acquire_temp_simd().unwrap();
+                let tmp2 = this.machine.acquire_temp_simd().unwrap();
+                // NaN and negative lanes become 0.
+                this.assembler
+                    .emit_vpxor(tmp1, XMMOrMemory::XMM(tmp1), tmp1);
+                this.assembler.emit_vmaxps(src, XMMOrMemory::XMM(tmp1), dst);
+                // tmp1 = 2147483648.0
+                this.assembler
+                    .emit_vpcmpeqd(tmp1, XMMOrMemory::XMM(tmp1), tmp1);
+                this.assembler.emit_vpsrld_imm8(tmp1, 1, tmp1);
+                this.assembler.emit_vcvtdq2ps(XMMOrMemory::XMM(tmp1), tmp1);
+                // tmp2 = the part of the lanes above i32::MAX, converted with saturation.
+                this.assembler
+                    .emit_vsubps(dst, XMMOrMemory::XMM(tmp1), tmp2);
+                this.assembler
+                    .emit_vcmpleps(tmp1, XMMOrMemory::XMM(tmp2), tmp1);
+                this.assembler.emit_vcvttps2dq(XMMOrMemory::XMM(tmp2), tmp2);
+                this.assembler
+                    .emit_vpxor(tmp2, XMMOrMemory::XMM(tmp1), tmp2);
+                this.assembler
+                    .emit_vpxor(tmp1, XMMOrMemory::XMM(tmp1), tmp1);
+                this.assembler
+                    .emit_vpmaxsd(tmp2, XMMOrMemory::XMM(tmp1), tmp2);
+                // Lanes above i32::MAX become 0x80000000, add the rest to them.
+                this.assembler.emit_vcvttps2dq(XMMOrMemory::XMM(dst), dst);
+                this.assembler.emit_vpaddd(dst, XMMOrMemory::XMM(tmp2), dst);
+                this.machine.release_temp_simd(tmp2);
+                this.machine.release_temp_simd(tmp1);
+            }),
+            Operator::F32x4ConvertI32x4S => self.emit_simd_unop_avx(Assembler::emit_vcvtdq2ps),
+            Operator::F32x4ConvertI32x4U => self.emit_simd_unop(|this, src, dst| {
+                // Convert the low and the high 16 bits separately, both conversions are exact
+                // so only the final addition rounds.
+                let tmp = this.machine.acquire_temp_simd().unwrap();
+                this.assembler.emit_vpslld_imm8(src, 16, tmp);
+                this.assembler.emit_vpsrld_imm8(tmp, 16, tmp);
+                this.assembler.emit_vpsubd(src, XMMOrMemory::XMM(tmp), dst);
+                this.assembler.emit_vcvtdq2ps(XMMOrMemory::XMM(tmp), tmp);
+                // Halve the high part so that it is a positive i32, and double it afterwards.
+                this.assembler.emit_vpsrld_imm8(dst, 1, dst);
+                this.assembler.emit_vcvtdq2ps(XMMOrMemory::XMM(dst), dst);
+                this.assembler.emit_vaddps(dst, XMMOrMemory::XMM(dst), dst);
+                this.assembler.emit_vaddps(dst, XMMOrMemory::XMM(tmp), dst);
+                this.machine.release_temp_simd(tmp);
+            }),
+
+            Operator::I8x16NarrowI16x8S => self.emit_simd_binop_avx(Assembler::emit_vpacksswb),
+            Operator::I8x16NarrowI16x8U => self.emit_simd_binop_avx(Assembler::emit_vpackuswb),
+            Operator::I16x8NarrowI32x4S => self.emit_simd_binop_avx(Assembler::emit_vpackssdw),
+            Operator::I16x8NarrowI32x4U => self.emit_simd_binop_avx(Assembler::emit_vpackusdw),
+            Operator::I16x8WidenLowI8x16S => self.emit_simd_unop_avx(Assembler::emit_vpmovsxbw),
+            Operator::I16x8WidenLowI8x16U => self.emit_simd_unop_avx(Assembler::emit_vpmovzxbw),
+            Operator::I32x4WidenLowI16x8S => self.emit_simd_unop_avx(Assembler::emit_vpmovsxwd),
+            Operator::I32x4WidenLowI16x8U => self.emit_simd_unop_avx(Assembler::emit_vpmovzxwd),
+            Operator::I16x8WidenHighI8x16S => self.emit_simd_unop(|this, src, dst| {
+                this.assembler
+                    .emit_vpunpckhqdq(src, XMMOrMemory::XMM(src), dst);
+                this.assembler.emit_vpmovsxbw(XMMOrMemory::XMM(dst), dst);
+            }),
+            Operator::I16x8WidenHighI8x16U => self.emit_simd_unop(|this, src, dst| {
+                this.assembler
+                    .emit_vpunpckhqdq(src, XMMOrMemory::XMM(src), dst);
+                this.assembler.emit_vpmovzxbw(XMMOrMemory::XMM(dst), dst);
+            }),
+            Operator::I32x4WidenHighI16x8S => self.emit_simd_unop(|this, src, dst| {
+                this.assembler
+                    .emit_vpunpckhqdq(src, XMMOrMemory::XMM(src), dst);
+                this.assembler.emit_vpmovsxwd(XMMOrMemory::XMM(dst), dst);
+            }),
+            Operator::I32x4WidenHighI16x8U => self.emit_simd_unop(|this, src, dst| {
+                this.assembler
+                    .emit_vpunpckhqdq(src, XMMOrMemory::XMM(src), dst);
+                this.assembler.emit_vpmovzxwd(XMMOrMemory::XMM(dst), dst);
+            }),
             _ => {
                 return Err(CodegenError {
                     message: format!("not yet implemented: {:?}", op),
@@ -8195,6 +9905,11 @@ impl<'a> FuncGen<'a> {
         self.mark_address_with_trap_code(TrapCode::HeapAccessOutOfBounds);
         self.assembler.emit_ud2();
 
+        self.assembler
+            .emit_label(self.special_labels.unaligned_atomic);
+        self.mark_address_with_trap_code(TrapCode::UnalignedAtomic);
+        self.assembler.emit_ud2();
+
         self.assembler
             .emit_label(self.special_labels.table_access_oob);
         self.mark_address_with_trap_code(TrapCode::TableAccessOutOfBounds);
@@ -8209,6 +9924,29 @@ impl<'a> FuncGen<'a> {
         self.mark_address_with_trap_code(TrapCode::BadSignature);
         self.assembler.emit_ud2();
 
+        // The interrupt is consumed by the code that observes it.
+        self.assembler.emit_label(self.special_labels.interrupt);
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(
+                Machine::get_vmctx_reg(),
+                self.vmoffsets.vmctx_interrupts() as i32,
+            ),
+            Location::GPR(GPR::RAX),
+        );
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Imm32(0),
+            Location::Memory(GPR::RAX, self.vmoffsets.vminterrupts_interrupt() as i32),
+        );
+        self.mark_address_with_trap_code(TrapCode::Interrupt);
+        self.assembler.emit_ud2();
+
+        self.assembler
+            .emit_label(self.special_labels.stack_overflow);
+        self.mark_address_with_trap_code(TrapCode::StackOverflow);
+        self.assembler.emit_ud2();
+
         // Notify the assembler backend to generate necessary code at end of function.
         self.assembler.finalize_function();
 
@@ -8239,15 +9977,12 @@ impl<'a> FuncGen<'a> {
     }
 }
 
-fn type_to_wp_type(ty: Type) -> WpType {
-    match ty {
-        Type::I32 => WpType::I32,
-        Type::I64 => WpType::I64,
-        Type::F32 => WpType::F32,
-        Type::F64 => WpType::F64,
-        Type::V128 => WpType::V128,
-        Type::ExternRef => WpType::ExternRef,
-        Type::FuncRef => WpType::FuncRef, // TODO: FuncRef or Func?
+/// Converts the location of a `v128` value to an operand of an SSE/AVX instruction.
+fn v128_operand(loc: Location) -> XMMOrMemory {
+    match loc {
+        Location::SIMD(x) => XMMOrMemory::XMM(x),
+        Location::Memory(base, disp) => XMMOrMemory::Memory(base, disp),
+        _ => unreachable!("v128 value at {:?}", loc),
     }
 }
 
@@ -8304,9 +10039,21 @@ fn sort_call_movs(movs: &mut [(Location, GPR)]) {
 pub fn gen_std_trampoline(sig: &FunctionType) -> FunctionBody {
     let mut a = Assembler::new().unwrap();
 
+    // Multiple results are written by the callee to the return area passed as the first
+    // parameter, which is `args_rets` itself.
+    let has_return_area = sig.results().len() > 1;
+
+    // Each parameter takes one 8-byte slot, except `v128` which takes two.
+    let n_slots: usize = sig
+        .params()
+        .iter()
+        .map(|&ty| if ty == Type::V128 { 2 } else { 1 })
+        .sum::<usize>()
+        + has_return_area as usize;
+
     // Calculate stack offset.
     let mut stack_offset: u32 = 0;
-    for (i, _param) in sig.params().iter().enumerate() {
+    for i in 0..n_slots {
         if let Location::Memory(_, _) = Machine::get_param_location(1 + i) {
             stack_offset += 8;
         }
@@ -8344,8 +10091,17 @@ pub fn gen_std_trampoline(sig: &FunctionType) -> FunctionBody {
     // `callee_vmctx` is already in the first argument register, so no need to move.
     {
         let mut n_stack_args: usize = 0;
-        for (i, _param) in sig.params().iter().enumerate() {
-            let src_loc = Location::Memory(GPR::R14, (i * 16) as _); // args_rets[i]
+        let mut slots: Vec<Location> = Vec::with_capacity(n_slots);
+        if has_return_area {
+            slots.push(Location::GPR(GPR::R14));
+        }
+        for (i, ty) in sig.params().iter().enumerate() {
+            slots.push(Location::Memory(GPR::R14, (i * 16) as _)); // args_rets[i]
+            if *ty == Type::V128 {
+                slots.push(Location::Memory(GPR::R14, (i * 16 + 8) as _));
+            }
+        }
+        for (i, src_loc) in slots.into_iter().enumerate() {
             let dst_loc = Machine::get_param_location(1 + i);
 
             match dst_loc {
@@ -8379,7 +10135,14 @@ pub fn gen_std_trampoline(sig: &FunctionType) -> FunctionBody {
     );
 
     // Write return value.
-    if !sig.results().is_empty() {
+    if has_return_area {
+        // Already written by the callee.
+    } else if sig.results().first() == Some(&Type::V128) {
+        a.emit_vmovdqu(
+            XMMOrMemory::XMM(XMM::XMM0),
+            XMMOrMemory::Memory(GPR::R14, 0),
+        );
+    } else if !sig.results().is_empty() {
         a.emit_mov(
             Size::S64,
             Location::GPR(GPR::RAX),
@@ -8406,25 +10169,70 @@ pub fn gen_std_dynamic_import_trampoline(
 ) -> FunctionBody {
     let mut a = Assembler::new().unwrap();
 
+    // Multiple results are copied from the values array to the return area passed as the
+    // first parameter, whose address is saved after the values array.
+    let has_return_area = sig.results().len() > 1;
+    let values_size: usize = 16 * std::cmp::max(sig.params().len(), sig.results().len());
+
     // Allocate argument array.
-    let stack_offset: usize = 16 * std::cmp::max(sig.params().len(), sig.results().len()) + 8; // 16 bytes each + 8 bytes sysv call padding
+    let stack_offset: usize = values_size + 8 + if has_return_area { 16 } else { 0 }; // 16 bytes each + 8 bytes sysv call padding
     a.emit_sub(
         Size::S64,
         Location::Imm32(stack_offset as _),
         Location::GPR(GPR::RSP),
     );
 
+    if has_return_area {
+        a.emit_mov(
+            Size::S64,
+            Location::GPR(GPR::RSI),
+            Location::Memory(GPR::RSP, values_size as _),
+        );
+    }
+
     // Copy arguments.
     if !sig.params().is_empty() {
         let mut argalloc = ArgumentRegisterAllocator::default();
         argalloc.next(Type::I64).unwrap(); // skip VMContext
+        if has_return_area {
+            argalloc.next(Type::I64).unwrap(); // skip the return area
+        }
 
         let mut stack_param_count: usize = 0;
 
         for (i, ty) in sig.params().iter().enumerate() {
+            if *ty == Type::V128 {
+                match argalloc.next(*ty) {
+                    Some(X64Register::XMM(xmm)) => {
+                        a.emit_vmovdqu(
+                            XMMOrMemory::XMM(xmm),
+                            XMMOrMemory::Memory(GPR::RSP, (i * 16) as _),
+                        );
+                    }
+                    _ => {
+                        for half in 0..2 {
+                            a.emit_mov(
+                                Size::S64,
+                                Location::Memory(
+                                    GPR::RSP,
+                                    (stack_offset + 8 + stack_param_count * 8) as _,
+                                ),
+                                Location::GPR(GPR::RAX),
+                            );
+                            a.emit_mov(
+                                Size::S64,
+                                Location::GPR(GPR::RAX),
+                                Location::Memory(GPR::RSP, (i * 16 + half * 8) as _),
+                            );
+                            stack_param_count += 1;
+                        }
+                    }
+                }
+                continue;
+            }
             let source_loc = match argalloc.next(*ty) {
                 Some(X64Register::GPR(gpr)) => Location::GPR(gpr),
-                Some(X64Register::XMM(xmm)) => Location::XMM(xmm),
+                Some(X64Register::XMM(xmm)) => Location::SIMD(xmm),
                 None => {
                     a.emit_mov(
                         Size::S64,
@@ -8467,7 +10275,30 @@ pub fn gen_std_dynamic_import_trampoline(
     a.emit_call_location(Location::GPR(GPR::RAX));
 
     // Fetch return value.
-    if !sig.results().is_empty() {
+    if has_return_area {
+        a.emit_mov(
+            Size::S64,
+            Location::Memory(GPR::RSP, values_size as _),
+            Location::GPR(GPR::RCX),
+        );
+        for i in 0..sig.results().len() * 2 {
+            a.emit_mov(
+                Size::S64,
+                Location::Memory(GPR::RSP, (i * 8) as _),
+                Location::GPR(GPR::RAX),
+            );
+            a.emit_mov(
+                Size::S64,
+                Location::GPR(GPR::RAX),
+                Location::Memory(GPR::RCX, (i * 8) as _),
+            );
+        }
+    } else if sig.results().first() == Some(&Type::V128) {
+        a.emit_vmovdqu(
+            XMMOrMemory::Memory(GPR::RSP, 0),
+            XMMOrMemory::XMM(XMM::XMM0),
+        );
+    } else if !sig.results().is_empty() {
         assert_eq!(sig.results().len(), 1);
         a.emit_mov(
             Size::S64,
@@ -8507,19 +10338,32 @@ pub fn gen_import_call_trampoline(
     //
     // FIXME: This is only a workaround. We should fix singlepass to use the standard CC.
 
+    // The pointer to the return area for multiple results is passed like an integer
+    // parameter in front of the others.
+    let mut params: Vec<Type> = vec![];
+    if sig.results().len() > 1 {
+        params.push(Type::I64);
+    }
+    params.extend_from_slice(sig.params());
+
     // Translation is expensive, so only do it if needed.
-    if sig
-        .params()
+    if params
         .iter()
-        .any(|&x| x == Type::F32 || x == Type::F64)
+        .any(|&x| x == Type::F32 || x == Type::F64 || x == Type::V128)
     {
         let mut param_locations: Vec<Location> = vec![];
 
+        // Each parameter takes one 8-byte slot, except `v128` which takes two.
+        let n_slots: usize = params
+            .iter()
+            .map(|&ty| if ty == Type::V128 { 2 } else { 1 })
+            .sum();
+
         // Allocate stack space for arguments.
-        let stack_offset: i32 = if sig.params().len() > 5 {
+        let stack_offset: i32 = if n_slots > 5 {
             5 * 8
         } else {
-            (sig.params().len() as i32) * 8
+            (n_slots as i32) * 8
         };
         if stack_offset > 0 {
             a.emit_sub(
@@ -8530,7 +10374,7 @@ pub fn gen_import_call_trampoline(
         }
 
         // Store all arguments to the stack to prevent overwrite.
-        for i in 0..sig.params().len() {
+        for i in 0..n_slots {
             let loc = match i {
                 0..=4 => {
                     static PARAM_REGS: &[GPR] = &[GPR::RSI, GPR::RDX, GPR::RCX, GPR::R8, GPR::R9];
@@ -8547,11 +10391,36 @@ pub fn gen_import_call_trampoline(
         let mut argalloc = ArgumentRegisterAllocator::default();
         argalloc.next(Type::I64).unwrap(); // skip VMContext
         let mut caller_stack_offset: i32 = 0;
-        for (i, ty) in sig.params().iter().enumerate() {
-            let prev_loc = param_locations[i];
+        let mut slot: usize = 0;
+        for ty in params.iter() {
+            let prev_loc = param_locations[slot];
+            slot += 1;
+            if *ty == Type::V128 {
+                let high_loc = param_locations[slot];
+                slot += 1;
+                match argalloc.next(*ty) {
+                    Some(X64Register::XMM(xmm)) => {
+                        a.emit_mov(Size::S64, prev_loc, Location::SIMD(xmm));
+                        a.emit_mov(Size::S64, high_loc, Location::GPR(GPR::RAX));
+                        a.emit_vpinsrq(xmm, GPR::RAX, 1, xmm);
+                    }
+                    _ => {
+                        for loc in [prev_loc, high_loc].iter() {
+                            a.emit_mov(Size::S64, *loc, Location::GPR(GPR::RAX));
+                            a.emit_mov(
+                                Size::S64,
+                                Location::GPR(GPR::RAX),
+                                Location::Memory(GPR::RSP, stack_offset + 8 + caller_stack_offset),
+                            );
+                            caller_stack_offset += 8;
+                        }
+                    }
+                }
+                continue;
+            }
             let target = match argalloc.next(*ty) {
                 Some(X64Register::GPR(gpr)) => Location::GPR(gpr),
-                Some(X64Register::XMM(xmm)) => Location::XMM(xmm),
+                Some(X64Register::XMM(xmm)) => Location::SIMD(xmm),
                 None => {
                     // No register can be allocated. Put this argument on the stack.
                     //
@@ -8606,42 +10475,3 @@ pub fn gen_import_call_trampoline(
         relocations: vec![],
     }
 }
-
-// Constants for the bounds of truncation operations. These are the least or
-// greatest exact floats in either f32 or f64 representation less-than (for
-// least) or greater-than (for greatest) the i32 or i64 or u32 or u64
-// min (for least) or max (for greatest), when rounding towards zero.
-
-/// Greatest Exact Float (32 bits) less-than i32::MIN when rounding towards zero.
-const GEF32_LT_I32_MIN: f32 = -2147483904.0;
-/// Least Exact Float (32 bits) greater-than i32::MAX when rounding towards zero.
-const LEF32_GT_I32_MAX: f32 = 2147483648.0;
-/// Greatest Exact Float (32 bits) less-than i64::MIN when rounding towards zero.
-const GEF32_LT_I64_MIN: f32 = -9223373136366403584.0;
-/// Least Exact Float (32 bits) greater-than i64::MAX when rounding towards zero.
-const LEF32_GT_I64_MAX: f32 = 9223372036854775808.0;
-/// Greatest Exact Float (32 bits) less-than u32::MIN when rounding towards zero.
-const GEF32_LT_U32_MIN: f32 = -1.0;
-/// Least Exact Float (32 bits) greater-than u32::MAX when rounding towards zero.
-const LEF32_GT_U32_MAX: f32 = 4294967296.0;
-/// Greatest Exact Float (32 bits) less-than u64::MIN when rounding towards zero.
-const GEF32_LT_U64_MIN: f32 = -1.0;
-/// Least Exact Float (32 bits) greater-than u64::MAX when rounding towards zero.
-const LEF32_GT_U64_MAX: f32 = 18446744073709551616.0;
-
-/// Greatest Exact Float (64 bits) less-than i32::MIN when rounding towards zero.
-const GEF64_LT_I32_MIN: f64 = -2147483649.0;
-/// Least Exact Float (64 bits) greater-than i32::MAX when rounding towards zero.
-const LEF64_GT_I32_MAX: f64 = 2147483648.0;
-/// Greatest Exact Float (64 bits) less-than i64::MIN when rounding towards zero.
-const GEF64_LT_I64_MIN: f64 = -9223372036854777856.0;
-/// Least Exact Float (64 bits) greater-than i64::MAX when rounding towards zero.
-const LEF64_GT_I64_MAX: f64 = 9223372036854775808.0;
-/// Greatest Exact Float (64 bits) less-than u32::MIN when rounding towards zero.
-const GEF64_LT_U32_MIN: f64 = -1.0;
-/// Least Exact Float (64 bits) greater-than u32::MAX when rounding towards zero.
-const LEF64_GT_U32_MAX: f64 = 4294967296.0;
-/// Greatest Exact Float (64 bits) less-than u64::MIN when rounding towards zero.
-const GEF64_LT_U64_MIN: f64 = -1.0;
-/// Least Exact Float (64 bits) greater-than u64::MAX when rounding towards zero.
-const LEF64_GT_U64_MAX: f64 = 18446744073709551616.0;
@@ -2,13 +2,12 @@
 // Allow unused imports while developing.
 #![allow(unused_imports, dead_code)]
 
-use crate::codegen_x64::{
-    gen_import_call_trampoline, gen_std_dynamic_import_trampoline, gen_std_trampoline,
-    CodegenError, FuncGen,
-};
+use crate::common_decl::CodegenError;
 use crate::config::Singlepass;
+use crate::{codegen_arm64, codegen_x64};
 use rayon::prelude::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use std::sync::Arc;
+use wasmer_compiler::wasmparser::{Operator, Type as WpType};
 use wasmer_compiler::TrapInformation;
 use wasmer_compiler::{
     Architecture, CompileModuleInfo, CompilerConfig, MiddlewareBinaryReader, ModuleMiddlewareChain,
@@ -17,8 +16,10 @@ use wasmer_compiler::{
 use wasmer_compiler::{Compilation, CompileError, CompiledFunction, Compiler, SectionIndex};
 use wasmer_compiler::{FunctionBody, FunctionBodyData};
 use wasmer_types::entity::{EntityRef, PrimaryMap};
-use wasmer_types::{FunctionIndex, FunctionType, LocalFunctionIndex, MemoryIndex, TableIndex};
-use wasmer_vm::{ModuleInfo, TrapCode, VMOffsets};
+use wasmer_types::{
+    FunctionIndex, FunctionType, LocalFunctionIndex, MemoryIndex, TableIndex, Type,
+};
+use wasmer_vm::{MemoryStyle, ModuleInfo, TableStyle, TrapCode, VMOffsets};
 
 /// A compiler that compiles a WebAssembly module with Singlepass.
 /// It does the compilation in one pass
@@ -39,6 +40,10 @@ impl SinglepassCompiler {
 }
 
 impl Compiler for SinglepassCompiler {
+    fn fingerprint(&self) -> Option<String> {
+        self.config().fingerprint()
+    }
+
     /// Compile the module using Singlepass, producing a compilation result with
     /// associated relocations.
     fn compile_module(
@@ -53,12 +58,11 @@ impl Compiler for SinglepassCompiler {
                 OperatingSystem::Windows.to_string(),
             ));
         }
-        if let Architecture::X86_32(arch) = target.triple().architecture {
-            return Err(CompileError::UnsupportedTarget(arch.to_string()));
-        }
-        if compile_info.features.multi_value {
-            return Err(CompileError::UnsupportedFeature("multivalue".to_string()));
-        }
+        let arch = match target.triple().architecture {
+            Architecture::X86_64 => TargetArch::X64,
+            Architecture::Aarch64(_) => TargetArch::ARM64,
+            arch => return Err(CompileError::UnsupportedTarget(arch.to_string())),
+        };
         let memory_styles = &compile_info.memory_styles;
         let table_styles = &compile_info.table_styles;
         let mut module = (*compile_info.module).clone();
@@ -66,14 +70,51 @@ impl Compiler for SinglepassCompiler {
         compile_info.module = Arc::new(module);
         let vmoffsets = VMOffsets::new(8, &compile_info.module);
         let module = &compile_info.module;
+        if module.memories.values().any(|memory| memory.is_64()) {
+            return Err(CompileError::UnsupportedFeature(
+                "64-bit memories in singlepass".to_string(),
+            ));
+        }
+        if module.memories.len() > 1 {
+            return Err(CompileError::UnsupportedFeature(
+                "multiple memories in singlepass".to_string(),
+            ));
+        }
+        if compile_info.features.tail_call {
+            reject_tail_calls(&function_body_inputs)?;
+        }
+        if arch == TargetArch::ARM64 {
+            // The ARM64 backend passes at most one value back to the caller, in a register.
+            if let Some(sig) = module.signatures.values().find(|sig| {
+                sig.results().len() > 1
+                    || sig
+                        .params()
+                        .iter()
+                        .chain(sig.results())
+                        .any(|&ty| ty == Type::V128)
+            }) {
+                return Err(CompileError::UnsupportedFeature(format!(
+                    "signature {} in singlepass on ARM64",
+                    sig
+                )));
+            }
+        }
         let import_trampolines: PrimaryMap<SectionIndex, _> = (0..module.num_imported_functions)
             .map(FunctionIndex::new)
             .collect::<Vec<_>>()
             .into_par_iter()
             .map(|i| {
-                gen_import_call_trampoline(&vmoffsets, i, &module.signatures[module.functions[i]])
+                let sig = &module.signatures[module.functions[i]];
+                match arch {
+                    TargetArch::X64 => {
+                        Ok(codegen_x64::gen_import_call_trampoline(&vmoffsets, i, sig))
+                    }
+                    TargetArch::ARM64 => {
+                        codegen_arm64::gen_import_call_trampoline(&vmoffsets, i, sig)
+                    }
+                }
             })
-            .collect::<Vec<_>>()
+            .collect::<Result<Vec<_>, CompileError>>()?
             .into_iter()
             .collect();
         let functions = function_body_inputs
@@ -99,24 +140,30 @@ impl Compiler for SinglepassCompiler {
                     }
                 }
 
-                let mut generator = FuncGen::new(
-                    module,
-                    &self.config,
-                    &vmoffsets,
-                    &memory_styles,
-                    &table_styles,
-                    *i,
-                    &locals,
-                )
-                .map_err(to_compile_error)?;
-
-                while generator.has_control_frames() {
-                    generator.set_srcloc(reader.original_position() as u32);
-                    let op = reader.read_operator()?;
-                    generator.feed_operator(op).map_err(to_compile_error)?;
+                match arch {
+                    TargetArch::X64 => compile_function::<codegen_x64::FuncGen>(
+                        module,
+                        &self.config,
+                        &vmoffsets,
+                        memory_styles,
+                        table_styles,
+                        *i,
+                        &locals,
+                        &mut reader,
+                        input,
+                    ),
+                    TargetArch::ARM64 => compile_function::<codegen_arm64::FuncGen>(
+                        module,
+                        &self.config,
+                        &vmoffsets,
+                        memory_styles,
+                        table_styles,
+                        *i,
+                        &locals,
+                        &mut reader,
+                        input,
+                    ),
                 }
-
-                Ok(generator.finalize(&input))
             })
             .collect::<Result<Vec<CompiledFunction>, CompileError>>()?
             .into_iter()
@@ -127,8 +174,10 @@ impl Compiler for SinglepassCompiler {
             .values()
             .collect::<Vec<_>>()
             .par_iter()
-            .cloned()
-            .map(gen_std_trampoline)
+            .map(|sig| match arch {
+                TargetArch::X64 => codegen_x64::gen_std_trampoline(sig),
+                TargetArch::ARM64 => codegen_arm64::gen_std_trampoline(sig),
+            })
             .collect::<Vec<_>>()
             .into_iter()
             .collect::<PrimaryMap<_, _>>();
@@ -137,8 +186,15 @@ impl Compiler for SinglepassCompiler {
             .imported_function_types()
             .collect::<Vec<_>>()
             .par_iter()
-            .map(|func_type| gen_std_dynamic_import_trampoline(&vmoffsets, &func_type))
-            .collect::<Vec<_>>()
+            .map(|func_type| match arch {
+                TargetArch::X64 => Ok(codegen_x64::gen_std_dynamic_import_trampoline(
+                    &vmoffsets, func_type,
+                )),
+                TargetArch::ARM64 => {
+                    codegen_arm64::gen_std_dynamic_import_trampoline(&vmoffsets, func_type)
+                }
+            })
+            .collect::<Result<Vec<_>, CompileError>>()?
             .into_iter()
             .collect::<PrimaryMap<FunctionIndex, FunctionBody>>();
 
@@ -152,6 +208,104 @@ impl Compiler for SinglepassCompiler {
     }
 }
 
+/// The instruction sets Singlepass generates code for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TargetArch {
+    X64,
+    ARM64,
+}
+
+/// The interface of the per-function code generators of each architecture.
+trait FunctionGenerator<'a>: Sized {
+    fn new(
+        module: &'a ModuleInfo,
+        config: &'a Singlepass,
+        vmoffsets: &'a VMOffsets,
+        memory_styles: &'a PrimaryMap<MemoryIndex, MemoryStyle>,
+        table_styles: &'a PrimaryMap<TableIndex, TableStyle>,
+        local_func_index: LocalFunctionIndex,
+        local_types_excluding_arguments: &[WpType],
+    ) -> Result<Self, CodegenError>;
+    fn has_control_frames(&self) -> bool;
+    fn set_srcloc(&mut self, offset: u32);
+    fn feed_operator(&mut self, op: Operator) -> Result<(), CodegenError>;
+    fn finalize(self, data: &FunctionBodyData) -> CompiledFunction;
+}
+
+macro_rules! impl_function_generator {
+    ($func_gen:ty) => {
+        impl<'a> FunctionGenerator<'a> for $func_gen {
+            fn new(
+                module: &'a ModuleInfo,
+                config: &'a Singlepass,
+                vmoffsets: &'a VMOffsets,
+                memory_styles: &'a PrimaryMap<MemoryIndex, MemoryStyle>,
+                table_styles: &'a PrimaryMap<TableIndex, TableStyle>,
+                local_func_index: LocalFunctionIndex,
+                local_types_excluding_arguments: &[WpType],
+            ) -> Result<Self, CodegenError> {
+                <$func_gen>::new(
+                    module,
+                    config,
+                    vmoffsets,
+                    memory_styles,
+                    table_styles,
+                    local_func_index,
+                    local_types_excluding_arguments,
+                )
+            }
+            fn has_control_frames(&self) -> bool {
+                <$func_gen>::has_control_frames(self)
+            }
+            fn set_srcloc(&mut self, offset: u32) {
+                <$func_gen>::set_srcloc(self, offset)
+            }
+            fn feed_operator(&mut self, op: Operator) -> Result<(), CodegenError> {
+                <$func_gen>::feed_operator(self, op)
+            }
+            fn finalize(self, data: &FunctionBodyData) -> CompiledFunction {
+                <$func_gen>::finalize(self, data)
+            }
+        }
+    };
+}
+
+impl_function_generator!(codegen_x64::FuncGen<'a>);
+impl_function_generator!(codegen_arm64::FuncGen<'a>);
+
+/// Compiles the body of a function with the code generator `G`.
+#[allow(clippy::too_many_arguments)]
+fn compile_function<'a, G: FunctionGenerator<'a>>(
+    module: &'a ModuleInfo,
+    config: &'a Singlepass,
+    vmoffsets: &'a VMOffsets,
+    memory_styles: &'a PrimaryMap<MemoryIndex, MemoryStyle>,
+    table_styles: &'a PrimaryMap<TableIndex, TableStyle>,
+    local_func_index: LocalFunctionIndex,
+    locals: &[WpType],
+    reader: &mut MiddlewareBinaryReader,
+    input: &FunctionBodyData,
+) -> Result<CompiledFunction, CompileError> {
+    let mut generator = G::new(
+        module,
+        config,
+        vmoffsets,
+        memory_styles,
+        table_styles,
+        local_func_index,
+        locals,
+    )
+    .map_err(to_compile_error)?;
+
+    while generator.has_control_frames() {
+        generator.set_srcloc(reader.original_position() as u32);
+        let op = reader.read_operator()?;
+        generator.feed_operator(op).map_err(to_compile_error)?;
+    }
+
+    Ok(generator.finalize(input))
+}
+
 trait ToCompileError {
     fn to_compile_error(self) -> CompileError;
 }
@@ -166,12 +320,40 @@ fn to_compile_error<T: ToCompileError>(x: T) -> CompileError {
     x.to_compile_error()
 }
 
+/// Reject the functions that use `return_call` or `return_call_indirect`,
+/// which singlepass doesn't support.
+fn reject_tail_calls(
+    function_body_inputs: &PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+) -> Result<(), CompileError> {
+    for input in function_body_inputs.values() {
+        let mut reader = MiddlewareBinaryReader::new_with_offset(input.data, input.module_offset);
+        let num_locals = reader.read_local_count()?;
+        for _ in 0..num_locals {
+            reader.read_local_decl()?;
+        }
+        while !reader.eof() {
+            if let Operator::ReturnCall { .. } | Operator::ReturnCallIndirect { .. } =
+                reader.read_operator()?
+            {
+                return Err(CompileError::UnsupportedFeature(
+                    "tail calls in singlepass".to_string(),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use object::{Object, ObjectSection, ObjectSymbol};
     use std::str::FromStr;
     use target_lexicon::triple;
-    use wasmer_compiler::{CpuFeature, Features, Triple};
+    use wasmer_compiler::{
+        CpuFeature, Features, ModuleEnvironment, RelocationTarget, Symbol, SymbolRegistry, Triple,
+    };
+    use wasmer_types::Pages;
     use wasmer_vm::{MemoryStyle, TableStyle};
 
     fn dummy_compilation_ingredients<'a>() -> (
@@ -221,4 +403,207 @@ mod tests {
             error => panic!("Unexpected error: {:?}", error),
         };
     }
+
+    struct TestSymbolRegistry;
+
+    impl SymbolRegistry for TestSymbolRegistry {
+        fn symbol_to_name(&self, symbol: Symbol) -> String {
+            match symbol {
+                Symbol::LocalFunction(index) => format!("function_{}", index.index()),
+                Symbol::Section(index) => format!("section_{}", index.index()),
+                Symbol::FunctionCallTrampoline(index) => format!("trampoline_{}", index.index()),
+                Symbol::DynamicFunctionTrampoline(index) => {
+                    format!("dynamic_trampoline_{}", index.index())
+                }
+            }
+        }
+
+        fn name_to_symbol(&self, _name: &str) -> Option<Symbol> {
+            unimplemented!()
+        }
+    }
+
+    /// Compiles `wat` for `target` and returns the compilation and the module info.
+    fn compile_wat(
+        target: &Target,
+        wat: &str,
+        memory_style: MemoryStyle,
+    ) -> Result<(Compilation, CompileModuleInfo), CompileError> {
+        let wasm = wat::parse_str(wat).unwrap();
+        let translation = ModuleEnvironment::new().translate(&wasm).unwrap();
+        let mut compile_info = CompileModuleInfo {
+            features: Features::new(),
+            memory_styles: translation
+                .module
+                .memories
+                .values()
+                .map(|_| memory_style.clone())
+                .collect(),
+            table_styles: translation
+                .module
+                .tables
+                .values()
+                .map(|_| TableStyle::CallerChecksSignature)
+                .collect(),
+            module: Arc::new(translation.module),
+        };
+        let compiler = SinglepassCompiler::new(Singlepass::default());
+        let compilation = compiler.compile_module(
+            target,
+            &mut compile_info,
+            translation.module_translation_state.as_ref().unwrap(),
+            translation.function_body_inputs,
+        )?;
+        Ok((compilation, compile_info))
+    }
+
+    const ARM64_TEST_MODULE: &str = r#"
+        (module
+          (type $binop (func (param i32 i32) (result i32)))
+          (import "env" "log" (func $log (param f64 i32 f32)))
+          (memory 1)
+          (table 2 funcref)
+          (elem (i32.const 0) $add $div)
+          (func $add (type $binop) (i32.add (local.get 0) (local.get 1)))
+          (func $div (type $binop) (i32.div_s (local.get 0) (local.get 1)))
+          (func $sum (param $n i32) (result i64) (local $acc i64)
+            (block $done
+              (loop $next
+                (br_if $done (i32.eqz (local.get $n)))
+                (local.set $acc
+                  (i64.add (local.get $acc) (i64.load32_u (local.get $n))))
+                (local.set $n (i32.sub (local.get $n) (i32.const 4)))
+                (br $next)))
+            (local.get $acc))
+          (func $dispatch (param i32 i32 i32) (result i32)
+            (call $log (f64.const 1.5) (local.get 0) (f32.const 2.5))
+            (block $b1
+              (block $b0
+                (br_table $b0 $b1 (local.get 2)))
+              (return (call_indirect (type $binop) (local.get 0) (local.get 1) (i32.const 1))))
+            (i32.trunc_f64_s
+              (f64.sqrt (f64.convert_i32_u (memory.grow (local.get 0))))))
+          (export "dispatch" (func $dispatch)))
+    "#;
+
+    #[test]
+    fn cross_compiles_to_aarch64_objects() {
+        let target = Target::new(triple!("aarch64-unknown-linux-gnu"), CpuFeature::set());
+        let memory_style = MemoryStyle::Dynamic {
+            offset_guard_size: 0,
+        };
+        let (compilation, compile_info) =
+            compile_wat(&target, ARM64_TEST_MODULE, memory_style).unwrap();
+        let module = &compile_info.module;
+
+        // Every call is relocated through a literal pool entry.
+        for relocations in compilation.get_relocations().values() {
+            for r in relocations {
+                assert_eq!(r.kind, wasmer_compiler::RelocationKind::Abs8);
+                assert_eq!(r.offset % 4, 0);
+            }
+        }
+        let dispatch = LocalFunctionIndex::new(3);
+        let dispatch_relocations = &compilation.get_relocations()[dispatch];
+        assert_eq!(dispatch_relocations.len(), 1);
+        assert_eq!(
+            dispatch_relocations[0].reloc_target,
+            RelocationTarget::CustomSection(SectionIndex::new(0))
+        );
+
+        // Instructions are all 4 bytes long.
+        for body in compilation.get_function_bodies().values() {
+            assert_eq!(body.body.len() % 4, 0);
+        }
+        // The division checks its operands.
+        let div = &compilation.get_frame_info()[LocalFunctionIndex::new(1)];
+        for code in &[TrapCode::IntegerDivisionByZero, TrapCode::IntegerOverflow] {
+            assert!(div.traps.iter().any(|trap| trap.trap_code == *code));
+        }
+
+        let triple = target.triple();
+        let mut obj = wasmer_object::get_object_for_target(triple).unwrap();
+        wasmer_object::emit_compilation(&mut obj, compilation, &TestSymbolRegistry, triple)
+            .unwrap();
+        let bytes = obj.write().unwrap();
+
+        let file = object::File::parse(&bytes).unwrap();
+        assert_eq!(file.architecture(), object::Architecture::Aarch64);
+        let symbols: Vec<_> = file
+            .symbols()
+            .filter_map(|symbol| symbol.name().ok().map(str::to_string))
+            .collect();
+        let expected = (0..module.functions.len() - module.num_imported_functions)
+            .map(|i| format!("function_{}", i))
+            .chain((0..module.num_imported_functions).map(|i| format!("section_{}", i)))
+            .chain((0..module.signatures.len()).map(|i| format!("trampoline_{}", i)))
+            .chain((0..module.num_imported_functions).map(|i| format!("dynamic_trampoline_{}", i)));
+        for name in expected {
+            assert!(symbols.contains(&name), "missing symbol {}", name);
+        }
+
+        let text = file.section_by_name(".text").unwrap();
+        let relocations: Vec<_> = text.relocations().collect();
+        assert_eq!(relocations.len(), 1);
+        let (_, relocation) = &relocations[0];
+        assert_eq!(relocation.kind(), object::RelocationKind::Absolute);
+        assert_eq!(relocation.size(), 64);
+    }
+
+    #[test]
+    fn aarch64_rejects_unsupported_signatures() {
+        let target = Target::new(triple!("aarch64-unknown-linux-gnu"), CpuFeature::set());
+        let result = compile_wat(
+            &target,
+            "(module (func (result i32 i32) (i32.const 0) (i32.const 1)))",
+            MemoryStyle::Static {
+                bound: Pages(0x10000),
+                offset_guard_size: 0x8000_0000,
+            },
+        );
+        match result {
+            Err(CompileError::UnsupportedFeature(message)) => assert!(message.contains("ARM64")),
+            Err(error) => panic!("Unexpected error: {:?}", error),
+            Ok(_) => panic!("Unexpected success"),
+        }
+    }
+
+    #[test]
+    fn rejects_64bit_memories() {
+        let target = Target::new(triple!("x86_64-unknown-linux-gnu"), CpuFeature::set());
+        let result = compile_wat(
+            &target,
+            "(module (memory i64 1) (func (result i64) (memory.size)))",
+            MemoryStyle::Dynamic {
+                offset_guard_size: 0x1_0000,
+            },
+        );
+        match result {
+            Err(CompileError::UnsupportedFeature(message)) => {
+                assert!(message.contains("64-bit memories"))
+            }
+            Err(error) => panic!("Unexpected error: {:?}", error),
+            Ok(_) => panic!("Unexpected success"),
+        }
+    }
+
+    #[test]
+    fn rejects_multiple_memories() {
+        let target = Target::new(triple!("x86_64-unknown-linux-gnu"), CpuFeature::set());
+        let result = compile_wat(
+            &target,
+            "(module (memory 1) (memory 1) (func (result i32) (memory.size 1)))",
+            MemoryStyle::Static {
+                bound: Pages(0x10000),
+                offset_guard_size: 0x8000_0000,
+            },
+        );
+        match result {
+            Err(CompileError::UnsupportedFeature(message)) => {
+                assert!(message.contains("multiple memories"))
+            }
+            Err(error) => panic!("Unexpected error: {:?}", error),
+            Ok(_) => panic!("Unexpected success"),
+        }
+    }
 }
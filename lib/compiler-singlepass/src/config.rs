@@ -3,7 +3,9 @@
 
 use crate::compiler::SinglepassCompiler;
 use std::sync::Arc;
-use wasmer_compiler::{Compiler, CompilerConfig, CpuFeature, ModuleMiddleware, Target};
+use wasmer_compiler::{
+    Compiler, CompilerConfig, CpuFeature, ModuleMiddleware, ModuleMiddlewareChain, Target,
+};
 use wasmer_types::Features;
 
 #[derive(Debug, Clone)]
@@ -28,7 +30,10 @@ impl Singlepass {
     /// Enable stack check.
     ///
     /// When enabled, an explicit stack depth check will be performed on entry
-    /// to each function to prevent stack overflow.
+    /// to each function to prevent stack overflow. The stack pointer is
+    /// compared with the stack limit of the `VMContext`, set from the maximum
+    /// stack size of the `Store`, and the function traps with
+    /// `TrapCode::StackOverflow` when it is below.
     ///
     /// Note that this doesn't guarantee deterministic execution across
     /// different platforms.
@@ -60,15 +65,22 @@ impl CompilerConfig for Singlepass {
 
     /// Gets the default features for this compiler in the given target
     fn default_features_for_target(&self, _target: &Target) -> Features {
-        let mut features = Features::default();
-        features.multi_value(false);
-        features
+        Features::default()
     }
 
     /// Pushes a middleware onto the back of the middleware chain.
     fn push_middleware(&mut self, middleware: Arc<dyn ModuleMiddleware>) {
         self.middlewares.push(middleware);
     }
+
+    fn fingerprint(&self) -> Option<String> {
+        Some(format!(
+            "singlepass(nan_canonicalization={}, stack_check={}, middlewares={})",
+            self.enable_nan_canonicalization,
+            self.enable_stack_check,
+            self.middlewares.fingerprint()?,
+        ))
+    }
 }
 
 impl Default for Singlepass {
@@ -0,0 +1,852 @@
+pub use crate::arm64_decl::{GPR, NEON};
+use crate::location::Location as AbstractLocation;
+pub use crate::location::Size;
+use dynasmrt::{aarch64::Assembler, AssemblyOffset, DynamicLabel, DynasmApi, DynasmLabelApi};
+
+/// Dynasm proc-macro picks the architecture from the last `.arch` directive of a
+/// source file, which does not apply to invocations coming from other macros.
+/// So every invocation states it.
+macro_rules! dynasm {
+    ($a:expr ; $($tt:tt)*) => {
+        dynasm::dynasm!(
+            $a
+            ; .arch aarch64
+            ; $($tt)*
+        )
+    };
+}
+
+pub type Location = AbstractLocation<GPR, NEON>;
+
+/// Condition codes, as set by `cmp` on integers or `fcmp` on floats.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[allow(dead_code)]
+pub enum Condition {
+    /// Equal.
+    Eq,
+    /// Not equal, or unordered.
+    Ne,
+    /// Unsigned higher or same.
+    Hs,
+    /// Unsigned lower.
+    Lo,
+    /// Negative. Less than for floats, false if unordered.
+    Mi,
+    /// Positive or zero.
+    Pl,
+    /// Overflow. Unordered for floats.
+    Vs,
+    /// No overflow.
+    Vc,
+    /// Unsigned higher.
+    Hi,
+    /// Unsigned lower or same. Less than or equal for floats, false if unordered.
+    Ls,
+    /// Signed greater than or equal.
+    Ge,
+    /// Signed less than.
+    Lt,
+    /// Signed greater than.
+    Gt,
+    /// Signed less than or equal.
+    Le,
+    /// Always.
+    Al,
+}
+
+impl Condition {
+    /// Returns the condition that holds exactly when `self` does not.
+    pub fn invert(self) -> Self {
+        match self {
+            Condition::Eq => Condition::Ne,
+            Condition::Ne => Condition::Eq,
+            Condition::Hs => Condition::Lo,
+            Condition::Lo => Condition::Hs,
+            Condition::Mi => Condition::Pl,
+            Condition::Pl => Condition::Mi,
+            Condition::Vs => Condition::Vc,
+            Condition::Vc => Condition::Vs,
+            Condition::Hi => Condition::Ls,
+            Condition::Ls => Condition::Hi,
+            Condition::Ge => Condition::Lt,
+            Condition::Lt => Condition::Ge,
+            Condition::Gt => Condition::Le,
+            Condition::Le => Condition::Gt,
+            Condition::Al => unreachable!("invert: `al` has no inverse"),
+        }
+    }
+}
+
+/// The ARM64 instructions used by the code generator.
+///
+/// X16 and X17 (the intra-procedure-call scratch registers) are reserved to the emitter:
+/// it uses them to materialize immediates and addresses that don't fit in an instruction.
+/// The code generator never allocates them.
+pub trait EmitterARM64 {
+    fn get_label(&mut self) -> DynamicLabel;
+    fn get_offset(&self) -> AssemblyOffset;
+
+    fn emit_u64(&mut self, x: u64);
+    fn emit_label(&mut self, label: DynamicLabel);
+
+    /// Moves `sz` bits from `src` to `dst`, any combination of locations being allowed.
+    ///
+    /// 8-bit and 16-bit values are only moved between registers and memory, and are
+    /// zero-extended when loaded.
+    fn emit_mov(&mut self, sz: Size, src: Location, dst: Location);
+    fn emit_mov_imm(&mut self, dst: GPR, val: u64);
+    /// Loads a `sz_src` value from `[base + offset]` and sign-extends it to `sz_dst`.
+    fn emit_ldr_sext(&mut self, sz_src: Size, sz_dst: Size, base: GPR, offset: i32, dst: GPR);
+    /// Computes `base + offset` into `dst`.
+    fn emit_add_offset(&mut self, base: GPR, offset: i64, dst: GPR);
+
+    fn emit_add(&mut self, sz: Size, src1: GPR, src2: GPR, dst: GPR);
+    /// Computes `src1 + (src2 << shift)` on 64 bits into `dst`.
+    fn emit_add_lsl(&mut self, src1: GPR, src2: GPR, shift: u32, dst: GPR);
+    fn emit_sub(&mut self, sz: Size, src1: GPR, src2: GPR, dst: GPR);
+    fn emit_mul(&mut self, sz: Size, src1: GPR, src2: GPR, dst: GPR);
+    fn emit_sdiv(&mut self, sz: Size, src1: GPR, src2: GPR, dst: GPR);
+    fn emit_udiv(&mut self, sz: Size, src1: GPR, src2: GPR, dst: GPR);
+    /// Computes `src3 - src1 * src2` into `dst`.
+    fn emit_msub(&mut self, sz: Size, src1: GPR, src2: GPR, src3: GPR, dst: GPR);
+    fn emit_and(&mut self, sz: Size, src1: GPR, src2: GPR, dst: GPR);
+    fn emit_orr(&mut self, sz: Size, src1: GPR, src2: GPR, dst: GPR);
+    fn emit_eor(&mut self, sz: Size, src1: GPR, src2: GPR, dst: GPR);
+    fn emit_lsl(&mut self, sz: Size, src1: GPR, src2: GPR, dst: GPR);
+    fn emit_lsr(&mut self, sz: Size, src1: GPR, src2: GPR, dst: GPR);
+    fn emit_asr(&mut self, sz: Size, src1: GPR, src2: GPR, dst: GPR);
+    fn emit_ror(&mut self, sz: Size, src1: GPR, src2: GPR, dst: GPR);
+    fn emit_neg(&mut self, sz: Size, src: GPR, dst: GPR);
+    fn emit_clz(&mut self, sz: Size, src: GPR, dst: GPR);
+    fn emit_rbit(&mut self, sz: Size, src: GPR, dst: GPR);
+    /// Counts the bits set in `src` into `dst`, using `tmp` as scratch.
+    fn emit_popcnt(&mut self, sz: Size, src: GPR, dst: GPR, tmp: NEON);
+    /// Sign-extends the low `sz_src` bits of `src` to `sz_dst` bits.
+    fn emit_sxt(&mut self, sz_src: Size, sz_dst: Size, src: GPR, dst: GPR);
+
+    fn emit_cmp(&mut self, sz: Size, left: GPR, right: GPR);
+    /// Compares `left` with an immediate, which may be of any size.
+    fn emit_cmp_imm(&mut self, sz: Size, left: GPR, right: u64);
+    /// Sets `dst` to 1 if `cond` holds, 0 otherwise.
+    fn emit_cset(&mut self, dst: GPR, cond: Condition);
+    /// Selects `src1` if `cond` holds, `src2` otherwise.
+    fn emit_csel(&mut self, sz: Size, src1: GPR, src2: GPR, dst: GPR, cond: Condition);
+
+    /// Jumps to `label` if `cond` holds. The label may be anywhere in the function.
+    fn emit_jmp(&mut self, cond: Condition, label: DynamicLabel);
+    fn emit_adr(&mut self, label: DynamicLabel, dst: GPR);
+    fn emit_br(&mut self, target: GPR);
+    fn emit_blr(&mut self, target: GPR);
+    /// Calls the address held by a literal, returning the offset of the 8-byte literal to
+    /// be patched by an `Abs8` relocation.
+    fn emit_call_literal(&mut self) -> usize;
+    fn emit_ret(&mut self);
+    fn emit_udf(&mut self);
+
+    /// Pushes the frame pointer and the link register, and sets up the frame pointer.
+    fn emit_prologue(&mut self);
+    /// Tears down the frame set up by `emit_prologue` and returns.
+    fn emit_epilogue(&mut self);
+    /// Sets the stack pointer to `offset` bytes below the frame pointer.
+    fn emit_set_sp_below_fp(&mut self, offset: usize);
+    fn emit_sub_sp(&mut self, size: u32);
+    fn emit_add_sp(&mut self, size: u32);
+    fn emit_mov_from_sp(&mut self, dst: GPR);
+    /// Zeroes `n` consecutive 8-byte slots starting at `[base + offset]`.
+    fn emit_zero_slots(&mut self, base: GPR, offset: i32, n: usize);
+
+    fn emit_fadd(&mut self, sz: Size, src1: NEON, src2: NEON, dst: NEON);
+    fn emit_fsub(&mut self, sz: Size, src1: NEON, src2: NEON, dst: NEON);
+    fn emit_fmul(&mut self, sz: Size, src1: NEON, src2: NEON, dst: NEON);
+    fn emit_fdiv(&mut self, sz: Size, src1: NEON, src2: NEON, dst: NEON);
+    fn emit_fmin(&mut self, sz: Size, src1: NEON, src2: NEON, dst: NEON);
+    fn emit_fmax(&mut self, sz: Size, src1: NEON, src2: NEON, dst: NEON);
+    fn emit_fsqrt(&mut self, sz: Size, src: NEON, dst: NEON);
+    fn emit_fneg(&mut self, sz: Size, src: NEON, dst: NEON);
+    fn emit_fabs(&mut self, sz: Size, src: NEON, dst: NEON);
+    /// Rounds to nearest, ties to even.
+    fn emit_frintn(&mut self, sz: Size, src: NEON, dst: NEON);
+    /// Rounds towards plus infinity.
+    fn emit_frintp(&mut self, sz: Size, src: NEON, dst: NEON);
+    /// Rounds towards minus infinity.
+    fn emit_frintm(&mut self, sz: Size, src: NEON, dst: NEON);
+    /// Rounds towards zero.
+    fn emit_frintz(&mut self, sz: Size, src: NEON, dst: NEON);
+    fn emit_fcmp(&mut self, sz: Size, left: NEON, right: NEON);
+    /// Converts between single and double precision.
+    fn emit_fcvt(&mut self, sz_src: Size, src: NEON, dst: NEON);
+    /// Converts a signed integer to a float.
+    fn emit_scvtf(&mut self, sz_src: Size, src: GPR, sz_dst: Size, dst: NEON);
+    /// Converts an unsigned integer to a float.
+    fn emit_ucvtf(&mut self, sz_src: Size, src: GPR, sz_dst: Size, dst: NEON);
+    /// Converts a float to a signed integer, rounding towards zero and saturating.
+    fn emit_fcvtzs(&mut self, sz_src: Size, src: NEON, sz_dst: Size, dst: GPR);
+    /// Converts a float to an unsigned integer, rounding towards zero and saturating.
+    fn emit_fcvtzu(&mut self, sz_src: Size, src: NEON, sz_dst: Size, dst: GPR);
+}
+
+/// Emits a load or store of `$reg` at `[$base + $offset]`, an access of `$scale` bytes,
+/// with the first addressing mode able to encode `$offset`.
+macro_rules! ldst {
+    ($assembler:tt, $ins:ident, $unscaled:ident, $fam:ident($reg:expr), $base:expr, $offset:expr, $scale:expr) => {{
+        let reg = $reg;
+        let base = $base.into_index();
+        let offset: i32 = $offset;
+        let scale: i32 = $scale;
+        if (-256..256).contains(&offset) {
+            dynasm!($assembler ; $unscaled $fam(reg), [X(base), offset]);
+        } else if offset >= 0 && offset % scale == 0 && offset / scale < 4096 {
+            dynasm!($assembler ; $ins $fam(reg), [X(base), offset as u32]);
+        } else {
+            $assembler.emit_mov_imm(GPR::X16, offset as i64 as u64);
+            dynasm!($assembler ; $ins $fam(reg), [X(base), X(16)]);
+        }
+    }};
+}
+
+macro_rules! binop_gpr {
+    ($ins:ident, $name:ident) => {
+        fn $name(&mut self, sz: Size, src1: GPR, src2: GPR, dst: GPR) {
+            let (src1, src2, dst) = (src1.into_index(), src2.into_index(), dst.into_index());
+            match sz {
+                Size::S32 => dynasm!(self ; $ins W(dst), W(src1), W(src2)),
+                Size::S64 => dynasm!(self ; $ins X(dst), X(src1), X(src2)),
+                _ => unreachable!(),
+            }
+        }
+    };
+}
+
+macro_rules! unop_gpr {
+    ($ins:ident, $name:ident) => {
+        fn $name(&mut self, sz: Size, src: GPR, dst: GPR) {
+            let (src, dst) = (src.into_index(), dst.into_index());
+            match sz {
+                Size::S32 => dynasm!(self ; $ins W(dst), W(src)),
+                Size::S64 => dynasm!(self ; $ins X(dst), X(src)),
+                _ => unreachable!(),
+            }
+        }
+    };
+}
+
+macro_rules! binop_neon {
+    ($ins:ident, $name:ident) => {
+        fn $name(&mut self, sz: Size, src1: NEON, src2: NEON, dst: NEON) {
+            let (src1, src2, dst) = (src1.into_index(), src2.into_index(), dst.into_index());
+            match sz {
+                Size::S32 => dynasm!(self ; $ins S(dst), S(src1), S(src2)),
+                Size::S64 => dynasm!(self ; $ins D(dst), D(src1), D(src2)),
+                _ => unreachable!(),
+            }
+        }
+    };
+}
+
+macro_rules! unop_neon {
+    ($ins:ident, $name:ident) => {
+        fn $name(&mut self, sz: Size, src: NEON, dst: NEON) {
+            let (src, dst) = (src.into_index(), dst.into_index());
+            match sz {
+                Size::S32 => dynasm!(self ; $ins S(dst), S(src)),
+                Size::S64 => dynasm!(self ; $ins D(dst), D(src)),
+                _ => unreachable!(),
+            }
+        }
+    };
+}
+
+macro_rules! int_to_float {
+    ($ins:ident, $name:ident) => {
+        fn $name(&mut self, sz_src: Size, src: GPR, sz_dst: Size, dst: NEON) {
+            let (src, dst) = (src.into_index(), dst.into_index());
+            match (sz_src, sz_dst) {
+                (Size::S32, Size::S32) => dynasm!(self ; $ins S(dst), W(src)),
+                (Size::S64, Size::S32) => dynasm!(self ; $ins S(dst), X(src)),
+                (Size::S32, Size::S64) => dynasm!(self ; $ins D(dst), W(src)),
+                (Size::S64, Size::S64) => dynasm!(self ; $ins D(dst), X(src)),
+                _ => unreachable!(),
+            }
+        }
+    };
+}
+
+macro_rules! float_to_int {
+    ($ins:ident, $name:ident) => {
+        fn $name(&mut self, sz_src: Size, src: NEON, sz_dst: Size, dst: GPR) {
+            let (src, dst) = (src.into_index(), dst.into_index());
+            match (sz_src, sz_dst) {
+                (Size::S32, Size::S32) => dynasm!(self ; $ins W(dst), S(src)),
+                (Size::S64, Size::S32) => dynasm!(self ; $ins W(dst), D(src)),
+                (Size::S32, Size::S64) => dynasm!(self ; $ins X(dst), S(src)),
+                (Size::S64, Size::S64) => dynasm!(self ; $ins X(dst), D(src)),
+                _ => unreachable!(),
+            }
+        }
+    };
+}
+
+fn emit_load(a: &mut Assembler, sz: Size, base: GPR, offset: i32, dst: GPR) {
+    let dst = dst.into_index();
+    match sz {
+        Size::S8 => ldst!(a, ldrb, ldurb, W(dst), base, offset, 1),
+        Size::S16 => ldst!(a, ldrh, ldurh, W(dst), base, offset, 2),
+        Size::S32 => ldst!(a, ldr, ldur, W(dst), base, offset, 4),
+        Size::S64 => ldst!(a, ldr, ldur, X(dst), base, offset, 8),
+    }
+}
+
+fn emit_store(a: &mut Assembler, sz: Size, src: GPR, base: GPR, offset: i32) {
+    let src = src.into_index();
+    match sz {
+        Size::S8 => ldst!(a, strb, sturb, W(src), base, offset, 1),
+        Size::S16 => ldst!(a, strh, sturh, W(src), base, offset, 2),
+        Size::S32 => ldst!(a, str, stur, W(src), base, offset, 4),
+        Size::S64 => ldst!(a, str, stur, X(src), base, offset, 8),
+    }
+}
+
+fn emit_load_neon(a: &mut Assembler, sz: Size, base: GPR, offset: i32, dst: NEON) {
+    let dst = dst.into_index();
+    match sz {
+        Size::S32 => ldst!(a, ldr, ldur, S(dst), base, offset, 4),
+        Size::S64 => ldst!(a, ldr, ldur, D(dst), base, offset, 8),
+        _ => unreachable!(),
+    }
+}
+
+fn emit_store_neon(a: &mut Assembler, sz: Size, src: NEON, base: GPR, offset: i32) {
+    let src = src.into_index();
+    match sz {
+        Size::S32 => ldst!(a, str, stur, S(src), base, offset, 4),
+        Size::S64 => ldst!(a, str, stur, D(src), base, offset, 8),
+        _ => unreachable!(),
+    }
+}
+
+fn emit_fmov_from_gpr(a: &mut Assembler, sz: Size, src: GPR, dst: NEON) {
+    let (src, dst) = (src.into_index(), dst.into_index());
+    match sz {
+        Size::S32 => dynasm!(a ; fmov S(dst), W(src)),
+        Size::S64 => dynasm!(a ; fmov D(dst), X(src)),
+        _ => unreachable!(),
+    }
+}
+
+fn emit_fmov_to_gpr(a: &mut Assembler, sz: Size, src: NEON, dst: GPR) {
+    let (src, dst) = (src.into_index(), dst.into_index());
+    match sz {
+        Size::S32 => dynasm!(a ; fmov W(dst), S(src)),
+        Size::S64 => dynasm!(a ; fmov X(dst), D(src)),
+        _ => unreachable!(),
+    }
+}
+
+impl EmitterARM64 for Assembler {
+    fn get_label(&mut self) -> DynamicLabel {
+        self.new_dynamic_label()
+    }
+
+    fn get_offset(&self) -> AssemblyOffset {
+        self.offset()
+    }
+
+    fn emit_u64(&mut self, x: u64) {
+        self.push_u64(x);
+    }
+
+    fn emit_label(&mut self, label: DynamicLabel) {
+        dynasm!(self ; => label);
+    }
+
+    fn emit_mov(&mut self, sz: Size, src: Location, dst: Location) {
+        match (src, dst) {
+            (Location::GPR(src), Location::GPR(dst)) => {
+                if src != dst {
+                    let (src, dst) = (src.into_index(), dst.into_index());
+                    match sz {
+                        Size::S32 => dynasm!(self ; mov W(dst), W(src)),
+                        Size::S64 => dynasm!(self ; mov X(dst), X(src)),
+                        _ => unreachable!(),
+                    }
+                }
+            }
+            (Location::Imm8(x), Location::GPR(dst)) => self.emit_mov_imm(dst, x as u64),
+            (Location::Imm32(x), Location::GPR(dst)) => self.emit_mov_imm(dst, x as u64),
+            (Location::Imm64(x), Location::GPR(dst)) => {
+                let x = if sz == Size::S64 { x } else { x as u32 as u64 };
+                self.emit_mov_imm(dst, x)
+            }
+            (Location::Memory(base, offset), Location::GPR(dst)) => {
+                emit_load(self, sz, base, offset, dst)
+            }
+            (Location::GPR(src), Location::Memory(base, offset)) => {
+                emit_store(self, sz, src, base, offset)
+            }
+            (Location::Imm8(_), Location::Memory(base, offset))
+            | (Location::Imm32(_), Location::Memory(base, offset))
+            | (Location::Imm64(_), Location::Memory(base, offset)) => {
+                let src = match src {
+                    Location::Imm8(0) | Location::Imm32(0) | Location::Imm64(0) => GPR::XzrSp,
+                    _ => {
+                        self.emit_mov(sz, src, Location::GPR(GPR::X17));
+                        GPR::X17
+                    }
+                };
+                emit_store(self, sz, src, base, offset)
+            }
+            (Location::Memory(_, _), Location::Memory(_, _)) => {
+                self.emit_mov(sz, src, Location::GPR(GPR::X17));
+                self.emit_mov(sz, Location::GPR(GPR::X17), dst);
+            }
+            (Location::SIMD(src), Location::SIMD(dst)) => {
+                if src != dst {
+                    let (src, dst) = (src.into_index(), dst.into_index());
+                    match sz {
+                        Size::S32 => dynasm!(self ; fmov S(dst), S(src)),
+                        Size::S64 => dynasm!(self ; fmov D(dst), D(src)),
+                        _ => unreachable!(),
+                    }
+                }
+            }
+            (Location::GPR(src), Location::SIMD(dst)) => emit_fmov_from_gpr(self, sz, src, dst),
+            (Location::SIMD(src), Location::GPR(dst)) => emit_fmov_to_gpr(self, sz, src, dst),
+            (Location::Memory(base, offset), Location::SIMD(dst)) => {
+                emit_load_neon(self, sz, base, offset, dst)
+            }
+            (Location::SIMD(src), Location::Memory(base, offset)) => {
+                emit_store_neon(self, sz, src, base, offset)
+            }
+            (Location::Imm8(_), Location::SIMD(dst))
+            | (Location::Imm32(_), Location::SIMD(dst))
+            | (Location::Imm64(_), Location::SIMD(dst)) => {
+                self.emit_mov(sz, src, Location::GPR(GPR::X17));
+                emit_fmov_from_gpr(self, sz, GPR::X17, dst);
+            }
+            _ => panic!("singlepass can't emit MOV {:?} {:?} {:?}", sz, src, dst),
+        }
+    }
+
+    fn emit_mov_imm(&mut self, dst: GPR, val: u64) {
+        let dst = dst.into_index();
+        let halves = [
+            val as u32 & 0xffff,
+            (val >> 16) as u32 & 0xffff,
+            (val >> 32) as u32 & 0xffff,
+            (val >> 48) as u32 & 0xffff,
+        ];
+        // Values with mostly ones start from `movn` and patch the other halves, the rest
+        // start from `movz`.
+        let inverted = halves.iter().filter(|&&x| x == 0xffff).count() > 2;
+        let implicit = if inverted { 0xffff } else { 0 };
+        let mut first = true;
+        for (i, &half) in halves.iter().enumerate() {
+            if half == implicit {
+                continue;
+            }
+            match (first, inverted, i) {
+                (true, true, 0) => dynasm!(self ; movn X(dst), !half & 0xffff),
+                (true, true, 1) => dynasm!(self ; movn X(dst), !half & 0xffff, LSL #16),
+                (true, true, 2) => dynasm!(self ; movn X(dst), !half & 0xffff, LSL #32),
+                (true, true, _) => dynasm!(self ; movn X(dst), !half & 0xffff, LSL #48),
+                (true, false, 0) => dynasm!(self ; movz X(dst), half),
+                (true, false, 1) => dynasm!(self ; movz X(dst), half, LSL #16),
+                (true, false, 2) => dynasm!(self ; movz X(dst), half, LSL #32),
+                (true, false, _) => dynasm!(self ; movz X(dst), half, LSL #48),
+                (false, _, 0) => dynasm!(self ; movk X(dst), half),
+                (false, _, 1) => dynasm!(self ; movk X(dst), half, LSL #16),
+                (false, _, 2) => dynasm!(self ; movk X(dst), half, LSL #32),
+                (false, _, _) => dynasm!(self ; movk X(dst), half, LSL #48),
+            }
+            first = false;
+        }
+        if first {
+            if inverted {
+                dynasm!(self ; movn X(dst), 0);
+            } else {
+                dynasm!(self ; movz X(dst), 0);
+            }
+        }
+    }
+
+    fn emit_ldr_sext(&mut self, sz_src: Size, sz_dst: Size, base: GPR, offset: i32, dst: GPR) {
+        let dst = dst.into_index();
+        match (sz_src, sz_dst) {
+            (Size::S8, Size::S32) => ldst!(self, ldrsb, ldursb, W(dst), base, offset, 1),
+            (Size::S8, Size::S64) => ldst!(self, ldrsb, ldursb, X(dst), base, offset, 1),
+            (Size::S16, Size::S32) => ldst!(self, ldrsh, ldursh, W(dst), base, offset, 2),
+            (Size::S16, Size::S64) => ldst!(self, ldrsh, ldursh, X(dst), base, offset, 2),
+            (Size::S32, Size::S64) => ldst!(self, ldrsw, ldursw, X(dst), base, offset, 4),
+            _ => unreachable!(),
+        }
+    }
+
+    fn emit_add_offset(&mut self, base: GPR, offset: i64, dst: GPR) {
+        let (base, dst) = (base.into_index(), dst.into_index());
+        if (0..4096).contains(&offset) {
+            dynasm!(self ; add X(dst), X(base), offset as u32);
+        } else if (-4095..0).contains(&offset) {
+            dynasm!(self ; sub X(dst), X(base), (-offset) as u32);
+        } else {
+            self.emit_mov_imm(GPR::X16, offset as u64);
+            dynasm!(self ; add X(dst), X(base), X(16));
+        }
+    }
+
+    binop_gpr!(add, emit_add);
+
+    fn emit_add_lsl(&mut self, src1: GPR, src2: GPR, shift: u32, dst: GPR) {
+        let (src1, src2, dst) = (src1.into_index(), src2.into_index(), dst.into_index());
+        dynasm!(self ; add X(dst), X(src1), X(src2), LSL shift);
+    }
+    binop_gpr!(sub, emit_sub);
+    binop_gpr!(mul, emit_mul);
+    binop_gpr!(sdiv, emit_sdiv);
+    binop_gpr!(udiv, emit_udiv);
+    binop_gpr!(and, emit_and);
+    binop_gpr!(orr, emit_orr);
+    binop_gpr!(eor, emit_eor);
+    binop_gpr!(lsl, emit_lsl);
+    binop_gpr!(lsr, emit_lsr);
+    binop_gpr!(asr, emit_asr);
+    binop_gpr!(ror, emit_ror);
+
+    fn emit_msub(&mut self, sz: Size, src1: GPR, src2: GPR, src3: GPR, dst: GPR) {
+        let (src1, src2, src3, dst) = (
+            src1.into_index(),
+            src2.into_index(),
+            src3.into_index(),
+            dst.into_index(),
+        );
+        match sz {
+            Size::S32 => dynasm!(self ; msub W(dst), W(src1), W(src2), W(src3)),
+            Size::S64 => dynasm!(self ; msub X(dst), X(src1), X(src2), X(src3)),
+            _ => unreachable!(),
+        }
+    }
+
+    unop_gpr!(neg, emit_neg);
+    unop_gpr!(clz, emit_clz);
+    unop_gpr!(rbit, emit_rbit);
+
+    fn emit_popcnt(&mut self, sz: Size, src: GPR, dst: GPR, tmp: NEON) {
+        emit_fmov_from_gpr(self, sz, src, tmp);
+        let (dst, tmp) = (dst.into_index(), tmp.into_index());
+        dynasm!(self
+            ; cnt V(tmp).B8, V(tmp).B8
+            ; addv B(tmp), V(tmp).B8
+            ; fmov W(dst), S(tmp)
+        );
+    }
+
+    fn emit_sxt(&mut self, sz_src: Size, sz_dst: Size, src: GPR, dst: GPR) {
+        let (src, dst) = (src.into_index(), dst.into_index());
+        match (sz_src, sz_dst) {
+            (Size::S8, Size::S32) => dynasm!(self ; sxtb W(dst), W(src)),
+            (Size::S8, Size::S64) => dynasm!(self ; sxtb X(dst), W(src)),
+            (Size::S16, Size::S32) => dynasm!(self ; sxth W(dst), W(src)),
+            (Size::S16, Size::S64) => dynasm!(self ; sxth X(dst), W(src)),
+            (Size::S32, Size::S64) => dynasm!(self ; sxtw X(dst), W(src)),
+            _ => unreachable!(),
+        }
+    }
+
+    fn emit_cmp(&mut self, sz: Size, left: GPR, right: GPR) {
+        let (left, right) = (left.into_index(), right.into_index());
+        match sz {
+            Size::S32 => dynasm!(self ; cmp W(left), W(right)),
+            Size::S64 => dynasm!(self ; cmp X(left), X(right)),
+            _ => unreachable!(),
+        }
+    }
+
+    fn emit_cmp_imm(&mut self, sz: Size, left: GPR, right: u64) {
+        if right < 4096 {
+            let left = left.into_index();
+            let right = right as u32;
+            match sz {
+                Size::S32 => dynasm!(self ; cmp W(left), right),
+                Size::S64 => dynasm!(self ; cmp X(left), right),
+                _ => unreachable!(),
+            }
+        } else {
+            self.emit_mov_imm(GPR::X17, right);
+            self.emit_cmp(sz, left, GPR::X17);
+        }
+    }
+
+    fn emit_cset(&mut self, dst: GPR, cond: Condition) {
+        let dst = dst.into_index();
+        match cond {
+            Condition::Eq => dynasm!(self ; cset W(dst), eq),
+            Condition::Ne => dynasm!(self ; cset W(dst), ne),
+            Condition::Hs => dynasm!(self ; cset W(dst), hs),
+            Condition::Lo => dynasm!(self ; cset W(dst), lo),
+            Condition::Mi => dynasm!(self ; cset W(dst), mi),
+            Condition::Pl => dynasm!(self ; cset W(dst), pl),
+            Condition::Vs => dynasm!(self ; cset W(dst), vs),
+            Condition::Vc => dynasm!(self ; cset W(dst), vc),
+            Condition::Hi => dynasm!(self ; cset W(dst), hi),
+            Condition::Ls => dynasm!(self ; cset W(dst), ls),
+            Condition::Ge => dynasm!(self ; cset W(dst), ge),
+            Condition::Lt => dynasm!(self ; cset W(dst), lt),
+            Condition::Gt => dynasm!(self ; cset W(dst), gt),
+            Condition::Le => dynasm!(self ; cset W(dst), le),
+            Condition::Al => dynasm!(self ; movz W(dst), 1),
+        }
+    }
+
+    fn emit_csel(&mut self, sz: Size, src1: GPR, src2: GPR, dst: GPR, cond: Condition) {
+        let (src1, src2, dst) = (src1.into_index(), src2.into_index(), dst.into_index());
+        macro_rules! csel {
+            ($cond:ident) => {
+                match sz {
+                    Size::S32 => dynasm!(self ; csel W(dst), W(src1), W(src2), $cond),
+                    Size::S64 => dynasm!(self ; csel X(dst), X(src1), X(src2), $cond),
+                    _ => unreachable!(),
+                }
+            };
+        }
+        match cond {
+            Condition::Eq => csel!(eq),
+            Condition::Ne => csel!(ne),
+            Condition::Hs => csel!(hs),
+            Condition::Lo => csel!(lo),
+            Condition::Mi => csel!(mi),
+            Condition::Pl => csel!(pl),
+            Condition::Vs => csel!(vs),
+            Condition::Vc => csel!(vc),
+            Condition::Hi => csel!(hi),
+            Condition::Ls => csel!(ls),
+            Condition::Ge => csel!(ge),
+            Condition::Lt => csel!(lt),
+            Condition::Gt => csel!(gt),
+            Condition::Le => csel!(le),
+            Condition::Al => csel!(al),
+        }
+    }
+
+    fn emit_jmp(&mut self, cond: Condition, label: DynamicLabel) {
+        // `b.cond` only reaches +/-1MB, so conditional jumps branch over an unconditional
+        // `b` when the condition does not hold.
+        if cond == Condition::Al {
+            dynasm!(self ; b =>label);
+            return;
+        }
+        match cond.invert() {
+            Condition::Eq => dynasm!(self ; b.eq >skip),
+            Condition::Ne => dynasm!(self ; b.ne >skip),
+            Condition::Hs => dynasm!(self ; b.hs >skip),
+            Condition::Lo => dynasm!(self ; b.lo >skip),
+            Condition::Mi => dynasm!(self ; b.mi >skip),
+            Condition::Pl => dynasm!(self ; b.pl >skip),
+            Condition::Vs => dynasm!(self ; b.vs >skip),
+            Condition::Vc => dynasm!(self ; b.vc >skip),
+            Condition::Hi => dynasm!(self ; b.hi >skip),
+            Condition::Ls => dynasm!(self ; b.ls >skip),
+            Condition::Ge => dynasm!(self ; b.ge >skip),
+            Condition::Lt => dynasm!(self ; b.lt >skip),
+            Condition::Gt => dynasm!(self ; b.gt >skip),
+            Condition::Le => dynasm!(self ; b.le >skip),
+            Condition::Al => unreachable!(),
+        }
+        dynasm!(self
+            ; b =>label
+            ; skip:
+        );
+    }
+
+    fn emit_adr(&mut self, label: DynamicLabel, dst: GPR) {
+        let dst = dst.into_index();
+        dynasm!(self ; adr X(dst), =>label);
+    }
+
+    fn emit_br(&mut self, target: GPR) {
+        let target = target.into_index();
+        dynasm!(self ; br X(target));
+    }
+
+    fn emit_blr(&mut self, target: GPR) {
+        let target = target.into_index();
+        dynasm!(self ; blr X(target));
+    }
+
+    fn emit_call_literal(&mut self) -> usize {
+        dynasm!(self
+            ; ldr x16, >literal
+            ; b >call
+            ; literal:
+        );
+        let offset = self.offset().0;
+        self.emit_u64(0);
+        dynasm!(self
+            ; call:
+            ; blr x16
+        );
+        offset
+    }
+
+    fn emit_ret(&mut self) {
+        dynasm!(self ; ret);
+    }
+
+    fn emit_udf(&mut self) {
+        dynasm!(self ; udf 0);
+    }
+
+    fn emit_prologue(&mut self) {
+        dynasm!(self
+            ; stp x29, x30, [sp, #-16]!
+            ; mov x29, sp
+        );
+    }
+
+    fn emit_epilogue(&mut self) {
+        dynasm!(self
+            ; mov sp, x29
+            ; ldp x29, x30, [sp], #16
+        );
+        self.emit_ret();
+    }
+
+    fn emit_set_sp_below_fp(&mut self, offset: usize) {
+        if offset < 4096 {
+            dynasm!(self ; sub sp, x29, offset as u32);
+        } else {
+            self.emit_mov_imm(GPR::X16, offset as u64);
+            dynasm!(self ; sub sp, x29, x16);
+        }
+    }
+
+    fn emit_sub_sp(&mut self, size: u32) {
+        if size < 4096 {
+            dynasm!(self ; sub sp, sp, size);
+        } else {
+            self.emit_mov_imm(GPR::X16, size as u64);
+            dynasm!(self ; sub sp, sp, x16);
+        }
+    }
+
+    fn emit_add_sp(&mut self, size: u32) {
+        if size < 4096 {
+            dynasm!(self ; add sp, sp, size);
+        } else {
+            self.emit_mov_imm(GPR::X16, size as u64);
+            dynasm!(self ; add sp, sp, x16);
+        }
+    }
+
+    fn emit_mov_from_sp(&mut self, dst: GPR) {
+        let dst = dst.into_index();
+        dynasm!(self ; mov X(dst), sp);
+    }
+
+    fn emit_zero_slots(&mut self, base: GPR, offset: i32, n: usize) {
+        self.emit_add_offset(base, offset as i64, GPR::X16);
+        self.emit_mov_imm(GPR::X17, n as u64);
+        dynasm!(self
+            ; zero:
+            ; str xzr, [x16], #8
+            ; subs x17, x17, #1
+            ; b.ne <zero
+        );
+    }
+
+    binop_neon!(fadd, emit_fadd);
+    binop_neon!(fsub, emit_fsub);
+    binop_neon!(fmul, emit_fmul);
+    binop_neon!(fdiv, emit_fdiv);
+    binop_neon!(fmin, emit_fmin);
+    binop_neon!(fmax, emit_fmax);
+    unop_neon!(fsqrt, emit_fsqrt);
+    unop_neon!(fneg, emit_fneg);
+    unop_neon!(fabs, emit_fabs);
+    unop_neon!(frintn, emit_frintn);
+    unop_neon!(frintp, emit_frintp);
+    unop_neon!(frintm, emit_frintm);
+    unop_neon!(frintz, emit_frintz);
+
+    fn emit_fcmp(&mut self, sz: Size, left: NEON, right: NEON) {
+        let (left, right) = (left.into_index(), right.into_index());
+        match sz {
+            Size::S32 => dynasm!(self ; fcmp S(left), S(right)),
+            Size::S64 => dynasm!(self ; fcmp D(left), D(right)),
+            _ => unreachable!(),
+        }
+    }
+
+    fn emit_fcvt(&mut self, sz_src: Size, src: NEON, dst: NEON) {
+        let (src, dst) = (src.into_index(), dst.into_index());
+        match sz_src {
+            Size::S32 => dynasm!(self ; fcvt D(dst), S(src)),
+            Size::S64 => dynasm!(self ; fcvt S(dst), D(src)),
+            _ => unreachable!(),
+        }
+    }
+
+    int_to_float!(scvtf, emit_scvtf);
+    int_to_float!(ucvtf, emit_ucvtf);
+    float_to_int!(fcvtzs, emit_fcvtzs);
+    float_to_int!(fcvtzu, emit_fcvtzu);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assemble<F: FnOnce(&mut Assembler)>(f: F) -> Vec<u32> {
+        let mut a = Assembler::new().unwrap();
+        f(&mut a);
+        a.finalize()
+            .unwrap()
+            .chunks(4)
+            .map(|x| u32::from_le_bytes([x[0], x[1], x[2], x[3]]))
+            .collect()
+    }
+
+    #[test]
+    fn test_mov_imm() {
+        // movz x0, #0x1234
+        assert_eq!(assemble(|a| a.emit_mov_imm(GPR::X0, 0x1234)), [0xd282_4680]);
+        // movz x0, #0
+        assert_eq!(assemble(|a| a.emit_mov_imm(GPR::X0, 0)), [0xd280_0000]);
+        // movn x0, #0
+        assert_eq!(assemble(|a| a.emit_mov_imm(GPR::X0, !0)), [0x9280_0000]);
+        // movz x0, #0x1, lsl #32; movk x0, #0x2, lsl #48
+        assert_eq!(
+            assemble(|a| a.emit_mov_imm(GPR::X0, 0x0002_0001_0000_0000)),
+            [0xd2c0_0020, 0xf2e0_0040]
+        );
+    }
+
+    #[test]
+    fn test_mov_memory_offsets() {
+        // ldur x0, [x29, #-8]
+        assert_eq!(
+            assemble(|a| a.emit_mov(
+                Size::S64,
+                Location::Memory(GPR::X29, -8),
+                Location::GPR(GPR::X0)
+            )),
+            [0xf85f_83a0]
+        );
+        // ldr x0, [x28, #1024]
+        assert_eq!(
+            assemble(|a| a.emit_mov(
+                Size::S64,
+                Location::Memory(GPR::X28, 1024),
+                Location::GPR(GPR::X0)
+            )),
+            [0xf942_0380]
+        );
+        // movn x16, #0x1007; ldr x0, [x29, x16]
+        assert_eq!(
+            assemble(|a| a.emit_mov(
+                Size::S64,
+                Location::Memory(GPR::X29, -0x1008),
+                Location::GPR(GPR::X0)
+            )),
+            [0x9280_0000 | (0x1007 << 5) | 16, 0xf870_6ba0]
+        );
+    }
+}
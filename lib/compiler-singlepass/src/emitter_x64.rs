@@ -1,3 +1,5 @@
+use crate::location::Location as AbstractLocation;
+pub use crate::location::Size;
 pub use crate::x64_decl::{GPR, XMM};
 use dynasm::dynasm;
 use dynasmrt::{x64::Assembler, AssemblyOffset, DynamicLabel, DynasmApi, DynasmLabelApi};
@@ -11,17 +13,7 @@ fn _dummy(_a: &Assembler) {
     );
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
-pub enum Location {
-    Imm8(u8),
-    Imm32(u32),
-    Imm64(u64),
-    // Imm128(u128),
-    GPR(GPR),
-    XMM(XMM),
-    Memory(GPR, i32),
-    MemoryAddTriple(GPR, GPR, i32),
-}
+pub type Location = AbstractLocation<GPR, XMM>;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Condition {
@@ -40,14 +32,6 @@ pub enum Condition {
     Carry,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
-pub enum Size {
-    S8,
-    S16,
-    S32,
-    S64,
-}
-
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[allow(dead_code)]
 pub enum XMMOrMemory {
@@ -198,6 +182,160 @@ pub trait Emitter {
     fn emit_vblendvps(&mut self, src1: XMM, src2: XMMOrMemory, mask: XMM, dst: XMM);
     fn emit_vblendvpd(&mut self, src1: XMM, src2: XMMOrMemory, mask: XMM, dst: XMM);
 
+    fn emit_vmovdqu(&mut self, src: XMMOrMemory, dst: XMMOrMemory);
+
+    fn emit_vpand(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpandn(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpor(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpxor(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpaddb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpaddw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpaddd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpaddq(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsubb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsubw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsubd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsubq(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpaddsb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpaddsw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpaddusb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpaddusw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsubsb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsubsw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsubusb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsubusw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpmullw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpmulld(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpmuludq(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpmaddwd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpminsb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpminsw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpminsd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpminub(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpminuw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpminud(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpmaxsb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpmaxsw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpmaxsd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpmaxub(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpmaxuw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpmaxud(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpavgb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpavgw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpcmpeqb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpcmpeqw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpcmpeqd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpcmpeqq(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpcmpgtb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpcmpgtw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpcmpgtd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpcmpgtq(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpacksswb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpackuswb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpackssdw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpackusdw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpunpcklbw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpunpckhbw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpunpcklqdq(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpunpckhqdq(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpshufb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpsllw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpslld(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsllq(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsrlw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsrld(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsrlq(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsraw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsrad(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vaddps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vaddpd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vsubps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vsubpd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vmulps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vmulpd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vdivps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vdivpd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vminps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vminpd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vmaxps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vmaxpd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vcmpeqps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vcmpeqpd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vcmpneqps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vcmpneqpd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vcmpltps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vcmpltpd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vcmpleps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vcmplepd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vcmpunordps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vcmpunordpd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpmovsxbw(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vpmovzxbw(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vpmovsxwd(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vpmovzxwd(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vpmovsxdq(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vpmovzxdq(&mut self, src: XMMOrMemory, dst: XMM);
+
+    fn emit_vpabsb(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vpabsw(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vpabsd(&mut self, src: XMMOrMemory, dst: XMM);
+
+    fn emit_vsqrtps(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vsqrtpd(&mut self, src: XMMOrMemory, dst: XMM);
+
+    fn emit_vcvtdq2ps(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vcvttps2dq(&mut self, src: XMMOrMemory, dst: XMM);
+
+    fn emit_vroundps_nearest(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vroundps_floor(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vroundps_ceil(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vroundps_trunc(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vroundpd_nearest(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vroundpd_floor(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vroundpd_ceil(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vroundpd_trunc(&mut self, src: XMMOrMemory, dst: XMM);
+
+    fn emit_vpsllw_imm8(&mut self, src: XMM, imm: u8, dst: XMM);
+    fn emit_vpslld_imm8(&mut self, src: XMM, imm: u8, dst: XMM);
+    fn emit_vpsllq_imm8(&mut self, src: XMM, imm: u8, dst: XMM);
+    fn emit_vpsrlw_imm8(&mut self, src: XMM, imm: u8, dst: XMM);
+    fn emit_vpsrld_imm8(&mut self, src: XMM, imm: u8, dst: XMM);
+    fn emit_vpsrlq_imm8(&mut self, src: XMM, imm: u8, dst: XMM);
+    fn emit_vpsraw_imm8(&mut self, src: XMM, imm: u8, dst: XMM);
+    fn emit_vpsrad_imm8(&mut self, src: XMM, imm: u8, dst: XMM);
+
+    fn emit_vpshufd(&mut self, src: XMMOrMemory, imm: u8, dst: XMM);
+    fn emit_vpshuflw(&mut self, src: XMMOrMemory, imm: u8, dst: XMM);
+
+    fn emit_vpinsrb(&mut self, src1: XMM, src2: GPR, lane: u8, dst: XMM);
+    fn emit_vpinsrw(&mut self, src1: XMM, src2: GPR, lane: u8, dst: XMM);
+    fn emit_vpinsrd(&mut self, src1: XMM, src2: GPR, lane: u8, dst: XMM);
+    fn emit_vpinsrq(&mut self, src1: XMM, src2: GPR, lane: u8, dst: XMM);
+    fn emit_vpextrb(&mut self, src: XMM, lane: u8, dst: GPR);
+    fn emit_vpextrw(&mut self, src: XMM, lane: u8, dst: GPR);
+    fn emit_vpextrd(&mut self, src: XMM, lane: u8, dst: GPR);
+    fn emit_vpextrq(&mut self, src: XMM, lane: u8, dst: GPR);
+
+    fn emit_vptest(&mut self, src1: XMM, src2: XMMOrMemory);
+    fn emit_vpmovmskb(&mut self, src: XMM, dst: GPR);
+    fn emit_vmovmskps(&mut self, src: XMM, dst: GPR);
+    fn emit_vmovmskpd(&mut self, src: XMM, dst: GPR);
+
     fn emit_test_gpr_64(&mut self, reg: GPR);
 
     fn emit_ud2(&mut self);
@@ -623,6 +761,47 @@ macro_rules! avx_round_fn {
     }
 }
 
+macro_rules! avx_unop_fn {
+    ($ins:ident, $name:ident) => {
+        fn $name(&mut self, src: XMMOrMemory, dst: XMM) {
+            match src {
+                XMMOrMemory::XMM(x) => dynasm!(self ; $ins Rx((dst as u8)), Rx((x as u8))),
+                XMMOrMemory::Memory(base, disp) => dynasm!(self ; $ins Rx((dst as u8)), [Rq((base as u8)) + disp]),
+            }
+        }
+    }
+}
+
+macro_rules! avx_round_packed_fn {
+    ($ins:ident, $name:ident, $mode:expr) => {
+        fn $name(&mut self, src: XMMOrMemory, dst: XMM) {
+            match src {
+                XMMOrMemory::XMM(x) => dynasm!(self ; $ins Rx((dst as u8)), Rx((x as u8)), $mode),
+                XMMOrMemory::Memory(base, disp) => dynasm!(self ; $ins Rx((dst as u8)), [Rq((base as u8)) + disp], $mode),
+            }
+        }
+    }
+}
+
+macro_rules! avx_shift_imm8_fn {
+    ($ins:ident, $name:ident) => {
+        fn $name(&mut self, src: XMM, imm: u8, dst: XMM) {
+            dynasm!(self ; $ins Rx((dst as u8)), Rx((src as u8)), imm as i8);
+        }
+    }
+}
+
+macro_rules! avx_shuffle_fn {
+    ($ins:ident, $name:ident) => {
+        fn $name(&mut self, src: XMMOrMemory, imm: u8, dst: XMM) {
+            match src {
+                XMMOrMemory::XMM(x) => dynasm!(self ; $ins Rx((dst as u8)), Rx((x as u8)), imm as i8),
+                XMMOrMemory::Memory(base, disp) => dynasm!(self ; $ins Rx((dst as u8)), [Rq((base as u8)) + disp], imm as i8),
+            }
+        }
+    }
+}
+
 impl Emitter for Assembler {
     type Label = DynamicLabel;
     type Offset = AssemblyOffset;
@@ -753,32 +932,32 @@ impl Emitter for Assembler {
                     (Size::S32, Location::Imm64(src), Location::Memory(dst, disp)) => {
                         dynasm!(self ; mov DWORD [Rq(dst as u8) + disp], src as i32);
                     }
-                    (Size::S32, Location::GPR(src), Location::XMM(dst)) => {
+                    (Size::S32, Location::GPR(src), Location::SIMD(dst)) => {
                         dynasm!(self ; movd Rx(dst as u8), Rd(src as u8));
                     }
-                    (Size::S32, Location::XMM(src), Location::GPR(dst)) => {
+                    (Size::S32, Location::SIMD(src), Location::GPR(dst)) => {
                         dynasm!(self ; movd Rd(dst as u8), Rx(src as u8));
                     }
-                    (Size::S32, Location::Memory(src, disp), Location::XMM(dst)) => {
+                    (Size::S32, Location::Memory(src, disp), Location::SIMD(dst)) => {
                         dynasm!(self ; movd Rx(dst as u8), [Rq(src as u8) + disp]);
                     }
-                    (Size::S32, Location::XMM(src), Location::Memory(dst, disp)) => {
+                    (Size::S32, Location::SIMD(src), Location::Memory(dst, disp)) => {
                         dynasm!(self ; movd [Rq(dst as u8) + disp], Rx(src as u8));
                     }
 
-                    (Size::S64, Location::GPR(src), Location::XMM(dst)) => {
+                    (Size::S64, Location::GPR(src), Location::SIMD(dst)) => {
                         dynasm!(self ; movq Rx(dst as u8), Rq(src as u8));
                     }
-                    (Size::S64, Location::XMM(src), Location::GPR(dst)) => {
+                    (Size::S64, Location::SIMD(src), Location::GPR(dst)) => {
                         dynasm!(self ; movq Rq(dst as u8), Rx(src as u8));
                     }
-                    (Size::S64, Location::Memory(src, disp), Location::XMM(dst)) => {
+                    (Size::S64, Location::Memory(src, disp), Location::SIMD(dst)) => {
                         dynasm!(self ; movq Rx(dst as u8), [Rq(src as u8) + disp]);
                     }
-                    (Size::S64, Location::XMM(src), Location::Memory(dst, disp)) => {
+                    (Size::S64, Location::SIMD(src), Location::Memory(dst, disp)) => {
                         dynasm!(self ; movq [Rq(dst as u8) + disp], Rx(src as u8));
                     }
-                    (_, Location::XMM(src), Location::XMM(dst)) => {
+                    (_, Location::SIMD(src), Location::SIMD(dst)) => {
                         dynasm!(self ; movq Rx(dst as u8), Rx(src as u8));
                     }
 
@@ -1313,6 +1492,212 @@ impl Emitter for Assembler {
         }
     }
 
+    fn emit_vmovdqu(&mut self, src: XMMOrMemory, dst: XMMOrMemory) {
+        match (src, dst) {
+            (XMMOrMemory::XMM(src), XMMOrMemory::XMM(dst)) => {
+                dynasm!(self ; vmovdqu Rx(dst as u8), Rx(src as u8))
+            }
+            (XMMOrMemory::Memory(base, disp), XMMOrMemory::XMM(dst)) => {
+                dynasm!(self ; vmovdqu Rx(dst as u8), [Rq(base as u8) + disp])
+            }
+            (XMMOrMemory::XMM(src), XMMOrMemory::Memory(base, disp)) => {
+                dynasm!(self ; vmovdqu [Rq(base as u8) + disp], Rx(src as u8))
+            }
+            _ => panic!("singlepass can't emit VMOVDQU {:?} {:?}", src, dst),
+        };
+    }
+
+    avx_fn!(vpand, emit_vpand);
+    avx_fn!(vpandn, emit_vpandn);
+    avx_fn!(vpor, emit_vpor);
+    avx_fn!(vpxor, emit_vpxor);
+
+    avx_fn!(vpaddb, emit_vpaddb);
+    avx_fn!(vpaddw, emit_vpaddw);
+    avx_fn!(vpaddd, emit_vpaddd);
+    avx_fn!(vpaddq, emit_vpaddq);
+    avx_fn!(vpsubb, emit_vpsubb);
+    avx_fn!(vpsubw, emit_vpsubw);
+    avx_fn!(vpsubd, emit_vpsubd);
+    avx_fn!(vpsubq, emit_vpsubq);
+
+    avx_fn!(vpaddsb, emit_vpaddsb);
+    avx_fn!(vpaddsw, emit_vpaddsw);
+    avx_fn!(vpaddusb, emit_vpaddusb);
+    avx_fn!(vpaddusw, emit_vpaddusw);
+    avx_fn!(vpsubsb, emit_vpsubsb);
+    avx_fn!(vpsubsw, emit_vpsubsw);
+    avx_fn!(vpsubusb, emit_vpsubusb);
+    avx_fn!(vpsubusw, emit_vpsubusw);
+
+    avx_fn!(vpmullw, emit_vpmullw);
+    avx_fn!(vpmulld, emit_vpmulld);
+    avx_fn!(vpmuludq, emit_vpmuludq);
+    avx_fn!(vpmaddwd, emit_vpmaddwd);
+
+    avx_fn!(vpminsb, emit_vpminsb);
+    avx_fn!(vpminsw, emit_vpminsw);
+    avx_fn!(vpminsd, emit_vpminsd);
+    avx_fn!(vpminub, emit_vpminub);
+    avx_fn!(vpminuw, emit_vpminuw);
+    avx_fn!(vpminud, emit_vpminud);
+
+    avx_fn!(vpmaxsb, emit_vpmaxsb);
+    avx_fn!(vpmaxsw, emit_vpmaxsw);
+    avx_fn!(vpmaxsd, emit_vpmaxsd);
+    avx_fn!(vpmaxub, emit_vpmaxub);
+    avx_fn!(vpmaxuw, emit_vpmaxuw);
+    avx_fn!(vpmaxud, emit_vpmaxud);
+
+    avx_fn!(vpavgb, emit_vpavgb);
+    avx_fn!(vpavgw, emit_vpavgw);
+
+    avx_fn!(vpcmpeqb, emit_vpcmpeqb);
+    avx_fn!(vpcmpeqw, emit_vpcmpeqw);
+    avx_fn!(vpcmpeqd, emit_vpcmpeqd);
+    avx_fn!(vpcmpeqq, emit_vpcmpeqq);
+    avx_fn!(vpcmpgtb, emit_vpcmpgtb);
+    avx_fn!(vpcmpgtw, emit_vpcmpgtw);
+    avx_fn!(vpcmpgtd, emit_vpcmpgtd);
+    avx_fn!(vpcmpgtq, emit_vpcmpgtq);
+
+    avx_fn!(vpacksswb, emit_vpacksswb);
+    avx_fn!(vpackuswb, emit_vpackuswb);
+    avx_fn!(vpackssdw, emit_vpackssdw);
+    avx_fn!(vpackusdw, emit_vpackusdw);
+
+    avx_fn!(vpunpcklbw, emit_vpunpcklbw);
+    avx_fn!(vpunpckhbw, emit_vpunpckhbw);
+    avx_fn!(vpunpcklqdq, emit_vpunpcklqdq);
+    avx_fn!(vpunpckhqdq, emit_vpunpckhqdq);
+
+    avx_fn!(vpshufb, emit_vpshufb);
+
+    avx_fn!(vpsllw, emit_vpsllw);
+    avx_fn!(vpslld, emit_vpslld);
+    avx_fn!(vpsllq, emit_vpsllq);
+    avx_fn!(vpsrlw, emit_vpsrlw);
+    avx_fn!(vpsrld, emit_vpsrld);
+    avx_fn!(vpsrlq, emit_vpsrlq);
+    avx_fn!(vpsraw, emit_vpsraw);
+    avx_fn!(vpsrad, emit_vpsrad);
+
+    avx_fn!(vaddps, emit_vaddps);
+    avx_fn!(vaddpd, emit_vaddpd);
+    avx_fn!(vsubps, emit_vsubps);
+    avx_fn!(vsubpd, emit_vsubpd);
+    avx_fn!(vmulps, emit_vmulps);
+    avx_fn!(vmulpd, emit_vmulpd);
+    avx_fn!(vdivps, emit_vdivps);
+    avx_fn!(vdivpd, emit_vdivpd);
+
+    avx_fn!(vminps, emit_vminps);
+    avx_fn!(vminpd, emit_vminpd);
+    avx_fn!(vmaxps, emit_vmaxps);
+    avx_fn!(vmaxpd, emit_vmaxpd);
+
+    avx_fn!(vcmpeqps, emit_vcmpeqps);
+    avx_fn!(vcmpeqpd, emit_vcmpeqpd);
+    avx_fn!(vcmpneqps, emit_vcmpneqps);
+    avx_fn!(vcmpneqpd, emit_vcmpneqpd);
+    avx_fn!(vcmpltps, emit_vcmpltps);
+    avx_fn!(vcmpltpd, emit_vcmpltpd);
+    avx_fn!(vcmpleps, emit_vcmpleps);
+    avx_fn!(vcmplepd, emit_vcmplepd);
+    avx_fn!(vcmpunordps, emit_vcmpunordps);
+    avx_fn!(vcmpunordpd, emit_vcmpunordpd);
+
+    avx_unop_fn!(vpmovsxbw, emit_vpmovsxbw);
+    avx_unop_fn!(vpmovzxbw, emit_vpmovzxbw);
+    avx_unop_fn!(vpmovsxwd, emit_vpmovsxwd);
+    avx_unop_fn!(vpmovzxwd, emit_vpmovzxwd);
+    avx_unop_fn!(vpmovsxdq, emit_vpmovsxdq);
+    avx_unop_fn!(vpmovzxdq, emit_vpmovzxdq);
+
+    avx_unop_fn!(vpabsb, emit_vpabsb);
+    avx_unop_fn!(vpabsw, emit_vpabsw);
+    avx_unop_fn!(vpabsd, emit_vpabsd);
+
+    avx_unop_fn!(vsqrtps, emit_vsqrtps);
+    avx_unop_fn!(vsqrtpd, emit_vsqrtpd);
+
+    avx_unop_fn!(vcvtdq2ps, emit_vcvtdq2ps);
+    avx_unop_fn!(vcvttps2dq, emit_vcvttps2dq);
+
+    avx_round_packed_fn!(vroundps, emit_vroundps_nearest, 0);
+    avx_round_packed_fn!(vroundps, emit_vroundps_floor, 1);
+    avx_round_packed_fn!(vroundps, emit_vroundps_ceil, 2);
+    avx_round_packed_fn!(vroundps, emit_vroundps_trunc, 3);
+    avx_round_packed_fn!(vroundpd, emit_vroundpd_nearest, 0);
+    avx_round_packed_fn!(vroundpd, emit_vroundpd_floor, 1);
+    avx_round_packed_fn!(vroundpd, emit_vroundpd_ceil, 2);
+    avx_round_packed_fn!(vroundpd, emit_vroundpd_trunc, 3);
+
+    avx_shift_imm8_fn!(vpsllw, emit_vpsllw_imm8);
+    avx_shift_imm8_fn!(vpslld, emit_vpslld_imm8);
+    avx_shift_imm8_fn!(vpsllq, emit_vpsllq_imm8);
+    avx_shift_imm8_fn!(vpsrlw, emit_vpsrlw_imm8);
+    avx_shift_imm8_fn!(vpsrld, emit_vpsrld_imm8);
+    avx_shift_imm8_fn!(vpsrlq, emit_vpsrlq_imm8);
+    avx_shift_imm8_fn!(vpsraw, emit_vpsraw_imm8);
+    avx_shift_imm8_fn!(vpsrad, emit_vpsrad_imm8);
+
+    avx_shuffle_fn!(vpshufd, emit_vpshufd);
+    avx_shuffle_fn!(vpshuflw, emit_vpshuflw);
+
+    fn emit_vpinsrb(&mut self, src1: XMM, src2: GPR, lane: u8, dst: XMM) {
+        dynasm!(self ; vpinsrb Rx(dst as u8), Rx(src1 as u8), Rd(src2 as u8), lane as i8);
+    }
+
+    fn emit_vpinsrw(&mut self, src1: XMM, src2: GPR, lane: u8, dst: XMM) {
+        dynasm!(self ; vpinsrw Rx(dst as u8), Rx(src1 as u8), Rd(src2 as u8), lane as i8);
+    }
+
+    fn emit_vpinsrd(&mut self, src1: XMM, src2: GPR, lane: u8, dst: XMM) {
+        dynasm!(self ; vpinsrd Rx(dst as u8), Rx(src1 as u8), Rd(src2 as u8), lane as i8);
+    }
+
+    fn emit_vpinsrq(&mut self, src1: XMM, src2: GPR, lane: u8, dst: XMM) {
+        dynasm!(self ; vpinsrq Rx(dst as u8), Rx(src1 as u8), Rq(src2 as u8), lane as i8);
+    }
+
+    fn emit_vpextrb(&mut self, src: XMM, lane: u8, dst: GPR) {
+        dynasm!(self ; vpextrb Rd(dst as u8), Rx(src as u8), lane as i8);
+    }
+
+    fn emit_vpextrw(&mut self, src: XMM, lane: u8, dst: GPR) {
+        dynasm!(self ; vpextrw Rd(dst as u8), Rx(src as u8), lane as i8);
+    }
+
+    fn emit_vpextrd(&mut self, src: XMM, lane: u8, dst: GPR) {
+        dynasm!(self ; vpextrd Rd(dst as u8), Rx(src as u8), lane as i8);
+    }
+
+    fn emit_vpextrq(&mut self, src: XMM, lane: u8, dst: GPR) {
+        dynasm!(self ; vpextrq Rq(dst as u8), Rx(src as u8), lane as i8);
+    }
+
+    fn emit_vptest(&mut self, src1: XMM, src2: XMMOrMemory) {
+        match src2 {
+            XMMOrMemory::XMM(x) => dynasm!(self ; vptest Rx(src1 as u8), Rx(x as u8)),
+            XMMOrMemory::Memory(base, disp) => {
+                dynasm!(self ; vptest Rx(src1 as u8), [Rq(base as u8) + disp])
+            }
+        }
+    }
+
+    fn emit_vpmovmskb(&mut self, src: XMM, dst: GPR) {
+        dynasm!(self ; pmovmskb Rd(dst as u8), Rx(src as u8));
+    }
+
+    fn emit_vmovmskps(&mut self, src: XMM, dst: GPR) {
+        dynasm!(self ; movmskps Rd(dst as u8), Rx(src as u8));
+    }
+
+    fn emit_vmovmskpd(&mut self, src: XMM, dst: GPR) {
+        dynasm!(self ; movmskpd Rd(dst as u8), Rx(src as u8));
+    }
+
     fn emit_ucomiss(&mut self, src: XMMOrMemory, dst: XMM) {
         match src {
             XMMOrMemory::XMM(x) => dynasm!(self ; ucomiss Rx(dst as u8), Rx(x as u8)),
@@ -0,0 +1,31 @@
+//! Architecture-independent description of where a value lives.
+
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// A machine register.
+pub trait Reg: Copy + Clone + Eq + PartialEq + Debug + Hash + Ord + 'static {}
+
+impl<T: Copy + Clone + Eq + PartialEq + Debug + Hash + Ord + 'static> Reg for T {}
+
+/// The location of a value, given an architecture's general purpose registers `R` and
+/// floating point/SIMD registers `S`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Location<R, S> {
+    Imm8(u8),
+    Imm32(u32),
+    Imm64(u64),
+    // Imm128(u128),
+    GPR(R),
+    SIMD(S),
+    Memory(R, i32),
+    MemoryAddTriple(R, R, i32),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Size {
+    S8,
+    S16,
+    S32,
+    S64,
+}
@@ -1,6 +1,5 @@
 use crate::common_decl::*;
-use crate::emitter_x64::*;
-use crate::x64_decl::{new_machine_state, X64Register};
+use crate::location::{Location as AbstractLocation, Reg};
 use smallvec::smallvec;
 use smallvec::SmallVec;
 use std::cmp;
@@ -9,25 +8,95 @@ use wasmer_compiler::wasmparser::Type as WpType;
 
 const NATIVE_PAGE_SIZE: usize = 4096;
 
+/// The architecture-specific part of a `Machine`: its registers, its calling convention
+/// and the few instructions the machine emits by itself.
+pub trait MachineArch {
+    /// General purpose registers.
+    type GPR: Reg;
+    /// Floating point/SIMD registers.
+    type SIMD: Reg;
+    /// The assembler the machine emits code with.
+    type Assembler;
+
+    /// Register holding the vmctx, saved and restored by the function itself.
+    const VMCTX: Self::GPR;
+    /// Frame pointer, the base of all stack slots.
+    const FP: Self::GPR;
+    /// Caller-saved registers for stack values.
+    const GPRS: &'static [Self::GPR];
+    /// Caller-saved registers for internal temporary use.
+    const TEMP_GPRS: &'static [Self::GPR];
+    /// Callee-saved registers holding the first locals.
+    const LOCAL_GPRS: &'static [Self::GPR];
+    /// Floating point registers for stack values.
+    const SIMDS: &'static [Self::SIMD];
+    /// Floating point registers for internal temporary use.
+    const TEMP_SIMDS: &'static [Self::SIMD];
+
+    /// Index of a general purpose register in the `MachineState`.
+    fn gpr_index(r: Self::GPR) -> RegisterIndex;
+    /// Index of a floating point/SIMD register in the `MachineState`.
+    fn simd_index(r: Self::SIMD) -> RegisterIndex;
+    /// Creates a `MachineState` with room for all the registers.
+    fn new_machine_state() -> MachineState;
+
+    /// Location of parameter slot `idx` on function entry. Slot 0 holds the vmctx.
+    fn param_location(idx: usize) -> AbstractLocation<Self::GPR, Self::SIMD>;
+
+    /// Moves the stack pointer from `old_offset` to `new_offset` bytes below the frame
+    /// pointer.
+    fn emit_stack_adjust(a: &mut Self::Assembler, old_offset: usize, new_offset: usize);
+    /// Moves a 64-bit value. May clobber a scratch register when neither side is a register.
+    fn emit_move(
+        a: &mut Self::Assembler,
+        src: AbstractLocation<Self::GPR, Self::SIMD>,
+        dst: AbstractLocation<Self::GPR, Self::SIMD>,
+    );
+    /// Zeroes `n` consecutive 8-byte stack slots, the lowest one being `start`.
+    ///
+    /// Only called while setting up the frame, before any local is live in a register.
+    fn emit_zero_slots(
+        a: &mut Self::Assembler,
+        start: AbstractLocation<Self::GPR, Self::SIMD>,
+        n: usize,
+    );
+    /// Restores the registers saved in the save area, in the order they were saved,
+    /// the save area ending `save_area_offset` bytes below the frame pointer.
+    fn emit_restore_registers(a: &mut Self::Assembler, save_area_offset: usize, regs: &[Self::GPR]);
+}
+
+type Location<A> = AbstractLocation<<A as MachineArch>::GPR, <A as MachineArch>::SIMD>;
+
 struct MachineStackOffset(usize);
 
-pub struct Machine {
-    used_gprs: HashSet<GPR>,
-    used_xmms: HashSet<XMM>,
+pub struct Machine<A: MachineArch> {
+    used_gprs: HashSet<A::GPR>,
+    used_simd: HashSet<A::SIMD>,
     stack_offset: MachineStackOffset,
     save_area_offset: Option<MachineStackOffset>,
+    /// Stack offsets of the 16-byte slots holding `v128` stack values.
+    v128_stack_slots: HashSet<usize>,
+    /// Stack slot holding the pointer to the caller's return area, for functions returning
+    /// multiple values.
+    return_area_ptr: Option<Location<A>>,
+    /// Area in the static part of the frame used to pass multiple values between blocks and
+    /// to receive the results of calls returning multiple values.
+    value_area: Option<Location<A>>,
     pub state: MachineState,
     pub(crate) track_state: bool,
 }
 
-impl Machine {
+impl<A: MachineArch> Machine<A> {
     pub fn new() -> Self {
         Machine {
             used_gprs: HashSet::new(),
-            used_xmms: HashSet::new(),
+            used_simd: HashSet::new(),
             stack_offset: MachineStackOffset(0),
             save_area_offset: None,
-            state: new_machine_state(),
+            v128_stack_slots: HashSet::new(),
+            return_area_ptr: None,
+            value_area: None,
+            state: A::new_machine_state(),
             track_state: true,
         }
     }
@@ -36,48 +105,65 @@ impl Machine {
         self.stack_offset.0
     }
 
-    pub fn get_used_gprs(&self) -> Vec<GPR> {
+    pub fn get_used_gprs(&self) -> Vec<A::GPR> {
         self.used_gprs.iter().cloned().collect()
     }
 
-    pub fn get_used_xmms(&self) -> Vec<XMM> {
-        self.used_xmms.iter().cloned().collect()
+    pub fn get_used_simd(&self) -> Vec<A::SIMD> {
+        self.used_simd.iter().cloned().collect()
+    }
+
+    /// Whether `loc` is a stack slot holding a `v128` value.
+    pub fn is_v128_stack_slot(&self, loc: Location<A>) -> bool {
+        match loc {
+            AbstractLocation::Memory(base, x) if base == A::FP && x < 0 => {
+                self.v128_stack_slots.contains(&((-x) as usize))
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the stack slot holding the pointer to the caller's return area.
+    ///
+    /// Only available in functions returning multiple values.
+    pub fn get_return_area_ptr(&self) -> Location<A> {
+        self.return_area_ptr
+            .expect("get_return_area_ptr: function does not return multiple values")
+    }
+
+    /// Returns the lowest address of the value area, whose 16-byte slots hold the values
+    /// passed between blocks with multiple results.
+    pub fn get_value_area(&self) -> Location<A> {
+        self.value_area
+            .expect("get_value_area: no value area was reserved")
     }
 
-    pub fn get_vmctx_reg() -> GPR {
-        GPR::R15
+    pub fn get_vmctx_reg() -> A::GPR {
+        A::VMCTX
     }
 
     /// Picks an unused general purpose register for local/stack/argument use.
     ///
     /// This method does not mark the register as used.
-    pub fn pick_gpr(&self) -> Option<GPR> {
-        use GPR::*;
-        static REGS: &[GPR] = &[RSI, RDI, R8, R9, R10, R11];
-        for r in REGS {
-            if !self.used_gprs.contains(r) {
-                return Some(*r);
-            }
-        }
-        None
+    pub fn pick_gpr(&self) -> Option<A::GPR> {
+        A::GPRS
+            .iter()
+            .find(|r| !self.used_gprs.contains(r))
+            .cloned()
     }
 
     /// Picks an unused general purpose register for internal temporary use.
     ///
     /// This method does not mark the register as used.
-    pub fn pick_temp_gpr(&self) -> Option<GPR> {
-        use GPR::*;
-        static REGS: &[GPR] = &[RAX, RCX, RDX];
-        for r in REGS {
-            if !self.used_gprs.contains(r) {
-                return Some(*r);
-            }
-        }
-        None
+    pub fn pick_temp_gpr(&self) -> Option<A::GPR> {
+        A::TEMP_GPRS
+            .iter()
+            .find(|r| !self.used_gprs.contains(r))
+            .cloned()
     }
 
     /// Acquires a temporary GPR.
-    pub fn acquire_temp_gpr(&mut self) -> Option<GPR> {
+    pub fn acquire_temp_gpr(&mut self) -> Option<A::GPR> {
         let gpr = self.pick_temp_gpr();
         if let Some(x) = gpr {
             self.used_gprs.insert(x);
@@ -86,92 +172,94 @@ impl Machine {
     }
 
     /// Releases a temporary GPR.
-    pub fn release_temp_gpr(&mut self, gpr: GPR) {
+    pub fn release_temp_gpr(&mut self, gpr: A::GPR) {
         assert!(self.used_gprs.remove(&gpr));
     }
 
     /// Specify that a given register is in use.
-    pub fn reserve_unused_temp_gpr(&mut self, gpr: GPR) -> GPR {
+    pub fn reserve_unused_temp_gpr(&mut self, gpr: A::GPR) -> A::GPR {
         assert!(!self.used_gprs.contains(&gpr));
         self.used_gprs.insert(gpr);
         gpr
     }
 
-    /// Picks an unused XMM register.
+    /// Picks an unused floating point/SIMD register.
     ///
     /// This method does not mark the register as used.
-    pub fn pick_xmm(&self) -> Option<XMM> {
-        use XMM::*;
-        static REGS: &[XMM] = &[XMM3, XMM4, XMM5, XMM6, XMM7];
-        for r in REGS {
-            if !self.used_xmms.contains(r) {
-                return Some(*r);
-            }
-        }
-        None
+    pub fn pick_simd(&self) -> Option<A::SIMD> {
+        A::SIMDS
+            .iter()
+            .find(|r| !self.used_simd.contains(r))
+            .cloned()
     }
 
-    /// Picks an unused XMM register for internal temporary use.
+    /// Picks an unused floating point/SIMD register for internal temporary use.
     ///
     /// This method does not mark the register as used.
-    pub fn pick_temp_xmm(&self) -> Option<XMM> {
-        use XMM::*;
-        static REGS: &[XMM] = &[XMM0, XMM1, XMM2];
-        for r in REGS {
-            if !self.used_xmms.contains(r) {
-                return Some(*r);
-            }
-        }
-        None
+    pub fn pick_temp_simd(&self) -> Option<A::SIMD> {
+        A::TEMP_SIMDS
+            .iter()
+            .find(|r| !self.used_simd.contains(r))
+            .cloned()
     }
 
-    /// Acquires a temporary XMM register.
-    pub fn acquire_temp_xmm(&mut self) -> Option<XMM> {
-        let xmm = self.pick_temp_xmm();
-        if let Some(x) = xmm {
-            self.used_xmms.insert(x);
+    /// Acquires a temporary floating point/SIMD register.
+    pub fn acquire_temp_simd(&mut self) -> Option<A::SIMD> {
+        let simd = self.pick_temp_simd();
+        if let Some(x) = simd {
+            self.used_simd.insert(x);
         }
-        xmm
+        simd
     }
 
-    /// Releases a temporary XMM register.
-    pub fn release_temp_xmm(&mut self, xmm: XMM) {
-        assert_eq!(self.used_xmms.remove(&xmm), true);
+    /// Releases a temporary floating point/SIMD register.
+    pub fn release_temp_simd(&mut self, simd: A::SIMD) {
+        assert_eq!(self.used_simd.remove(&simd), true);
     }
 
     /// Acquires locations from the machine state.
     ///
     /// If the returned locations are used for stack value, `release_location` needs to be called on them;
     /// Otherwise, if the returned locations are used for locals, `release_location` does not need to be called on them.
-    pub fn acquire_locations<E: Emitter>(
+    pub fn acquire_locations(
         &mut self,
-        assembler: &mut E,
+        assembler: &mut A::Assembler,
         tys: &[(WpType, MachineValue)],
         zeroed: bool,
-    ) -> SmallVec<[Location; 1]> {
+    ) -> SmallVec<[Location<A>; 1]> {
         let mut ret = smallvec![];
-        let mut delta_stack_offset: usize = 0;
+        let old_stack_offset = self.stack_offset.0;
 
         for (ty, mv) in tys {
             let loc = match *ty {
-                WpType::F32 | WpType::F64 => self.pick_xmm().map(Location::XMM),
-                WpType::I32 | WpType::I64 => self.pick_gpr().map(Location::GPR),
+                WpType::F32 | WpType::F64 => self.pick_simd().map(AbstractLocation::SIMD),
+                // References are represented as pointers.
+                WpType::I32 | WpType::I64 | WpType::FuncRef | WpType::ExternRef => {
+                    self.pick_gpr().map(AbstractLocation::GPR)
+                }
+                // 128-bit values always live in a 16-byte stack slot.
+                WpType::V128 => None,
                 _ => unreachable!(),
             };
 
             let loc = if let Some(x) = loc {
                 x
             } else {
-                self.stack_offset.0 += 8;
-                delta_stack_offset += 8;
-                Location::Memory(GPR::RBP, -(self.stack_offset.0 as i32))
+                let size = if *ty == WpType::V128 { 16 } else { 8 };
+                self.stack_offset.0 += size;
+                if size == 16 {
+                    self.v128_stack_slots.insert(self.stack_offset.0);
+                    // One entry for each 8-byte half.
+                    self.state.stack_values.push(mv.clone());
+                }
+                AbstractLocation::Memory(A::FP, -(self.stack_offset.0 as i32))
             };
-            if let Location::GPR(x) = loc {
+            if let AbstractLocation::GPR(x) = loc {
                 self.used_gprs.insert(x);
-                self.state.register_values[X64Register::GPR(x).to_index().0] = mv.clone();
-            } else if let Location::XMM(x) = loc {
-                self.used_xmms.insert(x);
-                self.state.register_values[X64Register::XMM(x).to_index().0] = mv.clone();
+                self.state.register_values[A::gpr_index(x).0] = mv.clone();
+            } else if let AbstractLocation::SIMD(x) = loc {
+                self.used_simd.insert(x);
+                self.state.register_values[A::simd_index(x).0] = mv.clone();
             } else {
                 self.state.stack_values.push(mv.clone());
             }
@@ -179,38 +267,41 @@ impl Machine {
             ret.push(loc);
         }
 
-        if delta_stack_offset != 0 {
-            assembler.emit_sub(
-                Size::S64,
-                Location::Imm32(delta_stack_offset as u32),
-                Location::GPR(GPR::RSP),
-            );
+        if self.stack_offset.0 != old_stack_offset {
+            A::emit_stack_adjust(assembler, old_stack_offset, self.stack_offset.0);
         }
         if zeroed {
             for i in 0..tys.len() {
-                assembler.emit_mov(Size::S64, Location::Imm32(0), ret[i]);
+                A::emit_move(assembler, AbstractLocation::Imm32(0), ret[i]);
+                if let AbstractLocation::Memory(base, x) = ret[i] {
+                    if tys[i].0 == WpType::V128 {
+                        A::emit_move(
+                            assembler,
+                            AbstractLocation::Imm32(0),
+                            AbstractLocation::Memory(base, x + 8),
+                        );
+                    }
+                }
             }
         }
         ret
     }
 
     /// Releases locations used for stack value.
-    pub fn release_locations<E: Emitter>(&mut self, assembler: &mut E, locs: &[Location]) {
-        let mut delta_stack_offset: usize = 0;
+    pub fn release_locations(&mut self, assembler: &mut A::Assembler, locs: &[Location<A>]) {
+        let old_stack_offset = self.stack_offset.0;
 
         for loc in locs.iter().rev() {
             match *loc {
-                Location::GPR(ref x) => {
+                AbstractLocation::GPR(ref x) => {
                     assert_eq!(self.used_gprs.remove(x), true);
-                    self.state.register_values[X64Register::GPR(*x).to_index().0] =
-                        MachineValue::Undefined;
+                    self.state.register_values[A::gpr_index(*x).0] = MachineValue::Undefined;
                 }
-                Location::XMM(ref x) => {
-                    assert_eq!(self.used_xmms.remove(x), true);
-                    self.state.register_values[X64Register::XMM(*x).to_index().0] =
-                        MachineValue::Undefined;
+                AbstractLocation::SIMD(ref x) => {
+                    assert_eq!(self.used_simd.remove(x), true);
+                    self.state.register_values[A::simd_index(*x).0] = MachineValue::Undefined;
                 }
-                Location::Memory(GPR::RBP, x) => {
+                AbstractLocation::Memory(base, x) if base == A::FP => {
                     if x >= 0 {
                         unreachable!();
                     }
@@ -218,36 +309,40 @@ impl Machine {
                     if offset != self.stack_offset.0 {
                         unreachable!();
                     }
-                    self.stack_offset.0 -= 8;
-                    delta_stack_offset += 8;
-                    self.state.stack_values.pop().unwrap();
+                    let size = self.release_stack_slot(offset);
+                    self.stack_offset.0 -= size;
                 }
                 _ => {}
             }
             self.state.wasm_stack.pop().unwrap();
         }
 
-        if delta_stack_offset != 0 {
-            assembler.emit_add(
-                Size::S64,
-                Location::Imm32(delta_stack_offset as u32),
-                Location::GPR(GPR::RSP),
-            );
+        if self.stack_offset.0 != old_stack_offset {
+            A::emit_stack_adjust(assembler, old_stack_offset, self.stack_offset.0);
         }
     }
 
-    pub fn release_locations_only_regs(&mut self, locs: &[Location]) {
+    /// Forgets about the stack slot at `offset`, returning its size.
+    fn release_stack_slot(&mut self, offset: usize) -> usize {
+        self.state.stack_values.pop().unwrap();
+        if self.v128_stack_slots.remove(&offset) {
+            self.state.stack_values.pop().unwrap();
+            16
+        } else {
+            8
+        }
+    }
+
+    pub fn release_locations_only_regs(&mut self, locs: &[Location<A>]) {
         for loc in locs.iter().rev() {
             match *loc {
-                Location::GPR(ref x) => {
+                AbstractLocation::GPR(ref x) => {
                     assert_eq!(self.used_gprs.remove(x), true);
-                    self.state.register_values[X64Register::GPR(*x).to_index().0] =
-                        MachineValue::Undefined;
+                    self.state.register_values[A::gpr_index(*x).0] = MachineValue::Undefined;
                 }
-                Location::XMM(ref x) => {
-                    assert_eq!(self.used_xmms.remove(x), true);
-                    self.state.register_values[X64Register::XMM(*x).to_index().0] =
-                        MachineValue::Undefined;
+                AbstractLocation::SIMD(ref x) => {
+                    assert_eq!(self.used_simd.remove(x), true);
+                    self.state.register_values[A::simd_index(*x).0] = MachineValue::Undefined;
                 }
                 _ => {}
             }
@@ -255,35 +350,33 @@ impl Machine {
         }
     }
 
-    pub fn release_locations_only_stack<E: Emitter>(
+    pub fn release_locations_only_stack(
         &mut self,
-        assembler: &mut E,
-        locs: &[Location],
+        assembler: &mut A::Assembler,
+        locs: &[Location<A>],
     ) {
-        let mut delta_stack_offset: usize = 0;
+        let old_stack_offset = self.stack_offset.0;
 
         for loc in locs.iter().rev() {
-            if let Location::Memory(GPR::RBP, x) = *loc {
-                if x >= 0 {
-                    unreachable!();
-                }
-                let offset = (-x) as usize;
-                if offset != self.stack_offset.0 {
-                    unreachable!();
+            match *loc {
+                AbstractLocation::Memory(base, x) if base == A::FP => {
+                    if x >= 0 {
+                        unreachable!();
+                    }
+                    let offset = (-x) as usize;
+                    if offset != self.stack_offset.0 {
+                        unreachable!();
+                    }
+                    let size = self.release_stack_slot(offset);
+                    self.stack_offset.0 -= size;
                 }
-                self.stack_offset.0 -= 8;
-                delta_stack_offset += 8;
-                self.state.stack_values.pop().unwrap();
+                _ => {}
             }
             // Wasm state popping is deferred to `release_locations_only_osr_state`.
         }
 
-        if delta_stack_offset != 0 {
-            assembler.emit_add(
-                Size::S64,
-                Location::Imm32(delta_stack_offset as u32),
-                Location::GPR(GPR::RSP),
-            );
+        if self.stack_offset.0 != old_stack_offset {
+            A::emit_stack_adjust(assembler, old_stack_offset, self.stack_offset.0);
         }
     }
 
@@ -297,58 +390,69 @@ impl Machine {
         self.state.wasm_stack.truncate(new_length);
     }
 
-    pub fn release_locations_keep_state<E: Emitter>(&self, assembler: &mut E, locs: &[Location]) {
-        let mut delta_stack_offset: usize = 0;
+    pub fn release_locations_keep_state(&self, assembler: &mut A::Assembler, locs: &[Location<A>]) {
         let mut stack_offset = self.stack_offset.0;
 
         for loc in locs.iter().rev() {
-            if let Location::Memory(GPR::RBP, x) = *loc {
-                if x >= 0 {
-                    unreachable!();
-                }
-                let offset = (-x) as usize;
-                if offset != stack_offset {
-                    unreachable!();
+            match *loc {
+                AbstractLocation::Memory(base, x) if base == A::FP => {
+                    if x >= 0 {
+                        unreachable!();
+                    }
+                    let offset = (-x) as usize;
+                    if offset != stack_offset {
+                        unreachable!();
+                    }
+                    let size = if self.v128_stack_slots.contains(&offset) {
+                        16
+                    } else {
+                        8
+                    };
+                    stack_offset -= size;
                 }
-                stack_offset -= 8;
-                delta_stack_offset += 8;
+                _ => {}
             }
         }
 
-        if delta_stack_offset != 0 {
-            assembler.emit_add(
-                Size::S64,
-                Location::Imm32(delta_stack_offset as u32),
-                Location::GPR(GPR::RSP),
-            );
+        if stack_offset != self.stack_offset.0 {
+            A::emit_stack_adjust(assembler, self.stack_offset.0, stack_offset);
         }
     }
 
-    pub fn init_locals<E: Emitter>(
+    /// Allocates the locations of locals and loads the parameters into them.
+    ///
+    /// When `has_return_area` is set, the first parameter slot holds the pointer to the
+    /// return area instead of a parameter. `value_area_slots` 16-byte slots are reserved
+    /// for the value area.
+    pub fn init_locals(
         &mut self,
-        a: &mut E,
-        n: usize,
+        a: &mut A::Assembler,
+        local_types: &[WpType],
         n_params: usize,
-    ) -> Vec<Location> {
+        has_return_area: bool,
+        value_area_slots: usize,
+    ) -> Vec<Location<A>> {
+        let n = local_types.len();
+
         // Determine whether a local should be allocated on the stack.
-        fn is_local_on_stack(idx: usize) -> bool {
-            idx > 3
+        fn is_local_on_stack<A: MachineArch>(idx: usize, ty: WpType) -> bool {
+            idx >= A::LOCAL_GPRS.len() || ty == WpType::V128
         }
 
-        // Determine a local's location.
-        fn get_local_location(idx: usize, callee_saved_regs_size: usize) -> Location {
-            // Use callee-saved registers for the first locals.
-            match idx {
-                0 => Location::GPR(GPR::R12),
-                1 => Location::GPR(GPR::R13),
-                2 => Location::GPR(GPR::R14),
-                3 => Location::GPR(GPR::RBX),
-                _ => Location::Memory(GPR::RBP, -(((idx - 3) * 8 + callee_saved_regs_size) as i32)),
+        // Size (in bytes) of a local on the stack.
+        fn local_size(ty: WpType) -> usize {
+            if ty == WpType::V128 {
+                16
+            } else {
+                8
             }
         }
 
         // How many machine stack slots will all the locals use?
-        let num_mem_slots = (0..n).filter(|&x| is_local_on_stack(x)).count();
+        let num_mem_slots: usize = (0..n)
+            .filter(|&x| is_local_on_stack::<A>(x, local_types[x]))
+            .map(|x| local_size(local_types[x]) / 8)
+            .sum();
 
         // Total size (in bytes) of the pre-allocated "static area" for this function's
         // locals and callee-saved registers.
@@ -358,138 +462,178 @@ impl Machine {
         // Keep this consistent with the "Save callee-saved registers" code below.
         for i in 0..n {
             // If a local is not stored on stack, then it is allocated to a callee-saved register.
-            if !is_local_on_stack(i) {
+            if !is_local_on_stack::<A>(i, local_types[i]) {
                 static_area_size += 8;
             }
         }
 
-        // Callee-saved R15 for vmctx.
+        // Callee-saved register for vmctx.
         static_area_size += 8;
 
         // Total size of callee saved registers.
         let callee_saved_regs_size = static_area_size;
 
+        // The return area pointer is stored right after the callee-saved registers.
+        let mut local_stack_offset = callee_saved_regs_size;
+        let mut extra_slots = 0;
+        if has_return_area {
+            local_stack_offset += 8;
+            extra_slots += 1;
+            self.return_area_ptr = Some(AbstractLocation::Memory(
+                A::FP,
+                -(local_stack_offset as i32),
+            ));
+        }
+
         // Now we can determine concrete locations for locals.
-        let locations: Vec<Location> = (0..n)
-            .map(|i| get_local_location(i, callee_saved_regs_size))
+        let locations: Vec<Location<A>> = (0..n)
+            .map(|i| {
+                if is_local_on_stack::<A>(i, local_types[i]) {
+                    local_stack_offset += local_size(local_types[i]);
+                    AbstractLocation::Memory(A::FP, -(local_stack_offset as i32))
+                } else {
+                    // Use callee-saved registers for the first locals.
+                    AbstractLocation::GPR(A::LOCAL_GPRS[i])
+                }
+            })
             .collect();
 
-        // Add size of locals on stack.
-        static_area_size += num_mem_slots * 8;
+        // The value area follows the locals.
+        if value_area_slots > 0 {
+            local_stack_offset += value_area_slots * 16;
+            extra_slots += value_area_slots * 2;
+            self.value_area = Some(AbstractLocation::Memory(
+                A::FP,
+                -(local_stack_offset as i32),
+            ));
+        }
+
+        // Add size of locals and the other slots on stack.
+        static_area_size += (num_mem_slots + extra_slots) * 8;
 
         // Allocate save area, without actually writing to it.
-        a.emit_sub(
-            Size::S64,
-            Location::Imm32(static_area_size as _),
-            Location::GPR(GPR::RSP),
-        );
+        A::emit_stack_adjust(a, 0, static_area_size);
 
         // Save callee-saved registers.
         for loc in locations.iter() {
-            if let Location::GPR(x) = *loc {
+            if let AbstractLocation::GPR(x) = *loc {
                 self.stack_offset.0 += 8;
-                a.emit_mov(
-                    Size::S64,
+                A::emit_move(
+                    a,
                     *loc,
-                    Location::Memory(GPR::RBP, -(self.stack_offset.0 as i32)),
+                    AbstractLocation::Memory(A::FP, -(self.stack_offset.0 as i32)),
                 );
-                self.state.stack_values.push(MachineValue::PreserveRegister(
-                    X64Register::GPR(x).to_index(),
-                ));
+                self.state
+                    .stack_values
+                    .push(MachineValue::PreserveRegister(A::gpr_index(x)));
             }
         }
 
-        // Save R15 for vmctx use.
+        // Save the vmctx register.
         self.stack_offset.0 += 8;
-        a.emit_mov(
-            Size::S64,
-            Location::GPR(GPR::R15),
-            Location::Memory(GPR::RBP, -(self.stack_offset.0 as i32)),
+        A::emit_move(
+            a,
+            AbstractLocation::GPR(A::VMCTX),
+            AbstractLocation::Memory(A::FP, -(self.stack_offset.0 as i32)),
         );
-        self.state.stack_values.push(MachineValue::PreserveRegister(
-            X64Register::GPR(GPR::R15).to_index(),
-        ));
+        self.state
+            .stack_values
+            .push(MachineValue::PreserveRegister(A::gpr_index(A::VMCTX)));
 
         // Save the offset of register save area.
         self.save_area_offset = Some(MachineStackOffset(self.stack_offset.0));
 
+        if has_return_area {
+            self.state.stack_values.push(MachineValue::Undefined);
+        }
+
         // Save location information for locals.
         for (i, loc) in locations.iter().enumerate() {
             match *loc {
-                Location::GPR(x) => {
-                    self.state.register_values[X64Register::GPR(x).to_index().0] =
-                        MachineValue::WasmLocal(i);
+                AbstractLocation::GPR(x) => {
+                    self.state.register_values[A::gpr_index(x).0] = MachineValue::WasmLocal(i);
                 }
-                Location::Memory(_, _) => {
-                    self.state.stack_values.push(MachineValue::WasmLocal(i));
+                AbstractLocation::Memory(_, _) => {
+                    for _ in 0..local_size(local_types[i]) / 8 {
+                        self.state.stack_values.push(MachineValue::WasmLocal(i));
+                    }
                 }
                 _ => unreachable!(),
             }
         }
+        for _ in 0..value_area_slots * 2 {
+            self.state.stack_values.push(MachineValue::Undefined);
+        }
+
+        // Save the return area pointer before it can be overwritten.
+        if let Some(loc) = self.return_area_ptr {
+            A::emit_move(a, A::param_location(1), loc);
+        }
 
         // Load in-register parameters into the allocated locations.
         // Locals are allocated on the stack from higher address to lower address,
         // so we won't skip the stack guard page here.
+        // A `v128` parameter is passed in two consecutive parameter slots, low half first.
+        let mut param_slot = if has_return_area { 2 } else { 1 };
         for i in 0..n_params {
-            let loc = Self::get_param_location(i + 1);
-            match loc {
-                Location::GPR(_) => {
-                    a.emit_mov(Size::S64, loc, locations[i]);
-                }
-                Location::Memory(_, _) => match locations[i] {
-                    Location::GPR(_) => {
-                        a.emit_mov(Size::S64, loc, locations[i]);
-                    }
-                    Location::Memory(_, _) => {
-                        a.emit_mov(Size::S64, loc, Location::GPR(GPR::RAX));
-                        a.emit_mov(Size::S64, Location::GPR(GPR::RAX), locations[i]);
-                    }
-                    _ => unreachable!(),
-                },
-                _ => unreachable!(),
+            let halves: SmallVec<[Location<A>; 2]> = match (local_types[i], locations[i]) {
+                (WpType::V128, AbstractLocation::Memory(base, off)) => smallvec![
+                    AbstractLocation::Memory(base, off),
+                    AbstractLocation::Memory(base, off + 8)
+                ],
+                _ => smallvec![locations[i]],
+            };
+            for dst in halves {
+                let loc = A::param_location(param_slot);
+                param_slot += 1;
+                A::emit_move(a, loc, dst);
             }
         }
 
-        // Load vmctx into R15.
-        a.emit_mov(
-            Size::S64,
-            Self::get_param_location(0),
-            Location::GPR(GPR::R15),
-        );
+        // Load vmctx into its register.
+        A::emit_move(a, A::param_location(0), AbstractLocation::GPR(A::VMCTX));
 
         // Stack probe.
         //
-        // `rep stosq` writes data from low address to high address and may skip the stack guard page.
-        // so here we probe it explicitly when needed.
-        for i in (n_params..n).step_by(NATIVE_PAGE_SIZE / 8).skip(1) {
-            a.emit_mov(Size::S64, Location::Imm32(0), locations[i]);
+        // Zeroing the locals below writes data from low address to high address and may skip
+        // the stack guard page, so here we probe it explicitly when needed.
+        // Locals take at most 16 bytes each.
+        for i in (n_params..n).step_by(NATIVE_PAGE_SIZE / 16).skip(1) {
+            A::emit_move(a, AbstractLocation::Imm32(0), locations[i]);
+        }
+        if let Some(AbstractLocation::Memory(base, lowest)) = self.value_area {
+            let highest = lowest + (value_area_slots * 16) as i32 - 8;
+            for offset in (lowest..=highest).rev().step_by(NATIVE_PAGE_SIZE) {
+                A::emit_move(
+                    a,
+                    AbstractLocation::Imm32(0),
+                    AbstractLocation::Memory(base, offset),
+                );
+            }
+            A::emit_move(
+                a,
+                AbstractLocation::Imm32(0),
+                AbstractLocation::Memory(base, lowest),
+            );
         }
 
         // Initialize all normal locals to zero.
         let mut init_stack_loc_cnt = 0;
-        let mut last_stack_loc = Location::Memory(GPR::RBP, i32::MAX);
+        let mut last_stack_loc = AbstractLocation::Memory(A::FP, i32::MAX);
         for i in n_params..n {
             match locations[i] {
-                Location::Memory(_, _) => {
-                    init_stack_loc_cnt += 1;
+                AbstractLocation::Memory(_, _) => {
+                    init_stack_loc_cnt += local_size(local_types[i]) / 8;
                     last_stack_loc = cmp::min(last_stack_loc, locations[i]);
                 }
-                Location::GPR(_) => {
-                    a.emit_mov(Size::S64, Location::Imm32(0), locations[i]);
+                AbstractLocation::GPR(_) => {
+                    A::emit_move(a, AbstractLocation::Imm32(0), locations[i]);
                 }
                 _ => unreachable!(),
             }
         }
         if init_stack_loc_cnt > 0 {
-            // Since these assemblies take up to 24 bytes, if more than 2 slots are initialized, then they are smaller.
-            a.emit_mov(
-                Size::S64,
-                Location::Imm64(init_stack_loc_cnt as u64),
-                Location::GPR(GPR::RCX),
-            );
-            a.emit_xor(Size::S64, Location::GPR(GPR::RAX), Location::GPR(GPR::RAX));
-            a.emit_lea(Size::S64, last_stack_loc, Location::GPR(GPR::RDI));
-            a.emit_rep_stosq();
+            A::emit_zero_slots(a, last_stack_loc, init_stack_loc_cnt);
         }
 
         // Add the size of all locals allocated to stack.
@@ -498,49 +642,34 @@ impl Machine {
         locations
     }
 
-    pub fn finalize_locals<E: Emitter>(&mut self, a: &mut E, locations: &[Location]) {
-        // Unwind stack to the "save area".
-        a.emit_lea(
-            Size::S64,
-            Location::Memory(
-                GPR::RBP,
-                -(self.save_area_offset.as_ref().unwrap().0 as i32),
-            ),
-            Location::GPR(GPR::RSP),
-        );
-
-        // Restore R15 used by vmctx.
-        a.emit_pop(Size::S64, Location::GPR(GPR::R15));
-
-        // Restore callee-saved registers.
-        for loc in locations.iter().rev() {
-            if let Location::GPR(_) = *loc {
-                a.emit_pop(Size::S64, *loc);
-            }
-        }
+    pub fn finalize_locals(&mut self, a: &mut A::Assembler, locations: &[Location<A>]) {
+        // Restore callee-saved registers, then the vmctx register, from the "save area".
+        let saved: SmallVec<[A::GPR; 8]> = locations
+            .iter()
+            .filter_map(|loc| match *loc {
+                AbstractLocation::GPR(x) => Some(x),
+                _ => None,
+            })
+            .chain(std::iter::once(A::VMCTX))
+            .collect();
+        A::emit_restore_registers(a, self.save_area_offset.as_ref().unwrap().0, &saved);
     }
 
-    pub fn get_param_location(idx: usize) -> Location {
-        match idx {
-            0 => Location::GPR(GPR::RDI),
-            1 => Location::GPR(GPR::RSI),
-            2 => Location::GPR(GPR::RDX),
-            3 => Location::GPR(GPR::RCX),
-            4 => Location::GPR(GPR::R8),
-            5 => Location::GPR(GPR::R9),
-            _ => Location::Memory(GPR::RBP, (16 + (idx - 6) * 8) as i32),
-        }
+    pub fn get_param_location(idx: usize) -> Location<A> {
+        A::param_location(idx)
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::emitter_x64::{Location, GPR};
+    use crate::machine_x64::X64;
     use dynasmrt::x64::Assembler;
 
     #[test]
     fn test_release_locations_keep_state_nopanic() {
-        let mut machine = Machine::new();
+        let mut machine = Machine::<X64>::new();
         let mut assembler = Assembler::new().unwrap();
         let locs = machine.acquire_locations(
             &mut assembler,
@@ -552,4 +681,54 @@ mod test {
 
         machine.release_locations_keep_state(&mut assembler, &locs);
     }
+
+    #[test]
+    fn test_v128_stack_slots() {
+        let mut machine = Machine::<X64>::new();
+        let mut assembler = Assembler::new().unwrap();
+        let locs = machine.acquire_locations(
+            &mut assembler,
+            &[
+                (WpType::V128, MachineValue::Undefined),
+                (WpType::I64, MachineValue::Undefined),
+                (WpType::V128, MachineValue::Undefined),
+            ],
+            false,
+        );
+        assert_eq!(locs[0], Location::Memory(GPR::RBP, -16));
+        assert_eq!(locs[2], Location::Memory(GPR::RBP, -32));
+        assert!(machine.is_v128_stack_slot(locs[0]));
+        assert!(!machine.is_v128_stack_slot(locs[1]));
+        assert_eq!(machine.get_stack_offset(), 32);
+        assert_eq!(machine.state.stack_values.len(), 4);
+
+        machine.release_locations(&mut assembler, &locs);
+        assert_eq!(machine.get_stack_offset(), 0);
+        assert!(machine.state.stack_values.is_empty());
+        assert!(!machine.is_v128_stack_slot(locs[2]));
+    }
+
+    #[test]
+    fn test_multi_value_areas() {
+        let mut machine = Machine::<X64>::new();
+        let mut assembler = Assembler::new().unwrap();
+        let locals = machine.init_locals(&mut assembler, &[WpType::I32, WpType::I64], 1, true, 3);
+        let return_area_ptr = match machine.get_return_area_ptr() {
+            Location::Memory(GPR::RBP, offset) => offset,
+            loc => panic!("unexpected return area pointer location {:?}", loc),
+        };
+        let value_area = match machine.get_value_area() {
+            Location::Memory(GPR::RBP, offset) => offset,
+            loc => panic!("unexpected value area location {:?}", loc),
+        };
+        // The value area is below everything else in the static area.
+        assert!(value_area + 3 * 16 <= return_area_ptr);
+        for loc in locals {
+            if let Location::Memory(_, offset) = loc {
+                assert!(offset < return_area_ptr);
+                assert!(offset >= value_area + 3 * 16);
+            }
+        }
+        assert!(machine.get_stack_offset() >= (-value_area) as usize);
+    }
 }
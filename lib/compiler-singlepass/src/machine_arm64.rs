@@ -0,0 +1,135 @@
+//! The ARM64 part of the `Machine`.
+
+use crate::arm64_decl::{new_machine_state, ARM64Register};
+use crate::common_decl::{MachineState, RegisterIndex};
+use crate::emitter_arm64::*;
+use crate::machine::MachineArch;
+use dynasmrt::aarch64::Assembler;
+
+/// The ARM64 architecture with the AAPCS64 calling convention.
+pub struct ARM64;
+
+/// Rounds a stack offset up to the 16-byte alignment the stack pointer requires.
+fn align_stack(offset: usize) -> usize {
+    (offset + 15) & !15
+}
+
+impl MachineArch for ARM64 {
+    type GPR = GPR;
+    type SIMD = NEON;
+    type Assembler = Assembler;
+
+    const VMCTX: GPR = GPR::X28;
+    const FP: GPR = GPR::X29;
+    const GPRS: &'static [GPR] = &[
+        GPR::X9,
+        GPR::X10,
+        GPR::X11,
+        GPR::X12,
+        GPR::X13,
+        GPR::X14,
+        GPR::X15,
+    ];
+    // The argument registers, which are free between calls. X8 is kept out of the
+    // allocator for the call sequences, and X16/X17 belong to the emitter.
+    const TEMP_GPRS: &'static [GPR] = &[
+        GPR::X0,
+        GPR::X1,
+        GPR::X2,
+        GPR::X3,
+        GPR::X4,
+        GPR::X5,
+        GPR::X6,
+        GPR::X7,
+    ];
+    const LOCAL_GPRS: &'static [GPR] = &[
+        GPR::X19,
+        GPR::X20,
+        GPR::X21,
+        GPR::X22,
+        GPR::X23,
+        GPR::X24,
+        GPR::X25,
+        GPR::X26,
+        GPR::X27,
+    ];
+    // V8-V15 are partially callee-saved, so they are never used.
+    const SIMDS: &'static [NEON] = &[
+        NEON::V16,
+        NEON::V17,
+        NEON::V18,
+        NEON::V19,
+        NEON::V20,
+        NEON::V21,
+        NEON::V22,
+        NEON::V23,
+    ];
+    const TEMP_SIMDS: &'static [NEON] = &[
+        NEON::V0,
+        NEON::V1,
+        NEON::V2,
+        NEON::V3,
+        NEON::V4,
+        NEON::V5,
+        NEON::V6,
+        NEON::V7,
+    ];
+
+    fn gpr_index(r: GPR) -> RegisterIndex {
+        ARM64Register::GPR(r).to_index()
+    }
+
+    fn simd_index(r: NEON) -> RegisterIndex {
+        ARM64Register::NEON(r).to_index()
+    }
+
+    fn new_machine_state() -> MachineState {
+        new_machine_state()
+    }
+
+    fn param_location(idx: usize) -> Location {
+        match idx {
+            0 => Location::GPR(GPR::X0),
+            1 => Location::GPR(GPR::X1),
+            2 => Location::GPR(GPR::X2),
+            3 => Location::GPR(GPR::X3),
+            4 => Location::GPR(GPR::X4),
+            5 => Location::GPR(GPR::X5),
+            6 => Location::GPR(GPR::X6),
+            7 => Location::GPR(GPR::X7),
+            // Above the saved frame pointer and link register.
+            _ => Location::Memory(GPR::X29, (16 + (idx - 8) * 8) as i32),
+        }
+    }
+
+    fn emit_stack_adjust(a: &mut Assembler, old_offset: usize, new_offset: usize) {
+        // The stack pointer must stay 16-byte aligned, so it only moves when the aligned
+        // size of the frame changes.
+        if align_stack(old_offset) != align_stack(new_offset) {
+            a.emit_set_sp_below_fp(align_stack(new_offset));
+        }
+    }
+
+    fn emit_move(a: &mut Assembler, src: Location, dst: Location) {
+        a.emit_mov(Size::S64, src, dst);
+    }
+
+    fn emit_zero_slots(a: &mut Assembler, start: Location, n: usize) {
+        match start {
+            Location::Memory(base, offset) => a.emit_zero_slots(base, offset, n),
+            _ => unreachable!(),
+        }
+    }
+
+    fn emit_restore_registers(a: &mut Assembler, save_area_offset: usize, regs: &[GPR]) {
+        // The registers were saved from the top of the frame downwards.
+        assert_eq!(save_area_offset, regs.len() * 8);
+        for (i, r) in regs.iter().enumerate() {
+            a.emit_mov(
+                Size::S64,
+                Location::Memory(GPR::X29, -(((i + 1) * 8) as i32)),
+                Location::GPR(*r),
+            );
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! The x86-64 part of the `Machine`.
+
+use crate::common_decl::{MachineState, RegisterIndex};
+use crate::emitter_x64::*;
+use crate::machine::MachineArch;
+use crate::x64_decl::{new_machine_state, X64Register};
+use dynasmrt::x64::Assembler;
+
+/// The x86-64 architecture with the System V calling convention.
+pub struct X64;
+
+impl MachineArch for X64 {
+    type GPR = GPR;
+    type SIMD = XMM;
+    type Assembler = Assembler;
+
+    const VMCTX: GPR = GPR::R15;
+    const FP: GPR = GPR::RBP;
+    const GPRS: &'static [GPR] = &[GPR::RSI, GPR::RDI, GPR::R8, GPR::R9, GPR::R10, GPR::R11];
+    const TEMP_GPRS: &'static [GPR] = &[GPR::RAX, GPR::RCX, GPR::RDX];
+    const LOCAL_GPRS: &'static [GPR] = &[GPR::R12, GPR::R13, GPR::R14, GPR::RBX];
+    const SIMDS: &'static [XMM] = &[XMM::XMM3, XMM::XMM4, XMM::XMM5, XMM::XMM6, XMM::XMM7];
+    // XMM8-XMM15 are never used for locals or stack values, so they are
+    // all free for the SIMD lowerings that need more scratch registers.
+    const TEMP_SIMDS: &'static [XMM] = &[
+        XMM::XMM0,
+        XMM::XMM1,
+        XMM::XMM2,
+        XMM::XMM8,
+        XMM::XMM9,
+        XMM::XMM10,
+        XMM::XMM11,
+        XMM::XMM12,
+        XMM::XMM13,
+        XMM::XMM14,
+        XMM::XMM15,
+    ];
+
+    fn gpr_index(r: GPR) -> RegisterIndex {
+        X64Register::GPR(r).to_index()
+    }
+
+    fn simd_index(r: XMM) -> RegisterIndex {
+        X64Register::XMM(r).to_index()
+    }
+
+    fn new_machine_state() -> MachineState {
+        new_machine_state()
+    }
+
+    fn param_location(idx: usize) -> Location {
+        match idx {
+            0 => Location::GPR(GPR::RDI),
+            1 => Location::GPR(GPR::RSI),
+            2 => Location::GPR(GPR::RDX),
+            3 => Location::GPR(GPR::RCX),
+            4 => Location::GPR(GPR::R8),
+            5 => Location::GPR(GPR::R9),
+            _ => Location::Memory(GPR::RBP, (16 + (idx - 6) * 8) as i32),
+        }
+    }
+
+    fn emit_stack_adjust(a: &mut Assembler, old_offset: usize, new_offset: usize) {
+        if new_offset > old_offset {
+            a.emit_sub(
+                Size::S64,
+                Location::Imm32((new_offset - old_offset) as u32),
+                Location::GPR(GPR::RSP),
+            );
+        } else if new_offset < old_offset {
+            a.emit_add(
+                Size::S64,
+                Location::Imm32((old_offset - new_offset) as u32),
+                Location::GPR(GPR::RSP),
+            );
+        }
+    }
+
+    fn emit_move(a: &mut Assembler, src: Location, dst: Location) {
+        match (src, dst) {
+            (Location::Memory(_, _), Location::Memory(_, _)) => {
+                a.emit_mov(Size::S64, src, Location::GPR(GPR::RAX));
+                a.emit_mov(Size::S64, Location::GPR(GPR::RAX), dst);
+            }
+            _ => a.emit_mov(Size::S64, src, dst),
+        }
+    }
+
+    fn emit_zero_slots(a: &mut Assembler, start: Location, n: usize) {
+        // Since these assemblies take up to 24 bytes, if more than 2 slots are initialized, then they are smaller.
+        a.emit_mov(
+            Size::S64,
+            Location::Imm64(n as u64),
+            Location::GPR(GPR::RCX),
+        );
+        a.emit_xor(Size::S64, Location::GPR(GPR::RAX), Location::GPR(GPR::RAX));
+        a.emit_lea(Size::S64, start, Location::GPR(GPR::RDI));
+        a.emit_rep_stosq();
+    }
+
+    fn emit_restore_registers(a: &mut Assembler, save_area_offset: usize, regs: &[GPR]) {
+        // Unwind stack to the "save area".
+        a.emit_lea(
+            Size::S64,
+            Location::Memory(GPR::RBP, -(save_area_offset as i32)),
+            Location::GPR(GPR::RSP),
+        );
+        for r in regs.iter().rev() {
+            a.emit_pop(Size::S64, Location::GPR(*r));
+        }
+    }
+}
@@ -46,6 +46,17 @@ pub trait CompilerConfig {
 
     /// Pushes a middleware onto the back of the middleware chain.
     fn push_middleware(&mut self, middleware: Arc<dyn ModuleMiddleware>);
+
+    /// Returns a stable description of the compiler and of every setting
+    /// changing the code it generates, including its middlewares.
+    ///
+    /// Artifacts compiled with configurations with different fingerprints
+    /// mustn't be used in place of each other, e.g. in a cache. The
+    /// default implementation returns `None`, for a configuration that
+    /// can't be described, whose artifacts mustn't be cached.
+    fn fingerprint(&self) -> Option<String> {
+        None
+    }
 }
 
 impl<T> From<T> for Box<dyn CompilerConfig + 'static>
@@ -59,6 +70,14 @@ where
 
 /// An implementation of a Compiler from parsed WebAssembly module to Compiled native code.
 pub trait Compiler: Send {
+    /// Returns the fingerprint of the configuration of the compiler,
+    /// see [`CompilerConfig::fingerprint`].
+    ///
+    /// The default implementation returns `None`.
+    fn fingerprint(&self) -> Option<String> {
+        None
+    }
+
     /// Validates a module.
     ///
     /// It returns the a succesful Result in case is valid, `CompileError` in case is not.
@@ -45,6 +45,26 @@ pub struct FunctionBody {
     pub unwind_info: Option<CompiledFunctionUnwindInfo>,
 }
 
+/// A function body borrowed from a `FunctionBody`, or from a
+/// serialized module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FunctionBodyRef<'a> {
+    /// The function body bytes.
+    pub body: &'a [u8],
+
+    /// The function unwind info
+    pub unwind_info: Option<&'a CompiledFunctionUnwindInfo>,
+}
+
+impl<'a> From<&'a FunctionBody> for FunctionBodyRef<'a> {
+    fn from(body: &'a FunctionBody) -> Self {
+        Self {
+            body: &body.body,
+            unwind_info: body.unwind_info.as_ref(),
+        }
+    }
+}
+
 /// The result of compiling a WebAssembly function.
 ///
 /// This structure only have the compiled information data
@@ -53,6 +53,26 @@ pub struct CustomSection {
     pub relocations: Vec<Relocation>,
 }
 
+/// The bytes of a custom section, with their memory protection,
+/// borrowed from a `CustomSection` or from a serialized module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomSectionRef<'a> {
+    /// Memory protection that applies to this section.
+    pub protection: CustomSectionProtection,
+
+    /// The bytes corresponding to this section.
+    pub bytes: &'a [u8],
+}
+
+impl<'a> From<&'a CustomSection> for CustomSectionRef<'a> {
+    fn from(section: &'a CustomSection) -> Self {
+        Self {
+            protection: section.protection.clone(),
+            bytes: section.bytes.as_slice(),
+        }
+    }
+}
+
 /// The bytes in the section.
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
@@ -25,6 +25,17 @@ pub trait ModuleMiddleware: Debug + Send + Sync {
 
     /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
     fn transform_module_info(&self, _: &mut ModuleInfo) {}
+
+    /// Returns a stable description of this middleware and of its
+    /// configuration, which is part of the fingerprint of the compilers
+    /// using it.
+    ///
+    /// The default implementation returns `None`, as the code generated
+    /// may depend on any state of the middleware: the compilers using it
+    /// then have no fingerprint, and their artifacts aren't cached.
+    fn fingerprint(&self) -> Option<String> {
+        None
+    }
 }
 
 /// A function middleware specialized for a single function.
@@ -70,6 +81,10 @@ pub trait ModuleMiddlewareChain {
 
     /// Applies the chain on a `ModuleInfo` struct.
     fn apply_on_module_info(&self, module_info: &mut ModuleInfo);
+
+    /// Returns the fingerprints of the middlewares of the chain, or `None`
+    /// if any of them has none.
+    fn fingerprint(&self) -> Option<String>;
 }
 
 impl<T: Deref<Target = dyn ModuleMiddleware>> ModuleMiddlewareChain for [T] {
@@ -89,6 +104,16 @@ impl<T: Deref<Target = dyn ModuleMiddleware>> ModuleMiddlewareChain for [T] {
             item.transform_module_info(module_info);
         }
     }
+
+    /// Returns the fingerprints of the middlewares of the chain, or `None`
+    /// if any of them has none.
+    fn fingerprint(&self) -> Option<String> {
+        let fingerprints = self
+            .iter()
+            .map(|x| x.fingerprint())
+            .collect::<Option<Vec<_>>>()?;
+        Some(format!("[{}]", fingerprints.join(", ")))
+    }
 }
 
 impl<'a> MiddlewareReaderState<'a> {
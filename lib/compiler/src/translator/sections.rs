@@ -29,7 +29,8 @@ use wasmparser::{
     ElementSectionReader, Export, ExportSectionReader, ExternalKind, FuncType as WPFunctionType,
     FunctionSectionReader, GlobalSectionReader, GlobalType as WPGlobalType, ImportSectionEntryType,
     ImportSectionReader, MemorySectionReader, MemoryType as WPMemoryType, NameSectionReader,
-    Naming, NamingReader, Operator, TableSectionReader, TypeDef, TypeSectionReader,
+    Naming, NamingReader, Operator, ResizableLimits64, TableSectionReader, TypeDef,
+    TypeSectionReader,
 };
 
 /// Helper function translating wasmparser types to Wasm Type.
@@ -113,17 +114,21 @@ pub fn parse_import_section<'data>(
                 shared,
             }) => {
                 environ.declare_memory_import(
-                    MemoryType {
-                        minimum: Pages(memlimits.initial),
-                        maximum: memlimits.maximum.map(Pages),
+                    MemoryType::new(
+                        Pages(memlimits.initial),
+                        memlimits.maximum.map(Pages),
                         shared,
-                    },
+                    ),
                     module_name,
                     field_name.unwrap_or_default(),
                 )?;
             }
-            ImportSectionEntryType::Memory(WPMemoryType::M64 { .. }) => {
-                unimplemented!("64bit memory not implemented yet")
+            ImportSectionEntryType::Memory(WPMemoryType::M64 { ref limits }) => {
+                environ.declare_memory_import(
+                    memory64_type(limits)?,
+                    module_name,
+                    field_name.unwrap_or_default(),
+                )?;
             }
             ImportSectionEntryType::Global(ref ty) => {
                 environ.declare_global_import(
@@ -204,19 +209,38 @@ pub fn parse_memory_section(
         let memory = entry?;
         match memory {
             WPMemoryType::M32 { limits, shared } => {
-                environ.declare_memory(MemoryType {
-                    minimum: Pages(limits.initial),
-                    maximum: limits.maximum.map(Pages),
-                    shared: shared,
-                })?;
+                environ.declare_memory(MemoryType::new(
+                    Pages(limits.initial),
+                    limits.maximum.map(Pages),
+                    shared,
+                ))?;
+            }
+            WPMemoryType::M64 { ref limits } => {
+                environ.declare_memory(memory64_type(limits)?)?;
             }
-            WPMemoryType::M64 { .. } => unimplemented!("64bit memory not implemented yet"),
         }
     }
 
     Ok(())
 }
 
+/// Converts the limits of a 64-bit memory to a `MemoryType`.
+///
+/// Page counts are stored as `u32`, so a maximum that doesn't fit is
+/// treated as no maximum at all.
+fn memory64_type(limits: &ResizableLimits64) -> WasmResult<MemoryType> {
+    let minimum = u32::try_from(limits.initial).map_err(|_| {
+        wasm_unsupported!(
+            "64-bit memory with a minimum of {} pages is too large",
+            limits.initial
+        )
+    })?;
+    let maximum = limits
+        .maximum
+        .and_then(|maximum| u32::try_from(maximum).ok());
+    Ok(MemoryType::new64(minimum, maximum))
+}
+
 /// Parses the Global section of the wasm module.
 pub fn parse_global_section(
     globals: GlobalSectionReader,
@@ -389,6 +413,11 @@ pub fn parse_data_section<'data>(
                 let mut init_expr_reader = init_expr.get_binary_reader();
                 let (base, offset) = match init_expr_reader.read_operator()? {
                     Operator::I32Const { value } => (None, value as u32 as usize),
+                    // An offset that doesn't fit in `usize` is out of bounds
+                    // for any memory, so make it fail at instantiation.
+                    Operator::I64Const { value } => {
+                        (None, usize::try_from(value as u64).unwrap_or(usize::MAX))
+                    }
                     Operator::GlobalGet { global_index } => {
                         (Some(GlobalIndex::from_u32(global_index)), 0)
                     }
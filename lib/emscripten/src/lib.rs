@@ -71,7 +71,7 @@ pub use self::utils::{
 #[derive(Clone)]
 /// The environment provided to the Emscripten imports.
 pub struct EmEnv {
-    memory: Arc<Option<Memory>>,
+    memories: Arc<Vec<Memory>>,
     data: Arc<Mutex<EmscriptenData>>,
 }
 
@@ -86,21 +86,29 @@ impl WasmerEnv for EmEnv {
 impl EmEnv {
     pub fn new(data: &EmscriptenGlobalsData, mapped_dirs: HashMap<String, PathBuf>) -> Self {
         Self {
-            memory: Arc::new(None),
+            memories: Arc::new(Vec::new()),
             data: Arc::new(Mutex::new(EmscriptenData::new(data.clone(), mapped_dirs))),
         }
     }
 
+    /// Set the memory the Emscripten imports operate on, as memory 0.
     pub fn set_memory(&mut self, memory: Memory) {
-        let ptr = Arc::as_ptr(&self.memory) as *mut _;
+        self.set_memories(vec![memory]);
+    }
+
+    /// Set the memories the Emscripten imports operate on, in index order.
+    pub fn set_memories(&mut self, memories: Vec<Memory>) {
+        let ptr = Arc::as_ptr(&self.memories) as *mut _;
         unsafe {
-            *ptr = Some(memory);
+            *ptr = memories;
         }
     }
 
-    /// Get a reference to the memory
-    pub fn memory(&self, _mem_idx: u32) -> &Memory {
-        (*self.memory).as_ref().unwrap()
+    /// Get a reference to the memory with the given index
+    pub fn memory(&self, mem_idx: u32) -> &Memory {
+        self.memories
+            .get(mem_idx as usize)
+            .unwrap_or_else(|| panic!("Memory {} should be set on `EmEnv` first", mem_idx))
     }
 }
 
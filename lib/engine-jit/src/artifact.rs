@@ -2,38 +2,58 @@
 //! done as separate steps.
 
 use crate::engine::{JITEngine, JITEngineInner};
-use crate::link::link_module;
-#[cfg(feature = "compiler")]
-use crate::serialize::SerializableCompilation;
-use crate::serialize::SerializableModule;
+use crate::link::{link_module, relocated_range};
+use crate::serialize::{ArchiveBlobs, ArchivedModule, ArtifactHeader, SerializableMetadata};
+use memmap2::Mmap;
+use std::path::Path;
+use std::slice;
 use std::sync::{Arc, Mutex};
-use wasmer_compiler::{CompileError, Features, Triple};
+use wasmer_compiler::{
+    CompileError, CustomSectionRef, Features, FunctionBodyRef, Relocation, SectionIndex, Target,
+    Triple,
+};
 #[cfg(feature = "compiler")]
 use wasmer_compiler::{CompileModuleInfo, ModuleEnvironment};
+#[cfg(feature = "compiler")]
+use wasmer_engine::Tunables;
 use wasmer_engine::{
-    register_frame_info, Artifact, DeserializeError, FunctionExtent, GlobalFrameInfoRegistration,
-    SerializeError,
+    register_frame_info, Artifact, DeserializeError, Engine, FunctionExtent,
+    GlobalFrameInfoRegistration, SerializableFunctionFrameInfo, SerializeError,
+    UnprocessedFunctionFrameInfo,
 };
-#[cfg(feature = "compiler")]
-use wasmer_engine::{Engine, SerializableFunctionFrameInfo, Tunables};
-use wasmer_types::entity::{BoxedSlice, PrimaryMap};
+use wasmer_types::entity::{BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{
     FunctionIndex, LocalFunctionIndex, MemoryIndex, OwnedDataInitializer, SignatureIndex,
     TableIndex,
 };
 use wasmer_vm::{
-    FunctionBodyPtr, MemoryStyle, ModuleInfo, TableStyle, VMSharedSignatureIndex, VMTrampoline,
+    FunctionBodyPtr, MemoryImage, MemoryStyle, ModuleInfo, SectionBodyPtr, TableStyle,
+    VMSharedSignatureIndex, VMTrampoline,
 };
 
 /// A compiled wasm module, ready to be instantiated.
+///
+/// The artifact doesn't keep the bytes it was loaded from: serializing it
+/// reads its code back from the code memory.
 pub struct JITArtifact {
-    serializable: SerializableModule,
+    target: Target,
+    metadata: SerializableMetadata,
+    /// The frame infos, which are only deserialized when a trap needs
+    /// them.
+    frame_infos: PrimaryMap<LocalFunctionIndex, UnprocessedFunctionFrameInfo>,
+    /// The bytes of the code which `link_module` overwrote, in the order
+    /// it applied the relocations.
+    unlinked_bytes: Box<[u8]>,
     finished_functions: BoxedSlice<LocalFunctionIndex, FunctionBodyPtr>,
     finished_function_call_trampolines: BoxedSlice<SignatureIndex, VMTrampoline>,
     finished_dynamic_function_trampolines: BoxedSlice<FunctionIndex, FunctionBodyPtr>,
+    finished_custom_sections: BoxedSlice<SectionIndex, SectionBodyPtr>,
     signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
     frame_info_registration: Mutex<Option<GlobalFrameInfoRegistration>>,
     finished_function_lengths: BoxedSlice<LocalFunctionIndex, usize>,
+    finished_function_call_trampoline_lengths: BoxedSlice<SignatureIndex, usize>,
+    finished_dynamic_function_trampoline_lengths: BoxedSlice<FunctionIndex, usize>,
+    finished_custom_section_lengths: BoxedSlice<SectionIndex, usize>,
 }
 
 impl JITArtifact {
@@ -89,39 +109,66 @@ impl JITArtifact {
             translation.module_translation_state.as_ref().unwrap(),
             translation.function_body_inputs,
         )?;
+        let function_bodies = compilation.get_function_bodies();
         let function_call_trampolines = compilation.get_function_call_trampolines();
         let dynamic_function_trampolines = compilation.get_dynamic_function_trampolines();
+        let custom_sections = compilation.get_custom_sections();
 
-        let data_initializers = translation
-            .data_initializers
-            .iter()
-            .map(OwnedDataInitializer::new)
-            .collect::<Vec<_>>()
-            .into_boxed_slice();
+        let (memory_images, data_initializers) =
+            MemoryImage::build(&compile_info.module, &translation.data_initializers);
 
         let frame_infos = compilation
             .get_frame_info()
             .values()
-            .map(|frame_info| SerializableFunctionFrameInfo::Processed(frame_info.clone()))
-            .collect::<PrimaryMap<LocalFunctionIndex, _>>();
+            .map(UnprocessedFunctionFrameInfo::serialize)
+            .collect::<Vec<_>>();
 
-        let serializable_compilation = SerializableCompilation {
-            function_bodies: compilation.get_function_bodies(),
+        let metadata = SerializableMetadata {
+            compile_info,
+            data_initializers: data_initializers.into_boxed_slice(),
+            memory_images: memory_images.into_boxed_slice(),
+            function_unwind_infos: function_bodies
+                .values()
+                .map(|body| body.unwind_info.clone())
+                .collect(),
             function_relocations: compilation.get_relocations(),
             function_jt_offsets: compilation.get_jt_offsets(),
-            function_frame_info: frame_infos,
-            function_call_trampolines,
-            dynamic_function_trampolines,
-            custom_sections: compilation.get_custom_sections(),
+            function_call_trampoline_unwind_infos: function_call_trampolines
+                .values()
+                .map(|body| body.unwind_info.clone())
+                .collect(),
+            dynamic_function_trampoline_unwind_infos: dynamic_function_trampolines
+                .values()
+                .map(|body| body.unwind_info.clone())
+                .collect(),
+            custom_section_protections: custom_sections
+                .values()
+                .map(|section| section.protection.clone())
+                .collect(),
             custom_section_relocations: compilation.get_custom_section_relocations(),
             debug: compilation.get_debug(),
         };
-        let serializable = SerializableModule {
-            compilation: serializable_compilation,
-            compile_info,
-            data_initializers,
+        let blobs = ArchiveBlobs {
+            function_bodies: function_bodies
+                .values()
+                .map(|body| &body.body[..])
+                .collect(),
+            function_call_trampolines: function_call_trampolines
+                .values()
+                .map(|body| &body.body[..])
+                .collect(),
+            dynamic_function_trampolines: dynamic_function_trampolines
+                .values()
+                .map(|body| &body.body[..])
+                .collect(),
+            custom_sections: custom_sections
+                .values()
+                .map(|section| section.bytes.as_slice())
+                .collect(),
+            function_frame_infos: frame_infos.iter().map(|info| info.bytes()).collect(),
         };
-        Self::from_parts(&mut inner_jit, serializable)
+
+        Self::from_parts(&mut inner_jit, jit.target(), metadata, &blobs)
     }
 
     /// Compile a data buffer into a `JITArtifact`, which may then be instantiated.
@@ -133,55 +180,131 @@ impl JITArtifact {
     }
 
     /// Deserialize a JITArtifact
+    ///
+    /// The artifact must have been serialized by the same version of
+    /// Wasmer, for the target of `jit`, or else
+    /// `DeserializeError::Incompatible` is returned.
+    ///
+    /// The code is copied from `bytes` straight into the code memory, and
+    /// only the frame infos are copied out of them otherwise, to be
+    /// deserialized when a trap needs them.
     pub fn deserialize(jit: &JITEngine, bytes: &[u8]) -> Result<Self, DeserializeError> {
         if !Self::is_deserializable(bytes) {
-            return Err(DeserializeError::Incompatible(
-                "The provided bytes are not wasmer-jit".to_string(),
-            ));
+            return Err(DeserializeError::Incompatible {
+                expected: "a wasmer-jit artifact".to_string(),
+                found: "bytes in another format".to_string(),
+            });
         }
 
-        let inner_bytes = &bytes[Self::MAGIC_HEADER.len()..];
-
-        // let r = flexbuffers::Reader::get_root(bytes).map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))?;
-        // let serializable = SerializableModule::deserialize(r).map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))?;
+        let archive = ArtifactHeader::check(&bytes[Self::MAGIC_HEADER.len()..], jit.target())?;
+        let metadata = archive.metadata()?;
 
-        let serializable: SerializableModule = bincode::deserialize(inner_bytes)
-            .map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))?;
+        Self::from_parts(
+            &mut jit.inner_mut(),
+            jit.target(),
+            metadata,
+            &archive.blobs(),
+        )
+        .map_err(DeserializeError::Compiler)
+    }
 
-        Self::from_parts(&mut jit.inner_mut(), serializable).map_err(DeserializeError::Compiler)
+    /// Deserialize a JITArtifact from a file, which is mapped in memory
+    /// and read in place rather than copied.
+    ///
+    /// # Safety
+    ///
+    /// The file mustn't be modified while the artifact is being loaded.
+    pub unsafe fn deserialize_from_file(
+        jit: &JITEngine,
+        path: &Path,
+    ) -> Result<Self, DeserializeError> {
+        let file = std::fs::File::open(path)?;
+        let mmap = Mmap::map(&file)?;
+        Self::deserialize(jit, &mmap)
     }
 
-    /// Construct a `JITArtifact` from component parts.
-    pub fn from_parts(
+    /// Construct a `JITArtifact` compiled for `target` from its
+    /// `metadata` and from the `blobs` it describes.
+    fn from_parts(
         inner_jit: &mut JITEngineInner,
-        serializable: SerializableModule,
+        target: &Target,
+        metadata: SerializableMetadata,
+        blobs: &ArchiveBlobs<'_>,
     ) -> Result<Self, CompileError> {
+        let function_bodies = metadata
+            .function_unwind_infos
+            .iter()
+            .map(|(index, unwind_info)| FunctionBodyRef {
+                body: blobs.function_bodies[index.index()],
+                unwind_info: unwind_info.as_ref(),
+            })
+            .collect::<PrimaryMap<_, _>>();
+        let function_call_trampolines = metadata
+            .function_call_trampoline_unwind_infos
+            .iter()
+            .map(|(index, unwind_info)| FunctionBodyRef {
+                body: blobs.function_call_trampolines[index.index()],
+                unwind_info: unwind_info.as_ref(),
+            })
+            .collect::<PrimaryMap<_, _>>();
+        let dynamic_function_trampolines = metadata
+            .dynamic_function_trampoline_unwind_infos
+            .iter()
+            .map(|(index, unwind_info)| FunctionBodyRef {
+                body: blobs.dynamic_function_trampolines[index.index()],
+                unwind_info: unwind_info.as_ref(),
+            })
+            .collect::<PrimaryMap<_, _>>();
+        let custom_sections = metadata
+            .custom_section_protections
+            .iter()
+            .map(|(index, protection)| CustomSectionRef {
+                protection: protection.clone(),
+                bytes: blobs.custom_sections[index.index()],
+            })
+            .collect::<PrimaryMap<_, _>>();
+
+        // Keep the bytes `link_module` overwrites, in the same order.
+        let mut unlinked_bytes = Vec::new();
+        for (index, relocations) in metadata.custom_section_relocations.iter() {
+            let body = custom_sections[index].bytes;
+            for r in relocations {
+                unlinked_bytes.extend_from_slice(&body[relocated_range(r)]);
+            }
+        }
+        for (index, relocations) in metadata.function_relocations.iter() {
+            let body = function_bodies[index].body;
+            for r in relocations {
+                unlinked_bytes.extend_from_slice(&body[relocated_range(r)]);
+            }
+        }
+
         let (
             finished_functions,
             finished_function_call_trampolines,
             finished_dynamic_function_trampolines,
-            custom_sections,
+            finished_custom_sections,
         ) = inner_jit.allocate(
-            &serializable.compile_info.module,
-            &serializable.compilation.function_bodies,
-            &serializable.compilation.function_call_trampolines,
-            &serializable.compilation.dynamic_function_trampolines,
-            &serializable.compilation.custom_sections,
+            &metadata.compile_info.module,
+            &function_bodies,
+            &function_call_trampolines,
+            &dynamic_function_trampolines,
+            &custom_sections,
         )?;
 
         link_module(
-            &serializable.compile_info.module,
+            &metadata.compile_info.module,
             &finished_functions,
-            &serializable.compilation.function_jt_offsets,
-            serializable.compilation.function_relocations.clone(),
-            &custom_sections,
-            &serializable.compilation.custom_section_relocations,
+            &metadata.function_jt_offsets,
+            metadata.function_relocations.clone(),
+            &finished_custom_sections,
+            &metadata.custom_section_relocations,
         );
 
         // Compute indices into the shared signature table.
         let signatures = {
             let signature_registry = inner_jit.signatures();
-            serializable
+            metadata
                 .compile_info
                 .module
                 .signatures
@@ -190,13 +313,10 @@ impl JITArtifact {
                 .collect::<PrimaryMap<_, _>>()
         };
 
-        let eh_frame = match &serializable.compilation.debug {
+        let eh_frame = match &metadata.debug {
             Some(debug) => {
-                let eh_frame_section_size = serializable.compilation.custom_sections
-                    [debug.eh_frame]
-                    .bytes
-                    .len();
-                let eh_frame_section_pointer = custom_sections[debug.eh_frame];
+                let eh_frame_section_size = custom_sections[debug.eh_frame].bytes.len();
+                let eh_frame_section_pointer = finished_custom_sections[debug.eh_frame];
                 Some(unsafe {
                     std::slice::from_raw_parts(*eh_frame_section_pointer, eh_frame_section_size)
                 })
@@ -222,19 +342,103 @@ impl JITArtifact {
             finished_function_call_trampolines.into_boxed_slice();
         let finished_dynamic_function_trampolines =
             finished_dynamic_function_trampolines.into_boxed_slice();
+        let finished_custom_sections = finished_custom_sections.into_boxed_slice();
         let signatures = signatures.into_boxed_slice();
 
         Ok(Self {
-            serializable,
+            target: target.clone(),
+            metadata,
+            frame_infos: blobs
+                .function_frame_infos
+                .iter()
+                .map(|bytes| UnprocessedFunctionFrameInfo::new(bytes.to_vec()))
+                .collect(),
+            unlinked_bytes: unlinked_bytes.into_boxed_slice(),
             finished_functions,
             finished_function_call_trampolines,
             finished_dynamic_function_trampolines,
+            finished_custom_sections,
             signatures,
             frame_info_registration: Mutex::new(None),
             finished_function_lengths,
+            finished_function_call_trampoline_lengths: lengths(&blobs.function_call_trampolines),
+            finished_dynamic_function_trampoline_lengths: lengths(
+                &blobs.dynamic_function_trampolines,
+            ),
+            finished_custom_section_lengths: lengths(&blobs.custom_sections),
         })
     }
 
+    /// Read the code of the artifact back from the code memory, as it was
+    /// before `link_module` patched it, and return its blobs.
+    fn unlinked_code(&self) -> UnlinkedCode {
+        let mut unlinked_bytes = &self.unlinked_bytes[..];
+        let mut unlink = |mut body: Vec<u8>, relocations: &[Relocation]| {
+            for r in relocations {
+                let range = relocated_range(r);
+                let (bytes, rest) = unlinked_bytes.split_at(range.len());
+                body[range].copy_from_slice(bytes);
+                unlinked_bytes = rest;
+            }
+            body
+        };
+        // SAFETY: the code memory holding the code is kept by the engine
+        // for as long as the artifact may run, and isn't written to once
+        // published.
+        let read = |ptr: *const u8, len: usize| unsafe { slice::from_raw_parts(ptr, len).to_vec() };
+
+        let custom_sections = self
+            .metadata
+            .custom_section_relocations
+            .iter()
+            .map(|(index, relocations)| {
+                let section = read(
+                    *self.finished_custom_sections[index],
+                    self.finished_custom_section_lengths[index],
+                );
+                unlink(section, relocations)
+            })
+            .collect();
+        let function_bodies = self
+            .metadata
+            .function_relocations
+            .iter()
+            .map(|(index, relocations)| {
+                let body = read(
+                    *self.finished_functions[index] as *const u8,
+                    self.finished_function_lengths[index],
+                );
+                unlink(body, relocations)
+            })
+            .collect();
+        let function_call_trampolines = self
+            .finished_function_call_trampolines
+            .iter()
+            .map(|(index, trampoline)| {
+                read(
+                    *trampoline as *const u8,
+                    self.finished_function_call_trampoline_lengths[index],
+                )
+            })
+            .collect();
+        let dynamic_function_trampolines = self
+            .finished_dynamic_function_trampolines
+            .iter()
+            .map(|(index, trampoline)| {
+                read(
+                    **trampoline as *const u8,
+                    self.finished_dynamic_function_trampoline_lengths[index],
+                )
+            })
+            .collect();
+        UnlinkedCode {
+            function_bodies,
+            function_call_trampolines,
+            dynamic_function_trampolines,
+            custom_sections,
+        }
+    }
+
     /// Get the default extension when serializing this artifact
     pub fn get_default_extension(_triple: &Triple) -> &'static str {
         // `.wjit` is the default extension for all the triples
@@ -244,15 +448,15 @@ impl JITArtifact {
 
 impl Artifact for JITArtifact {
     fn module(&self) -> Arc<ModuleInfo> {
-        self.serializable.compile_info.module.clone()
+        self.metadata.compile_info.module.clone()
     }
 
     fn module_ref(&self) -> &ModuleInfo {
-        &self.serializable.compile_info.module
+        &self.metadata.compile_info.module
     }
 
     fn module_mut(&mut self) -> Option<&mut ModuleInfo> {
-        Arc::get_mut(&mut self.serializable.compile_info.module)
+        Arc::get_mut(&mut self.metadata.compile_info.module)
     }
 
     fn register_frame_info(&self) {
@@ -271,28 +475,37 @@ impl Artifact for JITArtifact {
             .collect::<PrimaryMap<LocalFunctionIndex, _>>()
             .into_boxed_slice();
 
-        let frame_infos = &self.serializable.compilation.function_frame_info;
+        let frame_infos = self
+            .frame_infos
+            .values()
+            .cloned()
+            .map(SerializableFunctionFrameInfo::Unprocessed)
+            .collect();
         *info = register_frame_info(
-            self.serializable.compile_info.module.clone(),
+            self.metadata.compile_info.module.clone(),
             &finished_function_extents,
-            frame_infos.clone(),
+            frame_infos,
         );
     }
 
     fn features(&self) -> &Features {
-        &self.serializable.compile_info.features
+        &self.metadata.compile_info.features
     }
 
     fn data_initializers(&self) -> &[OwnedDataInitializer] {
-        &*self.serializable.data_initializers
+        &*self.metadata.data_initializers
+    }
+
+    fn memory_images(&self) -> &[MemoryImage] {
+        &*self.metadata.memory_images
     }
 
     fn memory_styles(&self) -> &PrimaryMap<MemoryIndex, MemoryStyle> {
-        &self.serializable.compile_info.memory_styles
+        &self.metadata.compile_info.memory_styles
     }
 
     fn table_styles(&self) -> &PrimaryMap<TableIndex, TableStyle> {
-        &self.serializable.compile_info.table_styles
+        &self.metadata.compile_info.table_styles
     }
 
     fn finished_functions(&self) -> &BoxedSlice<LocalFunctionIndex, FunctionBodyPtr> {
@@ -312,15 +525,44 @@ impl Artifact for JITArtifact {
     }
 
     fn serialize(&self) -> Result<Vec<u8>, SerializeError> {
-        // let mut s = flexbuffers::FlexbufferSerializer::new();
-        // self.serializable.serialize(&mut s).map_err(|e| SerializeError::Generic(format!("{:?}", e)));
-        // Ok(s.take_buffer())
-        let bytes = bincode::serialize(&self.serializable)
-            .map_err(|e| SerializeError::Generic(format!("{:?}", e)))?;
+        let code = self.unlinked_code();
+        let blobs = ArchiveBlobs {
+            function_bodies: as_slices(&code.function_bodies),
+            function_call_trampolines: as_slices(&code.function_call_trampolines),
+            dynamic_function_trampolines: as_slices(&code.dynamic_function_trampolines),
+            custom_sections: as_slices(&code.custom_sections),
+            function_frame_infos: self.frame_infos.values().map(|info| info.bytes()).collect(),
+        };
 
-        // Prepend the header.
-        let mut serialized = Self::MAGIC_HEADER.to_vec();
-        serialized.extend(bytes);
-        Ok(serialized)
+        let mut archive = Vec::new();
+        ArchivedModule::write(&self.metadata, &blobs, &mut archive)
+            .map_err(|e| SerializeError::Generic(format!("{:?}", e)))?;
+        let mut bytes = Self::MAGIC_HEADER.to_vec();
+        ArtifactHeader::new(&self.target, &ArchivedModule::new(&archive).unwrap())
+            .serialize_into(&mut bytes)
+            .map_err(|e| SerializeError::Generic(format!("{:?}", e)))?;
+        bytes.extend(archive);
+        Ok(bytes)
     }
 }
+
+/// Returns the lengths of `blobs`.
+fn lengths<K: EntityRef>(blobs: &[&[u8]]) -> BoxedSlice<K, usize> {
+    blobs
+        .iter()
+        .map(|blob| blob.len())
+        .collect::<PrimaryMap<K, _>>()
+        .into_boxed_slice()
+}
+
+fn as_slices(blobs: &[Vec<u8>]) -> Vec<&[u8]> {
+    blobs.iter().map(|blob| &blob[..]).collect()
+}
+
+/// The code of an artifact, as it was before `link_module` patched it.
+struct UnlinkedCode {
+    function_bodies: Vec<Vec<u8>>,
+    function_call_trampolines: Vec<Vec<u8>>,
+    dynamic_function_trampolines: Vec<Vec<u8>>,
+    custom_sections: Vec<Vec<u8>>,
+}
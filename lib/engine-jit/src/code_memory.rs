@@ -3,7 +3,7 @@
 
 //! Memory management for executable code.
 use crate::unwind::UnwindRegistry;
-use wasmer_compiler::{CompiledFunctionUnwindInfo, CustomSection, FunctionBody};
+use wasmer_compiler::{CompiledFunctionUnwindInfo, FunctionBodyRef};
 use wasmer_vm::{Mmap, VMFunctionBody};
 
 /// The optimal alignment for functions.
@@ -42,9 +42,9 @@ impl CodeMemory {
     /// Allocate a single contiguous block of memory for the functions and custom sections, and copy the data in place.
     pub fn allocate(
         &mut self,
-        functions: &[&FunctionBody],
-        executable_sections: &[&CustomSection],
-        data_sections: &[&CustomSection],
+        functions: &[FunctionBodyRef<'_>],
+        executable_sections: &[&[u8]],
+        data_sections: &[&[u8]],
     ) -> Result<(Vec<&mut [VMFunctionBody]>, Vec<&mut [u8]>, Vec<&mut [u8]>), String> {
         let mut function_result = vec![];
         let mut data_section_result = vec![];
@@ -69,11 +69,11 @@ impl CodeMemory {
                     ARCH_FUNCTION_ALIGNMENT,
                 )
             }) + executable_sections.iter().fold(0, |acc, exec| {
-                round_up(acc + exec.bytes.len(), ARCH_FUNCTION_ALIGNMENT)
+                round_up(acc + exec.len(), ARCH_FUNCTION_ALIGNMENT)
             }),
             page_size,
         ) + data_sections.iter().fold(0, |acc, data| {
-            round_up(acc + data.bytes.len(), DATA_SECTION_ALIGNMENT)
+            round_up(acc + data.len(), DATA_SECTION_ALIGNMENT)
         });
 
         // 2. Allocate the pages. Mark them all read-write.
@@ -99,13 +99,12 @@ impl CodeMemory {
             function_result.push(vmfunc);
         }
         for section in executable_sections {
-            let section = &section.bytes;
             assert_eq!(buf.as_mut_ptr() as usize % ARCH_FUNCTION_ALIGNMENT, 0);
             let len = round_up(section.len(), ARCH_FUNCTION_ALIGNMENT);
             let (s, next_buf) = buf.split_at_mut(len);
             buf = next_buf;
             bytes += len;
-            s[..section.len()].copy_from_slice(section.as_slice());
+            s[..section.len()].copy_from_slice(section);
             executable_section_result.push(s);
         }
 
@@ -118,12 +117,11 @@ impl CodeMemory {
             buf = buf.split_at_mut(padding).1;
 
             for section in data_sections {
-                let section = &section.bytes;
                 assert_eq!(buf.as_mut_ptr() as usize % DATA_SECTION_ALIGNMENT, 0);
                 let len = round_up(section.len(), DATA_SECTION_ALIGNMENT);
                 let (s, next_buf) = buf.split_at_mut(len);
                 buf = next_buf;
-                s[..section.len()].copy_from_slice(section.as_slice());
+                s[..section.len()].copy_from_slice(section);
                 data_section_result.push(s);
             }
         }
@@ -152,8 +150,8 @@ impl CodeMemory {
     }
 
     /// Calculates the allocation size of the given compiled function.
-    fn function_allocation_size(func: &FunctionBody) -> usize {
-        match &func.unwind_info {
+    fn function_allocation_size(func: &FunctionBodyRef<'_>) -> usize {
+        match func.unwind_info {
             Some(CompiledFunctionUnwindInfo::WindowsX64(info)) => {
                 // Windows unwind information is required to be emitted into code memory
                 // This is because it must be a positive relative offset from the start of the memory
@@ -169,7 +167,7 @@ impl CodeMemory {
     /// This will also add the function to the current function table.
     fn copy_function<'a>(
         registry: &mut UnwindRegistry,
-        func: &FunctionBody,
+        func: &FunctionBodyRef<'_>,
         buf: &'a mut [u8],
     ) -> &'a mut [VMFunctionBody] {
         assert_eq!(buf.as_ptr() as usize % ARCH_FUNCTION_ALIGNMENT, 0);
@@ -177,10 +175,10 @@ impl CodeMemory {
         let func_len = func.body.len();
 
         let (body, remainder) = buf.split_at_mut(func_len);
-        body.copy_from_slice(&func.body);
+        body.copy_from_slice(func.body);
         let vmfunc = Self::view_as_mut_vmfunc_slice(body);
 
-        if let Some(CompiledFunctionUnwindInfo::WindowsX64(info)) = &func.unwind_info {
+        if let Some(CompiledFunctionUnwindInfo::WindowsX64(info)) = func.unwind_info {
             // Windows unwind information is written following the function body
             // Keep unwind information 32-bit aligned (round up to the nearest 4 byte boundary)
             let unwind_start = (func_len + 3) & !3;
@@ -188,10 +186,10 @@ impl CodeMemory {
             let padding = unwind_start - func_len;
             assert_eq!((func_len + padding) % 4, 0);
             let slice = remainder.split_at_mut(padding + unwind_size).0;
-            slice[padding..].copy_from_slice(&info);
+            slice[padding..].copy_from_slice(info);
         }
 
-        if let Some(info) = &func.unwind_info {
+        if let Some(info) = func.unwind_info {
             registry
                 .register(vmfunc.as_ptr() as usize, 0, func_len as u32, info)
                 .expect("failed to register unwind information");
@@ -1,13 +1,16 @@
 //! JIT compilation.
 
 use crate::{CodeMemory, JITArtifact};
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 #[cfg(feature = "compiler")]
 use wasmer_compiler::Compiler;
 use wasmer_compiler::{
-    CompileError, CustomSection, CustomSectionProtection, FunctionBody, SectionIndex, Target,
+    CompileError, CustomSectionProtection, CustomSectionRef, FunctionBodyRef, SectionIndex, Target,
+};
+use wasmer_engine::{
+    engine_fingerprint, Artifact, DeserializeError, Engine, EngineId, FunctionExtent, Tunables,
 };
-use wasmer_engine::{Artifact, DeserializeError, Engine, EngineId, FunctionExtent, Tunables};
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::Features;
 use wasmer_types::{FunctionIndex, FunctionType, LocalFunctionIndex, SignatureIndex};
@@ -128,10 +131,38 @@ impl Engine for JITEngine {
         Ok(Arc::new(JITArtifact::deserialize(&self, &bytes)?))
     }
 
+    /// Deserializes a WebAssembly module from a path, which is mapped
+    /// in memory and read in place
+    unsafe fn deserialize_from_file(
+        &self,
+        file_ref: &Path,
+    ) -> Result<Arc<dyn Artifact>, DeserializeError> {
+        Ok(Arc::new(JITArtifact::deserialize_from_file(
+            self, file_ref,
+        )?))
+    }
+
     fn id(&self) -> &EngineId {
         &self.engine_id
     }
 
+    fn fingerprint(&self) -> Option<String> {
+        let inner = self.inner();
+        #[cfg(feature = "compiler")]
+        let compiler = match &inner.compiler {
+            Some(compiler) => Some(compiler.fingerprint()?),
+            None => None,
+        };
+        #[cfg(not(feature = "compiler"))]
+        let compiler: Option<String> = None;
+        Some(engine_fingerprint(
+            "jit",
+            &self.target,
+            inner.features(),
+            compiler.as_deref(),
+        ))
+    }
+
     fn cloned(&self) -> Arc<dyn Engine + Send + Sync> {
         Arc::new(self.clone())
     }
@@ -187,10 +218,10 @@ impl JITEngineInner {
     pub(crate) fn allocate(
         &mut self,
         _module: &ModuleInfo,
-        functions: &PrimaryMap<LocalFunctionIndex, FunctionBody>,
-        function_call_trampolines: &PrimaryMap<SignatureIndex, FunctionBody>,
-        dynamic_function_trampolines: &PrimaryMap<FunctionIndex, FunctionBody>,
-        custom_sections: &PrimaryMap<SectionIndex, CustomSection>,
+        functions: &PrimaryMap<LocalFunctionIndex, FunctionBodyRef<'_>>,
+        function_call_trampolines: &PrimaryMap<SignatureIndex, FunctionBodyRef<'_>>,
+        dynamic_function_trampolines: &PrimaryMap<FunctionIndex, FunctionBodyRef<'_>>,
+        custom_sections: &PrimaryMap<SectionIndex, CustomSectionRef<'_>>,
     ) -> Result<
         (
             PrimaryMap<LocalFunctionIndex, FunctionExtent>,
@@ -204,10 +235,19 @@ impl JITEngineInner {
             .values()
             .chain(function_call_trampolines.values())
             .chain(dynamic_function_trampolines.values())
+            .copied()
             .collect::<Vec<_>>();
         let (executable_sections, data_sections): (Vec<_>, _) = custom_sections
             .values()
             .partition(|section| section.protection == CustomSectionProtection::ReadExecute);
+        let executable_sections = executable_sections
+            .into_iter()
+            .map(|section| section.bytes)
+            .collect::<Vec<_>>();
+        let data_sections = data_sections
+            .into_iter()
+            .map(|section| section.bytes)
+            .collect::<Vec<_>>();
         self.code_memory.push(CodeMemory::new());
 
         let (mut allocated_functions, allocated_executable_sections, allocated_data_sections) =
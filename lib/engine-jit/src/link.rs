@@ -1,5 +1,6 @@
 //! Linking for JIT-compiled code.
 
+use std::ops::Range;
 use std::ptr::write_unaligned;
 use wasmer_compiler::{
     JumpTable, JumpTableOffsets, Relocation, RelocationKind, RelocationTarget, Relocations,
@@ -62,6 +63,20 @@ fn apply_relocation(
     }
 }
 
+/// Returns the range of the bytes of a body which `link_module`
+/// overwrites to apply `r`.
+pub(crate) fn relocated_range(r: &Relocation) -> Range<usize> {
+    let size = match r.kind {
+        #[cfg(target_pointer_width = "64")]
+        RelocationKind::Abs8 | RelocationKind::X86PCRel8 => 8,
+        #[cfg(target_pointer_width = "32")]
+        RelocationKind::X86PCRel4 | RelocationKind::X86CallPCRel4 => 4,
+        _ => 0,
+    };
+    let start = r.offset as usize;
+    start..start + size
+}
+
 /// Links a module, patching the allocated functions with the
 /// required relocations and jump tables.
 pub fn link_module(
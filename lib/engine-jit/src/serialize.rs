@@ -1,45 +1,551 @@
 use serde::{Deserialize, Serialize};
+use std::convert::{TryFrom, TryInto};
 use wasmer_compiler::{
-    CompileModuleInfo, CustomSection, Dwarf, FunctionBody, JumpTableOffsets, Relocation,
-    SectionIndex,
+    CompileModuleInfo, CompiledFunctionUnwindInfo, CustomSectionProtection, Dwarf,
+    JumpTableOffsets, Relocation, SectionIndex, Target,
 };
-use wasmer_engine::SerializableFunctionFrameInfo;
-use wasmer_types::entity::PrimaryMap;
+use wasmer_engine::DeserializeError;
+use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{FunctionIndex, LocalFunctionIndex, OwnedDataInitializer, SignatureIndex};
+use wasmer_vm::MemoryImage;
 
-// /// The serializable function data
-// #[derive(Serialize, Deserialize)]
-// pub struct SerializableFunction {
-//     #[serde(with = "serde_bytes")]
-//     pub body: &[u8],
-//     /// The unwind info for Windows
-//     #[serde(with = "serde_bytes")]
-//     pub windows_unwind_info: &[u8],
-// }
-
-/// The compilation related data for a serialized modules
+/// The metadata of a serialized module: everything but the bytes of the
+/// functions, trampolines, custom sections and frame infos.
+///
+/// Unlike those bytes, which are stored as blobs of the `ArchivedModule`
+/// and read in place, the metadata is decoded with `bincode` when the
+/// module is loaded.
 #[derive(Serialize, Deserialize)]
-pub struct SerializableCompilation {
-    pub function_bodies: PrimaryMap<LocalFunctionIndex, FunctionBody>,
+pub struct SerializableMetadata {
+    pub compile_info: CompileModuleInfo,
+    pub data_initializers: Box<[OwnedDataInitializer]>,
+    pub memory_images: Box<[MemoryImage]>,
+    pub function_unwind_infos: PrimaryMap<LocalFunctionIndex, Option<CompiledFunctionUnwindInfo>>,
     pub function_relocations: PrimaryMap<LocalFunctionIndex, Vec<Relocation>>,
     pub function_jt_offsets: PrimaryMap<LocalFunctionIndex, JumpTableOffsets>,
-    // This is `SerializableFunctionFrameInfo` instead of `CompiledFunctionFrameInfo`,
-    // to allow lazy frame_info deserialization, we convert it to it's lazy binary
-    // format upon serialization.
-    pub function_frame_info: PrimaryMap<LocalFunctionIndex, SerializableFunctionFrameInfo>,
-    pub function_call_trampolines: PrimaryMap<SignatureIndex, FunctionBody>,
-    pub dynamic_function_trampolines: PrimaryMap<FunctionIndex, FunctionBody>,
-    pub custom_sections: PrimaryMap<SectionIndex, CustomSection>,
+    pub function_call_trampoline_unwind_infos:
+        PrimaryMap<SignatureIndex, Option<CompiledFunctionUnwindInfo>>,
+    pub dynamic_function_trampoline_unwind_infos:
+        PrimaryMap<FunctionIndex, Option<CompiledFunctionUnwindInfo>>,
+    pub custom_section_protections: PrimaryMap<SectionIndex, CustomSectionProtection>,
     pub custom_section_relocations: PrimaryMap<SectionIndex, Vec<Relocation>>,
     // The section indices corresponding to the Dwarf debug info
     pub debug: Option<Dwarf>,
 }
 
-/// Serializable struct that is able to serialize from and to
-/// a `JITArtifactInfo`.
-#[derive(Serialize, Deserialize)]
-pub struct SerializableModule {
-    pub compilation: SerializableCompilation,
-    pub compile_info: CompileModuleInfo,
-    pub data_initializers: Box<[OwnedDataInitializer]>,
+/// The bytes stored in an archived module besides its metadata.
+pub struct ArchiveBlobs<'a> {
+    pub function_bodies: Vec<&'a [u8]>,
+    pub function_call_trampolines: Vec<&'a [u8]>,
+    pub dynamic_function_trampolines: Vec<&'a [u8]>,
+    pub custom_sections: Vec<&'a [u8]>,
+    /// The frame info of each function, serialized by
+    /// `UnprocessedFunctionFrameInfo::serialize`.
+    pub function_frame_infos: Vec<&'a [u8]>,
+}
+
+/// A serialized module, whose code and frame infos can be read in place,
+/// e.g. from a file mapped in memory, without deserializing them.
+///
+/// It starts with the number of local functions, of function call
+/// trampolines, of dynamic function trampolines and of custom sections,
+/// followed by a table of the offset and length of every blob in the
+/// archive, all as little-endian `u64`s. The blobs follow, in order:
+///  * the `SerializableMetadata`, serialized with `bincode`,
+///  * the bodies of the functions,
+///  * the function call trampolines,
+///  * the dynamic function trampolines,
+///  * the bytes of the custom sections,
+///  * the frame infos of the functions.
+#[derive(Clone, Copy)]
+pub struct ArchivedModule<'a> {
+    bytes: &'a [u8],
+    num_functions: usize,
+    num_function_call_trampolines: usize,
+    num_dynamic_function_trampolines: usize,
+    num_custom_sections: usize,
+}
+
+impl<'a> ArchivedModule<'a> {
+    const NUM_COUNTS: usize = 4;
+    const ENTRY_SIZE: usize = 16;
+
+    /// Serialize a module into an archive, and append it to `bytes`.
+    pub fn write(
+        metadata: &SerializableMetadata,
+        blobs: &ArchiveBlobs<'_>,
+        bytes: &mut Vec<u8>,
+    ) -> Result<(), bincode::Error> {
+        assert_eq!(
+            blobs.function_frame_infos.len(),
+            blobs.function_bodies.len()
+        );
+        let metadata = bincode::serialize(metadata)?;
+        for count in &[
+            blobs.function_bodies.len(),
+            blobs.function_call_trampolines.len(),
+            blobs.dynamic_function_trampolines.len(),
+            blobs.custom_sections.len(),
+        ] {
+            bytes.extend_from_slice(&(*count as u64).to_le_bytes());
+        }
+
+        let all_blobs = std::iter::once(&metadata[..])
+            .chain(blobs.function_bodies.iter().copied())
+            .chain(blobs.function_call_trampolines.iter().copied())
+            .chain(blobs.dynamic_function_trampolines.iter().copied())
+            .chain(blobs.custom_sections.iter().copied())
+            .chain(blobs.function_frame_infos.iter().copied())
+            .collect::<Vec<_>>();
+        let table_size = all_blobs.len() * Self::ENTRY_SIZE;
+        let mut offset = Self::NUM_COUNTS * 8 + table_size;
+        for blob in &all_blobs {
+            bytes.extend_from_slice(&(offset as u64).to_le_bytes());
+            bytes.extend_from_slice(&(blob.len() as u64).to_le_bytes());
+            offset += blob.len();
+        }
+        bytes.reserve(offset - Self::NUM_COUNTS * 8 - table_size);
+        for blob in &all_blobs {
+            bytes.extend_from_slice(blob);
+        }
+        Ok(())
+    }
+
+    /// Read the archive in `bytes`, checking that its table is in
+    /// bounds.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, DeserializeError> {
+        let truncated =
+            || DeserializeError::CorruptedBinary("the archive is truncated".to_string());
+        let read_usize = |offset: usize| -> Result<usize, DeserializeError> {
+            let value = offset
+                .checked_add(8)
+                .and_then(|end| bytes.get(offset..end))
+                .ok_or_else(truncated)?;
+            usize::try_from(u64::from_le_bytes(value.try_into().unwrap())).map_err(|_| truncated())
+        };
+
+        let archive = Self {
+            bytes,
+            num_functions: read_usize(0)?,
+            num_function_call_trampolines: read_usize(8)?,
+            num_dynamic_function_trampolines: read_usize(16)?,
+            num_custom_sections: read_usize(24)?,
+        };
+        let num_blobs = archive
+            .num_functions
+            .checked_mul(2)
+            .and_then(|n| n.checked_add(archive.num_function_call_trampolines))
+            .and_then(|n| n.checked_add(archive.num_dynamic_function_trampolines))
+            .and_then(|n| n.checked_add(archive.num_custom_sections))
+            .and_then(|n| n.checked_add(1))
+            .ok_or_else(truncated)?;
+        for index in 0..num_blobs {
+            let entry = Self::NUM_COUNTS * 8 + index * Self::ENTRY_SIZE;
+            let offset = read_usize(entry)?;
+            let len = read_usize(entry + 8)?;
+            if offset
+                .checked_add(len)
+                .filter(|end| *end <= bytes.len())
+                .is_none()
+            {
+                return Err(truncated());
+            }
+        }
+        Ok(archive)
+    }
+
+    /// Deserialize the metadata of the module, checking that it
+    /// describes the blobs of the archive.
+    pub fn metadata(&self) -> Result<SerializableMetadata, DeserializeError> {
+        let metadata: SerializableMetadata = bincode::deserialize(self.blob(0))
+            .map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))?;
+        if metadata.function_unwind_infos.len() != self.num_functions
+            || metadata.function_relocations.len() != self.num_functions
+            || metadata.function_jt_offsets.len() != self.num_functions
+            || metadata.function_call_trampoline_unwind_infos.len()
+                != self.num_function_call_trampolines
+            || metadata.dynamic_function_trampoline_unwind_infos.len()
+                != self.num_dynamic_function_trampolines
+            || metadata.custom_section_protections.len() != self.num_custom_sections
+            || metadata.custom_section_relocations.len() != self.num_custom_sections
+        {
+            return Err(DeserializeError::CorruptedBinary(
+                "the metadata doesn't match the archive".to_string(),
+            ));
+        }
+        Ok(metadata)
+    }
+
+    /// Returns the start of the archive, up to the end of the metadata:
+    /// the counts, the table of the blobs and the metadata.
+    pub fn metadata_region(&self) -> &'a [u8] {
+        let metadata = self.blob(0);
+        let end = metadata.as_ptr() as usize - self.bytes.as_ptr() as usize + metadata.len();
+        &self.bytes[..end]
+    }
+
+    /// Returns the blobs of the archive.
+    pub fn blobs(&self) -> ArchiveBlobs<'a> {
+        ArchiveBlobs {
+            function_bodies: (0..self.num_functions)
+                .map(|index| self.function_body(LocalFunctionIndex::new(index)))
+                .collect(),
+            function_call_trampolines: (0..self.num_function_call_trampolines)
+                .map(|index| self.function_call_trampoline(SignatureIndex::new(index)))
+                .collect(),
+            dynamic_function_trampolines: (0..self.num_dynamic_function_trampolines)
+                .map(|index| self.dynamic_function_trampoline(FunctionIndex::new(index)))
+                .collect(),
+            custom_sections: (0..self.num_custom_sections)
+                .map(|index| self.custom_section(SectionIndex::new(index)))
+                .collect(),
+            function_frame_infos: (0..self.num_functions)
+                .map(|index| self.function_frame_info(LocalFunctionIndex::new(index)))
+                .collect(),
+        }
+    }
+
+    /// Returns the body of a local function.
+    pub fn function_body(&self, index: LocalFunctionIndex) -> &'a [u8] {
+        assert!(index.index() < self.num_functions);
+        self.blob(1 + index.index())
+    }
+
+    /// Returns the body of a function call trampoline.
+    pub fn function_call_trampoline(&self, index: SignatureIndex) -> &'a [u8] {
+        assert!(index.index() < self.num_function_call_trampolines);
+        self.blob(1 + self.num_functions + index.index())
+    }
+
+    /// Returns the body of a dynamic function trampoline.
+    pub fn dynamic_function_trampoline(&self, index: FunctionIndex) -> &'a [u8] {
+        assert!(index.index() < self.num_dynamic_function_trampolines);
+        self.blob(1 + self.num_functions + self.num_function_call_trampolines + index.index())
+    }
+
+    /// Returns the bytes of a custom section.
+    pub fn custom_section(&self, index: SectionIndex) -> &'a [u8] {
+        assert!(index.index() < self.num_custom_sections);
+        self.blob(
+            1 + self.num_functions
+                + self.num_function_call_trampolines
+                + self.num_dynamic_function_trampolines
+                + index.index(),
+        )
+    }
+
+    /// Returns the serialized frame info of a local function.
+    pub fn function_frame_info(&self, index: LocalFunctionIndex) -> &'a [u8] {
+        assert!(index.index() < self.num_functions);
+        self.blob(
+            1 + self.num_functions
+                + self.num_function_call_trampolines
+                + self.num_dynamic_function_trampolines
+                + self.num_custom_sections
+                + index.index(),
+        )
+    }
+
+    /// Returns a blob, whose table entry was checked by `new`.
+    fn blob(&self, index: usize) -> &'a [u8] {
+        let entry = Self::NUM_COUNTS * 8 + index * Self::ENTRY_SIZE;
+        let read_usize = |offset: usize| {
+            u64::from_le_bytes(self.bytes[offset..offset + 8].try_into().unwrap()) as usize
+        };
+        let offset = read_usize(entry);
+        &self.bytes[offset..offset + read_usize(entry + 8)]
+    }
+}
+
+/// The header of a serialized `JITArtifact`, telling which engines can
+/// load it.
+///
+/// It follows the magic header, and is followed by the
+/// `ArchivedModule`. It's made of:
+///  * the format version, a little-endian `u32`,
+///  * the length of the rest of the header, a little-endian `u32`,
+///  * the rest of the header, serialized with `bincode`.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ArtifactHeader {
+    /// The version of Wasmer which produced the artifact.
+    pub wasmer_version: String,
+    /// The target triple the artifact was compiled for.
+    pub triple: String,
+    /// The CPU features the artifact may use.
+    pub cpu_features: Vec<String>,
+    /// The BLAKE3 hash of the `ArchivedModule::metadata_region` of the
+    /// archived module.
+    ///
+    /// The code and the frame infos aren't hashed, so that loading an
+    /// artifact doesn't read all of it: their table is checked to be in
+    /// bounds, and the caches check the artifacts they store.
+    pub metadata_hash: [u8; 32],
+}
+
+impl ArtifactHeader {
+    /// The version of the layout of serialized artifacts, to bump when
+    /// the layout of the header or of the archive changes.
+    pub const FORMAT_VERSION: u32 = 3;
+
+    /// Create the header of the archived module `archive`, compiled for
+    /// `target`.
+    pub fn new(target: &Target, archive: &ArchivedModule<'_>) -> Self {
+        Self {
+            wasmer_version: crate::VERSION.to_string(),
+            triple: target.triple().to_string(),
+            cpu_features: cpu_features(target),
+            metadata_hash: blake3::hash(archive.metadata_region()).into(),
+        }
+    }
+
+    /// Serialize the header, and append it to `bytes`.
+    pub fn serialize_into(&self, bytes: &mut Vec<u8>) -> Result<(), bincode::Error> {
+        let header = bincode::serialize(self)?;
+        bytes.extend_from_slice(&Self::FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&header);
+        Ok(())
+    }
+
+    /// Read the header at the start of `bytes`, check that an engine
+    /// compiling for `target` can load the artifact, and return the
+    /// archived module following the header.
+    pub fn check<'a>(
+        bytes: &'a [u8],
+        target: &Target,
+    ) -> Result<ArchivedModule<'a>, DeserializeError> {
+        let truncated = || DeserializeError::CorruptedBinary("the header is truncated".to_string());
+        let read_u32 = |offset: usize| -> Result<u32, DeserializeError> {
+            let bytes = bytes.get(offset..offset + 4).ok_or_else(truncated)?;
+            Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+        };
+
+        let format_version = read_u32(0)?;
+        if format_version != Self::FORMAT_VERSION {
+            return Err(DeserializeError::Incompatible {
+                expected: format!("format version {}", Self::FORMAT_VERSION),
+                found: format!("format version {}", format_version),
+            });
+        }
+        let header_end = 8 + read_u32(4)? as usize;
+        let header: Self = bincode::deserialize(bytes.get(8..header_end).ok_or_else(truncated)?)
+            .map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))?;
+
+        if header.wasmer_version != crate::VERSION {
+            return Err(DeserializeError::Incompatible {
+                expected: format!("Wasmer {}", crate::VERSION),
+                found: format!("Wasmer {}", header.wasmer_version),
+            });
+        }
+        let triple = target.triple().to_string();
+        if header.triple != triple {
+            return Err(DeserializeError::Incompatible {
+                expected: format!("target {}", triple),
+                found: format!("target {}", header.triple),
+            });
+        }
+        // The artifact may use a subset of the CPU features of the target.
+        let target_cpu_features = cpu_features(target);
+        if !header
+            .cpu_features
+            .iter()
+            .all(|feature| target_cpu_features.contains(feature))
+        {
+            return Err(DeserializeError::Incompatible {
+                expected: format!("CPU features [{}]", target_cpu_features.join(", ")),
+                found: format!("CPU features [{}]", header.cpu_features.join(", ")),
+            });
+        }
+        let archive = ArchivedModule::new(&bytes[header_end..])?;
+        if header.metadata_hash != <[u8; 32]>::from(blake3::hash(archive.metadata_region())) {
+            return Err(DeserializeError::CorruptedBinary(
+                "the module doesn't match its metadata hash".to_string(),
+            ));
+        }
+        Ok(archive)
+    }
+}
+
+fn cpu_features(target: &Target) -> Vec<String> {
+    target
+        .cpu_features()
+        .iter()
+        .map(|feature| feature.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use wasmer_compiler::{CpuFeature, Triple};
+    use wasmer_types::entity::SecondaryMap;
+    use wasmer_types::Features;
+    use wasmer_vm::ModuleInfo;
+
+    fn target(triple: &str, cpu_features: &[CpuFeature]) -> Target {
+        let cpu_features = cpu_features
+            .iter()
+            .fold(CpuFeature::set(), |set, f| set | *f);
+        Target::new(Triple::from_str(triple).unwrap(), cpu_features)
+    }
+
+    fn serialize(target: &Target) -> Vec<u8> {
+        let archive = archive(&metadata(2, 1));
+        let mut bytes = Vec::new();
+        ArtifactHeader::new(target, &ArchivedModule::new(&archive).unwrap())
+            .serialize_into(&mut bytes)
+            .unwrap();
+        bytes.extend_from_slice(&archive);
+        bytes
+    }
+
+    fn first_function(archive: ArchivedModule<'_>) -> &[u8] {
+        archive.function_body(LocalFunctionIndex::new(0))
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let compiled_for = target("x86_64-unknown-linux-gnu", &[CpuFeature::SSE2]);
+        let bytes = serialize(&compiled_for);
+        let archive = ArtifactHeader::check(&bytes, &compiled_for).unwrap();
+        assert_eq!(first_function(archive), b"first");
+
+        // The target may have more CPU features.
+        let host = target(
+            "x86_64-unknown-linux-gnu",
+            &[CpuFeature::SSE2, CpuFeature::AVX],
+        );
+        let archive = ArtifactHeader::check(&bytes, &host).unwrap();
+        assert_eq!(first_function(archive), b"first");
+    }
+
+    #[test]
+    fn header_rejects_incompatible_artifacts() {
+        let compiled_for = target("x86_64-unknown-linux-gnu", &[CpuFeature::AVX]);
+        let bytes = serialize(&compiled_for);
+        for other in &[
+            target("aarch64-unknown-linux-gnu", &[CpuFeature::AVX]),
+            target("x86_64-unknown-linux-gnu", &[CpuFeature::SSE2]),
+        ] {
+            match ArtifactHeader::check(&bytes, other) {
+                Err(DeserializeError::Incompatible { .. }) => {}
+                _ => panic!("the artifact should be incompatible"),
+            }
+        }
+
+        let mut newer = bytes.clone();
+        newer[0] += 1;
+        match ArtifactHeader::check(&newer, &compiled_for) {
+            Err(DeserializeError::Incompatible { expected, found }) => {
+                assert_eq!(expected, "format version 3");
+                assert_eq!(found, "format version 4");
+            }
+            _ => panic!("the artifact should be incompatible"),
+        }
+    }
+
+    #[test]
+    fn header_detects_corruption() {
+        let compiled_for = target("x86_64-unknown-linux-gnu", &[]);
+        let bytes = serialize(&compiled_for);
+        let archive = ArtifactHeader::check(&bytes, &compiled_for).unwrap();
+        let metadata_end = bytes.len() - archive.bytes.len() + archive.metadata_region().len();
+        let mut corrupted = bytes.clone();
+        corrupted[metadata_end - 1] ^= 1;
+        for bytes in &[&corrupted[..], &bytes[..6], &bytes[..bytes.len() - 1]] {
+            match ArtifactHeader::check(bytes, &compiled_for) {
+                Err(DeserializeError::CorruptedBinary(_)) => {}
+                _ => panic!("the artifact should be corrupted"),
+            }
+        }
+
+        // The code isn't hashed.
+        let mut patched = bytes.clone();
+        patched[metadata_end] = b'F';
+        let archive = ArtifactHeader::check(&patched, &compiled_for).unwrap();
+        assert_eq!(first_function(archive), b"First");
+    }
+
+    fn metadata(num_functions: usize, num_custom_sections: usize) -> SerializableMetadata {
+        SerializableMetadata {
+            compile_info: CompileModuleInfo {
+                features: Features::new(),
+                module: Arc::new(ModuleInfo::new()),
+                memory_styles: PrimaryMap::new(),
+                table_styles: PrimaryMap::new(),
+            },
+            data_initializers: Box::new([]),
+            memory_images: Box::new([]),
+            function_unwind_infos: (0..num_functions).map(|_| None).collect(),
+            function_relocations: (0..num_functions).map(|_| Vec::new()).collect(),
+            function_jt_offsets: (0..num_functions).map(|_| SecondaryMap::new()).collect(),
+            function_call_trampoline_unwind_infos: PrimaryMap::new(),
+            dynamic_function_trampoline_unwind_infos: PrimaryMap::new(),
+            custom_section_protections: (0..num_custom_sections)
+                .map(|_| CustomSectionProtection::Read)
+                .collect(),
+            custom_section_relocations: (0..num_custom_sections).map(|_| Vec::new()).collect(),
+            debug: None,
+        }
+    }
+
+    fn archive(metadata: &SerializableMetadata) -> Vec<u8> {
+        let blobs = ArchiveBlobs {
+            function_bodies: vec![b"first", b"second"],
+            function_call_trampolines: vec![],
+            dynamic_function_trampolines: vec![],
+            custom_sections: vec![b"section"],
+            function_frame_infos: vec![b"frame", b""],
+        };
+        let mut bytes = b"prefix".to_vec();
+        ArchivedModule::write(metadata, &blobs, &mut bytes).unwrap();
+        bytes.drain(..6);
+        bytes
+    }
+
+    #[test]
+    fn archive_round_trip() {
+        let bytes = archive(&metadata(2, 1));
+        let archive = ArchivedModule::new(&bytes).unwrap();
+        let metadata = archive.metadata().unwrap();
+        assert_eq!(metadata.function_relocations.len(), 2);
+        // The bodies follow the metadata.
+        assert!(bytes[archive.metadata_region().len()..].starts_with(b"first"));
+        let function = LocalFunctionIndex::new;
+        assert_eq!(archive.function_body(function(0)), b"first");
+        assert_eq!(archive.function_body(function(1)), b"second");
+        assert_eq!(archive.custom_section(SectionIndex::new(0)), b"section");
+        assert_eq!(archive.function_frame_info(function(0)), b"frame");
+        assert_eq!(archive.function_frame_info(function(1)), b"");
+
+        let blobs = archive.blobs();
+        assert_eq!(blobs.function_bodies, [&b"first"[..], b"second"]);
+        assert_eq!(blobs.custom_sections, [b"section"]);
+        assert_eq!(blobs.function_frame_infos, [&b"frame"[..], b""]);
+    }
+
+    #[test]
+    fn archive_detects_corruption() {
+        let bytes = archive(&metadata(2, 1));
+        // The last blob ends past the truncated archive.
+        let truncated = &bytes[..bytes.len() - 1];
+        // The table of the second function body has a huge length.
+        let mut out_of_bounds = bytes.clone();
+        out_of_bounds[4 * 8 + 16 + 8..][..8].copy_from_slice(&u64::MAX.to_le_bytes());
+        for bytes in &[truncated, &out_of_bounds[..], &bytes[..8]] {
+            match ArchivedModule::new(bytes) {
+                Err(DeserializeError::CorruptedBinary(_)) => {}
+                _ => panic!("the archive should be corrupted"),
+            }
+        }
+
+        // The metadata describes a single function.
+        let bytes = archive(&metadata(1, 1));
+        match ArchivedModule::new(&bytes).unwrap().metadata() {
+            Err(DeserializeError::CorruptedBinary(_)) => {}
+            _ => panic!("the metadata should be rejected"),
+        }
+    }
 }
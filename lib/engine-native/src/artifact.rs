@@ -32,8 +32,8 @@ use wasmer_types::{
     TableIndex,
 };
 use wasmer_vm::{
-    FunctionBodyPtr, MemoryStyle, ModuleInfo, TableStyle, VMFunctionBody, VMSharedSignatureIndex,
-    VMTrampoline,
+    FunctionBodyPtr, MemoryImage, MemoryStyle, ModuleInfo, TableStyle, VMFunctionBody,
+    VMSharedSignatureIndex, VMTrampoline,
 };
 
 /// A compiled wasm module, ready to be instantiated.
@@ -150,11 +150,8 @@ impl NativeArtifact {
         let (compile_info, function_body_inputs, data_initializers, module_translation) =
             Self::generate_metadata(data, engine_inner.features(), tunables)?;
 
-        let data_initializers = data_initializers
-            .iter()
-            .map(OwnedDataInitializer::new)
-            .collect::<Vec<_>>()
-            .into_boxed_slice();
+        let (memory_images, data_initializers) =
+            MemoryImage::build(&compile_info.module, &data_initializers);
 
         let target_triple = target.triple();
 
@@ -179,7 +176,8 @@ impl NativeArtifact {
         let mut metadata = ModuleMetadata {
             compile_info,
             prefix: engine_inner.get_prefix(&data),
-            data_initializers,
+            data_initializers: data_initializers.into_boxed_slice(),
+            memory_images: memory_images.into_boxed_slice(),
             function_body_lengths,
         };
 
@@ -477,9 +475,10 @@ impl NativeArtifact {
         bytes: &[u8],
     ) -> Result<Self, DeserializeError> {
         if !Self::is_deserializable(&bytes) {
-            return Err(DeserializeError::Incompatible(
-                "The provided bytes are not in any native format Wasmer can understand".to_string(),
-            ));
+            return Err(DeserializeError::Incompatible {
+                expected: "a native shared object".to_string(),
+                found: "bytes in another format".to_string(),
+            });
         }
         // Dump the bytes into a file, so we can read it with our `dlopen`
         let named_file = NamedTempFile::new()?;
@@ -504,9 +503,10 @@ impl NativeArtifact {
         // read up to 5 bytes
         file.read_exact(&mut buffer)?;
         if !Self::is_deserializable(&buffer) {
-            return Err(DeserializeError::Incompatible(
-                "The provided bytes are not in any native format Wasmer can understand".to_string(),
-            ));
+            return Err(DeserializeError::Incompatible {
+                expected: "a native shared object".to_string(),
+                found: "bytes in another format".to_string(),
+            });
         }
         Self::deserialize_from_file_unchecked(&engine, &path)
     }
@@ -579,6 +579,10 @@ impl Artifact for NativeArtifact {
         &*self.metadata.data_initializers
     }
 
+    fn memory_images(&self) -> &[MemoryImage] {
+        &*self.metadata.memory_images
+    }
+
     fn memory_styles(&self) -> &PrimaryMap<MemoryIndex, MemoryStyle> {
         &self.metadata.compile_info.memory_styles
     }
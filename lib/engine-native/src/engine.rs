@@ -8,7 +8,7 @@ use std::sync::Mutex;
 use wasmer_compiler::{CompileError, Target};
 #[cfg(feature = "compiler")]
 use wasmer_compiler::{Compiler, Triple};
-use wasmer_engine::{Artifact, DeserializeError, Engine, EngineId, Tunables};
+use wasmer_engine::{engine_fingerprint, Artifact, DeserializeError, Engine, EngineId, Tunables};
 #[cfg(feature = "compiler")]
 use wasmer_types::Features;
 use wasmer_types::FunctionType;
@@ -185,6 +185,30 @@ impl Engine for NativeEngine {
         &self.engine_id
     }
 
+    fn fingerprint(&self) -> Option<String> {
+        #[cfg(feature = "compiler")]
+        {
+            let inner = self.inner();
+            let compiler = match &inner.compiler {
+                Some(compiler) => Some(compiler.fingerprint()?),
+                None => None,
+            };
+            Some(engine_fingerprint(
+                "native",
+                &self.target,
+                &inner.features,
+                compiler.as_deref(),
+            ))
+        }
+        #[cfg(not(feature = "compiler"))]
+        Some(engine_fingerprint(
+            "native",
+            &self.target,
+            &Default::default(),
+            None,
+        ))
+    }
+
     fn cloned(&self) -> Arc<dyn Engine + Send + Sync> {
         Arc::new(self.clone())
     }
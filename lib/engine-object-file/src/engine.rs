@@ -5,7 +5,7 @@ use std::sync::{Arc, Mutex};
 #[cfg(feature = "compiler")]
 use wasmer_compiler::Compiler;
 use wasmer_compiler::{CompileError, Target};
-use wasmer_engine::{Artifact, DeserializeError, Engine, EngineId, Tunables};
+use wasmer_engine::{engine_fingerprint, Artifact, DeserializeError, Engine, EngineId, Tunables};
 #[cfg(feature = "compiler")]
 use wasmer_types::Features;
 use wasmer_types::FunctionType;
@@ -159,6 +159,30 @@ impl Engine for ObjectFileEngine {
         &self.engine_id
     }
 
+    fn fingerprint(&self) -> Option<String> {
+        #[cfg(feature = "compiler")]
+        {
+            let inner = self.inner();
+            let compiler = match &inner.compiler {
+                Some(compiler) => Some(compiler.fingerprint()?),
+                None => None,
+            };
+            Some(engine_fingerprint(
+                "object-file",
+                &self.target,
+                &inner.features,
+                compiler.as_deref(),
+            ))
+        }
+        #[cfg(not(feature = "compiler"))]
+        Some(engine_fingerprint(
+            "object-file",
+            &self.target,
+            &Default::default(),
+            None,
+        ))
+    }
+
     fn cloned(&self) -> Arc<dyn Engine + Send + Sync> {
         Arc::new(self.clone())
     }
@@ -12,8 +12,8 @@ use wasmer_types::{
     SignatureIndex, TableIndex,
 };
 use wasmer_vm::{
-    FunctionBodyPtr, InstanceAllocator, InstanceHandle, MemoryStyle, ModuleInfo, TableStyle,
-    VMSharedSignatureIndex, VMTrampoline,
+    FunctionBodyPtr, InstanceHandle, MemoryImage, MemoryStyle, ModuleInfo, TableStyle,
+    VMInterrupts, VMSharedSignatureIndex, VMTrampoline,
 };
 
 /// An `Artifact` is the product that the `Engine`
@@ -51,6 +51,12 @@ pub trait Artifact: Send + Sync + Upcastable {
     /// Returns data initializers to pass to `InstanceHandle::initialize`
     fn data_initializers(&self) -> &[OwnedDataInitializer];
 
+    /// Returns the images of the local memories, which replace their
+    /// data initializers.
+    fn memory_images(&self) -> &[MemoryImage] {
+        &[]
+    }
+
     /// Returns the functions allocated in memory or this `Artifact`
     /// ready to be run.
     fn finished_functions(&self) -> &BoxedSlice<LocalFunctionIndex, FunctionBodyPtr>;
@@ -90,6 +96,7 @@ pub trait Artifact: Send + Sync + Upcastable {
         &self,
         tunables: &dyn Tunables,
         resolver: &dyn Resolver,
+        interrupts: Arc<VMInterrupts>,
         host_state: Box<dyn Any>,
     ) -> Result<InstanceHandle, InstantiationError> {
         self.preinstantiate()?;
@@ -115,8 +122,9 @@ pub trait Artifact: Send + Sync + Upcastable {
         // Get pointers to where metadata about local memories should live in VM memory.
         // Get pointers to where metadata about local tables should live in VM memory.
 
-        let (allocator, memory_definition_locations, table_definition_locations) =
-            InstanceAllocator::new(&*module);
+        let (allocator, memory_definition_locations, table_definition_locations) = tunables
+            .create_instance_allocator(&module)
+            .map_err(InstantiationError::Link)?;
         let finished_memories = tunables
             .create_memories(&module, self.memory_styles(), &memory_definition_locations)
             .map_err(InstantiationError::Link)?
@@ -142,6 +150,7 @@ pub trait Artifact: Send + Sync + Upcastable {
             finished_globals,
             imports,
             self.signatures().clone(),
+            interrupts,
             host_state,
             import_function_envs,
         )
@@ -167,7 +176,7 @@ pub trait Artifact: Send + Sync + Upcastable {
             })
             .collect::<Vec<_>>();
         handle
-            .finish_instantiation(&data_initializers)
+            .finish_instantiation(self.memory_images(), &data_initializers)
             .map_err(|trap| InstantiationError::Start(RuntimeError::from_trap(trap)))
     }
 }
@@ -7,7 +7,7 @@ use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
 use std::sync::Arc;
 use wasmer_compiler::{CompileError, Target};
-use wasmer_types::FunctionType;
+use wasmer_types::{Features, FunctionType};
 use wasmer_vm::VMSharedSignatureIndex;
 
 /// A unimplemented Wasmer `Engine`.
@@ -64,10 +64,53 @@ pub trait Engine {
     /// of trait representation.
     fn id(&self) -> &EngineId;
 
+    /// Returns a stable description of this engine and of every setting
+    /// changing the artifacts it produces: its kind, its target, the
+    /// enabled features, the configuration of its compiler and the
+    /// version of Wasmer.
+    ///
+    /// Artifacts produced by engines with different fingerprints mustn't
+    /// be used in place of each other, e.g. in a cache. See
+    /// [`engine_fingerprint`].
+    ///
+    /// The default implementation returns `None`, for an engine whose
+    /// artifacts mustn't be cached. Engines with a compiler return `None`
+    /// as well when the compiler has no fingerprint.
+    fn fingerprint(&self) -> Option<String> {
+        None
+    }
+
     /// Clone the engine
     fn cloned(&self) -> Arc<dyn Engine + Send + Sync>;
 }
 
+/// Builds the [`Engine::fingerprint`] of an engine of kind `kind`,
+/// compiling for `target` with `features`, from the
+/// [`Compiler::fingerprint`] of its compiler, if it has one.
+///
+/// [`Compiler::fingerprint`]: wasmer_compiler::Compiler::fingerprint
+pub fn engine_fingerprint(
+    kind: &str,
+    target: &Target,
+    features: &Features,
+    compiler: Option<&str>,
+) -> String {
+    let cpu_features = target
+        .cpu_features()
+        .iter()
+        .map(|feature| feature.to_string())
+        .collect::<Vec<_>>();
+    format!(
+        "wasmer {}\nengine {}\ntarget {} [{}]\nfeatures {:?}\ncompiler {}",
+        crate::VERSION,
+        kind,
+        target.triple(),
+        cpu_features.join(", "),
+        features,
+        compiler.unwrap_or("none"),
+    )
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(transparent)]
 /// A unique identifier for an Engine.
@@ -27,9 +27,15 @@ pub enum DeserializeError {
     /// A generic deserialization error
     #[error("{0}")]
     Generic(String),
-    /// Incompatible serialized binary
-    #[error("incompatible binary: {0}")]
-    Incompatible(String),
+    /// The binary can't be loaded by this engine, e.g. because it was
+    /// produced by another version of Wasmer or for another target.
+    #[error("incompatible binary: expected {expected}, found {found}")]
+    Incompatible {
+        /// What the engine can load
+        expected: String,
+        /// What the binary is
+        found: String,
+    },
     /// The provided binary is corrupted
     #[error("corrupted binary: {0}")]
     CorruptedBinary(String),
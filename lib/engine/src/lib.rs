@@ -30,7 +30,7 @@ mod trap;
 mod tunables;
 
 pub use crate::artifact::Artifact;
-pub use crate::engine::{Engine, EngineId};
+pub use crate::engine::{engine_fingerprint, Engine, EngineId};
 pub use crate::error::{
     DeserializeError, ImportError, InstantiationError, LinkError, SerializeError,
 };
@@ -41,7 +41,7 @@ pub use crate::resolver::{
     resolve_imports, ChainableNamedResolver, NamedResolver, NamedResolverChain, NullResolver,
     Resolver,
 };
-pub use crate::serialize::SerializableFunctionFrameInfo;
+pub use crate::serialize::{SerializableFunctionFrameInfo, UnprocessedFunctionFrameInfo};
 pub use crate::trap::*;
 pub use crate::tunables::Tunables;
 
@@ -14,6 +14,17 @@ pub struct UnprocessedFunctionFrameInfo {
 }
 
 impl UnprocessedFunctionFrameInfo {
+    /// Wraps the bytes of a `CompiledFunctionFrameInfo` serialized by
+    /// `UnprocessedFunctionFrameInfo::serialize`.
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self { bytes }
+    }
+
+    /// Returns the bytes of the serialized `CompiledFunctionFrameInfo`.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
     /// Converts the `UnprocessedFunctionFrameInfo` to a `CompiledFunctionFrameInfo`
     pub fn deserialize(&self) -> CompiledFunctionFrameInfo {
         // let r = flexbuffers::Reader::get_root(&self.bytes).expect("Can't deserialize the info");
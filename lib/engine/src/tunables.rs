@@ -7,7 +7,7 @@ use wasmer_types::{
     TableIndex, TableType,
 };
 use wasmer_vm::MemoryError;
-use wasmer_vm::{Global, Memory, ModuleInfo, Table};
+use wasmer_vm::{Global, InstanceAllocator, Memory, ModuleInfo, Table};
 use wasmer_vm::{MemoryStyle, TableStyle};
 use wasmer_vm::{VMMemoryDefinition, VMTableDefinition};
 
@@ -56,6 +56,23 @@ pub trait Tunables {
         vm_definition_location: NonNull<VMTableDefinition>,
     ) -> Result<Arc<dyn Table>, String>;
 
+    /// Allocate the instance data of the current module, see
+    /// [`InstanceAllocator::new`].
+    #[allow(clippy::type_complexity)]
+    fn create_instance_allocator(
+        &self,
+        module: &ModuleInfo,
+    ) -> Result<
+        (
+            InstanceAllocator,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        LinkError,
+    > {
+        Ok(InstanceAllocator::new(module))
+    }
+
     /// Create a global with an unset value.
     fn create_global(&self, ty: GlobalType) -> Result<Arc<Global>, String> {
         Ok(Arc::new(Global::new(ty)))
@@ -75,7 +92,7 @@ pub trait Tunables {
             let mi = MemoryIndex::new(index);
             let ty = &module.memories[mi];
             let style = &memory_styles[mi];
-            let mdl = memory_definition_locations[index];
+            let mdl = memory_definition_locations[index - num_imports];
             memories.push(
                 self.create_vm_memory(ty, style, mdl)
                     .map_err(|e| LinkError::Resource(format!("Failed to create memory: {}", e)))?,
@@ -98,7 +115,7 @@ pub trait Tunables {
             let ti = TableIndex::new(index);
             let ty = &module.tables[ti];
             let style = &table_styles[ti];
-            let tdl = table_definition_locations[index];
+            let tdl = table_definition_locations[index - num_imports];
             tables.push(
                 self.create_vm_table(ty, style, tdl)
                     .map_err(LinkError::Resource)?,
@@ -54,6 +54,9 @@ pub struct Metering<F: Fn(&Operator) -> u64 + Copy + Clone + Send + Sync> {
     /// Function that maps each operator to a cost in "points".
     cost_function: F,
 
+    /// Identifier of the cost function, see `Metering::with_fingerprint`.
+    cost_function_id: Option<String>,
+
     /// The global indexes for metering points.
     global_indexes: Mutex<Option<MeteringGlobalIndexes>>,
 }
@@ -86,9 +89,20 @@ impl<F: Fn(&Operator) -> u64 + Copy + Clone + Send + Sync> Metering<F> {
         Self {
             initial_limit,
             cost_function,
+            cost_function_id: None,
             global_indexes: Mutex::new(None),
         }
     }
+
+    /// Identifies the cost function by `cost_function_id`, which must
+    /// change whenever the cost function does.
+    ///
+    /// Without it, the middleware has no fingerprint, and the modules
+    /// compiled with it aren't cached.
+    pub fn with_fingerprint(mut self, cost_function_id: impl Into<String>) -> Self {
+        self.cost_function_id = Some(cost_function_id.into());
+        self
+    }
 }
 
 impl<F: Fn(&Operator) -> u64 + Copy + Clone + Send + Sync> fmt::Debug for Metering<F> {
@@ -96,6 +110,7 @@ impl<F: Fn(&Operator) -> u64 + Copy + Clone + Send + Sync> fmt::Debug for Meteri
         f.debug_struct("Metering")
             .field("initial_limit", &self.initial_limit)
             .field("cost_function", &"<function>")
+            .field("cost_function_id", &self.cost_function_id)
             .field("global_indexes", &self.global_indexes)
             .finish()
     }
@@ -154,6 +169,17 @@ impl<F: Fn(&Operator) -> u64 + Copy + Clone + Send + Sync + 'static> ModuleMiddl
             points_exhausted_global_index,
         ))
     }
+
+    /// The cost function can't be told apart from another one, so there
+    /// is no fingerprint unless it is given an identifier with
+    /// `Metering::with_fingerprint`.
+    fn fingerprint(&self) -> Option<String> {
+        let cost_function_id = self.cost_function_id.as_ref()?;
+        Some(format!(
+            "Metering(initial_limit={}, cost_function={:?})",
+            self.initial_limit, cost_function_id
+        ))
+    }
 }
 
 impl<F: Fn(&Operator) -> u64 + Copy + Clone + Send + Sync> fmt::Debug for FunctionMetering<F> {
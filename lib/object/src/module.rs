@@ -203,18 +203,8 @@ pub fn emit_compilation(
     }
 
     // Add relocations (function and sections)
-    let (relocation_size, relocation_kind, relocation_encoding) = match triple.architecture {
-        Architecture::X86_64 => (
-            32,
-            RelocationKind::PltRelative,
-            RelocationEncoding::X86Branch,
-        ),
-        // Object doesn't fully support it yet
-        // Architecture::Aarch64(_) => (
-        //     32,
-        //     RelocationKind::PltRelative,
-        //     RelocationEncoding::Generic,
-        // ),
+    match triple.architecture {
+        Architecture::X86_64 | Architecture::Aarch64(_) => {}
         architecture => {
             return Err(ObjectError::UnsupportedArchitecture(
                 architecture.to_string(),
@@ -242,6 +232,8 @@ pub fn emit_compilation(
 
         for r in relocations {
             let relocation_address = section_offset + r.offset as u64;
+            let (relocation_size, relocation_kind, relocation_encoding) =
+                relocation_properties(r.kind);
 
             match r.reloc_target {
                 RelocationTarget::LocalFunc(index) => {
@@ -314,3 +306,21 @@ pub fn emit_compilation(
 
     Ok(())
 }
+
+/// Returns the size in bits, the kind and the encoding of an object file relocation
+/// corresponding to a relocation of the given kind.
+fn relocation_properties(
+    kind: wasmer_compiler::RelocationKind,
+) -> (u8, RelocationKind, RelocationEncoding) {
+    use wasmer_compiler::RelocationKind as Kind;
+    match kind {
+        Kind::Abs4 => (32, RelocationKind::Absolute, RelocationEncoding::Generic),
+        Kind::Abs8 => (64, RelocationKind::Absolute, RelocationEncoding::Generic),
+        Kind::X86PCRel8 => (64, RelocationKind::Relative, RelocationEncoding::Generic),
+        Kind::X86PCRel4 | Kind::X86PCRelRodata4 | Kind::X86CallPCRel4 | Kind::X86CallPLTRel4 => (
+            32,
+            RelocationKind::PltRelative,
+            RelocationEncoding::X86Branch,
+        ),
+    }
+}
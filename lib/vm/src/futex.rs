@@ -0,0 +1,239 @@
+//! Waiting on addresses of shared linear memories, for
+//! `memory.atomic.wait32`, `memory.atomic.wait64` and `memory.atomic.notify`.
+//!
+//! Like a futex, waiting threads are parked in a hash table keyed by the
+//! host address they wait on: the one of `parking_lot_core`, whose buckets
+//! are locked while a waiter checks the value it expects, so that a
+//! notification can't slip in between the check and the wait.
+//!
+//! Waits are also interrupted by the [`VMInterrupts`] of the instance
+//! waiting, which wake up all of its waiting threads.
+
+use crate::vmcontext::VMInterrupts;
+use parking_lot_core::{
+    park, unpark_filter, FilterOp, ParkResult, ParkToken, UnparkToken, DEFAULT_UNPARK_TOKEN,
+};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+lazy_static::lazy_static! {
+    /// The number of threads waiting on each address, so that interrupts
+    /// know which addresses to wake up threads on.
+    static ref WAITING: Mutex<HashMap<usize, usize>> = Default::default();
+}
+
+/// The token given to the threads woken up by an interrupt.
+const INTERRUPTED_TOKEN: UnparkToken = UnparkToken(1);
+
+/// The outcome of a wait, with the values returned by `memory.atomic.wait`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub(crate) enum WaitResult {
+    /// The thread was woken up by a notification.
+    Ok = 0,
+    /// The value in memory wasn't the expected one.
+    NotEqual = 1,
+    /// The timeout expired before a notification.
+    TimedOut = 2,
+    /// The wait was interrupted, which traps rather than returning a value.
+    Interrupted = 3,
+}
+
+/// Wait until a notification on `addr`, if it holds `expected`.
+///
+/// A `timeout` of `None` waits forever.
+///
+/// # Safety
+/// `addr` must be aligned and valid for reads.
+pub(crate) unsafe fn wait32(
+    addr: *const u32,
+    expected: u32,
+    timeout: Option<Duration>,
+    interrupts: &VMInterrupts,
+) -> WaitResult {
+    let value = &*(addr as *const AtomicU32);
+    wait(
+        addr as usize,
+        || value.load(Ordering::SeqCst) == expected,
+        timeout,
+        interrupts,
+    )
+}
+
+/// Wait until a notification on `addr`, if it holds `expected`.
+///
+/// A `timeout` of `None` waits forever.
+///
+/// # Safety
+/// `addr` must be aligned and valid for reads.
+pub(crate) unsafe fn wait64(
+    addr: *const u64,
+    expected: u64,
+    timeout: Option<Duration>,
+    interrupts: &VMInterrupts,
+) -> WaitResult {
+    let value = &*(addr as *const AtomicU64);
+    wait(
+        addr as usize,
+        || value.load(Ordering::SeqCst) == expected,
+        timeout,
+        interrupts,
+    )
+}
+
+unsafe fn wait(
+    key: usize,
+    validate: impl FnOnce() -> bool,
+    timeout: Option<Duration>,
+    interrupts: &VMInterrupts,
+) -> WaitResult {
+    // A deadline too far away to be represented is the same as none.
+    let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+    // The waiter is registered before checking for interrupts, so that an
+    // interrupt either is seen by the check or wakes the waiter up.
+    *WAITING.lock().unwrap().entry(key).or_insert(0) += 1;
+    let result = park(
+        key,
+        || interrupts.interrupt.load(Ordering::SeqCst) == 0 && validate(),
+        || {},
+        |_, _| {},
+        ParkToken(interrupts as *const VMInterrupts as usize),
+        deadline,
+    );
+    {
+        let mut waiting = WAITING.lock().unwrap();
+        let count = waiting.get_mut(&key).unwrap();
+        *count -= 1;
+        if *count == 0 {
+            waiting.remove(&key);
+        }
+    }
+    match result {
+        ParkResult::Unparked(INTERRUPTED_TOKEN) => {
+            interrupts.interrupt.store(0, Ordering::SeqCst);
+            WaitResult::Interrupted
+        }
+        ParkResult::Unparked(_) => WaitResult::Ok,
+        ParkResult::Invalid if interrupts.interrupt.swap(0, Ordering::SeqCst) != 0 => {
+            WaitResult::Interrupted
+        }
+        ParkResult::Invalid => WaitResult::NotEqual,
+        ParkResult::TimedOut => WaitResult::TimedOut,
+    }
+}
+
+/// Wake up at most `count` of the threads waiting on `addr`, returning how
+/// many were woken up.
+pub(crate) fn notify(addr: *const u8, count: u32) -> u32 {
+    let mut remaining = count;
+    let filter = |_| {
+        if remaining == 0 {
+            FilterOp::Stop
+        } else {
+            remaining -= 1;
+            FilterOp::Unpark
+        }
+    };
+    let result = unsafe { unpark_filter(addr as usize, filter, |_| DEFAULT_UNPARK_TOKEN) };
+    // At most `count` threads are woken up.
+    result.unparked_threads as u32
+}
+
+/// Wake up all the threads waiting with `interrupts`, whose waits then
+/// trap.
+pub(crate) fn interrupt_waiters(interrupts: &VMInterrupts) {
+    let token = ParkToken(interrupts as *const VMInterrupts as usize);
+    let keys = WAITING.lock().unwrap().keys().copied().collect::<Vec<_>>();
+    for key in keys {
+        let filter = |park_token| {
+            if park_token == token {
+                FilterOp::Unpark
+            } else {
+                FilterOp::Skip
+            }
+        };
+        unsafe { unpark_filter(key, filter, |_| INTERRUPTED_TOKEN) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn wait_returns_not_equal_for_another_value() {
+        let value = 1u32;
+        let result = unsafe { wait32(&value, 2, None, &VMInterrupts::default()) };
+        assert_eq!(result, WaitResult::NotEqual);
+    }
+
+    #[test]
+    fn wait_times_out() {
+        let value = 1u64;
+        let result = unsafe {
+            wait64(
+                &value,
+                1,
+                Some(Duration::from_millis(10)),
+                &VMInterrupts::default(),
+            )
+        };
+        assert_eq!(result, WaitResult::TimedOut);
+    }
+
+    #[test]
+    fn notify_wakes_up_waiters() {
+        let value = Arc::new(AtomicU32::new(0));
+        let woken = Arc::new(AtomicBool::new(false));
+        let waiter = {
+            let value = value.clone();
+            let woken = woken.clone();
+            thread::spawn(move || {
+                let addr = value.as_ref() as *const AtomicU32 as *const u32;
+                let interrupts = VMInterrupts::default();
+                assert_eq!(
+                    unsafe { wait32(addr, 0, None, &interrupts) },
+                    WaitResult::Ok
+                );
+                woken.store(true, Ordering::SeqCst);
+            })
+        };
+
+        let addr = value.as_ref() as *const AtomicU32 as *const u8;
+        while notify(addr, 1) == 0 {
+            assert!(!woken.load(Ordering::SeqCst));
+            thread::yield_now();
+        }
+        waiter.join().unwrap();
+        assert!(woken.load(Ordering::SeqCst));
+        assert_eq!(notify(addr, 1), 0);
+    }
+
+    #[test]
+    fn interrupts_wake_up_their_waiters() {
+        let interrupts = Arc::new(VMInterrupts::default());
+        let waiting = Arc::new(AtomicBool::new(false));
+        let waiter = {
+            let interrupts = interrupts.clone();
+            let waiting = waiting.clone();
+            thread::spawn(move || {
+                let value = 0u32;
+                waiting.store(true, Ordering::SeqCst);
+                unsafe { wait32(&value, 0, None, &interrupts) }
+            })
+        };
+
+        while !waiting.load(Ordering::SeqCst) {
+            thread::yield_now();
+        }
+        interrupts.interrupt();
+        assert_eq!(waiter.join().unwrap(), WaitResult::Interrupted);
+        // The interrupt is consumed by the wait.
+        assert_eq!(interrupts.interrupt.load(Ordering::SeqCst), 0);
+    }
+}
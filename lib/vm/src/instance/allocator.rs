@@ -1,10 +1,12 @@
 use super::{Instance, InstanceRef};
+use crate::pool::InstancePool;
 use crate::vmcontext::{VMMemoryDefinition, VMTableDefinition};
 use crate::{ModuleInfo, VMOffsets};
 use std::alloc::{self, Layout};
 use std::convert::TryFrom;
 use std::mem;
 use std::ptr::{self, NonNull};
+use std::sync::Arc;
 use wasmer_types::entity::EntityRef;
 use wasmer_types::{LocalMemoryIndex, LocalTableIndex};
 
@@ -37,6 +39,9 @@ pub struct InstanceAllocator {
     /// the dynamic fields.
     offsets: VMOffsets,
 
+    /// The pool the `instance_ptr` buffer comes from, if any.
+    pool: Option<Arc<InstancePool>>,
+
     /// Whether or not this type has transferred ownership of the
     /// `instance_ptr` buffer. If it has not when being dropped,
     /// the buffer should be freed.
@@ -50,8 +55,11 @@ impl Drop for InstanceAllocator {
             // over the buffer and must free it.
             let instance_ptr = self.instance_ptr.as_ptr();
 
-            unsafe {
-                std::alloc::dealloc(instance_ptr as *mut u8, self.instance_layout);
+            match &self.pool {
+                Some(pool) => pool.deallocate_instance(instance_ptr as *mut u8),
+                None => unsafe {
+                    std::alloc::dealloc(instance_ptr as *mut u8, self.instance_layout);
+                },
             }
         }
     }
@@ -74,7 +82,7 @@ impl InstanceAllocator {
         Vec<NonNull<VMMemoryDefinition>>,
         Vec<NonNull<VMTableDefinition>>,
     ) {
-        let offsets = VMOffsets::new(mem::size_of::<usize>() as u8, module);
+        let offsets = Self::offsets_for(module);
         let instance_layout = Self::instance_layout(&offsets);
 
         #[allow(clippy::cast_ptr_alignment)]
@@ -86,25 +94,57 @@ impl InstanceAllocator {
             alloc::handle_alloc_error(instance_layout);
         };
 
+        // # Safety
+        // The pointer has just been allocated with the layout computed
+        // from `offsets`.
+        unsafe { Self::from_parts(instance_ptr, instance_layout, offsets, None) }
+    }
+
+    /// Wraps a buffer taken from an [`InstancePool`], see
+    /// [`InstanceAllocator::new`].
+    ///
+    /// # Safety
+    ///
+    /// - `instance_ptr` must point to a buffer of at least
+    ///   `instance_layout.size()` bytes, aligned to
+    ///   `instance_layout.align()`, that was laid out from `offsets`.
+    /// - If `pool` is set, the buffer must come from it. Otherwise it
+    ///   must have been allocated with `instance_layout`.
+    pub(crate) unsafe fn from_parts(
+        instance_ptr: NonNull<Instance>,
+        instance_layout: Layout,
+        offsets: VMOffsets,
+        pool: Option<Arc<InstancePool>>,
+    ) -> (
+        Self,
+        Vec<NonNull<VMMemoryDefinition>>,
+        Vec<NonNull<VMTableDefinition>>,
+    ) {
         let allocator = Self {
             instance_ptr,
             instance_layout,
             offsets,
+            pool,
             consumed: false,
         };
 
         // # Safety
-        // Both of these calls are safe because we allocate the pointer
-        // above with the same `offsets` that these functions use.
+        // Both of these calls are safe because the buffer has been
+        // allocated with the same `offsets` that these functions use.
         // Thus there will be enough valid memory for both of them.
-        let memories = unsafe { allocator.memory_definition_locations() };
-        let tables = unsafe { allocator.table_definition_locations() };
+        let memories = allocator.memory_definition_locations();
+        let tables = allocator.table_definition_locations();
 
         (allocator, memories, tables)
     }
 
+    /// Calculate the [`VMOffsets`] of an instance of `module`.
+    pub(crate) fn offsets_for(module: &ModuleInfo) -> VMOffsets {
+        VMOffsets::new(mem::size_of::<usize>() as u8, module)
+    }
+
     /// Calculate the appropriate layout for the [`Instance`].
-    fn instance_layout(offsets: &VMOffsets) -> Layout {
+    pub(crate) fn instance_layout(offsets: &VMOffsets) -> Layout {
         let vmctx_size = usize::try_from(offsets.size_of_vmctx())
             .expect("Failed to convert the size of `vmctx` to a `usize`");
 
@@ -201,10 +241,11 @@ impl InstanceAllocator {
         }
         let instance = self.instance_ptr;
         let instance_layout = self.instance_layout;
+        let pool = self.pool.take();
 
         // This is correct because of the invariants of `Self` and
         // because we write `Instance` to the pointer in this function.
-        unsafe { InstanceRef::new(instance, instance_layout) }
+        unsafe { InstanceRef::new(instance, instance_layout, pool) }
     }
 
     /// Get the [`VMOffsets`] for the allocated buffer.
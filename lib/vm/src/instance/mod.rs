@@ -8,20 +8,28 @@
 //! wrapper around an `InstanceRef`.
 
 mod allocator;
+mod snapshot;
 
 pub use allocator::InstanceAllocator;
+pub use snapshot::{
+    GlobalSnapshot, InstanceSnapshot, MemorySegment, MemorySnapshot, SnapshotError, TableSnapshot,
+};
 
 use crate::export::VMExport;
+use crate::futex;
 use crate::global::Global;
 use crate::imports::Imports;
 use crate::memory::{Memory, MemoryError};
+use crate::memory_image::MemoryImage;
+use crate::pool::InstancePool;
 use crate::table::Table;
+use crate::tail_call;
 use crate::trap::{catch_traps, init_traps, Trap, TrapCode};
 use crate::vmcontext::{
     VMBuiltinFunctionsArray, VMCallerCheckedAnyfunc, VMContext, VMFunctionBody,
     VMFunctionEnvironment, VMFunctionImport, VMFunctionKind, VMGlobalDefinition, VMGlobalImport,
-    VMMemoryDefinition, VMMemoryImport, VMSharedSignatureIndex, VMTableDefinition, VMTableImport,
-    VMTrampoline,
+    VMInterrupts, VMMemoryDefinition, VMMemoryImport, VMSharedSignatureIndex, VMTableDefinition,
+    VMTableImport, VMTrampoline,
 };
 use crate::{FunctionBodyPtr, ModuleInfo, VMOffsets};
 use crate::{VMExportFunction, VMExportGlobal, VMExportMemory, VMExportTable};
@@ -31,11 +39,12 @@ use std::alloc::Layout;
 use std::any::Any;
 use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
-use std::convert::{TryFrom, TryInto};
+use std::convert::TryFrom;
 use std::ffi;
 use std::fmt;
 use std::ptr::NonNull;
 use std::sync::{atomic, Arc};
+use std::time::Duration;
 use std::{mem, ptr, slice};
 use wasmer_types::entity::{packed_option::ReservedValue, BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{
@@ -63,6 +72,10 @@ pub(crate) struct Instance {
     /// Offsets in the `vmctx` region.
     offsets: VMOffsets,
 
+    /// The interrupt flags checked by the compiled code, pointed to by
+    /// the `vmctx` region.
+    interrupts: Arc<VMInterrupts>,
+
     /// WebAssembly linear memory data.
     memories: BoxedSlice<LocalMemoryIndex, Arc<dyn Memory>>,
 
@@ -218,6 +231,11 @@ impl Instance {
         &*self.module
     }
 
+    /// Return a pointer to the pointer to the `VMInterrupts`.
+    fn interrupts_ptr(&self) -> *mut *const VMInterrupts {
+        unsafe { self.vmctx_plus_offset(self.offsets.vmctx_interrupts()) }
+    }
+
     /// Return a pointer to the `VMSharedSignatureIndex`s.
     fn signature_ids_ptr(&self) -> *mut VMSharedSignatureIndex {
         unsafe { self.vmctx_plus_offset(self.offsets.vmctx_signature_ids_begin()) }
@@ -409,11 +427,14 @@ impl Instance {
         };
 
         // Make the call.
+        let _stack_limit = self.interrupts.enter_wasm();
         unsafe {
             catch_traps(callee_vmctx, || {
                 mem::transmute::<*const VMFunctionBody, unsafe extern "C" fn(VMFunctionEnvironment)>(
                     callee_address,
-                )(callee_vmctx)
+                )(callee_vmctx);
+                // The start function returns no values.
+                tail_call::finish_tail_calls(ptr::null_mut());
             })
         }
     }
@@ -660,6 +681,26 @@ impl Instance {
         unsafe { memory.memory_copy(dst, src, len) }
     }
 
+    /// Perform a `memory.copy` between two different memories.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Trap` error when the source or destination ranges are out of
+    /// bounds.
+    pub(crate) fn memory_copy_between(
+        &self,
+        dst_memory_index: MemoryIndex,
+        src_memory_index: MemoryIndex,
+        dst: u32,
+        src: u32,
+        len: u32,
+    ) -> Result<(), Trap> {
+        let dst_memory = self.get_memory(dst_memory_index);
+        let src_memory = self.get_memory(src_memory_index);
+        // The following memory copy is not synchronized and is not atomic:
+        unsafe { dst_memory.memory_copy_from(&src_memory, dst, src, len) }
+    }
+
     /// Perform the `memory.fill` operation on a locally defined memory.
     ///
     /// # Errors
@@ -695,6 +736,137 @@ impl Instance {
         unsafe { memory.memory_fill(dst, val, len) }
     }
 
+    /// Perform the `memory.atomic.wait32` operation, returning 0 when woken
+    /// up, 1 when the memory doesn't hold `expected` and 2 on timeout.
+    ///
+    /// A negative `timeout`, in nanoseconds, waits forever.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Trap` error if the address is out of bounds or unaligned,
+    /// if the memory isn't shared, or if the wait is interrupted.
+    pub(crate) fn memory_atomic_wait32(
+        &self,
+        memory_index: MemoryIndex,
+        addr: u64,
+        offset: u32,
+        expected: u32,
+        timeout: i64,
+    ) -> Result<u32, Trap> {
+        let addr = self.atomic_address(memory_index, addr, offset, 4)?;
+        self.check_shared_memory(memory_index)?;
+        let timeout = wait_timeout(timeout);
+        match unsafe { futex::wait32(addr as *const u32, expected, timeout, &self.interrupts) } {
+            futex::WaitResult::Interrupted => Err(Trap::new_from_runtime(TrapCode::Interrupt)),
+            result => Ok(result as u32),
+        }
+    }
+
+    /// Perform the `memory.atomic.wait64` operation, returning 0 when woken
+    /// up, 1 when the memory doesn't hold `expected` and 2 on timeout.
+    ///
+    /// A negative `timeout`, in nanoseconds, waits forever.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Trap` error if the address is out of bounds or unaligned,
+    /// if the memory isn't shared, or if the wait is interrupted.
+    pub(crate) fn memory_atomic_wait64(
+        &self,
+        memory_index: MemoryIndex,
+        addr: u64,
+        offset: u32,
+        expected: u64,
+        timeout: i64,
+    ) -> Result<u32, Trap> {
+        let addr = self.atomic_address(memory_index, addr, offset, 8)?;
+        self.check_shared_memory(memory_index)?;
+        let timeout = wait_timeout(timeout);
+        match unsafe { futex::wait64(addr as *const u64, expected, timeout, &self.interrupts) } {
+            futex::WaitResult::Interrupted => Err(Trap::new_from_runtime(TrapCode::Interrupt)),
+            result => Ok(result as u32),
+        }
+    }
+
+    /// Perform the `memory.atomic.notify` operation, returning the number of
+    /// threads that were woken up.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Trap` error if the address is out of bounds or unaligned.
+    pub(crate) fn memory_atomic_notify(
+        &self,
+        memory_index: MemoryIndex,
+        addr: u64,
+        offset: u32,
+        count: u32,
+    ) -> Result<u32, Trap> {
+        let addr = self.atomic_address(memory_index, addr, offset, 4)?;
+        // Nothing can wait on a memory that isn't shared.
+        if !self.module.memories[memory_index].shared {
+            return Ok(0);
+        }
+        Ok(futex::notify(addr, count))
+    }
+
+    /// Perform the `return_call` and `return_call_indirect` operations,
+    /// recording a tail call to `callee`, whose signature is `sig_index`
+    /// in this instance's module.
+    ///
+    /// # Safety
+    ///
+    /// `callee` and `vmctx` must be a function of that signature, and
+    /// `values` must hold its arguments, 16 bytes apart.
+    pub(crate) unsafe fn return_call(
+        &self,
+        sig_index: SignatureIndex,
+        vmctx: *mut VMContext,
+        callee: *const VMFunctionBody,
+        values: *const u128,
+    ) {
+        let signature = &self.module.signatures[sig_index];
+        tail_call::record_tail_call(
+            self.function_call_trampolines[sig_index],
+            vmctx,
+            callee,
+            values,
+            signature.params().len(),
+            signature.results().len(),
+        );
+    }
+
+    /// Get the host address of an atomic access of `size` bytes to `addr +
+    /// offset`, checking that it's in bounds and aligned.
+    fn atomic_address(
+        &self,
+        memory_index: MemoryIndex,
+        addr: u64,
+        offset: u32,
+        size: u64,
+    ) -> Result<*const u8, Trap> {
+        let memory = self.get_memory(memory_index);
+        let start = addr
+            .checked_add(u64::from(offset))
+            .filter(|start| match start.checked_add(size) {
+                Some(end) => end <= memory.current_length as u64,
+                None => false,
+            })
+            .ok_or_else(|| Trap::new_from_runtime(TrapCode::HeapAccessOutOfBounds))?;
+        if start % size != 0 {
+            return Err(Trap::new_from_runtime(TrapCode::UnalignedAtomic));
+        }
+        Ok(unsafe { memory.base.add(start as usize) })
+    }
+
+    /// Check that a memory is shared, as required to wait on it.
+    fn check_shared_memory(&self, memory_index: MemoryIndex) -> Result<(), Trap> {
+        if self.module.memories[memory_index].shared {
+            Ok(())
+        } else {
+            Err(Trap::new_from_runtime(TrapCode::AtomicWaitOnUnsharedMemory))
+        }
+    }
+
     /// Performs the `memory.init` operation.
     ///
     /// # Errors
@@ -723,7 +895,7 @@ impl Instance {
             .map_or(true, |n| n as usize > data.len())
             || dst
                 .checked_add(len)
-                .map_or(true, |m| m > memory.current_length)
+                .map_or(true, |m| m as usize > memory.current_length)
         {
             return Err(Trap::new_from_runtime(TrapCode::HeapAccessOutOfBounds));
         }
@@ -804,6 +976,10 @@ pub struct InstanceRef {
     /// The layout of `Instance` (which can vary).
     instance_layout: Layout,
 
+    /// The pool the `Instance` buffer comes from, if any. The buffer
+    /// is given back to it instead of being deallocated.
+    pool: Option<Arc<InstancePool>>,
+
     /// The `Instance` itself. It must be the last field of
     /// `InstanceRef` since `Instance` is dyamically-sized.
     ///
@@ -828,10 +1004,15 @@ impl InstanceRef {
     /// and correctly initialized pointer to `Instance`. See
     /// [`InstanceAllocator`] for an example of how to correctly use
     /// this API.
-    pub(self) unsafe fn new(instance: NonNull<Instance>, instance_layout: Layout) -> Self {
+    pub(self) unsafe fn new(
+        instance: NonNull<Instance>,
+        instance_layout: Layout,
+        pool: Option<Arc<InstancePool>>,
+    ) -> Self {
         Self {
             strong: Arc::new(atomic::AtomicUsize::new(1)),
             instance_layout,
+            pool,
             instance,
         }
     }
@@ -852,7 +1033,10 @@ impl InstanceRef {
         let instance_ptr = self.instance.as_ptr();
 
         ptr::drop_in_place(instance_ptr);
-        std::alloc::dealloc(instance_ptr as *mut u8, self.instance_layout);
+        match &self.pool {
+            Some(pool) => pool.deallocate_instance(instance_ptr as *mut u8),
+            None => std::alloc::dealloc(instance_ptr as *mut u8, self.instance_layout),
+        }
     }
 
     /// Get the number of strong references pointing to this
@@ -920,6 +1104,7 @@ impl Clone for InstanceRef {
         Self {
             strong: self.strong.clone(),
             instance_layout: self.instance_layout,
+            pool: self.pool.clone(),
             instance: self.instance.clone(),
         }
     }
@@ -1017,6 +1202,7 @@ impl InstanceHandle {
         finished_globals: BoxedSlice<LocalGlobalIndex, Arc<Global>>,
         imports: Imports,
         vmshared_signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
+        interrupts: Arc<VMInterrupts>,
         host_state: Box<dyn Any>,
         imported_function_envs: BoxedSlice<FunctionIndex, ImportFunctionEnv>,
     ) -> Result<Self, Trap> {
@@ -1033,6 +1219,7 @@ impl InstanceHandle {
             let instance = Instance {
                 module,
                 offsets,
+                interrupts,
                 memories: finished_memories,
                 tables: finished_tables,
                 globals: finished_globals,
@@ -1054,6 +1241,7 @@ impl InstanceHandle {
         };
         let instance = handle.instance().as_ref();
 
+        ptr::write(instance.interrupts_ptr(), Arc::as_ptr(&instance.interrupts));
         ptr::copy(
             vmshared_signatures.values().as_slice().as_ptr(),
             instance.signature_ids_ptr() as *mut VMSharedSignatureIndex,
@@ -1110,11 +1298,15 @@ impl InstanceHandle {
 
     /// Finishes the instantiation process started by `Instance::new`.
     ///
+    /// The local memories are initialized from `memory_images` before
+    /// `data_initializers` are applied.
+    ///
     /// # Safety
     ///
     /// Only safe to call immediately after instantiation.
     pub unsafe fn finish_instantiation(
         &self,
+        memory_images: &[MemoryImage],
         data_initializers: &[DataInitializer<'_>],
     ) -> Result<(), Trap> {
         let instance = self.instance().as_ref();
@@ -1123,6 +1315,7 @@ impl InstanceHandle {
 
         // Apply the initializers.
         initialize_tables(instance)?;
+        initialize_memory_images(instance, memory_images)?;
         initialize_memories(instance, data_initializers)?;
 
         // The WebAssembly spec specifies that the start function is
@@ -1317,6 +1510,25 @@ impl InstanceHandle {
         self.instance().as_ref().get_local_table(index)
     }
 
+    /// Take a snapshot of the defined memories, mutable globals and
+    /// tables of this instance.
+    ///
+    /// The instance mustn't be running, in this thread or in another
+    /// one, while the snapshot is taken.
+    pub fn snapshot(&self) -> Result<InstanceSnapshot, SnapshotError> {
+        self.instance().as_ref().snapshot()
+    }
+
+    /// Restore a snapshot taken from an instance of the same module.
+    ///
+    /// The memories and tables of this instance are grown to the size
+    /// they have in the snapshot, and mustn't be larger. The instance
+    /// mustn't be running, in this thread or in another one, while the
+    /// snapshot is restored.
+    pub fn restore(&self, snapshot: &InstanceSnapshot) -> Result<(), SnapshotError> {
+        self.instance().as_ref().restore(snapshot)
+    }
+
     /// Initializes the host environments.
     ///
     /// # Safety
@@ -1382,6 +1594,16 @@ cfg_if::cfg_if! {
     }
 }
 
+/// The timeout of `memory.atomic.wait`, where a negative number of
+/// nanoseconds waits forever.
+fn wait_timeout(timeout: i64) -> Option<Duration> {
+    if timeout < 0 {
+        None
+    } else {
+        Some(Duration::from_nanos(timeout as u64))
+    }
+}
+
 fn check_table_init_bounds(instance: &Instance) -> Result<(), Trap> {
     let module = Arc::clone(&instance.module);
     for init in &module.table_initializers {
@@ -1403,13 +1625,22 @@ fn get_memory_init_start(init: &DataInitializer<'_>, instance: &Instance) -> usi
 
     if let Some(base) = init.location.base {
         let val = unsafe {
-            if let Some(def_index) = instance.module.local_global_index(base) {
-                instance.global(def_index).to_u32()
+            let global = if let Some(def_index) = instance.module.local_global_index(base) {
+                instance.global(def_index)
             } else {
-                instance.imported_global(base).definition.as_ref().to_u32()
+                instance.imported_global(base).definition.as_ref().clone()
+            };
+            // The offsets of 64-bit memories are `i64` globals.
+            if instance.module.memories[init.location.memory_index].is_64() {
+                global.to_u64()
+            } else {
+                u64::from(global.to_u32())
             }
         };
-        start += usize::try_from(val).unwrap();
+        start = usize::try_from(val)
+            .ok()
+            .and_then(|val| start.checked_add(val))
+            .unwrap_or(usize::MAX);
     }
 
     start
@@ -1430,7 +1661,7 @@ unsafe fn get_memory_slice<'instance>(
         let import = instance.imported_memory(init.location.memory_index);
         *import.definition.as_ref()
     };
-    slice::from_raw_parts_mut(memory.base, memory.current_length.try_into().unwrap())
+    slice::from_raw_parts_mut(memory.base, memory.current_length)
 }
 
 fn check_memory_init_bounds(
@@ -1441,7 +1672,8 @@ fn check_memory_init_bounds(
         let start = get_memory_init_start(init, instance);
         unsafe {
             let mem_slice = get_memory_slice(init, instance);
-            if mem_slice.get_mut(start..start + init.data.len()).is_none() {
+            let end = start.checked_add(init.data.len());
+            if end.and_then(|end| mem_slice.get_mut(start..end)).is_none() {
                 return Err(Trap::new_from_runtime(TrapCode::HeapSetterOutOfBounds));
             }
         }
@@ -1521,6 +1753,25 @@ fn initialize_passive_elements(instance: &Instance) {
     );
 }
 
+/// Initialize the local memories from their images.
+fn initialize_memory_images(
+    instance: &Instance,
+    memory_images: &[MemoryImage],
+) -> Result<(), Trap> {
+    for image in memory_images {
+        let memory = instance
+            .module
+            .local_memory_index(image.memory_index())
+            .and_then(|index| instance.memories.get(index))
+            .ok_or_else(|| Trap::new_from_runtime(TrapCode::HeapSetterOutOfBounds))?;
+        memory
+            .initialize_with_image(image)
+            .map_err(|_| Trap::new_from_runtime(TrapCode::HeapSetterOutOfBounds))?;
+    }
+
+    Ok(())
+}
+
 /// Initialize the table memory from the provided initializers.
 fn initialize_memories(
     instance: &Instance,
@@ -1532,7 +1783,7 @@ fn initialize_memories(
         let start = get_memory_init_start(init, instance);
         if start
             .checked_add(init.data.len())
-            .map_or(true, |end| end > memory.current_length.try_into().unwrap())
+            .map_or(true, |end| end > memory.current_length)
         {
             return Err(Trap::new_from_runtime(TrapCode::HeapAccessOutOfBounds));
         }
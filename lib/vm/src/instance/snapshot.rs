@@ -0,0 +1,477 @@
+//! Snapshots of the state of an [`Instance`]: the contents of its
+//! defined memories, mutable globals and tables.
+//!
+//! A snapshot can be restored onto any fresh instance of the same
+//! module, in this process or in another one, which makes it possible
+//! to skip the initialization of a module (as `wizer` does), or to
+//! migrate a guest between hosts.
+
+use super::Instance;
+use crate::memory::MemoryError;
+use crate::vmcontext::VMCallerCheckedAnyfunc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::slice;
+use thiserror::Error;
+use wasmer_types::entity::EntityRef;
+use wasmer_types::{
+    DataIndex, ElemIndex, FunctionIndex, GlobalIndex, MemoryIndex, Mutability, Pages, TableIndex,
+    Type,
+};
+
+/// Runs of zeros shorter than this are kept inside a [`MemorySegment`]
+/// rather than splitting it in two.
+const MAX_ZERO_GAP: usize = 64;
+
+/// The state of an instance, as returned by
+/// [`InstanceHandle::snapshot`](crate::InstanceHandle::snapshot).
+///
+/// Imported memories, tables and globals belong to another instance
+/// or to the host, and aren't part of the snapshot.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InstanceSnapshot {
+    /// The defined memories.
+    pub memories: Vec<MemorySnapshot>,
+    /// The defined, mutable globals.
+    pub globals: Vec<GlobalSnapshot>,
+    /// The defined tables.
+    pub tables: Vec<TableSnapshot>,
+    /// The passive data segments dropped with `data.drop`.
+    pub dropped_data: Vec<DataIndex>,
+    /// The passive element segments dropped with `elem.drop`.
+    pub dropped_elements: Vec<ElemIndex>,
+}
+
+/// The contents of a memory.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MemorySnapshot {
+    /// The index of the memory in the module.
+    pub index: MemoryIndex,
+    /// The size of the memory.
+    pub pages: Pages,
+    /// The non-zero parts of the memory, in increasing order of offset.
+    /// Everything else is zero.
+    pub segments: Vec<MemorySegment>,
+}
+
+/// A part of the contents of a memory.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MemorySegment {
+    /// The offset of the data in the memory.
+    pub offset: u64,
+    /// The data.
+    #[serde(with = "serde_bytes")]
+    pub data: Vec<u8>,
+}
+
+/// The value of a global.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GlobalSnapshot {
+    /// The index of the global in the module.
+    pub index: GlobalIndex,
+    /// The type of the global.
+    pub ty: Type,
+    /// The bits of the value, as returned by
+    /// [`VMGlobalDefinition::to_u128`](crate::VMGlobalDefinition::to_u128).
+    pub value: u128,
+}
+
+/// The elements of a table.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TableSnapshot {
+    /// The index of the table in the module.
+    pub index: TableIndex,
+    /// The elements, as the indices of the functions of the module
+    /// they refer to, or `None` for null elements.
+    pub elements: Vec<Option<FunctionIndex>>,
+}
+
+/// An error while taking or restoring a snapshot.
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    /// A table holds a function which is neither defined nor imported
+    /// by the module, and can't be recorded as a function index.
+    #[error("element {element} of table {} is not a function of the module", table.index())]
+    ForeignFunction {
+        /// The table.
+        table: TableIndex,
+        /// The index of the element in the table.
+        element: u32,
+    },
+    /// A global holds a reference, which can't be recorded.
+    #[error("global {} holds a reference of type {ty}", global.index())]
+    UnsupportedGlobal {
+        /// The global.
+        global: GlobalIndex,
+        /// The type of the global.
+        ty: Type,
+    },
+    /// The snapshot doesn't match the module of the instance, or the
+    /// instance is larger than the snapshot.
+    #[error("the snapshot doesn't match the instance: {0}")]
+    Incompatible(String),
+    /// A memory couldn't grow to the size of the snapshot.
+    #[error(transparent)]
+    Memory(#[from] MemoryError),
+    /// The snapshot couldn't be serialized or deserialized.
+    #[error("the snapshot couldn't be serialized or deserialized: {0}")]
+    Serialization(String),
+}
+
+impl InstanceSnapshot {
+    /// Serialize the snapshot to bytes.
+    pub fn serialize(&self) -> Result<Vec<u8>, SnapshotError> {
+        bincode::serialize(self).map_err(|error| SnapshotError::Serialization(error.to_string()))
+    }
+
+    /// Deserialize a snapshot from bytes returned by
+    /// [`InstanceSnapshot::serialize`].
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        bincode::deserialize(bytes).map_err(|error| SnapshotError::Serialization(error.to_string()))
+    }
+}
+
+/// Split `data` in the runs of non-zero bytes, separated by more than
+/// `MAX_ZERO_GAP` zeros.
+fn non_zero_segments(data: &[u8]) -> Vec<MemorySegment> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    while let Some(begin) = data[start..].iter().position(|byte| *byte != 0) {
+        let begin = start + begin;
+        let mut end = begin;
+        loop {
+            // `end` is past a non-zero byte: look for the next one.
+            end += data[end..]
+                .iter()
+                .position(|byte| *byte == 0)
+                .unwrap_or(data.len() - end);
+            match data[end..].iter().position(|byte| *byte != 0) {
+                Some(gap) if gap < MAX_ZERO_GAP => end += gap,
+                _ => break,
+            }
+        }
+        segments.push(MemorySegment {
+            offset: begin as u64,
+            data: data[begin..end].to_vec(),
+        });
+        start = end;
+    }
+    segments
+}
+
+fn incompatible(message: String) -> SnapshotError {
+    SnapshotError::Incompatible(message)
+}
+
+impl Instance {
+    /// The function index of every function this instance can refer to.
+    fn function_indices(&self) -> HashMap<(usize, usize), FunctionIndex> {
+        self.module
+            .functions
+            .keys()
+            .map(|index| {
+                let anyfunc = self.get_caller_checked_anyfunc(index);
+                (anyfunc_key(&anyfunc), index)
+            })
+            .collect()
+    }
+
+    /// Take a snapshot of the state of this instance.
+    pub(crate) fn snapshot(&self) -> Result<InstanceSnapshot, SnapshotError> {
+        let module = self.module_ref();
+
+        let memories = self
+            .memories
+            .iter()
+            .map(|(local_index, memory)| {
+                let definition = unsafe { memory.vmmemory().as_ref() };
+                let data =
+                    unsafe { slice::from_raw_parts(definition.base, definition.current_length) };
+                MemorySnapshot {
+                    index: module.memory_index(local_index),
+                    pages: memory.size(),
+                    segments: non_zero_segments(data),
+                }
+            })
+            .collect();
+
+        let mut globals = Vec::new();
+        for (local_index, global) in self.globals.iter() {
+            let ty = global.ty();
+            if ty.mutability != Mutability::Var {
+                continue;
+            }
+            let index = module.global_index(local_index);
+            if !ty.ty.is_num() {
+                return Err(SnapshotError::UnsupportedGlobal {
+                    global: index,
+                    ty: ty.ty,
+                });
+            }
+            globals.push(GlobalSnapshot {
+                index,
+                ty: ty.ty,
+                value: self.global(local_index).to_u128(),
+            });
+        }
+
+        let functions = self.function_indices();
+        let mut tables = Vec::new();
+        for (local_index, table) in self.tables.iter() {
+            let index = module.table_index(local_index);
+            let elements = (0..table.size())
+                .map(|element| {
+                    let anyfunc = table.get(element).unwrap_or_default();
+                    if anyfunc.func_ptr.is_null() {
+                        return Ok(None);
+                    }
+                    functions
+                        .get(&anyfunc_key(&anyfunc))
+                        .map(|function| Some(*function))
+                        .ok_or(SnapshotError::ForeignFunction {
+                            table: index,
+                            element,
+                        })
+                })
+                .collect::<Result<_, _>>()?;
+            tables.push(TableSnapshot { index, elements });
+        }
+
+        let passive_data = self.passive_data.borrow();
+        let mut dropped_data = module
+            .passive_data
+            .keys()
+            .filter(|index| !passive_data.contains_key(index))
+            .cloned()
+            .collect::<Vec<_>>();
+        dropped_data.sort();
+        let passive_elements = self.passive_elements.borrow();
+        let mut dropped_elements = module
+            .passive_elements
+            .keys()
+            .filter(|index| !passive_elements.contains_key(index))
+            .cloned()
+            .collect::<Vec<_>>();
+        dropped_elements.sort();
+
+        Ok(InstanceSnapshot {
+            memories,
+            globals,
+            tables,
+            dropped_data,
+            dropped_elements,
+        })
+    }
+
+    /// Restore a snapshot taken from an instance of the same module.
+    ///
+    /// The memories and tables of this instance mustn't be larger than
+    /// the ones of the snapshot.
+    pub(crate) fn restore(&self, snapshot: &InstanceSnapshot) -> Result<(), SnapshotError> {
+        let module = self.module_ref();
+        self.check_snapshot(snapshot)?;
+
+        for memory_snapshot in &snapshot.memories {
+            let local_index = module.local_memory_index(memory_snapshot.index).unwrap();
+            let memory = &self.memories[local_index];
+            let size = memory.size();
+            if size < memory_snapshot.pages {
+                memory.grow(memory_snapshot.pages - size)?;
+            }
+            let definition = unsafe { memory.vmmemory().as_ref() };
+            let data =
+                unsafe { slice::from_raw_parts_mut(definition.base, definition.current_length) };
+            for byte in data.iter_mut() {
+                *byte = 0;
+            }
+            for segment in &memory_snapshot.segments {
+                let offset = segment.offset as usize;
+                data[offset..offset + segment.data.len()].copy_from_slice(&segment.data);
+            }
+        }
+
+        for global_snapshot in &snapshot.globals {
+            let local_index = module.local_global_index(global_snapshot.index).unwrap();
+            unsafe {
+                *(*self.global_ptr(local_index).as_ptr()).as_u128_mut() = global_snapshot.value;
+            }
+        }
+
+        for table_snapshot in &snapshot.tables {
+            let local_index = module.local_table_index(table_snapshot.index).unwrap();
+            let table = self.get_local_table(local_index);
+            let size = table.size();
+            let len = table_snapshot.elements.len() as u32;
+            if size < len {
+                table.grow(len - size).ok_or_else(|| {
+                    incompatible(format!(
+                        "table {} can't grow to {} elements",
+                        table_snapshot.index.index(),
+                        len
+                    ))
+                })?;
+            }
+            for (element, function) in (0..).zip(&table_snapshot.elements) {
+                let anyfunc = match function {
+                    Some(function) => self.get_caller_checked_anyfunc(*function),
+                    None => VMCallerCheckedAnyfunc::default(),
+                };
+                table
+                    .set(element, anyfunc)
+                    .expect("the table was grown to the size of the snapshot");
+            }
+        }
+
+        let mut passive_data = self.passive_data.borrow_mut();
+        for index in &snapshot.dropped_data {
+            passive_data.remove(index);
+        }
+        let mut passive_elements = self.passive_elements.borrow_mut();
+        for index in &snapshot.dropped_elements {
+            passive_elements.remove(index);
+        }
+
+        Ok(())
+    }
+
+    /// Check that `snapshot` can be restored onto this instance,
+    /// before modifying anything.
+    fn check_snapshot(&self, snapshot: &InstanceSnapshot) -> Result<(), SnapshotError> {
+        let module = self.module_ref();
+
+        if snapshot.memories.len() != self.memories.len() {
+            return Err(incompatible(format!(
+                "the snapshot has {} memories, the instance defines {}",
+                snapshot.memories.len(),
+                self.memories.len()
+            )));
+        }
+        for memory_snapshot in &snapshot.memories {
+            let index = memory_snapshot.index;
+            let memory = module
+                .local_memory_index(index)
+                .and_then(|local_index| self.memories.get(local_index))
+                .ok_or_else(|| incompatible(format!("memory {} isn't defined", index.index())))?;
+            let size = memory.size();
+            if size > memory_snapshot.pages {
+                return Err(incompatible(format!(
+                    "memory {} has {} pages, the snapshot has {}",
+                    index.index(),
+                    size.0,
+                    memory_snapshot.pages.0
+                )));
+            }
+            let len = memory_snapshot.pages.bytes().0 as u64;
+            if memory_snapshot.segments.iter().any(|segment| {
+                segment
+                    .offset
+                    .checked_add(segment.data.len() as u64)
+                    .filter(|end| *end <= len)
+                    .is_none()
+            }) {
+                return Err(incompatible(format!(
+                    "a segment of memory {} is out of bounds",
+                    index.index()
+                )));
+            }
+        }
+
+        for global_snapshot in &snapshot.globals {
+            let index = global_snapshot.index;
+            let ty = module
+                .local_global_index(index)
+                .and_then(|local_index| self.globals.get(local_index))
+                .map(|global| global.ty())
+                .filter(|ty| ty.mutability == Mutability::Var)
+                .ok_or_else(|| {
+                    incompatible(format!("global {} isn't defined or mutable", index.index()))
+                })?;
+            if ty.ty != global_snapshot.ty || !ty.ty.is_num() {
+                return Err(incompatible(format!(
+                    "global {} has type {}, the snapshot has {}",
+                    index.index(),
+                    ty.ty,
+                    global_snapshot.ty
+                )));
+            }
+        }
+
+        if snapshot.tables.len() != self.tables.len() {
+            return Err(incompatible(format!(
+                "the snapshot has {} tables, the instance defines {}",
+                snapshot.tables.len(),
+                self.tables.len()
+            )));
+        }
+        for table_snapshot in &snapshot.tables {
+            let index = table_snapshot.index;
+            let table = module
+                .local_table_index(index)
+                .and_then(|local_index| self.tables.get(local_index))
+                .ok_or_else(|| incompatible(format!("table {} isn't defined", index.index())))?;
+            let size = table.size() as usize;
+            if size > table_snapshot.elements.len() {
+                return Err(incompatible(format!(
+                    "table {} has {} elements, the snapshot has {}",
+                    index.index(),
+                    size,
+                    table_snapshot.elements.len()
+                )));
+            }
+            if let Some(function) = table_snapshot
+                .elements
+                .iter()
+                .flatten()
+                .find(|function| function.index() >= module.functions.len())
+            {
+                return Err(incompatible(format!(
+                    "function {} doesn't exist",
+                    function.index()
+                )));
+            }
+            if u32::try_from(table_snapshot.elements.len()).is_err() {
+                return Err(incompatible(format!(
+                    "table {} is too large",
+                    index.index()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The identity of the function `anyfunc` refers to.
+fn anyfunc_key(anyfunc: &VMCallerCheckedAnyfunc) -> (usize, usize) {
+    (anyfunc.func_ptr as usize, unsafe {
+        anyfunc.vmctx.host_env as usize
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_zero_segments_merge_short_gaps() {
+        assert_eq!(non_zero_segments(&[0; 100]), vec![]);
+
+        let mut data = vec![0; 1000];
+        data[10] = 1;
+        data[11] = 2;
+        data[50] = 3;
+        data[500] = 4;
+        data[999] = 5;
+        let segments = non_zero_segments(&data);
+        assert_eq!(
+            segments
+                .iter()
+                .map(|segment| (segment.offset, segment.data.len()))
+                .collect::<Vec<_>>(),
+            vec![(10, 41), (500, 1), (999, 1)]
+        );
+        assert_eq!(segments[0].data[..2], [1, 2]);
+        assert_eq!(segments[0].data[40], 3);
+    }
+}
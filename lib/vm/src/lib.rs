@@ -21,15 +21,19 @@
 )]
 
 mod export;
+mod futex;
 mod global;
 mod imports;
 mod instance;
 mod memory;
+mod memory_image;
 mod mmap;
 mod module;
+mod pool;
 mod probestack;
 mod sig_registry;
 mod table;
+mod tail_call;
 mod trap;
 mod vmcontext;
 mod vmoffsets;
@@ -40,20 +44,23 @@ pub use crate::export::*;
 pub use crate::global::*;
 pub use crate::imports::Imports;
 pub use crate::instance::{
-    ImportFunctionEnv, ImportInitializerFuncPtr, InstanceAllocator, InstanceHandle,
+    GlobalSnapshot, ImportFunctionEnv, ImportInitializerFuncPtr, InstanceAllocator, InstanceHandle,
+    InstanceSnapshot, MemorySegment, MemorySnapshot, SnapshotError, TableSnapshot,
 };
 pub use crate::memory::{LinearMemory, Memory, MemoryError, MemoryStyle};
+pub use crate::memory_image::{MemoryImage, MEMORY_IMAGE_PAGE_SIZE};
 pub use crate::mmap::Mmap;
 pub use crate::module::{ExportsIterator, ImportsIterator, ModuleInfo};
+pub use crate::pool::{InstancePool, PoolingLimits};
 pub use crate::probestack::PROBESTACK;
 pub use crate::sig_registry::SignatureRegistry;
 pub use crate::table::{LinearTable, Table, TableStyle};
 pub use crate::trap::*;
 pub use crate::vmcontext::{
-    VMBuiltinFunctionIndex, VMCallerCheckedAnyfunc, VMContext, VMDynamicFunctionContext,
-    VMFunctionBody, VMFunctionEnvironment, VMFunctionImport, VMFunctionKind, VMGlobalDefinition,
-    VMGlobalImport, VMMemoryDefinition, VMMemoryImport, VMSharedSignatureIndex, VMTableDefinition,
-    VMTableImport, VMTrampoline,
+    InterruptHandle, StackLimitGuard, VMBuiltinFunctionIndex, VMCallerCheckedAnyfunc, VMContext,
+    VMDynamicFunctionContext, VMFunctionBody, VMFunctionEnvironment, VMFunctionImport,
+    VMFunctionKind, VMGlobalDefinition, VMGlobalImport, VMInterrupts, VMMemoryDefinition,
+    VMMemoryImport, VMSharedSignatureIndex, VMTableDefinition, VMTableImport, VMTrampoline,
 };
 pub use crate::vmoffsets::{TargetSharedSignatureIndex, VMOffsets};
 
@@ -94,3 +101,12 @@ impl std::ops::Deref for SectionBodyPtr {
         &self.0
     }
 }
+
+/// # Safety
+/// The section data isn't written to once the code memory holding it is
+/// published, so it may be read from any thread.
+unsafe impl Send for SectionBodyPtr {}
+/// # Safety
+/// The section data isn't written to once the code memory holding it is
+/// published, so it may be read from any thread.
+unsafe impl Sync for SectionBodyPtr {}
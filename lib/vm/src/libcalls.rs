@@ -36,11 +36,14 @@
 //!   ```
 
 use crate::probestack::PROBESTACK;
+use crate::tail_call;
 use crate::trap::{raise_lib_trap, Trap, TrapCode};
-use crate::vmcontext::VMContext;
+use crate::vmcontext::{VMContext, VMFunctionBody};
 use serde::{Deserialize, Serialize};
 use std::fmt;
-use wasmer_types::{DataIndex, ElemIndex, LocalMemoryIndex, MemoryIndex, TableIndex};
+use wasmer_types::{
+    DataIndex, ElemIndex, LocalMemoryIndex, MemoryIndex, SignatureIndex, TableIndex,
+};
 
 /// Implementation of f32.ceil
 #[no_mangle]
@@ -302,6 +305,30 @@ pub unsafe extern "C" fn wasmer_imported_memory_copy(
     }
 }
 
+/// Implementation of `memory.copy` between two different memories.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null.
+pub unsafe extern "C" fn wasmer_memory_copy_between(
+    vmctx: *mut VMContext,
+    dst_memory_index: u32,
+    src_memory_index: u32,
+    dst: u32,
+    src: u32,
+    len: u32,
+) {
+    let result = {
+        let dst_memory_index = MemoryIndex::from_u32(dst_memory_index);
+        let src_memory_index = MemoryIndex::from_u32(src_memory_index);
+        let instance = (&*vmctx).instance();
+        instance.memory_copy_between(dst_memory_index, src_memory_index, dst, src, len)
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
 /// Implementation of `memory.fill` for locally defined memories.
 ///
 /// # Safety
@@ -381,6 +408,113 @@ pub unsafe extern "C" fn wasmer_data_drop(vmctx: *mut VMContext, data_index: u32
     instance.data_drop(data_index)
 }
 
+/// Implementation of `memory.atomic.wait32`.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null.
+pub unsafe extern "C" fn wasmer_memory_atomic_wait32(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    offset: u32,
+    addr: u64,
+    expected: u32,
+    timeout: i64,
+) -> u32 {
+    let result = {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let instance = (&*vmctx).instance();
+        instance.memory_atomic_wait32(memory_index, addr, offset, expected, timeout)
+    };
+    match result {
+        Ok(result) => result,
+        Err(trap) => raise_lib_trap(trap),
+    }
+}
+
+/// Implementation of `memory.atomic.wait64`.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null.
+pub unsafe extern "C" fn wasmer_memory_atomic_wait64(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    offset: u32,
+    addr: u64,
+    expected: u64,
+    timeout: i64,
+) -> u32 {
+    let result = {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let instance = (&*vmctx).instance();
+        instance.memory_atomic_wait64(memory_index, addr, offset, expected, timeout)
+    };
+    match result {
+        Ok(result) => result,
+        Err(trap) => raise_lib_trap(trap),
+    }
+}
+
+/// Implementation of `memory.atomic.notify`.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null.
+pub unsafe extern "C" fn wasmer_memory_atomic_notify(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    offset: u32,
+    addr: u64,
+    count: u32,
+) -> u32 {
+    let result = {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let instance = (&*vmctx).instance();
+        instance.memory_atomic_notify(memory_index, addr, offset, count)
+    };
+    match result {
+        Ok(result) => result,
+        Err(trap) => raise_lib_trap(trap),
+    }
+}
+
+/// Implementation of `return_call` and `return_call_indirect`.
+///
+/// The call to `callee`, whose signature is `sig_index` in the module of
+/// `vmctx`, is made by [`wasmer_finish_tail_calls`] once the caller has
+/// returned.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null, `callee` and `callee_vmctx` must be
+/// a function of that signature, and `values` must hold its arguments, 16
+/// bytes apart.
+pub unsafe extern "C" fn wasmer_return_call(
+    vmctx: *mut VMContext,
+    sig_index: u32,
+    callee_vmctx: *mut VMContext,
+    callee: *const VMFunctionBody,
+    values: *const u128,
+) {
+    let sig_index = SignatureIndex::from_u32(sig_index);
+    let instance = (&*vmctx).instance();
+    instance.return_call(sig_index, callee_vmctx, callee, values)
+}
+
+/// Make the tail calls left by a function that just returned, if any,
+/// replacing the results of the function in `values` with those of the
+/// last call.
+///
+/// # Safety
+///
+/// Only safe to call when wasm code is on the stack, aka `wasmer_call` or
+/// `wasmer_call_trampoline` must have been previously called, and `values`
+/// must have room for the results of the function, 16 bytes apart.
+pub unsafe extern "C" fn wasmer_finish_tail_calls(values: *mut u128) {
+    tail_call::finish_tail_calls(values)
+}
+
 /// Implementation for raising a trap
 ///
 /// # Safety
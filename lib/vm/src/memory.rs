@@ -5,7 +5,9 @@
 //!
 //! `LinearMemory` is to WebAssembly linear memories what `Table` is to WebAssembly tables.
 
+use crate::memory_image::MemoryImage;
 use crate::mmap::Mmap;
+use crate::pool::InstancePool;
 use crate::vmcontext::VMMemoryDefinition;
 use more_asserts::assert_ge;
 use serde::{Deserialize, Serialize};
@@ -13,8 +15,11 @@ use std::borrow::BorrowMut;
 use std::cell::UnsafeCell;
 use std::convert::TryInto;
 use std::fmt;
+use std::mem;
+use std::ops::Range;
 use std::ptr::NonNull;
-use std::sync::Mutex;
+use std::slice;
+use std::sync::{Arc, Mutex};
 use thiserror::Error;
 use wasmer_types::{Bytes, MemoryType, Pages};
 
@@ -113,6 +118,20 @@ pub trait Memory: fmt::Debug + Send + Sync {
     ///
     /// The pointer returned in [`VMMemoryDefinition`] must be valid for the lifetime of this memory.
     fn vmmemory(&self) -> NonNull<VMMemoryDefinition>;
+
+    /// Initialize the memory with `image`, overwriting the bytes it covers.
+    ///
+    /// The default implementation copies the image into the memory.
+    fn initialize_with_image(&self, image: &MemoryImage) -> Result<(), MemoryError> {
+        unsafe {
+            let definition = self.vmmemory();
+            let definition = definition.as_ref();
+            image.copy_into(slice::from_raw_parts_mut(
+                definition.base,
+                definition.current_length,
+            ))
+        }
+    }
 }
 
 /// A linear memory instance.
@@ -140,6 +159,9 @@ pub struct LinearMemory {
     // Records whether we're using a bounds-checking strategy which requires
     // handlers to catch trapping accesses.
     pub(crate) needs_signal_handlers: bool,
+
+    /// The pool the underlying allocation comes from, if any.
+    pool: Option<Arc<InstancePool>>,
 }
 
 /// A type to help manage who is responsible for the backing memory of them
@@ -155,13 +177,24 @@ enum VMMemoryDefinitionOwnership {
     HostOwned(Box<UnsafeCell<VMMemoryDefinition>>),
 }
 
+impl Drop for LinearMemory {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.take() {
+            let mmap = self
+                .mmap
+                .get_mut()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let alloc = mem::replace(&mut mmap.alloc, Mmap::new());
+            pool.deallocate_memory(alloc, mmap.size.bytes().0, mmap.image.take());
+        }
+    }
+}
+
 /// We must implement this because of `VMMemoryDefinitionOwnership::VMOwned`.
 /// This is correct because synchronization of memory accesses is controlled
-/// by the VM.
-// REVIEW: I don't believe ^; this probably shouldn't be `Send`...
-// mutations from other threads into this data could be a problem, but we probably
-// don't want to use atomics for this in the generated code.
-// TODO:
+/// by the VM: the generated code only uses atomics where the Wasm program
+/// asks for them, and a shared memory, the only kind that can be accessed by
+/// several threads at once, never moves when it grows.
 unsafe impl Send for LinearMemory {}
 
 /// This is correct because all internal mutability is protected by a mutex.
@@ -173,6 +206,8 @@ struct WasmMmap {
     alloc: Mmap,
     // The current logical size in wasm pages of this linear memory.
     size: Pages,
+    // The range of `alloc` mapped from a memory image, if any.
+    image: Option<Range<usize>>,
 }
 
 impl LinearMemory {
@@ -205,18 +240,40 @@ impl LinearMemory {
         style: &MemoryStyle,
         vm_memory_location: Option<NonNull<VMMemoryDefinition>>,
     ) -> Result<Self, MemoryError> {
-        if memory.minimum > Pages::max_value() {
+        let request_bytes = Self::reserved_bytes(memory, style)?;
+        let mapped_pages = memory.minimum;
+        let mapped_bytes = mapped_pages.bytes();
+
+        let alloc = Mmap::accessible_reserved(mapped_bytes.0, request_bytes)
+            .map_err(MemoryError::Region)?;
+        Ok(Self::with_alloc(
+            memory,
+            style,
+            vm_memory_location,
+            alloc,
+            None,
+        ))
+    }
+
+    /// Check that `memory` can be implemented with `style`, and return
+    /// the number of bytes of address space to reserve for it.
+    pub(crate) fn reserved_bytes(
+        memory: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<usize, MemoryError> {
+        let max_pages = memory.max_pages();
+        if memory.minimum > max_pages {
             return Err(MemoryError::MinimumMemoryTooLarge {
                 min_requested: memory.minimum,
-                max_allowed: Pages::max_value(),
+                max_allowed: max_pages,
             });
         }
-        // `maximum` cannot be set to more than `65536` pages.
+        // `maximum` cannot be set to more than `65536` pages for 32-bit memories.
         if let Some(max) = memory.maximum {
-            if max > Pages::max_value() {
+            if max > max_pages {
                 return Err(MemoryError::MaximumMemoryTooLarge {
                     max_requested: max,
-                    max_allowed: Pages::max_value(),
+                    max_allowed: max_pages,
                 });
             }
             if max < memory.minimum {
@@ -229,6 +286,47 @@ impl LinearMemory {
             }
         }
 
+        // Other threads may be accessing a shared memory while it grows, so
+        // it can't move: reserve its maximum up front.
+        let shared_maximum = match (memory.shared, memory.maximum) {
+            (false, _) => None,
+            (true, Some(max)) => Some(max),
+            (true, None) => {
+                return Err(MemoryError::InvalidMemory {
+                    reason: "shared memories must have a maximum".to_string(),
+                })
+            }
+        };
+
+        let offset_guard_bytes = style.offset_guard_size() as usize;
+
+        let minimum_pages = match style {
+            MemoryStyle::Dynamic { .. } => shared_maximum.unwrap_or(memory.minimum),
+            MemoryStyle::Static { bound, .. } => {
+                assert_ge!(*bound, memory.minimum);
+                *bound
+            }
+        };
+        let minimum_bytes = minimum_pages.bytes().0;
+        Ok(minimum_bytes.checked_add(offset_guard_bytes).unwrap())
+    }
+
+    /// Build a `LinearMemory` on top of `alloc`, whose first `memory.minimum`
+    /// pages must be accessible.
+    ///
+    /// If `pool` is set, `alloc` is a memory slot of this pool: the memory
+    /// can't move to a bigger allocation, and `alloc` is given back to the
+    /// pool when the memory is dropped.
+    ///
+    /// # Safety
+    /// - `vm_memory_location` must point to a valid location in VM memory.
+    pub(crate) unsafe fn with_alloc(
+        memory: &MemoryType,
+        style: &MemoryStyle,
+        vm_memory_location: Option<NonNull<VMMemoryDefinition>>,
+        alloc: Mmap,
+        pool: Option<Arc<InstancePool>>,
+    ) -> Self {
         let offset_guard_bytes = style.offset_guard_size() as usize;
 
         // If we have an offset guard, or if we're doing the static memory
@@ -240,27 +338,15 @@ impl LinearMemory {
                 MemoryStyle::Static { .. } => true,
             };
 
-        let minimum_pages = match style {
-            MemoryStyle::Dynamic { .. } => memory.minimum,
-            MemoryStyle::Static { bound, .. } => {
-                assert_ge!(*bound, memory.minimum);
-                *bound
-            }
-        };
-        let minimum_bytes = minimum_pages.bytes().0;
-        let request_bytes = minimum_bytes.checked_add(offset_guard_bytes).unwrap();
-        let mapped_pages = memory.minimum;
-        let mapped_bytes = mapped_pages.bytes();
-
         let mut mmap = WasmMmap {
-            alloc: Mmap::accessible_reserved(mapped_bytes.0, request_bytes)
-                .map_err(MemoryError::Region)?,
+            alloc,
             size: memory.minimum,
+            image: None,
         };
 
         let base_ptr = mmap.alloc.as_mut_ptr();
-        let mem_length = memory.minimum.bytes().0.try_into().unwrap();
-        Ok(Self {
+        let mem_length = memory.minimum.bytes().0;
+        Self {
             mmap: Mutex::new(mmap),
             maximum: memory.maximum,
             offset_guard_size: offset_guard_bytes,
@@ -283,7 +369,8 @@ impl LinearMemory {
             },
             memory: *memory,
             style: style.clone(),
-        })
+            pool,
+        }
     }
 
     /// Get the `VMMemoryDefinition`.
@@ -334,9 +421,13 @@ impl Memory for LinearMemory {
             return Ok(mmap.size);
         }
 
+        let max_pages = self.memory.max_pages();
         let new_pages = mmap
             .size
-            .checked_add(delta)
+            .0
+            .checked_add(delta.0)
+            .map(Pages)
+            .filter(|new_pages| *new_pages <= max_pages)
             .ok_or(MemoryError::CouldNotGrow {
                 current: mmap.size,
                 attempted_delta: delta,
@@ -352,10 +443,20 @@ impl Memory for LinearMemory {
             }
         }
 
+        // A pooled memory can't grow out of its slot.
+        if let Some(pool) = &self.pool {
+            if new_pages > pool.limits().memory_pages {
+                return Err(MemoryError::CouldNotGrow {
+                    current: mmap.size,
+                    attempted_delta: delta,
+                });
+            }
+        }
+
         // Wasm linear memories are never allowed to grow beyond what is
         // indexable. If the memory has no maximum, enforce the greatest
         // limit here.
-        if new_pages >= Pages::max_value() {
+        if new_pages >= max_pages {
             // Linear memory size would exceed the index range.
             return Err(MemoryError::CouldNotGrow {
                 current: mmap.size,
@@ -370,6 +471,7 @@ impl Memory for LinearMemory {
         if new_bytes > mmap.alloc.len() - self.offset_guard_size {
             // If the new size is within the declared maximum, but needs more memory than we
             // have on hand, it's a dynamic heap and it can move.
+            debug_assert!(!self.memory.shared, "shared memories can't move");
             let guard_bytes = self.offset_guard_size;
             let request_bytes =
                 new_bytes
@@ -386,6 +488,7 @@ impl Memory for LinearMemory {
             new_mmap.as_mut_slice()[..copy_len].copy_from_slice(&mmap.alloc.as_slice()[..copy_len]);
 
             mmap.alloc = new_mmap;
+            mmap.image = None;
         } else if delta_bytes > 0 {
             // Make the newly allocated pages accessible.
             mmap.alloc
@@ -399,7 +502,7 @@ impl Memory for LinearMemory {
         unsafe {
             let mut md_ptr = self.get_vm_memory_definition();
             let md = md_ptr.as_mut();
-            md.current_length = new_pages.bytes().0.try_into().unwrap();
+            md.current_length = new_pages.bytes().0;
             md.base = mmap.alloc.as_mut_ptr() as _;
         }
 
@@ -411,4 +514,21 @@ impl Memory for LinearMemory {
         let _mmap_guard = self.mmap.lock().unwrap();
         unsafe { self.get_vm_memory_definition() }
     }
+
+    /// Map `image` copy-on-write over the memory where the platform
+    /// supports it, and copy it otherwise.
+    fn initialize_with_image(&self, image: &MemoryImage) -> Result<(), MemoryError> {
+        let mut mmap_guard = self.mmap.lock().unwrap();
+        let mmap = mmap_guard.borrow_mut();
+        let accessible_bytes = mmap.size.bytes().0;
+        // Only one image is tracked, so that it can be unmapped when a
+        // pooled memory is given back.
+        if mmap.image.is_none() {
+            if let Ok(range) = image.map_into(&mut mmap.alloc, accessible_bytes) {
+                mmap.image = Some(range);
+                return Ok(());
+            }
+        }
+        image.copy_into(&mut mmap.alloc.as_mut_slice()[..accessible_bytes])
+    }
 }
@@ -0,0 +1,350 @@
+//! Page-aligned images of the initial contents of linear memories.
+//!
+//! An image holds the contents the active data segments give to a local
+//! memory, so that an artifact can store it and initialize the memory in
+//! one go. On Linux, the image is written once to a memfd, which
+//! `LinearMemory` maps copy-on-write: instantiating doesn't copy anything,
+//! and the instances share the physical pages they don't write to.
+
+use crate::memory::MemoryError;
+use crate::mmap::Mmap;
+use crate::module::ModuleInfo;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::ops::Range;
+use std::sync::Mutex;
+use wasmer_types::{DataInitializer, MemoryIndex, MemoryType, OwnedDataInitializer};
+
+/// The alignment of the offset and of the size of memory images.
+pub const MEMORY_IMAGE_PAGE_SIZE: usize = 0x1000;
+
+/// Images larger than this many times the data they hold, which are
+/// mostly made of zeroes, aren't worth storing.
+const MAX_SPARSENESS: usize = 2;
+
+/// The initial contents of a local memory.
+#[derive(Serialize, Deserialize)]
+pub struct MemoryImage {
+    memory_index: MemoryIndex,
+    offset: usize,
+    #[serde(with = "serde_bytes")]
+    data: Vec<u8>,
+    /// The memfd holding `data`, created the first time the image is
+    /// mapped.
+    #[serde(skip)]
+    file: Mutex<Option<File>>,
+}
+
+impl MemoryImage {
+    /// Build the images of the local memories of `module` from its active
+    /// data segments, and return them with the data initializers they
+    /// don't replace.
+    ///
+    /// A memory only gets an image if all its segments have a constant
+    /// offset and fit in its minimum size, so that they can't trap.
+    pub fn build(
+        module: &ModuleInfo,
+        data_initializers: &[DataInitializer<'_>],
+    ) -> (Vec<Self>, Vec<OwnedDataInitializer>) {
+        let images = module
+            .memories
+            .iter()
+            .skip(module.num_imported_memories)
+            .filter_map(|(memory_index, ty)| {
+                let segments = data_initializers
+                    .iter()
+                    .filter(|init| init.location.memory_index == memory_index)
+                    .collect::<Vec<_>>();
+                Self::from_segments(memory_index, ty, &segments)
+            })
+            .collect::<Vec<_>>();
+        let data_initializers = data_initializers
+            .iter()
+            .filter(|init| {
+                images
+                    .iter()
+                    .all(|image| image.memory_index != init.location.memory_index)
+            })
+            .map(OwnedDataInitializer::new)
+            .collect();
+        (images, data_initializers)
+    }
+
+    fn from_segments(
+        memory_index: MemoryIndex,
+        ty: &MemoryType,
+        segments: &[&DataInitializer<'_>],
+    ) -> Option<Self> {
+        let minimum = ty.minimum.bytes().0;
+        let mut start = usize::MAX;
+        let mut end = 0;
+        let mut data_len = 0usize;
+        for segment in segments {
+            if segment.location.base.is_some() {
+                return None;
+            }
+            let segment_end = segment
+                .location
+                .offset
+                .checked_add(segment.data.len())
+                .filter(|end| *end <= minimum)?;
+            if !segment.data.is_empty() {
+                start = start.min(segment.location.offset);
+                end = end.max(segment_end);
+                data_len += segment.data.len();
+            }
+        }
+        if data_len == 0 {
+            return None;
+        }
+
+        // The minimum size is a whole number of Wasm pages, which are
+        // larger than image pages.
+        let start = start & !(MEMORY_IMAGE_PAGE_SIZE - 1);
+        let end = round_up_to_page_size(end, MEMORY_IMAGE_PAGE_SIZE);
+        if end - start > MAX_SPARSENESS * data_len + 2 * MEMORY_IMAGE_PAGE_SIZE {
+            return None;
+        }
+        let mut data = vec![0; end - start];
+        for segment in segments.iter().filter(|segment| !segment.data.is_empty()) {
+            let offset = segment.location.offset - start;
+            data[offset..offset + segment.data.len()].copy_from_slice(segment.data);
+        }
+        Some(Self {
+            memory_index,
+            offset: start,
+            data,
+            file: Mutex::new(None),
+        })
+    }
+
+    /// Returns the index of the memory this image initializes.
+    pub fn memory_index(&self) -> MemoryIndex {
+        self.memory_index
+    }
+
+    /// Returns the offset of the image in the memory.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the contents of the image.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn end(&self) -> usize {
+        self.offset + self.data.len()
+    }
+
+    /// Copy the image into `memory`, the accessible bytes of a memory.
+    pub(crate) fn copy_into(&self, memory: &mut [u8]) -> Result<(), MemoryError> {
+        let memory_len = memory.len();
+        memory
+            .get_mut(self.offset..self.end())
+            .ok_or_else(|| MemoryError::InvalidMemory {
+                reason: format!(
+                    "the memory image ends at byte {}, past the end of the memory ({} bytes)",
+                    self.end(),
+                    memory_len
+                ),
+            })?
+            .copy_from_slice(&self.data);
+        Ok(())
+    }
+
+    /// Map the image copy-on-write into `alloc`, whose first
+    /// `accessible_bytes` bytes are accessible, and return the range of
+    /// `alloc` it replaced.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn map_into(
+        &self,
+        alloc: &mut Mmap,
+        accessible_bytes: usize,
+    ) -> Result<Range<usize>, String> {
+        use std::os::unix::io::AsRawFd;
+
+        // The host pages may be larger than image pages.
+        let page_size = region::page::size();
+        let start = self.offset & !(page_size - 1);
+        let end = round_up_to_page_size(self.end(), page_size);
+        if end > accessible_bytes {
+            return Err("the memory image doesn't fit in the memory".to_string());
+        }
+
+        let mut file = self.file.lock().unwrap();
+        if file.is_none() {
+            *file = Some(self.create_file(self.offset - start, end - start)?);
+        }
+        alloc.map_file(start, end - start, file.as_ref().unwrap().as_raw_fd())?;
+        Ok(start..end)
+    }
+
+    /// Create a memfd of `len` bytes holding the image at `offset`.
+    #[cfg(target_os = "linux")]
+    fn create_file(&self, offset: usize, len: usize) -> Result<File, String> {
+        use std::io;
+        use std::os::unix::fs::FileExt;
+        use std::os::unix::io::FromRawFd;
+
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_memfd_create,
+                b"wasmer-memory-image\0".as_ptr(),
+                libc::MFD_CLOEXEC,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        let file = unsafe { File::from_raw_fd(fd as libc::c_int) };
+        file.set_len(len as u64).map_err(|e| e.to_string())?;
+        file.write_all_at(&self.data, offset as u64)
+            .map_err(|e| e.to_string())?;
+        Ok(file)
+    }
+
+    /// Mapping images is only implemented with memfds.
+    #[cfg(not(target_os = "linux"))]
+    pub(crate) fn map_into(
+        &self,
+        _alloc: &mut Mmap,
+        _accessible_bytes: usize,
+    ) -> Result<Range<usize>, String> {
+        Err("memory images can't be mapped on this platform".to_string())
+    }
+}
+
+impl fmt::Debug for MemoryImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryImage")
+            .field("memory_index", &self.memory_index)
+            .field("offset", &self.offset)
+            .field("len", &self.data.len())
+            .finish()
+    }
+}
+
+/// Round `size` up to the nearest multiple of `page_size`.
+fn round_up_to_page_size(size: usize, page_size: usize) -> usize {
+    (size + (page_size - 1)) & !(page_size - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{LinearMemory, Memory, MemoryStyle};
+    use crate::pool::{InstancePool, PoolingLimits};
+    use crate::vmcontext::VMMemoryDefinition;
+    use std::ptr::{self, NonNull};
+    use wasmer_types::entity::EntityRef;
+    use wasmer_types::{DataInitializerLocation, GlobalIndex, Pages};
+
+    fn segment(offset: usize, data: &[u8]) -> DataInitializer<'_> {
+        DataInitializer {
+            location: DataInitializerLocation {
+                memory_index: MemoryIndex::new(0),
+                base: None,
+                offset,
+            },
+            data,
+        }
+    }
+
+    fn module() -> ModuleInfo {
+        let mut module = ModuleInfo::new();
+        module.memories.push(MemoryType::new(Pages(1), None, false));
+        module
+    }
+
+    #[test]
+    fn images_merge_segments() {
+        let filler = vec![1; 0x800];
+        let segments = [
+            segment(0x10, b"abc"),
+            segment(0x1ffe, b"defg"),
+            segment(0x800, &filler),
+            segment(0x11, b"B"),
+        ];
+        let (images, remaining) = MemoryImage::build(&module(), &segments);
+        assert!(remaining.is_empty());
+        assert_eq!(images.len(), 1);
+        let image = &images[0];
+        assert_eq!(image.offset(), 0);
+        assert_eq!(image.data().len(), 0x3000);
+        assert_eq!(&image.data()[0x10..0x13], b"aBc");
+        assert_eq!(&image.data()[0x1ffe..0x2002], b"defg");
+        assert_eq!(image.data()[0x2002..], [0; 0xffe][..]);
+    }
+
+    #[test]
+    fn images_require_constant_in_bounds_segments() {
+        let mut global = segment(0, b"abc");
+        global.location.base = Some(GlobalIndex::new(0));
+        let out_of_bounds = segment(0xffff, b"ab");
+        let sparse = [segment(0, b"a"), segment(0x8000, b"b")];
+        for segments in &[&[segment(0, b"abc"), global][..], &[out_of_bounds], &sparse] {
+            let (images, remaining) = MemoryImage::build(&module(), segments);
+            assert!(images.is_empty());
+            assert_eq!(remaining.len(), segments.len());
+        }
+    }
+
+    #[test]
+    fn mapped_images_are_copy_on_write() {
+        let data = vec![7; 0x4000];
+        let segments = [segment(0x8000, &data)];
+        let (images, _) = MemoryImage::build(&module(), &segments);
+        let ty = MemoryType::new(Pages(1), Some(Pages(2)), false);
+        let style = MemoryStyle::Dynamic {
+            offset_guard_size: 0,
+        };
+        let first = LinearMemory::new(&ty, &style).unwrap();
+        let second = LinearMemory::new(&ty, &style).unwrap();
+        first.initialize_with_image(&images[0]).unwrap();
+        second.initialize_with_image(&images[0]).unwrap();
+        let contents = |memory: &LinearMemory| unsafe {
+            let definition = memory.vmmemory();
+            let definition = definition.as_ref();
+            std::slice::from_raw_parts_mut(definition.base, definition.current_length)
+        };
+
+        contents(&first)[0x8000] = 1;
+        assert_eq!(contents(&first)[0x7fff..0x8002], [0, 1, 7]);
+        assert_eq!(contents(&second)[0x7fff..0x8002], [0, 7, 7]);
+
+        // The contents survive moving to a bigger allocation.
+        first.grow(Pages(1)).unwrap();
+        assert_eq!(contents(&first)[0x8000..0x8002], [1, 7]);
+        assert_eq!(contents(&first)[0xc000], 0);
+    }
+
+    #[test]
+    fn pooled_memories_drop_their_image() {
+        let data = vec![7; 0x1000];
+        let segments = [segment(0, &data)];
+        let (images, _) = MemoryImage::build(&module(), &segments);
+        let pool = InstancePool::new(PoolingLimits {
+            instances: 1,
+            memory_pages: Pages(1),
+            ..PoolingLimits::default()
+        })
+        .unwrap();
+        let ty = MemoryType::new(1, None, false);
+        let style = pool.memory_style(&ty);
+        let mut definition = VMMemoryDefinition {
+            base: ptr::null_mut(),
+            current_length: 0,
+        };
+        let location = NonNull::from(&mut definition);
+
+        let memory = unsafe { pool.allocate_memory(&ty, &style, location) }.unwrap();
+        memory.initialize_with_image(&images[0]).unwrap();
+        assert_eq!(unsafe { *definition.base }, 7);
+        drop(memory);
+
+        let _memory = unsafe { pool.allocate_memory(&ty, &style, location) }.unwrap();
+        assert_eq!(unsafe { *definition.base }, 0);
+    }
+}
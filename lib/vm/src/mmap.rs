@@ -5,7 +5,6 @@
 //! of memory.
 
 use more_asserts::assert_le;
-use more_asserts::assert_lt;
 use std::io;
 use std::ptr;
 use std::slice;
@@ -186,8 +185,8 @@ impl Mmap {
         let page_size = region::page::size();
         assert_eq!(start & (page_size - 1), 0);
         assert_eq!(len & (page_size - 1), 0);
-        assert_lt!(len, self.len);
-        assert_lt!(start, self.len - len);
+        assert_le!(len, self.len);
+        assert_le!(start, self.len - len);
 
         // Commit the accessible size.
         let ptr = self.ptr as *const u8;
@@ -206,8 +205,8 @@ impl Mmap {
         let page_size = region::page::size();
         assert_eq!(start & (page_size - 1), 0);
         assert_eq!(len & (page_size - 1), 0);
-        assert_lt!(len, self.len);
-        assert_lt!(start, self.len - len);
+        assert_le!(len, self.len);
+        assert_le!(start, self.len - len);
 
         // Commit the accessible size.
         let ptr = self.ptr as *const u8;
@@ -227,6 +226,141 @@ impl Mmap {
         Ok(())
     }
 
+    /// Map the first `len` bytes of the file `fd` copy-on-write over the memory
+    /// starting at `start`, and make them accessible. `start` and `len` must be
+    /// native page-size multiples and describe a range within `self`'s reserved
+    /// memory.
+    #[cfg(not(target_os = "windows"))]
+    pub fn map_file(
+        &mut self,
+        start: usize,
+        len: usize,
+        fd: std::os::unix::io::RawFd,
+    ) -> Result<(), String> {
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.len);
+        assert_le!(start, self.len - len);
+
+        if len == 0 {
+            return Ok(());
+        }
+
+        let ptr = unsafe {
+            libc::mmap(
+                (self.ptr as *mut u8).add(start) as *mut libc::c_void,
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_FIXED,
+                fd,
+                0,
+            )
+        };
+        if ptr as isize == -1_isize {
+            return Err(io::Error::last_os_error().to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Map fresh inaccessible pages over the memory starting at `start` and
+    /// extending for `len` bytes, which also unmaps the files mapped there.
+    /// `start` and `len` must be native page-size multiples and describe a
+    /// range within `self`'s reserved memory.
+    #[cfg(not(target_os = "windows"))]
+    pub fn remap(&mut self, start: usize, len: usize) -> Result<(), String> {
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.len);
+        assert_le!(start, self.len - len);
+
+        if len == 0 {
+            return Ok(());
+        }
+
+        let ptr = unsafe {
+            libc::mmap(
+                (self.ptr as *mut u8).add(start) as *mut libc::c_void,
+                len,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_ANON | libc::MAP_FIXED,
+                -1,
+                0,
+            )
+        };
+        if ptr as isize == -1_isize {
+            return Err(io::Error::last_os_error().to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Discard the contents of the memory starting at `start` and extending for `len`
+    /// bytes, and make it inaccessible again, so that it reads as zeroes once it is
+    /// made accessible. `start` and `len` must be native page-size multiples and
+    /// describe a range within `self`'s reserved memory.
+    #[cfg(target_os = "linux")]
+    pub fn reset(&mut self, start: usize, len: usize) -> Result<(), String> {
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.len);
+        assert_le!(start, self.len - len);
+
+        if len == 0 {
+            return Ok(());
+        }
+
+        // Give the pages back to the kernel: private anonymous pages read as
+        // zeroes after `MADV_DONTNEED`.
+        let ptr = unsafe { (self.ptr as *mut u8).add(start) };
+        if unsafe { libc::madvise(ptr as *mut libc::c_void, len, libc::MADV_DONTNEED) } != 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        unsafe { region::protect(ptr, len, region::Protection::NONE) }.map_err(|e| e.to_string())
+    }
+
+    /// Discard the contents of the memory starting at `start` and extending for `len`
+    /// bytes, and make it inaccessible again, so that it reads as zeroes once it is
+    /// made accessible. `start` and `len` must be native page-size multiples and
+    /// describe a range within `self`'s reserved memory.
+    #[cfg(all(not(target_os = "linux"), not(target_os = "windows")))]
+    pub fn reset(&mut self, start: usize, len: usize) -> Result<(), String> {
+        // `MADV_DONTNEED` doesn't zero the pages on every platform, so map
+        // fresh inaccessible pages over the range instead.
+        self.remap(start, len)
+    }
+
+    /// Discard the contents of the memory starting at `start` and extending for `len`
+    /// bytes, and make it inaccessible again, so that it reads as zeroes once it is
+    /// made accessible. `start` and `len` must be native page-size multiples and
+    /// describe a range within `self`'s reserved memory.
+    #[cfg(target_os = "windows")]
+    pub fn reset(&mut self, start: usize, len: usize) -> Result<(), String> {
+        use winapi::ctypes::c_void;
+        use winapi::um::memoryapi::VirtualFree;
+        use winapi::um::winnt::MEM_DECOMMIT;
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.len);
+        assert_le!(start, self.len - len);
+
+        if len == 0 {
+            return Ok(());
+        }
+
+        // Decommitted pages are zeroed when they are committed again.
+        let ptr = self.ptr as *const u8;
+        if unsafe { VirtualFree(ptr.add(start) as *mut c_void, len, MEM_DECOMMIT) } == 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+
+        Ok(())
+    }
+
     /// Return the allocated memory as a slice of u8.
     pub fn as_slice(&self) -> &[u8] {
         unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
@@ -0,0 +1,465 @@
+//! Pooling allocation of instances, memories and tables.
+//!
+//! An [`InstancePool`] reserves everything up front: a fixed number of
+//! slots for the instances, and for their local memories and tables.
+//! Instantiating a module then takes free slots instead of allocating,
+//! and dropping it gives them back. Memory slots are reset with
+//! `madvise(MADV_DONTNEED)`, so that a reused memory reads as zeroes
+//! without being unmapped.
+//!
+//! The number of instance slots is the number of instances that can be
+//! alive at the same time.
+
+use crate::instance::{Instance, InstanceAllocator};
+use crate::memory::{LinearMemory, MemoryError, MemoryStyle};
+use crate::mmap::Mmap;
+use crate::table::{LinearTable, TableStyle};
+use crate::vmcontext::{VMCallerCheckedAnyfunc, VMMemoryDefinition, VMTableDefinition};
+use crate::ModuleInfo;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Range;
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex};
+use wasmer_types::{MemoryType, Pages, TableType};
+
+/// The limits of an [`InstancePool`], which determine how much it
+/// preallocates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolingLimits {
+    /// The maximum number of instances alive at the same time.
+    pub instances: u32,
+
+    /// The maximum size in bytes of an instance, its `VMContext`
+    /// included.
+    pub instance_size: usize,
+
+    /// The maximum number of local memories of an instance.
+    pub memories_per_instance: u32,
+
+    /// The maximum size in wasm pages of a memory.
+    pub memory_pages: Pages,
+
+    /// The size in bytes of the offset guard after each memory.
+    pub memory_offset_guard_size: u64,
+
+    /// The maximum number of local tables of an instance.
+    pub tables_per_instance: u32,
+
+    /// The maximum number of elements of a table.
+    pub table_elements: u32,
+}
+
+impl Default for PoolingLimits {
+    fn default() -> Self {
+        Self {
+            instances: 1000,
+            instance_size: 0x1_0000,
+            memories_per_instance: 1,
+            memory_pages: Pages(160),
+            memory_offset_guard_size: 0x1_0000,
+            tables_per_instance: 1,
+            table_elements: 10_000,
+        }
+    }
+}
+
+/// Preallocated slots for instances, memories and tables.
+///
+/// See the [module documentation](self) for more details.
+pub struct InstancePool {
+    limits: PoolingLimits,
+
+    /// The buffer holding all the instance slots.
+    instances: Mmap,
+
+    /// The size in bytes of an instance slot.
+    instance_slot_size: usize,
+
+    /// The indices of the free instance slots.
+    free_instances: Mutex<Vec<usize>>,
+
+    /// The free memory slots.
+    free_memories: Mutex<Vec<Mmap>>,
+
+    /// The free table slots, empty but with the capacity of a table.
+    free_tables: Mutex<Vec<Vec<VMCallerCheckedAnyfunc>>>,
+}
+
+/// This is correct because the table slots held by the pool are empty,
+/// and the instance and memory slots are only handed out once.
+unsafe impl Send for InstancePool {}
+/// This is correct because all internal mutability is protected by a mutex.
+unsafe impl Sync for InstancePool {}
+
+impl fmt::Debug for InstancePool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InstancePool")
+            .field("limits", &self.limits)
+            .field("available_instances", &self.available_instances())
+            .finish()
+    }
+}
+
+impl InstancePool {
+    /// Create a new pool, reserving all the slots for `limits`.
+    pub fn new(limits: PoolingLimits) -> Result<Arc<Self>, String> {
+        let page_size = region::page::size();
+        let num_instances = usize::try_from(limits.instances).unwrap();
+
+        let instance_slot_size = round_up_to_page_size(limits.instance_size, page_size);
+        let instances_size = instance_slot_size
+            .checked_mul(num_instances)
+            .ok_or_else(|| "The instance pool is too large".to_string())?;
+        let instances = Mmap::accessible_reserved(instances_size, instances_size)?;
+
+        let memory_slot_size = limits
+            .memory_pages
+            .bytes()
+            .0
+            .checked_add(usize::try_from(limits.memory_offset_guard_size).unwrap())
+            .map(|size| round_up_to_page_size(size, page_size))
+            .ok_or_else(|| "The memory slots are too large".to_string())?;
+        let num_memories = num_instances * usize::try_from(limits.memories_per_instance).unwrap();
+        let free_memories = (0..num_memories)
+            .map(|_| Mmap::accessible_reserved(0, memory_slot_size))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let num_tables = num_instances * usize::try_from(limits.tables_per_instance).unwrap();
+        let table_elements = usize::try_from(limits.table_elements).unwrap();
+        let free_tables = (0..num_tables)
+            .map(|_| Vec::with_capacity(table_elements))
+            .collect();
+
+        Ok(Arc::new(Self {
+            limits,
+            instances,
+            instance_slot_size,
+            free_instances: Mutex::new((0..num_instances).rev().collect()),
+            free_memories: Mutex::new(free_memories),
+            free_tables: Mutex::new(free_tables),
+        }))
+    }
+
+    /// Returns the limits of this pool.
+    pub fn limits(&self) -> &PoolingLimits {
+        &self.limits
+    }
+
+    /// Returns the number of instances that can still be allocated.
+    pub fn available_instances(&self) -> usize {
+        self.free_instances.lock().unwrap().len()
+    }
+
+    /// Returns the style of the memories of type `memory` allocated by
+    /// this pool.
+    ///
+    /// Compiled code may skip the bounds checks of static memories, so
+    /// memories are only static when the slots cover the whole index
+    /// space of 32-bit memories. 64-bit memories are always dynamic.
+    pub fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        if !memory.is_64() && self.limits.memory_pages >= Pages::max_value() {
+            MemoryStyle::Static {
+                bound: self.limits.memory_pages,
+                offset_guard_size: self.limits.memory_offset_guard_size,
+            }
+        } else {
+            MemoryStyle::Dynamic {
+                offset_guard_size: self.limits.memory_offset_guard_size,
+            }
+        }
+    }
+
+    /// Allocates the instance data of `module` in a free instance slot,
+    /// see [`InstanceAllocator::new`].
+    #[allow(clippy::type_complexity)]
+    pub fn allocate_instance(
+        self: &Arc<Self>,
+        module: &ModuleInfo,
+    ) -> Result<
+        (
+            InstanceAllocator,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        String,
+    > {
+        let num_memories = module.memories.len() - module.num_imported_memories;
+        if num_memories > usize::try_from(self.limits.memories_per_instance).unwrap() {
+            return Err(format!(
+                "The module defines {} memories, but the instance pool allows {}",
+                num_memories, self.limits.memories_per_instance
+            ));
+        }
+        let num_tables = module.tables.len() - module.num_imported_tables;
+        if num_tables > usize::try_from(self.limits.tables_per_instance).unwrap() {
+            return Err(format!(
+                "The module defines {} tables, but the instance pool allows {}",
+                num_tables, self.limits.tables_per_instance
+            ));
+        }
+
+        let offsets = InstanceAllocator::offsets_for(module);
+        let instance_layout = InstanceAllocator::instance_layout(&offsets);
+        if instance_layout.size() > self.instance_slot_size
+            || instance_layout.align() > region::page::size()
+        {
+            return Err(format!(
+                "The instance needs {} bytes, but the instance pool allows {}",
+                instance_layout.size(),
+                self.limits.instance_size
+            ));
+        }
+
+        let index = self.free_instances.lock().unwrap().pop().ok_or_else(|| {
+            format!(
+                "The instance pool is exhausted ({} instances)",
+                self.limits.instances
+            )
+        })?;
+        // # Safety
+        // The slot is inside `self.instances`, it is free and it is big
+        // enough and aligned enough for `instance_layout`.
+        unsafe {
+            let instance_ptr =
+                self.instances.as_ptr().add(index * self.instance_slot_size) as *mut Instance;
+            Ok(InstanceAllocator::from_parts(
+                NonNull::new_unchecked(instance_ptr),
+                instance_layout,
+                offsets,
+                Some(self.clone()),
+            ))
+        }
+    }
+
+    /// Creates a memory owned by the VM in a free memory slot.
+    ///
+    /// # Safety
+    /// - `vm_definition_location` must point to a valid, owned `VMMemoryDefinition`,
+    ///   for example in `VMContext`.
+    pub unsafe fn allocate_memory(
+        self: &Arc<Self>,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<LinearMemory, MemoryError> {
+        if ty.minimum > self.limits.memory_pages {
+            return Err(MemoryError::MinimumMemoryTooLarge {
+                min_requested: ty.minimum,
+                max_allowed: self.limits.memory_pages,
+            });
+        }
+        let request_bytes = LinearMemory::reserved_bytes(ty, style)?;
+        if style.offset_guard_size() > self.limits.memory_offset_guard_size
+            || request_bytes > self.memory_slot_size()
+        {
+            return Err(MemoryError::InvalidMemory {
+                reason: format!("the style {:?} doesn't fit in the instance pool", style),
+            });
+        }
+
+        let mut alloc = self.free_memories.lock().unwrap().pop().ok_or_else(|| {
+            MemoryError::Region("the instance pool has no memory slot left".to_string())
+        })?;
+        let accessible_bytes = ty.minimum.bytes().0;
+        if accessible_bytes > 0 {
+            if let Err(e) = alloc.make_accessible(0, accessible_bytes) {
+                self.free_memories.lock().unwrap().push(alloc);
+                return Err(MemoryError::Region(e));
+            }
+        }
+
+        Ok(LinearMemory::with_alloc(
+            ty,
+            style,
+            Some(vm_definition_location),
+            alloc,
+            Some(self.clone()),
+        ))
+    }
+
+    /// Creates a table owned by the VM in a free table slot.
+    ///
+    /// # Safety
+    /// - `vm_definition_location` must point to a valid, owned `VMTableDefinition`,
+    ///   for example in `VMContext`.
+    pub unsafe fn allocate_table(
+        self: &Arc<Self>,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<LinearTable, String> {
+        let table_minimum = LinearTable::check_type(ty)?;
+        if ty.minimum > self.limits.table_elements {
+            return Err(format!(
+                "Table minimum ({}) is larger than the instance pool allows ({})",
+                ty.minimum, self.limits.table_elements
+            ));
+        }
+
+        let mut vec = self
+            .free_tables
+            .lock()
+            .unwrap()
+            .pop()
+            .ok_or_else(|| "The instance pool has no table slot left".to_string())?;
+        vec.resize(table_minimum, VMCallerCheckedAnyfunc::default());
+
+        Ok(LinearTable::with_vec(
+            ty,
+            style,
+            Some(vm_definition_location),
+            vec,
+            Some(self.clone()),
+        ))
+    }
+
+    /// The size in bytes of a memory slot.
+    fn memory_slot_size(&self) -> usize {
+        round_up_to_page_size(
+            self.limits.memory_pages.bytes().0
+                + usize::try_from(self.limits.memory_offset_guard_size).unwrap(),
+            region::page::size(),
+        )
+    }
+
+    /// Gives back the instance slot at `instance_ptr`.
+    pub(crate) fn deallocate_instance(&self, instance_ptr: *mut u8) {
+        let offset = instance_ptr as usize - self.instances.as_ptr() as usize;
+        debug_assert_eq!(offset % self.instance_slot_size, 0);
+        self.free_instances
+            .lock()
+            .unwrap()
+            .push(offset / self.instance_slot_size);
+    }
+
+    /// Gives back a memory slot, whose first `accessible_bytes` bytes
+    /// are accessible, and whose `image` range is mapped from a memory
+    /// image.
+    pub(crate) fn deallocate_memory(
+        &self,
+        mut alloc: Mmap,
+        accessible_bytes: usize,
+        image: Option<Range<usize>>,
+    ) {
+        let reset = match image {
+            // `MADV_DONTNEED` would bring back the contents of the image
+            // rather than zeroes.
+            #[cfg(not(target_os = "windows"))]
+            Some(image) => alloc.remap(image.start, image.len()),
+            _ => Ok(()),
+        };
+        let alloc = match reset.and_then(|()| alloc.reset(0, accessible_bytes)) {
+            Ok(()) => alloc,
+            // Replace the slot if it can't be reused.
+            Err(_) => match Mmap::accessible_reserved(0, alloc.len()) {
+                Ok(alloc) => alloc,
+                Err(_) => return,
+            },
+        };
+        self.free_memories.lock().unwrap().push(alloc);
+    }
+
+    /// Gives back a table slot.
+    pub(crate) fn deallocate_table(&self, mut vec: Vec<VMCallerCheckedAnyfunc>) {
+        vec.clear();
+        self.free_tables.lock().unwrap().push(vec);
+    }
+}
+
+/// Round `size` up to the nearest multiple of `page_size`.
+fn round_up_to_page_size(size: usize, page_size: usize) -> usize {
+    (size + (page_size - 1)) & !(page_size - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::Memory;
+    use crate::table::Table;
+    use std::ptr;
+    use wasmer_types::Type;
+
+    fn limits() -> PoolingLimits {
+        PoolingLimits {
+            instances: 2,
+            memory_pages: Pages(2),
+            table_elements: 4,
+            ..PoolingLimits::default()
+        }
+    }
+
+    #[test]
+    fn instances_are_limited() {
+        let pool = InstancePool::new(limits()).unwrap();
+        let module = ModuleInfo::new();
+
+        let first = pool.allocate_instance(&module).unwrap();
+        let second = pool.allocate_instance(&module).unwrap();
+        assert_eq!(pool.available_instances(), 0);
+        assert!(pool.allocate_instance(&module).is_err());
+
+        drop(first);
+        assert_eq!(pool.available_instances(), 1);
+        drop(second);
+        assert_eq!(pool.available_instances(), 2);
+    }
+
+    #[test]
+    fn memories_are_reset_on_reuse() {
+        let pool = InstancePool::new(limits()).unwrap();
+        let ty = MemoryType::new(1, None, false);
+        let style = pool.memory_style(&ty);
+        assert_eq!(
+            style,
+            MemoryStyle::Dynamic {
+                offset_guard_size: 0x1_0000
+            }
+        );
+        assert_eq!(pool.memory_style(&MemoryType::new64(1, None)), style);
+        let mut definition = VMMemoryDefinition {
+            base: ptr::null_mut(),
+            current_length: 0,
+        };
+        let location = NonNull::from(&mut definition);
+
+        let memory = unsafe { pool.allocate_memory(&ty, &style, location) }.unwrap();
+        assert_eq!(memory.grow(Pages(1)), Ok(Pages(1)));
+        assert!(memory.grow(Pages(1)).is_err());
+        unsafe {
+            *definition.base = 42;
+            *definition.base.add(Pages(1).bytes().0) = 42;
+        }
+        drop(memory);
+
+        let memory = unsafe { pool.allocate_memory(&ty, &style, location) }.unwrap();
+        assert_eq!(memory.size(), Pages(1));
+        assert_eq!(unsafe { *definition.base }, 0);
+        assert_eq!(memory.grow(Pages(1)), Ok(Pages(1)));
+        assert_eq!(unsafe { *definition.base.add(Pages(1).bytes().0) }, 0);
+
+        let too_large = MemoryType::new(3, None, false);
+        assert!(unsafe { pool.allocate_memory(&too_large, &style, location) }.is_err());
+    }
+
+    #[test]
+    fn tables_are_reset_on_reuse() {
+        let pool = InstancePool::new(limits()).unwrap();
+        let ty = TableType::new(Type::FuncRef, 2, None);
+        let style = TableStyle::CallerChecksSignature;
+        let mut definition = VMTableDefinition {
+            base: ptr::null_mut(),
+            current_elements: 0,
+        };
+        let location = NonNull::from(&mut definition);
+
+        let table = unsafe { pool.allocate_table(&ty, &style, location) }.unwrap();
+        assert_eq!(table.grow(2), Some(2));
+        assert_eq!(table.grow(1), None);
+        drop(table);
+
+        let table = unsafe { pool.allocate_table(&ty, &style, location) }.unwrap();
+        assert_eq!(table.size(), 2);
+        assert_eq!(definition.current_elements, 2);
+    }
+}
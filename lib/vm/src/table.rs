@@ -5,6 +5,7 @@
 //!
 //! `Table` is to WebAssembly tables what `LinearMemory` is to WebAssembly linear memories.
 
+use crate::pool::InstancePool;
 use crate::trap::{Trap, TrapCode};
 use crate::vmcontext::{VMCallerCheckedAnyfunc, VMTableDefinition};
 use serde::{Deserialize, Serialize};
@@ -12,8 +13,9 @@ use std::borrow::{Borrow, BorrowMut};
 use std::cell::UnsafeCell;
 use std::convert::TryFrom;
 use std::fmt;
+use std::mem;
 use std::ptr::NonNull;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use wasmer_types::{TableType, Type as ValType};
 
 /// Implementation styles for WebAssembly tables.
@@ -113,6 +115,8 @@ pub struct LinearTable {
     /// Our chosen implementation style.
     style: TableStyle,
     vm_table_definition: VMTableDefinitionOwnership,
+    /// The pool the elements come from, if any.
+    pool: Option<Arc<InstancePool>>,
 }
 
 /// A type to help manage who is responsible for the backing table of the
@@ -128,6 +132,18 @@ enum VMTableDefinitionOwnership {
     HostOwned(Box<UnsafeCell<VMTableDefinition>>),
 }
 
+impl Drop for LinearTable {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.take() {
+            let vec = self
+                .vec
+                .get_mut()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            pool.deallocate_table(mem::take(vec));
+        }
+    }
+}
+
 /// This is correct because there is no thread-specific data tied to this type.
 unsafe impl Send for LinearTable {}
 /// This is correct because all internal mutability is protected by a mutex.
@@ -163,6 +179,14 @@ impl LinearTable {
         style: &TableStyle,
         vm_table_location: Option<NonNull<VMTableDefinition>>,
     ) -> Result<Self, String> {
+        let table_minimum = Self::check_type(table)?;
+        let vec = vec![VMCallerCheckedAnyfunc::default(); table_minimum];
+        Ok(Self::with_vec(table, style, vm_table_location, vec, None))
+    }
+
+    /// Check that `table` is a valid table type, and return its minimum
+    /// number of elements.
+    pub(crate) fn check_type(table: &TableType) -> Result<usize, String> {
         match table.ty {
             ValType::FuncRef => (),
             ty => return Err(format!("tables of types other than anyfunc ({})", ty)),
@@ -175,12 +199,29 @@ impl LinearTable {
                 ));
             }
         }
-        let table_minimum = usize::try_from(table.minimum)
-            .map_err(|_| "Table minimum is bigger than usize".to_string())?;
-        let mut vec = vec![VMCallerCheckedAnyfunc::default(); table_minimum];
+        usize::try_from(table.minimum).map_err(|_| "Table minimum is bigger than usize".to_string())
+    }
+
+    /// Build a `LinearTable` on top of `vec`, which must hold the
+    /// `table.minimum` initial elements.
+    ///
+    /// If `pool` is set, `vec` is a table slot of this pool: the table
+    /// can't grow beyond the capacity of `vec`, and `vec` is given back
+    /// to the pool when the table is dropped.
+    ///
+    /// # Safety
+    /// - `vm_table_location` must point to a valid location in VM memory.
+    pub(crate) unsafe fn with_vec(
+        table: &TableType,
+        style: &TableStyle,
+        vm_table_location: Option<NonNull<VMTableDefinition>>,
+        mut vec: Vec<VMCallerCheckedAnyfunc>,
+        pool: Option<Arc<InstancePool>>,
+    ) -> Self {
         let base = vec.as_mut_ptr();
+        let current_elements = vec.len() as u32;
         match style {
-            TableStyle::CallerChecksSignature => Ok(Self {
+            TableStyle::CallerChecksSignature => Self {
                 vec: Mutex::new(vec),
                 maximum: table.maximum,
                 table: *table,
@@ -190,18 +231,19 @@ impl LinearTable {
                         let mut ptr = table_loc.clone();
                         let td = ptr.as_mut();
                         td.base = base as _;
-                        td.current_elements = table_minimum as _;
+                        td.current_elements = current_elements;
                     }
                     VMTableDefinitionOwnership::VMOwned(table_loc)
                 } else {
                     VMTableDefinitionOwnership::HostOwned(Box::new(UnsafeCell::new(
                         VMTableDefinition {
                             base: base as _,
-                            current_elements: table_minimum as _,
+                            current_elements,
                         },
                     )))
                 },
-            }),
+                pool,
+            },
         }
     }
 
@@ -253,6 +295,12 @@ impl Table for LinearTable {
         if self.maximum.map_or(false, |max| new_len > max) {
             return None;
         }
+        // A pooled table can't grow out of its slot.
+        if let Some(pool) = &self.pool {
+            if new_len > pool.limits().table_elements {
+                return None;
+            }
+        }
         vec.resize(
             usize::try_from(new_len).unwrap(),
             VMCallerCheckedAnyfunc::default(),
@@ -0,0 +1,96 @@
+//! Tail calls, for `return_call` and `return_call_indirect`.
+//!
+//! None of the compilers has a calling convention in which the callee of a
+//! tail call can take over the stack frame of its caller. Instead, a
+//! function making a tail call records the call in a per-thread slot and
+//! returns. Its own caller, which is either compiled code or the host, then
+//! makes the call with [`finish_tail_calls`], and repeats it for as long as
+//! the callees make tail calls themselves. The calls are made through the
+//! call trampolines of their signatures, so the stack doesn't grow however
+//! many tail calls follow each other.
+
+use crate::vmcontext::{VMContext, VMFunctionBody, VMTrampoline};
+use std::cell::RefCell;
+use std::{cmp, mem, ptr, slice};
+
+/// A tail call made by a function that has returned.
+struct PendingTailCall {
+    trampoline: VMTrampoline,
+    vmctx: *mut VMContext,
+    callee: *const VMFunctionBody,
+    num_results: usize,
+}
+
+#[derive(Default)]
+struct TailCallState {
+    pending: Option<PendingTailCall>,
+    /// The arguments of the pending call, with room for its results.
+    values: Vec<u128>,
+}
+
+thread_local! {
+    static TAIL_CALL: RefCell<TailCallState> = RefCell::new(TailCallState::default());
+}
+
+/// Record a call to `callee`, to be made once the function making it has
+/// returned.
+///
+/// # Safety
+/// `trampoline` must be the call trampoline of a function taking
+/// `num_params` arguments and returning `num_results` values, `callee` and
+/// `vmctx` must be valid for it, and `values` must hold the `num_params`
+/// arguments, 16 bytes apart.
+pub(crate) unsafe fn record_tail_call(
+    trampoline: VMTrampoline,
+    vmctx: *mut VMContext,
+    callee: *const VMFunctionBody,
+    values: *const u128,
+    num_params: usize,
+    num_results: usize,
+) {
+    TAIL_CALL.with(|state| {
+        let mut state = state.borrow_mut();
+        state.values.clear();
+        state
+            .values
+            .extend_from_slice(slice::from_raw_parts(values, num_params));
+        state.values.resize(cmp::max(num_params, num_results), 0);
+        state.pending = Some(PendingTailCall {
+            trampoline,
+            vmctx,
+            callee,
+            num_results,
+        });
+    })
+}
+
+/// Make the tail calls left by the function that just returned, if any,
+/// and write the results of the last one to `values`.
+///
+/// # Safety
+/// `values` must have room for the results of the function that returned,
+/// 16 bytes apart, unless it returns none, and this must be called on a
+/// stack with WebAssembly traps handled.
+pub(crate) unsafe fn finish_tail_calls(values: *mut u128) {
+    let mut call_values = Vec::new();
+    let mut num_results = None;
+    loop {
+        // The state isn't borrowed during the call, in which the callee
+        // records its own tail call, if any.
+        let pending = TAIL_CALL.with(|state| {
+            let mut state = state.borrow_mut();
+            let pending = state.pending.take()?;
+            mem::swap(&mut state.values, &mut call_values);
+            Some(pending)
+        });
+        let pending = match pending {
+            Some(pending) => pending,
+            None => break,
+        };
+        (pending.trampoline)(pending.vmctx, pending.callee, call_values.as_mut_ptr());
+        num_results = Some(pending.num_results);
+    }
+    if let Some(num_results) = num_results.filter(|&num_results| num_results > 0) {
+        ptr::copy_nonoverlapping(call_values.as_ptr(), values, num_results);
+    }
+}
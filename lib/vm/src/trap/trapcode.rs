@@ -73,6 +73,10 @@ pub enum TrapCode {
 
     /// A trap indicating that the runtime was unable to allocate sufficient memory.
     VMOutOfMemory = 15,
+
+    /// `memory.atomic.wait32` or `memory.atomic.wait64` was used on a memory
+    /// that isn't shared.
+    AtomicWaitOnUnsharedMemory = 16,
     // /// A user-defined trap code.
     // User(u16),
 }
@@ -97,6 +101,7 @@ impl TrapCode {
             Self::Interrupt => "interrupt",
             Self::UnalignedAtomic => "unaligned atomic access",
             Self::VMOutOfMemory => "out of memory",
+            Self::AtomicWaitOnUnsharedMemory => "expected shared memory",
             // Self::User(_) => unreachable!(),
         }
     }
@@ -121,6 +126,7 @@ impl Display for TrapCode {
             Self::Interrupt => "interrupt",
             Self::UnalignedAtomic => "unalign_atom",
             Self::VMOutOfMemory => "oom",
+            Self::AtomicWaitOnUnsharedMemory => "wait_unshared",
             // User(x) => return write!(f, "user{}", x),
         };
         f.write_str(identifier)
@@ -149,6 +155,7 @@ impl FromStr for TrapCode {
             "interrupt" => Ok(Interrupt),
             "unalign_atom" => Ok(UnalignedAtomic),
             "oom" => Ok(VMOutOfMemory),
+            "wait_unshared" => Ok(AtomicWaitOnUnsharedMemory),
             // _ if s.starts_with("user") => s[4..].parse().map(User).map_err(|_| ()),
             _ => Err(()),
         }
@@ -160,7 +167,7 @@ mod tests {
     use super::*;
 
     // Everything but user-defined codes.
-    const CODES: [TrapCode; 15] = [
+    const CODES: [TrapCode; 16] = [
         TrapCode::StackOverflow,
         TrapCode::HeapSetterOutOfBounds,
         TrapCode::HeapAccessOutOfBounds,
@@ -176,6 +183,7 @@ mod tests {
         TrapCode::UnreachableCodeReached,
         TrapCode::Interrupt,
         TrapCode::UnalignedAtomic,
+        TrapCode::AtomicWaitOnUnsharedMemory,
     ];
 
     #[test]
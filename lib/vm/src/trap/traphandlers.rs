@@ -6,6 +6,7 @@
 
 use super::trapcode::TrapCode;
 use crate::instance::{Instance, SignalHandler};
+use crate::tail_call;
 use crate::vmcontext::{VMFunctionBody, VMFunctionEnvironment, VMTrampoline};
 use backtrace::Backtrace;
 use std::any::Any;
@@ -490,6 +491,9 @@ impl Trap {
 /// * `values_vec` - points to a buffer which holds the incoming arguments, and to
 ///   which the outgoing return values will be written.
 ///
+/// The tail calls left by the callee, if any, are made before returning, and
+/// the results of the last one are written to `values_vec`.
+///
 /// # Safety
 ///
 /// Wildly unsafe because it calls raw function pointers and reads/writes raw
@@ -503,7 +507,8 @@ pub unsafe fn wasmer_call_trampoline(
     catch_traps(vmctx, || {
         mem::transmute::<_, extern "C" fn(VMFunctionEnvironment, *const VMFunctionBody, *mut u8)>(
             trampoline,
-        )(vmctx, callee, values_vec)
+        )(vmctx, callee, values_vec);
+        tail_call::finish_tail_calls(values_vec as *mut u128);
     })
 }
 
@@ -596,33 +601,32 @@ impl CallThreadState {
     }
 
     fn with(mut self, closure: impl FnOnce(&Self) -> i32) -> Result<(), Trap> {
-        tls::with(|prev| {
-            self.prev = prev.map(|p| p as *const _);
-            let ret = tls::set(&self, || closure(&self));
-            match self.unwind.replace(UnwindReason::None) {
-                UnwindReason::None => {
-                    debug_assert_eq!(ret, 1);
-                    Ok(())
-                }
-                UnwindReason::UserTrap(data) => {
-                    debug_assert_eq!(ret, 0);
-                    Err(Trap::new_from_user(data))
-                }
-                UnwindReason::LibTrap(trap) => Err(trap),
-                UnwindReason::RuntimeTrap {
-                    backtrace,
-                    pc,
-                    signal_trap,
-                } => {
-                    debug_assert_eq!(ret, 0);
-                    Err(Trap::new_from_wasm(pc, backtrace, signal_trap))
-                }
-                UnwindReason::Panic(panic) => {
-                    debug_assert_eq!(ret, 0);
-                    std::panic::resume_unwind(panic)
-                }
+        let prev = tls::get();
+        self.prev = if prev.is_null() { None } else { Some(prev) };
+        let ret = tls::set(&self, || closure(&self));
+        match self.unwind.replace(UnwindReason::None) {
+            UnwindReason::None => {
+                debug_assert_eq!(ret, 1);
+                Ok(())
+            }
+            UnwindReason::UserTrap(data) => {
+                debug_assert_eq!(ret, 0);
+                Err(Trap::new_from_user(data))
             }
-        })
+            UnwindReason::LibTrap(trap) => Err(trap),
+            UnwindReason::RuntimeTrap {
+                backtrace,
+                pc,
+                signal_trap,
+            } => {
+                debug_assert_eq!(ret, 0);
+                Err(Trap::new_from_wasm(pc, backtrace, signal_trap))
+            }
+            UnwindReason::Panic(panic) => {
+                debug_assert_eq!(ret, 0);
+                std::panic::resume_unwind(panic)
+            }
+        }
     }
 
     fn any_instance(&self, func: impl Fn(&Instance) -> bool) -> bool {
@@ -737,6 +741,11 @@ impl Drop for CallThreadState {
 // happen which requires us to read some contextual state to figure out what to
 // do with the trap. This `tls` module is used to persist that information from
 // the caller to the trap site.
+//
+// Note that none of the functions below hold on to a reference to the
+// thread-local slot across a call to their closure: the stack that runs wasm
+// may be suspended and resumed on another thread (see `TlsRestore`), so the
+// slot is looked up again every time it is accessed.
 mod tls {
     use super::CallThreadState;
     use std::cell::Cell;
@@ -744,31 +753,80 @@ mod tls {
 
     thread_local!(static PTR: Cell<*const CallThreadState> = Cell::new(ptr::null()));
 
+    /// Returns the pointer currently configured with `set`, or null if
+    /// no wasm code is executing on this thread.
+    pub fn get() -> *const CallThreadState {
+        PTR.with(|p| p.get())
+    }
+
+    /// Replaces the pointer configured for this thread, returning the
+    /// previous one.
+    pub fn replace(ptr: *const CallThreadState) -> *const CallThreadState {
+        PTR.with(|p| p.replace(ptr))
+    }
+
     /// Configures thread local state such that for the duration of the
     /// execution of `closure` any call to `with` will yield `ptr`, unless this
     /// is recursively called again.
     pub fn set<R>(ptr: &CallThreadState, closure: impl FnOnce() -> R) -> R {
-        struct Reset<'a, T: Copy>(&'a Cell<T>, T);
+        struct Reset(*const CallThreadState);
 
-        impl<T: Copy> Drop for Reset<'_, T> {
+        impl Drop for Reset {
             fn drop(&mut self) {
-                self.0.set(self.1);
+                replace(self.0);
             }
         }
 
-        PTR.with(|p| {
-            let _r = Reset(p, p.replace(ptr));
-            closure()
-        })
+        let _r = Reset(replace(ptr));
+        closure()
     }
 
     /// Returns the last pointer configured with `set` above. Panics if `set`
     /// has not been previously called.
     pub fn with<R>(closure: impl FnOnce(Option<&CallThreadState>) -> R) -> R {
-        PTR.with(|ptr| {
-            let p = ptr.get();
-            unsafe { closure(if p.is_null() { None } else { Some(&*p) }) }
-        })
+        let p = get();
+        unsafe { closure(if p.is_null() { None } else { Some(&*p) }) }
+    }
+}
+
+/// Opaque state used to carry the trap handling state of a thread across
+/// a stack switch.
+///
+/// When wasm code runs on a separate stack (for example to support
+/// asynchronous host functions), the thread-local state registered by
+/// [`catch_traps`] belongs to that stack and not to the thread. It must be
+/// taken out of the thread with [`TlsRestore::take`] right before switching
+/// away from the stack, and put back with [`TlsRestore::replace`] right after
+/// switching back to it, possibly on another thread.
+pub struct TlsRestore(*const CallThreadState);
+
+impl TlsRestore {
+    /// Takes the trap handling state currently configured on this thread,
+    /// leaving the thread as if no wasm code was executing.
+    ///
+    /// # Safety
+    ///
+    /// The returned value must be given back to [`TlsRestore::replace`]
+    /// before the wasm code that configured it resumes execution.
+    pub unsafe fn take() -> Self {
+        Self(tls::replace(ptr::null()))
+    }
+
+    /// Restores a previously taken trap handling state on the current
+    /// thread.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called on the stack the state was taken from, and the
+    /// thread must not have any wasm code executing on it.
+    pub unsafe fn replace(self) -> Result<(), Trap> {
+        // The stack may have moved to a thread that never ran wasm code.
+        #[cfg(unix)]
+        setup_unix_sigaltstack()?;
+
+        let prev = tls::replace(self.0);
+        debug_assert!(prev.is_null());
+        Ok(())
     }
 }
 
@@ -4,6 +4,7 @@
 //! This file declares `VMContext` and several related structs which contain
 //! fields that compiled wasm code accesses directly.
 
+use crate::futex;
 use crate::global::Global;
 use crate::instance::Instance;
 use crate::memory::Memory;
@@ -13,6 +14,7 @@ use std::any::Any;
 use std::convert::TryFrom;
 use std::fmt;
 use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::u32;
 
@@ -322,7 +324,7 @@ pub struct VMMemoryDefinition {
     pub base: *mut u8,
 
     /// The current logical size of this linear memory in bytes.
-    pub current_length: u32,
+    pub current_length: usize,
 }
 
 /// # Safety
@@ -348,13 +350,30 @@ impl VMMemoryDefinition {
     /// The memory is not copied atomically and is not synchronized: it's the
     /// caller's responsibility to synchronize.
     pub(crate) unsafe fn memory_copy(&self, dst: u32, src: u32, len: u32) -> Result<(), Trap> {
+        self.memory_copy_from(self, dst, src, len)
+    }
+
+    /// Do an unsynchronized, non-atomic `memory.copy` from `src_memory` into
+    /// this memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Trap` error when the source or destination ranges are out of
+    /// bounds.
+    ///
+    /// # Safety
+    /// The memory is not copied atomically and is not synchronized: it's the
+    /// caller's responsibility to synchronize.
+    pub(crate) unsafe fn memory_copy_from(
+        &self,
+        src_memory: &Self,
+        dst: u32,
+        src: u32,
+        len: u32,
+    ) -> Result<(), Trap> {
         // https://webassembly.github.io/reference-types/core/exec/instructions.html#exec-memory-copy
-        if src
-            .checked_add(len)
-            .map_or(true, |n| n > self.current_length)
-            || dst
-                .checked_add(len)
-                .map_or(true, |m| m > self.current_length)
+        if !range_in_bounds(src, len, src_memory.current_length)
+            || !range_in_bounds(dst, len, self.current_length)
         {
             return Err(Trap::new_from_runtime(TrapCode::HeapAccessOutOfBounds));
         }
@@ -365,7 +384,7 @@ impl VMMemoryDefinition {
         // Bounds and casts are checked above, by this point we know that
         // everything is safe.
         let dst = self.base.add(dst);
-        let src = self.base.add(src);
+        let src = src_memory.base.add(src);
         ptr::copy(src, dst, len as usize);
 
         Ok(())
@@ -382,10 +401,7 @@ impl VMMemoryDefinition {
     /// The memory is not filled atomically and is not synchronized: it's the
     /// caller's responsibility to synchronize.
     pub(crate) unsafe fn memory_fill(&self, dst: u32, val: u32, len: u32) -> Result<(), Trap> {
-        if dst
-            .checked_add(len)
-            .map_or(true, |m| m > self.current_length)
-        {
+        if !range_in_bounds(dst, len, self.current_length) {
             return Err(Trap::new_from_runtime(TrapCode::HeapAccessOutOfBounds));
         }
 
@@ -401,6 +417,15 @@ impl VMMemoryDefinition {
     }
 }
 
+/// Returns whether the `len` bytes at `start` are within the first `length`
+/// bytes of a memory.
+fn range_in_bounds(start: u32, len: u32, length: usize) -> bool {
+    match start.checked_add(len).map(usize::try_from) {
+        Some(Ok(end)) => end <= length,
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod test_vmmemory_definition {
     use super::VMMemoryDefinition;
@@ -804,6 +829,205 @@ impl Default for VMCallerCheckedAnyfunc {
     }
 }
 
+/// The interrupt flags shared by all the instances of a `Store`, which
+/// JIT code checks at function entries and loop headers.
+///
+/// Every `VMContext` holds a pointer to it.
+#[derive(Debug, Default)]
+#[repr(C)]
+pub struct VMInterrupts {
+    /// A non-zero value requests the running code to trap with
+    /// [`TrapCode::Interrupt`]. The flag is cleared by the code that
+    /// observes it.
+    pub interrupt: AtomicUsize,
+
+    /// The lowest address the stack pointer may reach at the entry of a
+    /// function compiled with explicit stack checks, below which the
+    /// function traps with [`TrapCode::StackOverflow`]. Zero disables the
+    /// checks.
+    ///
+    /// It is set by [`VMInterrupts::enter_wasm`] from `max_wasm_stack`
+    /// for the duration of each call from the host.
+    pub stack_limit: AtomicUsize,
+
+    /// The maximum number of bytes of stack that WebAssembly code may use
+    /// below the point where the host calls into it, or zero for no limit.
+    /// It is not read by JIT code.
+    pub max_wasm_stack: AtomicUsize,
+}
+
+#[cfg(test)]
+mod test_vminterrupts {
+    use super::VMInterrupts;
+    use crate::module::ModuleInfo;
+    use crate::vmoffsets::VMOffsets;
+    use memoffset::offset_of;
+    use std::mem::size_of;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn check_vminterrupts_offsets() {
+        let module = ModuleInfo::new();
+        let offsets = VMOffsets::new(size_of::<*mut u8>() as u8, &module);
+        assert_eq!(
+            size_of::<VMInterrupts>(),
+            usize::from(offsets.size_of_vminterrupts())
+        );
+        assert_eq!(
+            offset_of!(VMInterrupts, interrupt),
+            usize::from(offsets.vminterrupts_interrupt())
+        );
+        assert_eq!(
+            offset_of!(VMInterrupts, stack_limit),
+            usize::from(offsets.vminterrupts_stack_limit())
+        );
+    }
+
+    #[test]
+    fn enter_wasm_restores_the_previous_stack_limit() {
+        let interrupts = Arc::new(VMInterrupts::default());
+        {
+            let _guard = interrupts.enter_wasm();
+            assert_eq!(interrupts.stack_limit(), 0);
+        }
+
+        interrupts.set_max_wasm_stack(0x10000);
+        {
+            let _outer = interrupts.enter_wasm();
+            let limit = interrupts.stack_limit();
+            assert_ne!(limit, 0);
+            // Nested calls get a limit of their own, from a deeper stack
+            // pointer.
+            #[inline(never)]
+            fn nested(interrupts: &VMInterrupts, outer_limit: usize) {
+                let _inner = interrupts.enter_wasm();
+                assert!(interrupts.stack_limit() < outer_limit);
+            }
+            nested(&interrupts, limit);
+            assert_eq!(interrupts.stack_limit(), limit);
+        }
+        assert_eq!(interrupts.stack_limit(), 0);
+
+        // A call on another stack, such as another thread, doesn't see
+        // the limit of this one.
+        let _guard = interrupts.enter_wasm();
+        let limit = interrupts.stack_limit();
+        let other = interrupts.clone();
+        thread::spawn(move || {
+            let _guard = other.enter_wasm();
+            assert_ne!(other.stack_limit(), limit);
+        })
+        .join()
+        .unwrap();
+        assert_eq!(interrupts.stack_limit(), limit);
+    }
+}
+
+impl VMInterrupts {
+    /// Requests the code running with these interrupts to trap with
+    /// [`TrapCode::Interrupt`] at its next function entry or loop
+    /// header.
+    ///
+    /// Threads waiting in `memory.atomic.wait` are woken up, and trap
+    /// right away.
+    pub fn interrupt(&self) {
+        self.interrupt.store(1, Ordering::SeqCst);
+        futex::interrupt_waiters(self);
+    }
+
+    /// Sets the maximum number of bytes of stack that WebAssembly code
+    /// may use when called from the host, zero meaning no limit.
+    ///
+    /// Only functions compiled with explicit stack checks enforce the
+    /// limit. It takes effect at the next call from the host.
+    pub fn set_max_wasm_stack(&self, max_wasm_stack: usize) {
+        self.max_wasm_stack.store(max_wasm_stack, Ordering::SeqCst);
+    }
+
+    /// Returns the current stack limit, zero meaning no limit.
+    pub fn stack_limit(&self) -> usize {
+        self.stack_limit.load(Ordering::SeqCst)
+    }
+
+    /// Sets the stack limit for a call from the host into WebAssembly,
+    /// `max_wasm_stack` bytes below the current stack pointer.
+    ///
+    /// The limit in effect before the call, which belongs to the caller
+    /// if this is a nested call, is restored when the returned guard is
+    /// dropped.
+    pub fn enter_wasm(&self) -> StackLimitGuard<'_> {
+        let max_wasm_stack = self.max_wasm_stack.load(Ordering::SeqCst);
+        if max_wasm_stack == 0 {
+            return StackLimitGuard {
+                interrupts: None,
+                prev_limit: 0,
+            };
+        }
+        // The address of a local approximates the stack pointer.
+        let marker = 0u8;
+        let stack_pointer = &marker as *const u8 as usize;
+        let limit = stack_pointer.saturating_sub(max_wasm_stack).max(1);
+        StackLimitGuard {
+            interrupts: Some(self),
+            prev_limit: self.stack_limit.swap(limit, Ordering::SeqCst),
+        }
+    }
+}
+
+/// Restores the stack limit replaced by [`VMInterrupts::enter_wasm`]
+/// when dropped.
+#[derive(Debug)]
+pub struct StackLimitGuard<'a> {
+    interrupts: Option<&'a VMInterrupts>,
+    prev_limit: usize,
+}
+
+impl<'a> Drop for StackLimitGuard<'a> {
+    fn drop(&mut self) {
+        if let Some(interrupts) = self.interrupts {
+            interrupts
+                .stack_limit
+                .store(self.prev_limit, Ordering::SeqCst);
+        }
+    }
+}
+
+/// A handle to the [`VMInterrupts`] of a `Store`, which can be sent to
+/// another thread to interrupt the WebAssembly code running there.
+///
+/// Interrupting while no WebAssembly code is running makes the next
+/// function entry or loop header trap.
+#[derive(Clone, Debug)]
+pub struct InterruptHandle {
+    interrupts: Arc<VMInterrupts>,
+}
+
+impl InterruptHandle {
+    /// Creates a handle to `interrupts`.
+    pub fn new(interrupts: Arc<VMInterrupts>) -> Self {
+        Self { interrupts }
+    }
+
+    /// Makes the WebAssembly code trap with [`TrapCode::Interrupt`] at
+    /// its next function entry or loop header.
+    pub fn interrupt(&self) {
+        self.interrupts.interrupt();
+    }
+
+    /// Returns whether an interrupt is pending, not yet seen by any
+    /// WebAssembly code.
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupts.interrupt.load(Ordering::SeqCst) != 0
+    }
+
+    /// Withdraws the pending interrupt, if any, so that it doesn't make
+    /// the next WebAssembly code to run trap.
+    pub fn clear(&self) {
+        self.interrupts.interrupt.store(0, Ordering::SeqCst);
+    }
+}
+
 /// An index type for builtin functions.
 #[derive(Copy, Clone, Debug)]
 pub struct VMBuiltinFunctionIndex(u32);
@@ -866,9 +1090,36 @@ impl VMBuiltinFunctionIndex {
     pub const fn get_raise_trap_index() -> Self {
         Self(13)
     }
+    /// Returns an index for wasm's `memory.copy` between two different
+    /// memories.
+    pub const fn get_memory_copy_between_index() -> Self {
+        Self(14)
+    }
+    /// Returns an index for wasm's `memory.atomic.wait32` instruction.
+    pub const fn get_memory_atomic_wait32_index() -> Self {
+        Self(15)
+    }
+    /// Returns an index for wasm's `memory.atomic.wait64` instruction.
+    pub const fn get_memory_atomic_wait64_index() -> Self {
+        Self(16)
+    }
+    /// Returns an index for wasm's `memory.atomic.notify` instruction.
+    pub const fn get_memory_atomic_notify_index() -> Self {
+        Self(17)
+    }
+    /// Returns an index for wasm's `return_call` and `return_call_indirect`
+    /// instructions.
+    pub const fn get_return_call_index() -> Self {
+        Self(18)
+    }
+    /// Returns an index for the builtin function making the tail calls left
+    /// by a function that returned.
+    pub const fn get_finish_tail_calls_index() -> Self {
+        Self(19)
+    }
     /// Returns the total number of builtin functions.
     pub const fn builtin_functions_total_number() -> u32 {
-        14
+        20
     }
 
     /// Return the index as an u32 number.
@@ -926,6 +1177,18 @@ impl VMBuiltinFunctionsArray {
             wasmer_data_drop as usize;
         ptrs[VMBuiltinFunctionIndex::get_raise_trap_index().index() as usize] =
             wasmer_raise_trap as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory_copy_between_index().index() as usize] =
+            wasmer_memory_copy_between as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory_atomic_wait32_index().index() as usize] =
+            wasmer_memory_atomic_wait32 as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory_atomic_wait64_index().index() as usize] =
+            wasmer_memory_atomic_wait64 as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory_atomic_notify_index().index() as usize] =
+            wasmer_memory_atomic_notify as usize;
+        ptrs[VMBuiltinFunctionIndex::get_return_call_index().index() as usize] =
+            wasmer_return_call as usize;
+        ptrs[VMBuiltinFunctionIndex::get_finish_tail_calls_index().index() as usize] =
+            wasmer_finish_tail_calls as usize;
 
         debug_assert!(ptrs.iter().cloned().all(|p| p != 0));
 
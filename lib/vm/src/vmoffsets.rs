@@ -242,7 +242,7 @@ impl VMOffsets {
 
     /// The size of the `current_length` field.
     pub const fn size_of_vmmemory_definition_current_length(&self) -> u8 {
-        4
+        self.pointer_size
     }
 
     /// Return the size of [`VMMemoryDefinition`].
@@ -334,13 +334,45 @@ impl VMOffsets {
     }
 }
 
+/// Offsets for [`VMInterrupts`].
+///
+/// [`VMInterrupts`]: crate::vmcontext::VMInterrupts
+impl VMOffsets {
+    /// The offset of the `interrupt` field.
+    #[allow(clippy::erasing_op)]
+    pub const fn vminterrupts_interrupt(&self) -> u8 {
+        0 * self.pointer_size
+    }
+
+    /// The offset of the `stack_limit` field.
+    pub const fn vminterrupts_stack_limit(&self) -> u8 {
+        self.pointer_size
+    }
+
+    /// Return the size of [`VMInterrupts`].
+    ///
+    /// [`VMInterrupts`]: crate::vmcontext::VMInterrupts
+    pub const fn size_of_vminterrupts(&self) -> u8 {
+        3 * self.pointer_size
+    }
+}
+
 /// Offsets for [`VMContext`].
 ///
 /// [`VMContext`]: crate::vmcontext::VMContext
 impl VMOffsets {
+    /// The offset of the pointer to the [`VMInterrupts`].
+    ///
+    /// [`VMInterrupts`]: crate::vmcontext::VMInterrupts
+    pub fn vmctx_interrupts(&self) -> u32 {
+        0
+    }
+
     /// The offset of the `signature_ids` array.
     pub fn vmctx_signature_ids_begin(&self) -> u32 {
-        0
+        self.vmctx_interrupts()
+            .checked_add(u32::from(self.pointer_size))
+            .unwrap()
     }
 
     /// The offset of the `tables` array.
@@ -17,19 +17,25 @@ mod macros;
 mod ptr;
 mod state;
 mod syscalls;
+mod threads;
 mod utils;
 
 use crate::syscalls::*;
+use crate::threads::WasiThreads;
 
 pub use crate::state::{
-    Fd, Pipe, Stderr, Stdin, Stdout, WasiFile, WasiFs, WasiFsError, WasiState, WasiStateBuilder,
-    WasiStateCreationError, ALL_RIGHTS, VIRTUAL_ROOT_FD,
+    DirEntry, Fd, FileSystem, HostFileSystem, HostSocket, LoopbackSocket, MemFile, MemFileSystem,
+    OpenOptions, Pipe, Stderr, Stdin, Stdout, WasiFile, WasiFs, WasiFsError, WasiSocket, WasiState,
+    WasiStateBuilder, WasiStateCreationError, ALL_RIGHTS, VIRTUAL_ROOT_FD,
 };
 pub use crate::syscalls::types;
 pub use crate::utils::{get_wasi_version, is_wasi_module, WasiVersion};
 
 use thiserror::Error;
-use wasmer::{imports, Function, ImportObject, LazyInit, Memory, Module, Store, WasmerEnv};
+use wasmer::{
+    imports, ChainableNamedResolver, Function, HostEnvInitError, ImportObject, Instance, LazyInit,
+    Memory, Module, NamedResolverChain, Store, WasmerEnv,
+};
 #[cfg(all(target_os = "macos", target_arch = "aarch64",))]
 use wasmer::{FunctionType, ValType};
 
@@ -46,7 +52,7 @@ pub enum WasiError {
 }
 
 /// The environment provided to the WASI imports.
-#[derive(Debug, Clone, WasmerEnv)]
+#[derive(Debug, Clone)]
 pub struct WasiEnv {
     /// Shared state of the WASI system. Manages all the data that the
     /// executing WASI program can see.
@@ -55,8 +61,18 @@ pub struct WasiEnv {
     /// if the lock is held and the Wasm calls into a host function that tries
     /// to lock this mutex, the program will deadlock.
     pub state: Arc<Mutex<WasiState>>,
-    #[wasmer(export)]
     memory: LazyInit<Memory>,
+    /// The name of the exported memory the WASI imports operate on.
+    memory_export_name: String,
+    threads: Arc<WasiThreads>,
+}
+
+impl WasmerEnv for WasiEnv {
+    fn init_with_instance(&mut self, instance: &Instance) -> Result<(), HostEnvInitError> {
+        let memory = instance.exports.get_memory(&self.memory_export_name)?;
+        self.memory.initialize(memory.clone());
+        Ok(())
+    }
 }
 
 impl WasiEnv {
@@ -64,9 +80,20 @@ impl WasiEnv {
         Self {
             state: Arc::new(Mutex::new(state)),
             memory: LazyInit::new(),
+            memory_export_name: "memory".to_string(),
+            threads: Default::default(),
         }
     }
 
+    /// Set the name of the exported memory the WASI imports operate on.
+    ///
+    /// Defaults to `"memory"`. Modules with several memories can use this to
+    /// bind WASI to a memory other than the first one. It must be set before
+    /// the instance is created.
+    pub fn set_memory_export_name(&mut self, name: impl Into<String>) {
+        self.memory_export_name = name.into();
+    }
+
     pub fn import_object(&mut self, module: &Module) -> Result<ImportObject, WasiError> {
         let wasi_version = get_wasi_version(module, false).ok_or(WasiError::UnknownWasiVersion)?;
         Ok(generate_import_object_from_env(
@@ -76,6 +103,49 @@ impl WasiEnv {
         ))
     }
 
+    /// Create the imports of a module built for `wasm32-wasi-threads`, which
+    /// spawns threads with the `wasi` `thread-spawn` import.
+    ///
+    /// Each new thread runs `wasi_thread_start` in a new instance of
+    /// `module`, created with the WASI imports and `imports`. These usually
+    /// hold the shared memory the module imports, so that all the threads
+    /// share it, as they share this `WasiEnv`.
+    ///
+    /// Exiting from any thread tears down all of them, by interrupting the
+    /// instances of the store of `module`. The threads blocked in a host
+    /// call, which can't be interrupted, are waited for up to a second, and
+    /// otherwise trap at their next WASI call.
+    pub fn import_object_with_threads(
+        &mut self,
+        module: &Module,
+        imports: ImportObject,
+    ) -> Result<NamedResolverChain<ImportObject, ImportObject>, WasiError> {
+        let wasi_version = get_wasi_version(module, false).ok_or(WasiError::UnknownWasiVersion)?;
+        self.threads
+            .set_spawner(module, wasi_version, imports.clone());
+        Ok(self.import_object(module)?.chain_back(imports))
+    }
+
+    /// Set the maximum number of threads the module can have spawned and
+    /// running at the same time, not counting the one it was started on.
+    ///
+    /// Defaults to 64. Spawning more threads fails with `EAGAIN`.
+    pub fn set_max_threads(&mut self, max_threads: usize) {
+        self.threads.set_max_threads(max_threads);
+    }
+
+    /// Get the code the module exited with, if any of its threads called
+    /// `proc_exit` or trapped.
+    ///
+    /// When a spawned thread exits, the other threads, including the one the
+    /// module was started on, are interrupted and trap with
+    /// [`TrapCode::Interrupt`](wasmer::TrapCode::Interrupt), or with
+    /// [`WasiError::Exit`] at their next WASI call: this is how to find out
+    /// the exit code there.
+    pub fn exit_code(&self) -> Option<types::__wasi_exitcode_t> {
+        self.threads.exit_code()
+    }
+
     /// Get the WASI state
     ///
     /// Be careful when using this in host functions that call into Wasm:
@@ -92,16 +162,53 @@ impl WasiEnv {
         self.state.lock().unwrap()
     }
 
+    /// Give the WASI module a connected socket, returning the fd it can
+    /// use the socket through.
+    ///
+    /// Unlike [`WasiStateBuilder::preopen_socket`], this can be used while
+    /// the module is running, for example to hand it connections as the
+    /// host accepts them.
+    pub fn open_socket(
+        &self,
+        socket: Box<dyn WasiSocket>,
+    ) -> Result<types::__wasi_fd_t, WasiFsError> {
+        self.state().fs.open_socket(socket)
+    }
+
+    /// Get a reference to the memory, if it has been set.
+    pub fn memory_ref(&self) -> Option<&Memory> {
+        self.memory.get_ref()
+    }
+
+    /// Get a reference to the memory without checking that it has been set.
+    ///
+    /// # Safety
+    /// The memory must have been set, by instantiating a module with the
+    /// WASI imports of this `WasiEnv`.
+    pub unsafe fn memory_ref_unchecked(&self) -> &Memory {
+        self.memory.get_unchecked()
+    }
+
     /// Get a reference to the memory
     pub fn memory(&self) -> &Memory {
         self.memory_ref()
             .expect("Memory should be set on `WasiEnv` first")
     }
 
+    /// Get the memory with the given index, as seen by the WASI imports,
+    /// together with the WASI state.
+    ///
+    /// The WASI imports only see one memory, the one exported under the
+    /// name given to [`WasiEnv::set_memory_export_name`], as memory 0.
     pub(crate) fn get_memory_and_wasi_state(
         &self,
-        _mem_index: u32,
+        mem_index: u32,
     ) -> (&Memory, MutexGuard<WasiState>) {
+        assert_eq!(
+            mem_index, 0,
+            "the WASI imports can only access memory 0, not memory {}",
+            mem_index
+        );
         let memory = self.memory();
         let state = self.state.lock().unwrap();
         (memory, state)
@@ -202,6 +309,9 @@ fn generate_import_object_snapshot0(store: &Store, env: WasiEnv) -> ImportObject
             "sock_send" => Function::new_native_with_env(store, env.clone(), sock_send),
             "sock_shutdown" => Function::new_native_with_env(store, env.clone(), sock_shutdown),
         },
+        "wasi" => {
+            "thread-spawn" => Function::new_native_with_env(store, env.clone(), thread_spawn),
+        },
     }
 }
 
@@ -254,6 +364,9 @@ fn generate_import_object_snapshot1(store: &Store, env: WasiEnv) -> ImportObject
             "sock_recv" => Function::new_native_with_env(store, env.clone(), sock_recv),
             "sock_send" => Function::new_native_with_env(store, env.clone(), sock_send),
             "sock_shutdown" => Function::new_native_with_env(store, env.clone(), sock_shutdown),
-        }
+        },
+        "wasi" => {
+            "thread-spawn" => Function::new_native_with_env(store, env.clone(), thread_spawn),
+        },
     }
 }
@@ -1,6 +1,8 @@
 //! Builder system for configuring a [`WasiState`] and creating it.
 
-use crate::state::{WasiFile, WasiFs, WasiFsError, WasiState};
+use crate::state::{
+    FileSystem, HostFileSystem, WasiFile, WasiFs, WasiFsError, WasiSocket, WasiState,
+};
 use crate::syscalls::types::{__WASI_STDERR_FILENO, __WASI_STDIN_FILENO, __WASI_STDOUT_FILENO};
 use crate::WasiEnv;
 use std::path::{Path, PathBuf};
@@ -42,6 +44,8 @@ pub struct WasiStateBuilder {
     stdout_override: Option<Box<dyn WasiFile>>,
     stderr_override: Option<Box<dyn WasiFile>>,
     stdin_override: Option<Box<dyn WasiFile>>,
+    fs_override: Option<Box<dyn FileSystem>>,
+    preopened_sockets: Vec<Box<dyn WasiSocket>>,
 }
 
 impl std::fmt::Debug for WasiStateBuilder {
@@ -54,6 +58,8 @@ impl std::fmt::Debug for WasiStateBuilder {
             .field("stdout_override exists", &self.stdout_override.is_some())
             .field("stderr_override exists", &self.stderr_override.is_some())
             .field("stdin_override exists", &self.stdin_override.is_some())
+            .field("fs_override exists", &self.fs_override.is_some())
+            .field("preopened_sockets", &self.preopened_sockets)
             .finish()
     }
 }
@@ -282,6 +288,25 @@ impl WasiStateBuilder {
         self
     }
 
+    /// Use the given [`FileSystem`] instead of the host's file system.
+    ///
+    /// Preopened and mapped directories are looked up in this file system.
+    pub fn set_fs(&mut self, fs: Box<dyn FileSystem>) -> &mut Self {
+        self.fs_override = Some(fs);
+
+        self
+    }
+
+    /// Give the WASI module an already connected socket.
+    ///
+    /// Sockets get the file descriptors after the preopened directories, in
+    /// the order they were added.
+    pub fn preopen_socket(&mut self, socket: Box<dyn WasiSocket>) -> &mut Self {
+        self.preopened_sockets.push(socket);
+
+        self
+    }
+
     /// Setup the WASI filesystem before running
     // TODO: improve ergonomics on this function
     pub fn setup_fs(
@@ -359,11 +384,21 @@ impl WasiStateBuilder {
             }
         }
 
-        // self.preopens are checked in [`PreopenDirBuilder::build`]
+        // self.preopens are checked in [`PreopenDirBuilder::build`], but
+        // whether they exist depends on the file system they are opened in
+        let fs_backend = self
+            .fs_override
+            .take()
+            .unwrap_or_else(|| Box::new(HostFileSystem));
+        for preopen in self.preopens.iter() {
+            if fs_backend.metadata(&preopen.path).is_err() {
+                return Err(WasiStateCreationError::PreopenedDirectoryNotFound(
+                    preopen.path.clone(),
+                ));
+            }
+        }
 
-        // this deprecation warning only applies to external callers
-        #[allow(deprecated)]
-        let mut wasi_fs = WasiFs::new_with_preopen(&self.preopens)
+        let mut wasi_fs = WasiFs::new_with_preopen(&self.preopens, fs_backend)
             .map_err(WasiStateCreationError::WasiFsCreationError)?;
         // set up the file system, overriding base files and calling the setup function
         if let Some(stdin_override) = self.stdin_override.take() {
@@ -381,6 +416,11 @@ impl WasiStateBuilder {
                 .swap_file(__WASI_STDERR_FILENO, stderr_override)
                 .map_err(WasiStateCreationError::WasiFsError)?;
         }
+        for socket in self.preopened_sockets.drain(..) {
+            wasi_fs
+                .open_socket(socket)
+                .map_err(WasiStateCreationError::WasiFsError)?;
+        }
         if let Some(f) = &self.setup_fs_fn {
             f(&mut wasi_fs).map_err(WasiStateCreationError::WasiFsSetupError)?;
         }
@@ -497,9 +537,6 @@ impl PreopenDirBuilder {
         }
         let path = self.path.clone().unwrap();
 
-        if !path.exists() {
-            return Err(WasiStateCreationError::PreopenedDirectoryNotFound(path));
-        }
         if let Some(alias) = &self.alias {
             validate_mapped_dir_alias(alias)?;
         }
@@ -557,6 +594,60 @@ mod test {
         );
     }
 
+    #[test]
+    fn preopens_are_looked_up_in_the_fs() {
+        let fs = crate::state::MemFileSystem::new();
+        fs.create_dir(Path::new("/data")).unwrap();
+
+        let output = create_wasi_state("test_prog")
+            .set_fs(Box::new(fs.clone()))
+            .preopen_dir("/missing")
+            .unwrap()
+            .build();
+        match output {
+            Err(WasiStateCreationError::PreopenedDirectoryNotFound(path)) => {
+                assert_eq!(path, Path::new("/missing"))
+            }
+            _ => panic!("preopening a directory missing from the fs must fail"),
+        }
+
+        let state = create_wasi_state("test_prog")
+            .set_fs(Box::new(fs))
+            .map_dir("data", "/data")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(state.fs.preopen_fds.len(), 2);
+    }
+
+    #[test]
+    fn preopened_sockets_come_after_preopened_dirs() {
+        use crate::state::{LoopbackSocket, WasiSocket};
+        use crate::syscalls::types::__WASI_FILETYPE_SOCKET_STREAM;
+
+        let fs = crate::state::MemFileSystem::new();
+        fs.create_dir(Path::new("/data")).unwrap();
+        let (mut host, guest) = LoopbackSocket::pair();
+
+        let mut state = create_wasi_state("test_prog")
+            .set_fs(Box::new(fs))
+            .preopen_socket(Box::new(guest))
+            .map_dir("data", "/data")
+            .unwrap()
+            .build()
+            .unwrap();
+        let socket_fd = state.fs.preopen_fds.iter().max().unwrap() + 1;
+        assert_eq!(
+            state.fs.fdstat(socket_fd).unwrap().fs_filetype,
+            __WASI_FILETYPE_SOCKET_STREAM
+        );
+
+        // closing the fd closes the socket
+        state.fs.close_fd(socket_fd).unwrap();
+        assert_eq!(host.recv(&mut [0; 4]), Ok(0));
+        assert!(state.fs.get_fd(socket_fd).is_err());
+    }
+
     #[test]
     fn nul_character_in_args() {
         let output = create_wasi_state("test_prog").arg("--h\0elp").build();
@@ -0,0 +1,261 @@
+//! The file system abstraction that backs [`WasiFs`].
+//!
+//! [`WasiFs`] keeps track of inodes, file descriptors and preopened
+//! directories; everything that actually touches files and directories goes
+//! through a [`FileSystem`].  By default that is the [`HostFileSystem`],
+//! [`MemFileSystem`] can be used instead to run a WASI module against a
+//! fully in-memory file system.
+//!
+//! [`WasiFs`]: super::WasiFs
+//! [`MemFileSystem`]: super::MemFileSystem
+
+use crate::state::{host_file_type_to_wasi_file_type, HostFile, WasiFile, WasiFsError};
+use crate::syscalls::types::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A file system that WASI paths are resolved against.
+///
+/// The paths given to a [`FileSystem`] are the paths of the preopened
+/// directories joined with the sandboxed path the WASI module asked for.
+#[typetag::serde(tag = "type")]
+pub trait FileSystem: fmt::Debug + Send + 'static {
+    /// Lists the entries of the directory at `path`.
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, WasiFsError>;
+
+    /// Creates a new, empty directory at `path`.
+    fn create_dir(&self, path: &Path) -> Result<(), WasiFsError>;
+
+    /// Removes the empty directory at `path`.
+    fn remove_dir(&self, path: &Path) -> Result<(), WasiFsError>;
+
+    /// Moves the file or directory at `from` to `to`.
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), WasiFsError>;
+
+    /// Removes the file at `path`.
+    fn remove_file(&self, path: &Path) -> Result<(), WasiFsError>;
+
+    /// Gets the metadata of the file or directory at `path`, following
+    /// symlinks.
+    fn metadata(&self, path: &Path) -> Result<__wasi_filestat_t, WasiFsError>;
+
+    /// Gets the metadata of the file, directory or symlink at `path`
+    /// without following symlinks.
+    fn symlink_metadata(&self, path: &Path) -> Result<__wasi_filestat_t, WasiFsError> {
+        self.metadata(path)
+    }
+
+    /// Reads the value of the symlink at `path`.
+    fn read_link(&self, _path: &Path) -> Result<PathBuf, WasiFsError> {
+        Err(WasiFsError::InvalidInput)
+    }
+
+    /// Sets the last accessed and last modified times, in nanoseconds as a
+    /// UNIX timestamp, of the file or directory at `path`.  `None` leaves
+    /// the time as it is.
+    fn set_times(
+        &self,
+        path: &Path,
+        last_accessed: Option<__wasi_timestamp_t>,
+        last_modified: Option<__wasi_timestamp_t>,
+    ) -> Result<(), WasiFsError>;
+
+    /// Opens the file at `path`.
+    fn open(&self, path: &Path, options: &OpenOptions) -> Result<Box<dyn WasiFile>, WasiFsError>;
+}
+
+/// Converts a WASI timestamp, in nanoseconds since the UNIX epoch, to a
+/// host file time.
+pub(crate) fn to_file_time(timestamp: __wasi_timestamp_t) -> filetime::FileTime {
+    filetime::FileTime::from_unix_time(
+        (timestamp / 1_000_000_000) as i64,
+        (timestamp % 1_000_000_000) as u32,
+    )
+}
+
+/// An entry returned by [`FileSystem::read_dir`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    /// The name of the entry in its directory
+    pub name: String,
+    /// The type of the entry
+    pub file_type: __wasi_filetype_t,
+}
+
+/// Options used to open a file with [`FileSystem::open`].
+///
+/// These mirror the options of `std::fs::OpenOptions`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpenOptions {
+    pub read: bool,
+    pub write: bool,
+    pub append: bool,
+    pub truncate: bool,
+    pub create: bool,
+    pub create_new: bool,
+}
+
+impl OpenOptions {
+    /// Creates a blank set of options, all options are `false`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    pub fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self
+    }
+
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    pub fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self
+    }
+
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
+    }
+
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+}
+
+/// The [`FileSystem`] of the host, paths are used as they are.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct HostFileSystem;
+
+#[typetag::serde]
+impl FileSystem for HostFileSystem {
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, WasiFsError> {
+        fs::read_dir(path)?
+            .map(|entry| {
+                let entry = entry?;
+                Ok(DirEntry {
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    file_type: host_file_type_to_wasi_file_type(entry.file_type()?),
+                })
+            })
+            .collect()
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<(), WasiFsError> {
+        fs::create_dir(path).map_err(Into::into)
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<(), WasiFsError> {
+        fs::remove_dir(path).map_err(Into::into)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), WasiFsError> {
+        fs::rename(from, to).map_err(Into::into)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<(), WasiFsError> {
+        fs::remove_file(path).map_err(Into::into)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<__wasi_filestat_t, WasiFsError> {
+        host_metadata_to_filestat(path.metadata()?)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<__wasi_filestat_t, WasiFsError> {
+        host_metadata_to_filestat(path.symlink_metadata()?)
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf, WasiFsError> {
+        path.read_link().map_err(Into::into)
+    }
+
+    fn set_times(
+        &self,
+        path: &Path,
+        last_accessed: Option<__wasi_timestamp_t>,
+        last_modified: Option<__wasi_timestamp_t>,
+    ) -> Result<(), WasiFsError> {
+        if let Some(last_accessed) = last_accessed {
+            filetime::set_file_atime(path, to_file_time(last_accessed))?;
+        }
+        if let Some(last_modified) = last_modified {
+            filetime::set_file_mtime(path, to_file_time(last_modified))?;
+        }
+        Ok(())
+    }
+
+    fn open(&self, path: &Path, options: &OpenOptions) -> Result<Box<dyn WasiFile>, WasiFsError> {
+        let file = fs::OpenOptions::new()
+            .read(options.read)
+            .write(options.write)
+            .append(options.append)
+            .truncate(options.truncate)
+            .create(options.create)
+            .create_new(options.create_new)
+            .open(path)?;
+        Ok(Box::new(HostFile::new(
+            file,
+            path.to_path_buf(),
+            options.read,
+            options.write,
+            options.append,
+        )))
+    }
+}
+
+fn host_metadata_to_filestat(md: fs::Metadata) -> Result<__wasi_filestat_t, WasiFsError> {
+    let to_nanos = |time: SystemTime| {
+        time.duration_since(SystemTime::UNIX_EPOCH)
+            .map(|t| t.as_nanos() as u64)
+            .map_err(|_| WasiFsError::IOError)
+    };
+    Ok(__wasi_filestat_t {
+        st_filetype: host_file_type_to_wasi_file_type(md.file_type()),
+        st_size: md.len(),
+        st_atim: to_nanos(md.accessed()?)?,
+        st_mtim: to_nanos(md.modified()?)?,
+        st_ctim: md
+            .created()
+            .ok()
+            .and_then(|ct| to_nanos(ct).ok())
+            .unwrap_or(0),
+        ..__wasi_filestat_t::default()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn host_set_times() {
+        let path =
+            std::env::temp_dir().join(format!("wasmer-wasi-set-times-{}", std::process::id()));
+        fs::write(&path, b"hello").unwrap();
+
+        let fs = HostFileSystem;
+        let last_modified = 1_000_000_000_123_456_789;
+        fs.set_times(&path, None, Some(last_modified)).unwrap();
+        assert_eq!(fs.metadata(&path).unwrap().st_mtim, last_modified);
+
+        let last_accessed = 2_000_000_000_000_000_000;
+        fs.set_times(&path, Some(last_accessed), None).unwrap();
+        let stat = fs.metadata(&path).unwrap();
+        assert_eq!(stat.st_atim, last_accessed);
+        assert_eq!(stat.st_mtim, last_modified);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,757 @@
+//! A [`FileSystem`] that lives entirely in memory.
+
+use crate::state::{DirEntry, FileSystem, OpenOptions, WasiFile, WasiFsError};
+use crate::syscalls::types::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{self, Read, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+/// An in-memory [`FileSystem`] supporting files and directories.
+///
+/// Cloning a [`MemFileSystem`] gives another handle to the same file system,
+/// which lets the host populate it before handing it to WASI and inspect it
+/// afterwards.
+///
+/// Usage:
+///
+/// ```no_run
+/// # use std::io::Write;
+/// # use std::path::Path;
+/// # use wasmer_wasi::{FileSystem, MemFileSystem, OpenOptions, WasiState};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let fs = MemFileSystem::new();
+/// fs.create_dir(Path::new("/data"))?;
+/// fs.open(
+///     Path::new("/data/hello.txt"),
+///     OpenOptions::new().write(true).create(true),
+/// )?
+/// .write_all(b"hello")?;
+///
+/// WasiState::new("program_name")
+///     .set_fs(Box::new(fs.clone()))
+///     .preopen_dir("/data")?
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MemFileSystem {
+    root: Arc<Mutex<Directory>>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Times {
+    accessed: __wasi_timestamp_t,
+    modified: __wasi_timestamp_t,
+    created: __wasi_timestamp_t,
+}
+
+impl Default for Times {
+    fn default() -> Self {
+        let now = now();
+        Self {
+            accessed: now,
+            modified: now,
+            created: now,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Directory {
+    entries: BTreeMap<String, Node>,
+    times: Times,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FileData {
+    contents: Vec<u8>,
+    times: Times,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Node {
+    File(Arc<Mutex<FileData>>),
+    Dir(Directory),
+}
+
+impl Node {
+    fn stat(&self) -> __wasi_filestat_t {
+        match self {
+            Node::File(data) => {
+                let data = data.lock().unwrap();
+                __wasi_filestat_t {
+                    st_filetype: __WASI_FILETYPE_REGULAR_FILE,
+                    st_size: data.contents.len() as u64,
+                    st_atim: data.times.accessed,
+                    st_mtim: data.times.modified,
+                    st_ctim: data.times.created,
+                    ..__wasi_filestat_t::default()
+                }
+            }
+            Node::Dir(dir) => dir.stat(),
+        }
+    }
+
+    fn file_type(&self) -> __wasi_filetype_t {
+        match self {
+            Node::File(_) => __WASI_FILETYPE_REGULAR_FILE,
+            Node::Dir(_) => __WASI_FILETYPE_DIRECTORY,
+        }
+    }
+}
+
+impl Directory {
+    fn stat(&self) -> __wasi_filestat_t {
+        __wasi_filestat_t {
+            st_filetype: __WASI_FILETYPE_DIRECTORY,
+            st_atim: self.times.accessed,
+            st_mtim: self.times.modified,
+            st_ctim: self.times.created,
+            ..__wasi_filestat_t::default()
+        }
+    }
+
+    fn dir(&self, path: &[String]) -> Result<&Directory, WasiFsError> {
+        let mut cur = self;
+        for name in path {
+            cur = match cur.entries.get(name) {
+                Some(Node::Dir(dir)) => dir,
+                Some(Node::File(_)) => return Err(WasiFsError::BaseNotDirectory),
+                None => return Err(WasiFsError::EntityNotFound),
+            };
+        }
+        Ok(cur)
+    }
+
+    fn dir_mut(&mut self, path: &[String]) -> Result<&mut Directory, WasiFsError> {
+        let mut cur = self;
+        for name in path {
+            cur = match cur.entries.get_mut(name) {
+                Some(Node::Dir(dir)) => dir,
+                Some(Node::File(_)) => return Err(WasiFsError::BaseNotDirectory),
+                None => return Err(WasiFsError::EntityNotFound),
+            };
+        }
+        Ok(cur)
+    }
+
+    /// Finds the path of the given file, used by [`MemFile`] which doesn't
+    /// keep track of where it lives.
+    fn find_file(&self, data: &Arc<Mutex<FileData>>) -> Option<Vec<String>> {
+        for (name, node) in self.entries.iter() {
+            match node {
+                Node::File(file) if Arc::ptr_eq(file, data) => return Some(vec![name.clone()]),
+                Node::Dir(dir) => {
+                    if let Some(mut path) = dir.find_file(data) {
+                        path.insert(0, name.clone());
+                        return Some(path);
+                    }
+                }
+                _ => (),
+            }
+        }
+        None
+    }
+
+    fn touch(&mut self) {
+        self.times.modified = now();
+    }
+}
+
+/// Normalizes `path` into its components, the in-memory file system has no
+/// notion of a current directory so relative paths start at the root too.
+fn components(path: &Path) -> Result<Vec<String>, WasiFsError> {
+    let mut out = vec![];
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => (),
+            Component::ParentDir => {
+                out.pop();
+            }
+            Component::Normal(name) => {
+                out.push(name.to_str().ok_or(WasiFsError::InvalidInput)?.to_string())
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Splits `path` into the components of its parent directory and its name.
+fn parent_and_name(path: &Path) -> Result<(Vec<String>, String), WasiFsError> {
+    let mut parent = components(path)?;
+    // the root has no parent, so it can't be created, removed or renamed
+    let name = parent.pop().ok_or(WasiFsError::PermissionDenied)?;
+    Ok((parent, name))
+}
+
+fn now() -> __wasi_timestamp_t {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|t| t.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+impl MemFileSystem {
+    /// Creates an empty in-memory file system.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn root(&self) -> MutexGuard<Directory> {
+        self.root.lock().unwrap()
+    }
+
+    fn rename_components(&self, from: &[String], to: &Path) -> Result<(), WasiFsError> {
+        let (to_parent, to_name) = parent_and_name(to)?;
+        if to_parent.starts_with(from) {
+            // a directory can't be moved into itself
+            return Err(WasiFsError::InvalidInput);
+        }
+        let (from_name, from_parent) = from.split_last().ok_or(WasiFsError::PermissionDenied)?;
+
+        if to_parent == from_parent && &to_name == from_name {
+            return Ok(());
+        }
+
+        let mut root = self.root();
+        let source_is_dir = match root.dir(from_parent)?.entries.get(from_name) {
+            Some(node) => matches!(node, Node::Dir(_)),
+            None => return Err(WasiFsError::EntityNotFound),
+        };
+        // like `rename(2)`, an existing target is replaced if it's of the
+        // same kind as the source, and if it's an empty directory
+        match (source_is_dir, root.dir(&to_parent)?.entries.get(&to_name)) {
+            (_, None) | (false, Some(Node::File(_))) => (),
+            (true, Some(Node::Dir(target))) if target.entries.is_empty() => (),
+            (true, Some(Node::Dir(_))) => return Err(WasiFsError::DirectoryNotEmpty),
+            (true, Some(Node::File(_))) => return Err(WasiFsError::BaseNotDirectory),
+            (false, Some(Node::Dir(_))) => return Err(WasiFsError::IsADirectory),
+        }
+        let source_dir = root.dir_mut(from_parent)?;
+        let node = source_dir
+            .entries
+            .remove(from_name)
+            .ok_or(WasiFsError::EntityNotFound)?;
+        source_dir.touch();
+        let target_dir = root.dir_mut(&to_parent)?;
+        target_dir.entries.insert(to_name, node);
+        target_dir.touch();
+
+        Ok(())
+    }
+}
+
+#[typetag::serde]
+impl FileSystem for MemFileSystem {
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, WasiFsError> {
+        let root = self.root();
+        let dir = root.dir(&components(path)?)?;
+        Ok(dir
+            .entries
+            .iter()
+            .map(|(name, node)| DirEntry {
+                name: name.clone(),
+                file_type: node.file_type(),
+            })
+            .collect())
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<(), WasiFsError> {
+        let (parent, name) = parent_and_name(path)?;
+        let mut root = self.root();
+        let dir = root.dir_mut(&parent)?;
+        if dir.entries.contains_key(&name) {
+            return Err(WasiFsError::AlreadyExists);
+        }
+        dir.entries.insert(name, Node::Dir(Directory::default()));
+        dir.touch();
+
+        Ok(())
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<(), WasiFsError> {
+        let (parent, name) = parent_and_name(path)?;
+        let mut root = self.root();
+        let dir = root.dir_mut(&parent)?;
+        match dir.entries.get(&name) {
+            Some(Node::Dir(child)) if !child.entries.is_empty() => {
+                return Err(WasiFsError::DirectoryNotEmpty)
+            }
+            Some(Node::Dir(_)) => (),
+            Some(Node::File(_)) => return Err(WasiFsError::BaseNotDirectory),
+            None => return Err(WasiFsError::EntityNotFound),
+        }
+        dir.entries.remove(&name);
+        dir.touch();
+
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), WasiFsError> {
+        self.rename_components(&components(from)?, to)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<(), WasiFsError> {
+        let (parent, name) = parent_and_name(path)?;
+        let mut root = self.root();
+        let dir = root.dir_mut(&parent)?;
+        match dir.entries.get(&name) {
+            Some(Node::File(_)) => (),
+            Some(Node::Dir(_)) => return Err(WasiFsError::NotAFile),
+            None => return Err(WasiFsError::EntityNotFound),
+        }
+        dir.entries.remove(&name);
+        dir.touch();
+
+        Ok(())
+    }
+
+    fn metadata(&self, path: &Path) -> Result<__wasi_filestat_t, WasiFsError> {
+        let root = self.root();
+        match parent_and_name(path) {
+            Ok((parent, name)) => root
+                .dir(&parent)?
+                .entries
+                .get(&name)
+                .map(Node::stat)
+                .ok_or(WasiFsError::EntityNotFound),
+            Err(_) => Ok(root.stat()),
+        }
+    }
+
+    fn set_times(
+        &self,
+        path: &Path,
+        last_accessed: Option<__wasi_timestamp_t>,
+        last_modified: Option<__wasi_timestamp_t>,
+    ) -> Result<(), WasiFsError> {
+        let set = |times: &mut Times| {
+            if let Some(accessed) = last_accessed {
+                times.accessed = accessed;
+            }
+            if let Some(modified) = last_modified {
+                times.modified = modified;
+            }
+        };
+
+        let mut root = self.root();
+        let (parent, name) = match parent_and_name(path) {
+            Ok(parent_and_name) => parent_and_name,
+            Err(_) => {
+                set(&mut root.times);
+                return Ok(());
+            }
+        };
+        match root.dir_mut(&parent)?.entries.get_mut(&name) {
+            Some(Node::File(data)) => set(&mut data.lock().unwrap().times),
+            Some(Node::Dir(dir)) => set(&mut dir.times),
+            None => return Err(WasiFsError::EntityNotFound),
+        }
+
+        Ok(())
+    }
+
+    fn open(&self, path: &Path, options: &OpenOptions) -> Result<Box<dyn WasiFile>, WasiFsError> {
+        let (parent, name) = parent_and_name(path)?;
+        let mut root = self.root();
+        let dir = root.dir_mut(&parent)?;
+        let data = match dir.entries.get(&name) {
+            Some(Node::File(_)) if options.create_new => return Err(WasiFsError::AlreadyExists),
+            Some(Node::File(data)) => {
+                if options.truncate {
+                    let mut data = data.lock().unwrap();
+                    data.contents.clear();
+                    data.times.modified = now();
+                }
+                data.clone()
+            }
+            Some(Node::Dir(_)) => return Err(WasiFsError::NotAFile),
+            None if options.create || options.create_new => {
+                let data = Arc::new(Mutex::new(FileData::default()));
+                dir.entries.insert(name, Node::File(data.clone()));
+                dir.touch();
+                data
+            }
+            None => return Err(WasiFsError::EntityNotFound),
+        };
+
+        Ok(Box::new(MemFile {
+            fs: self.clone(),
+            data,
+            cursor: 0,
+            read: options.read,
+            write: options.write || options.append,
+            append: options.append,
+        }))
+    }
+}
+
+/// An open file of a [`MemFileSystem`].
+///
+/// When a [`WasiState`] is frozen, the contents of open files are saved
+/// along with the handle; after thawing, the handle no longer shares them
+/// with the file system.
+///
+/// [`WasiState`]: super::WasiState
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemFile {
+    #[serde(skip)]
+    fs: MemFileSystem,
+    data: Arc<Mutex<FileData>>,
+    cursor: u64,
+    read: bool,
+    write: bool,
+    append: bool,
+}
+
+impl MemFile {
+    fn data(&self) -> MutexGuard<FileData> {
+        self.data.lock().unwrap()
+    }
+
+    fn path(&self) -> Result<Vec<String>, WasiFsError> {
+        self.fs
+            .root()
+            .find_file(&self.data)
+            .ok_or(WasiFsError::EntityNotFound)
+    }
+}
+
+impl Read for MemFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.read {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "file was not opened for reading",
+            ));
+        }
+        let mut data = self.data();
+        let start = std::cmp::min(self.cursor as usize, data.contents.len());
+        let amt = std::cmp::min(buf.len(), data.contents.len() - start);
+        buf[..amt].copy_from_slice(&data.contents[start..start + amt]);
+        data.times.accessed = now();
+        drop(data);
+        self.cursor += amt as u64;
+        Ok(amt)
+    }
+}
+
+impl Write for MemFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.write {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "file was not opened for writing",
+            ));
+        }
+        let mut data = self.data();
+        let start = if self.append {
+            data.contents.len()
+        } else {
+            self.cursor as usize
+        };
+        let end = start + buf.len();
+        if data.contents.len() < end {
+            data.contents.resize(end, 0);
+        }
+        data.contents[start..end].copy_from_slice(buf);
+        data.times.modified = now();
+        drop(data);
+        self.cursor = end as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemFile {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            io::SeekFrom::Start(offset) => {
+                self.cursor = offset;
+                return Ok(offset);
+            }
+            io::SeekFrom::End(offset) => (self.size(), offset),
+            io::SeekFrom::Current(offset) => (self.cursor, offset),
+        };
+        let new_cursor = if offset >= 0 {
+            base.checked_add(offset as u64)
+        } else {
+            base.checked_sub(offset.wrapping_neg() as u64)
+        };
+        match new_cursor {
+            Some(cursor) => {
+                self.cursor = cursor;
+                Ok(cursor)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+#[typetag::serde]
+impl WasiFile for MemFile {
+    fn last_accessed(&self) -> __wasi_timestamp_t {
+        self.data().times.accessed
+    }
+
+    fn set_last_accessed(&self, last_accessed: __wasi_timestamp_t) {
+        self.data().times.accessed = last_accessed;
+    }
+
+    fn last_modified(&self) -> __wasi_timestamp_t {
+        self.data().times.modified
+    }
+
+    fn set_last_modified(&self, last_modified: __wasi_timestamp_t) {
+        self.data().times.modified = last_modified;
+    }
+
+    fn created_time(&self) -> __wasi_timestamp_t {
+        self.data().times.created
+    }
+
+    fn set_created_time(&self, created_time: __wasi_timestamp_t) {
+        self.data().times.created = created_time;
+    }
+
+    fn size(&self) -> u64 {
+        self.data().contents.len() as u64
+    }
+
+    fn set_len(&mut self, new_size: __wasi_filesize_t) -> Result<(), WasiFsError> {
+        let mut data = self.data();
+        data.contents.resize(new_size as usize, 0);
+        data.times.modified = now();
+        Ok(())
+    }
+
+    fn unlink(&mut self) -> Result<(), WasiFsError> {
+        let path: PathBuf = self.path()?.iter().collect();
+        self.fs.remove_file(&path)
+    }
+
+    fn rename_file(&self, new_name: &Path) -> Result<(), WasiFsError> {
+        self.fs.rename_components(&self.path()?, new_name)
+    }
+
+    fn bytes_available(&self) -> Result<usize, WasiFsError> {
+        Ok(self
+            .data()
+            .contents
+            .len()
+            .saturating_sub(self.cursor as usize))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn open(fs: &MemFileSystem, path: &str, options: &OpenOptions) -> Box<dyn WasiFile> {
+        fs.open(Path::new(path), options).unwrap()
+    }
+
+    fn read_dir(fs: &MemFileSystem, path: &str) -> Vec<String> {
+        fs.read_dir(Path::new(path))
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect()
+    }
+
+    #[test]
+    fn files_and_directories() {
+        let fs = MemFileSystem::new();
+        fs.create_dir(Path::new("/a")).unwrap();
+        fs.create_dir(Path::new("/a/b")).unwrap();
+        assert_eq!(
+            fs.create_dir(Path::new("/a")),
+            Err(WasiFsError::AlreadyExists)
+        );
+        assert_eq!(
+            fs.create_dir(Path::new("/missing/b")),
+            Err(WasiFsError::EntityNotFound)
+        );
+
+        open(&fs, "/a/file", OpenOptions::new().write(true).create(true))
+            .write_all(b"hello world")
+            .unwrap();
+        assert_eq!(read_dir(&fs, "/a"), vec!["b", "file"]);
+
+        let mut contents = String::new();
+        open(&fs, "/a/file", OpenOptions::new().read(true))
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "hello world");
+
+        let stat = fs.metadata(Path::new("/a/file")).unwrap();
+        assert_eq!(stat.st_filetype, __WASI_FILETYPE_REGULAR_FILE);
+        assert_eq!(stat.st_size, 11);
+        let stat = fs.metadata(Path::new("/a/b/..")).unwrap();
+        assert_eq!(stat.st_filetype, __WASI_FILETYPE_DIRECTORY);
+
+        assert_eq!(
+            fs.remove_dir(Path::new("/a")),
+            Err(WasiFsError::DirectoryNotEmpty)
+        );
+        fs.remove_dir(Path::new("/a/b")).unwrap();
+        fs.remove_file(Path::new("/a/file")).unwrap();
+        fs.remove_dir(Path::new("/a")).unwrap();
+        assert!(read_dir(&fs, "/").is_empty());
+    }
+
+    #[test]
+    fn open_options() {
+        let fs = MemFileSystem::new();
+        assert_eq!(
+            fs.open(Path::new("/file"), OpenOptions::new().read(true))
+                .unwrap_err(),
+            WasiFsError::EntityNotFound
+        );
+
+        let mut file = open(
+            &fs,
+            "/file",
+            OpenOptions::new().write(true).create_new(true),
+        );
+        file.write_all(b"0123456789").unwrap();
+        assert_eq!(
+            fs.open(Path::new("/file"), OpenOptions::new().create_new(true))
+                .unwrap_err(),
+            WasiFsError::AlreadyExists
+        );
+
+        let mut file = open(&fs, "/file", OpenOptions::new().append(true));
+        file.seek(io::SeekFrom::Start(0)).unwrap();
+        file.write_all(b"ab").unwrap();
+        assert_eq!(file.size(), 12);
+
+        let mut file = open(&fs, "/file", OpenOptions::new().write(true).truncate(true));
+        assert_eq!(file.size(), 0);
+        file.seek(io::SeekFrom::Start(2)).unwrap();
+        file.write_all(b"x").unwrap();
+
+        let mut contents = vec![];
+        open(&fs, "/file", OpenOptions::new().read(true))
+            .read_to_end(&mut contents)
+            .unwrap();
+        assert_eq!(contents, b"\0\0x");
+    }
+
+    #[test]
+    fn rename_and_unlink() {
+        let fs = MemFileSystem::new();
+        fs.create_dir(Path::new("/a")).unwrap();
+        fs.create_dir(Path::new("/b")).unwrap();
+        let mut file = open(
+            &fs,
+            "/a/file",
+            OpenOptions::new().read(true).write(true).create(true),
+        );
+
+        fs.rename(Path::new("/a"), Path::new("/b/c")).unwrap();
+        assert_eq!(read_dir(&fs, "/b/c"), vec!["file"]);
+        assert_eq!(
+            fs.rename(Path::new("/b"), Path::new("/b/c/d")),
+            Err(WasiFsError::InvalidInput)
+        );
+
+        // open handles follow their file around
+        file.rename_file(Path::new("/renamed")).unwrap();
+        assert_eq!(read_dir(&fs, "/"), vec!["b", "renamed"]);
+        assert!(read_dir(&fs, "/b/c").is_empty());
+
+        // and keep working after they have been unlinked
+        file.unlink().unwrap();
+        assert_eq!(read_dir(&fs, "/"), vec!["b"]);
+        file.write_all(b"still here").unwrap();
+        assert_eq!(file.size(), 10);
+    }
+
+    #[test]
+    fn rename_onto_existing_entries() {
+        let fs = MemFileSystem::new();
+        fs.create_dir(Path::new("/dir")).unwrap();
+        fs.create_dir(Path::new("/empty")).unwrap();
+        fs.create_dir(Path::new("/full")).unwrap();
+        fs.create_dir(Path::new("/full/child")).unwrap();
+        open(&fs, "/file", OpenOptions::new().write(true).create(true));
+        open(&fs, "/other", OpenOptions::new().write(true).create(true));
+
+        // an existing target must be of the same kind as the source
+        assert_eq!(
+            fs.rename(Path::new("/dir"), Path::new("/file")),
+            Err(WasiFsError::BaseNotDirectory)
+        );
+        assert_eq!(
+            fs.rename(Path::new("/file"), Path::new("/dir")),
+            Err(WasiFsError::IsADirectory)
+        );
+        assert_eq!(
+            fs.rename(Path::new("/dir"), Path::new("/full")),
+            Err(WasiFsError::DirectoryNotEmpty)
+        );
+        assert_eq!(
+            read_dir(&fs, "/"),
+            vec!["dir", "empty", "file", "full", "other"]
+        );
+
+        // and is replaced if it's a file or an empty directory
+        fs.rename(Path::new("/file"), Path::new("/other")).unwrap();
+        fs.rename(Path::new("/dir"), Path::new("/empty")).unwrap();
+        fs.rename(Path::new("/full"), Path::new("/full")).unwrap();
+        assert_eq!(read_dir(&fs, "/"), vec!["empty", "full", "other"]);
+    }
+
+    #[test]
+    fn timestamps() {
+        let fs = MemFileSystem::new();
+        let mut file = open(&fs, "/file", OpenOptions::new().write(true).create(true));
+        file.set_last_modified(1);
+        assert_eq!(fs.metadata(Path::new("/file")).unwrap().st_mtim, 1);
+        file.write_all(b"data").unwrap();
+        assert!(fs.metadata(Path::new("/file")).unwrap().st_mtim > 1);
+
+        fs.set_times(Path::new("/file"), Some(10), Some(20))
+            .unwrap();
+        assert_eq!(file.last_accessed(), 10);
+        assert_eq!(file.last_modified(), 20);
+
+        fs.set_times(Path::new("/"), None, Some(30)).unwrap();
+        let stat = fs.metadata(Path::new("/")).unwrap();
+        assert_eq!(stat.st_filetype, __WASI_FILETYPE_DIRECTORY);
+        assert_eq!(stat.st_mtim, 30);
+    }
+
+    #[test]
+    fn freeze_and_thaw() {
+        let fs = MemFileSystem::new();
+        fs.create_dir(Path::new("/dir")).unwrap();
+        open(
+            &fs,
+            "/dir/file",
+            OpenOptions::new().write(true).create(true),
+        )
+        .write_all(b"frozen")
+        .unwrap();
+
+        let bytes = bincode::serialize(&(Box::new(fs) as Box<dyn FileSystem>)).unwrap();
+        let fs: Box<dyn FileSystem> = bincode::deserialize(&bytes).unwrap();
+        let mut contents = String::new();
+        fs.open(Path::new("/dir/file"), OpenOptions::new().read(true))
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "frozen");
+    }
+}
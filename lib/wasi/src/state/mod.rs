@@ -16,9 +16,15 @@
 #![allow(clippy::cognitive_complexity, clippy::too_many_arguments)]
 
 mod builder;
+mod filesystem;
+mod mem_fs;
+mod socket;
 mod types;
 
 pub use self::builder::*;
+pub use self::filesystem::*;
+pub use self::mem_fs::*;
+pub use self::socket::*;
 pub use self::types::*;
 use crate::syscalls::types::*;
 use generational_arena::Arena;
@@ -31,7 +37,6 @@ use std::{
     fs,
     io::Write,
     path::{Path, PathBuf},
-    time::SystemTime,
 };
 use tracing::debug;
 
@@ -52,6 +57,12 @@ const STDOUT_DEFAULT_RIGHTS: __wasi_rights_t = __WASI_RIGHT_FD_DATASYNC
     | __WASI_RIGHT_FD_FILESTAT_GET
     | __WASI_RIGHT_POLL_FD_READWRITE;
 const STDERR_DEFAULT_RIGHTS: __wasi_rights_t = STDOUT_DEFAULT_RIGHTS;
+const SOCKET_DEFAULT_RIGHTS: __wasi_rights_t = __WASI_RIGHT_FD_READ
+    | __WASI_RIGHT_FD_WRITE
+    | __WASI_RIGHT_FD_FDSTAT_SET_FLAGS
+    | __WASI_RIGHT_FD_FILESTAT_GET
+    | __WASI_RIGHT_POLL_FD_READWRITE
+    | __WASI_RIGHT_SOCK_SHUTDOWN;
 
 /// A completely aribtrary "big enough" number used as the upper limit for
 /// the number of symlinks that can be traversed when resolving a path
@@ -73,8 +84,7 @@ pub enum Kind {
     File {
         /// the open file, if it's open
         handle: Option<Box<dyn WasiFile>>,
-        /// The path on the host system where the file is located
-        /// This is deprecated and will be removed soon
+        /// The path of the file in the [`FileSystem`] backing the [`WasiFs`]
         path: PathBuf,
         /// Marks the file as a special file that only one `fd` can exist for
         /// This is useful when dealing with host-provided special files that
@@ -85,8 +95,7 @@ pub enum Kind {
     Dir {
         /// Parent directory
         parent: Option<Inode>,
-        /// The path on the host system where the directory is located
-        // TODO: wrap it like WasiFile
+        /// The path of the directory in the [`FileSystem`] backing the [`WasiFs`]
         path: PathBuf,
         /// The entries of a directory are lazily filled.
         entries: HashMap<String, Inode>,
@@ -115,6 +124,10 @@ pub enum Kind {
     Buffer {
         buffer: Vec<u8>,
     },
+    /// A connected socket handed to the WASI module by the host
+    Socket {
+        socket: Box<dyn WasiSocket>,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -163,6 +176,8 @@ pub struct WasiFs {
     inode_counter: Cell<u64>,
     /// for fds still open after the file has been deleted
     pub orphan_fds: HashMap<Inode, InodeVal>,
+    /// The file system that files and directories are looked up in
+    pub fs_backend: Box<dyn FileSystem>,
 }
 
 impl WasiFs {
@@ -176,21 +191,21 @@ impl WasiFs {
         preopened_dirs: &[PathBuf],
         mapped_dirs: &[(String, PathBuf)],
     ) -> Result<Self, String> {
-        let (mut wasi_fs, root_inode) = Self::new_init()?;
+        let (mut wasi_fs, root_inode) = Self::new_init(Box::new(HostFileSystem))?;
 
         debug!("wasi::fs::preopen_dirs");
         for dir in preopened_dirs {
             debug!("Attempting to preopen {}", &dir.to_string_lossy());
             // TODO: think about this
             let default_rights = ALL_RIGHTS;
-            let cur_dir_metadata = dir.metadata().map_err(|e| {
+            let cur_dir_metadata = wasi_fs.fs_backend.metadata(dir).map_err(|e| {
                 format!(
                     "Could not get metadata for file {:?}: {}",
                     dir,
                     e.to_string()
                 )
             })?;
-            let kind = if cur_dir_metadata.is_dir() {
+            let kind = if cur_dir_metadata.st_filetype == __WASI_FILETYPE_DIRECTORY {
                 Kind::Dir {
                     parent: Some(root_inode),
                     path: dir.clone(),
@@ -236,14 +251,14 @@ impl WasiFs {
             debug!("Attempting to open {:?} at {}", real_dir, alias);
             // TODO: think about this
             let default_rights = ALL_RIGHTS;
-            let cur_dir_metadata = real_dir.metadata().map_err(|e| {
+            let cur_dir_metadata = wasi_fs.fs_backend.metadata(real_dir).map_err(|e| {
                 format!(
                     "Could not get metadata for file {:?}: {}",
                     &real_dir,
                     e.to_string()
                 )
             })?;
-            let kind = if cur_dir_metadata.is_dir() {
+            let kind = if cur_dir_metadata.st_filetype == __WASI_FILETYPE_DIRECTORY {
                 Kind::Dir {
                     parent: Some(root_inode),
                     path: real_dir.clone(),
@@ -290,8 +305,11 @@ impl WasiFs {
     }
 
     /// Created for the builder API. like `new` but with more information
-    pub(crate) fn new_with_preopen(preopens: &[PreopenedDir]) -> Result<Self, String> {
-        let (mut wasi_fs, root_inode) = Self::new_init()?;
+    pub(crate) fn new_with_preopen(
+        preopens: &[PreopenedDir],
+        fs_backend: Box<dyn FileSystem>,
+    ) -> Result<Self, String> {
+        let (mut wasi_fs, root_inode) = Self::new_init(fs_backend)?;
 
         for PreopenedDir {
             path,
@@ -306,7 +324,7 @@ impl WasiFs {
                 &path.to_string_lossy(),
                 &alias
             );
-            let cur_dir_metadata = path.metadata().map_err(|e| {
+            let cur_dir_metadata = wasi_fs.fs_backend.metadata(path).map_err(|e| {
                 format!(
                     "Could not get metadata for file {:?}: {}",
                     path,
@@ -314,7 +332,7 @@ impl WasiFs {
                 )
             })?;
 
-            let kind = if cur_dir_metadata.is_dir() {
+            let kind = if cur_dir_metadata.st_filetype == __WASI_FILETYPE_DIRECTORY {
                 Kind::Dir {
                     parent: Some(root_inode),
                     path: path.clone(),
@@ -417,7 +435,7 @@ impl WasiFs {
 
     /// Private helper function to init the filesystem, called in `new` and
     /// `new_with_preopen`
-    fn new_init() -> Result<(Self, Inode), String> {
+    fn new_init(fs_backend: Box<dyn FileSystem>) -> Result<(Self, Inode), String> {
         debug!("Initializing WASI filesystem");
         let inodes = Arena::new();
         let mut wasi_fs = Self {
@@ -428,6 +446,7 @@ impl WasiFs {
             next_fd: Cell::new(3),
             inode_counter: Cell::new(1024),
             orphan_fds: HashMap::new(),
+            fs_backend,
         };
         wasi_fs.create_stdin();
         wasi_fs.create_stdout();
@@ -780,13 +799,17 @@ impl WasiFs {
                                 cd.push(component);
                                 cd
                             };
-                            let metadata = file.symlink_metadata().ok().ok_or(__WASI_EINVAL)?;
-                            let file_type = metadata.file_type();
+                            let metadata = self
+                                .fs_backend
+                                .symlink_metadata(&file)
+                                .ok()
+                                .ok_or(__WASI_EINVAL)?;
+                            let file_type = metadata.st_filetype;
                             // we want to insert newly opened dirs and files, but not transient symlinks
                             // TODO: explain why (think about this deeply when well rested)
                             let mut should_insert = false;
 
-                            let kind = if file_type.is_dir() {
+                            let kind = if file_type == __WASI_FILETYPE_DIRECTORY {
                                 should_insert = true;
                                 // load DIR
                                 Kind::Dir {
@@ -794,7 +817,7 @@ impl WasiFs {
                                     path: file.clone(),
                                     entries: Default::default(),
                                 }
-                            } else if file_type.is_file() {
+                            } else if file_type == __WASI_FILETYPE_REGULAR_FILE {
                                 should_insert = true;
                                 // load file
                                 Kind::File {
@@ -802,8 +825,9 @@ impl WasiFs {
                                     path: file.clone(),
                                     fd: None,
                                 }
-                            } else if file_type.is_symlink() {
-                                let link_value = file.read_link().ok().ok_or(__WASI_EIO)?;
+                            } else if file_type == __WASI_FILETYPE_SYMBOLIC_LINK {
+                                let link_value =
+                                    self.fs_backend.read_link(&file).ok().ok_or(__WASI_EIO)?;
                                 debug!("attempting to decompose path {:?}", link_value);
 
                                 let (pre_open_dir_fd, relative_path) = if link_value.is_relative() {
@@ -819,57 +843,36 @@ impl WasiFs {
                                     relative_path: link_value,
                                 }
                             } else {
-                                #[cfg(unix)]
+                                // devices, sockets, etc.
+                                let kind = Kind::File {
+                                    handle: None,
+                                    path: file.clone(),
+                                    fd: None,
+                                };
+                                let new_inode = self.create_inode_with_stat(
+                                    kind,
+                                    false,
+                                    file.to_string_lossy().to_string(),
+                                    __wasi_filestat_t {
+                                        st_filetype: file_type,
+                                        ..__wasi_filestat_t::default()
+                                    },
+                                );
+                                if let Kind::Dir {
+                                    ref mut entries, ..
+                                } = &mut self.inodes[cur_inode].kind
                                 {
-                                    use std::os::unix::fs::FileTypeExt;
-                                    let file_type: __wasi_filetype_t = if file_type.is_char_device()
-                                    {
-                                        __WASI_FILETYPE_CHARACTER_DEVICE
-                                    } else if file_type.is_block_device() {
-                                        __WASI_FILETYPE_BLOCK_DEVICE
-                                    } else if file_type.is_fifo() {
-                                        // FIFO doesn't seem to fit any other type, so unknown
-                                        __WASI_FILETYPE_UNKNOWN
-                                    } else if file_type.is_socket() {
-                                        // TODO: how do we know if it's a `__WASI_FILETYPE_SOCKET_STREAM` or
-                                        // a `__WASI_FILETYPE_SOCKET_DGRAM`?
-                                        __WASI_FILETYPE_SOCKET_STREAM
-                                    } else {
-                                        unimplemented!("state::get_inode_at_path unknown file type: not file, directory, symlink, char device, block device, fifo, or socket");
-                                    };
-
-                                    let kind = Kind::File {
-                                        handle: None,
-                                        path: file.clone(),
-                                        fd: None,
-                                    };
-                                    let new_inode = self.create_inode_with_stat(
-                                        kind,
-                                        false,
-                                        file.to_string_lossy().to_string(),
-                                        __wasi_filestat_t {
-                                            st_filetype: file_type,
-                                            ..__wasi_filestat_t::default()
-                                        },
+                                    entries.insert(
+                                        component.as_os_str().to_string_lossy().to_string(),
+                                        new_inode,
+                                    );
+                                } else {
+                                    unreachable!(
+                                        "Attempted to insert special device into non-directory"
                                     );
-                                    if let Kind::Dir {
-                                        ref mut entries, ..
-                                    } = &mut self.inodes[cur_inode].kind
-                                    {
-                                        entries.insert(
-                                            component.as_os_str().to_string_lossy().to_string(),
-                                            new_inode,
-                                        );
-                                    } else {
-                                        unreachable!(
-                                            "Attempted to insert special device into non-directory"
-                                        );
-                                    }
-                                    // perhaps just continue with symlink resolution and return at the end
-                                    return Ok(new_inode);
                                 }
-                                #[cfg(not(unix))]
-                                unimplemented!("state::get_inode_at_path unknown file type: not file, directory, or symlink");
+                                // perhaps just continue with symlink resolution and return at the end
+                                return Ok(new_inode);
                             };
 
                             let new_inode =
@@ -910,7 +913,7 @@ impl WasiFs {
                             return Err(__WASI_EINVAL);
                         }
                     }
-                    Kind::File { .. } => {
+                    Kind::File { .. } | Kind::Socket { .. } => {
                         return Err(__WASI_ENOTDIR);
                     }
                     Kind::Symlink {
@@ -1085,6 +1088,28 @@ impl WasiFs {
             .map(|v| (v, new_entity_name))
     }
 
+    /// Updates the paths of `inode` and of everything loaded beneath it
+    /// after it was moved to `new_path` in the backing [`FileSystem`]
+    pub(crate) fn rebase_inode_path(&mut self, inode: Inode, new_path: PathBuf) {
+        let children: Vec<(String, Inode)> = match &mut self.inodes[inode].kind {
+            Kind::Dir { path, entries, .. } => {
+                *path = new_path.clone();
+                entries
+                    .iter()
+                    .map(|(name, inode)| (name.clone(), *inode))
+                    .collect()
+            }
+            Kind::File { path, .. } => {
+                *path = new_path.clone();
+                vec![]
+            }
+            _ => vec![],
+        };
+        for (name, child) in children {
+            self.rebase_inode_path(child, new_path.join(name));
+        }
+    }
+
     pub fn get_fd(&self, fd: __wasi_fd_t) -> Result<&Fd, __wasi_errno_t> {
         self.fd_map.get(&fd).ok_or(__WASI_EBADF)
     }
@@ -1151,6 +1176,7 @@ impl WasiFs {
                 Kind::File { .. } => __WASI_FILETYPE_REGULAR_FILE,
                 Kind::Dir { .. } => __WASI_FILETYPE_DIRECTORY,
                 Kind::Symlink { .. } => __WASI_FILETYPE_SYMBOLIC_LINK,
+                Kind::Socket { .. } => __WASI_FILETYPE_SOCKET_STREAM,
                 _ => __WASI_FILETYPE_UNKNOWN,
             },
             fs_flags: fd.flags,
@@ -1213,7 +1239,7 @@ impl WasiFs {
                     // TODO: verify this behavior
                     Kind::Dir { .. } => return Err(__WASI_EISDIR),
                     Kind::Symlink { .. } => unimplemented!("WasiFs::flush Kind::Symlink"),
-                    Kind::Buffer { .. } => (),
+                    Kind::Buffer { .. } | Kind::Socket { .. } => (),
                     _ => return Err(__WASI_EIO),
                 }
             }
@@ -1285,6 +1311,19 @@ impl WasiFs {
         Ok(idx)
     }
 
+    /// Gives the WASI module a connected socket, returning the fd the
+    /// module can use it through.
+    pub fn open_socket(&mut self, socket: Box<dyn WasiSocket>) -> Result<__wasi_fd_t, WasiFsError> {
+        let stat = __wasi_filestat_t {
+            st_filetype: __WASI_FILETYPE_SOCKET_STREAM,
+            ..__wasi_filestat_t::default()
+        };
+        let inode =
+            self.create_inode_with_stat(Kind::Socket { socket }, false, "socket".to_string(), stat);
+        self.create_fd(SOCKET_DEFAULT_RIGHTS, 0, 0, Fd::READ | Fd::WRITE, inode)
+            .map_err(WasiFsError::from_wasi_err)
+    }
+
     /// Low level function to remove an inode, that is it deletes the WASI FS's
     /// knowledge of a file.
     ///
@@ -1382,7 +1421,7 @@ impl WasiFs {
     }
 
     pub fn get_stat_for_kind(&self, kind: &Kind) -> Option<__wasi_filestat_t> {
-        let md = match kind {
+        let stat = match kind {
             Kind::File { handle, path, .. } => match handle {
                 Some(wf) => {
                     return Some(__wasi_filestat_t {
@@ -1395,9 +1434,9 @@ impl WasiFs {
                         ..__wasi_filestat_t::default()
                     })
                 }
-                None => path.metadata().ok()?,
+                None => self.fs_backend.metadata(path).ok()?,
             },
-            Kind::Dir { path, .. } => path.metadata().ok()?,
+            Kind::Dir { path, .. } => self.fs_backend.metadata(path).ok()?,
             Kind::Symlink {
                 base_po_dir,
                 path_to_symlink,
@@ -1407,7 +1446,7 @@ impl WasiFs {
                 let base_po_inode_v = &self.inodes[*base_po_inode];
                 match &base_po_inode_v.kind {
                     Kind::Root { .. } => {
-                        path_to_symlink.clone().symlink_metadata().ok()?
+                        self.fs_backend.symlink_metadata(path_to_symlink).ok()?
                     }
                     Kind::Dir { path, .. } => {
                         let mut real_path = path.clone();
@@ -1418,7 +1457,7 @@ impl WasiFs {
                         // TODO: adjust size of symlink, too
                         //      for all paths adjusted think about this
                         real_path.push(path_to_symlink);
-                        real_path.symlink_metadata().ok()?
+                        self.fs_backend.symlink_metadata(&real_path).ok()?
                     }
                     // if this triggers, there's a bug in the symlink code
                     _ => unreachable!("Symlink pointing to something that's not a directory as its base preopened directory"),
@@ -1426,29 +1465,7 @@ impl WasiFs {
             }
             _ => return None,
         };
-        Some(__wasi_filestat_t {
-            st_filetype: host_file_type_to_wasi_file_type(md.file_type()),
-            st_size: md.len(),
-            st_atim: md
-                .accessed()
-                .ok()?
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .ok()?
-                .as_nanos() as u64,
-            st_mtim: md
-                .modified()
-                .ok()?
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .ok()?
-                .as_nanos() as u64,
-            st_ctim: md
-                .created()
-                .ok()
-                .and_then(|ct| ct.duration_since(SystemTime::UNIX_EPOCH).ok())
-                .map(|ct| ct.as_nanos() as u64)
-                .unwrap_or(0),
-            ..__wasi_filestat_t::default()
-        })
+        Some(stat)
     }
 
     /// Closes an open FD, handling all details such as FD being preopen
@@ -1501,6 +1518,11 @@ impl WasiFs {
             }
             Kind::Root { .. } => return Err(__WASI_EACCES),
             Kind::Symlink { .. } | Kind::Buffer { .. } => return Err(__WASI_EINVAL),
+            Kind::Socket { .. } => {
+                // dropping the socket closes it
+                let inode = self.fd_map.remove(&fd).ok_or(__WASI_EBADF)?.inode;
+                self.inodes.remove(inode);
+            }
         }
 
         Ok(())
@@ -1570,6 +1592,20 @@ pub fn host_file_type_to_wasi_file_type(file_type: fs::FileType) -> __wasi_filet
     } else if file_type.is_symlink() {
         __WASI_FILETYPE_SYMBOLIC_LINK
     } else {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+            if file_type.is_char_device() {
+                return __WASI_FILETYPE_CHARACTER_DEVICE;
+            } else if file_type.is_block_device() {
+                return __WASI_FILETYPE_BLOCK_DEVICE;
+            } else if file_type.is_socket() {
+                // TODO: how do we know if it's a `__WASI_FILETYPE_SOCKET_STREAM` or
+                // a `__WASI_FILETYPE_SOCKET_DGRAM`?
+                return __WASI_FILETYPE_SOCKET_STREAM;
+            }
+        }
+        // FIFO doesn't seem to fit any other type, so unknown
         __WASI_FILETYPE_UNKNOWN
     }
 }
@@ -0,0 +1,278 @@
+//! Sockets that can be handed to a WASI module.
+//!
+//! WASI can't create or accept connections by itself, the host gives the
+//! module sockets that are already connected, see
+//! [`WasiStateBuilder::preopen_socket`].  The module then uses `sock_recv`,
+//! `sock_send` and `sock_shutdown` (or plainly `fd_read` and `fd_write`) on
+//! the file descriptors of those sockets.
+//!
+//! [`WasiStateBuilder::preopen_socket`]: super::WasiStateBuilder::preopen_socket
+
+use crate::state::WasiFsError;
+use crate::syscalls::types::*;
+use serde::{de, ser, Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::sync::{Arc, Mutex};
+
+/// A connected socket the WASI module can receive data from and send data to.
+#[typetag::serde(tag = "type")]
+pub trait WasiSocket: fmt::Debug + Send + 'static {
+    /// Receives data from the socket into `buf`, returning the number of
+    /// bytes received.  `0` means that the peer won't send any more data.
+    fn recv(&mut self, buf: &mut [u8]) -> Result<usize, WasiFsError>;
+
+    /// Like [`WasiSocket::recv`], but leaves the data in the socket so that
+    /// the next call to `recv` receives it again.
+    fn peek(&mut self, _buf: &mut [u8]) -> Result<usize, WasiFsError> {
+        Err(WasiFsError::NotSupported)
+    }
+
+    /// Sends the data in `buf`, returning the number of bytes sent.
+    fn send(&mut self, buf: &[u8]) -> Result<usize, WasiFsError>;
+
+    /// Shuts down the receiving half, the sending half, or both halves of
+    /// the socket, depending on `how`.
+    fn shutdown(&mut self, how: __wasi_sdflags_t) -> Result<(), WasiFsError>;
+}
+
+fn sdflags_to_shutdown(how: __wasi_sdflags_t) -> Result<Shutdown, WasiFsError> {
+    match how {
+        __WASI_SHUT_RD => Ok(Shutdown::Read),
+        __WASI_SHUT_WR => Ok(Shutdown::Write),
+        _ if how == __WASI_SHUT_RD | __WASI_SHUT_WR => Ok(Shutdown::Both),
+        _ => Err(WasiFsError::InvalidInput),
+    }
+}
+
+/// A socket of the host, for example a TCP stream accepted by the host.
+///
+/// Host sockets can't be serialized, freezing a [`WasiState`] that contains
+/// one fails.
+///
+/// [`WasiState`]: super::WasiState
+#[derive(Debug)]
+pub enum HostSocket {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl From<TcpStream> for HostSocket {
+    fn from(stream: TcpStream) -> Self {
+        HostSocket::Tcp(stream)
+    }
+}
+
+#[cfg(unix)]
+impl From<UnixStream> for HostSocket {
+    fn from(stream: UnixStream) -> Self {
+        HostSocket::Unix(stream)
+    }
+}
+
+impl Serialize for HostSocket {
+    fn serialize<S>(&self, _serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        Err(ser::Error::custom("host sockets can not be serialized"))
+    }
+}
+
+impl<'de> Deserialize<'de> for HostSocket {
+    fn deserialize<D>(_deserializer: D) -> Result<HostSocket, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Err(de::Error::custom("host sockets can not be deserialized"))
+    }
+}
+
+#[typetag::serde]
+impl WasiSocket for HostSocket {
+    fn recv(&mut self, buf: &mut [u8]) -> Result<usize, WasiFsError> {
+        match self {
+            HostSocket::Tcp(stream) => stream.read(buf).map_err(Into::into),
+            #[cfg(unix)]
+            HostSocket::Unix(stream) => stream.read(buf).map_err(Into::into),
+        }
+    }
+
+    fn peek(&mut self, buf: &mut [u8]) -> Result<usize, WasiFsError> {
+        match self {
+            HostSocket::Tcp(stream) => stream.peek(buf).map_err(Into::into),
+            // TODO: use `UnixStream::peek` once it's stable
+            #[cfg(unix)]
+            HostSocket::Unix(_) => Err(WasiFsError::NotSupported),
+        }
+    }
+
+    fn send(&mut self, buf: &[u8]) -> Result<usize, WasiFsError> {
+        match self {
+            HostSocket::Tcp(stream) => stream.write(buf).map_err(Into::into),
+            #[cfg(unix)]
+            HostSocket::Unix(stream) => stream.write(buf).map_err(Into::into),
+        }
+    }
+
+    fn shutdown(&mut self, how: __wasi_sdflags_t) -> Result<(), WasiFsError> {
+        let how = sdflags_to_shutdown(how)?;
+        match self {
+            HostSocket::Tcp(stream) => stream.shutdown(how).map_err(Into::into),
+            #[cfg(unix)]
+            HostSocket::Unix(stream) => stream.shutdown(how).map_err(Into::into),
+        }
+    }
+}
+
+/// One direction of a [`LoopbackSocket`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Channel {
+    data: VecDeque<u8>,
+    /// No more data will be written to the channel
+    closed: bool,
+}
+
+/// One end of a pair of in-memory sockets, what is sent on one end is
+/// received on the other.
+///
+/// Receiving never blocks: if the peer hasn't sent anything yet `recv`
+/// fails with [`WasiFsError::WouldBlock`].
+///
+/// ```
+/// use wasmer_wasi::{LoopbackSocket, WasiSocket};
+///
+/// let (mut host, mut guest) = LoopbackSocket::pair();
+/// host.send(b"ping").unwrap();
+///
+/// let mut buf = [0; 4];
+/// assert_eq!(guest.recv(&mut buf).unwrap(), 4);
+/// assert_eq!(&buf, b"ping");
+/// ```
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoopbackSocket {
+    incoming: Arc<Mutex<Channel>>,
+    outgoing: Arc<Mutex<Channel>>,
+}
+
+impl LoopbackSocket {
+    /// Creates two connected sockets.
+    pub fn pair() -> (LoopbackSocket, LoopbackSocket) {
+        let a_to_b = Arc::new(Mutex::new(Channel::default()));
+        let b_to_a = Arc::new(Mutex::new(Channel::default()));
+        (
+            LoopbackSocket {
+                incoming: b_to_a.clone(),
+                outgoing: a_to_b.clone(),
+            },
+            LoopbackSocket {
+                incoming: a_to_b,
+                outgoing: b_to_a,
+            },
+        )
+    }
+
+    fn read_incoming(&mut self, buf: &mut [u8], consume: bool) -> Result<usize, WasiFsError> {
+        let mut incoming = self.incoming.lock().unwrap();
+        if incoming.data.is_empty() {
+            return if incoming.closed {
+                Ok(0)
+            } else {
+                Err(WasiFsError::WouldBlock)
+            };
+        }
+        let amt = buf.len().min(incoming.data.len());
+        for (dst, src) in buf.iter_mut().zip(incoming.data.iter()) {
+            *dst = *src;
+        }
+        if consume {
+            incoming.data.drain(..amt);
+        }
+        Ok(amt)
+    }
+}
+
+#[typetag::serde]
+impl WasiSocket for LoopbackSocket {
+    fn recv(&mut self, buf: &mut [u8]) -> Result<usize, WasiFsError> {
+        self.read_incoming(buf, true)
+    }
+
+    fn peek(&mut self, buf: &mut [u8]) -> Result<usize, WasiFsError> {
+        self.read_incoming(buf, false)
+    }
+
+    fn send(&mut self, buf: &[u8]) -> Result<usize, WasiFsError> {
+        let mut outgoing = self.outgoing.lock().unwrap();
+        if outgoing.closed {
+            return Err(WasiFsError::BrokenPipe);
+        }
+        outgoing.data.extend(buf);
+        Ok(buf.len())
+    }
+
+    fn shutdown(&mut self, how: __wasi_sdflags_t) -> Result<(), WasiFsError> {
+        let how = sdflags_to_shutdown(how)?;
+        if how != Shutdown::Write {
+            let mut incoming = self.incoming.lock().unwrap();
+            incoming.data.clear();
+            incoming.closed = true;
+        }
+        if how != Shutdown::Read {
+            self.outgoing.lock().unwrap().closed = true;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for LoopbackSocket {
+    fn drop(&mut self) {
+        // let the peer know that nothing more will be sent
+        if let Ok(mut outgoing) = self.outgoing.lock() {
+            outgoing.closed = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn loopback_send_recv_and_peek() {
+        let (mut a, mut b) = LoopbackSocket::pair();
+        let mut buf = [0; 8];
+        assert_eq!(b.recv(&mut buf), Err(WasiFsError::WouldBlock));
+
+        assert_eq!(a.send(b"hello").unwrap(), 5);
+        assert_eq!(b.peek(&mut buf[..2]).unwrap(), 2);
+        assert_eq!(&buf[..2], b"he");
+        assert_eq!(b.recv(&mut buf).unwrap(), 5);
+        assert_eq!(&buf[..5], b"hello");
+
+        assert_eq!(b.send(b"world").unwrap(), 5);
+        assert_eq!(a.recv(&mut buf).unwrap(), 5);
+        assert_eq!(&buf[..5], b"world");
+    }
+
+    #[test]
+    fn loopback_shutdown() {
+        let (mut a, mut b) = LoopbackSocket::pair();
+        a.send(b"bye").unwrap();
+        a.shutdown(__WASI_SHUT_WR).unwrap();
+        assert_eq!(a.send(b"more"), Err(WasiFsError::BrokenPipe));
+
+        let mut buf = [0; 8];
+        assert_eq!(b.recv(&mut buf).unwrap(), 3);
+        assert_eq!(b.recv(&mut buf).unwrap(), 0);
+
+        assert_eq!(a.shutdown(0), Err(WasiFsError::InvalidInput));
+        drop(b);
+        assert_eq!(a.recv(&mut buf).unwrap(), 0);
+    }
+}
@@ -1,3 +1,4 @@
+use super::filesystem::to_file_time;
 /// types for use in the WASI filesystem
 use crate::syscalls::types::*;
 use serde::{de, Deserialize, Serialize};
@@ -26,6 +27,9 @@ pub enum WasiFsError {
     /// Expected a file but found not a file
     #[error("fd not a file")]
     NotAFile,
+    /// Expected a file or nothing but found a directory
+    #[error("is a directory")]
+    IsADirectory,
     /// The fd given was not usable
     #[error("invalid fd")]
     InvalidFd,
@@ -72,6 +76,12 @@ pub enum WasiFsError {
     /// The requested device couldn't be accessed
     #[error("can't access device")]
     NoDevice,
+    /// The directory is not empty
+    #[error("directory not empty")]
+    DirectoryNotEmpty,
+    /// The operation is not supported by this file or socket
+    #[error("operation not supported")]
+    NotSupported,
     /// Caller was not allowed to perform this operation
     #[error("permission denied")]
     PermissionDenied,
@@ -100,6 +110,7 @@ impl WasiFsError {
             __WASI_EBADF => WasiFsError::InvalidFd,
             __WASI_EEXIST => WasiFsError::AlreadyExists,
             __WASI_EIO => WasiFsError::IOError,
+            __WASI_EISDIR => WasiFsError::IsADirectory,
             __WASI_EADDRINUSE => WasiFsError::AddressInUse,
             __WASI_EADDRNOTAVAIL => WasiFsError::AddressNotAvailable,
             __WASI_EPIPE => WasiFsError::BrokenPipe,
@@ -111,6 +122,8 @@ impl WasiFsError {
             __WASI_ENOTCONN => WasiFsError::NotConnected,
             __WASI_ENODEV => WasiFsError::NoDevice,
             __WASI_ENOENT => WasiFsError::EntityNotFound,
+            __WASI_ENOTEMPTY => WasiFsError::DirectoryNotEmpty,
+            __WASI_ENOTSUP => WasiFsError::NotSupported,
             __WASI_EPERM => WasiFsError::PermissionDenied,
             __WASI_ETIMEDOUT => WasiFsError::TimedOut,
             __WASI_EPROTO => WasiFsError::UnexpectedEof,
@@ -135,10 +148,13 @@ impl WasiFsError {
             WasiFsError::InvalidFd => __WASI_EBADF,
             WasiFsError::InvalidInput => __WASI_EINVAL,
             WasiFsError::IOError => __WASI_EIO,
+            WasiFsError::IsADirectory => __WASI_EISDIR,
             WasiFsError::NoDevice => __WASI_ENODEV,
             WasiFsError::NotAFile => __WASI_EINVAL,
             WasiFsError::NotConnected => __WASI_ENOTCONN,
             WasiFsError::EntityNotFound => __WASI_ENOENT,
+            WasiFsError::DirectoryNotEmpty => __WASI_ENOTEMPTY,
+            WasiFsError::NotSupported => __WASI_ENOTSUP,
             WasiFsError::PermissionDenied => __WASI_EPERM,
             WasiFsError::TimedOut => __WASI_ETIMEDOUT,
             WasiFsError::UnexpectedEof => __WASI_EPROTO,
@@ -575,8 +591,11 @@ impl WasiFile for HostFile {
             .unwrap_or(0)
     }
 
-    fn set_last_accessed(&self, _last_accessed: __wasi_timestamp_t) {
-        // TODO: figure out how to do this
+    fn set_last_accessed(&self, last_accessed: __wasi_timestamp_t) {
+        let last_accessed = to_file_time(last_accessed);
+        if let Err(e) = filetime::set_file_handle_times(&self.inner, Some(last_accessed), None) {
+            debug!("could not set the last accessed time of {:?}: {}", self, e);
+        }
     }
 
     fn last_modified(&self) -> u64 {
@@ -588,8 +607,11 @@ impl WasiFile for HostFile {
             .unwrap_or(0)
     }
 
-    fn set_last_modified(&self, _last_modified: __wasi_timestamp_t) {
-        // TODO: figure out how to do this
+    fn set_last_modified(&self, last_modified: __wasi_timestamp_t) {
+        let last_modified = to_file_time(last_modified);
+        if let Err(e) = filetime::set_file_handle_times(&self.inner, None, Some(last_modified)) {
+            debug!("could not set the last modified time of {:?}: {}", self, e);
+        }
     }
 
     fn created_time(&self) -> u64 {
@@ -646,6 +668,14 @@ impl WasiFile for HostFile {
 
 impl From<io::Error> for WasiFsError {
     fn from(io_error: io::Error) -> Self {
+        // these have no `io::ErrorKind`
+        #[cfg(unix)]
+        match io_error.raw_os_error() {
+            Some(libc::EISDIR) => return WasiFsError::IsADirectory,
+            Some(libc::ENOTDIR) => return WasiFsError::BaseNotDirectory,
+            Some(libc::ENOTEMPTY) => return WasiFsError::DirectoryNotEmpty,
+            _ => (),
+        }
         match io_error.kind() {
             io::ErrorKind::AddrInUse => WasiFsError::AddressInUse,
             io::ErrorKind::AddrNotAvailable => WasiFsError::AddressNotAvailable,
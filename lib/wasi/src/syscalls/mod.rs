@@ -18,7 +18,7 @@ use crate::{
     ptr::{Array, WasmPtr},
     state::{
         self, host_file_type_to_wasi_file_type, iterate_poll_events, poll, Fd, HostFile, Inode,
-        InodeVal, Kind, PollEvent, PollEventBuilder, WasiFile, WasiFsError, WasiState,
+        InodeVal, Kind, PollEvent, PollEventBuilder, WasiFile, WasiFsError, WasiSocket, WasiState,
         MAX_SYMLINKS,
     },
     WasiEnv, WasiError,
@@ -87,6 +87,88 @@ fn read_bytes<T: Read>(
     Ok(bytes_read)
 }
 
+/// The most bytes `recv_bytes` copies through the host at once.
+const RECV_CHUNK_SIZE: usize = 64 * 1024;
+
+fn recv_bytes(
+    socket: &mut dyn WasiSocket,
+    memory: &Memory,
+    iovs_arr_cell: &[Cell<__wasi_iovec_t>],
+    ri_flags: __wasi_riflags_t,
+) -> Result<u32, __wasi_errno_t> {
+    // validate every iovec before touching the socket
+    let mut iovs = Vec::with_capacity(iovs_arr_cell.len());
+    let mut total_len = 0usize;
+    for iov in iovs_arr_cell {
+        let iov_inner = iov.get();
+        let bytes = iov_inner.buf.deref(memory, 0, iov_inner.buf_len)?;
+        total_len += bytes.len();
+        iovs.push(bytes);
+    }
+
+    // receive into one buffer so that peeking doesn't see the same data in
+    // every iovec; a short read is fine if the iovecs are bigger than a chunk
+    let mut chunk = vec![0; total_len.min(RECV_CHUNK_SIZE)];
+    if ri_flags & __WASI_SOCK_RECV_PEEK != 0 {
+        let amt = socket
+            .peek(&mut chunk)
+            .map_err(WasiFsError::into_wasi_err)?;
+        scatter_bytes(&iovs, 0, &chunk[..amt]);
+        return Ok(amt as u32);
+    }
+
+    let mut bytes_read = 0;
+    while bytes_read < total_len {
+        let len = chunk.len().min(total_len - bytes_read);
+        let amt = match socket.recv(&mut chunk[..len]) {
+            Ok(amt) => amt,
+            // the bytes already received are gone from the socket
+            Err(_) if bytes_read > 0 => break,
+            Err(e) => return Err(e.into_wasi_err()),
+        };
+        scatter_bytes(&iovs, bytes_read, &chunk[..amt]);
+        bytes_read += amt;
+        if amt == 0 || ri_flags & __WASI_SOCK_RECV_WAITALL == 0 {
+            break;
+        }
+    }
+    Ok(bytes_read as u32)
+}
+
+/// Copy `data` into the iovecs, starting `offset` bytes into them.
+fn scatter_bytes(iovs: &[&[Cell<u8>]], mut offset: usize, mut data: &[u8]) {
+    for bytes in iovs {
+        if data.is_empty() {
+            break;
+        }
+        if offset >= bytes.len() {
+            offset -= bytes.len();
+            continue;
+        }
+        let amt = (bytes.len() - offset).min(data.len());
+        for (cell, &byte) in bytes[offset..].iter().zip(&data[..amt]) {
+            cell.set(byte);
+        }
+        offset = 0;
+        data = &data[amt..];
+    }
+}
+
+fn send_bytes(
+    socket: &mut dyn WasiSocket,
+    memory: &Memory,
+    iovs_arr_cell: &[Cell<__wasi_ciovec_t>],
+) -> Result<u32, __wasi_errno_t> {
+    let mut buffer = vec![];
+    for iov in iovs_arr_cell {
+        let iov_inner = iov.get();
+        let bytes = iov_inner.buf.deref(memory, 0, iov_inner.buf_len)?;
+        buffer.extend(bytes.iter().map(|b_cell| b_cell.get()));
+    }
+    let bytes_written = socket.send(&buffer).map_err(WasiFsError::into_wasi_err)?;
+    Ok(bytes_written as u32)
+}
+
 /// checks that `rights_check_set` is a subset of `rights_set`
 fn has_rights(rights_set: __wasi_rights_t, rights_check_set: __wasi_rights_t) -> bool {
     rights_set | rights_check_set == rights_set
@@ -140,6 +222,7 @@ pub fn args_get(
     argv_buf: WasmPtr<u8, Array>,
 ) -> __wasi_errno_t {
     debug!("wasi::args_get");
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
     let result = write_buffer_array(memory, &*state.args, argv, argv_buf);
@@ -175,6 +258,7 @@ pub fn args_sizes_get(
     argv_buf_size: WasmPtr<u32>,
 ) -> __wasi_errno_t {
     debug!("wasi::args_sizes_get");
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
     let argc = wasi_try!(argc.deref(memory));
@@ -204,6 +288,7 @@ pub fn clock_res_get(
     resolution: WasmPtr<__wasi_timestamp_t>,
 ) -> __wasi_errno_t {
     debug!("wasi::clock_res_get");
+    env.threads.check_exit();
     let memory = env.memory();
 
     let out_addr = wasi_try!(resolution.deref(memory));
@@ -230,6 +315,7 @@ pub fn clock_time_get(
         "wasi::clock_time_get clock_id: {}, precision: {}",
         clock_id, precision
     );
+    env.threads.check_exit();
     let memory = env.memory();
 
     let out_addr = wasi_try!(time.deref(memory));
@@ -256,6 +342,7 @@ pub fn environ_get(
     environ_buf: WasmPtr<u8, Array>,
 ) -> __wasi_errno_t {
     debug!("wasi::environ_get");
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
     write_buffer_array(memory, &*state.envs, environ, environ_buf)
@@ -274,6 +361,7 @@ pub fn environ_sizes_get(
     environ_buf_size: WasmPtr<u32>,
 ) -> __wasi_errno_t {
     debug!("wasi::environ_sizes_get");
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
     let environ_count = wasi_try!(environ_count.deref(memory));
@@ -311,6 +399,7 @@ pub fn fd_advise(
     advice: __wasi_advice_t,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_advise: fd={}", fd);
+    env.threads.check_exit();
 
     // this is used for our own benefit, so just returning success is a valid
     // implementation for now
@@ -333,6 +422,7 @@ pub fn fd_allocate(
     len: __wasi_filesize_t,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_allocate");
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
     let inode = fd_entry.inode;
@@ -354,6 +444,7 @@ pub fn fd_allocate(
             buffer.resize(new_size as usize, 0);
         }
         Kind::Symlink { .. } => return __WASI_EBADF,
+        Kind::Socket { .. } => return __WASI_EINVAL,
         Kind::Dir { .. } | Kind::Root { .. } => return __WASI_EISDIR,
     }
     state.fs.inodes[inode].stat.st_size = new_size;
@@ -374,6 +465,7 @@ pub fn fd_allocate(
 ///     If `fd` is invalid or not open
 pub fn fd_close(env: &WasiEnv, fd: __wasi_fd_t) -> __wasi_errno_t {
     debug!("wasi::fd_close: fd={}", fd);
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
@@ -390,6 +482,7 @@ pub fn fd_close(env: &WasiEnv, fd: __wasi_fd_t) -> __wasi_errno_t {
 ///     The file descriptor to sync
 pub fn fd_datasync(env: &WasiEnv, fd: __wasi_fd_t) -> __wasi_errno_t {
     debug!("wasi::fd_datasync");
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
     if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_DATASYNC) {
@@ -421,6 +514,7 @@ pub fn fd_fdstat_get(
         fd,
         buf_ptr.offset()
     );
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
 
@@ -445,6 +539,7 @@ pub fn fd_fdstat_set_flags(
     flags: __wasi_fdflags_t,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_fdstat_set_flags");
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
 
@@ -472,6 +567,7 @@ pub fn fd_fdstat_set_rights(
     fs_rights_inheriting: __wasi_rights_t,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_fdstat_set_rights");
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
 
@@ -502,6 +598,7 @@ pub fn fd_filestat_get(
     buf: WasmPtr<__wasi_filestat_t>,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_filestat_get");
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
     if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_FILESTAT_GET) {
@@ -529,6 +626,7 @@ pub fn fd_filestat_set_size(
     st_size: __wasi_filesize_t,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_filestat_set_size");
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
     let inode = fd_entry.inode;
@@ -549,6 +647,7 @@ pub fn fd_filestat_set_size(
             buffer.resize(st_size as usize, 0);
         }
         Kind::Symlink { .. } => return __WASI_EBADF,
+        Kind::Socket { .. } => return __WASI_EINVAL,
         Kind::Dir { .. } | Kind::Root { .. } => return __WASI_EISDIR,
     }
     state.fs.inodes[inode].stat.st_size = st_size;
@@ -573,6 +672,7 @@ pub fn fd_filestat_set_times(
     fst_flags: __wasi_fstflags_t,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_filestat_set_times");
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
 
@@ -647,6 +747,7 @@ pub fn fd_pread(
     nread: WasmPtr<u32>,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_pread: fd={}, offset={}", fd, offset);
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
     let iov_cells = wasi_try!(iovs.deref(memory, 0, iovs_len));
@@ -691,6 +792,7 @@ pub fn fd_pread(
                 }
                 Kind::Dir { .. } | Kind::Root { .. } => return __WASI_EISDIR,
                 Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_pread"),
+                Kind::Socket { .. } => return __WASI_ESPIPE,
                 Kind::Buffer { buffer } => {
                     wasi_try!(read_bytes(&buffer[(offset as usize)..], memory, iov_cells))
                 }
@@ -717,6 +819,7 @@ pub fn fd_prestat_get(
     buf: WasmPtr<__wasi_prestat_t>,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_prestat_get: fd={}", fd);
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
     let prestat_ptr = wasi_try!(buf.deref(memory));
@@ -736,6 +839,7 @@ pub fn fd_prestat_dir_name(
         "wasi::fd_prestat_dir_name: fd={}, path_len={}",
         fd, path_len
     );
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let path_chars = wasi_try!(path.deref(memory, 0, path_len));
 
@@ -768,7 +872,9 @@ pub fn fd_prestat_dir_name(
                 __WASI_EOVERFLOW
             }
         }
-        Kind::Symlink { .. } | Kind::Buffer { .. } | Kind::File { .. } => __WASI_ENOTDIR,
+        Kind::Symlink { .. } | Kind::Buffer { .. } | Kind::File { .. } | Kind::Socket { .. } => {
+            __WASI_ENOTDIR
+        }
     }
 }
 
@@ -795,6 +901,7 @@ pub fn fd_pwrite(
     nwritten: WasmPtr<u32>,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_pwrite");
+    env.threads.check_exit();
     // TODO: refactor, this is just copied from `fd_write`...
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let iovs_arr_cell = wasi_try!(iovs.deref(memory, 0, iovs_len));
@@ -846,6 +953,7 @@ pub fn fd_pwrite(
                     return __WASI_EISDIR;
                 }
                 Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_pwrite"),
+                Kind::Socket { .. } => return __WASI_ESPIPE,
                 Kind::Buffer { buffer } => wasi_try!(write_bytes(
                     &mut buffer[(offset as usize)..],
                     memory,
@@ -880,6 +988,7 @@ pub fn fd_read(
     nread: WasmPtr<u32>,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_read: fd={}", fd);
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
     let iovs_arr_cell = wasi_try!(iovs.deref(memory, 0, iovs_len));
@@ -907,6 +1016,7 @@ pub fn fd_read(
             let offset = fd_entry.offset as usize;
             let inode_idx = fd_entry.inode;
             let inode = &mut state.fs.inodes[inode_idx];
+            let is_socket = matches!(inode.kind, Kind::Socket { .. });
 
             let bytes_read = match &mut inode.kind {
                 Kind::File { handle, .. } => {
@@ -922,14 +1032,19 @@ pub fn fd_read(
                     return __WASI_EISDIR;
                 }
                 Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_read"),
+                Kind::Socket { socket } => {
+                    wasi_try!(recv_bytes(socket.as_mut(), memory, iovs_arr_cell, 0))
+                }
                 Kind::Buffer { buffer } => {
                     wasi_try!(read_bytes(&buffer[offset..], memory, iovs_arr_cell))
                 }
             };
 
-            // reborrow
-            let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
-            fd_entry.offset += bytes_read as u64;
+            if !is_socket {
+                // reborrow
+                let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
+                fd_entry.offset += bytes_read as u64;
+            }
 
             bytes_read
         }
@@ -964,6 +1079,7 @@ pub fn fd_readdir(
     bufused: WasmPtr<u32>,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_readdir");
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     // TODO: figure out how this is supposed to work;
     // is it supposed to pack the buffer full every time until it can't? or do one at a time?
@@ -980,17 +1096,21 @@ pub fn fd_readdir(
             // we need to support multiple calls,
             // simple and obviously correct implementation for now:
             // maintain consistent order via lexacographic sorting
-            let fs_info = wasi_try!(wasi_try!(std::fs::read_dir(path).map_err(|_| __WASI_EIO))
-                .collect::<Result<Vec<_>, _>>()
-                .map_err(|_| __WASI_EIO));
-            let mut entry_vec = wasi_try!(fs_info
+            let fs_info = wasi_try!(state
+                .fs
+                .fs_backend
+                .read_dir(path)
+                .map_err(WasiFsError::into_wasi_err));
+            let mut entry_vec = fs_info
                 .into_iter()
-                .map(|entry| Ok((
-                    entry.file_name().to_string_lossy().to_string(),
-                    host_file_type_to_wasi_file_type(entry.file_type().map_err(|_| __WASI_EIO)?),
-                    0, // TODO: inode
-                )))
-                .collect::<Result<Vec<(String, u8, u64)>, _>>());
+                .map(|entry| {
+                    (
+                        entry.name,
+                        entry.file_type,
+                        0, // TODO: inode
+                    )
+                })
+                .collect::<Vec<(String, u8, u64)>>();
             entry_vec.extend(
                 entries
                     .iter()
@@ -1026,7 +1146,9 @@ pub fn fd_readdir(
                 })
                 .collect()
         }
-        Kind::File { .. } | Kind::Symlink { .. } | Kind::Buffer { .. } => return __WASI_ENOTDIR,
+        Kind::File { .. } | Kind::Symlink { .. } | Kind::Buffer { .. } | Kind::Socket { .. } => {
+            return __WASI_ENOTDIR
+        }
     };
 
     for (entry_path_str, wasi_file_type, ino) in entries.iter().skip(cookie as usize) {
@@ -1074,6 +1196,7 @@ pub fn fd_readdir(
 ///     Location to copy file descriptor to
 pub fn fd_renumber(env: &WasiEnv, from: __wasi_fd_t, to: __wasi_fd_t) -> __wasi_errno_t {
     debug!("wasi::fd_renumber: from={}, to={}", from, to);
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.fd_map.get(&from).ok_or(__WASI_EBADF));
     let new_fd_entry = Fd {
@@ -1107,6 +1230,7 @@ pub fn fd_seek(
     newoffset: WasmPtr<__wasi_filesize_t>,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_seek: fd={}, offset={}", fd, offset);
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let new_offset_cell = wasi_try!(newoffset.deref(memory));
 
@@ -1147,6 +1271,7 @@ pub fn fd_seek(
                     // TODO: implement this
                     return __WASI_EINVAL;
                 }
+                Kind::Socket { .. } => return __WASI_ESPIPE,
             }
         }
         __WASI_WHENCE_SET => fd_entry.offset = offset as u64,
@@ -1170,6 +1295,7 @@ pub fn fd_seek(
 /// - `__WASI_ENOTCAPABLE`
 pub fn fd_sync(env: &WasiEnv, fd: __wasi_fd_t) -> __wasi_errno_t {
     debug!("wasi::fd_sync");
+    env.threads.check_exit();
     debug!("=> fd={}", fd);
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
@@ -1188,7 +1314,7 @@ pub fn fd_sync(env: &WasiEnv, fd: __wasi_fd_t) -> __wasi_errno_t {
             }
         }
         Kind::Root { .. } | Kind::Dir { .. } => return __WASI_EISDIR,
-        Kind::Buffer { .. } | Kind::Symlink { .. } => return __WASI_EINVAL,
+        Kind::Buffer { .. } | Kind::Symlink { .. } | Kind::Socket { .. } => return __WASI_EINVAL,
     }
 
     __WASI_ESUCCESS
@@ -1208,6 +1334,7 @@ pub fn fd_tell(
     offset: WasmPtr<__wasi_filesize_t>,
 ) -> __wasi_errno_t {
     debug!("wasi::fd_tell");
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let offset_cell = wasi_try!(offset.deref(memory));
 
@@ -1251,6 +1378,7 @@ pub fn fd_write(
     } else {
         trace!("wasi::fd_write: fd={}", fd);
     }
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let iovs_arr_cell = wasi_try!(iovs.deref(memory, 0, iovs_len));
     let nwritten_cell = wasi_try!(nwritten.deref(memory));
@@ -1285,6 +1413,7 @@ pub fn fd_write(
             let offset = fd_entry.offset as usize;
             let inode_idx = fd_entry.inode;
             let inode = &mut state.fs.inodes[inode_idx];
+            let is_socket = matches!(inode.kind, Kind::Socket { .. });
 
             let bytes_written = match &mut inode.kind {
                 Kind::File { handle, .. } => {
@@ -1300,15 +1429,21 @@ pub fn fd_write(
                     return __WASI_EISDIR;
                 }
                 Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_write"),
+                Kind::Socket { socket } => {
+                    wasi_try!(send_bytes(socket.as_mut(), memory, iovs_arr_cell))
+                }
                 Kind::Buffer { buffer } => {
                     wasi_try!(write_bytes(&mut buffer[offset..], memory, iovs_arr_cell))
                 }
             };
 
-            // reborrow
-            let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
-            fd_entry.offset += bytes_written as u64;
-            wasi_try!(state.fs.filestat_resync_size(fd));
+            // sockets have neither an offset nor a size
+            if !is_socket {
+                // reborrow
+                let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
+                fd_entry.offset += bytes_written as u64;
+                wasi_try!(state.fs.filestat_resync_size(fd));
+            }
 
             bytes_written
         }
@@ -1339,6 +1474,7 @@ pub fn path_create_directory(
     path_len: u32,
 ) -> __wasi_errno_t {
     debug!("wasi::path_create_directory");
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
     let working_dir = wasi_try!(state.fs.get_fd(fd));
@@ -1392,10 +1528,16 @@ pub fn path_create_directory(
                     let mut adjusted_path = path.clone();
                     // TODO: double check this doesn't risk breaking the sandbox
                     adjusted_path.push(comp);
-                    if adjusted_path.exists() && !adjusted_path.is_dir() {
-                        return __WASI_ENOTDIR;
-                    } else if !adjusted_path.exists() {
-                        wasi_try!(std::fs::create_dir(&adjusted_path).ok(), __WASI_EIO);
+                    match state.fs.fs_backend.metadata(&adjusted_path) {
+                        Ok(stat) if stat.st_filetype != __WASI_FILETYPE_DIRECTORY => {
+                            return __WASI_ENOTDIR
+                        }
+                        Ok(_) => (),
+                        Err(_) => wasi_try!(state
+                            .fs
+                            .fs_backend
+                            .create_dir(&adjusted_path)
+                            .map_err(WasiFsError::into_wasi_err)),
                     }
                     let kind = Kind::Dir {
                         parent: Some(cur_dir_inode),
@@ -1444,6 +1586,7 @@ pub fn path_filestat_get(
     buf: WasmPtr<__wasi_filestat_t>,
 ) -> __wasi_errno_t {
     debug!("wasi::path_filestat_get");
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
     let root_dir = wasi_try!(state.fs.get_fd(fd));
@@ -1503,9 +1646,9 @@ pub fn path_filestat_set_times(
     fst_flags: __wasi_fstflags_t,
 ) -> __wasi_errno_t {
     debug!("wasi::path_filestat_set_times");
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
-    let fd_inode = fd_entry.inode;
     if !has_rights(fd_entry.rights, __WASI_RIGHT_PATH_FILESTAT_SET_TIMES) {
         return __WASI_EACCES;
     }
@@ -1529,35 +1672,53 @@ pub fn path_filestat_set_times(
         .get_stat_for_kind(&state.fs.inodes[file_inode].kind)
         .ok_or(__WASI_EIO));
 
-    let inode = &mut state.fs.inodes[fd_inode];
+    let last_accessed = if fst_flags & __WASI_FILESTAT_SET_ATIM != 0 {
+        Some(st_atim)
+    } else if fst_flags & __WASI_FILESTAT_SET_ATIM_NOW != 0 {
+        Some(wasi_try!(get_current_time_in_nanos()))
+    } else {
+        None
+    };
+    let last_modified = if fst_flags & __WASI_FILESTAT_SET_MTIM != 0 {
+        Some(st_mtim)
+    } else if fst_flags & __WASI_FILESTAT_SET_MTIM_NOW != 0 {
+        Some(wasi_try!(get_current_time_in_nanos()))
+    } else {
+        None
+    };
 
-    if fst_flags & __WASI_FILESTAT_SET_ATIM != 0 || fst_flags & __WASI_FILESTAT_SET_ATIM_NOW != 0 {
-        let time_to_set = if fst_flags & __WASI_FILESTAT_SET_ATIM != 0 {
-            st_atim
-        } else {
-            wasi_try!(get_current_time_in_nanos())
-        };
-        inode.stat.st_atim = time_to_set;
-        // TODO: set it for more than just files
-        if let Kind::File { handle, .. } = &mut inode.kind {
-            if let Some(handle) = handle {
+    match &state.fs.inodes[file_inode].kind {
+        Kind::File {
+            handle: Some(handle),
+            ..
+        } => {
+            if let Some(time_to_set) = last_accessed {
                 handle.set_last_accessed(time_to_set);
             }
-        }
-    }
-    if fst_flags & __WASI_FILESTAT_SET_MTIM != 0 || fst_flags & __WASI_FILESTAT_SET_MTIM_NOW != 0 {
-        let time_to_set = if fst_flags & __WASI_FILESTAT_SET_MTIM != 0 {
-            st_mtim
-        } else {
-            wasi_try!(get_current_time_in_nanos())
-        };
-        inode.stat.st_mtim = time_to_set;
-        // TODO: set it for more than just files
-        if let Kind::File { handle, .. } = &mut inode.kind {
-            if let Some(handle) = handle {
+            if let Some(time_to_set) = last_modified {
                 handle.set_last_modified(time_to_set);
             }
         }
+        Kind::File {
+            handle: None, path, ..
+        }
+        | Kind::Dir { path, .. } => {
+            wasi_try!(state
+                .fs
+                .fs_backend
+                .set_times(path, last_accessed, last_modified)
+                .map_err(WasiFsError::into_wasi_err));
+        }
+        // TODO: set it for more than just files and directories
+        _ => (),
+    }
+
+    let inode = &mut state.fs.inodes[file_inode];
+    if let Some(time_to_set) = last_accessed {
+        inode.stat.st_atim = time_to_set;
+    }
+    if let Some(time_to_set) = last_modified {
+        inode.stat.st_mtim = time_to_set;
     }
 
     __WASI_ESUCCESS
@@ -1591,6 +1752,7 @@ pub fn path_link(
     new_path_len: u32,
 ) -> __wasi_errno_t {
     debug!("wasi::path_link");
+    env.threads.check_exit();
     if old_flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0 {
         debug!("  - will follow symlinks when opening path");
     }
@@ -1632,7 +1794,9 @@ pub fn path_link(
             entries.insert(new_entry_name, source_inode);
         }
         Kind::Root { .. } => return __WASI_EINVAL,
-        Kind::File { .. } | Kind::Symlink { .. } | Kind::Buffer { .. } => return __WASI_ENOTDIR,
+        Kind::File { .. } | Kind::Symlink { .. } | Kind::Buffer { .. } | Kind::Socket { .. } => {
+            return __WASI_ENOTDIR
+        }
     }
     state.fs.inodes[source_inode].stat.st_nlink += 1;
 
@@ -1676,6 +1840,7 @@ pub fn path_open(
     fd: WasmPtr<__wasi_fd_t>,
 ) -> __wasi_errno_t {
     debug!("wasi::path_open");
+    env.threads.check_exit();
     if dirflags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0 {
         debug!("  - will follow symlinks when opening path");
     }
@@ -1718,7 +1883,8 @@ pub fn path_open(
     let adjusted_rights = /*fs_rights_base &*/ working_dir_rights_inheriting;
     let inode = if let Ok(inode) = maybe_inode {
         // Happy path, we found the file we're trying to open
-        match &mut state.fs.inodes[inode].kind {
+        let wasi_fs = &mut state.fs;
+        match &mut wasi_fs.inodes[inode].kind {
             Kind::File {
                 ref mut handle,
                 path,
@@ -1733,10 +1899,10 @@ pub fn path_open(
                 if o_flags & __WASI_O_DIRECTORY != 0 {
                     return __WASI_ENOTDIR;
                 }
-                if o_flags & __WASI_O_EXCL != 0 && path.exists() {
+                if o_flags & __WASI_O_EXCL != 0 && wasi_fs.fs_backend.metadata(path).is_ok() {
                     return __WASI_EEXIST;
                 }
-                let mut open_options = std::fs::OpenOptions::new();
+                let mut open_options = state::OpenOptions::new();
                 let write_permission = adjusted_rights & __WASI_RIGHT_FD_WRITE != 0;
                 // append, truncate, and create all require the permission to write
                 let (append_permission, truncate_permission, create_permission) =
@@ -1766,18 +1932,16 @@ pub fn path_open(
                 if o_flags & __WASI_O_TRUNC != 0 {
                     open_flags |= Fd::TRUNCATE;
                 }
-                *handle = Some(Box::new(HostFile::new(
-                    wasi_try!(open_options.open(&path).map_err(|_| __WASI_EIO)),
-                    path.to_path_buf(),
-                    true,
-                    adjusted_rights & __WASI_RIGHT_FD_WRITE != 0,
-                    false,
-                )));
+                *handle = Some(wasi_try!(wasi_fs
+                    .fs_backend
+                    .open(path, open_options)
+                    .map_err(WasiFsError::into_wasi_err)));
             }
             Kind::Buffer { .. } => unimplemented!("wasi::path_open for Buffer type files"),
+            Kind::Socket { .. } => return __WASI_ENOTSUP,
             Kind::Dir { .. } | Kind::Root { .. } => {
-                // TODO: adjust these to be correct
-                if o_flags & __WASI_O_EXCL != 0 && path_arg.exists() {
+                // the directory was found, so it already exists
+                if o_flags & __WASI_O_EXCL != 0 {
                     return __WASI_EEXIST;
                 }
             }
@@ -1819,7 +1983,7 @@ pub fn path_open(
             // once we got the data we need from the parent, we lookup the host file
             // todo: extra check that opening with write access is okay
             let handle = {
-                let mut open_options = std::fs::OpenOptions::new();
+                let mut open_options = state::OpenOptions::new();
                 let open_options = open_options
                     .read(true)
                     .append(fs_flags & __WASI_FDFLAG_APPEND != 0)
@@ -1829,16 +1993,14 @@ pub fn path_open(
                     .create_new(true);
                 open_flags |= Fd::READ | Fd::WRITE | Fd::CREATE | Fd::TRUNCATE;
 
-                Some(Box::new(HostFile::new(
-                    wasi_try!(open_options.open(&new_file_host_path).map_err(|e| {
+                Some(wasi_try!(state
+                    .fs
+                    .fs_backend
+                    .open(&new_file_host_path, open_options)
+                    .map_err(|e| {
                         debug!("Error opening file {}", e);
-                        __WASI_EIO
-                    })),
-                    new_file_host_path.clone(),
-                    true,
-                    true,
-                    true,
-                )) as Box<dyn WasiFile>)
+                        e.into_wasi_err()
+                    })))
             };
 
             let new_inode = {
@@ -1937,6 +2099,7 @@ pub fn path_readlink(
     buf_used: WasmPtr<u32>,
 ) -> __wasi_errno_t {
     debug!("wasi::path_readlink");
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
     let base_dir = wasi_try!(state.fs.fd_map.get(&dir_fd).ok_or(__WASI_EBADF));
@@ -1980,6 +2143,7 @@ pub fn path_remove_directory(
 ) -> __wasi_errno_t {
     // TODO check if fd is a dir, ensure it's within sandbox, etc.
     debug!("wasi::path_remove_directory");
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
     let base_dir = wasi_try!(state.fs.fd_map.get(&fd), __WASI_EBADF);
@@ -1994,7 +2158,12 @@ pub fn path_remove_directory(
     let host_path_to_remove = match &state.fs.inodes[inode].kind {
         Kind::Dir { entries, path, .. } => {
             if !entries.is_empty()
-                || wasi_try!(std::fs::read_dir(path).ok(), __WASI_EIO).count() != 0
+                || !wasi_try!(state
+                    .fs
+                    .fs_backend
+                    .read_dir(path)
+                    .map_err(WasiFsError::into_wasi_err))
+                .is_empty()
             {
                 return __WASI_ENOTEMPTY;
             }
@@ -2018,7 +2187,7 @@ pub fn path_remove_directory(
         ),
     }
 
-    if std::fs::remove_dir(path_str).is_err() {
+    if let Err(e) = state.fs.fs_backend.remove_dir(&host_path_to_remove) {
         // reinsert to prevent FS from being in bad state
         if let Kind::Dir {
             ref mut entries, ..
@@ -2026,8 +2195,7 @@ pub fn path_remove_directory(
         {
             entries.insert(childs_name, inode);
         }
-        // TODO: more intelligently return error value by inspecting returned error value
-        return __WASI_EIO;
+        return e.into_wasi_err();
     }
 
     __WASI_ESUCCESS
@@ -2061,6 +2229,7 @@ pub fn path_rename(
         "wasi::path_rename: old_fd = {}, new_fd = {}",
         old_fd, new_fd
     );
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let source_str = unsafe { get_input_str!(memory, old_path, old_path_len) };
     let source_path = std::path::Path::new(source_str);
@@ -2090,49 +2259,53 @@ pub fn path_rename(
                 return __WASI_EEXIST;
             }
             let mut out_path = path.clone();
-            out_path.push(target_path);
+            out_path.push(&target_entry_name);
             out_path
         }
         Kind::Root { .. } => return __WASI_ENOTCAPABLE,
-        Kind::Symlink { .. } | Kind::File { .. } | Kind::Buffer { .. } => {
+        Kind::Symlink { .. } | Kind::File { .. } | Kind::Buffer { .. } | Kind::Socket { .. } => {
             unreachable!("Fatal internal logic error: parent of inode is not a directory")
         }
     };
     let source_entry = match &mut state.fs.inodes[source_parent_inode].kind {
         Kind::Dir { entries, .. } => wasi_try!(entries.remove(&source_entry_name), __WASI_EINVAL),
         Kind::Root { .. } => return __WASI_ENOTCAPABLE,
-        Kind::Symlink { .. } | Kind::File { .. } | Kind::Buffer { .. } => {
+        Kind::Symlink { .. } | Kind::File { .. } | Kind::Buffer { .. } | Kind::Socket { .. } => {
             unreachable!("Fatal internal logic error: parent of inode is not a directory")
         }
     };
 
-    match &mut state.fs.inodes[source_entry].kind {
-        Kind::File {
-            handle,
-            ref mut path,
-            ..
-        } => {
+    let wasi_fs = &mut state.fs;
+    let result = match &mut wasi_fs.inodes[source_entry].kind {
+        Kind::File { handle, path, .. } => {
             let result = if let Some(h) = handle {
                 h.rename_file(&host_adjusted_target_path)
-                    .map_err(|e| e.into_wasi_err())
             } else {
-                let out =
-                    std::fs::rename(&path, &host_adjusted_target_path).map_err(|_| __WASI_EIO);
-                *path = host_adjusted_target_path;
-                out
+                wasi_fs.fs_backend.rename(path, &host_adjusted_target_path)
             };
-            // if the above operation failed we have to revert the previous change and then fail
-            if let Err(e) = result {
-                if let Kind::Dir { entries, .. } = &mut state.fs.inodes[source_parent_inode].kind {
-                    entries.insert(source_entry_name, source_entry);
-                    return e;
-                }
+            if result.is_ok() {
+                *path = host_adjusted_target_path;
             }
+            result
         }
-        Kind::Dir { path, .. } => unimplemented!("wasi::path_rename on Directories"),
-        Kind::Buffer { .. } => {}
-        Kind::Symlink { .. } => {}
+        Kind::Dir { path, parent, .. } => {
+            let result = wasi_fs.fs_backend.rename(path, &host_adjusted_target_path);
+            if result.is_ok() {
+                *parent = Some(target_parent_inode);
+                wasi_fs.rebase_inode_path(source_entry, host_adjusted_target_path);
+            }
+            result
+        }
+        Kind::Buffer { .. } | Kind::Socket { .. } => Ok(()),
+        Kind::Symlink { .. } => Ok(()),
         Kind::Root { .. } => unreachable!("The root can not be moved"),
+    };
+    // if the above operation failed we have to revert the previous change and then fail
+    if let Err(e) = result {
+        if let Kind::Dir { entries, .. } = &mut state.fs.inodes[source_parent_inode].kind {
+            entries.insert(source_entry_name, source_entry);
+        }
+        return e.into_wasi_err();
     }
 
     if let Kind::Dir { entries, .. } = &mut state.fs.inodes[target_parent_inode].kind {
@@ -2168,6 +2341,7 @@ pub fn path_symlink(
     new_path_len: u32,
 ) -> __wasi_errno_t {
     debug!("wasi::path_symlink");
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let old_path_str = unsafe { get_input_str!(memory, old_path, old_path_len) };
     let new_path_str = unsafe { get_input_str!(memory, new_path, new_path_len) };
@@ -2193,7 +2367,7 @@ pub fn path_symlink(
             }
         }
         Kind::Root { .. } => return __WASI_ENOTCAPABLE,
-        Kind::File { .. } | Kind::Symlink { .. } | Kind::Buffer { .. } => {
+        Kind::File { .. } | Kind::Symlink { .. } | Kind::Buffer { .. } | Kind::Socket { .. } => {
             unreachable!("get_parent_inode_at_path returned something other than a Dir or Root")
         }
     }
@@ -2245,6 +2419,7 @@ pub fn path_unlink_file(
     path_len: u32,
 ) -> __wasi_errno_t {
     debug!("wasi::path_unlink_file");
+    env.threads.check_exit();
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
     let base_dir = wasi_try!(state.fs.fd_map.get(&fd).ok_or(__WASI_EBADF));
@@ -2278,15 +2453,17 @@ pub fn path_unlink_file(
 
     state.fs.inodes[removed_inode].stat.st_nlink -= 1;
     if state.fs.inodes[removed_inode].stat.st_nlink == 0 {
-        match &mut state.fs.inodes[removed_inode].kind {
+        let wasi_fs = &mut state.fs;
+        match &mut wasi_fs.inodes[removed_inode].kind {
             Kind::File { handle, path, .. } => {
                 if let Some(h) = handle {
                     wasi_try!(h.unlink().map_err(WasiFsError::into_wasi_err));
                 } else {
-                    // File is closed
-                    // problem with the abstraction, we can't call unlink because there's no handle
-                    // TODO: replace this code
-                    wasi_try!(std::fs::remove_file(path).map_err(|_| __WASI_EIO));
+                    // File is closed, remove it from the file system directly
+                    wasi_try!(wasi_fs
+                        .fs_backend
+                        .remove_file(path)
+                        .map_err(WasiFsError::into_wasi_err));
                 }
             }
             Kind::Dir { .. } | Kind::Root { .. } => return __WASI_EISDIR,
@@ -2340,6 +2517,7 @@ pub fn poll_oneoff(
     nevents: WasmPtr<u32>,
 ) -> __wasi_errno_t {
     debug!("wasi::poll_oneoff");
+    env.threads.check_exit();
     debug!("  => nsubscriptions = {}", nsubscriptions);
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
@@ -2437,6 +2615,8 @@ pub fn poll_oneoff(
                         | Kind::Symlink { .. } => {
                             unimplemented!("polling read on non-files not yet supported")
                         }
+                        // TODO: support polling sockets
+                        Kind::Socket { .. } => return __WASI_ENOTSUP,
                     }
                 }
             };
@@ -2521,15 +2701,34 @@ pub fn poll_oneoff(
 
 pub fn proc_exit(env: &WasiEnv, code: __wasi_exitcode_t) {
     debug!("wasi::proc_exit, {}", code);
+    env.threads.exit(code);
     RuntimeError::raise(Box::new(WasiError::Exit(code)));
     unreachable!();
 }
 
 pub fn proc_raise(env: &WasiEnv, sig: __wasi_signal_t) -> __wasi_errno_t {
     debug!("wasi::proc_raise");
+    env.threads.check_exit();
     unimplemented!("wasi::proc_raise")
 }
 
+/// ### `thread_spawn()`
+/// Start a thread running `wasi_thread_start` in a new instance of the
+/// module, which shares its memory
+/// Inputs:
+/// - `void *start_arg`
+///     The argument given to `wasi_thread_start`
+/// Output:
+/// - The id of the new thread, or a negated errno on failure
+pub fn thread_spawn(env: &WasiEnv, start_arg: u32) -> i32 {
+    debug!("wasi::thread_spawn start_arg: {}", start_arg);
+    env.threads.check_exit();
+    match env.threads.spawn(env, start_arg) {
+        Ok(thread_id) => thread_id as i32,
+        Err(errno) => -(errno as i32),
+    }
+}
+
 /// ### `random_get()`
 /// Fill buffer with high-quality random data.  This function may be slow and block
 /// Inputs:
@@ -2539,6 +2738,7 @@ pub fn proc_raise(env: &WasiEnv, sig: __wasi_signal_t) -> __wasi_errno_t {
 ///     The number of bytes that will be written
 pub fn random_get(env: &WasiEnv, buf: WasmPtr<u8, Array>, buf_len: u32) -> __wasi_errno_t {
     debug!("wasi::random_get buf_len: {}", buf_len);
+    env.threads.check_exit();
     let memory = env.memory();
 
     let buf = wasi_try!(buf.deref(memory, 0, buf_len));
@@ -2557,10 +2757,27 @@ pub fn random_get(env: &WasiEnv, buf: WasmPtr<u8, Array>, buf_len: u32) -> __was
 /// Yields execution of the thread
 pub fn sched_yield(env: &WasiEnv) -> __wasi_errno_t {
     debug!("wasi::sched_yield");
+    env.threads.check_exit();
     ::std::thread::yield_now();
     __WASI_ESUCCESS
 }
 
+/// ### `sock_recv()`
+/// Receive a message from a socket
+/// Inputs:
+/// - `__wasi_fd_t sock`
+///     The socket to receive from
+/// - `const __wasi_iovec_t *ri_data`
+///     The vectors to store the received data in
+/// - `u32 ri_data_len`
+///     The number of vectors in `ri_data`
+/// - `__wasi_riflags_t ri_flags`
+///     Flags modifying how the message is received
+/// Output:
+/// - `u32 *ro_datalen`
+///     The number of bytes received
+/// - `__wasi_roflags_t *ro_flags`
+///     Flags describing the received message
 pub fn sock_recv(
     env: &WasiEnv,
     sock: __wasi_fd_t,
@@ -2570,9 +2787,47 @@ pub fn sock_recv(
     ro_datalen: WasmPtr<u32>,
     ro_flags: WasmPtr<__wasi_roflags_t>,
 ) -> __wasi_errno_t {
-    debug!("wasi::sock_recv");
-    unimplemented!("wasi::sock_recv")
+    debug!("wasi::sock_recv: sock={}", sock);
+    env.threads.check_exit();
+    let (memory, mut state) = env.get_memory_and_wasi_state(0);
+
+    let iovs_arr_cell = wasi_try!(ri_data.deref(memory, 0, ri_data_len));
+    let ro_datalen_cell = wasi_try!(ro_datalen.deref(memory));
+    let ro_flags_cell = wasi_try!(ro_flags.deref(memory));
+
+    let fd_entry = wasi_try!(state.fs.get_fd(sock));
+    if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_READ) {
+        return __WASI_EACCES;
+    }
+    let inode = fd_entry.inode;
+
+    let bytes_read = match &mut state.fs.inodes[inode].kind {
+        Kind::Socket { socket } => {
+            wasi_try!(recv_bytes(socket.as_mut(), memory, iovs_arr_cell, ri_flags))
+        }
+        _ => return __WASI_ENOTSOCK,
+    };
+
+    ro_datalen_cell.set(bytes_read);
+    ro_flags_cell.set(0);
+
+    __WASI_ESUCCESS
 }
+
+/// ### `sock_send()`
+/// Send a message on a socket
+/// Inputs:
+/// - `__wasi_fd_t sock`
+///     The socket to send on
+/// - `const __wasi_ciovec_t *si_data`
+///     The vectors holding the data to send
+/// - `u32 si_data_len`
+///     The number of vectors in `si_data`
+/// - `__wasi_siflags_t si_flags`
+///     Flags modifying how the message is sent, none are defined yet
+/// Output:
+/// - `u32 *so_datalen`
+///     The number of bytes sent
 pub fn sock_send(
     env: &WasiEnv,
     sock: __wasi_fd_t,
@@ -2581,10 +2836,53 @@ pub fn sock_send(
     si_flags: __wasi_siflags_t,
     so_datalen: WasmPtr<u32>,
 ) -> __wasi_errno_t {
-    debug!("wasi::sock_send");
-    unimplemented!("wasi::sock_send")
+    debug!("wasi::sock_send: sock={}", sock);
+    env.threads.check_exit();
+    let (memory, mut state) = env.get_memory_and_wasi_state(0);
+
+    let iovs_arr_cell = wasi_try!(si_data.deref(memory, 0, si_data_len));
+    let so_datalen_cell = wasi_try!(so_datalen.deref(memory));
+
+    let fd_entry = wasi_try!(state.fs.get_fd(sock));
+    if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_WRITE) {
+        return __WASI_EACCES;
+    }
+    let inode = fd_entry.inode;
+
+    let bytes_written = match &mut state.fs.inodes[inode].kind {
+        Kind::Socket { socket } => wasi_try!(send_bytes(socket.as_mut(), memory, iovs_arr_cell)),
+        _ => return __WASI_ENOTSOCK,
+    };
+
+    so_datalen_cell.set(bytes_written);
+
+    __WASI_ESUCCESS
 }
+
+/// ### `sock_shutdown()`
+/// Shut down socket send and receive channels
+/// Inputs:
+/// - `__wasi_fd_t sock`
+///     The socket to shut down
+/// - `__wasi_sdflags_t how`
+///     Which channels of the socket to shut down
 pub fn sock_shutdown(env: &WasiEnv, sock: __wasi_fd_t, how: __wasi_sdflags_t) -> __wasi_errno_t {
-    debug!("wasi::sock_shutdown");
-    unimplemented!("wasi::sock_shutdown")
+    debug!("wasi::sock_shutdown: sock={}, how={}", sock, how);
+    env.threads.check_exit();
+    let mut state = env.state();
+
+    let fd_entry = wasi_try!(state.fs.get_fd(sock));
+    if !has_rights(fd_entry.rights, __WASI_RIGHT_SOCK_SHUTDOWN) {
+        return __WASI_EACCES;
+    }
+    let inode = fd_entry.inode;
+
+    match &mut state.fs.inodes[inode].kind {
+        Kind::Socket { socket } => {
+            wasi_try!(socket.shutdown(how).map_err(WasiFsError::into_wasi_err))
+        }
+        _ => return __WASI_ENOTSOCK,
+    }
+
+    __WASI_ESUCCESS
 }
@@ -0,0 +1,292 @@
+//! WASI threads: the `wasi` `thread-spawn` import of modules built for
+//! `wasm32-wasi-threads`, which runs `wasi_thread_start` in a new instance
+//! of the module on a host thread.
+//!
+//! The threads come from a pool shared by all the clones of a `WasiEnv`,
+//! which also makes `proc_exit` from any thread tear down all of them:
+//! the threads running WebAssembly code are interrupted, and the ones in a
+//! host call trap at their next WASI call.
+
+use crate::syscalls::types::*;
+use crate::{generate_import_object_from_env, WasiEnv, WasiError, WasiVersion};
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::{cmp, fmt};
+use tracing::debug;
+use wasmer::{
+    ChainableNamedResolver, Export, ImportObject, Instance, Module, NamedResolver, RuntimeError,
+};
+
+/// The default maximum number of threads a module can spawn.
+pub(crate) const DEFAULT_MAX_THREADS: usize = 64;
+
+/// The highest thread id, as thread ids are kept to 29 bits.
+const MAX_THREAD_ID: u32 = 0x1FFF_FFFF;
+
+/// How often the threads still running are interrupted while exiting.
+const EXIT_INTERRUPT_PERIOD: Duration = Duration::from_millis(1);
+
+/// How long exiting waits for the other threads to stop. The threads
+/// blocked in a host call for longer are left to trap at their next WASI
+/// call.
+const EXIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+thread_local! {
+    /// Whether the current thread is one of the pool's.
+    static IN_POOL_THREAD: Cell<bool> = Cell::new(false);
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The imports of the module, other than the WASI ones, which new threads
+/// are instantiated with.
+struct ThreadImports(Vec<((String, String), Export)>);
+
+impl NamedResolver for ThreadImports {
+    fn resolve_by_name(&self, module: &str, field: &str) -> Option<Export> {
+        self.0
+            .iter()
+            .find(|((m, f), _)| m == module && f == field)
+            .map(|(_, export)| export.clone())
+    }
+}
+
+/// What new threads instantiate.
+struct Spawner {
+    module: Module,
+    version: WasiVersion,
+    imports: Arc<ThreadImports>,
+}
+
+#[derive(Default)]
+struct Pool {
+    /// The number of threads running a job.
+    running: usize,
+    /// The idle threads, waiting for a job.
+    idle: Vec<mpsc::Sender<Job>>,
+}
+
+/// The threads of a WASI module.
+pub(crate) struct WasiThreads {
+    max_threads: AtomicUsize,
+    next_thread_id: AtomicU32,
+    spawner: Mutex<Option<Spawner>>,
+    pool: Mutex<Pool>,
+    /// Notified whenever a thread finishes its job.
+    finished: Condvar,
+    /// Whether a module that can spawn threads has exited, for the WASI
+    /// calls to check.
+    exited: AtomicBool,
+    exit_code: Mutex<Option<__wasi_exitcode_t>>,
+}
+
+impl Default for WasiThreads {
+    fn default() -> Self {
+        Self {
+            max_threads: AtomicUsize::new(DEFAULT_MAX_THREADS),
+            next_thread_id: AtomicU32::new(1),
+            spawner: Mutex::new(None),
+            pool: Default::default(),
+            finished: Condvar::new(),
+            exited: AtomicBool::new(false),
+            exit_code: Mutex::new(None),
+        }
+    }
+}
+
+impl fmt::Debug for WasiThreads {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WasiThreads")
+            .field("max_threads", &self.max_threads)
+            .field("exit_code", &self.exit_code())
+            .finish()
+    }
+}
+
+impl WasiThreads {
+    pub(crate) fn set_max_threads(&self, max_threads: usize) {
+        self.max_threads.store(max_threads, Ordering::SeqCst);
+    }
+
+    /// Make new threads instantiate `module` with the WASI imports and
+    /// `imports`.
+    pub(crate) fn set_spawner(&self, module: &Module, version: WasiVersion, imports: ImportObject) {
+        let imports = ThreadImports(imports.into_iter().collect());
+        *self.spawner.lock().unwrap() = Some(Spawner {
+            module: module.clone(),
+            version,
+            imports: Arc::new(imports),
+        });
+    }
+
+    pub(crate) fn exit_code(&self) -> Option<__wasi_exitcode_t> {
+        *self.exit_code.lock().unwrap()
+    }
+
+    /// Trap with the exit code of the module if it has exited, so that the
+    /// threads left running stop at their next WASI call.
+    ///
+    /// This must be called with no lock held, as the trap doesn't unwind.
+    pub(crate) fn check_exit(&self) {
+        if !self.exited.load(Ordering::SeqCst) {
+            return;
+        }
+        if let Some(code) = self.exit_code() {
+            RuntimeError::raise(Box::new(WasiError::Exit(code)));
+        }
+    }
+
+    /// Start a thread running `wasi_thread_start` with `start_arg` in a new
+    /// instance of the module, returning its thread id.
+    pub(crate) fn spawn(
+        self: &Arc<Self>,
+        env: &WasiEnv,
+        start_arg: u32,
+    ) -> Result<u32, __wasi_errno_t> {
+        if self.exit_code().is_some() {
+            return Err(__WASI_EAGAIN);
+        }
+        let (module, version, imports) = match &*self.spawner.lock().unwrap() {
+            Some(spawner) => (
+                spawner.module.clone(),
+                spawner.version,
+                spawner.imports.clone(),
+            ),
+            None => return Err(__WASI_ENOSYS),
+        };
+        let thread_id = self.next_thread_id.fetch_add(1, Ordering::SeqCst);
+        if thread_id > MAX_THREAD_ID {
+            return Err(__WASI_EAGAIN);
+        }
+
+        let env = env.clone();
+        let threads = self.clone();
+        self.run(Box::new(move || {
+            let result = (|| -> Result<(), RuntimeError> {
+                let wasi_imports = generate_import_object_from_env(module.store(), env, version);
+                let instance = Instance::new(&module, &wasi_imports.chain_back(&*imports))
+                    .map_err(|error| RuntimeError::new(error.to_string()))?;
+                let start = instance
+                    .exports
+                    .get_native_function::<(i32, i32), ()>("wasi_thread_start")
+                    .map_err(|error| RuntimeError::new(error.to_string()))?;
+                start.call(thread_id as i32, start_arg as i32)
+            })();
+            // A trap in any thread tears down all of them, like an exit.
+            if let Err(error) = result {
+                if threads.exit_code().is_none() {
+                    debug!("wasi::thread_spawn: thread {} failed: {}", thread_id, error);
+                    threads.exit(1);
+                }
+            }
+        }))?;
+        Ok(thread_id)
+    }
+
+    /// Run `job` on an idle thread of the pool, or on a new one.
+    fn run(self: &Arc<Self>, mut job: Job) -> Result<(), __wasi_errno_t> {
+        let mut pool = self.pool.lock().unwrap();
+        if pool.running >= self.max_threads.load(Ordering::SeqCst) {
+            return Err(__WASI_EAGAIN);
+        }
+        while let Some(idle) = pool.idle.pop() {
+            match idle.send(job) {
+                Ok(()) => {
+                    pool.running += 1;
+                    return Ok(());
+                }
+                Err(mpsc::SendError(returned)) => job = returned,
+            }
+        }
+        let threads = Arc::downgrade(self);
+        thread::Builder::new()
+            .name("wasi-thread".to_string())
+            .spawn(move || pool_thread(threads, job))
+            .map_err(|_| __WASI_EAGAIN)?;
+        pool.running += 1;
+        Ok(())
+    }
+
+    /// Record the exit code of the module and tear down its threads.
+    ///
+    /// The threads of the pool are interrupted until they've all finished,
+    /// and then the thread the module was started on, if it isn't the one
+    /// exiting, until it has seen the interrupt. This takes at most
+    /// `EXIT_TIMEOUT`, after which the interrupt is withdrawn, so that it
+    /// doesn't trap the next call into the store.
+    pub(crate) fn exit(&self, code: __wasi_exitcode_t) {
+        {
+            let mut exit_code = self.exit_code.lock().unwrap();
+            if exit_code.is_some() {
+                return;
+            }
+            *exit_code = Some(code);
+        }
+        let interrupt_handle = match &*self.spawner.lock().unwrap() {
+            Some(spawner) => spawner.module.store().interrupt_handle(),
+            // No thread can have been spawned.
+            None => return,
+        };
+        self.exited.store(true, Ordering::SeqCst);
+        let in_pool_thread = IN_POOL_THREAD.with(Cell::get);
+        let deadline = Instant::now() + EXIT_TIMEOUT;
+        let mut pool = self.pool.lock().unwrap();
+        // The exiting thread, when it's one of the pool's, is still running.
+        while pool.running > in_pool_thread as usize {
+            let now = Instant::now();
+            if now >= deadline {
+                debug!(
+                    "wasi::proc_exit: {} threads still running",
+                    pool.running - in_pool_thread as usize
+                );
+                break;
+            }
+            // All the instances share the same interrupts, whose flag is
+            // cleared by the first thread to see it.
+            interrupt_handle.interrupt();
+            pool = self
+                .finished
+                .wait_timeout(pool, cmp::min(EXIT_INTERRUPT_PERIOD, deadline - now))
+                .unwrap()
+                .0;
+        }
+        drop(pool);
+        if in_pool_thread {
+            interrupt_handle.interrupt();
+            while interrupt_handle.is_interrupted() && Instant::now() < deadline {
+                thread::sleep(EXIT_INTERRUPT_PERIOD);
+            }
+        }
+        interrupt_handle.clear();
+    }
+}
+
+/// Run jobs, starting with `job`, until the pool is gone.
+fn pool_thread(threads: Weak<WasiThreads>, mut job: Job) {
+    IN_POOL_THREAD.with(|in_pool_thread| in_pool_thread.set(true));
+    loop {
+        // The thread must go on counting as running until the end of the
+        // job, even when the job panics.
+        let _ = panic::catch_unwind(AssertUnwindSafe(job));
+        let receiver = {
+            let threads = match threads.upgrade() {
+                Some(threads) => threads,
+                None => return,
+            };
+            let (sender, receiver) = mpsc::channel();
+            let mut pool = threads.pool.lock().unwrap();
+            pool.running -= 1;
+            pool.idle.push(sender);
+            threads.finished.notify_all();
+            receiver
+        };
+        job = match receiver.recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+    }
+}
@@ -4,7 +4,7 @@ use crate::lib::std::fmt;
 use crate::lib::std::format;
 use crate::lib::std::string::{String, ToString};
 use crate::lib::std::vec::Vec;
-use crate::units::Pages;
+use crate::units::{Pages, WASM64_MAX_PAGES};
 use crate::values::Value;
 
 #[cfg(feature = "enable-serde")]
@@ -161,11 +161,13 @@ fn is_memory_compatible(exported: &MemoryType, imported: &MemoryType) -> bool {
         minimum: exported_minimum,
         maximum: exported_maximum,
         shared: exported_shared,
+        index_type: exported_index_type,
     } = exported;
     let MemoryType {
         minimum: imported_minimum,
         maximum: imported_maximum,
         shared: imported_shared,
+        index_type: imported_index_type,
     } = imported;
 
     imported_minimum <= exported_minimum
@@ -173,6 +175,7 @@ fn is_memory_compatible(exported: &MemoryType, imported: &MemoryType) -> bool {
             || (!exported_maximum.is_none()
                 && imported_maximum.unwrap() >= exported_maximum.unwrap()))
         && exported_shared == imported_shared
+        && exported_index_type == imported_index_type
 }
 
 macro_rules! accessors {
@@ -475,10 +478,31 @@ impl fmt::Display for TableType {
 
 // Memory Types
 
+/// The type of the addresses used to index a linear memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub enum IndexType {
+    /// A memory addressed with `i32` values.
+    I32,
+    /// A memory addressed with `i64` values, as defined by the
+    /// memory64 proposal.
+    I64,
+}
+
+impl fmt::Display for IndexType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::I32 => write!(f, "i32"),
+            Self::I64 => write!(f, "i64"),
+        }
+    }
+}
+
 /// A descriptor for a WebAssembly memory type.
 ///
 /// Memories are described in units of pages (64KB) and represent contiguous
-/// chunks of addressable memory.
+/// chunks of addressable memory. They are indexed with 32-bit addresses
+/// unless created with [`MemoryType::new64`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub struct MemoryType {
@@ -488,11 +512,13 @@ pub struct MemoryType {
     pub maximum: Option<Pages>,
     /// Whether the memory may be shared between multiple threads.
     pub shared: bool,
+    /// The type of the addresses used to index the memory.
+    index_type: IndexType,
 }
 
 impl MemoryType {
-    /// Creates a new descriptor for a WebAssembly memory given the specified
-    /// limits of the memory.
+    /// Creates a new descriptor for a 32-bit WebAssembly memory given
+    /// the specified limits of the memory.
     pub fn new<IntoPages>(minimum: IntoPages, maximum: Option<IntoPages>, shared: bool) -> Self
     where
         IntoPages: Into<Pages>,
@@ -501,6 +527,40 @@ impl MemoryType {
             minimum: minimum.into(),
             maximum: maximum.map(Into::into),
             shared,
+            index_type: IndexType::I32,
+        }
+    }
+
+    /// Creates a new descriptor for a 64-bit WebAssembly memory given
+    /// the specified limits of the memory.
+    pub fn new64<IntoPages>(minimum: IntoPages, maximum: Option<IntoPages>) -> Self
+    where
+        IntoPages: Into<Pages>,
+    {
+        Self {
+            minimum: minimum.into(),
+            maximum: maximum.map(Into::into),
+            shared: false,
+            index_type: IndexType::I64,
+        }
+    }
+
+    /// Returns the type of the addresses used to index the memory.
+    pub fn index_type(&self) -> IndexType {
+        self.index_type
+    }
+
+    /// Returns whether the memory is indexed with 64-bit addresses.
+    pub fn is_64(&self) -> bool {
+        self.index_type == IndexType::I64
+    }
+
+    /// Returns the largest number of pages the memory can address
+    /// given its index type, regardless of its declared maximum.
+    pub fn max_pages(&self) -> Pages {
+        match self.index_type {
+            IndexType::I32 => Pages::max_value(),
+            IndexType::I64 => Pages(WASM64_MAX_PAGES),
         }
     }
 }
@@ -508,10 +568,15 @@ impl MemoryType {
 impl fmt::Display for MemoryType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let shared = if self.shared { "shared" } else { "not shared" };
+        let index_type = if self.is_64() { " i64" } else { "" };
         if let Some(maximum) = self.maximum {
-            write!(f, "{} ({:?}..{:?})", shared, self.minimum, maximum)
+            write!(
+                f,
+                "{}{} ({:?}..{:?})",
+                shared, index_type, self.minimum, maximum
+            )
         } else {
-            write!(f, "{} ({:?}..)", shared, self.minimum)
+            write!(f, "{}{} ({:?}..)", shared, index_type, self.minimum)
         }
     }
 }
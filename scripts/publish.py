@@ -50,6 +50,7 @@ dep_graph = {
                          "wasmer-compiler-llvm", "wasmer-emscripten", "wasmer-engine", "wasmer-engine-jit",
                          "wasmer-engine-native", "wasmer-engine-object-file", "wasmer-wasi", "wasmer-types"]),
     "wasmer-middlewares": set(["wasmer", "wasmer-types", "wasmer-vm"]),
+    "wasmer-canonical-abi": set(["wasmer"]),
 }
 
 # where each crate is located in the `lib` directory
@@ -75,6 +76,7 @@ location = {
     "wasmer-wasi-experimental-io-devices": "wasi-experimental-io-devices",
     "wasmer-c-api": "c-api",
     "wasmer-middlewares": "middlewares",
+    "wasmer-canonical-abi": "canonical-abi",
 }
 
 no_dry_run = False
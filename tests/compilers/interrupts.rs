@@ -0,0 +1,56 @@
+use crate::utils::get_store;
+use anyhow::Result;
+use std::thread;
+use std::time::Duration;
+use wasmer::*;
+
+#[test]
+fn interrupt_infinite_loop() -> Result<()> {
+    let store = get_store(false);
+    let wat = r#"
+        (module
+          (func (export "run")
+            (loop $l
+              (br $l))))
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let run = instance.exports.get_function("run")?;
+
+    let handle = instance.interrupt_handle();
+    let interrupter = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        handle.interrupt();
+    });
+
+    let e = run.call(&[]).unwrap_err();
+    interrupter.join().unwrap();
+    assert_eq!(e.to_trap(), Some(TrapCode::Interrupt));
+
+    Ok(())
+}
+
+#[test]
+fn interrupt_before_call_traps_at_function_entry() -> Result<()> {
+    let store = get_store(false);
+    let wat = r#"
+        (module
+          (func (export "add") (param i32 i32) (result i32)
+            (i32.add (local.get 0) (local.get 1))))
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let add = instance.exports.get_function("add")?;
+
+    store.interrupt_handle().interrupt();
+    let e = add.call(&[Val::I32(1), Val::I32(2)]).unwrap_err();
+    assert_eq!(e.to_trap(), Some(TrapCode::Interrupt));
+
+    // The interrupt is consumed by the trap.
+    let result = add.call(&[Val::I32(1), Val::I32(2)])?;
+    assert_eq!(result.into_vec(), vec![Val::I32(3)]);
+
+    Ok(())
+}
@@ -5,11 +5,15 @@
 //! on what's available on the target.
 
 mod imports;
+mod interrupts;
 mod metering;
 mod middlewares;
 mod multi_value_imports;
 mod native_functions;
 mod serialize;
+mod snapshot;
+mod tail_calls;
+mod threads;
 mod traps;
 mod utils;
 mod wasi;
@@ -1,6 +1,11 @@
-use crate::utils::{get_headless_store, get_store};
+use crate::utils::{
+    get_headless_store, get_store, get_store_with_features, get_store_with_middlewares,
+};
 use anyhow::Result;
+use std::sync::Arc;
+use wasmer::wasmparser::Operator;
 use wasmer::*;
+use wasmer_middlewares::Metering;
 
 #[test]
 fn test_serialize() -> Result<()> {
@@ -75,3 +80,134 @@ fn test_deserialize() -> Result<()> {
     assert_eq!(result.to_vec(), vec![Value::I64(1500)]);
     Ok(())
 }
+
+#[test]
+fn test_deserialize_initializes_memories() -> Result<()> {
+    let store = get_store(false);
+    let wat = r#"
+        (module
+            (memory (export "memory") 2)
+            (data (i32.const 16) "static")
+            (data (i32.const 65540) "data")
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let serialized_bytes = module.serialize()?;
+    let headless_store = get_headless_store();
+    let module = unsafe { Module::deserialize(&headless_store, &serialized_bytes)? };
+
+    let read = |memory: &Memory, offset: usize, len: usize| {
+        memory.view::<u8>()[offset..offset + len]
+            .iter()
+            .map(|cell| cell.get())
+            .collect::<Vec<_>>()
+    };
+    let first = Instance::new(&module, &imports! {})?;
+    let second = Instance::new(&module, &imports! {})?;
+    let memory = first.exports.get_memory("memory")?;
+    assert_eq!(read(memory, 16, 6), b"static");
+    assert_eq!(read(memory, 65540, 4), b"data");
+
+    // The instances don't share their writes.
+    memory.view::<u8>()[16].set(b'S');
+    assert_eq!(read(memory, 16, 6), b"Static");
+    assert_eq!(read(second.exports.get_memory("memory")?, 16, 6), b"static");
+    Ok(())
+}
+
+#[test]
+fn test_deserialize_from_file() -> Result<()> {
+    let store = get_store(false);
+    let wat = r#"
+        (module
+            (func $double (param i32) (result i32)
+                local.get 0
+                i32.const 2
+                i32.mul)
+            (func (export "quadruple") (param i32) (result i32)
+                local.get 0
+                call $double
+                call $double)
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let temp_dir = tempfile::tempdir()?;
+    let path = temp_dir.path().join("module.wasmer");
+    module.serialize_to_file(&path)?;
+
+    let headless_store = get_headless_store();
+    let deserialized_module = unsafe { Module::deserialize_from_file(&headless_store, &path)? };
+    // The artifact doesn't change when loaded from a file.
+    assert_eq!(deserialized_module.serialize()?, std::fs::read(&path)?);
+    let instance = Instance::new(&deserialized_module, &imports! {})?;
+    let quadruple = instance
+        .exports
+        .get_native_function::<i32, i32>("quadruple")?;
+    assert_eq!(quadruple.call(5)?, 20);
+    Ok(())
+}
+
+#[test]
+fn test_serialize_deserialized_module() -> Result<()> {
+    let store = get_store(false);
+    // The module calls a function and a libcall, so that its code is
+    // patched when it's linked.
+    let wat = r#"
+        (module
+            (memory 1)
+            (func (param i32) (result i32)
+                (i32.add (local.get 0) (memory.grow (i32.const 1))))
+            (func (export "grow") (param i32) (result i32)
+                (call 0 (local.get 0)))
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let serialized_bytes = module.serialize()?;
+    let deserialized_module = unsafe { Module::deserialize(&store, &serialized_bytes)? };
+    // The code is serialized as it was before being linked.
+    assert_eq!(deserialized_module.serialize()?, serialized_bytes);
+    let instance = Instance::new(&deserialized_module, &imports! {})?;
+    let grow = instance.exports.get_native_function::<i32, i32>("grow")?;
+    assert_eq!(grow.call(10)?, 11);
+    assert_eq!(grow.call(10)?, 12);
+    Ok(())
+}
+
+#[test]
+fn test_engine_fingerprint() -> Result<()> {
+    fn cost(_: &Operator) -> u64 {
+        1
+    }
+    let metering = |metering: Metering<_>| {
+        get_store_with_middlewares(std::iter::once(
+            Arc::new(metering) as Arc<dyn ModuleMiddleware>
+        ))
+    };
+    let mut features = Features::new();
+    features.multi_memory(true);
+    let fingerprint = |store: Store| store.engine().fingerprint();
+
+    let default = fingerprint(get_store(false));
+    assert!(default.is_some());
+    assert_eq!(default, fingerprint(get_store(false)));
+    let others = [
+        fingerprint(get_store(true)),
+        fingerprint(get_store_with_features(features)),
+        fingerprint(metering(Metering::new(10, cost).with_fingerprint("cost"))),
+        fingerprint(metering(Metering::new(20, cost).with_fingerprint("cost"))),
+        fingerprint(metering(
+            Metering::new(10, cost).with_fingerprint("another cost"),
+        )),
+    ];
+    for (i, other) in others.iter().enumerate() {
+        assert_ne!(&default, other);
+        assert!(other.is_some());
+        assert!(others[i + 1..].iter().all(|next| next != other));
+    }
+    // The cost function can't be identified without its fingerprint.
+    assert_eq!(fingerprint(metering(Metering::new(10, cost))), None);
+    Ok(())
+}
@@ -0,0 +1,159 @@
+//! Snapshots of instances, restored onto fresh instances of the same
+//! module.
+
+use crate::utils::get_store;
+use anyhow::Result;
+use wasmer::*;
+
+fn get_module(store: &Store) -> Result<Module> {
+    let wat = r#"
+        (module
+          (import "host" "double" (func $double (param i32) (result i32)))
+          (memory (export "memory") 1 4)
+          (table 2 funcref)
+          (global $counter (export "counter") (mut i32) (i32.const 0))
+          (global $total (export "total") (mut i64) (i64.const 0))
+          (global $ratio (export "ratio") (mut f64) (f64.const 0.5))
+          (global $base (export "base") i32 (i32.const 100))
+          (type $unary (func (param i32) (result i32)))
+          (data (i32.const 16) "initial")
+          (data $greeting "hello")
+          (elem (i32.const 0) $increment)
+          (elem $doubles func $double)
+
+          (func $increment (param i32) (result i32)
+            (i32.add (local.get 0) (i32.const 1)))
+
+          ;; Mutates the memory, the globals and the table.
+          (func (export "init")
+            (drop (memory.grow (i32.const 1)))
+            (i32.store (i32.const 16) (i32.const 0))
+            (i32.store (i32.const 65544) (i32.const 42))
+            (memory.init $greeting (i32.const 32) (i32.const 0) (i32.const 5))
+            (data.drop $greeting)
+            (global.set $counter (i32.const 7))
+            (global.set $total (i64.const 0x1234567890))
+            (global.set $ratio (f64.const 2.25))
+            (table.init $doubles (i32.const 1) (i32.const 0) (i32.const 1))
+            (elem.drop $doubles))
+
+          (func (export "greet")
+            (memory.init $greeting (i32.const 32) (i32.const 0) (i32.const 5)))
+
+          (func (export "call") (param i32 i32) (result i32)
+            (call_indirect (type $unary) (local.get 1) (local.get 0))))
+    "#;
+    Ok(Module::new(store, wat)?)
+}
+
+fn instantiate(module: &Module) -> Result<Instance> {
+    let double = Function::new_native(module.store(), |x: i32| x * 2);
+    Ok(Instance::new(
+        module,
+        &imports! {
+            "host" => {
+                "double" => double,
+            },
+        },
+    )?)
+}
+
+#[test]
+fn restore_onto_a_fresh_instance() -> Result<()> {
+    let store = get_store(false);
+    let module = get_module(&store)?;
+    let instance = instantiate(&module)?;
+    instance
+        .exports
+        .get_native_function::<(), ()>("init")?
+        .call()?;
+
+    let snapshot = instance.snapshot()?;
+    // The immutable global isn't part of the snapshot.
+    assert_eq!(snapshot.globals.len(), 3);
+    let snapshot = InstanceSnapshot::deserialize(&snapshot.serialize()?)?;
+
+    let restored = instantiate(&module)?;
+    restored.restore(&snapshot)?;
+    assert_eq!(restored.snapshot()?, snapshot);
+
+    let memory = restored.exports.get_memory("memory")?;
+    assert_eq!(memory.size(), Pages(2));
+    let view = memory.view::<u8>();
+    let read = |offset: usize, len: usize| {
+        view[offset..offset + len]
+            .iter()
+            .map(|cell| cell.get())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(read(16, 7), b"\0\0\0\0ial");
+    assert_eq!(read(32, 5), b"hello");
+    assert_eq!(read(65544, 4), 42i32.to_le_bytes());
+
+    let global = |name: &str| -> Result<Val> { Ok(restored.exports.get_global(name)?.get()) };
+    assert_eq!(global("counter")?.i32(), Some(7));
+    assert_eq!(global("total")?.i64(), Some(0x1234567890));
+    assert_eq!(global("ratio")?.f64(), Some(2.25));
+    assert_eq!(global("base")?.i32(), Some(100));
+
+    // The table refers to the functions of the fresh instance.
+    let call = restored
+        .exports
+        .get_native_function::<(i32, i32), i32>("call")?;
+    assert_eq!(call.call(0, 41)?, 42);
+    assert_eq!(call.call(1, 21)?, 42);
+
+    // The passive data segment was dropped.
+    let greet = restored.exports.get_native_function::<(), ()>("greet")?;
+    assert!(greet.call().is_err());
+    Ok(())
+}
+
+#[test]
+fn restore_requires_the_same_module() -> Result<()> {
+    let store = get_store(false);
+    let module = get_module(&store)?;
+    let instance = instantiate(&module)?;
+    instance
+        .exports
+        .get_native_function::<(), ()>("init")?
+        .call()?;
+    let snapshot = instance.snapshot()?;
+
+    let other = Module::new(&store, "(module (memory 1))")?;
+    let other = Instance::new(&other, &imports! {})?;
+    assert!(matches!(
+        other.restore(&snapshot),
+        Err(SnapshotError::Incompatible(_))
+    ));
+
+    // The memory is larger than in the snapshot.
+    let fresh = instantiate(&module)?;
+    let snapshot = fresh.snapshot()?;
+    assert!(matches!(
+        instance.restore(&snapshot),
+        Err(SnapshotError::Incompatible(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn foreign_functions_are_rejected() -> Result<()> {
+    let store = get_store(false);
+    let module = Module::new(
+        &store,
+        r#"(module
+             (table (export "table") 1 funcref))"#,
+    )?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let foreign = Function::new_native(&store, |x: i32| x);
+    instance
+        .exports
+        .get_table("table")?
+        .set(0, Val::FuncRef(foreign))?;
+    assert!(matches!(
+        instance.snapshot(),
+        Err(SnapshotError::ForeignFunction { element: 0, .. })
+    ));
+    Ok(())
+}
@@ -0,0 +1,120 @@
+//! Tail calls must not grow the stack, however many of them follow each
+//! other. Singlepass doesn't support them, and rejects the modules that
+//! make them.
+
+use crate::utils::get_store_with_features;
+use anyhow::Result;
+use wasmer::*;
+
+/// The number of tail calls made by the tests, enough to overflow the stack
+/// if each of them took a stack frame.
+const CALLS: i64 = 1_000_000;
+
+fn get_store() -> Store {
+    let mut features = Features::default();
+    features.tail_call(true);
+    get_store_with_features(features)
+}
+
+const COUNTDOWN_WAT: &str = r#"
+    (module
+      (type $countdown (func (param i64) (result i64)))
+      (table 1 funcref)
+      (elem (i32.const 0) $countdown)
+      (func $countdown (export "countdown") (type $countdown)
+        (if (result i64) (i64.eqz (local.get 0))
+          (then (i64.const 42))
+          (else
+            (return_call_indirect (type $countdown)
+              (i64.sub (local.get 0) (i64.const 1))
+              (i32.const 0))))))
+"#;
+
+#[test]
+#[cfg_attr(feature = "test-singlepass", ignore)]
+fn return_call_indirect_does_not_grow_the_stack() -> Result<()> {
+    let store = get_store();
+    let module = Module::new(&store, COUNTDOWN_WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let countdown = instance
+        .exports
+        .get_native_function::<i64, i64>("countdown")?;
+    assert_eq!(countdown.call(CALLS)?, 42);
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(feature = "test-singlepass", ignore)]
+fn return_call_to_an_import_does_not_grow_the_stack() -> Result<()> {
+    let store = get_store();
+    // `pong` calls whatever `ping` put in the table, so that the two
+    // instances tail-call each other.
+    let pong_wat = r#"
+        (module
+          (type $countdown (func (param i64) (result i64)))
+          (table (export "table") 1 funcref)
+          (func (export "pong") (type $countdown)
+            (return_call_indirect (type $countdown) (local.get 0) (i32.const 0))))
+    "#;
+    let ping_wat = r#"
+        (module
+          (type $countdown (func (param i64) (result i64)))
+          (import "pong" "pong" (func $pong (type $countdown)))
+          (import "pong" "table" (table 1 funcref))
+          (elem (i32.const 0) $ping)
+          (func $ping (export "ping") (type $countdown)
+            (if (result i64) (i64.eqz (local.get 0))
+              (then (i64.const 42))
+              (else (return_call $pong (i64.sub (local.get 0) (i64.const 1)))))))
+    "#;
+    let pong = Instance::new(&Module::new(&store, pong_wat)?, &imports! {})?;
+    let ping = Instance::new(
+        &Module::new(&store, ping_wat)?,
+        &imports! {
+            "pong" => {
+                "pong" => pong.exports.get_function("pong")?.clone(),
+                "table" => pong.exports.get_table("table")?.clone(),
+            },
+        },
+    )?;
+    let ping = ping.exports.get_native_function::<i64, i64>("ping")?;
+    assert_eq!(ping.call(CALLS)?, 42);
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(feature = "test-singlepass", ignore)]
+fn return_call_to_a_host_function() -> Result<()> {
+    let store = get_store();
+    let wat = r#"
+        (module
+          (import "env" "add" (func $add (param i64 i64) (result i64)))
+          (func (export "add_one") (param i64) (result i64)
+            (return_call $add (local.get 0) (i64.const 1))))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let add = Function::new_native(&store, |a: i64, b: i64| a + b);
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "add" => add,
+            },
+        },
+    )?;
+    let add_one = instance
+        .exports
+        .get_native_function::<i64, i64>("add_one")?;
+    assert_eq!(add_one.call(41)?, 42);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "test-singlepass")]
+fn singlepass_rejects_tail_calls() {
+    let store = get_store();
+    assert!(matches!(
+        Module::new(&store, COUNTDOWN_WAT),
+        Err(CompileError::UnsupportedFeature(_))
+    ));
+}
@@ -0,0 +1,133 @@
+//! Shared memories and the atomic wait/notify instructions, with
+//! instances running on several host threads.
+
+use crate::utils::get_store_with_features;
+use anyhow::Result;
+use std::thread;
+use wasmer::*;
+
+/// The number of host threads waiting on the shared memory.
+const THREADS: usize = 4;
+
+fn get_store() -> Store {
+    let mut features = Features::default();
+    features.threads(true);
+    get_store_with_features(features)
+}
+
+/// Atomic loads, stores and read-modify-writes aren't available with every
+/// compiler, so the module only relies on wait/notify to synchronize.
+fn get_module(store: &Store) -> Result<Module> {
+    let wat = r#"
+        (module
+          (import "env" "memory" (memory 1 1 shared))
+          (func (export "wait_for_flag")
+            (block $done
+              (loop $retry
+                (br_if $done (i32.load (i32.const 0)))
+                (drop (memory.atomic.wait32 (i32.const 0) (i32.const 0) (i64.const -1)))
+                (br $retry))))
+          (func (export "set_flag") (param $count i32) (result i32)
+            (i32.store (i32.const 0) (i32.const 1))
+            (memory.atomic.notify (i32.const 0) (local.get $count)))
+          (func (export "wait32") (param i32 i32 i64) (result i32)
+            (memory.atomic.wait32 (local.get 0) (local.get 1) (local.get 2))))
+    "#;
+    Ok(Module::new(store, wat)?)
+}
+
+fn instantiate(module: &Module, memory: &Memory) -> Result<Instance> {
+    Ok(Instance::new(
+        module,
+        &imports! {
+            "env" => {
+                "memory" => memory.clone(),
+            },
+        },
+    )?)
+}
+
+#[test]
+fn notify_wakes_waiters_on_other_threads() -> Result<()> {
+    let store = get_store();
+    let module = get_module(&store)?;
+    let memory = Memory::new(&store, MemoryType::new(1, Some(1), true))?;
+
+    let waiters = (0..THREADS)
+        .map(|_| {
+            let module = module.clone();
+            let memory = memory.clone();
+            thread::spawn(move || -> Result<()> {
+                let instance = instantiate(&module, &memory)?;
+                let wait_for_flag = instance
+                    .exports
+                    .get_native_function::<(), ()>("wait_for_flag")?;
+                wait_for_flag.call()?;
+                Ok(())
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let instance = instantiate(&module, &memory)?;
+    let set_flag = instance
+        .exports
+        .get_native_function::<i32, i32>("set_flag")?;
+    let woken = set_flag.call(THREADS as i32)?;
+    assert!(woken as usize <= THREADS);
+
+    for waiter in waiters {
+        waiter.join().unwrap()?;
+    }
+    assert_eq!(memory.view::<u32>()[0].get(), 1);
+    Ok(())
+}
+
+#[test]
+fn wait_returns_early() -> Result<()> {
+    let store = get_store();
+    let module = get_module(&store)?;
+    let memory = Memory::new(&store, MemoryType::new(1, Some(1), true))?;
+    let instance = instantiate(&module, &memory)?;
+    let wait32 = instance
+        .exports
+        .get_native_function::<(i32, i32, i64), i32>("wait32")?;
+
+    // The value doesn't match what the caller expected.
+    assert_eq!(wait32.call(0, 1, -1)?, 1);
+    // Nobody notifies the waiter before the 1ms timeout.
+    assert_eq!(wait32.call(0, 0, 1_000_000)?, 2);
+    Ok(())
+}
+
+#[test]
+fn wait_traps_on_unaligned_addresses() -> Result<()> {
+    let store = get_store();
+    let module = get_module(&store)?;
+    let memory = Memory::new(&store, MemoryType::new(1, Some(1), true))?;
+    let instance = instantiate(&module, &memory)?;
+    let wait32 = instance
+        .exports
+        .get_native_function::<(i32, i32, i64), i32>("wait32")?;
+
+    let error = wait32.call(1, 0, -1).unwrap_err();
+    assert_eq!(error.message(), "unaligned atomic access");
+    Ok(())
+}
+
+#[test]
+fn wait_traps_on_unshared_memories() -> Result<()> {
+    let store = get_store();
+    let wat = r#"
+        (module
+          (memory 1 1)
+          (func (export "wait32") (result i32)
+            (memory.atomic.wait32 (i32.const 0) (i32.const 0) (i64.const -1))))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let wait32 = instance.exports.get_native_function::<(), i32>("wait32")?;
+
+    let error = wait32.call().unwrap_err();
+    assert_eq!(error.message(), "expected shared memory");
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use crate::utils::get_store;
 use anyhow::Result;
+use std::convert::TryFrom;
 use std::panic::{self, AssertUnwindSafe};
 use wasmer::*;
 
@@ -162,6 +163,39 @@ fn test_trap_stack_overflow() -> Result<()> {
     Ok(())
 }
 
+#[test]
+#[cfg(feature = "test-singlepass")]
+fn test_trap_explicit_stack_check() -> Result<()> {
+    let mut compiler = wasmer_compiler_singlepass::Singlepass::new();
+    compiler.enable_stack_check(true);
+    let store = crate::utils::get_store_with_compiler(compiler);
+    store.set_max_wasm_stack(64 * 1024);
+    let wat = r#"
+        (module
+            (func $run (export "run") (param i32) (result i32)
+                (if (result i32) (local.get 0)
+                    (then (i32.add (i32.const 1)
+                                   (call $run (i32.sub (local.get 0) (i32.const 1)))))
+                    (else (i32.const 0))))
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let run = instance.exports.get_native_function::<i32, i32>("run")?;
+
+    // The check traps long before the guard page of the host stack.
+    assert_eq!(run.call(100)?, 100);
+    let e = run.call(100_000).unwrap_err();
+    assert_eq!(e.to_trap(), Some(TrapCode::StackOverflow));
+
+    // A maximum of zero disables the limit.
+    store.set_max_wasm_stack(0);
+    assert_eq!(run.call(1000)?, 1000);
+
+    Ok(())
+}
+
 #[test]
 #[cfg_attr(
     any(
@@ -518,3 +552,45 @@ fn present_after_module_drop() -> Result<()> {
         // assert_eq!(t.trace()[0].func_index(), 0);
     }
 }
+
+#[test]
+fn pooled_memory_out_of_bounds_traps() -> Result<()> {
+    let tunables = PoolingTunables::new(PoolingLimits {
+        instances: 2,
+        memory_pages: Pages(2),
+        ..PoolingLimits::default()
+    })
+    .map_err(anyhow::Error::msg)?;
+    let store = Store::new_with_tunables(&crate::utils::get_engine(false), tunables);
+    let wat = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "store") (param i32)
+            (i32.store (local.get 0) (i32.const 42))))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let first = Instance::new(&module, &imports! {})?;
+    let second = Instance::new(&module, &imports! {})?;
+    let memory =
+        |instance: &Instance| -> Result<_> { Ok(instance.exports.get_memory("memory")?.clone()) };
+
+    // Indices are zero extended, so the instance with the lowest memory
+    // may reach the slot of the other one without bounds checks.
+    let (low, high) = if memory(&first)?.data_ptr() < memory(&second)?.data_ptr() {
+        (&first, &second)
+    } else {
+        (&second, &first)
+    };
+    let high_memory = memory(high)?;
+    let distance = high_memory.data_ptr() as usize - memory(low)?.data_ptr() as usize;
+    let store_fn = low.exports.get_native_function::<u32, ()>("store")?;
+    for &address in &[Pages(1).bytes().0, Pages(3).bytes().0, distance] {
+        if let Ok(address) = u32::try_from(address) {
+            let e = store_fn.call(address).unwrap_err();
+            assert_eq!(e.to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+        }
+    }
+    assert_eq!(high_memory.view::<u8>()[0].get(), 0);
+
+    Ok(())
+}
@@ -1,6 +1,6 @@
 #![cfg(all(feature = "compiler", feature = "engine"))]
 
-use crate::utils::get_store;
+use crate::utils::{get_store, get_store_with_features};
 use std::fs::File;
 use std::io::Read;
 use wasmer_wast::WasiTest;
@@ -38,3 +38,271 @@ pub fn run_wasi(wast_path: &str, base_dir: &str, compiler: &str) -> anyhow::Resu
 
     Ok(())
 }
+
+#[test]
+fn fd_write_and_fd_read_on_socket() -> anyhow::Result<()> {
+    use wasmer::{Instance, Module};
+    use wasmer_wasi::{LoopbackSocket, WasiSocket, WasiState};
+
+    let store = get_store(false);
+    let wat = r#"
+        (module
+          (import "wasi_snapshot_preview1" "fd_write"
+            (func $fd_write (param i32 i32 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "fd_read"
+            (func $fd_read (param i32 i32 i32 i32) (result i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 16) "hello")
+          ;; the iovec at 0 points to the 5 bytes at 16, the count goes to 8
+          (func $iovec
+            (i32.store (i32.const 0) (i32.const 16))
+            (i32.store (i32.const 4) (i32.const 5)))
+          (func (export "write") (param $fd i32) (result i32)
+            (call $iovec)
+            (call $fd_write (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 8)))
+          (func (export "read") (param $fd i32) (result i32)
+            (call $iovec)
+            (call $fd_read (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 8))))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let mut wasi_env = WasiState::new("socket").finalize()?;
+    let (mut host, guest) = LoopbackSocket::pair();
+    let fd = wasi_env.open_socket(Box::new(guest))?;
+    let import_object = wasi_env.import_object(&module)?;
+    let instance = Instance::new(&module, &import_object)?;
+    let memory = instance.exports.get_memory("memory")?;
+    let count = || memory.view::<u32>()[2].get();
+
+    let write = instance.exports.get_native_function::<u32, u32>("write")?;
+    assert_eq!(write.call(fd)?, 0);
+    assert_eq!(count(), 5);
+    let mut buf = [0; 8];
+    assert_eq!(host.recv(&mut buf)?, 5);
+    assert_eq!(&buf[..5], b"hello");
+
+    host.send(b"world")?;
+    let read = instance.exports.get_native_function::<u32, u32>("read")?;
+    assert_eq!(read.call(fd)?, 0);
+    assert_eq!(count(), 5);
+    let view = memory.view::<u8>();
+    let bytes = view[16..21].iter().map(|b| b.get()).collect::<Vec<u8>>();
+    assert_eq!(bytes, b"world");
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(feature = "test-singlepass", ignore)]
+fn set_memory_export_name_binds_another_memory() -> anyhow::Result<()> {
+    use wasmer::{Features, Instance, Module};
+    use wasmer_wasi::WasiState;
+
+    let mut features = Features::default();
+    features.multi_memory(true);
+    let store = get_store_with_features(features);
+    let wat = r#"
+        (module
+          (import "wasi_snapshot_preview1" "args_sizes_get"
+            (func $args_sizes_get (param i32 i32) (result i32)))
+          (memory (export "memory") 1)
+          (memory (export "wasi_memory") 1)
+          (func (export "args_sizes_get") (result i32)
+            (call $args_sizes_get (i32.const 0) (i32.const 4))))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let mut wasi_env = WasiState::new("program").arg("argument").finalize()?;
+    wasi_env.set_memory_export_name("wasi_memory");
+    let import_object = wasi_env.import_object(&module)?;
+    let instance = Instance::new(&module, &import_object)?;
+
+    let args_sizes_get = instance
+        .exports
+        .get_native_function::<(), u32>("args_sizes_get")?;
+    assert_eq!(args_sizes_get.call()?, 0);
+    let wasi_memory = instance.exports.get_memory("wasi_memory")?;
+    assert_eq!(wasi_memory.view::<u32>()[0].get(), 2);
+    assert_eq!(wasi_memory.view::<u32>()[1].get(), 17);
+    let memory = instance.exports.get_memory("memory")?;
+    assert_eq!(memory.view::<u32>()[0].get(), 0);
+    assert_eq!(memory.view::<u32>()[1].get(), 0);
+
+    Ok(())
+}
+
+/// A module built like `wasm32-wasi-threads` ones, whose new threads do
+/// what their argument says: 0 spins forever, 1 exits with code 3, 2
+/// sleeps for 3 seconds in `poll_oneoff` before storing it, and anything
+/// else is stored in the slot of the thread, at 4 times its id.
+const WASI_THREADS_WAT: &str = r#"
+    (module
+      (import "env" "memory" (memory 1 1 shared))
+      (import "wasi" "thread-spawn" (func $thread_spawn (param i32) (result i32)))
+      (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+      (import "wasi_snapshot_preview1" "poll_oneoff"
+        (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+      (import "wasi_snapshot_preview1" "sched_yield" (func $sched_yield (result i32)))
+      (export "memory" (memory 0))
+      (func $slot (param $tid i32) (result i32)
+        (i32.shl (local.get $tid) (i32.const 2)))
+      (func (export "wasi_thread_start") (param $tid i32) (param $arg i32)
+        (if (i32.eqz (local.get $arg))
+          (then (loop $spin (br $spin))))
+        (if (i32.eq (local.get $arg) (i32.const 1))
+          (then (call $proc_exit (i32.const 3))))
+        (if (i32.eq (local.get $arg) (i32.const 2))
+          (then
+            ;; the realtime clock subscription at 1024 times out after 3s
+            (i64.store (i32.const 1048) (i64.const 3000000000))
+            (drop (call $poll_oneoff (i32.const 1024) (i32.const 1072) (i32.const 1) (i32.const 1104)))
+            (drop (call $sched_yield))))
+        (i32.store (call $slot (local.get $tid)) (local.get $arg))
+        (drop (memory.atomic.notify (call $slot (local.get $tid)) (i32.const 1))))
+      (func (export "spawn") (param $arg i32) (result i32)
+        (call $thread_spawn (local.get $arg)))
+      (func (export "join") (param $tid i32) (result i32)
+        (block $done
+          (loop $retry
+            (br_if $done (i32.load (call $slot (local.get $tid))))
+            (drop (memory.atomic.wait32 (call $slot (local.get $tid)) (i32.const 0) (i64.const -1)))
+            (br $retry)))
+        (i32.load (call $slot (local.get $tid))))
+      (func (export "exit") (param $code i32)
+        (call $proc_exit (local.get $code))))
+"#;
+
+fn instantiate_wasi_threads(
+    max_threads: usize,
+) -> anyhow::Result<(wasmer::Instance, wasmer_wasi::WasiEnv)> {
+    use wasmer::{imports, Features, Instance, Memory, MemoryType, Module};
+    use wasmer_wasi::WasiState;
+
+    let mut features = Features::default();
+    features.threads(true);
+    let store = get_store_with_features(features);
+    let module = Module::new(&store, WASI_THREADS_WAT)?;
+    let memory = Memory::new(&store, MemoryType::new(1, Some(1), true))?;
+    let mut wasi_env = WasiState::new("threads").finalize()?;
+    wasi_env.set_max_threads(max_threads);
+    let import_object = wasi_env.import_object_with_threads(
+        &module,
+        imports! {
+            "env" => {
+                "memory" => memory,
+            },
+        },
+    )?;
+    let instance = Instance::new(&module, &import_object)?;
+    Ok((instance, wasi_env))
+}
+
+#[test]
+fn thread_spawn_runs_threads_sharing_memory() -> anyhow::Result<()> {
+    // A joined thread may still be finishing when the next one is spawned,
+    // so the maximum leaves room for all of them.
+    let (instance, wasi_env) = instantiate_wasi_threads(12)?;
+    let spawn = instance.exports.get_native_function::<i32, i32>("spawn")?;
+    let join = instance.exports.get_native_function::<i32, i32>("join")?;
+
+    for round in 0..3 {
+        let thread_ids = (0..4)
+            .map(|i| spawn.call(100 * round + 10 + i))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (i, thread_id) in thread_ids.into_iter().enumerate() {
+            assert!(thread_id > 0);
+            assert_eq!(join.call(thread_id)?, 100 * round + 10 + i as i32);
+        }
+    }
+    assert_eq!(wasi_env.exit_code(), None);
+
+    Ok(())
+}
+
+#[test]
+fn thread_spawn_fails_past_the_maximum_number_of_threads() -> anyhow::Result<()> {
+    use wasmer_wasi::types::__WASI_EAGAIN;
+
+    let (instance, wasi_env) = instantiate_wasi_threads(0)?;
+    let spawn = instance.exports.get_native_function::<i32, i32>("spawn")?;
+
+    assert_eq!(spawn.call(10)?, -(__WASI_EAGAIN as i32));
+    assert_eq!(wasi_env.exit_code(), None);
+
+    Ok(())
+}
+
+#[test]
+fn proc_exit_tears_down_the_spawned_threads() -> anyhow::Result<()> {
+    use wasmer_wasi::WasiError;
+
+    let (instance, wasi_env) = instantiate_wasi_threads(4)?;
+    let spawn = instance.exports.get_native_function::<i32, i32>("spawn")?;
+    let exit = instance.exports.get_native_function::<i32, ()>("exit")?;
+
+    // Spinning threads are interrupted.
+    assert!(spawn.call(0)? > 0);
+    assert!(spawn.call(0)? > 0);
+    let error = exit.call(7).unwrap_err();
+    match error.downcast::<WasiError>() {
+        Ok(WasiError::Exit(7)) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+    assert_eq!(wasi_env.exit_code(), Some(7));
+
+    Ok(())
+}
+
+#[test]
+fn proc_exit_in_a_spawned_thread_tears_down_the_main_thread() -> anyhow::Result<()> {
+    use wasmer::TrapCode;
+
+    let (instance, wasi_env) = instantiate_wasi_threads(4)?;
+    let spawn = instance.exports.get_native_function::<i32, i32>("spawn")?;
+    let join = instance.exports.get_native_function::<i32, i32>("join")?;
+
+    // The thread exits instead of filling its slot, so joining it waits
+    // until the main thread is interrupted.
+    let thread_id = spawn.call(1)?;
+    let error = join.call(thread_id).unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::Interrupt));
+    assert_eq!(wasi_env.exit_code(), Some(3));
+
+    Ok(())
+}
+
+#[test]
+fn proc_exit_does_not_wait_for_threads_blocked_in_a_host_call() -> anyhow::Result<()> {
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use wasmer::{imports, Instance, Module};
+    use wasmer_wasi::WasiError;
+
+    let (instance, wasi_env) = instantiate_wasi_threads(4)?;
+    let spawn = instance.exports.get_native_function::<i32, i32>("spawn")?;
+    let exit = instance.exports.get_native_function::<i32, ()>("exit")?;
+    let memory = instance.exports.get_memory("memory")?;
+
+    let start = Instant::now();
+    let thread_id = spawn.call(2)?;
+    thread::sleep(Duration::from_millis(100));
+    let error = exit.call(7).unwrap_err();
+    match error.downcast::<WasiError>() {
+        Ok(WasiError::Exit(7)) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+    assert!(start.elapsed() < Duration::from_secs(3));
+    assert_eq!(wasi_env.exit_code(), Some(7));
+
+    // The interrupt isn't left pending for the next call into the store.
+    let module = Module::new(instance.store(), r#"(module (func (export "run")))"#)?;
+    let other = Instance::new(&module, &imports! {})?;
+    other.exports.get_native_function::<(), ()>("run")?.call()?;
+
+    // Once awake, the thread traps at its next WASI call, before storing
+    // its argument.
+    if let Some(rest) = Duration::from_millis(3500).checked_sub(start.elapsed()) {
+        thread::sleep(rest);
+    }
+    assert_eq!(memory.view::<u32>()[thread_id as usize].get(), 0);
+
+    Ok(())
+}
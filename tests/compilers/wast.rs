@@ -49,14 +49,28 @@ pub fn run_wast(wast_path: &str, compiler: &str) -> anyhow::Result<()> {
     let mut features = Features::default();
     let is_bulkmemory = wast_path.contains("bulk-memory");
     let is_simd = wast_path.contains("simd");
+    let is_memory64 = wast_path.contains("memory64");
+    let is_multi_memory = wast_path.contains("multi-memory");
+    let is_threads = wast_path.contains("threads");
+    let is_tail_call = wast_path.contains("tail-call");
     if is_bulkmemory {
         features.bulk_memory(true);
     }
     if is_simd {
         features.simd(true);
     }
-    if cfg!(feature = "test-singlepass") {
-        features.multi_value(false);
+    if is_memory64 {
+        features.memory64(true);
+    }
+    if is_multi_memory {
+        features.multi_memory(true);
+        features.bulk_memory(true);
+    }
+    if is_threads {
+        features.threads(true);
+    }
+    if is_tail_call {
+        features.tail_call(true);
     }
     let store = get_store(features, try_nan_canonicalization);
     let mut wast = Wast::new_with_spectest(store);
@@ -85,13 +99,6 @@ pub fn run_wast(wast_path: &str, compiler: &str) -> anyhow::Result<()> {
             "Validation error: Invalid var_u32",
         ]);
     }
-    if compiler == "singlepass" {
-        // We don't support multivalue yet in singlepass
-        wast.allow_instantiation_failures(&[
-            "Validation error: invalid result arity: func type returns multiple values",
-            "Validation error: blocks, loops, and ifs accept no parameters when multi-value is not enabled",
-        ]);
-    }
     wast.fail_fast = false;
     let path = Path::new(wast_path);
     wast.run_file(path)
@@ -0,0 +1,81 @@
+//! CLI tests for the preinit subcommand.
+
+use anyhow::Context;
+use std::fs;
+use wasmer_integration_tests_cli::*;
+
+/// Records how many times each function ran in a global, and the
+/// initialization in the memory.
+const MODULE: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (global $starts (mut i32) (i32.const 0))
+  (global $inits (mut i32) (i32.const 0))
+  (data (i32.const 0) "\00")
+  (func $start
+    (global.set $starts (i32.add (global.get $starts) (i32.const 1))))
+  (start $start)
+  (func (export "init")
+    (global.set $inits (i32.add (global.get $inits) (i32.const 1)))
+    (drop (memory.grow (i32.const 1)))
+    (i32.store (i32.const 0) (i32.const 42))
+    (i32.store (i32.const 65536) (i32.const 7)))
+  (func (export "state") (result i32 i32 i32 i32 i32)
+    (global.get $starts)
+    (global.get $inits)
+    (memory.size)
+    (i32.load (i32.const 0))
+    (i32.load (i32.const 65536))))
+"#;
+
+#[test]
+fn preinit_runs_the_init_function_once() -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir().context("Making a temp dir")?;
+    let module_path = temp_dir.path().join("module.wat");
+    let preinit_path = temp_dir.path().join("preinit.wasm");
+    fs::write(&module_path, MODULE)?;
+
+    run_code(
+        temp_dir.path(),
+        &get_wasmer_path(),
+        &[
+            "preinit".to_string(),
+            module_path.display().to_string(),
+            "--init-func".to_string(),
+            "init".to_string(),
+            "-o".to_string(),
+            preinit_path.display().to_string(),
+        ],
+    )
+    .context("Failed to pre-initialize the module")?;
+
+    let state = |path: &std::path::Path| {
+        run_code(
+            temp_dir.path(),
+            &get_wasmer_path(),
+            &[
+                "run".to_string(),
+                path.display().to_string(),
+                "--invoke".to_string(),
+                "state".to_string(),
+            ],
+        )
+    };
+    assert_eq!(state(&module_path)?.trim(), "1 0 1 0 0");
+    // The start function doesn't run again.
+    assert_eq!(state(&preinit_path)?.trim(), "1 1 2 42 7");
+
+    // The init function isn't exported anymore.
+    let result = run_code(
+        temp_dir.path(),
+        &get_wasmer_path(),
+        &[
+            "run".to_string(),
+            preinit_path.display().to_string(),
+            "--invoke".to_string(),
+            "init".to_string(),
+        ],
+    );
+    assert!(result.is_err());
+    Ok(())
+}
@@ -113,9 +113,10 @@ impl DummyArtifact {
     /// Deserialize a DummyArtifact
     pub fn deserialize(engine: &DummyEngine, bytes: &[u8]) -> Result<Self, DeserializeError> {
         if !Self::is_deserializable(bytes) {
-            return Err(DeserializeError::Incompatible(
-                "The provided bytes are not of the dummy engine".to_string(),
-            ));
+            return Err(DeserializeError::Incompatible {
+                expected: "a wasmer-dummy artifact".to_string(),
+                found: "bytes in another format".to_string(),
+            });
         }
 
         let inner_bytes = &bytes[Self::MAGIC_HEADER.len()..];
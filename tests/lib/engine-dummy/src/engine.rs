@@ -3,7 +3,7 @@
 use crate::DummyArtifact;
 use std::sync::Arc;
 use wasmer_compiler::{CompileError, Features, Target};
-use wasmer_engine::{Artifact, DeserializeError, Engine, EngineId, Tunables};
+use wasmer_engine::{engine_fingerprint, Artifact, DeserializeError, Engine, EngineId, Tunables};
 use wasmer_types::FunctionType;
 use wasmer_vm::{SignatureRegistry, VMContext, VMFunctionBody, VMSharedSignatureIndex};
 
@@ -108,6 +108,15 @@ impl Engine for DummyEngine {
         &self.engine_id
     }
 
+    fn fingerprint(&self) -> Option<String> {
+        Some(engine_fingerprint(
+            "dummy",
+            &self.target,
+            &self.features,
+            None,
+        ))
+    }
+
     fn cloned(&self) -> Arc<dyn Engine + Send + Sync> {
         Arc::new(self.clone())
     }
@@ -0,0 +1,84 @@
+;; Load i32 data with different offset/align arguments
+
+(module
+  (memory i64 1)
+  (data (i64.const 0) "abcdefghijklmnopqrstuvwxyz")
+
+  (func (export "8u_good1") (param $i i64) (result i32)
+    (i32.load8_u offset=0 (local.get $i))                   ;; 97 'a'
+  )
+  (func (export "8u_good2") (param $i i64) (result i32)
+    (i32.load8_u align=1 (local.get $i))                    ;; 97 'a'
+  )
+  (func (export "8u_good3") (param $i i64) (result i32)
+    (i32.load8_u offset=1 align=1 (local.get $i))           ;; 98 'b'
+  )
+  (func (export "8u_good4") (param $i i64) (result i32)
+    (i32.load8_u offset=25 align=1 (local.get $i))          ;; 122 'z'
+  )
+  (func (export "8u_bad") (param $i i64)
+    (drop (i32.load8_u offset=4294967295 (local.get $i)))
+  )
+
+  (func (export "32_good1") (param $i i64) (result i32)
+    (i32.load offset=0 (local.get $i))                      ;; 1684234849 'abcd'
+  )
+  (func (export "32_good2") (param $i i64) (result i32)
+    (i32.load offset=1 align=1 (local.get $i))              ;; 1701077858 'bcde'
+  )
+  (func (export "32_good3") (param $i i64) (result i32)
+    (i32.load offset=25 align=1 (local.get $i))             ;; 122 'z\0\0\0'
+  )
+  (func (export "32_bad") (param $i i64)
+    (drop (i32.load offset=4294967295 (local.get $i)))
+  )
+
+  (func (export "64_good1") (param $i i64) (result i64)
+    (i64.load offset=0 (local.get $i))                      ;; 0x6867666564636261 'abcdefgh'
+  )
+  (func (export "64_good2") (param $i i64) (result i64)
+    (i64.load offset=25 align=1 (local.get $i))             ;; 122 'z\0\0\0\0\0\0\0'
+  )
+  (func (export "64_bad") (param $i i64)
+    (drop (i64.load offset=4294967295 (local.get $i)))
+  )
+)
+
+(assert_return (invoke "8u_good1" (i64.const 0)) (i32.const 97))
+(assert_return (invoke "8u_good2" (i64.const 0)) (i32.const 97))
+(assert_return (invoke "8u_good3" (i64.const 0)) (i32.const 98))
+(assert_return (invoke "8u_good4" (i64.const 0)) (i32.const 122))
+
+(assert_return (invoke "8u_good1" (i64.const 65503)) (i32.const 0))
+(assert_return (invoke "8u_good4" (i64.const 65503)) (i32.const 0))
+(assert_trap (invoke "8u_good4" (i64.const 65511)) "out of bounds memory access")
+
+(assert_trap (invoke "8u_bad" (i64.const 0)) "out of bounds memory access")
+(assert_trap (invoke "8u_bad" (i64.const 1)) "out of bounds memory access")
+
+;; Addresses that don't fit in 32 bits are not truncated.
+(assert_trap (invoke "8u_good1" (i64.const 0x1_0000_0000)) "out of bounds memory access")
+(assert_trap (invoke "8u_good1" (i64.const 0x1_0000_0001)) "out of bounds memory access")
+(assert_trap (invoke "8u_good1" (i64.const -1)) "out of bounds memory access")
+(assert_trap (invoke "8u_good3" (i64.const -1)) "out of bounds memory access")
+(assert_trap (invoke "8u_good4" (i64.const -25)) "out of bounds memory access")
+
+(assert_return (invoke "32_good1" (i64.const 0)) (i32.const 1684234849))
+(assert_return (invoke "32_good2" (i64.const 0)) (i32.const 1701077858))
+(assert_return (invoke "32_good3" (i64.const 0)) (i32.const 122))
+(assert_return (invoke "32_good1" (i64.const 65532)) (i32.const 0))
+(assert_trap (invoke "32_good1" (i64.const 65533)) "out of bounds memory access")
+(assert_trap (invoke "32_good3" (i64.const 65508)) "out of bounds memory access")
+(assert_trap (invoke "32_bad" (i64.const 0)) "out of bounds memory access")
+(assert_trap (invoke "32_good1" (i64.const 0x1_0000_0000)) "out of bounds memory access")
+(assert_trap (invoke "32_good1" (i64.const -1)) "out of bounds memory access")
+(assert_trap (invoke "32_good1" (i64.const -4)) "out of bounds memory access")
+(assert_trap (invoke "32_good2" (i64.const -1)) "out of bounds memory access")
+
+(assert_return (invoke "64_good1" (i64.const 0)) (i64.const 0x6867666564636261))
+(assert_return (invoke "64_good2" (i64.const 0)) (i64.const 122))
+(assert_return (invoke "64_good1" (i64.const 65528)) (i64.const 0))
+(assert_trap (invoke "64_good1" (i64.const 65529)) "out of bounds memory access")
+(assert_trap (invoke "64_bad" (i64.const 0)) "out of bounds memory access")
+(assert_trap (invoke "64_good1" (i64.const 0xffff_ffff_ffff_fff8)) "out of bounds memory access")
+(assert_trap (invoke "64_good2" (i64.const 0xffff_ffff_ffff_ffe7)) "out of bounds memory access")
@@ -0,0 +1,124 @@
+;; Test memory section structure
+
+(module (memory i64 0 0))
+(module (memory i64 0 1))
+(module (memory i64 1 256))
+(module (memory i64 0 65536))
+(module (memory i64 0 0x1_0000_0000))
+(module (memory i64 0 0xffff_ffff_ffff))
+
+(module (memory i64 0) (data (i64.const 0)))
+(module (memory i64 1) (data (i64.const 0) "a" "" "bcd"))
+(module (memory i64 1) (data (i64.const 0xffff) "a"))
+
+(assert_invalid
+  (module (memory i64 1) (func (drop (i32.load (i32.const 0)))))
+  "type mismatch"
+)
+(assert_invalid
+  (module (memory i64 1) (func (i32.store (i32.const 0) (i32.const 0))))
+  "type mismatch"
+)
+(assert_invalid
+  (module (memory i64 1) (func (result i32) (memory.size)))
+  "type mismatch"
+)
+(assert_invalid
+  (module (memory i64 1) (func (drop (memory.grow (i32.const 1)))))
+  "type mismatch"
+)
+
+(assert_unlinkable
+  (module (memory i64 1) (data (i64.const 0x1_0000) "a"))
+  "out of bounds"
+)
+
+(module
+  (memory i64 0)
+  (func (export "memsize") (result i64) (memory.size))
+)
+(assert_return (invoke "memsize") (i64.const 0))
+
+(module
+  (memory i64 0 0)
+  (data (i64.const 0))
+  (func (export "memsize") (result i64) (memory.size))
+)
+(assert_return (invoke "memsize") (i64.const 0))
+
+(module
+  (memory i64 1 1)
+  (data (i64.const 0) "x")
+  (func (export "memsize") (result i64) (memory.size))
+)
+(assert_return (invoke "memsize") (i64.const 1))
+
+(module
+  (memory i64 1)
+  (data (i64.const 0) "ABC\a7D") (data (i64.const 20) "WASM")
+
+  ;; Data section
+  (func (export "data") (result i32)
+    (i32.and
+      (i32.and
+        (i32.and
+          (i32.eq (i32.load8_u (i64.const 0)) (i32.const 65))
+          (i32.eq (i32.load8_u (i64.const 3)) (i32.const 167))
+        )
+        (i32.and
+          (i32.eq (i32.load8_u (i64.const 6)) (i32.const 0))
+          (i32.eq (i32.load8_u (i64.const 19)) (i32.const 0))
+        )
+      )
+      (i32.and
+        (i32.and
+          (i32.eq (i32.load8_u (i64.const 20)) (i32.const 87))
+          (i32.eq (i32.load8_u (i64.const 23)) (i32.const 77))
+        )
+        (i32.and
+          (i32.eq (i32.load8_u (i64.const 24)) (i32.const 0))
+          (i32.eq (i32.load8_u (i64.const 1023)) (i32.const 0))
+        )
+      )
+    )
+  )
+
+  ;; Memory cast
+  (func (export "cast") (result f64)
+    (i64.store (i64.const 8) (i64.const -12345))
+    (if
+      (f64.eq
+        (f64.load (i64.const 8))
+        (f64.reinterpret_i64 (i64.const -12345))
+      )
+      (then (return (f64.const 0)))
+    )
+    (i64.store align=1 (i64.const 9) (i64.const 0))
+    (i32.store16 align=1 (i64.const 15) (i32.const 16453))
+    (f64.load align=1 (i64.const 9))
+  )
+
+  ;; Sign and zero extending memory loads
+  (func (export "i64_load8_s") (param $i i64) (result i64)
+    (i64.store8 (i64.const 8) (local.get $i))
+    (i64.load8_s (i64.const 8))
+  )
+  (func (export "i64_load32_u") (param $i i64) (result i64)
+    (i64.store32 (i64.const 8) (local.get $i))
+    (i64.load32_u (i64.const 8))
+  )
+)
+
+(assert_return (invoke "data") (i32.const 1))
+(assert_return (invoke "cast") (f64.const 42.0))
+(assert_return (invoke "i64_load8_s" (i64.const -1)) (i64.const -1))
+(assert_return (invoke "i64_load8_s" (i64.const 0xfedc6543)) (i64.const 0x43))
+(assert_return (invoke "i64_load32_u" (i64.const -1)) (i64.const 4294967295))
+(assert_return (invoke "i64_load32_u" (i64.const 0x3456436598bacdef)) (i64.const 0x98bacdef))
+
+;; Data offsets given by an imported global
+(module
+  (global (import "spectest" "global_i64") i64)
+  (memory i64 1)
+  (data (global.get 0) "a")
+)
@@ -0,0 +1,75 @@
+(module
+    (memory i64 1)
+
+    (func $addr_limit (result i64)
+      (i64.mul (memory.size) (i64.const 0x10000))
+    )
+
+    (func (export "store") (param $i i64) (param $v i32)
+      (i32.store (i64.add (call $addr_limit) (local.get $i)) (local.get $v))
+    )
+
+    (func (export "load") (param $i i64) (result i32)
+      (i32.load (i64.add (call $addr_limit) (local.get $i)))
+    )
+
+    (func (export "memory.grow") (param i64) (result i64)
+      (memory.grow (local.get 0))
+    )
+)
+
+(assert_return (invoke "store" (i64.const -4) (i32.const 42)))
+(assert_return (invoke "load" (i64.const -4)) (i32.const 42))
+(assert_trap (invoke "store" (i64.const -3) (i32.const 13)) "out of bounds memory access")
+(assert_trap (invoke "load" (i64.const -3)) "out of bounds memory access")
+(assert_trap (invoke "store" (i64.const -2) (i32.const 13)) "out of bounds memory access")
+(assert_trap (invoke "load" (i64.const -2)) "out of bounds memory access")
+(assert_trap (invoke "store" (i64.const -1) (i32.const 13)) "out of bounds memory access")
+(assert_trap (invoke "load" (i64.const -1)) "out of bounds memory access")
+(assert_trap (invoke "store" (i64.const 0) (i32.const 13)) "out of bounds memory access")
+(assert_trap (invoke "load" (i64.const 0)) "out of bounds memory access")
+(assert_trap (invoke "store" (i64.const 0x80000000) (i32.const 13)) "out of bounds memory access")
+(assert_trap (invoke "load" (i64.const 0x80000000)) "out of bounds memory access")
+(assert_trap (invoke "store" (i64.const 0xffff_0000) (i32.const 13)) "out of bounds memory access")
+(assert_trap (invoke "load" (i64.const 0xffff_0000)) "out of bounds memory access")
+(assert_return (invoke "memory.grow" (i64.const 0x1_0000_0000)) (i64.const -1))
+
+(module
+  (memory i64 1)
+  (data (i64.const 0) "abcdefghijklmnopqrstuvwxyz")
+
+  (func (export "i32.load") (param $a i64) (result i32)
+    (i32.load (local.get $a))
+  )
+  (func (export "i64.load") (param $a i64) (result i64)
+    (i64.load (local.get $a))
+  )
+  (func (export "i64.load8_u") (param $a i64) (result i64)
+    (i64.load8_u (local.get $a))
+  )
+  (func (export "i64.store") (param $a i64) (param $v i64)
+    (i64.store (local.get $a) (local.get $v))
+  )
+  (func (export "i32.store8") (param $a i64) (param $v i32)
+    (i32.store8 (local.get $a) (local.get $v))
+  )
+)
+
+(assert_trap (invoke "i32.load" (i64.const 0x10000)) "out of bounds memory access")
+(assert_trap (invoke "i32.load" (i64.const 0xffff)) "out of bounds memory access")
+(assert_trap (invoke "i32.load" (i64.const 0xfffd)) "out of bounds memory access")
+(assert_trap (invoke "i32.load" (i64.const -1)) "out of bounds memory access")
+(assert_trap (invoke "i32.load" (i64.const -4)) "out of bounds memory access")
+(assert_trap (invoke "i32.load" (i64.const 0x1_0000_0000)) "out of bounds memory access")
+(assert_trap (invoke "i64.load" (i64.const 0xfff9)) "out of bounds memory access")
+(assert_trap (invoke "i64.load" (i64.const -8)) "out of bounds memory access")
+(assert_trap (invoke "i64.load8_u" (i64.const 0x1_0000_0000)) "out of bounds memory access")
+(assert_trap (invoke "i64.store" (i64.const 0xfff9) (i64.const 0)) "out of bounds memory access")
+(assert_trap (invoke "i64.store" (i64.const -8) (i64.const 0)) "out of bounds memory access")
+(assert_trap (invoke "i32.store8" (i64.const 0x1_0000) (i32.const 0)) "out of bounds memory access")
+(assert_trap (invoke "i32.store8" (i64.const -1) (i32.const 0)) "out of bounds memory access")
+
+(assert_return (invoke "i64.load" (i64.const 0xfff8)) (i64.const 0))
+(assert_return (invoke "i32.load" (i64.const 0xfffc)) (i32.const 0))
+(assert_return (invoke "i64.load8_u" (i64.const 0)) (i64.const 97))
+(assert_return (invoke "i64.load8_u" (i64.const 25)) (i64.const 122))
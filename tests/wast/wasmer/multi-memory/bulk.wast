@@ -0,0 +1,50 @@
+;; Bulk memory operations on several memories.
+
+(module
+  (memory $mem0 1)
+  (memory $mem1 1)
+  (data $d "\01\02\03\04")
+  (data (memory $mem0) (i32.const 0) "\0a\0b\0c\0d")
+
+  (func (export "copy_0_to_1") (param i32 i32 i32)
+    (memory.copy $mem1 $mem0 (local.get 0) (local.get 1) (local.get 2)))
+  (func (export "copy_1_to_0") (param i32 i32 i32)
+    (memory.copy $mem0 $mem1 (local.get 0) (local.get 1) (local.get 2)))
+  (func (export "copy_1") (param i32 i32 i32)
+    (memory.copy $mem1 $mem1 (local.get 0) (local.get 1) (local.get 2)))
+  (func (export "fill1") (param i32 i32 i32)
+    (memory.fill $mem1 (local.get 0) (local.get 1) (local.get 2)))
+  (func (export "init1") (param i32 i32 i32)
+    (memory.init $d $mem1 (local.get 0) (local.get 1) (local.get 2)))
+
+  (func (export "load0") (param i32) (result i32)
+    (i32.load8_u $mem0 (local.get 0)))
+  (func (export "load1") (param i32) (result i32)
+    (i32.load8_u $mem1 (local.get 0)))
+)
+
+(assert_return (invoke "copy_0_to_1" (i32.const 8) (i32.const 0) (i32.const 4)))
+(assert_return (invoke "load1" (i32.const 8)) (i32.const 0x0a))
+(assert_return (invoke "load1" (i32.const 11)) (i32.const 0x0d))
+(assert_return (invoke "load0" (i32.const 8)) (i32.const 0))
+
+(assert_return (invoke "copy_1" (i32.const 9) (i32.const 8) (i32.const 4)))
+(assert_return (invoke "load1" (i32.const 9)) (i32.const 0x0a))
+(assert_return (invoke "load1" (i32.const 12)) (i32.const 0x0d))
+
+(assert_return (invoke "copy_1_to_0" (i32.const 0xfffc) (i32.const 9) (i32.const 4)))
+(assert_return (invoke "load0" (i32.const 0xfffc)) (i32.const 0x0a))
+(assert_return (invoke "load0" (i32.const 0xffff)) (i32.const 0x0d))
+(assert_trap (invoke "copy_1_to_0" (i32.const 0xfffd) (i32.const 0) (i32.const 4))
+  "out of bounds memory access")
+(assert_trap (invoke "copy_0_to_1" (i32.const 0) (i32.const 0xfffd) (i32.const 4))
+  "out of bounds memory access")
+
+(assert_return (invoke "fill1" (i32.const 32) (i32.const 0xff) (i32.const 2)))
+(assert_return (invoke "load1" (i32.const 33)) (i32.const 0xff))
+(assert_return (invoke "load0" (i32.const 33)) (i32.const 0))
+
+(assert_return (invoke "init1" (i32.const 64) (i32.const 1) (i32.const 3)))
+(assert_return (invoke "load1" (i32.const 64)) (i32.const 2))
+(assert_return (invoke "load1" (i32.const 66)) (i32.const 4))
+(assert_return (invoke "load0" (i32.const 64)) (i32.const 0))
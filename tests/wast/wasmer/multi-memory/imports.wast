@@ -0,0 +1,52 @@
+;; Modules importing and exporting several memories.
+
+(module $M
+  (memory $a (export "a") 1)
+  (memory $b (export "b") 1)
+  (data (memory $a) (i32.const 0) "a")
+  (data (memory $b) (i32.const 0) "b")
+  (func (export "load_a") (param i32) (result i32) (i32.load8_u $a (local.get 0)))
+  (func (export "load_b") (param i32) (result i32) (i32.load8_u $b (local.get 0)))
+)
+(register "M" $M)
+
+(module
+  (import "M" "b" (memory $b 1))
+  (import "M" "a" (memory $a 1))
+  (memory $local 1)
+
+  (data (memory $local) (i32.const 0) "l")
+
+  (func (export "load_a") (param i32) (result i32) (i32.load8_u $a (local.get 0)))
+  (func (export "load_b") (param i32) (result i32) (i32.load8_u $b (local.get 0)))
+  (func (export "load_local") (param i32) (result i32) (i32.load8_u $local (local.get 0)))
+  (func (export "store_a") (param i32 i32) (i32.store8 $a (local.get 0) (local.get 1)))
+  (func (export "store_b") (param i32 i32) (i32.store8 $b (local.get 0) (local.get 1)))
+
+  (func (export "size_a") (result i32) (memory.size $a))
+  (func (export "size_b") (result i32) (memory.size $b))
+  (func (export "size_local") (result i32) (memory.size $local))
+  (func (export "grow_b") (param i32) (result i32) (memory.grow $b (local.get 0)))
+  (func (export "grow_local") (param i32) (result i32) (memory.grow $local (local.get 0)))
+)
+
+(assert_return (invoke "load_a" (i32.const 0)) (i32.const 0x61))
+(assert_return (invoke "load_b" (i32.const 0)) (i32.const 0x62))
+(assert_return (invoke "load_local" (i32.const 0)) (i32.const 0x6c))
+
+(assert_return (invoke "store_a" (i32.const 1) (i32.const 0x41)))
+(assert_return (invoke "store_b" (i32.const 1) (i32.const 0x42)))
+(assert_return (invoke $M "load_a" (i32.const 1)) (i32.const 0x41))
+(assert_return (invoke $M "load_b" (i32.const 1)) (i32.const 0x42))
+
+(assert_return (invoke "grow_local" (i32.const 2)) (i32.const 1))
+(assert_return (invoke "size_a") (i32.const 1))
+(assert_return (invoke "size_b") (i32.const 1))
+(assert_return (invoke "size_local") (i32.const 3))
+(assert_return (invoke "grow_b" (i32.const 1)) (i32.const 1))
+(assert_return (invoke "size_a") (i32.const 1))
+(assert_return (invoke "size_b") (i32.const 2))
+(assert_return (invoke "size_local") (i32.const 3))
+(assert_return (invoke "store_b" (i32.const 0x10000) (i32.const 0x42)))
+(assert_return (invoke $M "load_b" (i32.const 0x10000)) (i32.const 0x42))
+(assert_trap (invoke "store_a" (i32.const 0x10000) (i32.const 0x41)) "out of bounds memory access")
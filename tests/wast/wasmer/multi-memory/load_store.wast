@@ -0,0 +1,63 @@
+;; Loads and stores on several memories.
+
+(module
+  (memory $mem0 1)
+  (memory $mem1 1)
+  (memory $mem2 2)
+
+  (data (memory $mem0) (i32.const 0) "\01")
+  (data (memory $mem1) (i32.const 0) "\02")
+  (data (memory $mem2) (i32.const 0x10000) "\03")
+
+  (func (export "load0") (param i32) (result i32)
+    (i32.load8_u $mem0 (local.get 0)))
+  (func (export "load1") (param i32) (result i32)
+    (i32.load8_u $mem1 (local.get 0)))
+  (func (export "load2") (param i32) (result i32)
+    (i32.load8_u $mem2 (local.get 0)))
+
+  (func (export "store0") (param i32 i64)
+    (i64.store $mem0 (local.get 0) (local.get 1)))
+  (func (export "store1") (param i32 i64)
+    (i64.store $mem1 (local.get 0) (local.get 1)))
+  (func (export "store2") (param i32 i64)
+    (i64.store $mem2 offset=8 (local.get 0) (local.get 1)))
+
+  (func (export "load64_0") (param i32) (result i64)
+    (i64.load $mem0 (local.get 0)))
+  (func (export "load64_1") (param i32) (result i64)
+    (i64.load $mem1 (local.get 0)))
+  (func (export "load64_2") (param i32) (result i64)
+    (i64.load $mem2 offset=8 (local.get 0)))
+)
+
+(assert_return (invoke "load0" (i32.const 0)) (i32.const 1))
+(assert_return (invoke "load1" (i32.const 0)) (i32.const 2))
+(assert_return (invoke "load2" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "load2" (i32.const 0x10000)) (i32.const 3))
+
+(assert_return (invoke "store0" (i32.const 16) (i64.const 0x1111)))
+(assert_return (invoke "store1" (i32.const 16) (i64.const 0x2222)))
+(assert_return (invoke "store2" (i32.const 16) (i64.const 0x3333)))
+(assert_return (invoke "load64_0" (i32.const 16)) (i64.const 0x1111))
+(assert_return (invoke "load64_1" (i32.const 16)) (i64.const 0x2222))
+(assert_return (invoke "load64_2" (i32.const 16)) (i64.const 0x3333))
+(assert_return (invoke "load64_0" (i32.const 24)) (i64.const 0))
+(assert_return (invoke "load64_1" (i32.const 24)) (i64.const 0))
+
+(assert_trap (invoke "load0" (i32.const 0x10000)) "out of bounds memory access")
+(assert_trap (invoke "load1" (i32.const 0x10000)) "out of bounds memory access")
+(assert_return (invoke "load2" (i32.const 0x1ffff)) (i32.const 0))
+(assert_trap (invoke "load2" (i32.const 0x20000)) "out of bounds memory access")
+(assert_trap (invoke "store1" (i32.const 0xfff9) (i64.const 0)) "out of bounds memory access")
+(assert_trap (invoke "store2" (i32.const 0x1fff1) (i64.const 0)) "out of bounds memory access")
+
+;; A data segment for a memory that doesn't fit traps at instantiation.
+(assert_trap
+  (module
+    (memory $mem0 1)
+    (memory $mem1 0)
+    (data (memory $mem1) (i32.const 0) "\01")
+  )
+  "out of bounds"
+)
@@ -0,0 +1,41 @@
+;; `memory.size` and `memory.grow` on several memories.
+
+(module
+  (memory $mem0 1)
+  (memory $mem1 0 2)
+  (memory $mem2 3)
+
+  (func (export "size0") (result i32) (memory.size $mem0))
+  (func (export "size1") (result i32) (memory.size $mem1))
+  (func (export "size2") (result i32) (memory.size $mem2))
+  (func (export "grow0") (param i32) (result i32) (memory.grow $mem0 (local.get 0)))
+  (func (export "grow1") (param i32) (result i32) (memory.grow $mem1 (local.get 0)))
+  (func (export "grow2") (param i32) (result i32) (memory.grow $mem2 (local.get 0)))
+
+  (func (export "store1") (param i32 i32)
+    (i32.store $mem1 (local.get 0) (local.get 1)))
+  (func (export "load1") (param i32) (result i32)
+    (i32.load $mem1 (local.get 0)))
+)
+
+(assert_return (invoke "size0") (i32.const 1))
+(assert_return (invoke "size1") (i32.const 0))
+(assert_return (invoke "size2") (i32.const 3))
+
+(assert_trap (invoke "store1" (i32.const 0) (i32.const 7)) "out of bounds memory access")
+(assert_return (invoke "grow1" (i32.const 1)) (i32.const 0))
+(assert_return (invoke "size0") (i32.const 1))
+(assert_return (invoke "size1") (i32.const 1))
+(assert_return (invoke "size2") (i32.const 3))
+(assert_return (invoke "store1" (i32.const 0) (i32.const 7)))
+(assert_return (invoke "load1" (i32.const 0)) (i32.const 7))
+
+(assert_return (invoke "grow1" (i32.const 2)) (i32.const -1))
+(assert_return (invoke "grow1" (i32.const 1)) (i32.const 1))
+(assert_return (invoke "size1") (i32.const 2))
+
+(assert_return (invoke "grow2" (i32.const 2)) (i32.const 3))
+(assert_return (invoke "grow0" (i32.const 1)) (i32.const 1))
+(assert_return (invoke "size0") (i32.const 2))
+(assert_return (invoke "size1") (i32.const 2))
+(assert_return (invoke "size2") (i32.const 5))